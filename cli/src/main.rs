@@ -0,0 +1,227 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use clap::Parser;
+use comms::{
+    command::{self, UserCommand},
+    event::{ContentType, Event},
+    handshake::Compression,
+    transport::{
+        self,
+        client::{CommandWriter, EventStream},
+    },
+};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    net::TcpStream,
+};
+use tokio_stream::StreamExt;
+
+use output::OutputFormat;
+
+mod output;
+
+/// Joins a room, sends the lines read from stdin to it and prints the events received, one per line
+///
+/// Meant for shell pipelines and cron jobs, e.g. `echo "backup done" | chat-cli --room ops`.
+/// The session is closed once stdin ends, unless `--follow` is given.
+#[derive(Debug, Parser)]
+#[command(name = "chat-cli")]
+struct Args {
+    /// The room to join and send the messages to
+    #[arg(short, long)]
+    room: String,
+    /// The host and port of the server, or the path of its Unix domain socket prefixed with `unix:`
+    #[arg(short, long, default_value = "localhost:8080")]
+    addr: String,
+    /// The token to log in with, e.g. the token of a bot
+    #[arg(short, long, env = "CHAT_CLI_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+    /// Read the token to log in with from this file, the token is replaced there when the server refreshes it
+    #[arg(long, conflicts_with = "token")]
+    token_file: Option<PathBuf>,
+    /// The name to chat as on servers without an identity provider, the server picks one if not given
+    #[arg(short, long, conflicts_with = "guest")]
+    username: Option<String>,
+    /// Log in as a guest with a temporary identity, if the server allows guests
+    #[arg(long)]
+    guest: bool,
+    /// How the received events are printed
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+    /// Keep printing the events of the room once stdin has ended, until interrupted
+    #[arg(long)]
+    follow: bool,
+}
+
+/// Prints the event, returns true if nobody reads stdout anymore and the session should be closed
+fn print_or_close(format: OutputFormat, event: &Event) -> anyhow::Result<bool> {
+    match format.print(event) {
+        Ok(()) => Ok(false),
+        Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => Ok(true),
+        Err(err) => Err(err).context("could not print the event"),
+    }
+}
+
+fn read_token(path: &Path) -> anyhow::Result<String> {
+    let token = std::fs::read_to_string(path)
+        .with_context(|| format!("could not read the token from {}", path.display()))?;
+
+    Ok(String::from(token.trim()))
+}
+
+/// Replace the token in the file, writing a temporary file first so the token is never lost halfway
+fn write_token(path: &Path, token: &str) -> anyhow::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+
+    std::fs::write(&tmp_path, format!("{}\n", token))
+        .and_then(|()| std::fs::rename(&tmp_path, path))
+        .with_context(|| format!("could not write the token to {}", path.display()))
+}
+
+/// Keep the refreshed token where the current one has been read from, the server expires the current one later on
+fn keep_refreshed_token(token_file: Option<&Path>, token: &str) -> anyhow::Result<()> {
+    match token_file {
+        Some(path) => write_token(path, token),
+        None => {
+            eprintln!("the server has refreshed the token, pass --token-file to keep it");
+            Ok(())
+        }
+    }
+}
+
+/// Connects and logs in to the server, over TCP or the Unix domain socket of the address
+async fn connect(
+    addr: &str,
+    login: command::LoginCommand,
+) -> anyhow::Result<(EventStream, CommandWriter)> {
+    const COMPRESSION: &[Compression] = &[Compression::Deflate, Compression::None];
+
+    let (events, mut command_writer) = connect_transport(addr, COMPRESSION).await?;
+    command_writer
+        .write(&UserCommand::Login(login))
+        .await
+        .context("could not log in")?;
+
+    Ok((events, command_writer))
+}
+
+async fn connect_transport(
+    addr: &str,
+    compression: &[Compression],
+) -> anyhow::Result<(EventStream, CommandWriter)> {
+    #[cfg(unix)]
+    if let Some(path) = addr.strip_prefix("unix:") {
+        let stream = tokio::net::UnixStream::connect(path)
+            .await
+            .with_context(|| format!("could not connect to {}", path))?;
+
+        return transport::client::connect_stream(stream, compression).await;
+    }
+
+    let stream = TcpStream::connect(addr)
+        .await
+        .with_context(|| format!("could not connect to {}", addr))?;
+
+    transport::client::connect_tcp_stream(stream, compression).await
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    let token = match &args.token_file {
+        Some(path) => Some(read_token(path)?),
+        None => args.token.clone(),
+    };
+    let login = command::LoginCommand {
+        username: args.username.clone(),
+        token,
+        guest: args.guest,
+    };
+    let (mut events, mut command_writer) = connect(&args.addr, login).await?;
+
+    match events.next().await {
+        Some(Ok(event @ Event::LoginSuccessful(_))) => {
+            if print_or_close(args.format, &event)? {
+                return Ok(());
+            }
+        }
+        Some(Ok(Event::LoginRejected(event))) => anyhow::bail!("login rejected, {}", event.reason),
+        Some(Ok(event)) => anyhow::bail!("unexpected event before login: {:?}", event),
+        Some(Err(err)) => return Err(err),
+        None => anyhow::bail!("the server closed the connection before login"),
+    }
+
+    command_writer
+        .write(&UserCommand::JoinRoom(command::JoinRoomCommand {
+            room: args.room.clone(),
+            wait_if_full: false,
+        }))
+        .await
+        .context("could not join the room")?;
+
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut is_stdin_open = true;
+
+    loop {
+        tokio::select! {
+            line = lines.next_line(), if is_stdin_open => match line.context("could not read stdin")? {
+                Some(line) if line.trim().is_empty() => {}
+                Some(content) => {
+                    command_writer
+                        .write(&UserCommand::SendMessage(command::SendMessageCommand {
+                            room: args.room.clone(),
+                            content,
+                            content_type: ContentType::Plain,
+                            nonce: None,
+                        }))
+                        .await
+                        .context("could not send the message")?;
+                }
+                None => {
+                    is_stdin_open = false;
+
+                    // the server closes the connection once it has handled the messages sent before
+                    if !args.follow {
+                        command_writer
+                            .write(&UserCommand::Quit(command::QuitCommand))
+                            .await
+                            .context("could not quit the session")?;
+                    }
+                }
+            },
+            event = events.next() => match event {
+                // never printed, the token is a secret
+                Some(Ok(Event::TokenRefreshed(event))) => {
+                    keep_refreshed_token(args.token_file.as_deref(), &event.token)?;
+                }
+                Some(Ok(event)) => {
+                    if print_or_close(args.format, &event)? {
+                        break;
+                    }
+
+                    match event {
+                        Event::CommandRejected(event) if event.command == "join_room" => {
+                            anyhow::bail!("could not join #{}, {}", args.room, event.reason)
+                        }
+                        Event::RoomFull(event) => anyhow::bail!("#{} is full", event.room),
+                        Event::SessionTerminated(event) => anyhow::bail!("session closed, {}", event.reason),
+                        _ => {}
+                    }
+                }
+                Some(Err(err)) => return Err(err),
+                None => break,
+            },
+            _ = tokio::signal::ctrl_c() => {
+                let _ = command_writer
+                    .write(&UserCommand::Quit(command::QuitCommand))
+                    .await;
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
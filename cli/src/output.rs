@@ -0,0 +1,69 @@
+use std::io::Write;
+
+use clap::ValueEnum;
+use comms::event::{self, Event};
+
+/// How the received events are printed, one per line
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    /// Messages, joins, leaves and announcements as plain text, rejections go to stderr
+    Text,
+    /// Every event as it has been received from the server, as json
+    Json,
+}
+
+impl OutputFormat {
+    /// Fails if stdout has been closed, e.g. by `head` at the end of a pipeline
+    pub fn print(&self, event: &Event) -> std::io::Result<()> {
+        let line = match self {
+            OutputFormat::Json => Some(serde_json::to_string(event)?),
+            OutputFormat::Text => text(event),
+        };
+
+        match line {
+            Some(line) => writeln!(std::io::stdout(), "{}", line),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The line printed for an event, rejections are printed to stderr instead
+fn text(event: &Event) -> Option<String> {
+    let line = match event {
+        Event::UserMessage(event) => {
+            let author = match &event.display_name {
+                Some(display_name) => format!("{} (@{})", display_name, event.user_id),
+                None => format!("@{}", event.user_id),
+            };
+
+            format!("#{} {}: {}", event.room, author, event.content)
+        }
+        Event::RoomParticipation(event) => {
+            let status = match event.status {
+                event::RoomParticipationStatus::Joined => "joined",
+                event::RoomParticipationStatus::Left => "left",
+            };
+
+            format!("#{} @{} {}", event.room, event.user_id, status)
+        }
+        Event::Announcement(event) => format!("#{} announcement: {}", event.room, event.message),
+        Event::MessageRejected(event) => {
+            eprintln!("message to #{} rejected: {:?}", event.room, event.reason);
+            return None;
+        }
+        Event::RoomResynced(event) => {
+            eprintln!(
+                "fell behind in #{}, {} events were missed",
+                event.room, event.missed_events
+            );
+            return None;
+        }
+        Event::CommandRejected(event) => {
+            eprintln!("{} rejected: {}", event.command, event.reason);
+            return None;
+        }
+        _ => return None,
+    };
+
+    Some(line)
+}
@@ -0,0 +1,95 @@
+use std::time::{Duration, Instant};
+
+use comms::{
+    event::{self, Event},
+    handshake::Compression,
+    transport,
+};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    runtime::Runtime,
+};
+use tokio_stream::StreamExt;
+
+// a history response is the largest event sent by the server, hence benefits the most from compression
+fn history_response_event() -> Event {
+    Event::HistoryResponse(event::HistoryResponseEvent {
+        room: "general".into(),
+        history: (0..100)
+            .map(|i| event::HistoricalMessage {
+                id: format!("{}", i),
+                user_id: format!("user-{}", i % 5),
+                display_name: None,
+                content: format!("message number {} sent to the general room", i),
+                content_type: event::ContentType::Plain,
+                timestamp: 1_700_000_000_000 + i * 1000,
+                kind: event::HistoricalMessageKind::Message,
+            })
+            .collect(),
+    })
+}
+
+// connect a client and a server over the loopback interface with the given compression
+async fn connect(
+    compression: Compression,
+) -> (
+    transport::server::EventWriter,
+    transport::client::EventStream,
+) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let (server, client) = tokio::join!(
+        async {
+            let (tcp_stream, _) = listener.accept().await.unwrap();
+            transport::server::accept_tcp_stream(tcp_stream)
+                .await
+                .unwrap()
+        },
+        async {
+            let tcp_stream = TcpStream::connect(addr).await.unwrap();
+            transport::client::connect_tcp_stream(tcp_stream, &[compression])
+                .await
+                .unwrap()
+        }
+    );
+
+    (server.1, client.0)
+}
+
+fn bench_event_round_trip(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let event = history_response_event();
+    let mut group = c.benchmark_group("history_response_round_trip");
+
+    for compression in [Compression::None, Compression::Deflate] {
+        let (mut event_writer, mut event_stream) = runtime.block_on(connect(compression));
+
+        group.bench_function(
+            BenchmarkId::from_parameter(format!("{:?}", compression)),
+            |b| {
+                b.iter_custom(|iters| {
+                    runtime.block_on(async {
+                        let start = Instant::now();
+                        for _ in 0..iters {
+                            event_writer.write(&event).await.unwrap();
+                            event_stream.next().await.unwrap().unwrap();
+                        }
+
+                        start.elapsed()
+                    })
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().measurement_time(Duration::from_secs(5));
+    targets = bench_event_round_trip
+}
+criterion_main!(benches);
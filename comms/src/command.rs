@@ -1,11 +1,32 @@
 use serde::{Deserialize, Serialize};
 
+use crate::event::{ContentType, PresenceStatus};
+
+/// User Command for logging in, the first command of every session.
+/// The server answers with a `LoginSuccessful` event, or with a `LoginRejected` event after which another login may be sent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoginCommand {
+    // The name the user would like to chat as, only taken by servers without an identity provider.
+    // The server picks a random one if it is not given.
+    #[serde(rename = "u", default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    // A token issued by the identity provider the server trusts, or the token of a bot.
+    #[serde(rename = "t", default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    // Whether to log in as a guest with a temporary identity instead, if the server allows guests.
+    #[serde(rename = "g", default, skip_serializing_if = "std::ops::Not::not")]
+    pub guest: bool,
+}
+
 /// User Command for joining a room.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct JoinRoomCommand {
     // The room to join.
     #[serde(rename = "r")]
     pub room: String,
+    // Whether to wait in the queue of the room until a slot frees up if the room is full.
+    #[serde(rename = "w", default)]
+    pub wait_if_full: bool,
 }
 
 /// User Command for leaving a room.
@@ -16,6 +37,18 @@ pub struct LeaveRoomCommand {
     pub room: String,
 }
 
+/// Prefix of the content of messages encrypted end-to-end by the clients, followed by the base64 encoded ciphertext.
+/// The server relays the content of such messages without being able to read it.
+pub const ENCRYPTED_CONTENT_PREFIX: &str = "e2e:";
+
+/// The longest encrypted content a message whose plain text has at most the given number of characters can have.
+/// Every character takes up to 4 bytes, sealed with ChaCha20-Poly1305 along with a 12 byte nonce and a 16 byte tag.
+pub fn max_encrypted_length(max_length: usize) -> usize {
+    let sealed_length = 12 + max_length * 4 + 16;
+
+    ENCRYPTED_CONTENT_PREFIX.len() + sealed_length.div_ceil(3) * 4
+}
+
 /// User Command for sending a message.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SendMessageCommand {
@@ -25,6 +58,22 @@ pub struct SendMessageCommand {
     // The content of the message.
     #[serde(rename = "c")]
     pub content: String,
+    // How the content of the message is meant to be shown, plain if it is not given.
+    #[serde(rename = "ty", default, skip_serializing_if = "ContentType::is_plain")]
+    pub content_type: ContentType,
+    // An id the client picks for the message, echoed along with it, so the sending session can tell its own
+    // messages apart, e.g. to replace the one shown while it was being sent. At most 64 characters are echoed.
+    #[serde(rename = "n", default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+}
+
+/// User Command for marking a room as read, e.g. when the user opens it.
+/// The read state is synced to the other sessions of the user.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarkRoomReadCommand {
+    // The name of the room to mark as read.
+    #[serde(rename = "r")]
+    pub room: String,
 }
 
 /// User Command for quitting the whole chat session.
@@ -33,6 +82,306 @@ pub struct GetHistoryCommand {
     // The room to send the message to.
     #[serde(rename = "r")]
     pub room: String,
+    // The number of entries to send at once, the history is streamed in chunks followed by a done event if set,
+    // and sent as a single response otherwise.
+    #[serde(rename = "cs", default, skip_serializing_if = "Option::is_none")]
+    pub chunk_size: Option<usize>,
+}
+
+/// User Command for changing the welcome message of a room, only allowed for the owners of the room.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SetWelcomeMessageCommand {
+    // The room to change the welcome message of.
+    #[serde(rename = "r")]
+    pub room: String,
+    // The new welcome message, the welcome message is removed if not set.
+    #[serde(rename = "m", default)]
+    pub message: Option<String>,
+}
+
+/// User Command for adding a user to or removing a user from a group, only allowed for admins.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupMembershipCommand {
+    // The group to change the members of.
+    #[serde(rename = "g")]
+    pub group: String,
+    // The user to add or remove.
+    #[serde(rename = "u")]
+    pub user_id: String,
+}
+
+/// The capabilities granted to a bot account.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BotScope {
+    // The rooms the bot may join, any room accessible to the bot if not set.
+    #[serde(rename = "r", default, skip_serializing_if = "Option::is_none")]
+    pub rooms: Option<Vec<String>>,
+    // Whether the bot may only read the rooms it joins without sending messages.
+    #[serde(rename = "ro", default)]
+    pub read_only: bool,
+}
+
+/// User Command for issuing a token to a bot account, only allowed for admins.
+/// Issuing a new token for the same bot replaces its previous token.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IssueBotTokenCommand {
+    // The name of the bot, its user id is derived from it.
+    #[serde(rename = "b")]
+    pub bot_name: String,
+    // The capabilities of the bot.
+    #[serde(rename = "s")]
+    pub scope: BotScope,
+}
+
+/// User Command for revoking the token of a bot account, only allowed for admins.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RevokeBotTokenCommand {
+    // The name of the bot.
+    #[serde(rename = "b")]
+    pub bot_name: String,
+}
+
+/// User Command for listing the connected sessions of the user, e.g. their other devices.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListSessionsCommand;
+
+/// User Command for closing another session of the user remotely.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DisconnectSessionCommand {
+    // The id of the session to close.
+    #[serde(rename = "s")]
+    pub session_id: String,
+}
+
+/// User Command for telling the other users whether the user is paying attention, e.g. away from the terminal.
+/// The user is shown as away only once all of their sessions are away.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SetPresenceCommand {
+    // The presence of the session sending the command.
+    #[serde(rename = "s")]
+    pub status: PresenceStatus,
+}
+
+/// User Command for telling the other users what the user is up to in their own words, e.g. "in a meeting".
+/// The status text is kept while the user is connected and shown next to their presence.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SetStatusTextCommand {
+    // The new status text, the status text is removed if not set.
+    #[serde(rename = "t", default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+/// User Command for looking up another user, e.g. whether they are online and which rooms they share with the user.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GetUserInfoCommand {
+    // The id of the user to look up.
+    #[serde(rename = "u")]
+    pub user_id: String,
+}
+
+/// User Command for finding the users known to the server whose id contains the query, e.g. to invite them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchUsersCommand {
+    // The part of the user id to look for, case insensitive.
+    #[serde(rename = "q")]
+    pub query: String,
+}
+
+/// User Command for looking up a room, e.g. its topic and how many users are in it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GetRoomInfoCommand {
+    // The name of the room to look up.
+    #[serde(rename = "r")]
+    pub room: String,
+}
+
+/// User Command for checking which of the given rooms still exist, e.g. the rooms the user has left lately.
+/// Rooms the user can not access are reported as missing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckRoomsCommand {
+    // The names of the rooms to check.
+    #[serde(rename = "rs")]
+    pub rooms: Vec<String>,
+}
+
+/// User Command for listing the rooms the user can access, e.g. to catch up with rooms created or removed meanwhile.
+/// The server answers with a [crate::event::RoomListReplyEvent].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListRoomsCommand;
+
+/// User Command for asking the other members of an encrypted room for its key.
+/// The members holding the key answer with a [ShareRoomKeyCommand] once their users have confirmed the session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RequestRoomKeyCommand {
+    // The name of the encrypted room.
+    #[serde(rename = "r")]
+    pub room: String,
+    // The base64 encoded X25519 public key of the session, the room key is sealed for it.
+    #[serde(rename = "k")]
+    pub public_key: String,
+}
+
+/// User Command for telling the server the session holds the key of an encrypted room, once it has opened a shared key.
+/// Sessions joining the room ask for its key instead of creating a new one as long as a session holds it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HoldRoomKeyCommand {
+    // The name of the encrypted room.
+    #[serde(rename = "r")]
+    pub room: String,
+}
+
+/// User Command for handing the key of an encrypted room to the session which requested it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShareRoomKeyCommand {
+    // The name of the encrypted room.
+    #[serde(rename = "r")]
+    pub room: String,
+    // The id of the user who requested the key.
+    #[serde(rename = "u")]
+    pub user_id: String,
+    // The id of the session which requested the key.
+    #[serde(rename = "s")]
+    pub session_id: String,
+    // The base64 encoded X25519 public key of the sharing session, needed to open the sealed key.
+    #[serde(rename = "k")]
+    pub public_key: String,
+    // The room key, sealed for the public key of the requesting session.
+    #[serde(rename = "sk")]
+    pub sealed_key: String,
+}
+
+/// User Command for reporting a message to the moderators of the room it was sent to.
+/// The server looks the author and the content of the message up in the history of the room.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportMessageCommand {
+    // The name of the room the message was sent to.
+    #[serde(rename = "r")]
+    pub room: String,
+    // The id of the reported message.
+    #[serde(rename = "m")]
+    pub message_id: String,
+    // Why the message is reported.
+    #[serde(rename = "rs")]
+    pub reason: String,
+}
+
+/// User Command for listing the open reports, only allowed for moderators.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListReportsCommand;
+
+/// User Command for closing a report once it has been dealt with, only allowed for moderators.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResolveReportCommand {
+    // The id of the report.
+    #[serde(rename = "i")]
+    pub report_id: String,
+}
+
+/// User Command for letting a user into a room which requires approval or turning them away, only allowed for its moderators.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JoinRequestDecisionCommand {
+    // The name of the room the user asked to join.
+    #[serde(rename = "r")]
+    pub room: String,
+    // The user who asked to join.
+    #[serde(rename = "u")]
+    pub user_id: String,
+}
+
+/// User Command for looking at how the broadcast channels of the rooms keep up, only allowed for admins.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GetBroadcastMetricsCommand;
+
+/// User Command for the activity of a room, or of every room if none is given, which only admins may ask for.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GetRoomStatsCommand {
+    // The name of the room.
+    #[serde(rename = "r", default, skip_serializing_if = "Option::is_none")]
+    pub room: Option<String>,
+}
+
+/// User Command for archiving a room, only allowed for the owners of the room and admins.
+/// Archived rooms keep their history but take no more messages until they are reopened.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveRoomCommand {
+    // The room to archive.
+    #[serde(rename = "r")]
+    pub room: String,
+    // When to archive the room as a unix timestamp in milliseconds, right away if not set.
+    #[serde(rename = "t", default, skip_serializing_if = "Option::is_none")]
+    pub archive_at: Option<u64>,
+}
+
+/// User Command for reopening an archived room, or calling off its scheduled archival, only allowed for the owners of the room and admins.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReopenRoomCommand {
+    // The room to reopen.
+    #[serde(rename = "r")]
+    pub room: String,
+}
+
+/// User Command for changing the description or the capacity of a room, only allowed for the owners of the room and admins.
+/// The users who can access the room are sent the new metadata, see [crate::event::RoomMetadataUpdatedBroadcastEvent].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UpdateRoomMetadataCommand {
+    // The room to update.
+    #[serde(rename = "r")]
+    pub room: String,
+    // The new description of the room, kept as it is if not set.
+    #[serde(rename = "d", default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    // The new maximum number of unique users in the room, 0 lifts the limit, kept as it is if not set.
+    #[serde(rename = "c", default, skip_serializing_if = "Option::is_none")]
+    pub capacity: Option<usize>,
+}
+
+/// User Command for deleting everything the server keeps about a user, e.g. on their request, only allowed for admins.
+/// Their messages are removed from the history of every room and their sessions are closed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PurgeUserCommand {
+    // The id of the user to purge.
+    #[serde(rename = "u")]
+    pub user_id: String,
+}
+
+/// User Command for the commands a user has recently sent, e.g. to find out why their client misbehaves, only allowed for admins.
+/// Only the kind of each command and when it was sent are kept, never its content.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GetCommandHistoryCommand {
+    // The id of the user to look up.
+    #[serde(rename = "u")]
+    pub user_id: String,
+}
+
+/// User Command for creating an invite code to a private room, only allowed for its owners and the admins.
+/// Whoever redeems the code with a [JoinWithCodeCommand] can access the room from then on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreateInviteCodeCommand {
+    // The slug of the private room to invite to.
+    #[serde(rename = "r")]
+    pub room: String,
+    // How long the code can be redeemed for, in seconds, a day if not set.
+    #[serde(rename = "ttl", default, skip_serializing_if = "Option::is_none")]
+    pub ttl_secs: Option<u64>,
+    // How many users can redeem the code, a single one if not set.
+    #[serde(rename = "mu", default, skip_serializing_if = "Option::is_none")]
+    pub max_uses: Option<u32>,
+}
+
+/// User Command for joining the room of an invite code, granting access to it first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JoinWithCodeCommand {
+    // The invite code, as created by a [CreateInviteCodeCommand].
+    #[serde(rename = "c")]
+    pub code: String,
+}
+
+/// User Command for measuring the round trip time to the server, answered right away with a pong event.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PingCommand {
+    // The time the ping was sent at as a unix timestamp in milliseconds, echoed back by the server.
+    #[serde(rename = "t")]
+    pub sent_at: u64,
 }
 
 /// User Command for quitting the whole chat session.
@@ -44,32 +393,135 @@ pub struct QuitCommand;
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "_ct", rename_all = "snake_case")]
 pub enum UserCommand {
+    Login(LoginCommand),
     JoinRoom(JoinRoomCommand),
     LeaveRoom(LeaveRoomCommand),
     SendMessage(SendMessageCommand),
     GetHistory(GetHistoryCommand),
+    SetWelcomeMessage(SetWelcomeMessageCommand),
+    AddGroupMember(GroupMembershipCommand),
+    RemoveGroupMember(GroupMembershipCommand),
+    IssueBotToken(IssueBotTokenCommand),
+    RevokeBotToken(RevokeBotTokenCommand),
+    ListSessions(ListSessionsCommand),
+    DisconnectSession(DisconnectSessionCommand),
+    MarkRoomRead(MarkRoomReadCommand),
+    SetPresence(SetPresenceCommand),
+    SetStatusText(SetStatusTextCommand),
+    GetUserInfo(GetUserInfoCommand),
+    SearchUsers(SearchUsersCommand),
+    GetRoomInfo(GetRoomInfoCommand),
+    CheckRooms(CheckRoomsCommand),
+    ListRooms(ListRoomsCommand),
+    RequestRoomKey(RequestRoomKeyCommand),
+    HoldRoomKey(HoldRoomKeyCommand),
+    ShareRoomKey(ShareRoomKeyCommand),
+    ReportMessage(ReportMessageCommand),
+    ListReports(ListReportsCommand),
+    ResolveReport(ResolveReportCommand),
+    ApproveJoinRequest(JoinRequestDecisionCommand),
+    DenyJoinRequest(JoinRequestDecisionCommand),
+    GetBroadcastMetrics(GetBroadcastMetricsCommand),
+    GetRoomStats(GetRoomStatsCommand),
+    ArchiveRoom(ArchiveRoomCommand),
+    ReopenRoom(ReopenRoomCommand),
+    UpdateRoomMetadata(UpdateRoomMetadataCommand),
+    PurgeUser(PurgeUserCommand),
+    GetCommandHistory(GetCommandHistoryCommand),
+    CreateInviteCode(CreateInviteCodeCommand),
+    JoinWithCode(JoinWithCodeCommand),
+    Ping(PingCommand),
     Quit(QuitCommand),
 }
 
+impl UserCommand {
+    /// The name of the command as it is sent over the wire, e.g. `join_room`
+    pub fn name(&self) -> &'static str {
+        match self {
+            UserCommand::Login(_) => "login",
+            UserCommand::JoinRoom(_) => "join_room",
+            UserCommand::LeaveRoom(_) => "leave_room",
+            UserCommand::SendMessage(_) => "send_message",
+            UserCommand::GetHistory(_) => "get_history",
+            UserCommand::SetWelcomeMessage(_) => "set_welcome_message",
+            UserCommand::AddGroupMember(_) => "add_group_member",
+            UserCommand::RemoveGroupMember(_) => "remove_group_member",
+            UserCommand::IssueBotToken(_) => "issue_bot_token",
+            UserCommand::RevokeBotToken(_) => "revoke_bot_token",
+            UserCommand::ListSessions(_) => "list_sessions",
+            UserCommand::DisconnectSession(_) => "disconnect_session",
+            UserCommand::MarkRoomRead(_) => "mark_room_read",
+            UserCommand::SetPresence(_) => "set_presence",
+            UserCommand::SetStatusText(_) => "set_status_text",
+            UserCommand::GetUserInfo(_) => "get_user_info",
+            UserCommand::SearchUsers(_) => "search_users",
+            UserCommand::GetRoomInfo(_) => "get_room_info",
+            UserCommand::CheckRooms(_) => "check_rooms",
+            UserCommand::ListRooms(_) => "list_rooms",
+            UserCommand::RequestRoomKey(_) => "request_room_key",
+            UserCommand::HoldRoomKey(_) => "hold_room_key",
+            UserCommand::ShareRoomKey(_) => "share_room_key",
+            UserCommand::ReportMessage(_) => "report_message",
+            UserCommand::ListReports(_) => "list_reports",
+            UserCommand::ResolveReport(_) => "resolve_report",
+            UserCommand::ApproveJoinRequest(_) => "approve_join_request",
+            UserCommand::DenyJoinRequest(_) => "deny_join_request",
+            UserCommand::GetBroadcastMetrics(_) => "get_broadcast_metrics",
+            UserCommand::GetRoomStats(_) => "get_room_stats",
+            UserCommand::ArchiveRoom(_) => "archive_room",
+            UserCommand::ReopenRoom(_) => "reopen_room",
+            UserCommand::UpdateRoomMetadata(_) => "update_room_metadata",
+            UserCommand::PurgeUser(_) => "purge_user",
+            UserCommand::GetCommandHistory(_) => "get_command_history",
+            UserCommand::CreateInviteCode(_) => "create_invite_code",
+            UserCommand::JoinWithCode(_) => "join_with_code",
+            UserCommand::Ping(_) => "ping",
+            UserCommand::Quit(_) => "quit",
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     // given a command enum, and an expect string, asserts that command is serialized / deserialized appropriately
+    // and that its name is the one it is tagged with
     fn assert_command_serialization(command: &UserCommand, expected: &str) {
         let serialized = serde_json::to_string(&command).unwrap();
         assert_eq!(serialized, expected);
+        assert!(serialized.starts_with(&format!(r#"{{"_ct":"{}""#, command.name())));
         let deserialized: UserCommand = serde_json::from_str(&serialized).unwrap();
         assert_eq!(deserialized, *command);
     }
 
+    #[test]
+    fn test_login_command() {
+        let command = UserCommand::Login(LoginCommand {
+            username: Some("test".to_string()),
+            token: Some("test".to_string()),
+            guest: false,
+        });
+
+        assert_command_serialization(&command, r#"{"_ct":"login","u":"test","t":"test"}"#);
+
+        let command = UserCommand::Login(LoginCommand {
+            username: None,
+            token: None,
+            guest: true,
+        });
+
+        assert_command_serialization(&command, r#"{"_ct":"login","g":true}"#);
+    }
+
     #[test]
     fn test_join_command() {
         let command = UserCommand::JoinRoom(JoinRoomCommand {
             room: "test".to_string(),
+            wait_if_full: true,
         });
 
-        assert_command_serialization(&command, r#"{"_ct":"join_room","r":"test"}"#);
+        assert_command_serialization(&command, r#"{"_ct":"join_room","r":"test","w":true}"#);
     }
 
     #[test]
@@ -86,11 +538,407 @@ mod tests {
         let command = UserCommand::SendMessage(SendMessageCommand {
             room: "test".to_string(),
             content: "test".to_string(),
+            content_type: ContentType::Plain,
+            nonce: None,
         });
 
         assert_command_serialization(&command, r#"{"_ct":"send_message","r":"test","c":"test"}"#);
     }
 
+    #[test]
+    fn test_message_command_with_nonce() {
+        let command = UserCommand::SendMessage(SendMessageCommand {
+            room: "test".to_string(),
+            content: "test".to_string(),
+            content_type: ContentType::Plain,
+            nonce: Some("abc".to_string()),
+        });
+
+        assert_command_serialization(
+            &command,
+            r#"{"_ct":"send_message","r":"test","c":"test","n":"abc"}"#,
+        );
+    }
+
+    #[test]
+    fn test_message_command_with_content_type() {
+        let command = UserCommand::SendMessage(SendMessageCommand {
+            room: "test".to_string(),
+            content: "waves".to_string(),
+            content_type: ContentType::Action,
+            nonce: None,
+        });
+
+        assert_command_serialization(
+            &command,
+            r#"{"_ct":"send_message","r":"test","c":"waves","ty":"action"}"#,
+        );
+    }
+
+    #[test]
+    fn test_set_welcome_message_command() {
+        let command = UserCommand::SetWelcomeMessage(SetWelcomeMessageCommand {
+            room: "test".to_string(),
+            message: Some("test".to_string()),
+        });
+
+        assert_command_serialization(
+            &command,
+            r#"{"_ct":"set_welcome_message","r":"test","m":"test"}"#,
+        );
+    }
+
+    #[test]
+    fn test_group_membership_commands() {
+        let membership = GroupMembershipCommand {
+            group: "test".to_string(),
+            user_id: "test".to_string(),
+        };
+
+        assert_command_serialization(
+            &UserCommand::AddGroupMember(membership.clone()),
+            r#"{"_ct":"add_group_member","g":"test","u":"test"}"#,
+        );
+        assert_command_serialization(
+            &UserCommand::RemoveGroupMember(membership),
+            r#"{"_ct":"remove_group_member","g":"test","u":"test"}"#,
+        );
+    }
+
+    #[test]
+    fn test_issue_bot_token_command() {
+        let command = UserCommand::IssueBotToken(IssueBotTokenCommand {
+            bot_name: "test".to_string(),
+            scope: BotScope {
+                rooms: Some(vec!["test".to_string()]),
+                read_only: true,
+            },
+        });
+
+        assert_command_serialization(
+            &command,
+            r#"{"_ct":"issue_bot_token","b":"test","s":{"r":["test"],"ro":true}}"#,
+        );
+    }
+
+    #[test]
+    fn test_revoke_bot_token_command() {
+        let command = UserCommand::RevokeBotToken(RevokeBotTokenCommand {
+            bot_name: "test".to_string(),
+        });
+
+        assert_command_serialization(&command, r#"{"_ct":"revoke_bot_token","b":"test"}"#);
+    }
+
+    #[test]
+    fn test_list_sessions_command() {
+        let command = UserCommand::ListSessions(ListSessionsCommand);
+
+        assert_command_serialization(&command, r#"{"_ct":"list_sessions"}"#);
+    }
+
+    #[test]
+    fn test_disconnect_session_command() {
+        let command = UserCommand::DisconnectSession(DisconnectSessionCommand {
+            session_id: "test".to_string(),
+        });
+
+        assert_command_serialization(&command, r#"{"_ct":"disconnect_session","s":"test"}"#);
+    }
+
+    #[test]
+    fn test_mark_room_read_command() {
+        let command = UserCommand::MarkRoomRead(MarkRoomReadCommand {
+            room: "test".to_string(),
+        });
+
+        assert_command_serialization(&command, r#"{"_ct":"mark_room_read","r":"test"}"#);
+    }
+
+    #[test]
+    fn test_set_presence_command() {
+        let command = UserCommand::SetPresence(SetPresenceCommand {
+            status: PresenceStatus::Away,
+        });
+
+        assert_command_serialization(&command, r#"{"_ct":"set_presence","s":"away"}"#);
+    }
+
+    #[test]
+    fn test_set_status_text_command() {
+        let command = UserCommand::SetStatusText(SetStatusTextCommand {
+            text: Some("in a meeting".to_string()),
+        });
+        assert_command_serialization(&command, r#"{"_ct":"set_status_text","t":"in a meeting"}"#);
+
+        let command = UserCommand::SetStatusText(SetStatusTextCommand { text: None });
+        assert_command_serialization(&command, r#"{"_ct":"set_status_text"}"#);
+    }
+
+    #[test]
+    fn test_get_user_info_command() {
+        let command = UserCommand::GetUserInfo(GetUserInfoCommand {
+            user_id: "test".to_string(),
+        });
+
+        assert_command_serialization(&command, r#"{"_ct":"get_user_info","u":"test"}"#);
+    }
+
+    #[test]
+    fn test_search_users_command() {
+        let command = UserCommand::SearchUsers(SearchUsersCommand {
+            query: "test".to_string(),
+        });
+
+        assert_command_serialization(&command, r#"{"_ct":"search_users","q":"test"}"#);
+    }
+
+    #[test]
+    fn test_get_room_info_command() {
+        let command = UserCommand::GetRoomInfo(GetRoomInfoCommand {
+            room: "test".to_string(),
+        });
+
+        assert_command_serialization(&command, r#"{"_ct":"get_room_info","r":"test"}"#);
+    }
+
+    #[test]
+    fn test_check_rooms_command() {
+        let command = UserCommand::CheckRooms(CheckRoomsCommand {
+            rooms: vec!["test".to_string()],
+        });
+
+        assert_command_serialization(&command, r#"{"_ct":"check_rooms","rs":["test"]}"#);
+    }
+
+    #[test]
+    fn test_list_rooms_command() {
+        let command = UserCommand::ListRooms(ListRoomsCommand);
+
+        assert_command_serialization(&command, r#"{"_ct":"list_rooms"}"#);
+    }
+
+    #[test]
+    fn test_request_room_key_command() {
+        let command = UserCommand::RequestRoomKey(RequestRoomKeyCommand {
+            room: "test".to_string(),
+            public_key: "key".to_string(),
+        });
+
+        assert_command_serialization(
+            &command,
+            r#"{"_ct":"request_room_key","r":"test","k":"key"}"#,
+        );
+    }
+
+    #[test]
+    fn test_hold_room_key_command() {
+        let command = UserCommand::HoldRoomKey(HoldRoomKeyCommand {
+            room: "test".to_string(),
+        });
+
+        assert_command_serialization(&command, r#"{"_ct":"hold_room_key","r":"test"}"#);
+    }
+
+    #[test]
+    fn test_share_room_key_command() {
+        let command = UserCommand::ShareRoomKey(ShareRoomKeyCommand {
+            room: "test".to_string(),
+            user_id: "test".to_string(),
+            session_id: "session".to_string(),
+            public_key: "key".to_string(),
+            sealed_key: "sealed".to_string(),
+        });
+
+        assert_command_serialization(
+            &command,
+            r#"{"_ct":"share_room_key","r":"test","u":"test","s":"session","k":"key","sk":"sealed"}"#,
+        );
+    }
+
+    #[test]
+    fn test_report_message_command() {
+        let command = UserCommand::ReportMessage(ReportMessageCommand {
+            room: "test".to_string(),
+            message_id: "message".to_string(),
+            reason: "spam".to_string(),
+        });
+
+        assert_command_serialization(
+            &command,
+            r#"{"_ct":"report_message","r":"test","m":"message","rs":"spam"}"#,
+        );
+    }
+
+    #[test]
+    fn test_list_reports_command() {
+        let command = UserCommand::ListReports(ListReportsCommand);
+
+        assert_command_serialization(&command, r#"{"_ct":"list_reports"}"#);
+    }
+
+    #[test]
+    fn test_resolve_report_command() {
+        let command = UserCommand::ResolveReport(ResolveReportCommand {
+            report_id: "test".to_string(),
+        });
+
+        assert_command_serialization(&command, r#"{"_ct":"resolve_report","i":"test"}"#);
+    }
+
+    #[test]
+    fn test_join_request_decision_commands() {
+        let decision = JoinRequestDecisionCommand {
+            room: "test".to_string(),
+            user_id: "test".to_string(),
+        };
+
+        assert_command_serialization(
+            &UserCommand::ApproveJoinRequest(decision.clone()),
+            r#"{"_ct":"approve_join_request","r":"test","u":"test"}"#,
+        );
+        assert_command_serialization(
+            &UserCommand::DenyJoinRequest(decision),
+            r#"{"_ct":"deny_join_request","r":"test","u":"test"}"#,
+        );
+    }
+
+    #[test]
+    fn test_get_broadcast_metrics_command() {
+        let command = UserCommand::GetBroadcastMetrics(GetBroadcastMetricsCommand);
+
+        assert_command_serialization(&command, r#"{"_ct":"get_broadcast_metrics"}"#);
+    }
+
+    #[test]
+    fn test_get_room_stats_command() {
+        let command = UserCommand::GetRoomStats(GetRoomStatsCommand {
+            room: Some("test".to_string()),
+        });
+
+        assert_command_serialization(&command, r#"{"_ct":"get_room_stats","r":"test"}"#);
+
+        let command = UserCommand::GetRoomStats(GetRoomStatsCommand { room: None });
+
+        assert_command_serialization(&command, r#"{"_ct":"get_room_stats"}"#);
+    }
+
+    #[test]
+    fn test_archive_room_command() {
+        let command = UserCommand::ArchiveRoom(ArchiveRoomCommand {
+            room: "test".to_string(),
+            archive_at: Some(1700000000000),
+        });
+
+        assert_command_serialization(
+            &command,
+            r#"{"_ct":"archive_room","r":"test","t":1700000000000}"#,
+        );
+
+        let command = UserCommand::ArchiveRoom(ArchiveRoomCommand {
+            room: "test".to_string(),
+            archive_at: None,
+        });
+
+        assert_command_serialization(&command, r#"{"_ct":"archive_room","r":"test"}"#);
+    }
+
+    #[test]
+    fn test_update_room_metadata_command() {
+        let command = UserCommand::UpdateRoomMetadata(UpdateRoomMetadataCommand {
+            room: "test".to_string(),
+            description: Some("test".to_string()),
+            capacity: Some(10),
+        });
+
+        assert_command_serialization(
+            &command,
+            r#"{"_ct":"update_room_metadata","r":"test","d":"test","c":10}"#,
+        );
+
+        let command = UserCommand::UpdateRoomMetadata(UpdateRoomMetadataCommand {
+            room: "test".to_string(),
+            description: None,
+            capacity: None,
+        });
+
+        assert_command_serialization(&command, r#"{"_ct":"update_room_metadata","r":"test"}"#);
+    }
+
+    #[test]
+    fn test_reopen_room_command() {
+        let command = UserCommand::ReopenRoom(ReopenRoomCommand {
+            room: "test".to_string(),
+        });
+
+        assert_command_serialization(&command, r#"{"_ct":"reopen_room","r":"test"}"#);
+    }
+
+    #[test]
+    fn test_get_history_command() {
+        let command = UserCommand::GetHistory(GetHistoryCommand {
+            room: "test".to_string(),
+            chunk_size: Some(100),
+        });
+
+        assert_command_serialization(&command, r#"{"_ct":"get_history","r":"test","cs":100}"#);
+
+        let command = UserCommand::GetHistory(GetHistoryCommand {
+            room: "test".to_string(),
+            chunk_size: None,
+        });
+
+        assert_command_serialization(&command, r#"{"_ct":"get_history","r":"test"}"#);
+    }
+
+    #[test]
+    fn test_purge_user_command() {
+        let command = UserCommand::PurgeUser(PurgeUserCommand {
+            user_id: "alice".to_string(),
+        });
+
+        assert_command_serialization(&command, r#"{"_ct":"purge_user","u":"alice"}"#);
+    }
+
+    #[test]
+    fn test_get_command_history_command() {
+        let command = UserCommand::GetCommandHistory(GetCommandHistoryCommand {
+            user_id: "alice".to_string(),
+        });
+
+        assert_command_serialization(&command, r#"{"_ct":"get_command_history","u":"alice"}"#);
+    }
+
+    #[test]
+    fn test_create_invite_code_command() {
+        let command = UserCommand::CreateInviteCode(CreateInviteCodeCommand {
+            room: "test".to_string(),
+            ttl_secs: Some(3600),
+            max_uses: None,
+        });
+
+        assert_command_serialization(
+            &command,
+            r#"{"_ct":"create_invite_code","r":"test","ttl":3600}"#,
+        );
+    }
+
+    #[test]
+    fn test_join_with_code_command() {
+        let command = UserCommand::JoinWithCode(JoinWithCodeCommand {
+            code: "abcd-efgh".to_string(),
+        });
+
+        assert_command_serialization(&command, r#"{"_ct":"join_with_code","c":"abcd-efgh"}"#);
+    }
+
+    #[test]
+    fn test_ping_command() {
+        let command = UserCommand::Ping(PingCommand { sent_at: 1 });
+
+        assert_command_serialization(&command, r#"{"_ct":"ping","t":1}"#);
+    }
+
     #[test]
     fn test_quit_command() {
         let command = UserCommand::Quit(QuitCommand);
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
 /// The detail of a given room
@@ -9,6 +11,29 @@ pub struct RoomDetail {
     /// The description of the room
     #[serde(rename = "d")]
     pub description: String,
+    /// Whether the messages of the room are encrypted end-to-end by the clients
+    #[serde(rename = "e", default, skip_serializing_if = "std::ops::Not::not")]
+    pub encrypted: bool,
+    /// Whether the room has been archived, it keeps its history but takes no more messages
+    #[serde(rename = "a", default, skip_serializing_if = "std::ops::Not::not")]
+    pub archived: bool,
+    /// The category the room is listed under, e.g. `engineering`, uncategorized rooms are listed on their own
+    #[serde(rename = "c", default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+}
+
+/// The detail of a connected session of a user, e.g. one of their devices
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionDetail {
+    /// The id of the session
+    #[serde(rename = "s")]
+    pub session_id: String,
+    /// The address the session connected from
+    #[serde(rename = "a")]
+    pub address: String,
+    /// When the session connected, in milliseconds since the unix epoch
+    #[serde(rename = "ca")]
+    pub connected_at: u64,
 }
 
 /// A user has successfully logged in
@@ -23,6 +48,12 @@ pub struct LoginSuccessfulReplyEvent {
     /// The list of rooms the user can participate, unique and ordered
     #[serde(rename = "rs")]
     pub rooms: Vec<RoomDetail>,
+    /// The maximum number of characters a message can have
+    #[serde(rename = "ml")]
+    pub max_message_length: usize,
+    /// The number of connected sessions of the user, including this one
+    #[serde(rename = "sc")]
+    pub session_count: usize,
 }
 
 /// Users new room participation status
@@ -47,6 +78,155 @@ pub struct RoomParticipationBroadcastEvent {
     pub status: RoomParticipationStatus,
 }
 
+/// Whether a user is paying attention to the chat
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresenceStatus {
+    Online,
+    Away,
+}
+
+/// A user has become away or has come back online, broadcast to the rooms the user is in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PresenceChangedBroadcastEvent {
+    /// The slug of the room the user is in
+    #[serde(rename = "r")]
+    pub room: String,
+    /// The id of the user whose presence has changed
+    #[serde(rename = "u")]
+    pub user_id: String,
+    /// The new presence of the user
+    #[serde(rename = "s")]
+    pub status: PresenceStatus,
+}
+
+/// A user has changed or removed their status text, broadcast to the rooms the user is in
+/// Also broadcast to a room when a user with a status text joins it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusTextChangedBroadcastEvent {
+    /// The slug of the room the user is in
+    #[serde(rename = "r")]
+    pub room: String,
+    /// The id of the user whose status text has changed
+    #[serde(rename = "u")]
+    pub user_id: String,
+    /// The new status text of the user, not set if they have removed it
+    #[serde(rename = "t", default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+/// A reply to the user with what is known about another user
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserInfoReplyEvent {
+    /// The id of the user that has been looked up
+    #[serde(rename = "u")]
+    pub user_id: String,
+    /// The presence of the user, not set if they are offline
+    #[serde(rename = "p", default, skip_serializing_if = "Option::is_none")]
+    pub presence: Option<PresenceStatus>,
+    /// The status text of the user, not set if they have none or are offline
+    #[serde(rename = "st", default, skip_serializing_if = "Option::is_none")]
+    pub status_text: Option<String>,
+    /// The slugs of the rooms both users are in
+    #[serde(rename = "sr")]
+    pub shared_rooms: Vec<String>,
+}
+
+/// A user found by a search
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserSearchResult {
+    /// The id of the user
+    #[serde(rename = "u")]
+    pub user_id: String,
+    /// The presence of the user, not set if they are offline
+    #[serde(rename = "p", default, skip_serializing_if = "Option::is_none")]
+    pub presence: Option<PresenceStatus>,
+}
+
+/// A reply to the user with the known users matching their search
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserSearchResultsReplyEvent {
+    /// The query that has been searched for
+    #[serde(rename = "q")]
+    pub query: String,
+    /// The matching users, the ones starting with the query first and the recently seen ones after
+    #[serde(rename = "us")]
+    pub users: Vec<UserSearchResult>,
+}
+
+/// A reply to the user with the details of a room
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomInfoReplyEvent {
+    /// The slug of the room that has been looked up
+    #[serde(rename = "r")]
+    pub room: String,
+    /// The description of the room, its topic
+    #[serde(rename = "d")]
+    pub description: String,
+    /// The number of unique users in the room
+    #[serde(rename = "mc")]
+    pub member_count: usize,
+    /// The maximum number of unique users in the room, unlimited if not set
+    #[serde(rename = "c", default, skip_serializing_if = "Option::is_none")]
+    pub capacity: Option<usize>,
+    /// The ids of the users who manage the room
+    #[serde(rename = "o")]
+    pub owners: Vec<String>,
+}
+
+/// A reply to the user with which of the rooms they have asked about still exist
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomsCheckedReplyEvent {
+    /// The rooms which exist and the user can access
+    #[serde(rename = "e")]
+    pub existing: Vec<String>,
+    /// The rooms which do not exist, or which the user can not access anymore
+    #[serde(rename = "m")]
+    pub missing: Vec<String>,
+}
+
+/// A reply to the user with the rooms they can access, as they are listed at login
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomListReplyEvent {
+    /// The complete list of rooms the user can participate, unique and ordered
+    #[serde(rename = "rs")]
+    pub rooms: Vec<RoomDetail>,
+}
+
+/// A member of an encrypted room asks for its key, broadcast to the room
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomKeyRequestedBroadcastEvent {
+    /// The slug of the encrypted room
+    #[serde(rename = "r")]
+    pub room: String,
+    /// The id of the user asking for the key
+    #[serde(rename = "u")]
+    pub user_id: String,
+    /// The id of the session asking for the key
+    #[serde(rename = "s")]
+    pub session_id: String,
+    /// The base64 encoded X25519 public key to seal the room key for
+    #[serde(rename = "k")]
+    pub public_key: String,
+}
+
+/// A reply to the session which asked for the key of an encrypted room
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomKeySharedReplyEvent {
+    /// The slug of the encrypted room
+    #[serde(rename = "r")]
+    pub room: String,
+    /// The id of the user who has shared the key
+    #[serde(rename = "u")]
+    pub user_id: String,
+    /// The base64 encoded X25519 public key of the sharing session
+    #[serde(rename = "k")]
+    pub public_key: String,
+    /// The room key, sealed for the public key of the session which asked for it
+    #[serde(rename = "sk")]
+    pub sealed_key: String,
+}
+
 /// A reply to the user when they have joined a room
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct UserJoinedRoomReplyEvent {
@@ -56,6 +236,55 @@ pub struct UserJoinedRoomReplyEvent {
     /// The users currently in the room, unique and ordered
     #[serde(rename = "us")]
     pub users: Vec<String>,
+    /// The status texts of the users in the room who have one
+    #[serde(rename = "st", default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub status_texts: BTreeMap<String, String>,
+    /// True if the room is encrypted and no session in it holds its key, the joining session creates the key then
+    /// Otherwise the session asks the sessions holding the key for it
+    #[serde(rename = "ck", default, skip_serializing_if = "std::ops::Not::not")]
+    pub creates_room_key: bool,
+}
+
+/// A reply to the user when the room they tried to join has no free slots
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomFullReplyEvent {
+    /// The slug of the room the user tried to join
+    #[serde(rename = "r")]
+    pub room: String,
+    /// The maximum number of users the room can have
+    #[serde(rename = "c")]
+    pub capacity: usize,
+}
+
+/// A reply to the user waiting for a slot in a full room whenever their position in the queue changes
+/// The user is joined to the room automatically once a slot frees up
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomQueuePositionReplyEvent {
+    /// The slug of the room the user is waiting for
+    #[serde(rename = "r")]
+    pub room: String,
+    /// The position of the user in the queue, starting from 1
+    #[serde(rename = "p")]
+    pub position: usize,
+}
+
+/// How the content of a message is meant to be shown
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentType {
+    /// Text, as it has been typed
+    #[default]
+    Plain,
+    /// A snippet of code, shown verbatim in a code style, e.g. sent in a ``` fence
+    Code,
+    /// Something the user does, shown after their name, e.g. sent as `/me waves`
+    Action,
+}
+
+impl ContentType {
+    pub fn is_plain(&self) -> bool {
+        *self == ContentType::Plain
+    }
 }
 
 /// A user has sent a message to a room
@@ -64,12 +293,97 @@ pub struct UserMessageBroadcastEvent {
     /// The slug of the room the user has sent the message to
     #[serde(rename = "r")]
     pub room: String,
+    /// The id of the message, the same as the id of its entry in the history of the room
+    #[serde(rename = "i")]
+    pub id: String,
     /// The id of the user that has sent the message
     #[serde(rename = "u")]
     pub user_id: String,
+    /// The name the message is shown under instead of the user id, e.g. set by an alerting system posting through a webhook
+    #[serde(rename = "dn", default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
     /// The content of the message
     #[serde(rename = "c")]
     pub content: String,
+    /// How the content of the message is meant to be shown
+    #[serde(rename = "ty", default, skip_serializing_if = "ContentType::is_plain")]
+    pub content_type: ContentType,
+    /// The time the message was sent at, in milliseconds since the unix epoch, the same as the one of its history entry
+    /// 0 in recordings made before servers sent it
+    #[serde(rename = "ts", default)]
+    pub timestamp: u64,
+    /// The nonce the sending session has picked for the message, if any
+    #[serde(rename = "n", default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+}
+
+/// An announcement of the server to a room, e.g. a scheduled maintenance window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnnouncementBroadcastEvent {
+    /// The slug of the room the announcement is made in
+    #[serde(rename = "r")]
+    pub room: String,
+    /// The text of the announcement
+    #[serde(rename = "m")]
+    pub message: String,
+}
+
+/// A preview of a link in a message of a room, fetched by the server after the message was sent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinkPreviewBroadcastEvent {
+    /// The slug of the room of the message
+    #[serde(rename = "r")]
+    pub room: String,
+    /// The id of the message the link is in
+    #[serde(rename = "i")]
+    pub message_id: String,
+    /// The previewed link
+    #[serde(rename = "l")]
+    pub url: String,
+    /// The title of the linked page
+    #[serde(rename = "ti", default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// A short description of the linked page, as given by the page itself
+    #[serde(rename = "d", default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// What a [HistoricalMessage] records
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoricalMessageKind {
+    /// A message the user has sent to the room
+    Message,
+    /// The user has joined the room, only recorded by rooms which keep participation history
+    Joined,
+    /// The user has left the room, only recorded by rooms which keep participation history
+    Left,
+}
+
+/// An entry of the history of a chat room
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoricalMessage {
+    /// The id of the entry, unique within the room
+    #[serde(rename = "i")]
+    pub id: String,
+    /// The id of the user the entry is about
+    #[serde(rename = "u")]
+    pub user_id: String,
+    /// The name the message is shown under instead of the user id, if it has been posted with one
+    #[serde(rename = "dn", default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    /// The content of the message, empty for joins and leaves
+    #[serde(rename = "c")]
+    pub content: String,
+    /// How the content of the message is meant to be shown, plain for joins and leaves
+    #[serde(rename = "ty", default, skip_serializing_if = "ContentType::is_plain")]
+    pub content_type: ContentType,
+    /// The time the entry was recorded at, in milliseconds since the unix epoch
+    #[serde(rename = "ts")]
+    pub timestamp: u64,
+    /// What the entry records
+    #[serde(rename = "k")]
+    pub kind: HistoricalMessageKind,
 }
 
 /// A reply to the user chat history request
@@ -78,104 +392,1798 @@ pub struct HistoryResponseEvent {
     /// The slug of the room the user has sent the message to
     #[serde(rename = "r")]
     pub room: String,
-    /// The history of the chat room
+    /// The history of the chat room, from the oldest to the newest entry
     #[serde(rename = "h")]
-    pub history: Vec<(String, String)>,
+    pub history: Vec<HistoricalMessage>,
 }
 
+/// A part of the history of a room streamed to the user, the chunks are sent from the oldest to the newest entries
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(tag = "_et", rename_all = "snake_case")]
-/// Events that can be sent to the client
-/// Events maybe related to different users and rooms, the recipient is a single chat session
-pub enum Event {
-    LoginSuccessful(LoginSuccessfulReplyEvent),
-    RoomParticipation(RoomParticipationBroadcastEvent),
-    UserJoinedRoom(UserJoinedRoomReplyEvent),
-    UserMessage(UserMessageBroadcastEvent),
-    HistoryResponse(HistoryResponseEvent),
+pub struct HistoryChunkEvent {
+    /// The slug of the room
+    #[serde(rename = "r")]
+    pub room: String,
+    /// The entries of the chunk, from the oldest to the newest
+    #[serde(rename = "h")]
+    pub history: Vec<HistoricalMessage>,
+    /// The number of entries of the whole history, to show how much of it has arrived
+    #[serde(rename = "tc")]
+    pub total_count: usize,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Sent after the last chunk of the history of a room, even if the history is empty
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryDoneEvent {
+    /// The slug of the room
+    #[serde(rename = "r")]
+    pub room: String,
+}
 
-    // given an event enum, and an expect string, asserts that event is serialized / deserialized appropriately
-    fn assert_event_serialization(event: &Event, expected: &str) {
-        let serialized = serde_json::to_string(&event).unwrap();
-        assert_eq!(serialized, expected);
-        let deserialized: Event = serde_json::from_str(&serialized).unwrap();
-        assert_eq!(deserialized, *event);
-    }
+/// The reason why a message was not accepted by the server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "t", rename_all = "snake_case")]
+pub enum MessageRejectionReason {
+    /// The message has more characters than the server allows
+    TooLong {
+        #[serde(rename = "l")]
+        length: usize,
+        #[serde(rename = "ml")]
+        max_length: usize,
+    },
+    /// The message looked like a server command but could not be parsed
+    InvalidCommand {
+        #[serde(rename = "m")]
+        message: String,
+    },
+    /// The user is not allowed to send messages to the room, e.g. a read-only bot
+    NotPermitted,
+    /// The room is encrypted end-to-end but the message was sent in plain text
+    NotEncrypted,
+    /// The room has been archived, it is read-only until it is reopened
+    Archived,
+    /// The user has sent more messages than they may in a while, e.g. as a guest
+    RateLimited {
+        /// How long to wait before sending the next message, in milliseconds
+        #[serde(rename = "ra")]
+        retry_after: u64,
+    },
+    /// A message filter of the room, e.g. a moderation plugin of the server, has blocked the message
+    Blocked,
+    /// The message asked for a reminder, but the user already has as many pending reminders as the server keeps
+    TooManyReminders {
+        #[serde(rename = "mr")]
+        max_reminders: usize,
+    },
+}
 
-    #[test]
-    fn test_login_successful_event() {
-        let event = Event::LoginSuccessful(LoginSuccessfulReplyEvent {
-            session_id: "session-id-1".to_string(),
-            user_id: "user-id-1".to_string(),
-            rooms: vec![RoomDetail {
-                name: "room-1".to_string(),
-                description: "some description".to_string(),
-            }],
-        });
+/// A reply to the user when the message they have sent is not delivered to the room
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageRejectedReplyEvent {
+    /// The slug of the room the message was sent to
+    #[serde(rename = "r")]
+    pub room: String,
+    /// Why the message was rejected
+    #[serde(rename = "rs")]
+    pub reason: MessageRejectionReason,
+    /// The nonce the session has picked for the message, if any
+    #[serde(rename = "n", default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+}
 
-        assert_event_serialization(
-            &event,
-            r#"{"_et":"login_successful","s":"session-id-1","u":"user-id-1","rs":[{"n":"room-1","d":"some description"}]}"#,
-        );
-    }
+/// The reason why a user id, bot name or room name was not accepted by the server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "t", rename_all = "snake_case")]
+pub enum NameRejectionReason {
+    /// The name has fewer characters than the server requires
+    TooShort {
+        #[serde(rename = "ml")]
+        min_length: usize,
+    },
+    /// The name has more characters than the server allows
+    TooLong {
+        #[serde(rename = "ml")]
+        max_length: usize,
+    },
+    /// The name contains a character which is not allowed, e.g. whitespace or an invisible character
+    InvalidCharacter {
+        #[serde(rename = "ch")]
+        character: char,
+    },
+    /// The name is kept by the server, e.g. `admin`, or looks like such a name
+    Reserved,
+    /// The name can be mistaken for a name which is already taken, e.g. `admin` spelled with a cyrillic `а`
+    Confusable {
+        #[serde(rename = "n")]
+        name: String,
+    },
+    /// The name is in use by a user who is connected right now
+    Taken,
+}
 
-    #[test]
-    fn test_room_participation_join_event() {
-        let event = Event::RoomParticipation(RoomParticipationBroadcastEvent {
-            room: "test".to_string(),
-            user_id: "test".to_string(),
-            status: RoomParticipationStatus::Joined,
-        });
+/// A reply to the user when they could not be logged in, another login may be sent on the same connection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoginRejectedReplyEvent {
+    /// A human readable explanation of the rejection
+    #[serde(rename = "rs")]
+    pub reason: String,
+    /// Set if the login was rejected because the user id is not a valid name
+    #[serde(rename = "nr", default, skip_serializing_if = "Option::is_none")]
+    pub name_rejection: Option<NameRejectionReason>,
+}
 
-        assert_event_serialization(
-            &event,
-            r#"{"_et":"room_participation","r":"test","u":"test","s":"joined"}"#,
-        );
-    }
+/// A reply to the user when a name given in a command is not accepted, e.g. the name of a new bot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NameRejectedReplyEvent {
+    /// The name of the refused command, e.g. `issue_bot_token`
+    #[serde(rename = "c")]
+    pub command: String,
+    /// The name which was not accepted
+    #[serde(rename = "n")]
+    pub name: String,
+    #[serde(rename = "rs")]
+    pub reason: NameRejectionReason,
+}
 
-    #[test]
-    fn test_room_participation_leave_event() {
-        let event = Event::RoomParticipation(RoomParticipationBroadcastEvent {
-            room: "test".to_string(),
-            user_id: "test".to_string(),
-            status: RoomParticipationStatus::Left,
-        });
+/// Sent to the other sessions of a user when they connect from another device
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceConnectedReplyEvent {
+    /// The newly connected session
+    #[serde(rename = "sd")]
+    pub session: SessionDetail,
+    /// The number of connected sessions of the user, including the new one
+    #[serde(rename = "sc")]
+    pub session_count: usize,
+}
 
-        assert_event_serialization(
-            &event,
-            r#"{"_et":"room_participation","r":"test","u":"test","s":"left"}"#,
-        );
-    }
+/// Sent to the other sessions of a user when one of their devices disconnects
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceDisconnectedReplyEvent {
+    /// The id of the disconnected session
+    #[serde(rename = "s")]
+    pub session_id: String,
+    /// The number of sessions of the user which are still connected
+    #[serde(rename = "sc")]
+    pub session_count: usize,
+}
 
-    #[test]
-    fn test_user_joined_room_event() {
-        let event = Event::UserJoinedRoom(UserJoinedRoomReplyEvent {
-            room: "test".to_string(),
-            users: vec!["test".to_string()],
-        });
+/// A reply to the user with all of their connected sessions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionListReplyEvent {
+    /// The connected sessions of the user, including the requesting one
+    #[serde(rename = "ss")]
+    pub sessions: Vec<SessionDetail>,
+}
 
-        assert_event_serialization(
-            &event,
-            r#"{"_et":"user_joined_room","r":"test","us":["test"]}"#,
-        );
-    }
+/// The last event a session receives before the server closes it, e.g. when disconnected from another device
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionTerminatedReplyEvent {
+    /// A human readable explanation of why the session has been closed
+    #[serde(rename = "rs")]
+    pub reason: String,
+    /// Why the session has been closed, for clients deciding whether to connect again, None for older servers
+    #[serde(rename = "c", default, skip_serializing_if = "Option::is_none")]
+    pub cause: Option<SessionTerminationCause>,
+}
 
-    #[test]
-    fn test_user_message_event() {
-        let event = Event::UserMessage(UserMessageBroadcastEvent {
-            room: "test".to_string(),
-            user_id: "test".to_string(),
-            content: "test".to_string(),
-        });
+/// Why the server has closed a session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "t", rename_all = "snake_case")]
+pub enum SessionTerminationCause {
+    /// The user has disconnected the session from another one of theirs
+    DisconnectedByUser,
+    /// An admin has deleted the data of the user
+    UserPurged,
+    /// The session could not keep up with the events of a room
+    Lagged,
+    /// The token the session has logged in with has expired without being refreshed, a new one is needed
+    TokenExpired,
+}
 
-        assert_event_serialization(
-            &event,
-            r#"{"_et":"user_message","r":"test","u":"test","c":"test"}"#,
-        );
+/// A new token for the bot, sent to its sessions ahead of the expiry of its current token
+///
+/// The client should keep it in place of the token it has logged in with, which stops working once it expires.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenRefreshedReplyEvent {
+    /// The token to log in with from now on
+    #[serde(rename = "t")]
+    pub token: String,
+    /// When the new token expires, in milliseconds since the unix epoch
+    #[serde(rename = "ea")]
+    pub expires_at: u64,
+}
+
+/// The last event a session receives when the user has logged in again and the server only keeps the newest session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionReplacedReplyEvent {
+    /// The session which has taken over
+    #[serde(rename = "s")]
+    pub session: SessionDetail,
+}
+
+/// The last event a session receives when the server hands it to another one, e.g. while it is being migrated
+///
+/// The client should connect to the given address, log in again and join the rooms it was in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RedirectReplyEvent {
+    /// The address of the server to connect to instead, e.g. `chat-2.example.com:8080`
+    #[serde(rename = "a")]
+    pub addr: String,
+}
+
+/// The user has read a room up to the given time, sent to every session of the user
+/// so that the unread state of the room agrees across their devices
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReadStateSyncedReplyEvent {
+    /// The slug of the room that has been read
+    #[serde(rename = "r")]
+    pub room: String,
+    /// The time the room has been read at, in milliseconds since the unix epoch
+    #[serde(rename = "lr")]
+    pub last_read_at: u64,
+}
+
+/// A private greeting sent to the user the first time they join a room
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomWelcomeReplyEvent {
+    /// The slug of the room the user has joined
+    #[serde(rename = "r")]
+    pub room: String,
+    /// The welcome message configured for the room
+    #[serde(rename = "m")]
+    pub message: String,
+}
+
+/// A reply to a room owner when the welcome message of the room has been changed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WelcomeMessageUpdatedReplyEvent {
+    /// The slug of the room
+    #[serde(rename = "r")]
+    pub room: String,
+    /// The new welcome message, not set if it has been removed
+    #[serde(rename = "m")]
+    pub message: Option<String>,
+}
+
+/// A reply to the user when a command they have sent is refused by the server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandRejectedReplyEvent {
+    /// The name of the refused command, e.g. `set_welcome_message`
+    #[serde(rename = "c")]
+    pub command: String,
+    /// A human readable explanation of the refusal
+    #[serde(rename = "rs")]
+    pub reason: String,
+}
+
+/// Sent to every session of a user when the set of rooms they can access changes,
+/// e.g. after they have been added to or removed from a group
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccessibleRoomsChangedReplyEvent {
+    /// The complete list of rooms the user can participate, unique and ordered
+    #[serde(rename = "rs")]
+    pub rooms: Vec<RoomDetail>,
+}
+
+/// A reply to an admin when the members of a group have been changed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupMembershipChangedReplyEvent {
+    /// The name of the group
+    #[serde(rename = "g")]
+    pub group: String,
+    /// The id of the user whose membership has changed
+    #[serde(rename = "u")]
+    pub user_id: String,
+    /// Whether the user is now a member of the group
+    #[serde(rename = "m")]
+    pub is_member: bool,
+}
+
+/// A reply to an admin with a newly issued bot token, the token is not retrievable afterwards
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BotTokenIssuedReplyEvent {
+    /// The name of the bot
+    #[serde(rename = "b")]
+    pub bot_name: String,
+    /// The user id the bot acts as
+    #[serde(rename = "u")]
+    pub user_id: String,
+    /// The token the bot presents when connecting
+    #[serde(rename = "t")]
+    pub token: String,
+    /// When the token expires unless it is refreshed, in milliseconds since the unix epoch, None if it never expires
+    #[serde(rename = "ea", default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+}
+
+/// A reply to an admin when the token of a bot has been revoked
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BotTokenRevokedReplyEvent {
+    /// The name of the bot
+    #[serde(rename = "b")]
+    pub bot_name: String,
+    /// Whether the bot had a token to revoke
+    #[serde(rename = "rv")]
+    pub revoked: bool,
+}
+
+/// A reply to the user when a reminder has been stored by the server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReminderSetReplyEvent {
+    /// The slug of the room the reminder was requested in
+    #[serde(rename = "r")]
+    pub room: String,
+    /// When the reminder is due, in milliseconds since the unix epoch
+    #[serde(rename = "d")]
+    pub due_at: u64,
+}
+
+/// A reminder the user has requested earlier which is now due
+/// Delivered to every session of the user, or as soon as the user connects if they were offline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReminderDueReplyEvent {
+    /// The slug of the room the reminder was requested in
+    #[serde(rename = "r")]
+    pub room: String,
+    /// The text the user asked to be reminded of
+    #[serde(rename = "c")]
+    pub content: String,
+}
+
+/// A message reported by a user, waiting in the moderation queue until a moderator resolves it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageReport {
+    /// The id of the report
+    #[serde(rename = "i")]
+    pub id: String,
+    /// The slug of the room the message was sent to
+    #[serde(rename = "r")]
+    pub room: String,
+    /// The id of the reported message, empty for reports filed before the id was kept
+    #[serde(rename = "m", default)]
+    pub message_id: String,
+    /// The id of the user who has sent the message, as recorded in the history of the room
+    #[serde(rename = "u")]
+    pub user_id: String,
+    /// The content of the message, as recorded in the history of the room
+    #[serde(rename = "c")]
+    pub content: String,
+    /// Why the message is reported
+    #[serde(rename = "rs")]
+    pub reason: String,
+    /// The id of the user who has reported the message
+    #[serde(rename = "rb")]
+    pub reported_by: String,
+    /// When the message has been reported, in milliseconds since the unix epoch
+    #[serde(rename = "ts")]
+    pub reported_at: u64,
+}
+
+/// A reply to the user when their report has been put in the moderation queue
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportFiledReplyEvent {
+    /// The id of the report
+    #[serde(rename = "i")]
+    pub report_id: String,
+    /// The slug of the room the reported message was sent to
+    #[serde(rename = "r")]
+    pub room: String,
+}
+
+/// Sent to every session of the moderators of a room when a message of the room has been reported
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageReportedEvent {
+    /// The new report
+    #[serde(rename = "rp")]
+    pub report: MessageReport,
+}
+
+/// A reply to a moderator with the open reports of the rooms they moderate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportListReplyEvent {
+    /// The open reports, from the oldest to the newest
+    #[serde(rename = "rps")]
+    pub reports: Vec<MessageReport>,
+}
+
+/// A reply to a moderator when a report has been resolved
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportResolvedReplyEvent {
+    /// The id of the report
+    #[serde(rename = "i")]
+    pub report_id: String,
+    /// Whether the report was still open
+    #[serde(rename = "rv")]
+    pub resolved: bool,
+}
+
+/// Sent to the user asking to join a room which requires approval, and to every session of its moderators
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JoinRequestPendingEvent {
+    /// The room the user asked to join
+    #[serde(rename = "r")]
+    pub room: String,
+    /// The user who asked to join
+    #[serde(rename = "u")]
+    pub user_id: String,
+}
+
+/// Sent to every session of the user who asked to join a room and of its moderators once a moderator has decided
+/// An approved user can join the room from then on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JoinRequestDecidedEvent {
+    /// The room the user asked to join
+    #[serde(rename = "r")]
+    pub room: String,
+    /// The user who asked to join
+    #[serde(rename = "u")]
+    pub user_id: String,
+    /// Whether the user has been let in
+    #[serde(rename = "a")]
+    pub approved: bool,
+    /// The moderator who decided
+    #[serde(rename = "db")]
+    pub decided_by: String,
+}
+
+/// What happens to a session which falls so far behind the events of a room that some of them are dropped
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LagPolicy {
+    /// The session is told how many events it has missed and is sent the history of the room to catch up
+    #[default]
+    Resync,
+    /// The session is closed, the client has to reconnect
+    Disconnect,
+}
+
+/// Sent to a session which has fallen behind the events of a room, followed by the history of the room
+/// Messages older than the history are lost to the session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomResyncedReplyEvent {
+    /// The slug of the room
+    #[serde(rename = "r")]
+    pub room: String,
+    /// The number of events the session has missed
+    #[serde(rename = "me")]
+    pub missed_events: u64,
+}
+
+/// How the broadcast channel of a room has kept up since the server started
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomBroadcastMetrics {
+    /// The slug of the room
+    #[serde(rename = "r")]
+    pub room: String,
+    /// The number of events a session may fall behind before it misses some
+    #[serde(rename = "c")]
+    pub capacity: usize,
+    #[serde(rename = "lp")]
+    pub lag_policy: LagPolicy,
+    /// The number of times a session has fallen behind
+    #[serde(rename = "lc")]
+    pub lag_count: u64,
+    /// The number of events missed by the sessions which have fallen behind, in total
+    #[serde(rename = "de")]
+    pub dropped_events: u64,
+    /// The number of sessions closed for falling behind
+    #[serde(rename = "ds")]
+    pub disconnected_sessions: u64,
+    /// The number of sessions which have stopped taking part in the room without leaving it,
+    /// they are still listed in the room
+    #[serde(rename = "lh", default)]
+    pub leaked_handles: u64,
+}
+
+/// A reply to an admin asking for the broadcast metrics, with every room
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BroadcastMetricsReplyEvent {
+    #[serde(rename = "rs")]
+    pub rooms: Vec<RoomBroadcastMetrics>,
+}
+
+/// The activity of a room since the server started
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomStats {
+    /// The slug of the room
+    #[serde(rename = "r")]
+    pub room: String,
+    /// The number of messages sent in each of the last 24 hours, the current hour last
+    #[serde(rename = "mph")]
+    pub messages_per_hour: Vec<u32>,
+    /// The number of users who have sent a message in the last 24 hours
+    #[serde(rename = "au")]
+    pub active_users: usize,
+    /// The number of users in the room right now
+    #[serde(rename = "mc")]
+    pub member_count: usize,
+    /// The highest number of users who have been in the room at the same time
+    #[serde(rename = "pc")]
+    pub peak_concurrency: usize,
+}
+
+/// A reply to a user asking for the activity of a room, or to an admin asking for every room
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomStatsReplyEvent {
+    #[serde(rename = "rs")]
+    pub rooms: Vec<RoomStats>,
+}
+
+/// A broadcast to the users who can access a room when it has been archived
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomArchivedBroadcastEvent {
+    /// The slug of the room
+    #[serde(rename = "r")]
+    pub room: String,
+}
+
+/// A broadcast to the users who can access a room when it has been reopened after being archived
+/// Also a reply to an owner calling off the scheduled archival of a room
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomReopenedBroadcastEvent {
+    /// The slug of the room
+    #[serde(rename = "r")]
+    pub room: String,
+}
+
+/// A broadcast to the users who can access a room when its description or capacity has been changed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomMetadataUpdatedBroadcastEvent {
+    /// The slug of the room
+    #[serde(rename = "r")]
+    pub room: String,
+    /// The description of the room
+    #[serde(rename = "d")]
+    pub description: String,
+    /// The maximum number of unique users in the room, unlimited if not set
+    #[serde(rename = "c", default, skip_serializing_if = "Option::is_none")]
+    pub capacity: Option<usize>,
+}
+
+/// A reply to an owner when the archival of a room has been scheduled
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomArchivalScheduledReplyEvent {
+    /// The slug of the room
+    #[serde(rename = "r")]
+    pub room: String,
+    /// When the room is archived, in milliseconds since the unix epoch
+    #[serde(rename = "t")]
+    pub archive_at: u64,
+}
+
+/// A broadcast to the users who can access a room when messages of a user have been removed from its history
+/// Clients are expected to drop the messages from what they show as well
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessagesRedactedBroadcastEvent {
+    /// The slug of the room
+    #[serde(rename = "r")]
+    pub room: String,
+    /// The id of the user whose messages have been removed
+    #[serde(rename = "u")]
+    pub user_id: String,
+    /// The ids of the removed history entries
+    #[serde(rename = "is")]
+    pub message_ids: Vec<String>,
+}
+
+/// A reply to an admin when everything the server kept about a user has been deleted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserPurgedReplyEvent {
+    /// The id of the purged user
+    #[serde(rename = "u")]
+    pub user_id: String,
+    /// The number of history entries removed across all rooms
+    #[serde(rename = "rc")]
+    pub redacted_count: usize,
+}
+
+/// A command the server has received from a user, without its content
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandHistoryEntry {
+    /// The name of the command as it is sent over the wire, e.g. `join_room`
+    #[serde(rename = "c")]
+    pub command: String,
+    /// The session the command was sent from
+    #[serde(rename = "s")]
+    pub session_id: String,
+    /// When the server received the command as a unix timestamp in milliseconds
+    #[serde(rename = "t")]
+    pub received_at: u64,
+    /// Whether the command was rejected before it was handled, e.g. for lacking the permission
+    #[serde(rename = "rj", default, skip_serializing_if = "std::ops::Not::not")]
+    pub rejected: bool,
+}
+
+/// A reply to an admin asking for the commands a user has recently sent, the oldest first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandHistoryReplyEvent {
+    /// The id of the user
+    #[serde(rename = "u")]
+    pub user_id: String,
+    #[serde(rename = "es")]
+    pub entries: Vec<CommandHistoryEntry>,
+}
+
+/// A reply to a moderator with a newly created invite code to a private room
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InviteCodeCreatedReplyEvent {
+    /// The slug of the room the code invites to
+    #[serde(rename = "r")]
+    pub room: String,
+    /// The code to hand out, redeemed with the join with code command
+    #[serde(rename = "c")]
+    pub code: String,
+    /// When the code can not be redeemed anymore, in milliseconds since the unix epoch
+    #[serde(rename = "ea")]
+    pub expires_at: u64,
+    /// How many users can redeem the code
+    #[serde(rename = "mu")]
+    pub max_uses: u32,
+}
+
+/// A reply to a ping of the session, the round trip time is the time passed since the ping was sent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PongReplyEvent {
+    /// The time the ping was sent at, as given by the session
+    #[serde(rename = "t")]
+    pub sent_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "_et", rename_all = "snake_case")]
+/// Events that can be sent to the client
+/// Events maybe related to different users and rooms, the recipient is a single chat session
+pub enum Event {
+    LoginSuccessful(LoginSuccessfulReplyEvent),
+    LoginRejected(LoginRejectedReplyEvent),
+    RoomParticipation(RoomParticipationBroadcastEvent),
+    UserJoinedRoom(UserJoinedRoomReplyEvent),
+    RoomFull(RoomFullReplyEvent),
+    RoomQueuePosition(RoomQueuePositionReplyEvent),
+    UserMessage(UserMessageBroadcastEvent),
+    Announcement(AnnouncementBroadcastEvent),
+    LinkPreview(LinkPreviewBroadcastEvent),
+    HistoryResponse(HistoryResponseEvent),
+    HistoryChunk(HistoryChunkEvent),
+    HistoryDone(HistoryDoneEvent),
+    MessageRejected(MessageRejectedReplyEvent),
+    ReminderSet(ReminderSetReplyEvent),
+    ReminderDue(ReminderDueReplyEvent),
+    RoomWelcome(RoomWelcomeReplyEvent),
+    WelcomeMessageUpdated(WelcomeMessageUpdatedReplyEvent),
+    CommandRejected(CommandRejectedReplyEvent),
+    AccessibleRoomsChanged(AccessibleRoomsChangedReplyEvent),
+    GroupMembershipChanged(GroupMembershipChangedReplyEvent),
+    BotTokenIssued(BotTokenIssuedReplyEvent),
+    BotTokenRevoked(BotTokenRevokedReplyEvent),
+    DeviceConnected(DeviceConnectedReplyEvent),
+    DeviceDisconnected(DeviceDisconnectedReplyEvent),
+    SessionList(SessionListReplyEvent),
+    SessionTerminated(SessionTerminatedReplyEvent),
+    TokenRefreshed(TokenRefreshedReplyEvent),
+    SessionReplaced(SessionReplacedReplyEvent),
+    Redirect(RedirectReplyEvent),
+    ReadStateSynced(ReadStateSyncedReplyEvent),
+    PresenceChanged(PresenceChangedBroadcastEvent),
+    StatusTextChanged(StatusTextChangedBroadcastEvent),
+    UserInfo(UserInfoReplyEvent),
+    UserSearchResults(UserSearchResultsReplyEvent),
+    RoomInfo(RoomInfoReplyEvent),
+    RoomsChecked(RoomsCheckedReplyEvent),
+    RoomList(RoomListReplyEvent),
+    RoomKeyRequested(RoomKeyRequestedBroadcastEvent),
+    RoomKeyShared(RoomKeySharedReplyEvent),
+    ReportFiled(ReportFiledReplyEvent),
+    MessageReported(MessageReportedEvent),
+    ReportList(ReportListReplyEvent),
+    ReportResolved(ReportResolvedReplyEvent),
+    JoinRequestPending(JoinRequestPendingEvent),
+    JoinRequestDecided(JoinRequestDecidedEvent),
+    NameRejected(NameRejectedReplyEvent),
+    RoomResynced(RoomResyncedReplyEvent),
+    BroadcastMetrics(BroadcastMetricsReplyEvent),
+    RoomStats(RoomStatsReplyEvent),
+    RoomArchived(RoomArchivedBroadcastEvent),
+    RoomReopened(RoomReopenedBroadcastEvent),
+    RoomArchivalScheduled(RoomArchivalScheduledReplyEvent),
+    RoomMetadataUpdated(RoomMetadataUpdatedBroadcastEvent),
+    MessagesRedacted(MessagesRedactedBroadcastEvent),
+    UserPurged(UserPurgedReplyEvent),
+    CommandHistory(CommandHistoryReplyEvent),
+    InviteCodeCreated(InviteCodeCreatedReplyEvent),
+    Pong(PongReplyEvent),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // given an event enum, and an expect string, asserts that event is serialized / deserialized appropriately
+    fn assert_event_serialization(event: &Event, expected: &str) {
+        let serialized = serde_json::to_string(&event).unwrap();
+        assert_eq!(serialized, expected);
+        let deserialized: Event = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, *event);
+    }
+
+    #[test]
+    fn test_login_successful_event() {
+        let event = Event::LoginSuccessful(LoginSuccessfulReplyEvent {
+            session_id: "session-id-1".to_string(),
+            user_id: "user-id-1".to_string(),
+            rooms: vec![RoomDetail {
+                name: "room-1".to_string(),
+                description: "some description".to_string(),
+                encrypted: false,
+                archived: false,
+                category: None,
+            }],
+            max_message_length: 512,
+            session_count: 2,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"login_successful","s":"session-id-1","u":"user-id-1","rs":[{"n":"room-1","d":"some description"}],"ml":512,"sc":2}"#,
+        );
+    }
+
+    #[test]
+    fn test_room_participation_join_event() {
+        let event = Event::RoomParticipation(RoomParticipationBroadcastEvent {
+            room: "test".to_string(),
+            user_id: "test".to_string(),
+            status: RoomParticipationStatus::Joined,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"room_participation","r":"test","u":"test","s":"joined"}"#,
+        );
+    }
+
+    #[test]
+    fn test_room_participation_leave_event() {
+        let event = Event::RoomParticipation(RoomParticipationBroadcastEvent {
+            room: "test".to_string(),
+            user_id: "test".to_string(),
+            status: RoomParticipationStatus::Left,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"room_participation","r":"test","u":"test","s":"left"}"#,
+        );
+    }
+
+    #[test]
+    fn test_user_joined_room_event() {
+        let event = Event::UserJoinedRoom(UserJoinedRoomReplyEvent {
+            room: "test".to_string(),
+            users: vec!["test".to_string()],
+            status_texts: BTreeMap::new(),
+            creates_room_key: false,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"user_joined_room","r":"test","us":["test"]}"#,
+        );
+    }
+
+    #[test]
+    fn test_user_joined_room_event_creating_the_room_key() {
+        let event = Event::UserJoinedRoom(UserJoinedRoomReplyEvent {
+            room: "test".to_string(),
+            users: vec!["test".to_string()],
+            status_texts: BTreeMap::new(),
+            creates_room_key: true,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"user_joined_room","r":"test","us":["test"],"ck":true}"#,
+        );
+    }
+
+    #[test]
+    fn test_user_joined_room_event_with_status_texts() {
+        let event = Event::UserJoinedRoom(UserJoinedRoomReplyEvent {
+            room: "test".to_string(),
+            users: vec!["alice".to_string(), "bob".to_string()],
+            status_texts: BTreeMap::from([("bob".to_string(), "in a meeting".to_string())]),
+            creates_room_key: false,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"user_joined_room","r":"test","us":["alice","bob"],"st":{"bob":"in a meeting"}}"#,
+        );
+    }
+
+    #[test]
+    fn test_room_full_event() {
+        let event = Event::RoomFull(RoomFullReplyEvent {
+            room: "test".to_string(),
+            capacity: 10,
+        });
+
+        assert_event_serialization(&event, r#"{"_et":"room_full","r":"test","c":10}"#);
+    }
+
+    #[test]
+    fn test_room_queue_position_event() {
+        let event = Event::RoomQueuePosition(RoomQueuePositionReplyEvent {
+            room: "test".to_string(),
+            position: 2,
+        });
+
+        assert_event_serialization(&event, r#"{"_et":"room_queue_position","r":"test","p":2}"#);
+    }
+
+    #[test]
+    fn test_user_message_event() {
+        let event = Event::UserMessage(UserMessageBroadcastEvent {
+            room: "test".to_string(),
+            id: "1".to_string(),
+            user_id: "test".to_string(),
+            display_name: None,
+            content: "test".to_string(),
+            content_type: ContentType::Plain,
+            timestamp: 1735689600000,
+            nonce: None,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"user_message","r":"test","i":"1","u":"test","c":"test","ts":1735689600000}"#,
+        );
+    }
+
+    #[test]
+    fn test_user_message_event_without_timestamp() {
+        let event: Event = serde_json::from_str(
+            r#"{"_et":"user_message","r":"test","i":"1","u":"test","c":"test"}"#,
+        )
+        .unwrap();
+
+        let Event::UserMessage(event) = event else {
+            panic!("the event should have been a message");
+        };
+        assert_eq!(event.timestamp, 0);
+    }
+
+    #[test]
+    fn test_user_message_event_with_display_name() {
+        let event = Event::UserMessage(UserMessageBroadcastEvent {
+            room: "test".to_string(),
+            id: "1".to_string(),
+            user_id: "bot:test".to_string(),
+            display_name: Some("Test".to_string()),
+            content: "test".to_string(),
+            content_type: ContentType::Plain,
+            timestamp: 1735689600000,
+            nonce: None,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"user_message","r":"test","i":"1","u":"bot:test","dn":"Test","c":"test","ts":1735689600000}"#,
+        );
+    }
+
+    #[test]
+    fn test_user_message_event_with_content_type() {
+        let event = Event::UserMessage(UserMessageBroadcastEvent {
+            room: "test".to_string(),
+            id: "1".to_string(),
+            user_id: "test".to_string(),
+            display_name: None,
+            content: "cargo build".to_string(),
+            content_type: ContentType::Code,
+            timestamp: 1735689600000,
+            nonce: None,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"user_message","r":"test","i":"1","u":"test","c":"cargo build","ty":"code","ts":1735689600000}"#,
+        );
+    }
+
+    #[test]
+    fn test_user_message_event_with_nonce() {
+        let event = Event::UserMessage(UserMessageBroadcastEvent {
+            room: "test".to_string(),
+            id: "1".to_string(),
+            user_id: "test".to_string(),
+            display_name: None,
+            content: "test".to_string(),
+            content_type: ContentType::Plain,
+            timestamp: 1735689600000,
+            nonce: Some("abc".to_string()),
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"user_message","r":"test","i":"1","u":"test","c":"test","ts":1735689600000,"n":"abc"}"#,
+        );
+    }
+
+    #[test]
+    fn test_announcement_event() {
+        let event = Event::Announcement(AnnouncementBroadcastEvent {
+            room: "test".to_string(),
+            message: "test".to_string(),
+        });
+
+        assert_event_serialization(&event, r#"{"_et":"announcement","r":"test","m":"test"}"#);
+    }
+
+    #[test]
+    fn test_link_preview_event() {
+        let event = Event::LinkPreview(LinkPreviewBroadcastEvent {
+            room: "test".to_string(),
+            message_id: "1".to_string(),
+            url: "https://example.com".to_string(),
+            title: Some("Example".to_string()),
+            description: None,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"link_preview","r":"test","i":"1","l":"https://example.com","ti":"Example"}"#,
+        );
+    }
+
+    #[test]
+    fn test_message_rejected_event() {
+        let event = Event::MessageRejected(MessageRejectedReplyEvent {
+            room: "test".to_string(),
+            reason: MessageRejectionReason::TooLong {
+                length: 600,
+                max_length: 512,
+            },
+            nonce: None,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"message_rejected","r":"test","rs":{"t":"too_long","l":600,"ml":512}}"#,
+        );
+    }
+
+    #[test]
+    fn test_invalid_command_rejected_event() {
+        let event = Event::MessageRejected(MessageRejectedReplyEvent {
+            room: "test".to_string(),
+            reason: MessageRejectionReason::InvalidCommand {
+                message: "usage".to_string(),
+            },
+            nonce: None,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"message_rejected","r":"test","rs":{"t":"invalid_command","m":"usage"}}"#,
+        );
+    }
+
+    #[test]
+    fn test_reminder_set_event() {
+        let event = Event::ReminderSet(ReminderSetReplyEvent {
+            room: "test".to_string(),
+            due_at: 1700000000000,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"reminder_set","r":"test","d":1700000000000}"#,
+        );
+    }
+
+    #[test]
+    fn test_reminder_due_event() {
+        let event = Event::ReminderDue(ReminderDueReplyEvent {
+            room: "test".to_string(),
+            content: "test".to_string(),
+        });
+
+        assert_event_serialization(&event, r#"{"_et":"reminder_due","r":"test","c":"test"}"#);
+    }
+
+    #[test]
+    fn test_room_welcome_event() {
+        let event = Event::RoomWelcome(RoomWelcomeReplyEvent {
+            room: "test".to_string(),
+            message: "test".to_string(),
+        });
+
+        assert_event_serialization(&event, r#"{"_et":"room_welcome","r":"test","m":"test"}"#);
+    }
+
+    #[test]
+    fn test_welcome_message_updated_event() {
+        let event = Event::WelcomeMessageUpdated(WelcomeMessageUpdatedReplyEvent {
+            room: "test".to_string(),
+            message: None,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"welcome_message_updated","r":"test","m":null}"#,
+        );
+    }
+
+    #[test]
+    fn test_command_rejected_event() {
+        let event = Event::CommandRejected(CommandRejectedReplyEvent {
+            command: "test".to_string(),
+            reason: "test".to_string(),
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"command_rejected","c":"test","rs":"test"}"#,
+        );
+    }
+
+    #[test]
+    fn test_accessible_rooms_changed_event() {
+        let event = Event::AccessibleRoomsChanged(AccessibleRoomsChangedReplyEvent {
+            rooms: vec![RoomDetail {
+                name: "test".to_string(),
+                description: "test".to_string(),
+                encrypted: false,
+                archived: true,
+                category: Some("test".to_string()),
+            }],
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"accessible_rooms_changed","rs":[{"n":"test","d":"test","a":true,"c":"test"}]}"#,
+        );
+    }
+
+    #[test]
+    fn test_group_membership_changed_event() {
+        let event = Event::GroupMembershipChanged(GroupMembershipChangedReplyEvent {
+            group: "test".to_string(),
+            user_id: "test".to_string(),
+            is_member: true,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"group_membership_changed","g":"test","u":"test","m":true}"#,
+        );
+    }
+
+    #[test]
+    fn test_login_rejected_event() {
+        let event = Event::LoginRejected(LoginRejectedReplyEvent {
+            reason: "test".to_string(),
+            name_rejection: None,
+        });
+
+        assert_event_serialization(&event, r#"{"_et":"login_rejected","rs":"test"}"#);
+    }
+
+    #[test]
+    fn test_login_rejected_for_name_event() {
+        let event = Event::LoginRejected(LoginRejectedReplyEvent {
+            reason: "test".to_string(),
+            name_rejection: Some(NameRejectionReason::TooLong { max_length: 64 }),
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"login_rejected","rs":"test","nr":{"t":"too_long","ml":64}}"#,
+        );
+
+        let event = Event::LoginRejected(LoginRejectedReplyEvent {
+            reason: "test".to_string(),
+            name_rejection: Some(NameRejectionReason::Taken),
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"login_rejected","rs":"test","nr":{"t":"taken"}}"#,
+        );
+    }
+
+    #[test]
+    fn test_name_rejected_event() {
+        let event = Event::NameRejected(NameRejectedReplyEvent {
+            command: "test".to_string(),
+            name: "аdmin".to_string(),
+            reason: NameRejectionReason::Confusable {
+                name: "admin".to_string(),
+            },
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"name_rejected","c":"test","n":"аdmin","rs":{"t":"confusable","n":"admin"}}"#,
+        );
+
+        let event = Event::NameRejected(NameRejectedReplyEvent {
+            command: "test".to_string(),
+            name: "a b".to_string(),
+            reason: NameRejectionReason::InvalidCharacter { character: ' ' },
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"name_rejected","c":"test","n":"a b","rs":{"t":"invalid_character","ch":" "}}"#,
+        );
+    }
+
+    #[test]
+    fn test_not_permitted_rejected_event() {
+        let event = Event::MessageRejected(MessageRejectedReplyEvent {
+            room: "test".to_string(),
+            reason: MessageRejectionReason::NotPermitted,
+            nonce: None,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"message_rejected","r":"test","rs":{"t":"not_permitted"}}"#,
+        );
+    }
+
+    #[test]
+    fn test_bot_token_issued_event() {
+        let event = Event::BotTokenIssued(BotTokenIssuedReplyEvent {
+            bot_name: "test".to_string(),
+            user_id: "bot:test".to_string(),
+            token: "test".to_string(),
+            expires_at: Some(1),
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"bot_token_issued","b":"test","u":"bot:test","t":"test","ea":1}"#,
+        );
+    }
+
+    #[test]
+    fn test_bot_token_revoked_event() {
+        let event = Event::BotTokenRevoked(BotTokenRevokedReplyEvent {
+            bot_name: "test".to_string(),
+            revoked: true,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"bot_token_revoked","b":"test","rv":true}"#,
+        );
+    }
+
+    #[test]
+    fn test_device_connected_event() {
+        let event = Event::DeviceConnected(DeviceConnectedReplyEvent {
+            session: SessionDetail {
+                session_id: "test".to_string(),
+                address: "127.0.0.1:5000".to_string(),
+                connected_at: 1700000000000,
+            },
+            session_count: 2,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"device_connected","sd":{"s":"test","a":"127.0.0.1:5000","ca":1700000000000},"sc":2}"#,
+        );
+    }
+
+    #[test]
+    fn test_device_disconnected_event() {
+        let event = Event::DeviceDisconnected(DeviceDisconnectedReplyEvent {
+            session_id: "test".to_string(),
+            session_count: 1,
+        });
+
+        assert_event_serialization(&event, r#"{"_et":"device_disconnected","s":"test","sc":1}"#);
+    }
+
+    #[test]
+    fn test_session_list_event() {
+        let event = Event::SessionList(SessionListReplyEvent {
+            sessions: vec![SessionDetail {
+                session_id: "test".to_string(),
+                address: "127.0.0.1:5000".to_string(),
+                connected_at: 1700000000000,
+            }],
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"session_list","ss":[{"s":"test","a":"127.0.0.1:5000","ca":1700000000000}]}"#,
+        );
+    }
+
+    #[test]
+    fn test_session_terminated_event() {
+        let event = Event::SessionTerminated(SessionTerminatedReplyEvent {
+            reason: "test".to_string(),
+            cause: None,
+        });
+
+        assert_event_serialization(&event, r#"{"_et":"session_terminated","rs":"test"}"#);
+
+        let event = Event::SessionTerminated(SessionTerminatedReplyEvent {
+            reason: "test".to_string(),
+            cause: Some(SessionTerminationCause::TokenExpired),
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"session_terminated","rs":"test","c":{"t":"token_expired"}}"#,
+        );
+    }
+
+    #[test]
+    fn test_token_refreshed_event() {
+        let event = Event::TokenRefreshed(TokenRefreshedReplyEvent {
+            token: "bot_test".to_string(),
+            expires_at: 1,
+        });
+
+        assert_event_serialization(&event, r#"{"_et":"token_refreshed","t":"bot_test","ea":1}"#);
+    }
+
+    #[test]
+    fn test_session_replaced_event() {
+        let event = Event::SessionReplaced(SessionReplacedReplyEvent {
+            session: SessionDetail {
+                session_id: "test".to_string(),
+                address: "127.0.0.1:1234".to_string(),
+                connected_at: 1,
+            },
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"session_replaced","s":{"s":"test","a":"127.0.0.1:1234","ca":1}}"#,
+        );
+    }
+
+    #[test]
+    fn test_redirect_event() {
+        let event = Event::Redirect(RedirectReplyEvent {
+            addr: "localhost:8081".to_string(),
+        });
+
+        assert_event_serialization(&event, r#"{"_et":"redirect","a":"localhost:8081"}"#);
+    }
+
+    #[test]
+    fn test_history_response_event() {
+        let event = Event::HistoryResponse(HistoryResponseEvent {
+            room: "test".to_string(),
+            history: vec![
+                HistoricalMessage {
+                    id: "1".to_string(),
+                    user_id: "test".to_string(),
+                    display_name: None,
+                    content: String::new(),
+                    content_type: ContentType::Plain,
+                    timestamp: 1,
+                    kind: HistoricalMessageKind::Joined,
+                },
+                HistoricalMessage {
+                    id: "2".to_string(),
+                    user_id: "test".to_string(),
+                    display_name: None,
+                    content: "test".to_string(),
+                    content_type: ContentType::Plain,
+                    timestamp: 2,
+                    kind: HistoricalMessageKind::Message,
+                },
+            ],
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"history_response","r":"test","h":[{"i":"1","u":"test","c":"","ts":1,"k":"joined"},{"i":"2","u":"test","c":"test","ts":2,"k":"message"}]}"#,
+        );
+    }
+
+    #[test]
+    fn test_read_state_synced_event() {
+        let event = Event::ReadStateSynced(ReadStateSyncedReplyEvent {
+            room: "test".to_string(),
+            last_read_at: 1,
+        });
+
+        assert_event_serialization(&event, r#"{"_et":"read_state_synced","r":"test","lr":1}"#);
+    }
+
+    #[test]
+    fn test_presence_changed_event() {
+        let event = Event::PresenceChanged(PresenceChangedBroadcastEvent {
+            room: "test".to_string(),
+            user_id: "test".to_string(),
+            status: PresenceStatus::Away,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"presence_changed","r":"test","u":"test","s":"away"}"#,
+        );
+    }
+
+    #[test]
+    fn test_status_text_changed_event() {
+        let event = Event::StatusTextChanged(StatusTextChangedBroadcastEvent {
+            room: "test".to_string(),
+            user_id: "test".to_string(),
+            text: Some("in a meeting".to_string()),
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"status_text_changed","r":"test","u":"test","t":"in a meeting"}"#,
+        );
+    }
+
+    #[test]
+    fn test_user_info_event() {
+        let event = Event::UserInfo(UserInfoReplyEvent {
+            user_id: "test".to_string(),
+            presence: Some(PresenceStatus::Online),
+            status_text: Some("in a meeting".to_string()),
+            shared_rooms: vec!["test".to_string()],
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"user_info","u":"test","p":"online","st":"in a meeting","sr":["test"]}"#,
+        );
+    }
+
+    #[test]
+    fn test_user_info_event_offline() {
+        let event = Event::UserInfo(UserInfoReplyEvent {
+            user_id: "test".to_string(),
+            presence: None,
+            status_text: None,
+            shared_rooms: vec![],
+        });
+
+        assert_event_serialization(&event, r#"{"_et":"user_info","u":"test","sr":[]}"#);
+    }
+
+    #[test]
+    fn test_user_search_results_event() {
+        let event = Event::UserSearchResults(UserSearchResultsReplyEvent {
+            query: "te".to_string(),
+            users: vec![
+                UserSearchResult {
+                    user_id: "test".to_string(),
+                    presence: Some(PresenceStatus::Away),
+                },
+                UserSearchResult {
+                    user_id: "other-test".to_string(),
+                    presence: None,
+                },
+            ],
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"user_search_results","q":"te","us":[{"u":"test","p":"away"},{"u":"other-test"}]}"#,
+        );
+    }
+
+    #[test]
+    fn test_encrypted_room_detail() {
+        let event = Event::AccessibleRoomsChanged(AccessibleRoomsChangedReplyEvent {
+            rooms: vec![RoomDetail {
+                name: "test".to_string(),
+                description: "test".to_string(),
+                encrypted: true,
+                archived: false,
+                category: None,
+            }],
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"accessible_rooms_changed","rs":[{"n":"test","d":"test","e":true}]}"#,
+        );
+    }
+
+    #[test]
+    fn test_room_key_requested_event() {
+        let event = Event::RoomKeyRequested(RoomKeyRequestedBroadcastEvent {
+            room: "test".to_string(),
+            user_id: "test".to_string(),
+            session_id: "session".to_string(),
+            public_key: "key".to_string(),
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"room_key_requested","r":"test","u":"test","s":"session","k":"key"}"#,
+        );
+    }
+
+    #[test]
+    fn test_room_key_shared_event() {
+        let event = Event::RoomKeyShared(RoomKeySharedReplyEvent {
+            room: "test".to_string(),
+            user_id: "test".to_string(),
+            public_key: "key".to_string(),
+            sealed_key: "sealed".to_string(),
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"room_key_shared","r":"test","u":"test","k":"key","sk":"sealed"}"#,
+        );
+    }
+
+    #[test]
+    fn test_report_filed_event() {
+        let event = Event::ReportFiled(ReportFiledReplyEvent {
+            report_id: "test".to_string(),
+            room: "test".to_string(),
+        });
+
+        assert_event_serialization(&event, r#"{"_et":"report_filed","i":"test","r":"test"}"#);
+    }
+
+    #[test]
+    fn test_message_reported_event() {
+        let event = Event::MessageReported(MessageReportedEvent {
+            report: MessageReport {
+                id: "test".to_string(),
+                room: "test".to_string(),
+                message_id: "message".to_string(),
+                user_id: "test".to_string(),
+                content: "test".to_string(),
+                reason: "spam".to_string(),
+                reported_by: "reporter".to_string(),
+                reported_at: 1,
+            },
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"message_reported","rp":{"i":"test","r":"test","m":"message","u":"test","c":"test","rs":"spam","rb":"reporter","ts":1}}"#,
+        );
+    }
+
+    #[test]
+    fn test_report_list_event() {
+        let event = Event::ReportList(ReportListReplyEvent { reports: vec![] });
+
+        assert_event_serialization(&event, r#"{"_et":"report_list","rps":[]}"#);
+    }
+
+    #[test]
+    fn test_report_resolved_event() {
+        let event = Event::ReportResolved(ReportResolvedReplyEvent {
+            report_id: "test".to_string(),
+            resolved: true,
+        });
+
+        assert_event_serialization(&event, r#"{"_et":"report_resolved","i":"test","rv":true}"#);
+    }
+
+    #[test]
+    fn test_room_info_event() {
+        let event = Event::RoomInfo(RoomInfoReplyEvent {
+            room: "test".to_string(),
+            description: "test".to_string(),
+            member_count: 1,
+            capacity: Some(2),
+            owners: vec!["test".to_string()],
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"room_info","r":"test","d":"test","mc":1,"c":2,"o":["test"]}"#,
+        );
+    }
+
+    #[test]
+    fn test_rooms_checked_event() {
+        let event = Event::RoomsChecked(RoomsCheckedReplyEvent {
+            existing: vec!["test".to_string()],
+            missing: vec!["gone".to_string()],
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"rooms_checked","e":["test"],"m":["gone"]}"#,
+        );
+    }
+
+    #[test]
+    fn test_room_list_event() {
+        let event = Event::RoomList(RoomListReplyEvent { rooms: vec![] });
+
+        assert_event_serialization(&event, r#"{"_et":"room_list","rs":[]}"#);
+    }
+
+    #[test]
+    fn test_join_request_pending_event() {
+        let event = Event::JoinRequestPending(JoinRequestPendingEvent {
+            room: "test".to_string(),
+            user_id: "test".to_string(),
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"join_request_pending","r":"test","u":"test"}"#,
+        );
+    }
+
+    #[test]
+    fn test_join_request_decided_event() {
+        let event = Event::JoinRequestDecided(JoinRequestDecidedEvent {
+            room: "test".to_string(),
+            user_id: "test".to_string(),
+            approved: true,
+            decided_by: "test".to_string(),
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"join_request_decided","r":"test","u":"test","a":true,"db":"test"}"#,
+        );
+    }
+
+    #[test]
+    fn test_room_resynced_event() {
+        let event = Event::RoomResynced(RoomResyncedReplyEvent {
+            room: "test".to_string(),
+            missed_events: 12,
+        });
+
+        assert_event_serialization(&event, r#"{"_et":"room_resynced","r":"test","me":12}"#);
+    }
+
+    #[test]
+    fn test_broadcast_metrics_event() {
+        let event = Event::BroadcastMetrics(BroadcastMetricsReplyEvent {
+            rooms: vec![RoomBroadcastMetrics {
+                room: "test".to_string(),
+                capacity: 100,
+                lag_policy: LagPolicy::Disconnect,
+                lag_count: 2,
+                dropped_events: 30,
+                disconnected_sessions: 2,
+                leaked_handles: 1,
+            }],
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"broadcast_metrics","rs":[{"r":"test","c":100,"lp":"disconnect","lc":2,"de":30,"ds":2,"lh":1}]}"#,
+        );
+    }
+
+    #[test]
+    fn test_room_stats_event() {
+        let event = Event::RoomStats(RoomStatsReplyEvent {
+            rooms: vec![RoomStats {
+                room: "test".to_string(),
+                messages_per_hour: vec![0, 3, 12],
+                active_users: 4,
+                member_count: 2,
+                peak_concurrency: 5,
+            }],
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"room_stats","rs":[{"r":"test","mph":[0,3,12],"au":4,"mc":2,"pc":5}]}"#,
+        );
+    }
+
+    #[test]
+    fn test_room_archived_event() {
+        let event = Event::RoomArchived(RoomArchivedBroadcastEvent {
+            room: "test".to_string(),
+        });
+
+        assert_event_serialization(&event, r#"{"_et":"room_archived","r":"test"}"#);
+    }
+
+    #[test]
+    fn test_room_reopened_event() {
+        let event = Event::RoomReopened(RoomReopenedBroadcastEvent {
+            room: "test".to_string(),
+        });
+
+        assert_event_serialization(&event, r#"{"_et":"room_reopened","r":"test"}"#);
+    }
+
+    #[test]
+    fn test_room_metadata_updated_event() {
+        let event = Event::RoomMetadataUpdated(RoomMetadataUpdatedBroadcastEvent {
+            room: "test".to_string(),
+            description: "test".to_string(),
+            capacity: Some(10),
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"room_metadata_updated","r":"test","d":"test","c":10}"#,
+        );
+    }
+
+    #[test]
+    fn test_room_archival_scheduled_event() {
+        let event = Event::RoomArchivalScheduled(RoomArchivalScheduledReplyEvent {
+            room: "test".to_string(),
+            archive_at: 1700000000000,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"room_archival_scheduled","r":"test","t":1700000000000}"#,
+        );
+    }
+
+    #[test]
+    fn test_history_chunk_event() {
+        let event = Event::HistoryChunk(HistoryChunkEvent {
+            room: "test".to_string(),
+            history: vec![HistoricalMessage {
+                id: "1".to_string(),
+                user_id: "test".to_string(),
+                display_name: None,
+                content: "test".to_string(),
+                content_type: ContentType::Plain,
+                timestamp: 1,
+                kind: HistoricalMessageKind::Message,
+            }],
+            total_count: 250,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"history_chunk","r":"test","h":[{"i":"1","u":"test","c":"test","ts":1,"k":"message"}],"tc":250}"#,
+        );
+    }
+
+    #[test]
+    fn test_history_done_event() {
+        let event = Event::HistoryDone(HistoryDoneEvent {
+            room: "test".to_string(),
+        });
+
+        assert_event_serialization(&event, r#"{"_et":"history_done","r":"test"}"#);
+    }
+
+    #[test]
+    fn test_messages_redacted_event() {
+        let event = Event::MessagesRedacted(MessagesRedactedBroadcastEvent {
+            room: "test".to_string(),
+            user_id: "alice".to_string(),
+            message_ids: vec!["1".to_string(), "2".to_string()],
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"messages_redacted","r":"test","u":"alice","is":["1","2"]}"#,
+        );
+    }
+
+    #[test]
+    fn test_user_purged_event() {
+        let event = Event::UserPurged(UserPurgedReplyEvent {
+            user_id: "alice".to_string(),
+            redacted_count: 3,
+        });
+
+        assert_event_serialization(&event, r#"{"_et":"user_purged","u":"alice","rc":3}"#);
+    }
+
+    #[test]
+    fn test_command_history_event() {
+        let event = Event::CommandHistory(CommandHistoryReplyEvent {
+            user_id: "alice".to_string(),
+            entries: vec![
+                CommandHistoryEntry {
+                    command: "join_room".to_string(),
+                    session_id: "session-id-1".to_string(),
+                    received_at: 1,
+                    rejected: false,
+                },
+                CommandHistoryEntry {
+                    command: "purge_user".to_string(),
+                    session_id: "session-id-1".to_string(),
+                    received_at: 2,
+                    rejected: true,
+                },
+            ],
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"command_history","u":"alice","es":[{"c":"join_room","s":"session-id-1","t":1},{"c":"purge_user","s":"session-id-1","t":2,"rj":true}]}"#,
+        );
+    }
+
+    #[test]
+    fn test_invite_code_created_event() {
+        let event = Event::InviteCodeCreated(InviteCodeCreatedReplyEvent {
+            room: "test".to_string(),
+            code: "abcd-efgh".to_string(),
+            expires_at: 1,
+            max_uses: 5,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"invite_code_created","r":"test","c":"abcd-efgh","ea":1,"mu":5}"#,
+        );
+    }
+
+    #[test]
+    fn test_archived_rejected_event() {
+        let event = Event::MessageRejected(MessageRejectedReplyEvent {
+            room: "test".to_string(),
+            reason: MessageRejectionReason::Archived,
+            nonce: None,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"message_rejected","r":"test","rs":{"t":"archived"}}"#,
+        );
+
+        let event = Event::MessageRejected(MessageRejectedReplyEvent {
+            room: "test".to_string(),
+            reason: MessageRejectionReason::RateLimited { retry_after: 1000 },
+            nonce: None,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"message_rejected","r":"test","rs":{"t":"rate_limited","ra":1000}}"#,
+        );
+    }
+
+    #[test]
+    fn test_blocked_rejected_event() {
+        let event = Event::MessageRejected(MessageRejectedReplyEvent {
+            room: "test".to_string(),
+            reason: MessageRejectionReason::Blocked,
+            nonce: None,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"message_rejected","r":"test","rs":{"t":"blocked"}}"#,
+        );
+    }
+
+    #[test]
+    fn test_too_many_reminders_rejected_event() {
+        let event = Event::MessageRejected(MessageRejectedReplyEvent {
+            room: "test".to_string(),
+            reason: MessageRejectionReason::TooManyReminders { max_reminders: 50 },
+            nonce: None,
+        });
+
+        assert_event_serialization(
+            &event,
+            r#"{"_et":"message_rejected","r":"test","rs":{"t":"too_many_reminders","mr":50}}"#,
+        );
+    }
+
+    #[test]
+    fn test_pong_event() {
+        let event = Event::Pong(PongReplyEvent { sent_at: 1 });
+
+        assert_event_serialization(&event, r#"{"_et":"pong","t":1}"#);
     }
 }
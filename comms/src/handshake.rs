@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+
+/// Compression applied to every frame of a connection once the handshake is completed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Compression {
+    /// Frames are sent as plain new line delimited json
+    None,
+    /// Frames are sent through a single deflate stream which is flushed after every frame
+    Deflate,
+}
+
+/// The first frame a client sends after connecting to the server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClientHello {
+    /// The compression algorithms the client supports, in order of preference
+    #[serde(rename = "c")]
+    pub compression: Vec<Compression>,
+}
+
+/// The reply of the server to a [ClientHello], both sides switch to the selected options afterwards
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerHello {
+    /// The compression algorithm selected for the rest of the connection
+    #[serde(rename = "c")]
+    pub compression: Compression,
+}
+
+impl ClientHello {
+    /// Picks the most preferred compression of the client that the server also supports
+    /// Falls back to [Compression::None] if there is no common algorithm
+    pub fn negotiate_compression(&self, supported: &[Compression]) -> Compression {
+        self.compression
+            .iter()
+            .find(|compression| supported.contains(compression))
+            .copied()
+            .unwrap_or(Compression::None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_hello() {
+        let hello = ClientHello {
+            compression: vec![Compression::Deflate, Compression::None],
+        };
+
+        let serialized = serde_json::to_string(&hello).unwrap();
+        assert_eq!(serialized, r#"{"c":["deflate","none"]}"#);
+        assert_eq!(
+            serde_json::from_str::<ClientHello>(&serialized).unwrap(),
+            hello
+        );
+    }
+
+    #[test]
+    fn test_server_hello() {
+        let hello = ServerHello {
+            compression: Compression::Deflate,
+        };
+
+        let serialized = serde_json::to_string(&hello).unwrap();
+        assert_eq!(serialized, r#"{"c":"deflate"}"#);
+        assert_eq!(
+            serde_json::from_str::<ServerHello>(&serialized).unwrap(),
+            hello
+        );
+    }
+
+    #[test]
+    fn test_negotiate_compression() {
+        let hello = ClientHello {
+            compression: vec![Compression::Deflate, Compression::None],
+        };
+
+        assert_eq!(
+            hello.negotiate_compression(&[Compression::None, Compression::Deflate]),
+            Compression::Deflate
+        );
+        assert_eq!(
+            hello.negotiate_compression(&[Compression::None]),
+            Compression::None
+        );
+        assert_eq!(
+            ClientHello {
+                compression: vec![],
+            }
+            .negotiate_compression(&[Compression::Deflate]),
+            Compression::None
+        );
+    }
+}
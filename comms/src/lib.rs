@@ -2,6 +2,8 @@
 pub mod command;
 /// Set of events split into Broadcast and Reply events according to their source
 pub mod event;
+/// Handshake frames exchanged right after connecting, used to negotiate options such as compression
+pub mod handshake;
 /// Implementation of event and command transportation over TCP Streams.
 /// Requires 'server' or 'client' features to be enabled and will bring in tokio dependency alongside with other dependencies
 pub mod transport;
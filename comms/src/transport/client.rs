@@ -1,13 +1,16 @@
-use anyhow::Context;
+use std::sync::Arc;
+
 use tokio::{
-    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
-    net::{tcp::OwnedWriteHalf, TcpStream},
+    io::{AsyncRead, AsyncWrite, BufReader},
+    net::TcpStream,
 };
-use tokio_stream::{wrappers::LinesStream, StreamExt};
 
-use crate::{command, event};
+use crate::{
+    command, event,
+    handshake::{ClientHello, Compression, ServerHello},
+};
 
-use super::common::{BoxedStream, NEW_LINE};
+use super::common::{self, BoxedStream, BoxedWriter};
 
 /// [EventStream] is a stream of [event::Event]s sent by the server
 ///
@@ -17,14 +20,32 @@ use super::common::{BoxedStream, NEW_LINE};
 /// without the risk of missing events.
 pub type EventStream = BoxedStream<anyhow::Result<event::Event>>;
 
+/// Which way a frame has gone over the connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameDirection {
+    /// An event sent by the server
+    Inbound,
+    /// A command sent by the client
+    Outbound,
+}
+
+/// [FrameTap] is shown every frame going over the connection after the handshake, as the line of json it is
+/// sent as, without the new line. Inbound frames are shown before they are deserialized, so frames the client
+/// does not understand are shown as well, e.g. the ones of a modified server.
+pub type FrameTap = Arc<dyn Fn(FrameDirection, &str) + Send + Sync>;
+
 /// [CommandWriter] is a wrapper around a [TcpStream] which writes [command::UserCommand]s to the server
 pub struct CommandWriter {
-    writer: OwnedWriteHalf,
+    writer: BoxedWriter,
+    tap: Option<FrameTap>,
 }
 
 impl CommandWriter {
-    pub fn new(writer: OwnedWriteHalf) -> Self {
-        Self { writer }
+    pub fn new<W: AsyncWrite + Send + 'static>(writer: W) -> Self {
+        Self {
+            writer: Box::pin(writer),
+            tap: None,
+        }
     }
 
     /// Send a [command::UserCommand] to the backing [TcpStream]
@@ -37,16 +58,25 @@ impl CommandWriter {
     /// partially written, but future calls to `write` will start over
     /// from the beginning of the buffer. Causing undefined behaviour.
     pub async fn write(&mut self, command: &command::UserCommand) -> anyhow::Result<()> {
-        let mut serialized_bytes = serde_json::to_vec(command)?;
-        serialized_bytes.extend_from_slice(NEW_LINE);
+        let encoded_frame = common::encode_frame(command)?;
+        if let Some(tap) = self.tap.as_ref() {
+            tap(
+                FrameDirection::Outbound,
+                &String::from_utf8_lossy(encoded_frame.trim_ascii_end()),
+            );
+        }
 
-        self.writer.write_all(serialized_bytes.as_slice()).await?;
+        common::write_encoded_frame(&mut self.writer, &encoded_frame).await
+    }
 
-        Ok(())
+    /// Gracefully close the connection, no further [command::UserCommand]s can be written afterwards
+    pub async fn shutdown(&mut self) -> anyhow::Result<()> {
+        common::shutdown(&mut self.writer).await
     }
 }
 
 /// Splits a TCP stream into a stream of events and a command writer.
+/// No handshake takes place, the connection is used as plain new line delimited json.
 ///
 /// # Arguments
 ///
@@ -55,15 +85,93 @@ pub fn split_tcp_stream(stream: TcpStream) -> (EventStream, CommandWriter) {
     let (reader, writer) = stream.into_split();
 
     (
-        Box::pin(
-            LinesStream::new(BufReader::new(reader).lines()).map(|line| {
-                line.context("could not read line from the server")
-                    .and_then(|line| {
-                        serde_json::from_str::<event::Event>(&line)
-                            .context("failed to deserialize event from the server")
-                    })
-            }),
-        ),
+        common::frame_stream(Box::pin(BufReader::new(reader)), "server"),
         CommandWriter::new(writer),
     )
 }
+
+/// Sends a [ClientHello] over a freshly connected TCP stream, waits for the [ServerHello]
+/// and splits the stream into a stream of events and a command writer using the negotiated options.
+/// The client logs in afterwards by writing a [crate::command::LoginCommand].
+///
+/// # Arguments
+///
+/// - `stream` - A [TcpStream] to connect over
+/// - `compression` - The compression algorithms the client supports, in order of preference
+pub async fn connect_tcp_stream(
+    stream: TcpStream,
+    compression: &[Compression],
+) -> anyhow::Result<(EventStream, CommandWriter)> {
+    let (reader, writer) = stream.into_split();
+
+    connect_split_stream(reader, writer, compression, None).await
+}
+
+/// Same as [connect_tcp_stream], every frame going over the connection is shown to the tap, e.g. to inspect the traffic
+///
+/// # Arguments
+///
+/// - `stream` - A [TcpStream] to connect over
+/// - `compression` - The compression algorithms the client supports, in order of preference
+/// - `tap` - What is shown the frames, see [FrameTap]
+pub async fn connect_tcp_stream_with_tap(
+    stream: TcpStream,
+    compression: &[Compression],
+    tap: FrameTap,
+) -> anyhow::Result<(EventStream, CommandWriter)> {
+    let (reader, writer) = stream.into_split();
+
+    connect_split_stream(reader, writer, compression, Some(tap)).await
+}
+
+/// Same as [connect_tcp_stream] for any other kind of connection, e.g. a Unix domain socket
+///
+/// # Arguments
+///
+/// - `stream` - A freshly established connection to connect over
+/// - `compression` - The compression algorithms the client supports, in order of preference
+pub async fn connect_stream<S>(
+    stream: S,
+    compression: &[Compression],
+) -> anyhow::Result<(EventStream, CommandWriter)>
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (reader, writer) = tokio::io::split(stream);
+
+    connect_split_stream(reader, writer, compression, None).await
+}
+
+async fn connect_split_stream<R, W>(
+    reader: R,
+    mut writer: W,
+    compression: &[Compression],
+    tap: Option<FrameTap>,
+) -> anyhow::Result<(EventStream, CommandWriter)>
+where
+    R: AsyncRead + Send + Unpin + 'static,
+    W: AsyncWrite + Send + Unpin + 'static,
+{
+    // the same buffered reader must be kept, since it may already hold bytes following the hello
+    let mut reader = BufReader::new(reader);
+
+    common::write_frame(
+        &mut writer,
+        &ClientHello {
+            compression: compression.to_vec(),
+        },
+    )
+    .await?;
+    let server_hello: ServerHello = common::read_frame(&mut reader).await?;
+
+    let (reader, writer) = common::wrap_with_compression(reader, writer, server_hello.compression);
+
+    let event_stream = match tap.clone() {
+        Some(tap) => common::inspected_frame_stream(reader, "server", move |line| {
+            tap(FrameDirection::Inbound, line)
+        }),
+        None => common::frame_stream(reader, "server"),
+    };
+
+    Ok((event_stream, CommandWriter { writer, tap }))
+}
@@ -1,7 +1,243 @@
-use std::pin::Pin;
+use std::{
+    pin::Pin,
+    task::{ready, Context as TaskContext, Poll},
+    time::Duration,
+};
 
-use tokio_stream::Stream;
+use anyhow::Context;
+use async_compression::tokio::{bufread::DeflateDecoder, write::DeflateEncoder};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio_stream::{Stream, StreamExt};
+use tokio_util::codec::{FramedRead, LinesCodec};
+
+use crate::handshake::Compression;
 
 pub const NEW_LINE: &[u8; 2] = b"\r\n";
 
+/// How long the peer may take to send its hello, so that idle connections do not linger before the handshake
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a hello may be in bytes, far more than any hello takes
+pub const MAX_HANDSHAKE_FRAME_LENGTH: u64 = 16 * 1024;
+
+/// How long any other frame may be in bytes once decompressed, far more than any frame takes
+/// A peer could otherwise make the other one buffer an endless line, e.g. with a few bytes inflating to gigabytes
+pub const MAX_FRAME_LENGTH: usize = 1024 * 1024;
+
 pub type BoxedStream<Item> = Pin<Box<dyn Stream<Item = Item> + Send>>;
+
+pub type BoxedReader = Pin<Box<dyn AsyncBufRead + Send>>;
+
+pub type BoxedWriter = Pin<Box<dyn AsyncWrite + Send>>;
+
+/// Wraps both halves of a connection according to the negotiated [Compression]
+pub fn wrap_with_compression<R, W>(
+    reader: R,
+    writer: W,
+    compression: Compression,
+) -> (BoxedReader, BoxedWriter)
+where
+    R: AsyncBufRead + Send + 'static,
+    W: AsyncWrite + Send + 'static,
+{
+    match compression {
+        Compression::None => (Box::pin(reader), Box::pin(writer)),
+        Compression::Deflate => (
+            Box::pin(BufReader::new(DeflateDecoder::new(reader))),
+            Box::pin(DeflateEncoder::new(writer)),
+        ),
+    }
+}
+
+/// Serializes the given frame as a single line and flushes it to the writer
+pub async fn write_frame<T, W>(writer: &mut W, frame: &T) -> anyhow::Result<()>
+where
+    T: Serialize,
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    write_encoded_frame(writer, &encode_frame(frame)?).await
+}
+
+/// Serializes the given frame as a single line, terminated by a new line
+pub fn encode_frame<T: Serialize>(frame: &T) -> anyhow::Result<Vec<u8>> {
+    let mut serialized_bytes = serde_json::to_vec(frame)?;
+    serialized_bytes.extend_from_slice(NEW_LINE);
+
+    Ok(serialized_bytes)
+}
+
+/// Flushes a frame which is already serialized by [encode_frame] to the writer
+pub async fn write_encoded_frame<W>(writer: &mut W, encoded_frame: &[u8]) -> anyhow::Result<()>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    writer.write_all(encoded_frame).await?;
+    // compressed writers only emit the frame to the peer when flushed
+    writer.flush().await?;
+
+    Ok(())
+}
+
+/// Finishes any pending compression stream and shuts down the writer
+/// Peers of a compressed connection otherwise observe a truncated stream as a read error
+pub async fn shutdown<W>(writer: &mut W) -> anyhow::Result<()>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    writer.shutdown().await?;
+
+    Ok(())
+}
+
+/// Reads a single line from the reader and deserializes it as a frame
+/// Used during the handshake, before the reader is turned into a stream
+/// Fails if the line is longer than [MAX_HANDSHAKE_FRAME_LENGTH] or is not complete within [HANDSHAKE_TIMEOUT]
+pub async fn read_frame<T, R>(reader: &mut R) -> anyhow::Result<T>
+where
+    T: DeserializeOwned,
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    let mut line = String::new();
+    let read = tokio::time::timeout(
+        HANDSHAKE_TIMEOUT,
+        reader.take(MAX_HANDSHAKE_FRAME_LENGTH).read_line(&mut line),
+    )
+    .await
+    .context("timed out waiting for the handshake frame")??;
+    if read == 0 {
+        return Err(anyhow::anyhow!("connection closed during the handshake"));
+    }
+    if !line.ends_with('\n') && read as u64 == MAX_HANDSHAKE_FRAME_LENGTH {
+        return Err(anyhow::anyhow!("the handshake frame is too long"));
+    }
+
+    serde_json::from_str::<T>(&line).context("failed to deserialize the handshake frame")
+}
+
+/// Ends right after the first error of the lines, without polling them again
+///
+/// The lines must not be read on after a read error, nor after a line longer than [MAX_FRAME_LENGTH],
+/// whose end the codec would otherwise skip to, so the peer gets to send another one.
+struct LinesUntilError<S> {
+    lines: Option<S>,
+}
+
+impl<S, E> Stream for LinesUntilError<S>
+where
+    S: Stream<Item = Result<String, E>> + Unpin,
+{
+    type Item = Result<String, E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Option<Self::Item>> {
+        let Some(lines) = self.lines.as_mut() else {
+            return Poll::Ready(None);
+        };
+
+        let line = ready!(Pin::new(lines).poll_next(cx));
+        if !matches!(line, Some(Ok(_))) {
+            self.lines = None;
+        }
+
+        Poll::Ready(line)
+    }
+}
+
+/// Turns the reader into a stream of frames, one per line
+///
+/// The stream ends after the first read error, since a broken connection would keep failing otherwise,
+/// and after the first line longer than [MAX_FRAME_LENGTH], so the connection is closed.
+pub fn frame_stream<T>(reader: BoxedReader, peer: &'static str) -> BoxedStream<anyhow::Result<T>>
+where
+    T: DeserializeOwned + 'static,
+{
+    inspected_frame_stream(reader, peer, |_| {})
+}
+
+/// Same as [frame_stream], every line is shown to the inspector before it is deserialized
+pub fn inspected_frame_stream<T, F>(
+    reader: BoxedReader,
+    peer: &'static str,
+    inspect: F,
+) -> BoxedStream<anyhow::Result<T>>
+where
+    T: DeserializeOwned + 'static,
+    F: Fn(&str) + Send + 'static,
+{
+    Box::pin(
+        LinesUntilError {
+            lines: Some(FramedRead::new(
+                reader,
+                LinesCodec::new_with_max_length(MAX_FRAME_LENGTH),
+            )),
+        }
+        .map(move |line| {
+            line.with_context(|| format!("could not read line from the {}", peer))
+                .and_then(|line| {
+                    inspect(&line);
+                    serde_json::from_str::<T>(&line)
+                        .with_context(|| format!("failed to deserialize frame from the {}", peer))
+                })
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncWriteExt;
+
+    use super::*;
+    use crate::handshake::ClientHello;
+
+    #[tokio::test]
+    async fn test_handshake_frame_is_read() {
+        let (mut writer, reader) = tokio::io::duplex(1024);
+        writer.write_all(b"{\"c\":[\"none\"]}\r\n").await.unwrap();
+
+        let hello: ClientHello = read_frame(&mut BufReader::new(reader)).await.unwrap();
+        assert_eq!(hello.compression, vec![Compression::None]);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_handshake_frame_is_rejected() {
+        let (mut writer, reader) = tokio::io::duplex(1024);
+        tokio::spawn(async move {
+            let padding = vec![b' '; MAX_HANDSHAKE_FRAME_LENGTH as usize + 1];
+            let _ = writer.write_all(&padding).await;
+        });
+
+        let result = read_frame::<ClientHello, _>(&mut BufReader::new(reader)).await;
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("the handshake frame is too long"));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_inflated_frame_ends_the_stream() {
+        let (client, server) = tokio::io::duplex(1024);
+        let (_, mut writer) = wrap_with_compression(
+            BufReader::new(tokio::io::empty()),
+            client,
+            Compression::Deflate,
+        );
+        tokio::spawn(async move {
+            // deflated to a few bytes, but far too long to be buffered once inflated
+            let padding = vec![b' '; MAX_FRAME_LENGTH + 1];
+            let _ = writer.write_all(&padding).await;
+            let _ = writer.write_all(b"{\"c\":[\"none\"]}\r\n").await;
+            let _ = writer.shutdown().await;
+        });
+
+        let (reader, _) = wrap_with_compression(
+            BufReader::new(server),
+            tokio::io::sink(),
+            Compression::Deflate,
+        );
+        let mut frames = frame_stream::<ClientHello>(reader, "client");
+        assert!(frames.next().await.unwrap().is_err());
+        assert!(frames.next().await.is_none());
+    }
+}
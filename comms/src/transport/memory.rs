@@ -0,0 +1,101 @@
+use tokio::io::{BufReader, DuplexStream};
+
+use super::{client, common, server};
+
+/// How many bytes one end may write before the other end has read them
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// The client end of an in-memory connection, as [client::split_tcp_stream] returns it
+pub type ClientEnd = (client::EventStream, client::CommandWriter);
+
+/// The server end of an in-memory connection, as [server::split_tcp_stream] returns it
+pub type ServerEnd = (server::CommandStream, server::EventWriter);
+
+/// Connects a client and a server in memory, without a handshake
+///
+/// Frames go over the connection as plain new line delimited json, the same as over a split TCP stream.
+/// Dropping or shutting down the writer of one end ends the stream of the other one.
+pub fn pair() -> (ClientEnd, ServerEnd) {
+    // a pipe per direction, the halves of a split stream only close it once both of them are dropped
+    let (client_writer, server_reader) = tokio::io::duplex(BUFFER_SIZE);
+    let (server_writer, client_reader) = tokio::io::duplex(BUFFER_SIZE);
+
+    (
+        (
+            common::frame_stream(Box::pin(BufReader::new(client_reader)), "server"),
+            client::CommandWriter::new(client_writer),
+        ),
+        (
+            common::frame_stream(Box::pin(BufReader::new(server_reader)), "client"),
+            server::EventWriter::new(server_writer),
+        ),
+    )
+}
+
+/// Two connected in-memory streams, for when the handshake should take place as well
+///
+/// One is handed to [client::connect_stream], the other one to [server::accept_stream],
+/// or to whatever accepts a freshly established connection, e.g. a server session.
+pub fn stream_pair() -> (DuplexStream, DuplexStream) {
+    tokio::io::duplex(BUFFER_SIZE)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_stream::StreamExt;
+
+    use super::*;
+    use crate::{
+        command::{self, UserCommand},
+        event::{self, Event},
+        handshake::Compression,
+    };
+
+    fn list_command() -> UserCommand {
+        UserCommand::ListRooms(command::ListRoomsCommand)
+    }
+
+    fn room_list_event() -> Event {
+        Event::RoomList(event::RoomListReplyEvent { rooms: Vec::new() })
+    }
+
+    #[tokio::test]
+    async fn test_frames_go_both_ways() {
+        let ((mut events, mut command_writer), (mut commands, mut event_writer)) = pair();
+
+        command_writer.write(&list_command()).await.unwrap();
+        assert_eq!(commands.next().await.unwrap().unwrap(), list_command());
+
+        event_writer.write(&room_list_event()).await.unwrap();
+        assert_eq!(events.next().await.unwrap().unwrap(), room_list_event());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_writer_ends_the_stream() {
+        let ((mut events, command_writer), (mut commands, event_writer)) = pair();
+
+        drop(command_writer);
+        assert!(commands.next().await.is_none());
+
+        drop(event_writer);
+        assert!(events.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_handshake_takes_place_over_the_stream_pair() {
+        let (client_stream, server_stream) = stream_pair();
+
+        let (client, server) = tokio::join!(
+            client::connect_stream(client_stream, &[Compression::Deflate]),
+            server::accept_stream(server_stream)
+        );
+        let ((mut events, mut command_writer), (mut commands, mut event_writer)) =
+            (client.unwrap(), server.unwrap());
+
+        command_writer.write(&list_command()).await.unwrap();
+        assert_eq!(commands.next().await.unwrap().unwrap(), list_command());
+
+        event_writer.write(&room_list_event()).await.unwrap();
+        assert_eq!(events.next().await.unwrap().unwrap(), room_list_event());
+    }
+}
@@ -3,6 +3,9 @@
 pub mod client;
 #[cfg(any(feature = "client", feature = "server"))]
 mod common;
+/// In-memory connections between a client and a server, e.g. for testing either of them without sockets
+#[cfg(feature = "test-util")]
+pub mod memory;
 /// Transport over TCP implementation for a server to interact with a single client TCP Stream
 #[cfg(feature = "server")]
 pub mod server;
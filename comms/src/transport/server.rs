@@ -1,13 +1,18 @@
-use anyhow::Context;
+use bytes::Bytes;
 use tokio::{
-    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
-    net::{tcp::OwnedWriteHalf, TcpStream},
+    io::{AsyncRead, AsyncWrite, BufReader},
+    net::TcpStream,
 };
-use tokio_stream::{wrappers::LinesStream, StreamExt};
 
-use crate::{command, event};
+use crate::{
+    command, event,
+    handshake::{ClientHello, Compression, ServerHello},
+};
+
+use super::common::{self, BoxedStream, BoxedWriter};
 
-use super::common::{BoxedStream, NEW_LINE};
+/// The compression algorithms the server is willing to use, in order of preference
+pub const SUPPORTED_COMPRESSION: &[Compression] = &[Compression::Deflate, Compression::None];
 
 /// [CommandStream] is a stream of [command::UserCommand]s sent by the client
 ///
@@ -17,14 +22,39 @@ use super::common::{BoxedStream, NEW_LINE};
 /// without the risk of missing commands.
 pub type CommandStream = BoxedStream<anyhow::Result<command::UserCommand>>;
 
+/// [EncodedEvent] is an [event::Event] serialized as a frame, ready to be written to any number of clients
+///
+/// Cloning it only clones a reference to the serialized frame, hence an event broadcast to the
+/// participants of a room is serialized once rather than once per participant.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncodedEvent(Bytes);
+
+impl EncodedEvent {
+    pub fn encode(event: &event::Event) -> anyhow::Result<Self> {
+        Ok(EncodedEvent(Bytes::from(common::encode_frame(event)?)))
+    }
+
+    /// Deserializes the frame back into the [event::Event], e.g. for inspecting a broadcast
+    pub fn decode(&self) -> anyhow::Result<event::Event> {
+        Ok(serde_json::from_slice(&self.0)?)
+    }
+
+    /// The serialized frame, including its trailing new line
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
 /// [EventWriter] is a wrapper around a [TcpStream] which writes [event::Event]s to the client
 pub struct EventWriter {
-    writer: OwnedWriteHalf,
+    writer: BoxedWriter,
 }
 
 impl EventWriter {
-    pub fn new(writer: OwnedWriteHalf) -> Self {
-        Self { writer }
+    pub fn new<W: AsyncWrite + Send + 'static>(writer: W) -> Self {
+        Self {
+            writer: Box::pin(writer),
+        }
     }
 
     /// Send a [event::Event] to the backing [TcpStream]
@@ -37,16 +67,26 @@ impl EventWriter {
     /// partially written, but future calls to `write` will start over
     /// from the beginning of the buffer. Causing undefined behaviour.
     pub async fn write(&mut self, event: &event::Event) -> anyhow::Result<()> {
-        let mut serialized_bytes = serde_json::to_vec(event)?;
-        serialized_bytes.extend_from_slice(NEW_LINE);
+        common::write_frame(&mut self.writer, event).await
+    }
 
-        self.writer.write_all(serialized_bytes.as_slice()).await?;
+    /// Send an [EncodedEvent] to the backing [TcpStream] as is, without serializing it again
+    ///
+    /// # Cancel Safety
+    ///
+    /// This method is not cancellation safe, for the same reasons as [EventWriter::write].
+    pub async fn write_encoded(&mut self, encoded_event: &EncodedEvent) -> anyhow::Result<()> {
+        common::write_encoded_frame(&mut self.writer, encoded_event.as_bytes()).await
+    }
 
-        Ok(())
+    /// Gracefully close the connection, no further [event::Event]s can be written afterwards
+    pub async fn shutdown(&mut self) -> anyhow::Result<()> {
+        common::shutdown(&mut self.writer).await
     }
 }
 
 /// Splits a TCP stream into a stream of commands and an event writer.
+/// No handshake takes place, the connection is used as plain new line delimited json.
 ///
 /// # Arguments
 ///
@@ -55,15 +95,56 @@ pub fn split_tcp_stream(stream: TcpStream) -> (CommandStream, EventWriter) {
     let (reader, writer) = stream.into_split();
 
     (
-        Box::pin(
-            LinesStream::new(BufReader::new(reader).lines()).map(|line| {
-                line.context("could not read line from the client")
-                    .and_then(|line| {
-                        serde_json::from_str::<command::UserCommand>(&line)
-                            .context("failed to deserialize command from client")
-                    })
-            }),
-        ),
+        common::frame_stream(Box::pin(BufReader::new(reader)), "client"),
         EventWriter::new(writer),
     )
 }
+
+/// Waits for the [ClientHello] of a freshly accepted TCP stream, replies with a [ServerHello]
+/// and splits the stream into a stream of commands and an event writer using the negotiated options.
+///
+/// # Arguments
+///
+/// - `stream` - A [TcpStream] to accept
+pub async fn accept_tcp_stream(stream: TcpStream) -> anyhow::Result<(CommandStream, EventWriter)> {
+    let (reader, writer) = stream.into_split();
+
+    accept_split_stream(reader, writer).await
+}
+
+/// Same as [accept_tcp_stream] for any other kind of connection, e.g. a Unix domain socket
+///
+/// # Arguments
+///
+/// - `stream` - A freshly accepted connection to accept
+pub async fn accept_stream<S>(stream: S) -> anyhow::Result<(CommandStream, EventWriter)>
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (reader, writer) = tokio::io::split(stream);
+
+    accept_split_stream(reader, writer).await
+}
+
+async fn accept_split_stream<R, W>(
+    reader: R,
+    mut writer: W,
+) -> anyhow::Result<(CommandStream, EventWriter)>
+where
+    R: AsyncRead + Send + Unpin + 'static,
+    W: AsyncWrite + Send + Unpin + 'static,
+{
+    // the same buffered reader must be kept, since it may already hold bytes following the hello
+    let mut reader = BufReader::new(reader);
+
+    let client_hello: ClientHello = common::read_frame(&mut reader).await?;
+    let compression = client_hello.negotiate_compression(SUPPORTED_COMPRESSION);
+    common::write_frame(&mut writer, &ServerHello { compression }).await?;
+
+    let (reader, writer) = common::wrap_with_compression(reader, writer, compression);
+
+    Ok((
+        common::frame_stream(reader, "client"),
+        EventWriter { writer },
+    ))
+}
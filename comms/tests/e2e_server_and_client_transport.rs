@@ -1,50 +1,156 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
 use comms::{
     command::{self, UserCommand},
     event::{self, Event},
-    transport,
+    handshake::Compression,
+    transport::{
+        self,
+        client::{FrameDirection, FrameTap},
+    },
 };
 use tokio::net::{TcpListener, TcpStream};
 use tokio_stream::StreamExt;
 
-const PORT: usize = 8081;
+/// Binds to a port the system picks, so tests running side by side never collide
+async fn bind() -> (TcpListener, SocketAddr) {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("could not bind to a port");
+    let addr = listener
+        .local_addr()
+        .expect("could not get the bound address");
+
+    (listener, addr)
+}
 
 #[tokio::test]
 async fn assert_server_client_transport() {
-    let (server_collected_commands, client_collected_events) =
-        tokio::join!(execute_server(), execute_client());
+    let (listener, addr) = bind().await;
+    let (server_collected_commands, client_collected_events) = tokio::join!(
+        execute_server(listener, false),
+        execute_client(addr, None, None)
+    );
+
+    assert_collected(server_collected_commands, client_collected_events);
+}
+
+#[tokio::test]
+async fn assert_server_client_transport_with_compression() {
+    let (listener, addr) = bind().await;
+    let (server_collected_commands, client_collected_events) = tokio::join!(
+        execute_server(listener, true),
+        execute_client(addr, Some(&[Compression::Deflate]), None)
+    );
 
-    assert!(server_collected_commands.is_ok());
-    assert!(client_collected_events.is_ok());
+    assert_collected(server_collected_commands, client_collected_events);
+}
+
+#[tokio::test]
+async fn assert_tapped_frames() {
+    let tapped_frames = Arc::new(Mutex::new(Vec::new()));
+    let tap: FrameTap = {
+        let tapped_frames = Arc::clone(&tapped_frames);
+        Arc::new(move |direction, line| {
+            tapped_frames
+                .lock()
+                .unwrap()
+                .push((direction, line.to_string()))
+        })
+    };
+    let (listener, addr) = bind().await;
+    let (server_collected_commands, client_collected_events) = tokio::join!(
+        execute_server(listener, true),
+        execute_client(addr, Some(&[Compression::Deflate]), Some(tap))
+    );
+
+    assert_collected(server_collected_commands, client_collected_events);
+    let tapped_frames = tapped_frames.lock().unwrap();
+    assert_eq!(
+        tapped_frames
+            .iter()
+            .map(|(direction, _)| *direction)
+            .collect::<Vec<_>>(),
+        vec![
+            FrameDirection::Inbound,
+            FrameDirection::Inbound,
+            FrameDirection::Outbound,
+            FrameDirection::Outbound,
+        ]
+    );
+    assert_eq!(
+        tapped_frames[1].1,
+        serde_json::to_string(&room_message_event()).unwrap()
+    );
+    assert!(tapped_frames[3].1.contains("content-1"));
+}
+
+fn assert_collected(
+    server_collected_commands: anyhow::Result<Vec<UserCommand>>,
+    client_collected_events: anyhow::Result<Vec<Event>>,
+) {
+    assert!(
+        server_collected_commands.is_ok(),
+        "{:?}",
+        server_collected_commands
+    );
+    assert!(
+        client_collected_events.is_ok(),
+        "{:?}",
+        client_collected_events
+    );
 
     assert_eq!(
         server_collected_commands.unwrap(),
         vec![
             UserCommand::JoinRoom(command::JoinRoomCommand {
                 room: "room-1".into(),
+                wait_if_full: false,
             }),
             UserCommand::SendMessage(command::SendMessageCommand {
                 room: "room-1".into(),
                 content: "content-1".into(),
+                content_type: event::ContentType::Plain,
+                nonce: None,
             }),
         ]
     );
 
     assert_eq!(
         client_collected_events.unwrap(),
-        vec![Event::LoginSuccessful(event::LoginSuccessfulReplyEvent {
-            user_id: "user-id-1".into(),
-            session_id: "session-id-1".into(),
-            rooms: Vec::default(),
-        }),]
+        vec![
+            Event::LoginSuccessful(event::LoginSuccessfulReplyEvent {
+                user_id: "user-id-1".into(),
+                session_id: "session-id-1".into(),
+                rooms: Vec::default(),
+                max_message_length: 512,
+                session_count: 1,
+            }),
+            room_message_event(),
+        ]
     );
 }
 
-async fn execute_server() -> anyhow::Result<Vec<UserCommand>> {
-    // bind to the example port to wait for client connection
-    let listener = TcpListener::bind(format!("0.0.0.0:{}", PORT))
-        .await
-        .expect("could not bind to the port");
+fn room_message_event() -> Event {
+    Event::UserMessage(event::UserMessageBroadcastEvent {
+        id: "message-id-1".into(),
+        room: "room-1".into(),
+        user_id: "user-id-2".into(),
+        display_name: None,
+        content: "content-2".into(),
+        content_type: event::ContentType::Plain,
+        timestamp: 1735689600000,
+        nonce: None,
+    })
+}
 
+async fn execute_server(
+    listener: TcpListener,
+    handshake: bool,
+) -> anyhow::Result<Vec<UserCommand>> {
     // accept the only client connection we will have
     let tcp_stream = match listener.accept().await {
         Ok((tcp_stream, _addr)) => tcp_stream,
@@ -52,7 +158,12 @@ async fn execute_server() -> anyhow::Result<Vec<UserCommand>> {
     };
 
     // break the client connection into higher level API for ease of use
-    let (mut command_stream, mut event_writer) = transport::server::split_tcp_stream(tcp_stream);
+    // negotiating the connection options first if a handshake is expected
+    let (mut command_stream, mut event_writer) = if handshake {
+        transport::server::accept_tcp_stream(tcp_stream).await?
+    } else {
+        transport::server::split_tcp_stream(tcp_stream)
+    };
     // store commands received from the client
     let mut collected_commands = Vec::new();
 
@@ -62,8 +173,16 @@ async fn execute_server() -> anyhow::Result<Vec<UserCommand>> {
             user_id: "user-id-1".into(),
             session_id: "session-id-1".into(),
             rooms: Vec::default(),
+            max_message_length: 512,
+            session_count: 1,
         }))
         .await?;
+    // follow up with an event serialized ahead, as broadcasts are
+    event_writer
+        .write_encoded(&transport::server::EncodedEvent::encode(
+            &room_message_event(),
+        )?)
+        .await?;
 
     // listen for commands from the client until the connection is closed
     while let Some(result) = command_stream.next().await {
@@ -79,33 +198,50 @@ async fn execute_server() -> anyhow::Result<Vec<UserCommand>> {
     Ok(collected_commands)
 }
 
-async fn execute_client() -> anyhow::Result<Vec<Event>> {
+async fn execute_client(
+    addr: SocketAddr,
+    compression: Option<&[Compression]>,
+    tap: Option<FrameTap>,
+) -> anyhow::Result<Vec<Event>> {
     // create a client connection to the server
-    let tcp_stream = match TcpStream::connect(format!("localhost:{}", PORT)).await {
+    let tcp_stream = match TcpStream::connect(addr).await {
         Ok(tcp_stream) => tcp_stream,
         Err(e) => return Err(anyhow::anyhow!("failed to connect to server: {}", e)),
     };
 
     // break the server connection into higher level API for ease of use
-    let (mut event_stream, mut command_writer) = transport::client::split_tcp_stream(tcp_stream);
+    // sending a handshake first if the client wants to negotiate compression
+    // showing every frame to the tap if one is given
+    let (mut event_stream, mut command_writer) = match (compression, tap) {
+        (Some(compression), Some(tap)) => {
+            transport::client::connect_tcp_stream_with_tap(tcp_stream, compression, tap).await?
+        }
+        (Some(compression), None) => {
+            transport::client::connect_tcp_stream(tcp_stream, compression).await?
+        }
+        (None, _) => transport::client::split_tcp_stream(tcp_stream),
+    };
     // store events received from the server
     let mut collected_events = Vec::new();
 
-    // read the welcome event from the server
-    match event_stream.next().await {
-        // server has sent a valid event which we could read and parse
-        Some(Ok(event)) => collected_events.push(event),
-        // server has sent an event which we could not read or parse
-        // could be a bug in the server, malicious server, breaking api changes etc.
-        Some(Err(e)) => return Err(anyhow::anyhow!("could not parse event: {}", e)),
-        // server has closed the connection, return an error
-        None => return Err(anyhow::anyhow!("server closed the connection")),
+    // read the welcome event and the room message from the server
+    for _ in 0..2 {
+        match event_stream.next().await {
+            // server has sent a valid event which we could read and parse
+            Some(Ok(event)) => collected_events.push(event),
+            // server has sent an event which we could not read or parse
+            // could be a bug in the server, malicious server, breaking api changes etc.
+            Some(Err(e)) => return Err(anyhow::anyhow!("could not parse event: {}", e)),
+            // server has closed the connection, return an error
+            None => return Err(anyhow::anyhow!("server closed the connection")),
+        }
     }
 
     // send some commands to the server
     command_writer
         .write(&UserCommand::JoinRoom(command::JoinRoomCommand {
             room: "room-1".into(),
+            wait_if_full: false,
         }))
         .await?;
 
@@ -113,8 +249,13 @@ async fn execute_client() -> anyhow::Result<Vec<Event>> {
         .write(&UserCommand::SendMessage(command::SendMessageCommand {
             room: "room-1".into(),
             content: "content-1".into(),
+            content_type: event::ContentType::Plain,
+            nonce: None,
         }))
         .await?;
 
+    // close the connection gracefully so the server sees a clean end of the stream
+    command_writer.shutdown().await?;
+
     Ok(collected_events)
 }
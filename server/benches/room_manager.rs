@@ -0,0 +1,215 @@
+use std::time::Instant;
+
+use comms::{
+    event::{self, Event},
+    transport::server::EncodedEvent,
+};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use server::room_manager::{
+    ChatRoomMetadata, RoomJoinOutcome, RoomManager, RoomManagerBuilder, SessionAndUserId,
+    UserSessionHandle,
+};
+use tokio::{
+    runtime::Runtime,
+    sync::broadcast::{self, error::TryRecvError},
+};
+
+const ROOM: &str = "bench";
+
+fn room_manager() -> RoomManager {
+    RoomManagerBuilder::new()
+        .create_room(ChatRoomMetadata {
+            name: String::from(ROOM),
+            description: String::from("a room for the benchmarks"),
+            category: None,
+            capacity: None,
+            welcome_message: None,
+            owners: Vec::new(),
+            groups: Vec::new(),
+            record_participation: false,
+            encrypted: false,
+            requires_approval: false,
+            broadcast_capacity: None,
+            lag_policy: event::LagPolicy::Resync,
+        })
+        .build()
+}
+
+fn session(idx: usize) -> SessionAndUserId {
+    SessionAndUserId {
+        session_id: format!("session-{}", idx),
+        user_id: format!("user-{}", idx),
+    }
+}
+
+async fn join(
+    room_manager: &RoomManager,
+    session_and_user_id: &SessionAndUserId,
+) -> (broadcast::Receiver<EncodedEvent>, UserSessionHandle) {
+    match room_manager
+        .join_room(ROOM, session_and_user_id, None)
+        .await
+        .unwrap()
+    {
+        RoomJoinOutcome::Joined((broadcast_rx, handle, _, _, _)) => (broadcast_rx, handle),
+        RoomJoinOutcome::Full { .. } => panic!("a room without a capacity is never full"),
+    }
+}
+
+// the events the server sends the most, a message and the history sent when a room is opened
+fn bench_event_serialization(c: &mut Criterion) {
+    let message = Event::UserMessage(event::UserMessageBroadcastEvent {
+        id: String::from("V1StGXR8_Z5jdHi6B-myT"),
+        room: String::from("general"),
+        user_id: String::from("user-1"),
+        display_name: None,
+        content: String::from("a message of an average length sent to the general room"),
+        content_type: event::ContentType::Plain,
+        timestamp: 1735689600000,
+        nonce: None,
+    });
+    let history = Event::HistoryResponse(event::HistoryResponseEvent {
+        room: String::from("general"),
+        history: (0..100)
+            .map(|i| event::HistoricalMessage {
+                id: format!("{}", i),
+                user_id: format!("user-{}", i % 5),
+                display_name: None,
+                content: format!("message number {} sent to the general room", i),
+                content_type: event::ContentType::Plain,
+                timestamp: 1_700_000_000_000 + i * 1000,
+                kind: event::HistoricalMessageKind::Message,
+            })
+            .collect(),
+    });
+    let mut group = c.benchmark_group("event_serialization");
+
+    for (name, event) in [("user_message", message), ("history_response", history)] {
+        let serialized = serde_json::to_string(&event).unwrap();
+
+        group.bench_function(BenchmarkId::new("serialize", name), |b| {
+            b.iter(|| serde_json::to_string(&event).unwrap())
+        });
+        group.bench_function(BenchmarkId::new("deserialize", name), |b| {
+            b.iter(|| serde_json::from_str::<Event>(&serialized).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+// a message sent to a room is only delivered once every participant has received it
+fn bench_broadcast_fan_out(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("broadcast_fan_out");
+
+    for subscriber_count in [1, 10, 100, 1000] {
+        let room_manager = room_manager();
+        let (receivers, handles): (Vec<_>, Vec<_>) = runtime.block_on(async {
+            let mut participants = Vec::new();
+            for idx in 0..subscriber_count {
+                participants.push(join(&room_manager, &session(idx)).await);
+            }
+
+            participants.into_iter().unzip()
+        });
+        let mut receivers = receivers;
+        // the joins of the others are not part of the measurement, the early receivers have lagged behind them
+        for broadcast_rx in receivers.iter_mut() {
+            while !matches!(broadcast_rx.try_recv(), Err(TryRecvError::Empty)) {}
+        }
+
+        group.bench_function(BenchmarkId::from_parameter(subscriber_count), |b| {
+            b.iter_custom(|iters| {
+                runtime.block_on(async {
+                    let start = Instant::now();
+                    for _ in 0..iters {
+                        room_manager
+                            .send_message(
+                                &handles[0],
+                                String::from("hello everyone"),
+                                event::ContentType::Plain,
+                                None,
+                            )
+                            .await
+                            .unwrap();
+                        for broadcast_rx in receivers.iter_mut() {
+                            broadcast_rx.recv().await.unwrap();
+                        }
+                    }
+
+                    start.elapsed()
+                })
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_history(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let room_manager = room_manager();
+    let (_broadcast_rx, handle) = runtime.block_on(join(&room_manager, &session(0)));
+    let mut group = c.benchmark_group("history");
+
+    group.bench_function("append", |b| {
+        b.to_async(&runtime).iter(|| async {
+            room_manager
+                .post_message(
+                    ROOM,
+                    String::from("bot:bench"),
+                    None,
+                    String::from("a message posted without a session"),
+                )
+                .await
+                .unwrap()
+        })
+    });
+    // the history is full after the appends above
+    group.bench_function("fetch", |b| {
+        b.to_async(&runtime)
+            .iter(|| async { room_manager.get_room_history(&handle).await.unwrap() })
+    });
+
+    group.finish();
+}
+
+// users opening and closing a room, e.g. while switching between rooms
+fn bench_join_leave_churn(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("join_leave_churn");
+
+    for participant_count in [0, 100] {
+        let room_manager = room_manager();
+        let participants = runtime.block_on(async {
+            let mut participants = Vec::new();
+            for idx in 0..participant_count {
+                participants.push(join(&room_manager, &session(idx)).await);
+            }
+
+            participants
+        });
+        let churner = session(participant_count);
+
+        group.bench_function(BenchmarkId::from_parameter(participant_count), |b| {
+            b.to_async(&runtime).iter(|| async {
+                let (_, handle) = join(&room_manager, &churner).await;
+                room_manager.drop_user_session_handle(handle).await.unwrap();
+            })
+        });
+
+        drop(participants);
+    }
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_event_serialization,
+    bench_broadcast_fan_out,
+    bench_history,
+    bench_join_leave_churn
+);
+criterion_main!(benches);
@@ -1,8 +1,9 @@
 use std::time::Duration;
 
 use comms::{
-    command::{JoinRoomCommand, UserCommand},
-    event::Event,
+    command::{JoinRoomCommand, LoginCommand, UserCommand},
+    event::{ContentType, Event},
+    handshake::Compression,
     transport,
 };
 use nanoid::nanoid;
@@ -11,12 +12,12 @@ use serde::{Deserialize, Serialize};
 use tokio::{net::TcpStream, task::JoinSet};
 use tokio_stream::StreamExt;
 
-/// Stress Test for the Chat Server
-///
-/// Generates synthetic load with users who joins and sends messages to random roms.
-/// The number of users, number of rooms joined per user and chatting of users can be configured.
-///
-/// !IMPORTANT! Be sure to check and configure your socket limits, before you run the tests
+// Stress Test for the Chat Server
+//
+// Generates synthetic load with users who joins and sends messages to random roms.
+// The number of users, number of rooms joined per user and chatting of users can be configured.
+//
+// !IMPORTANT! Be sure to check and configure your socket limits, before you run the tests
 
 const SERVER_ADDR: &str = "localhost:8080";
 const CHAT_ROOMS_METADATA: &str = include_str!("../resources/chat_rooms_metadata.json");
@@ -73,7 +74,7 @@ async fn spawn_single_user(rooms_to_join: Vec<String>) -> anyhow::Result<()> {
 
     match result.as_ref() {
         Ok(_) => println!("exited without problems"),
-        Err(err) => println!("some error occurred = {}", err.to_string()),
+        Err(err) => println!("some error occurred = {}", err),
     }
 
     result
@@ -81,7 +82,16 @@ async fn spawn_single_user(rooms_to_join: Vec<String>) -> anyhow::Result<()> {
 
 async fn spawn_single_user_raw(rooms_to_join: Vec<String>) -> anyhow::Result<()> {
     let tcp_stream = TcpStream::connect(SERVER_ADDR).await?;
-    let (mut event_stream, mut command_writer) = transport::client::split_tcp_stream(tcp_stream);
+    // plain frames keep the results comparable with the outcomes documented in the README
+    let (mut event_stream, mut command_writer) =
+        transport::client::connect_tcp_stream(tcp_stream, &[Compression::None]).await?;
+    command_writer
+        .write(&UserCommand::Login(LoginCommand {
+            username: None,
+            token: None,
+            guest: false,
+        }))
+        .await?;
 
     let _login_event = match event_stream.next().await {
         Some(Ok(Event::LoginSuccessful(login_event))) => login_event,
@@ -91,7 +101,8 @@ async fn spawn_single_user_raw(rooms_to_join: Vec<String>) -> anyhow::Result<()>
     for room_name in rooms_to_join.iter() {
         command_writer
             .write(&UserCommand::JoinRoom(JoinRoomCommand {
-                room: String::from(room_name),
+                room: room_name.clone(),
+                wait_if_full: false,
             }))
             .await?;
     }
@@ -113,8 +124,10 @@ async fn spawn_single_user_raw(rooms_to_join: Vec<String>) -> anyhow::Result<()>
                 let _ = command_writer
                     .write(&UserCommand::SendMessage(
                         comms::command::SendMessageCommand {
-                            room: String::from(room_name),
+                            room: room_name,
                             content: nanoid!(),
+                            content_type: ContentType::Plain,
+                            nonce: None,
                         },
                     ))
                     .await;
@@ -124,7 +137,7 @@ async fn spawn_single_user_raw(rooms_to_join: Vec<String>) -> anyhow::Result<()>
         }
     });
 
-    while let Some(_) = event_stream.next().await {}
+    while event_stream.next().await.is_some() {}
 
     join_handle.abort();
     Ok(())
@@ -134,8 +147,8 @@ async fn spawn_single_user_raw(rooms_to_join: Vec<String>) -> anyhow::Result<()>
 async fn main() {
     let load_increments: Vec<LoadIncrements> =
         serde_json::from_str(LOAD_INCREMENTS).expect("could not parse the load increments");
-    let chat_room_metadata: Vec<ChatRoomMetadata> = serde_json::from_str(CHAT_ROOMS_METADATA)
-        .expect("could not parse the chat rooms metadata");
+    let chat_room_metadata: Vec<ChatRoomMetadata> =
+        serde_json::from_str(CHAT_ROOMS_METADATA).expect("could not parse the chat rooms metadata");
 
     let mut room_iterator = RotatingIterator::new(chat_room_metadata);
     let mut join_set: JoinSet<anyhow::Result<()>> = JoinSet::new();
@@ -164,5 +177,5 @@ async fn main() {
         }
     }
 
-    while let Some(_) = join_set.join_next().await {}
+    while join_set.join_next().await.is_some() {}
 }
@@ -0,0 +1,128 @@
+use std::path::Path;
+
+use anyhow::Context;
+use chrono::Utc;
+use croner::Cron;
+use serde::Deserialize;
+use tokio::sync::broadcast;
+
+use crate::room_manager::RoomManager;
+
+/// An entry of the announcements file
+#[derive(Debug, Deserialize)]
+struct AnnouncementConfig {
+    /// A cron expression with five fields, e.g. `45 8 * * 1-5`, evaluated in UTC
+    schedule: String,
+    /// The messages announced in turn, one per occurrence of the schedule
+    messages: Vec<String>,
+    /// The rooms the messages are announced in, every room if empty
+    #[serde(default)]
+    rooms: Vec<String>,
+}
+
+#[derive(Debug)]
+struct Announcement {
+    schedule: Cron,
+    messages: Vec<String>,
+    rooms: Vec<String>,
+    /// The index of the message announced next, the messages rotate
+    next_message: usize,
+}
+
+/// [Announcements] broadcasts recurring messages to the rooms, e.g. maintenance windows or stand-up reminders
+///
+/// Read from the json file at the `CHAT_ANNOUNCEMENTS_FILE` environment variable, nothing is announced if it is not set.
+#[derive(Debug)]
+pub struct Announcements {
+    announcements: Vec<Announcement>,
+}
+
+impl Announcements {
+    /// Load the announcements from the file given by the environment, checking that their rooms exist
+    pub fn from_env(room_manager: &RoomManager) -> anyhow::Result<Self> {
+        match std::env::var("CHAT_ANNOUNCEMENTS_FILE") {
+            Ok(path) => Self::load(Path::new(&path), room_manager),
+            Err(_) => Ok(Announcements {
+                announcements: Vec::new(),
+            }),
+        }
+    }
+
+    fn load(path: &Path, room_manager: &RoomManager) -> anyhow::Result<Self> {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("could not read the announcements {:?}", path))?;
+        let configs: Vec<AnnouncementConfig> = serde_json::from_slice(&bytes)
+            .with_context(|| format!("could not parse the announcements {:?}", path))?;
+
+        let announcements = configs
+            .into_iter()
+            .map(|config| {
+                let schedule = Cron::new(&config.schedule)
+                    .parse()
+                    .with_context(|| format!("invalid schedule '{}'", config.schedule))?;
+
+                anyhow::ensure!(
+                    !config.messages.is_empty(),
+                    "the announcement scheduled at '{}' has no messages",
+                    config.schedule
+                );
+                if let Some(room) = config.rooms.iter().find(|room| {
+                    !room_manager
+                        .chat_room_metadata()
+                        .iter()
+                        .any(|metadata| &metadata.name == *room)
+                }) {
+                    anyhow::bail!("there is no room named '{}' to announce in", room);
+                }
+
+                Ok(Announcement {
+                    schedule,
+                    messages: config.messages,
+                    rooms: config.rooms,
+                    next_message: 0,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Announcements { announcements })
+    }
+
+    /// Announce the messages as their schedules come up until the server shuts down
+    pub async fn announce_until_quit(
+        mut self,
+        room_manager: &RoomManager,
+        mut quit_rx: broadcast::Receiver<()>,
+    ) {
+        loop {
+            let now = Utc::now();
+            let occurrences = self
+                .announcements
+                .iter()
+                .map(|announcement| announcement.schedule.find_next_occurrence(&now, false).ok())
+                .collect::<Vec<_>>();
+
+            // nothing is ever going to be announced, e.g. there are no announcements at all
+            let Some(due_at) = occurrences.iter().flatten().min().copied() else {
+                return;
+            };
+
+            let delay = (due_at - now).to_std().unwrap_or_default();
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = quit_rx.recv() => break,
+            }
+
+            // announcements with the same schedule are all announced at once
+            for (announcement, occurrence) in self.announcements.iter_mut().zip(occurrences) {
+                if occurrence != Some(due_at) {
+                    continue;
+                }
+
+                let message = &announcement.messages[announcement.next_message];
+                room_manager.announce(&announcement.rooms, message).await;
+                announcement.next_message =
+                    (announcement.next_message + 1) % announcement.messages.len();
+            }
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::RwLock,
+    time::Duration,
+};
+
+use comms::event::{self, Event};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, Mutex, Notify};
+
+use crate::{clock::unix_millis_now, context::ServerContext, logging, storage::Storage};
+
+/// The name of the storage document the archived rooms are persisted in
+const ARCHIVE_DOCUMENT: &str = "archived_rooms";
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct ArchiveState {
+    /// The rooms which are read-only, their history is kept
+    archived: BTreeSet<String>,
+    /// The rooms which are archived later, by when they are due in milliseconds since the unix epoch
+    scheduled: BTreeMap<String, u64>,
+}
+
+/// What reopening a room has changed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReopenOutcome {
+    /// The room was archived and takes messages again
+    Reopened,
+    /// The room was not archived yet, its scheduled archival has been called off
+    ScheduleCancelled,
+    /// The room was neither archived nor scheduled to be
+    NotArchived,
+}
+
+/// [RoomArchive] keeps which rooms are archived, or are going to be, and archives them once they are due
+///
+/// Archived rooms are read-only; users can still join them and read their history but not send messages.
+/// Every change is persisted to the [Storage], hence archived and scheduled rooms survive server restarts.
+#[derive(Debug)]
+pub struct RoomArchive {
+    storage: Storage,
+    // held while the state is saved, so the changes are persisted in the order they are made
+    state: Mutex<ArchiveState>,
+    // a copy of the archived rooms once they are saved, the interceptors check it synchronously
+    archived: RwLock<BTreeSet<String>>,
+    changed: Notify,
+}
+
+impl RoomArchive {
+    /// Load the archived and scheduled rooms from the storage
+    pub async fn load(storage: Storage) -> anyhow::Result<Self> {
+        let state: ArchiveState = storage.load(ARCHIVE_DOCUMENT).await?;
+
+        Ok(RoomArchive {
+            storage,
+            archived: RwLock::new(state.archived.clone()),
+            state: Mutex::new(state),
+            changed: Notify::new(),
+        })
+    }
+
+    pub fn is_archived(&self, room: &str) -> bool {
+        self.archived.read().unwrap().contains(room)
+    }
+
+    /// Archive a room right away, calling off its scheduled archival
+    /// Returns false if the room was archived already
+    pub async fn archive(&self, room: &str) -> anyhow::Result<bool> {
+        let mut state = self.state.lock().await;
+        state.scheduled.remove(room);
+        if !state.archived.insert(String::from(room)) {
+            return Ok(false);
+        }
+        self.save(&state).await?;
+
+        Ok(true)
+    }
+
+    /// Archive a room once the given time has come, replacing the time it was scheduled for before
+    pub async fn schedule(&self, room: &str, archive_at: u64) -> anyhow::Result<()> {
+        let mut state = self.state.lock().await;
+        state.scheduled.insert(String::from(room), archive_at);
+        self.save(&state).await?;
+
+        self.changed.notify_one();
+
+        Ok(())
+    }
+
+    /// Let the room take messages again, or call off its scheduled archival
+    pub async fn reopen(&self, room: &str) -> anyhow::Result<ReopenOutcome> {
+        let mut state = self.state.lock().await;
+        let outcome = if state.archived.remove(room) {
+            state.scheduled.remove(room);
+            ReopenOutcome::Reopened
+        } else if state.scheduled.remove(room).is_some() {
+            ReopenOutcome::ScheduleCancelled
+        } else {
+            return Ok(ReopenOutcome::NotArchived);
+        };
+        self.save(&state).await?;
+
+        Ok(outcome)
+    }
+
+    /// Persist the state, then let the interceptors see the rooms it archives
+    async fn save(&self, state: &ArchiveState) -> anyhow::Result<()> {
+        self.storage.save(ARCHIVE_DOCUMENT, state).await?;
+        *self.archived.write().unwrap() = state.archived.clone();
+
+        Ok(())
+    }
+
+    /// Archive the scheduled rooms as they become due until the server shuts down
+    /// The users who can access a room are told once it is archived
+    pub async fn archive_until_quit(
+        &self,
+        context: &ServerContext,
+        mut quit_rx: broadcast::Receiver<()>,
+    ) {
+        loop {
+            let next_due_at = match self.archive_due(context).await {
+                Ok(next_due_at) => next_due_at,
+                Err(err) => {
+                    logging::error(format!("could not archive the scheduled rooms: {:?}", err));
+                    None
+                }
+            };
+
+            let sleep = async {
+                match next_due_at {
+                    Some(due_at) => {
+                        let delay = due_at.saturating_sub(unix_millis_now());
+                        tokio::time::sleep(Duration::from_millis(delay)).await
+                    }
+                    None => std::future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                _ = sleep => {}
+                _ = self.changed.notified() => {}
+                _ = quit_rx.recv() => break,
+            }
+        }
+    }
+
+    /// Archive the rooms which are due
+    /// Returns when the next scheduled room is due, if there is any
+    async fn archive_due(&self, context: &ServerContext) -> anyhow::Result<Option<u64>> {
+        let now = unix_millis_now();
+        let due_rooms = self
+            .state
+            .lock()
+            .await
+            .scheduled
+            .iter()
+            .filter(|(_, due_at)| **due_at <= now)
+            .map(|(room, _)| room.clone())
+            .collect::<Vec<_>>();
+
+        for room in due_rooms {
+            if self.archive(&room).await? {
+                let event =
+                    Event::RoomArchived(event::RoomArchivedBroadcastEvent { room: room.clone() });
+                context.notify_room_audience(&room, &event).await;
+            }
+        }
+
+        Ok(self.state.lock().await.scheduled.values().min().copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::task::JoinSet;
+
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_changes_are_all_persisted() {
+        let storage =
+            Storage::new(std::env::temp_dir().join(format!("chat-archive-{}", nanoid::nanoid!())))
+                .unwrap();
+        let room_archive = Arc::new(RoomArchive::load(storage.clone()).await.unwrap());
+
+        let mut changes = JoinSet::new();
+        for idx in 0..10 {
+            let room_archive = Arc::clone(&room_archive);
+            changes.spawn(async move {
+                let room = format!("room-{}", idx);
+                if idx % 2 == 0 {
+                    room_archive.archive(&room).await.unwrap();
+                } else {
+                    room_archive.schedule(&room, u64::MAX).await.unwrap();
+                }
+            });
+        }
+        while let Some(change) = changes.join_next().await {
+            change.unwrap();
+        }
+
+        let reloaded = RoomArchive::load(storage).await.unwrap();
+        let state = reloaded.state.lock().await;
+        assert_eq!(state.archived.len(), 5);
+        assert_eq!(state.scheduled.len(), 5);
+        assert!(reloaded.is_archived("room-0"));
+        assert!(!reloaded.is_archived("room-1"));
+    }
+}
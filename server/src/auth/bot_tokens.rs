@@ -0,0 +1,339 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use comms::{
+    command::BotScope,
+    event::{self, Event, SessionTerminationCause},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::{broadcast, Mutex};
+
+use crate::{clock::unix_millis_now, logging, session::SessionRegistry, storage::Storage};
+
+/// The name of the storage document the bot tokens are persisted in
+const BOT_TOKENS_DOCUMENT: &str = "bot_tokens";
+/// Tells bot tokens apart from the tokens of the identity provider
+pub const BOT_TOKEN_PREFIX: &str = "bot_";
+/// Prefixed to the name of a bot to build its user id, keeping bots apart from human accounts
+const BOT_USER_ID_PREFIX: &str = "bot:";
+/// A token is refreshed once less than this fraction of its lifetime is left, e.g. the last fifth
+const REFRESH_WINDOW_DIVISOR: u32 = 5;
+/// The longest the server waits between looking for tokens to refresh or to expire
+const MAX_REFRESH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A bot account, only the hash of its token is kept
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BotAccount {
+    bot_name: String,
+    token_hash: String,
+    scope: BotScope,
+    /// When the token expires, in milliseconds since the unix epoch, None if it never expires
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at: Option<u64>,
+    /// The token replaced by the last refresh, it keeps working until it expires in case the bot missed the new one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    previous_token: Option<PreviousToken>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PreviousToken {
+    token_hash: String,
+    expires_at: u64,
+}
+
+impl BotAccount {
+    fn accepts(&self, token_hash: &str, now: u64) -> bool {
+        let is_current = self.token_hash == token_hash
+            && self.expires_at.is_none_or(|expires_at| now < expires_at);
+        let is_previous = self.previous_token.as_ref().is_some_and(|previous_token| {
+            previous_token.token_hash == token_hash && now < previous_token.expires_at
+        });
+
+        is_current || is_previous
+    }
+}
+
+/// A token as handed out to a bot, the server only keeps its hash
+#[derive(Debug, Clone, PartialEq)]
+pub struct IssuedToken {
+    pub token: String,
+    /// When the token expires, in milliseconds since the unix epoch, None if it never expires
+    pub expires_at: Option<u64>,
+}
+
+/// [BotTokens] keeps the credentials of the bot accounts issued by admins
+///
+/// Tokens never expire unless `CHAT_BOT_TOKEN_TTL` gives their lifetime in seconds. Expiring tokens of connected
+/// bots are refreshed ahead of time and pushed to their sessions, sessions whose token expires anyway are closed.
+#[derive(Debug)]
+pub struct BotTokens {
+    storage: Storage,
+    bot_accounts: Mutex<Vec<BotAccount>>,
+    ttl: Option<Duration>,
+}
+
+/// The lifetime of the bot tokens from the `CHAT_BOT_TOKEN_TTL` environment variable, in seconds
+pub(super) fn ttl_from_env() -> anyhow::Result<Option<Duration>> {
+    std::env::var("CHAT_BOT_TOKEN_TTL")
+        .ok()
+        .map(|ttl| {
+            ttl.trim()
+                .parse()
+                .ok()
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+                .with_context(|| format!("invalid bot token lifetime '{}'", ttl))
+        })
+        .transpose()
+}
+
+fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// The user id a bot acts as
+pub fn bot_user_id(bot_name: &str) -> String {
+    format!("{}{}", BOT_USER_ID_PREFIX, bot_name)
+}
+
+impl BotTokens {
+    /// Load the bot accounts from the storage, the tokens issued from now on expire after the given lifetime
+    pub async fn load(storage: Storage, ttl: Option<Duration>) -> anyhow::Result<Self> {
+        let bot_accounts = storage.load(BOT_TOKENS_DOCUMENT).await?;
+
+        Ok(BotTokens {
+            storage,
+            bot_accounts: Mutex::new(bot_accounts),
+            ttl,
+        })
+    }
+
+    fn new_token(&self) -> IssuedToken {
+        IssuedToken {
+            token: format!("{}{}", BOT_TOKEN_PREFIX, nanoid::nanoid!(32)),
+            expires_at: self
+                .ttl
+                .map(|ttl| unix_millis_now() + ttl.as_millis() as u64),
+        }
+    }
+
+    /// Issue a new token for the bot, replacing its previous token if there is any
+    /// Returns the token, which can not be recovered later on
+    pub async fn issue(&self, bot_name: &str, scope: BotScope) -> anyhow::Result<IssuedToken> {
+        let issued_token = self.new_token();
+        let mut bot_accounts = self.bot_accounts.lock().await;
+
+        bot_accounts.retain(|bot_account| bot_account.bot_name != bot_name);
+        bot_accounts.push(BotAccount {
+            bot_name: String::from(bot_name),
+            token_hash: hash_token(&issued_token.token),
+            scope,
+            expires_at: issued_token.expires_at,
+            previous_token: None,
+        });
+        self.storage
+            .save(BOT_TOKENS_DOCUMENT, &*bot_accounts)
+            .await?;
+
+        Ok(issued_token)
+    }
+
+    /// Replace the token of the bot with a new one, the current token keeps working until it expires
+    /// Returns None if the bot has no token or its token never expires
+    async fn refresh(&self, bot_name: &str) -> anyhow::Result<Option<IssuedToken>> {
+        let issued_token = self.new_token();
+        let mut bot_accounts = self.bot_accounts.lock().await;
+
+        let Some(bot_account) = bot_accounts
+            .iter_mut()
+            .find(|bot_account| bot_account.bot_name == bot_name)
+        else {
+            return Ok(None);
+        };
+        let (Some(expires_at), Some(_)) = (bot_account.expires_at, issued_token.expires_at) else {
+            return Ok(None);
+        };
+
+        bot_account.previous_token = Some(PreviousToken {
+            token_hash: std::mem::replace(
+                &mut bot_account.token_hash,
+                hash_token(&issued_token.token),
+            ),
+            expires_at,
+        });
+        bot_account.expires_at = issued_token.expires_at;
+        self.storage
+            .save(BOT_TOKENS_DOCUMENT, &*bot_accounts)
+            .await?;
+
+        Ok(Some(issued_token))
+    }
+
+    /// Revoke the token of the bot, returns false if the bot had no token
+    pub async fn revoke(&self, bot_name: &str) -> anyhow::Result<bool> {
+        let mut bot_accounts = self.bot_accounts.lock().await;
+        let count = bot_accounts.len();
+
+        bot_accounts.retain(|bot_account| bot_account.bot_name != bot_name);
+        if bot_accounts.len() == count {
+            return Ok(false);
+        }
+        self.storage
+            .save(BOT_TOKENS_DOCUMENT, &*bot_accounts)
+            .await?;
+
+        Ok(true)
+    }
+
+    /// The names of the bots which have a token
+    pub async fn bot_names(&self) -> Vec<String> {
+        self.bot_accounts
+            .lock()
+            .await
+            .iter()
+            .map(|bot_account| bot_account.bot_name.clone())
+            .collect()
+    }
+
+    /// Find the bot the token was issued to, returns its user id and scope
+    pub async fn find(&self, token: &str) -> Option<(String, BotScope)> {
+        let token_hash = hash_token(token);
+        let now = unix_millis_now();
+
+        self.bot_accounts
+            .lock()
+            .await
+            .iter()
+            .find(|bot_account| bot_account.accepts(&token_hash, now))
+            .map(|bot_account| {
+                (
+                    bot_user_id(&bot_account.bot_name),
+                    bot_account.scope.clone(),
+                )
+            })
+    }
+
+    /// Refresh the tokens of the connected bots before they expire, and close the sessions of the bots whose
+    /// tokens have expired anyway, until the server shuts down
+    pub async fn refresh_until_quit(
+        &self,
+        session_registry: &SessionRegistry,
+        mut quit_rx: broadcast::Receiver<()>,
+    ) {
+        // nothing ever expires
+        let Some(ttl) = self.ttl else {
+            return;
+        };
+        let check_interval = (ttl / (2 * REFRESH_WINDOW_DIVISOR)).min(MAX_REFRESH_CHECK_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(check_interval) => {}
+                _ = quit_rx.recv() => break,
+            }
+
+            if let Err(err) = self.refresh_due(session_registry, ttl).await {
+                logging::error(format!("could not refresh the bot tokens: {:?}", err));
+            }
+        }
+    }
+
+    async fn refresh_due(
+        &self,
+        session_registry: &SessionRegistry,
+        ttl: Duration,
+    ) -> anyhow::Result<()> {
+        let refresh_window = (ttl / REFRESH_WINDOW_DIVISOR).as_millis() as u64;
+        let now = unix_millis_now();
+        let expiring_accounts = self
+            .bot_accounts
+            .lock()
+            .await
+            .iter()
+            .filter_map(|bot_account| {
+                bot_account
+                    .expires_at
+                    .filter(|expires_at| expires_at.saturating_sub(refresh_window) <= now)
+                    .map(|expires_at| (bot_account.bot_name.clone(), expires_at))
+            })
+            .collect::<Vec<_>>();
+
+        for (bot_name, expires_at) in expiring_accounts {
+            let user_id = bot_user_id(&bot_name);
+            let sessions = session_registry.sessions_of(&user_id);
+            if sessions.is_empty() {
+                continue;
+            }
+
+            if expires_at <= now {
+                for session in sessions {
+                    session_registry.terminate(
+                        &user_id,
+                        &session.session_id,
+                        Event::SessionTerminated(event::SessionTerminatedReplyEvent {
+                            reason: String::from("the token of the bot has expired"),
+                            cause: Some(SessionTerminationCause::TokenExpired),
+                        }),
+                    );
+                }
+            } else if let Some(issued_token) = self.refresh(&bot_name).await? {
+                session_registry.send_to_user(
+                    &user_id,
+                    &Event::TokenRefreshed(event::TokenRefreshedReplyEvent {
+                        token: issued_token.token,
+                        expires_at: issued_token.expires_at.unwrap_or_default(),
+                    }),
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn bot_tokens(ttl: Option<Duration>) -> BotTokens {
+        let storage =
+            Storage::new(std::env::temp_dir().join(format!("chat-bots-{}", nanoid::nanoid!())))
+                .unwrap();
+
+        BotTokens::load(storage, ttl).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_refreshed_tokens_keep_the_previous_one_working_until_it_expires() {
+        let bot_tokens = bot_tokens(Some(Duration::from_secs(60))).await;
+        let issued_token = bot_tokens.issue("ci", BotScope::default()).await.unwrap();
+        let refreshed_token = bot_tokens.refresh("ci").await.unwrap().unwrap();
+
+        assert!(refreshed_token.expires_at >= issued_token.expires_at);
+        assert!(bot_tokens.find(&issued_token.token).await.is_some());
+        assert!(bot_tokens.find(&refreshed_token.token).await.is_some());
+
+        // a second refresh drops the first token
+        bot_tokens.refresh("ci").await.unwrap();
+        assert!(bot_tokens.find(&issued_token.token).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_expired_tokens_are_refused() {
+        let bot_tokens = bot_tokens(Some(Duration::from_secs(60))).await;
+        let issued_token = bot_tokens.issue("ci", BotScope::default()).await.unwrap();
+        bot_tokens.bot_accounts.lock().await[0].expires_at = Some(unix_millis_now() - 1);
+
+        assert!(bot_tokens.find(&issued_token.token).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tokens_without_lifetime_are_not_refreshed() {
+        let bot_tokens = bot_tokens(None).await;
+        let issued_token = bot_tokens.issue("ci", BotScope::default()).await.unwrap();
+
+        assert_eq!(issued_token.expires_at, None);
+        assert_eq!(bot_tokens.refresh("ci").await.unwrap(), None);
+    }
+}
@@ -0,0 +1,161 @@
+use anyhow::Context;
+use comms::command::BotScope;
+
+use crate::storage::Storage;
+
+pub use self::bot_tokens::{bot_user_id, BotTokens, IssuedToken};
+use self::{bot_tokens::BOT_TOKEN_PREFIX, oidc::OidcValidator};
+
+mod bot_tokens;
+mod oidc;
+
+/// The claim used as the user id unless `CHAT_OIDC_USER_CLAIM` says otherwise
+const DEFAULT_USER_ID_CLAIM: &str = "sub";
+
+/// The prefix of the user ids given to guests, followed by a few random characters
+pub const GUEST_USER_ID_PREFIX: &str = "guest-";
+/// The characters following the prefix of a guest user id
+const GUEST_ID_ALPHABET: [char; 36] = [
+    'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's',
+    't', 'u', 'v', 'w', 'x', 'y', 'z', '0', '1', '2', '3', '4', '5', '6', '7', '8', '9',
+];
+
+/// [IdentityProvider] decides who the human users are
+#[derive(Debug)]
+pub enum IdentityProvider {
+    /// Users chat as the name they pick at login, or are given a random user id if they do not pick one
+    Anonymous,
+    /// Users present an id token issued by an OIDC provider, the user id is taken from its claims
+    Oidc(OidcValidator),
+}
+
+impl IdentityProvider {
+    /// Build the identity provider from the environment
+    ///
+    /// OIDC is enabled by setting `CHAT_OIDC_ISSUER`, which requires `CHAT_OIDC_AUDIENCE` as well.
+    /// `CHAT_OIDC_USER_CLAIM` selects the claim the user id is taken from, `sub` by default.
+    fn from_env() -> anyhow::Result<Self> {
+        let Ok(issuer) = std::env::var("CHAT_OIDC_ISSUER") else {
+            return Ok(IdentityProvider::Anonymous);
+        };
+        let audience = std::env::var("CHAT_OIDC_AUDIENCE")
+            .context("CHAT_OIDC_AUDIENCE must be set along with CHAT_OIDC_ISSUER")?;
+        let user_id_claim = std::env::var("CHAT_OIDC_USER_CLAIM")
+            .unwrap_or_else(|_| String::from(DEFAULT_USER_ID_CLAIM));
+
+        Ok(IdentityProvider::Oidc(OidcValidator::new(
+            issuer,
+            audience,
+            user_id_claim,
+        )))
+    }
+}
+
+/// Whether guests may log in, `CHAT_GUEST_LOGIN` is either `true` or `false`, guests are not allowed if it is not set
+fn guests_from_env() -> anyhow::Result<bool> {
+    let Ok(guest_login) = std::env::var("CHAT_GUEST_LOGIN") else {
+        return Ok(false);
+    };
+
+    match guest_login.trim().to_lowercase().as_str() {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => anyhow::bail!(
+            "unknown guest login setting '{}', expected true or false",
+            guest_login
+        ),
+    }
+}
+
+/// [Identity] is who a connection belongs to
+#[derive(Debug, Clone)]
+pub struct Identity {
+    pub user_id: String,
+    /// The capabilities of the account if it is a bot, humans are not limited
+    pub bot_scope: Option<BotScope>,
+    /// Whether the user is trying the server as a guest, guests are restricted and forgotten once they disconnect
+    pub is_guest: bool,
+}
+
+/// [Authenticator] decides which user or bot a new connection belongs to
+#[derive(Debug)]
+pub struct Authenticator {
+    identity_provider: IdentityProvider,
+    pub bot_tokens: BotTokens,
+    /// Whether users may log in as guests without an account, read from `CHAT_GUEST_LOGIN`
+    pub allows_guests: bool,
+}
+
+impl Authenticator {
+    /// Build the authenticator from the environment and the bot tokens persisted in the storage
+    pub async fn load(storage: Storage) -> anyhow::Result<Self> {
+        Ok(Authenticator {
+            identity_provider: IdentityProvider::from_env()?,
+            bot_tokens: BotTokens::load(storage, bot_tokens::ttl_from_env()?).await?,
+            allows_guests: guests_from_env()?,
+        })
+    }
+
+    /// Whether the users chat as the name they pick at login, instead of the one their token is issued for
+    pub fn takes_usernames(&self) -> bool {
+        matches!(self.identity_provider, IdentityProvider::Anonymous)
+    }
+
+    /// Returns the identity the connection belongs to, given the token and the name the user has picked
+    /// Fails with a reason which is safe to show to the user if they can not be authenticated
+    pub async fn authenticate(
+        &self,
+        bearer_token: Option<&str>,
+        username: Option<&str>,
+    ) -> anyhow::Result<Identity> {
+        if let Some(bot_token) = bearer_token.filter(|token| token.starts_with(BOT_TOKEN_PREFIX)) {
+            let (user_id, bot_scope) = self
+                .bot_tokens
+                .find(bot_token)
+                .await
+                .context("the bot token is not valid")?;
+
+            return Ok(Identity {
+                user_id,
+                bot_scope: Some(bot_scope),
+                is_guest: false,
+            });
+        }
+
+        let user_id = match &self.identity_provider {
+            // Generate a random id for the user if they have not picked a name
+            IdentityProvider::Anonymous => match username {
+                Some(username) => String::from(username),
+                None => String::from(&nanoid::nanoid!()[0..5]),
+            },
+            IdentityProvider::Oidc(validator) => {
+                let bearer_token = bearer_token
+                    .context("the server requires a token from its identity provider")?;
+
+                validator.validate(bearer_token).await?
+            }
+        };
+
+        Ok(Identity {
+            user_id,
+            bot_scope: None,
+            is_guest: false,
+        })
+    }
+
+    /// Returns a new guest identity with a random user id, e.g. `guest-x7k2`
+    /// Fails with a reason which is safe to show to the user if the server does not allow guests
+    pub fn authenticate_guest(&self) -> anyhow::Result<Identity> {
+        anyhow::ensure!(self.allows_guests, "the server does not allow guests");
+
+        Ok(Identity {
+            user_id: format!(
+                "{}{}",
+                GUEST_USER_ID_PREFIX,
+                nanoid::nanoid!(4, &GUEST_ID_ALPHABET)
+            ),
+            bot_scope: None,
+            is_guest: true,
+        })
+    }
+}
@@ -0,0 +1,148 @@
+use std::{collections::HashMap, str::FromStr, time::Duration};
+
+use anyhow::Context;
+use jsonwebtoken::{
+    jwk::{Jwk, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
+use serde::Deserialize;
+use tokio::{sync::RwLock, time::Instant};
+
+/// How long the keys are trusted to be complete before a token with an unknown key may fetch them again
+/// Otherwise anyone could make the server hit the identity provider with every login attempt
+const MIN_JWKS_REFETCH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The part of the OIDC discovery document the server needs
+#[derive(Debug, Deserialize)]
+struct ProviderMetadata {
+    jwks_uri: String,
+}
+
+/// The signing keys of the issuer along with when they were last fetched
+#[derive(Debug)]
+struct SigningKeys {
+    jwks: JwkSet,
+    fetched_at: Option<Instant>,
+}
+
+/// [OidcValidator] validates id tokens against the signing keys published by the issuer
+///
+/// The keys are fetched lazily and fetched again when a token is signed with an unknown key,
+/// hence key rotations of the issuer are picked up without a restart.
+/// The keys are fetched at most once every [MIN_JWKS_REFETCH_INTERVAL] though.
+#[derive(Debug)]
+pub struct OidcValidator {
+    issuer: String,
+    audience: String,
+    user_id_claim: String,
+    http_client: reqwest::Client,
+    signing_keys: RwLock<SigningKeys>,
+}
+
+impl OidcValidator {
+    pub fn new(issuer: String, audience: String, user_id_claim: String) -> Self {
+        OidcValidator {
+            issuer,
+            audience,
+            user_id_claim,
+            http_client: reqwest::Client::new(),
+            signing_keys: RwLock::new(SigningKeys {
+                jwks: JwkSet { keys: Vec::new() },
+                fetched_at: None,
+            }),
+        }
+    }
+
+    /// Fetch the signing keys of the issuer through its discovery document
+    async fn fetch_jwks(&self) -> anyhow::Result<JwkSet> {
+        let discovery_url = format!(
+            "{}/.well-known/openid-configuration",
+            self.issuer.trim_end_matches('/')
+        );
+        let provider_metadata: ProviderMetadata = self
+            .http_client
+            .get(&discovery_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .context("could not read the discovery document of the identity provider")?;
+
+        self.http_client
+            .get(&provider_metadata.jwks_uri)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .context("could not read the signing keys of the identity provider")
+    }
+
+    /// Find the signing key with the given id, fetching the keys again if it is not known yet
+    /// and they were not fetched recently
+    async fn find_key(&self, kid: &str) -> anyhow::Result<Jwk> {
+        if let Some(jwk) = self.signing_keys.read().await.jwks.find(kid) {
+            return Ok(jwk.clone());
+        }
+
+        let mut signing_keys = self.signing_keys.write().await;
+        // the keys may have been fetched while waiting for the lock
+        if let Some(jwk) = signing_keys.jwks.find(kid) {
+            return Ok(jwk.clone());
+        }
+        let fetched_recently = signing_keys
+            .fetched_at
+            .is_some_and(|fetched_at| fetched_at.elapsed() < MIN_JWKS_REFETCH_INTERVAL);
+        if !fetched_recently {
+            // a failed fetch counts as well, an unreachable provider is not asked again right away
+            signing_keys.fetched_at = Some(Instant::now());
+            signing_keys.jwks = self.fetch_jwks().await?;
+        }
+
+        signing_keys
+            .jwks
+            .find(kid)
+            .cloned()
+            .context("the token is signed with an unknown key")
+    }
+
+    /// Validate the signature, issuer, audience and expiry of the token
+    /// Returns the user id taken from the configured claim
+    pub async fn validate(&self, token: &str) -> anyhow::Result<String> {
+        let header = jsonwebtoken::decode_header(token).context("the token is malformed")?;
+        let kid = header
+            .kid
+            .context("the token does not name its signing key")?;
+        let jwk = self.find_key(&kid).await?;
+
+        // never let the token pick a different algorithm than the one its key is meant for
+        if let Some(key_algorithm) = jwk.common.key_algorithm {
+            // keys meant for encryption have no signing algorithm, and never match
+            let key_algorithm = Algorithm::from_str(&key_algorithm.to_string()).ok();
+            if key_algorithm != Some(header.alg) {
+                return Err(anyhow::anyhow!(
+                    "the token is signed with an unexpected algorithm"
+                ));
+            }
+        }
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.audience]);
+
+        let claims = jsonwebtoken::decode::<HashMap<String, serde_json::Value>>(
+            token,
+            &DecodingKey::from_jwk(&jwk)?,
+            &validation,
+        )
+        .context("the token is not valid")?
+        .claims;
+
+        claims
+            .get(&self.user_id_claim)
+            .and_then(|user_id| user_id.as_str())
+            .map(String::from)
+            .with_context(|| format!("the token has no '{}' claim", self.user_id_claim))
+    }
+}
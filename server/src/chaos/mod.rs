@@ -0,0 +1,254 @@
+use std::{collections::VecDeque, time::Duration};
+
+use anyhow::Context;
+use rand::Rng;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::logging;
+
+/// The longest an event is held back by a delay
+const MAX_DELAY: Duration = Duration::from_millis(1500);
+/// The longest a reordered event waits for the next one to overtake it, it is delivered on its own afterwards
+const MAX_HOLD_BACK: Duration = Duration::from_millis(500);
+/// The most events skipped by an injected lag
+const MAX_LAGGED_EVENTS: u64 = 5;
+
+/// What happens to an event on its way to a participant
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Fault {
+    /// The event and the ones after it wait for the given time
+    Delay(Duration),
+    /// The event is delivered after the next one
+    Reorder,
+    /// The event is never delivered
+    Drop,
+    /// Up to the given number of events are skipped and the participant is told it has lagged behind
+    Lag(u64),
+}
+
+/// [Chaos] disturbs the delivery of the events of the rooms, to exercise the gap detection and the resync of clients
+///
+/// Meant for development only and left out of the documentation. Turned on by the `CHAT_CHAOS` environment
+/// variable, a comma separated list of faults and the chance of injecting them into the delivery of an event,
+/// e.g. `CHAT_CHAOS=delay=0.1,reorder=0.05,drop=0.02,lag=0.01`:
+/// - `delay` - the event and the ones after it are held back for up to 1.5 seconds
+/// - `reorder` - the event swaps places with the next one, unless none arrives within half a second
+/// - `drop` - the event is never delivered
+/// - `lag` - up to 5 events are skipped as if the participant could not keep up, the lag policy of the room applies
+///
+/// Every participant is disturbed on their own, the events are left alone in the history of the room.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Chaos {
+    delay: f64,
+    reorder: f64,
+    drop: f64,
+    lag: f64,
+}
+
+impl Chaos {
+    /// Read the faults from the environment, nothing is disturbed unless it lists some
+    pub fn from_env() -> anyhow::Result<Self> {
+        let Ok(faults) = std::env::var("CHAT_CHAOS") else {
+            return Ok(Chaos::default());
+        };
+
+        let chaos = Self::parse(&faults)?;
+        if chaos.is_enabled() {
+            logging::warn(format!(
+                "Injecting faults into the delivery of the room events: {}",
+                faults.trim()
+            ));
+        }
+
+        Ok(chaos)
+    }
+
+    fn parse(faults: &str) -> anyhow::Result<Self> {
+        faults
+            .split(',')
+            .map(str::trim)
+            .filter(|fault| !fault.is_empty())
+            .try_fold(Chaos::default(), |mut chaos, fault| {
+                let (name, chance) = fault.split_once('=').with_context(|| {
+                    format!("invalid fault '{}', expected <fault>=<chance>", fault)
+                })?;
+                let chance = chance
+                    .trim()
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|chance| (0.0..=1.0).contains(chance))
+                    .with_context(|| {
+                        format!("invalid chance '{}', expected 0 to 1", chance.trim())
+                    })?;
+
+                match name.trim() {
+                    "delay" => chaos.delay = chance,
+                    "reorder" => chaos.reorder = chance,
+                    "drop" => chaos.drop = chance,
+                    "lag" => chaos.lag = chance,
+                    name => anyhow::bail!(
+                        "unknown fault '{}', expected delay, reorder, drop or lag",
+                        name
+                    ),
+                }
+
+                Ok(chaos)
+            })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.delay > 0.0 || self.reorder > 0.0 || self.drop > 0.0 || self.lag > 0.0
+    }
+
+    /// Disturb the events received from the room, they are received as they are if no fault is configured
+    pub fn disturb<T: Clone>(&self, rx: broadcast::Receiver<T>) -> ChaoticReceiver<T> {
+        ChaoticReceiver {
+            rx,
+            chaos: *self,
+            held_back: None,
+            ready: VecDeque::new(),
+        }
+    }
+
+    /// The fault injected into the delivery of the next event, None if it is delivered as it is
+    fn next_fault(&self) -> Option<Fault> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let mut rng = rand::thread_rng();
+        if rng.gen_bool(self.lag) {
+            Some(Fault::Lag(rng.gen_range(1..=MAX_LAGGED_EVENTS)))
+        } else if rng.gen_bool(self.drop) {
+            Some(Fault::Drop)
+        } else if rng.gen_bool(self.reorder) {
+            Some(Fault::Reorder)
+        } else if rng.gen_bool(self.delay) {
+            Some(Fault::Delay(rng.gen_range(Duration::ZERO..=MAX_DELAY)))
+        } else {
+            None
+        }
+    }
+}
+
+/// [ChaoticReceiver] receives the events of a room like a [broadcast::Receiver], with the faults of the [Chaos]
+pub struct ChaoticReceiver<T> {
+    rx: broadcast::Receiver<T>,
+    chaos: Chaos,
+    /// The event waiting for the next one to overtake it
+    held_back: Option<T>,
+    /// The events to deliver before receiving more
+    ready: VecDeque<T>,
+}
+
+impl<T: Clone> ChaoticReceiver<T> {
+    pub async fn recv(&mut self) -> Result<T, RecvError> {
+        loop {
+            if let Some(event) = self.ready.pop_front() {
+                return Ok(event);
+            }
+
+            let event = match self.held_back.take() {
+                Some(held_back) => {
+                    match tokio::time::timeout(MAX_HOLD_BACK, self.rx.recv()).await {
+                        Ok(Ok(event)) => {
+                            self.ready.push_back(held_back);
+                            return Ok(event);
+                        }
+                        Ok(Err(err)) => {
+                            self.ready.push_back(held_back);
+                            return Err(err);
+                        }
+                        Err(_) => return Ok(held_back),
+                    }
+                }
+                None => self.rx.recv().await?,
+            };
+
+            match self.chaos.next_fault() {
+                None => return Ok(event),
+                Some(Fault::Delay(delay)) => {
+                    tokio::time::sleep(delay).await;
+                    return Ok(event);
+                }
+                Some(Fault::Reorder) => self.held_back = Some(event),
+                Some(Fault::Drop) => {}
+                Some(Fault::Lag(max_missed_events)) => {
+                    // the event is missed along with the ones already waiting, as if the channel had overflown
+                    let mut missed_events = 1;
+                    while missed_events < max_missed_events && self.rx.try_recv().is_ok() {
+                        missed_events += 1;
+                    }
+
+                    return Err(RecvError::Lagged(missed_events));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_faults() {
+        let chaos = Chaos::parse(" delay=0.1, drop=1 ,").unwrap();
+
+        assert_eq!(
+            chaos,
+            Chaos {
+                delay: 0.1,
+                drop: 1.0,
+                ..Default::default()
+            }
+        );
+        assert!(chaos.is_enabled());
+        assert!(!Chaos::parse("").unwrap().is_enabled());
+        assert!(Chaos::parse("drop=2").is_err());
+        assert!(Chaos::parse("drop").is_err());
+        assert!(Chaos::parse("explode=0.5").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_events_pass_undisturbed_without_faults() {
+        let (tx, rx) = broadcast::channel(8);
+        let mut rx = Chaos::default().disturb(rx);
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+
+        assert_eq!(rx.recv().await, Ok(1));
+        assert_eq!(rx.recv().await, Ok(2));
+    }
+
+    #[tokio::test]
+    async fn test_reordered_events_swap_places() {
+        let (tx, rx) = broadcast::channel(8);
+        let mut rx = Chaos::parse("reorder=1").unwrap().disturb(rx);
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+
+        assert_eq!(rx.recv().await, Ok(2));
+        assert_eq!(rx.recv().await, Ok(1));
+    }
+
+    #[tokio::test]
+    async fn test_injected_lag_skips_events() {
+        let (tx, rx) = broadcast::channel(8);
+        let mut rx = Chaos::parse("lag=1").unwrap().disturb(rx);
+        tx.send(1).unwrap();
+
+        assert_eq!(rx.recv().await, Err(RecvError::Lagged(1)));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_events_never_arrive() {
+        let (tx, rx) = broadcast::channel(8);
+        let mut rx = Chaos::parse("drop=1").unwrap().disturb(rx);
+        tx.send(1).unwrap();
+
+        assert!(tokio::time::timeout(Duration::from_millis(50), rx.recv())
+            .await
+            .is_err());
+    }
+}
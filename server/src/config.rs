@@ -0,0 +1,154 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+
+use anyhow::Context;
+
+/// The port sessions are accepted on, unless overridden by the `PORT` environment variable
+const DEFAULT_PORT: u16 = 8080;
+/// How long the running sessions and background tasks may take to wind down once the server is asked to stop
+const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_CHAT_ROOMS: &str = include_str!("../resources/chat_rooms_metadata.json");
+
+/// [RuntimeConfig] holds how the server itself runs, read from the environment so a container needs no files mounted
+///
+/// - `CHAT_BIND_ADDRESS` - the comma separated addresses sessions are accepted on, `::` by default,
+///   which takes IPv6 and IPv4 connections alike, see [crate::listener::bind]
+/// - `PORT` - the port sessions are accepted on, `8080` by default
+/// - `CHAT_SHUTDOWN_TIMEOUT` - the seconds the server waits for its tasks to wind down on shutdown, `10` by default
+/// - `CHAT_ROOMS` - the json metadata of the rooms, the ones in `resources/chat_rooms_metadata.json` by default
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeConfig {
+    /// The addresses sessions are accepted on, all of them on the same port
+    pub bind_addrs: Vec<SocketAddr>,
+    pub shutdown_timeout: Duration,
+    pub chat_rooms: String,
+}
+
+impl RuntimeConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> anyhow::Result<Self> {
+        let ips = match var("CHAT_BIND_ADDRESS") {
+            Some(ips) => ips
+                .split(',')
+                .map(str::trim)
+                .filter(|ip| !ip.is_empty())
+                .map(|ip| {
+                    ip.parse()
+                        .with_context(|| format!("invalid bind address '{}'", ip))
+                })
+                .collect::<anyhow::Result<Vec<IpAddr>>>()?,
+            None => vec![IpAddr::V6(Ipv6Addr::UNSPECIFIED)],
+        };
+        if ips.is_empty() {
+            anyhow::bail!("no bind address is given");
+        }
+        let port = var("PORT")
+            .map(|port| {
+                port.trim()
+                    .parse()
+                    .with_context(|| format!("invalid port '{}'", port))
+            })
+            .transpose()?
+            .unwrap_or(DEFAULT_PORT);
+        let shutdown_timeout = var("CHAT_SHUTDOWN_TIMEOUT")
+            .map(|secs| {
+                secs.trim()
+                    .parse()
+                    .map(Duration::from_secs)
+                    .with_context(|| format!("invalid shutdown timeout '{}'", secs))
+            })
+            .transpose()?
+            .unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT);
+
+        Ok(RuntimeConfig {
+            bind_addrs: ips
+                .into_iter()
+                .map(|ip| SocketAddr::new(ip, port))
+                .collect(),
+            shutdown_timeout,
+            chat_rooms: var("CHAT_ROOMS").unwrap_or_else(|| String::from(DEFAULT_CHAT_ROOMS)),
+        })
+    }
+
+    /// The address a client on the same host reaches the server at, e.g. for a health check
+    pub fn local_addr(&self) -> SocketAddr {
+        let bind_addr = self.bind_addrs[0];
+        let ip = match bind_addr.ip() {
+            // IPv6 listeners take IPv4 connections as well, and fall back to IPv4 on hosts without IPv6
+            IpAddr::V4(ip) if ip.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(ip) if ip.is_unspecified() && self.bind_addrs.len() == 1 => {
+                IpAddr::V4(Ipv4Addr::LOCALHOST)
+            }
+            IpAddr::V6(ip) if ip.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            ip => ip,
+        };
+
+        SocketAddr::new(ip, bind_addr.port())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn from(vars: &[(&str, &str)]) -> anyhow::Result<RuntimeConfig> {
+        let vars = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<HashMap<_, _>>();
+
+        RuntimeConfig::from_vars(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_defaults_apply_without_environment() {
+        let config = from(&[]).unwrap();
+
+        assert_eq!(config.bind_addrs, vec!["[::]:8080".parse().unwrap()]);
+        assert_eq!(config.local_addr(), "127.0.0.1:8080".parse().unwrap());
+        assert_eq!(config.shutdown_timeout, DEFAULT_SHUTDOWN_TIMEOUT);
+        assert_eq!(config.chat_rooms, DEFAULT_CHAT_ROOMS);
+    }
+
+    #[test]
+    fn test_environment_overrides_the_defaults() {
+        let config = from(&[
+            ("CHAT_BIND_ADDRESS", "::"),
+            ("PORT", "9000"),
+            ("CHAT_SHUTDOWN_TIMEOUT", "30"),
+            ("CHAT_ROOMS", "[]"),
+        ])
+        .unwrap();
+
+        assert_eq!(config.bind_addrs, vec!["[::]:9000".parse().unwrap()]);
+        assert_eq!(config.local_addr(), "127.0.0.1:9000".parse().unwrap());
+        assert_eq!(config.shutdown_timeout, Duration::from_secs(30));
+        assert_eq!(config.chat_rooms, "[]");
+
+        assert!(from(&[("PORT", "http")]).is_err());
+    }
+
+    #[test]
+    fn test_several_bind_addresses_share_the_port() {
+        let config = from(&[("CHAT_BIND_ADDRESS", ":: , 0.0.0.0"), ("PORT", "9000")]).unwrap();
+
+        assert_eq!(
+            config.bind_addrs,
+            vec![
+                "[::]:9000".parse().unwrap(),
+                "0.0.0.0:9000".parse().unwrap()
+            ]
+        );
+        assert_eq!(config.local_addr(), "[::1]:9000".parse().unwrap());
+
+        assert!(from(&[("CHAT_BIND_ADDRESS", "::1,localhost")]).is_err());
+        assert!(from(&[("CHAT_BIND_ADDRESS", ",")]).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::HashSet;
+
+use comms::event::{Event, RoomDetail};
+
+use crate::{
+    archival::RoomArchive,
+    auth::Authenticator,
+    chaos::Chaos,
+    directory::UserDirectory,
+    groups::Groups,
+    invites::RoomInvites,
+    message_filters::MessageFilters,
+    mirror::Mirror,
+    moderation::ModerationQueue,
+    read_markers::ReadMarkers,
+    reminders::Reminders,
+    room_manager::RoomManager,
+    session::{CommandHistory, DuplicateLoginPolicy, SessionRegistry},
+};
+
+/// [ServerContext] bundles the server wide services shared by every user session
+#[derive(Debug)]
+pub struct ServerContext {
+    pub authenticator: Authenticator,
+    pub room_manager: RoomManager,
+    pub session_registry: SessionRegistry,
+    pub reminders: Reminders,
+    pub groups: Groups,
+    pub read_markers: ReadMarkers,
+    pub moderation_queue: ModerationQueue,
+    pub user_directory: UserDirectory,
+    pub room_archive: RoomArchive,
+    pub room_invites: RoomInvites,
+    /// The filters the messages of the rooms pass, e.g. the moderation plugins of the operator
+    pub message_filters: MessageFilters,
+    /// The files some rooms are mirrored to, if any, purging a user removes their messages from them
+    pub mirror: Option<Mirror>,
+    /// The faults injected into the delivery of the room events, to test how clients cope with them
+    pub chaos: Chaos,
+    /// The last commands of every user, for admins looking into misbehaving clients
+    pub command_history: CommandHistory,
+    /// What happens when a user logs in while they already have a session
+    pub duplicate_login_policy: DuplicateLoginPolicy,
+    /// The server the sessions are handed to when this one shuts down, they are closed if there is none
+    pub redirect_addr: Option<String>,
+    /// The ids of the users who are allowed to run administrative commands, e.g. managing groups
+    pub admins: HashSet<String>,
+}
+
+impl ServerContext {
+    pub fn is_admin(&self, user_id: &str) -> bool {
+        self.admins.contains(user_id)
+    }
+
+    /// Returns true if the user may deal with the reports about the room; admins moderate every room, owners their own
+    pub fn is_moderator(&self, room: &str, user_id: &str) -> bool {
+        self.moderators_of(room).contains(user_id)
+    }
+
+    /// Returns true if the user moderates at least one room
+    pub fn is_any_moderator(&self, user_id: &str) -> bool {
+        self.is_admin(user_id)
+            || self
+                .room_manager
+                .chat_room_metadata()
+                .iter()
+                .any(|metadata| metadata.owners.iter().any(|owner| owner == user_id))
+    }
+
+    /// The users who are notified about the reports about the room, the admins and the owners of the room
+    pub fn moderators_of(&self, room: &str) -> HashSet<String> {
+        let owners = self
+            .room_manager
+            .chat_room_metadata()
+            .iter()
+            .filter(|metadata| metadata.name == room)
+            .flat_map(|metadata| metadata.owners.iter().cloned())
+            .collect::<Vec<_>>();
+
+        self.admins.iter().cloned().chain(owners).collect()
+    }
+
+    /// The rooms the user can see and join; public rooms, the private rooms granted to their groups
+    /// and the private rooms they have been invited to
+    pub async fn accessible_rooms(&self, user_id: &str) -> Vec<RoomDetail> {
+        let groups = self.groups.groups_of(user_id).await;
+
+        self.room_manager
+            .chat_room_metadata()
+            .iter()
+            .filter(|metadata| {
+                metadata.is_accessible_by(user_id, &groups)
+                    || self.room_invites.is_invited(&metadata.name, user_id)
+            })
+            .map(|metadata| RoomDetail {
+                name: metadata.name.clone(),
+                description: metadata.description.clone(),
+                encrypted: metadata.encrypted,
+                archived: self.room_archive.is_archived(&metadata.name),
+                category: metadata.category.clone(),
+            })
+            .collect()
+    }
+
+    /// Send an event to every connected user who can access the room, whether they have joined it or not
+    pub async fn notify_room_audience(&self, room: &str, event: &Event) {
+        for user_id in self.session_registry.connected_user_ids() {
+            if self.can_access_room(room, &user_id).await {
+                self.session_registry.send_to_user(&user_id, event);
+            }
+        }
+    }
+
+    /// Returns true if the user is allowed to join the room
+    pub async fn can_access_room(&self, room: &str, user_id: &str) -> bool {
+        let groups = self.groups.groups_of(user_id).await;
+
+        self.room_manager
+            .chat_room_metadata()
+            .iter()
+            .any(|metadata| {
+                metadata.name == room
+                    && (metadata.is_accessible_by(user_id, &groups)
+                        || self.room_invites.is_invited(room, user_id))
+            })
+    }
+}
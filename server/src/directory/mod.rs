@@ -0,0 +1,118 @@
+use std::{cmp::Reverse, collections::BTreeMap};
+
+use comms::event::NameRejectionReason;
+use tokio::sync::Mutex;
+
+use crate::{names, storage::Storage};
+
+/// The name of the storage document the known users are persisted in
+const DIRECTORY_DOCUMENT: &str = "users";
+/// The maximum number of users a single search returns
+const MAX_SEARCH_RESULTS: usize = 20;
+
+/// [UserDirectory] keeps the users who have ever logged in alongside the time they were last seen
+///
+/// Lets users find each other by a part of their id, e.g. to invite someone they do not share a room with.
+/// Every login is persisted to the [Storage].
+#[derive(Debug)]
+pub struct UserDirectory {
+    storage: Storage,
+    user_id_to_last_seen_at: Mutex<BTreeMap<String, u64>>,
+}
+
+impl UserDirectory {
+    /// Load the known users from the storage
+    pub async fn load(storage: Storage) -> anyhow::Result<Self> {
+        let user_id_to_last_seen_at = storage.load(DIRECTORY_DOCUMENT).await?;
+
+        Ok(UserDirectory {
+            storage,
+            user_id_to_last_seen_at: Mutex::new(user_id_to_last_seen_at),
+        })
+    }
+
+    /// Remember that the user has been seen at the given time, e.g. when they log in
+    pub async fn record_seen(&self, user_id: &str, seen_at: u64) -> anyhow::Result<()> {
+        let mut user_id_to_last_seen_at = self.user_id_to_last_seen_at.lock().await;
+
+        let last_seen_at = user_id_to_last_seen_at
+            .entry(String::from(user_id))
+            .or_default();
+        *last_seen_at = (*last_seen_at).max(seen_at);
+
+        self.storage
+            .save(DIRECTORY_DOCUMENT, &*user_id_to_last_seen_at)
+            .await
+    }
+
+    /// When the user was last seen, not set if they have never logged in or have been forgotten
+    pub async fn last_seen_at(&self, user_id: &str) -> Option<u64> {
+        self.user_id_to_last_seen_at
+            .lock()
+            .await
+            .get(user_id)
+            .copied()
+    }
+
+    /// Move the time the user was last seen forward, e.g. when they disconnect
+    /// Unlike [UserDirectory::record_seen], a user who has been forgotten meanwhile stays forgotten
+    pub async fn refresh_seen(&self, user_id: &str, seen_at: u64) -> anyhow::Result<()> {
+        let mut user_id_to_last_seen_at = self.user_id_to_last_seen_at.lock().await;
+
+        match user_id_to_last_seen_at.get_mut(user_id) {
+            Some(last_seen_at) if *last_seen_at < seen_at => *last_seen_at = seen_at,
+            _ => return Ok(()),
+        }
+
+        self.storage
+            .save(DIRECTORY_DOCUMENT, &*user_id_to_last_seen_at)
+            .await
+    }
+
+    /// Forget the user, e.g. when their data is deleted
+    pub async fn forget(&self, user_id: &str) -> anyhow::Result<()> {
+        let mut user_id_to_last_seen_at = self.user_id_to_last_seen_at.lock().await;
+
+        if user_id_to_last_seen_at.remove(user_id).is_some() {
+            self.storage
+                .save(DIRECTORY_DOCUMENT, &*user_id_to_last_seen_at)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Fails if the user id can be mistaken for the id of another known user, e.g. `AIice` for `alice`
+    pub async fn check_confusable(&self, user_id: &str) -> Result<(), NameRejectionReason> {
+        let user_id_to_last_seen_at = self.user_id_to_last_seen_at.lock().await;
+
+        match names::find_confusable(user_id, user_id_to_last_seen_at.keys().map(String::as_str)) {
+            Some(known_user_id) => Err(NameRejectionReason::Confusable {
+                name: String::from(known_user_id),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// The ids of the known users containing the query, ignoring the case
+    /// Users whose id starts with the query come first, the recently seen ones before the others
+    pub async fn search(&self, query: &str) -> Vec<String> {
+        let query = query.to_lowercase();
+        let user_id_to_last_seen_at = self.user_id_to_last_seen_at.lock().await;
+
+        let mut matches = user_id_to_last_seen_at
+            .iter()
+            .map(|(user_id, last_seen_at)| (user_id.to_lowercase(), user_id, *last_seen_at))
+            .filter(|(lowercase_id, _, _)| lowercase_id.contains(&query))
+            .collect::<Vec<_>>();
+        matches.sort_by_key(|(lowercase_id, _, last_seen_at)| {
+            (!lowercase_id.starts_with(&query), Reverse(*last_seen_at))
+        });
+
+        matches
+            .into_iter()
+            .take(MAX_SEARCH_RESULTS)
+            .map(|(_, user_id, _)| user_id.clone())
+            .collect()
+    }
+}
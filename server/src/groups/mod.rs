@@ -0,0 +1,102 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use tokio::sync::Mutex;
+
+use crate::storage::Storage;
+
+/// The name of the storage document the group memberships are persisted in
+const GROUPS_DOCUMENT: &str = "groups";
+
+/// [Groups] keeps the members of the user groups, e.g. "engineering"
+///
+/// Private rooms grant access to groups instead of individual users.
+/// Every change is persisted to the [Storage].
+#[derive(Debug)]
+pub struct Groups {
+    storage: Storage,
+    group_to_user_ids: Mutex<BTreeMap<String, BTreeSet<String>>>,
+}
+
+impl Groups {
+    /// Load the group memberships from the storage
+    pub async fn load(storage: Storage) -> anyhow::Result<Self> {
+        let group_to_user_ids = storage.load(GROUPS_DOCUMENT).await?;
+
+        Ok(Groups {
+            storage,
+            group_to_user_ids: Mutex::new(group_to_user_ids),
+        })
+    }
+
+    /// The groups the user is a member of
+    pub async fn groups_of(&self, user_id: &str) -> HashSet<String> {
+        self.group_to_user_ids
+            .lock()
+            .await
+            .iter()
+            .filter(|(_, user_ids)| user_ids.contains(user_id))
+            .map(|(group, _)| group.clone())
+            .collect()
+    }
+
+    /// Add the user to the group, creating the group if necessary
+    /// Returns false if the user was already a member
+    pub async fn add_member(&self, group: &str, user_id: &str) -> anyhow::Result<bool> {
+        let mut group_to_user_ids = self.group_to_user_ids.lock().await;
+
+        let is_added = group_to_user_ids
+            .entry(String::from(group))
+            .or_default()
+            .insert(String::from(user_id));
+
+        if is_added {
+            self.storage
+                .save(GROUPS_DOCUMENT, &*group_to_user_ids)
+                .await?;
+        }
+
+        Ok(is_added)
+    }
+
+    /// Remove the user from the group, the group is removed along with its last member
+    /// Returns false if the user was not a member
+    pub async fn remove_member(&self, group: &str, user_id: &str) -> anyhow::Result<bool> {
+        let mut group_to_user_ids = self.group_to_user_ids.lock().await;
+
+        let Some(user_ids) = group_to_user_ids.get_mut(group) else {
+            return Ok(false);
+        };
+
+        let is_removed = user_ids.remove(user_id);
+        if user_ids.is_empty() {
+            group_to_user_ids.remove(group);
+        }
+
+        if is_removed {
+            self.storage
+                .save(GROUPS_DOCUMENT, &*group_to_user_ids)
+                .await?;
+        }
+
+        Ok(is_removed)
+    }
+
+    /// Remove the user from every group they are a member of, e.g. when their data is deleted
+    pub async fn forget(&self, user_id: &str) -> anyhow::Result<()> {
+        let mut group_to_user_ids = self.group_to_user_ids.lock().await;
+
+        let mut is_removed = false;
+        for user_ids in group_to_user_ids.values_mut() {
+            is_removed |= user_ids.remove(user_id);
+        }
+        group_to_user_ids.retain(|_, user_ids| !user_ids.is_empty());
+
+        if is_removed {
+            self.storage
+                .save(GROUPS_DOCUMENT, &*group_to_user_ids)
+                .await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+use std::{net::SocketAddr, time::Duration};
+
+use anyhow::Context;
+use comms::handshake::{ClientHello, Compression, ServerHello};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+
+use crate::storage::Storage;
+
+/// How long the server may take to answer before it is considered unhealthy
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Checks that the server accepts connections and that its storage can be written to, e.g. for container probes
+///
+/// Only the handshake is carried out, no user logs in.
+pub async fn check(addr: SocketAddr, storage: &Storage) -> anyhow::Result<()> {
+    tokio::time::timeout(TIMEOUT, handshake(addr))
+        .await
+        .with_context(|| format!("the server at {} did not answer in time", addr))??;
+    storage
+        .probe()
+        .await
+        .context("the data directory is not usable")?;
+
+    Ok(())
+}
+
+async fn handshake(addr: SocketAddr) -> anyhow::Result<()> {
+    let stream = TcpStream::connect(addr)
+        .await
+        .with_context(|| format!("could not connect to the server at {}", addr))?;
+    let (reader, mut writer) = stream.into_split();
+
+    let mut hello = serde_json::to_vec(&ClientHello {
+        compression: vec![Compression::None],
+    })?;
+    hello.push(b'\n');
+    writer.write_all(&hello).await?;
+
+    let mut line = String::new();
+    BufReader::new(reader).read_line(&mut line).await?;
+    let _: ServerHello = serde_json::from_str(&line)
+        .with_context(|| format!("the server at {} did not complete the handshake", addr))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_servers_not_accepting_connections_are_unhealthy() {
+        let storage =
+            Storage::new(std::env::temp_dir().join(format!("chat-health-{}", nanoid::nanoid!())))
+                .unwrap();
+        // nothing listens on the port once the listener is dropped
+        let addr = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        assert!(check(addr, &storage).await.is_err());
+    }
+}
@@ -0,0 +1,208 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::RwLock,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{clock::unix_millis_now, storage::Storage};
+
+/// The name of the storage document the invite codes are persisted in
+const INVITES_DOCUMENT: &str = "invites";
+/// The characters of an invite code, without the ones easily mistaken for each other such as `0` and `o`
+const INVITE_CODE_ALPHABET: [char; 31] = [
+    'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'j', 'k', 'm', 'n', 'p', 'q', 'r', 's', 't', 'u', 'v',
+    'w', 'x', 'y', 'z', '2', '3', '4', '5', '6', '7', '8', '9',
+];
+const INVITE_CODE_LENGTH: usize = 10;
+
+/// A code which can still be redeemed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct InviteCode {
+    room: String,
+    created_by: String,
+    /// When the code can not be redeemed anymore, in milliseconds since the unix epoch
+    expires_at: u64,
+    max_uses: u32,
+    uses: u32,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct InviteState {
+    codes: BTreeMap<String, InviteCode>,
+    /// The users who have redeemed a code, by the room they have been invited to
+    invited: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// A newly created invite code
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreatedInvite {
+    pub code: String,
+    pub expires_at: u64,
+}
+
+/// [RoomInvites] keeps the invite codes to private rooms and who has been let in with them
+///
+/// A code can be redeemed until it expires or has been used as often as its creator allowed, the users who have
+/// redeemed it can access the room from then on. Every change is persisted to the [Storage].
+#[derive(Debug)]
+pub struct RoomInvites {
+    storage: Storage,
+    // held while the state is saved, so the changes are persisted in the order they are made
+    state: Mutex<InviteState>,
+    // a copy of the invited users once they are saved, the access checks read it while holding the room metadata
+    invited: RwLock<BTreeMap<String, BTreeSet<String>>>,
+}
+
+impl RoomInvites {
+    /// Load the invite codes and the invited users from the storage
+    pub async fn load(storage: Storage) -> anyhow::Result<Self> {
+        let state: InviteState = storage.load(INVITES_DOCUMENT).await?;
+
+        Ok(RoomInvites {
+            storage,
+            invited: RwLock::new(state.invited.clone()),
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Returns true if the user has redeemed a code to the room
+    pub fn is_invited(&self, room: &str, user_id: &str) -> bool {
+        self.invited
+            .read()
+            .unwrap()
+            .get(room)
+            .is_some_and(|user_ids| user_ids.contains(user_id))
+    }
+
+    /// Create a code to the room, dropping the codes which have expired meanwhile
+    pub async fn create(
+        &self,
+        room: &str,
+        created_by: &str,
+        ttl: Duration,
+        max_uses: u32,
+    ) -> anyhow::Result<CreatedInvite> {
+        let now = unix_millis_now();
+        let created_invite = CreatedInvite {
+            code: nanoid::nanoid!(INVITE_CODE_LENGTH, &INVITE_CODE_ALPHABET),
+            expires_at: now + ttl.as_millis() as u64,
+        };
+
+        let mut state = self.state.lock().await;
+        state.codes.retain(|_, code| now < code.expires_at);
+        state.codes.insert(
+            created_invite.code.clone(),
+            InviteCode {
+                room: String::from(room),
+                created_by: String::from(created_by),
+                expires_at: created_invite.expires_at,
+                max_uses,
+                uses: 0,
+            },
+        );
+        self.save(&state).await?;
+
+        Ok(created_invite)
+    }
+
+    /// Let the user into the room of the code, counting the use of the code
+    /// Returns the room, None if the code is unknown, has expired or has been used up
+    pub async fn redeem(&self, code: &str, user_id: &str) -> anyhow::Result<Option<String>> {
+        let now = unix_millis_now();
+
+        let mut state = self.state.lock().await;
+        let Some(invite_code) = state
+            .codes
+            .get_mut(code.trim())
+            .filter(|invite_code| now < invite_code.expires_at)
+        else {
+            return Ok(None);
+        };
+
+        invite_code.uses += 1;
+        let room = invite_code.room.clone();
+        if invite_code.uses >= invite_code.max_uses {
+            state.codes.remove(code.trim());
+        }
+        state
+            .invited
+            .entry(room.clone())
+            .or_default()
+            .insert(String::from(user_id));
+        self.save(&state).await?;
+
+        Ok(Some(room))
+    }
+
+    /// Drop what is kept about the user, the codes they have created and the rooms they have been invited to
+    pub async fn forget(&self, user_id: &str) -> anyhow::Result<()> {
+        let mut state = self.state.lock().await;
+        state.codes.retain(|_, code| code.created_by != user_id);
+        for user_ids in state.invited.values_mut() {
+            user_ids.remove(user_id);
+        }
+        state.invited.retain(|_, user_ids| !user_ids.is_empty());
+
+        self.save(&state).await
+    }
+
+    /// Persist the state, then let the access checks see the users it invites
+    async fn save(&self, state: &InviteState) -> anyhow::Result<()> {
+        self.storage.save(INVITES_DOCUMENT, state).await?;
+        *self.invited.write().unwrap() = state.invited.clone();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn room_invites() -> RoomInvites {
+        let storage =
+            Storage::new(std::env::temp_dir().join(format!("chat-invites-{}", nanoid::nanoid!())))
+                .unwrap();
+
+        RoomInvites::load(storage).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_codes_can_be_redeemed_as_often_as_allowed() {
+        let room_invites = room_invites().await;
+        let invite = room_invites
+            .create("secret", "owner", Duration::from_secs(60), 2)
+            .await
+            .unwrap();
+
+        for user_id in ["alice", "bob"] {
+            assert_eq!(
+                room_invites.redeem(&invite.code, user_id).await.unwrap(),
+                Some(String::from("secret"))
+            );
+            assert!(room_invites.is_invited("secret", user_id));
+        }
+        assert_eq!(
+            room_invites.redeem(&invite.code, "carol").await.unwrap(),
+            None
+        );
+        assert!(!room_invites.is_invited("secret", "carol"));
+    }
+
+    #[tokio::test]
+    async fn test_expired_codes_can_not_be_redeemed() {
+        let room_invites = room_invites().await;
+        let invite = room_invites
+            .create("secret", "owner", Duration::ZERO, 1)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            room_invites.redeem(&invite.code, "alice").await.unwrap(),
+            None
+        );
+    }
+}
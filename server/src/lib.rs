@@ -0,0 +1,29 @@
+//! The chat server as a library, so the binary and the benchmarks share the same building blocks
+
+pub mod announcements;
+pub mod archival;
+pub mod auth;
+pub mod chaos;
+mod clock;
+pub mod config;
+pub mod context;
+pub mod directory;
+pub mod groups;
+pub mod healthcheck;
+pub mod invites;
+pub mod link_previews;
+pub mod listener;
+pub mod logging;
+pub mod message_filters;
+pub mod mirror;
+pub mod moderation;
+pub mod names;
+pub mod provisioning;
+pub mod read_markers;
+pub mod reminders;
+pub mod room_manager;
+pub mod session;
+pub mod storage;
+#[cfg(unix)]
+pub mod unix_socket;
+pub mod webhooks;
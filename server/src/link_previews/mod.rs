@@ -0,0 +1,307 @@
+mod page_summary;
+
+use std::{
+    collections::VecDeque,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use comms::{
+    event::{self, Event},
+    transport::server::EncodedEvent,
+};
+use reqwest::{header::CONTENT_TYPE, redirect, Url};
+use tokio::{
+    sync::{
+        broadcast::{self, error::RecvError},
+        mpsc,
+    },
+    task::JoinSet,
+};
+
+use self::page_summary::PageSummary;
+use crate::{logging, room_manager::RoomManager};
+
+/// The number of pages fetched per minute, unless overridden by `CHAT_LINK_PREVIEW_RATE`
+const DEFAULT_FETCHES_PER_MINUTE: usize = 20;
+const FETCH_WINDOW: Duration = Duration::from_secs(60);
+/// How long fetching a page may take, including its redirects
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+/// The number of redirects followed, as long as they stay on allowed hosts
+const MAX_REDIRECTS: usize = 3;
+/// The part of a page read for its summary, the head of a page comes first
+const MAX_PAGE_BYTES: usize = 256 * 1024;
+/// The number of links waiting to be previewed, more are dropped while the previewer catches up
+const PENDING_LINKS_CAPACITY: usize = 64;
+
+/// A link found in a message, waiting to be previewed
+#[derive(Debug)]
+struct PendingLink {
+    room: String,
+    message_id: String,
+    url: Url,
+}
+
+/// [LinkPreviewer] fetches the pages linked in messages and broadcasts their title and description to the room
+///
+/// Configured by the environment, nothing is fetched unless `CHAT_LINK_PREVIEW_HOSTS` is set:
+/// - `CHAT_LINK_PREVIEW_HOSTS` - the comma separated hosts pages are fetched from, along with their subdomains,
+///   e.g. `github.com,docs.rs`, links to any other host are left alone
+/// - `CHAT_LINK_PREVIEW_RATE` - the number of pages fetched per minute across all rooms, links beyond are skipped
+///
+/// Only the first allowed link of a message is previewed. Pages are fetched one at a time, with a timeout and
+/// up to a size, and redirects leaving the allowed hosts are not followed. Encrypted rooms are left alone,
+/// the server can not read their links.
+#[derive(Debug)]
+pub struct LinkPreviewer {
+    allowed_hosts: Arc<Vec<String>>,
+    fetches_per_minute: usize,
+    http_client: reqwest::Client,
+}
+
+impl LinkPreviewer {
+    /// Read the previewer configuration from the environment
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Ok(allowed_hosts) = std::env::var("CHAT_LINK_PREVIEW_HOSTS") else {
+            return Ok(None);
+        };
+        let allowed_hosts = allowed_hosts
+            .split(',')
+            .map(|host| host.trim().trim_start_matches('.').to_lowercase())
+            .filter(|host| !host.is_empty())
+            .collect::<Vec<_>>();
+        if allowed_hosts.is_empty() {
+            anyhow::bail!("no hosts are allowed to preview links from");
+        }
+        let fetches_per_minute = std::env::var("CHAT_LINK_PREVIEW_RATE")
+            .ok()
+            .map(|rate| {
+                rate.trim()
+                    .parse()
+                    .with_context(|| format!("invalid link preview rate '{}'", rate))
+            })
+            .transpose()?
+            .unwrap_or(DEFAULT_FETCHES_PER_MINUTE);
+
+        Ok(Some(Self::new(allowed_hosts, fetches_per_minute)?))
+    }
+
+    fn new(allowed_hosts: Vec<String>, fetches_per_minute: usize) -> anyhow::Result<Self> {
+        let allowed_hosts = Arc::new(allowed_hosts);
+        let http_client = reqwest::Client::builder()
+            .timeout(FETCH_TIMEOUT)
+            .redirect(redirect::Policy::custom({
+                let allowed_hosts = Arc::clone(&allowed_hosts);
+                move |attempt| {
+                    if attempt.previous().len() > MAX_REDIRECTS {
+                        attempt.error("too many redirects")
+                    } else if is_allowed(&allowed_hosts, attempt.url()) {
+                        attempt.follow()
+                    } else {
+                        attempt.stop()
+                    }
+                }
+            }))
+            .build()
+            .context("could not create the http client of the link previewer")?;
+
+        Ok(LinkPreviewer {
+            allowed_hosts,
+            fetches_per_minute,
+            http_client,
+        })
+    }
+
+    /// Preview the links of the messages sent to the rooms until the server shuts down
+    pub async fn preview_until_quit(
+        self,
+        room_manager: &RoomManager,
+        mut quit_rx: broadcast::Receiver<()>,
+    ) -> anyhow::Result<()> {
+        let (pending_tx, mut pending_rx) = mpsc::channel(PENDING_LINKS_CAPACITY);
+        let rooms = room_manager
+            .chat_room_metadata()
+            .iter()
+            .filter(|metadata| !metadata.encrypted)
+            .map(|metadata| metadata.name.clone())
+            .collect::<Vec<_>>();
+        // the rooms are left alone once the previewer stops, dropping the join set aborts their tasks
+        let mut join_set = JoinSet::new();
+        for room in rooms {
+            let events = room_manager.subscribe(&room).await?;
+            join_set.spawn(find_links(
+                Arc::clone(&self.allowed_hosts),
+                events,
+                pending_tx.clone(),
+            ));
+        }
+        drop(pending_tx);
+
+        let mut fetched_at = VecDeque::new();
+        loop {
+            let link = tokio::select! {
+                link = pending_rx.recv() => match link {
+                    Some(link) => link,
+                    None => break,
+                },
+                _ = quit_rx.recv() => break,
+            };
+
+            let now = Instant::now();
+            while fetched_at
+                .front()
+                .is_some_and(|fetched_at| now.duration_since(*fetched_at) >= FETCH_WINDOW)
+            {
+                fetched_at.pop_front();
+            }
+            if fetched_at.len() >= self.fetches_per_minute {
+                continue;
+            }
+            fetched_at.push_back(now);
+
+            let summary = tokio::select! {
+                summary = self.fetch_summary(&link.url) => summary,
+                _ = quit_rx.recv() => break,
+            };
+            match summary {
+                Ok(summary) if !summary.is_empty() => {
+                    room_manager
+                        .broadcast_link_preview(event::LinkPreviewBroadcastEvent {
+                            room: link.room,
+                            message_id: link.message_id,
+                            url: link.url.to_string(),
+                            title: summary.title,
+                            description: summary.description,
+                        })
+                        .await?;
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    logging::warn(format!("could not preview {}: {:#}", link.url, err));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fetch the beginning of the html page and summarize it
+    async fn fetch_summary(&self, url: &Url) -> anyhow::Result<PageSummary> {
+        let mut response = self
+            .http_client
+            .get(url.clone())
+            .send()
+            .await?
+            .error_for_status()?;
+        let is_html = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .is_some_and(|content_type| content_type.starts_with("text/html"));
+        // a redirect that was not followed leaves nothing to summarize
+        if !response.status().is_success() || !is_html {
+            return Ok(PageSummary::default());
+        }
+
+        let mut page = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            page.extend_from_slice(&chunk);
+            if page.len() >= MAX_PAGE_BYTES {
+                page.truncate(MAX_PAGE_BYTES);
+                break;
+            }
+        }
+
+        Ok(PageSummary::from_html(&String::from_utf8_lossy(&page)))
+    }
+}
+
+/// Queue the first allowed link of every message sent to the room, until the room is gone
+async fn find_links(
+    allowed_hosts: Arc<Vec<String>>,
+    mut events: broadcast::Receiver<EncodedEvent>,
+    pending_tx: mpsc::Sender<PendingLink>,
+) {
+    loop {
+        let encoded_event = match events.recv().await {
+            Ok(encoded_event) => encoded_event,
+            // the missed messages are not previewed
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => break,
+        };
+        let Ok(Event::UserMessage(message)) = encoded_event.decode() else {
+            continue;
+        };
+
+        let Some(url) = links_in(&message.content).find(|url| is_allowed(&allowed_hosts, url))
+        else {
+            continue;
+        };
+        let link = PendingLink {
+            room: message.room,
+            message_id: message.id,
+            url,
+        };
+        // the previewer is busy, the link is skipped like the ones beyond the rate
+        if let Err(mpsc::error::TrySendError::Closed(_)) = pending_tx.try_send(link) {
+            break;
+        }
+    }
+}
+
+/// The http and https links in the text, without the punctuation around them
+fn links_in(text: &str) -> impl Iterator<Item = Url> + '_ {
+    text.split_whitespace()
+        .map(|word| word.trim_start_matches(['(', '<', '"', '\'']))
+        .filter(|word| word.starts_with("https://") || word.starts_with("http://"))
+        .map(|word| word.trim_end_matches(['.', ',', ';', ':', '!', '?', ')', '>', '"', '\'']))
+        .filter_map(|word| Url::parse(word).ok())
+}
+
+/// Whether the link points to one of the allowed hosts or to a subdomain of one of them
+fn is_allowed(allowed_hosts: &[String], url: &Url) -> bool {
+    if !matches!(url.scheme(), "http" | "https") {
+        return false;
+    }
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+
+    allowed_hosts.iter().any(|allowed_host| {
+        host == allowed_host
+            || host
+                .strip_suffix(allowed_host.as_str())
+                .is_some_and(|subdomain| subdomain.ends_with('.'))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_links_are_found_without_surrounding_punctuation() {
+        let links =
+            links_in("see (https://docs.rs/tokio). or <http://example.com/a?b=c>, not ftp://x")
+                .map(|url| url.to_string())
+                .collect::<Vec<_>>();
+
+        assert_eq!(
+            links,
+            vec!["https://docs.rs/tokio", "http://example.com/a?b=c"]
+        );
+    }
+
+    #[test]
+    fn test_only_allowed_hosts_and_their_subdomains_are_allowed() {
+        let allowed_hosts = vec![String::from("github.com")];
+        let is_allowed = |url: &str| is_allowed(&allowed_hosts, &Url::parse(url).unwrap());
+
+        assert!(is_allowed("https://github.com/rust-lang"));
+        assert!(is_allowed("https://gist.github.com/a"));
+        assert!(!is_allowed("https://notgithub.com"));
+        assert!(!is_allowed("https://github.com.evil.com"));
+        assert!(!is_allowed("file://github.com/etc/passwd"));
+    }
+}
@@ -0,0 +1,172 @@
+/// The longest title kept for a preview, longer ones are cut off
+const MAX_TITLE_CHARS: usize = 120;
+/// The longest description kept for a preview, longer ones are cut off
+const MAX_DESCRIPTION_CHARS: usize = 300;
+
+/// [PageSummary] is what a preview shows of a linked page, read from its html
+#[derive(Debug, Default, PartialEq)]
+pub struct PageSummary {
+    pub title: Option<String>,
+    pub description: Option<String>,
+}
+
+impl PageSummary {
+    /// Read the title and the description of the page, preferring the ones given for link previews (`og:`)
+    /// Only the tags are looked for, the html is not validated
+    pub fn from_html(html: &str) -> Self {
+        // lowercasing ascii keeps the byte offsets, so the tags can be found in one and read from the other
+        let lowercase = html.to_ascii_lowercase();
+
+        let mut og_title = None;
+        let mut og_description = None;
+        let mut description = None;
+        let mut rest = 0;
+        while let Some(start) = lowercase[rest..].find("<meta").map(|start| rest + start) {
+            let end = lowercase[start..]
+                .find('>')
+                .map_or(lowercase.len(), |end| start + end);
+            let attributes = parse_attributes(&html[start + "<meta".len()..end]);
+            let attribute = |name: &str| {
+                attributes
+                    .iter()
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.as_str())
+            };
+
+            let content = attribute("content").map(String::from);
+            match attribute("property").or_else(|| attribute("name")) {
+                Some("og:title") => og_title = og_title.or(content),
+                Some("og:description") => og_description = og_description.or(content),
+                Some("description") => description = description.or(content),
+                _ => {}
+            }
+            rest = end;
+        }
+
+        let title = lowercase.find("<title").and_then(|start| {
+            let content_start = start + lowercase[start..].find('>')? + 1;
+            let content_end = content_start + lowercase[content_start..].find("</title")?;
+            Some(String::from(&html[content_start..content_end]))
+        });
+
+        PageSummary {
+            title: og_title
+                .or(title)
+                .and_then(|title| clean(&title, MAX_TITLE_CHARS)),
+            description: og_description
+                .or(description)
+                .and_then(|description| clean(&description, MAX_DESCRIPTION_CHARS)),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.description.is_none()
+    }
+}
+
+/// The lowercased names and the values of the attributes of a tag, e.g. `name="description" content="..."`
+fn parse_attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut rest = tag.trim_start();
+    while let Some(equals) = rest.find('=') {
+        let name = rest[..equals]
+            .split_whitespace()
+            .last()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let value = rest[equals + 1..].trim_start();
+
+        let (value, remaining) = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let value = &value[1..];
+                let end = value.find(quote).unwrap_or(value.len());
+                (&value[..end], value.get(end + 1..).unwrap_or_default())
+            }
+            _ => {
+                let end = value.find(char::is_whitespace).unwrap_or(value.len());
+                (&value[..end], &value[end..])
+            }
+        };
+
+        attributes.push((name, String::from(value)));
+        rest = remaining;
+    }
+
+    attributes
+}
+
+/// The text with its entities decoded and its whitespace collapsed, cut off at the given length
+/// None if nothing is left
+fn clean(text: &str, max_chars: usize) -> Option<String> {
+    let text = text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&nbsp;", " ")
+        // last, so escaped entities such as `&amp;lt;` are not decoded twice
+        .replace("&amp;", "&");
+
+    if text.is_empty() {
+        return None;
+    }
+    if text.chars().count() <= max_chars {
+        return Some(text);
+    }
+
+    let mut cut_off = text.chars().take(max_chars - 1).collect::<String>();
+    cut_off.push('…');
+    Some(cut_off)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_preview_tags_are_preferred() {
+        let summary = PageSummary::from_html(
+            r#"<html><head>
+                <TITLE>Plain title</TITLE>
+                <meta name="description" content="Plain description">
+                <meta property="og:title" content="Rust &amp; Tokio" />
+                <meta content='Async in practice' property='og:description'>
+            </head></html>"#,
+        );
+
+        assert_eq!(
+            summary,
+            PageSummary {
+                title: Some(String::from("Rust & Tokio")),
+                description: Some(String::from("Async in practice")),
+            }
+        );
+    }
+
+    #[test]
+    fn test_plain_tags_are_read_without_link_preview_tags() {
+        let summary = PageSummary::from_html(
+            "<head><title>\n  The   Book\n</title><meta name=description content=Learn></head>",
+        );
+
+        assert_eq!(summary.title.as_deref(), Some("The Book"));
+        assert_eq!(summary.description.as_deref(), Some("Learn"));
+    }
+
+    #[test]
+    fn test_long_titles_are_cut_off() {
+        let summary = PageSummary::from_html(&format!(
+            "<title>{}</title>",
+            "a".repeat(MAX_TITLE_CHARS * 2)
+        ));
+
+        let title = summary.title.unwrap();
+        assert_eq!(title.chars().count(), MAX_TITLE_CHARS);
+        assert!(title.ends_with('…'));
+        assert!(PageSummary::from_html("<p>no head</p>").is_empty());
+    }
+}
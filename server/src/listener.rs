@@ -0,0 +1,133 @@
+use std::{
+    io::ErrorKind,
+    net::{Ipv4Addr, SocketAddr},
+    task::Poll,
+};
+
+use anyhow::Context;
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::logging;
+
+/// How many connections may wait to be accepted before the operating system refuses new ones
+const BACKLOG: i32 = 1024;
+
+/// Binds a listener to each of the addresses sessions are accepted on
+///
+/// IPv6 listeners take IPv4 connections as well, e.g. `::` listens on every address of both families,
+/// unless an IPv4 address is listed too, which then takes them. A lone `::` falls back to `0.0.0.0`
+/// on hosts without IPv6.
+pub fn bind(addrs: &[SocketAddr]) -> anyhow::Result<Vec<TcpListener>> {
+    let dual_stack = !addrs.iter().any(SocketAddr::is_ipv4);
+
+    addrs
+        .iter()
+        .map(|addr| {
+            match bind_one(*addr, dual_stack) {
+                Err(err)
+                    if addrs.len() == 1
+                        && addr.ip().is_unspecified()
+                        && addr.is_ipv6()
+                        && !matches!(
+                            err.kind(),
+                            ErrorKind::AddrInUse | ErrorKind::PermissionDenied
+                        ) =>
+                {
+                    let fallback = SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), addr.port());
+                    logging::warn(format!(
+                        "could not listen on {} ({}), listening on {} instead",
+                        addr, err, fallback
+                    ));
+                    bind_one(fallback, false)
+                }
+                result => result,
+            }
+            .with_context(|| format!("could not bind to {}", addr))
+        })
+        .collect()
+}
+
+fn bind_one(addr: SocketAddr, dual_stack: bool) -> std::io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    if addr.is_ipv6() {
+        // the operating systems differ in their default, e.g. Windows takes IPv6 connections only
+        socket.set_only_v6(!dual_stack)?;
+    }
+    // as tokio does, so a restarted server does not wait for the connections of the previous one to time out
+    #[cfg(unix)]
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(BACKLOG)?;
+
+    TcpListener::from_std(socket.into())
+}
+
+/// Waits for a connection on any of the listeners
+pub async fn accept(listeners: &[TcpListener]) -> std::io::Result<(TcpStream, SocketAddr)> {
+    std::future::poll_fn(|cx| {
+        listeners
+            .iter()
+            .find_map(|listener| match listener.poll_accept(cx) {
+                Poll::Ready(result) => Some(result),
+                Poll::Pending => None,
+            })
+            .map_or(Poll::Pending, Poll::Ready)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn connects(listeners: &[TcpListener], addr: SocketAddr) -> bool {
+        let Ok(mut stream) = TcpStream::connect(addr).await else {
+            return false;
+        };
+        let (accepted, _) = accept(listeners).await.unwrap();
+        drop(accepted);
+
+        tokio::io::AsyncReadExt::read(&mut stream, &mut [0; 1])
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn test_unspecified_ipv6_address_takes_both_families() {
+        let listeners = bind(&["[::]:0".parse().unwrap()]).unwrap();
+        let local_addr = listeners[0].local_addr().unwrap();
+
+        assert!(
+            connects(
+                &listeners,
+                SocketAddr::new("127.0.0.1".parse().unwrap(), local_addr.port())
+            )
+            .await
+        );
+        // the host may have no IPv6, e.g. a container without it, the listener has fallen back to IPv4 then
+        if local_addr.is_ipv6() {
+            assert!(
+                connects(
+                    &listeners,
+                    SocketAddr::new("::1".parse().unwrap(), local_addr.port())
+                )
+                .await
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_every_address_is_listened_on() {
+        let listeners = bind(&[
+            "127.0.0.1:0".parse().unwrap(),
+            "127.0.0.1:0".parse().unwrap(),
+        ])
+        .unwrap();
+
+        for listener in &listeners {
+            assert!(connects(&listeners, listener.local_addr().unwrap()).await);
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use std::{fmt::Display, sync::OnceLock};
+
+use chrono::{SecondsFormat, Utc};
+use serde::Serialize;
+
+static FORMAT: OnceLock<LogFormat> = OnceLock::new();
+
+/// How the server writes its log lines to stdout, read from the `CHAT_LOG_FORMAT` environment variable
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// The message as is, for people reading the output
+    #[default]
+    Text,
+    /// One json object per line with a timestamp and a level, for log collectors, e.g. of a container platform
+    Json,
+}
+
+impl LogFormat {
+    pub fn from_env() -> anyhow::Result<Self> {
+        match std::env::var("CHAT_LOG_FORMAT").as_deref() {
+            Err(_) | Ok("text") => Ok(LogFormat::Text),
+            Ok("json") => Ok(LogFormat::Json),
+            Ok(format) => anyhow::bail!("unknown log format '{}', expected text or json", format),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Level {
+    Info,
+    Warn,
+    Error,
+}
+
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    timestamp: String,
+    level: Level,
+    message: &'a str,
+}
+
+/// Set the format of every following log line, the first call wins
+pub fn init(format: LogFormat) {
+    let _ = FORMAT.set(format);
+}
+
+pub fn info(message: impl Display) {
+    log(Level::Info, message);
+}
+
+pub fn warn(message: impl Display) {
+    log(Level::Warn, message);
+}
+
+pub fn error(message: impl Display) {
+    log(Level::Error, message);
+}
+
+fn log(level: Level, message: impl Display) {
+    println!(
+        "{}",
+        format_line(FORMAT.get().copied().unwrap_or_default(), level, message)
+    );
+}
+
+fn format_line(format: LogFormat, level: Level, message: impl Display) -> String {
+    let message = message.to_string();
+    match format {
+        LogFormat::Text => message,
+        LogFormat::Json => serde_json::to_string(&JsonLine {
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            level,
+            message: &message,
+        })
+        .unwrap_or(message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_lines_carry_the_level_and_the_message() {
+        let line = format_line(LogFormat::Json, Level::Warn, "room \"general\" lagged");
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+
+        assert_eq!(value["level"], "warn");
+        assert_eq!(value["message"], "room \"general\" lagged");
+        assert!(value["timestamp"].as_str().unwrap().ends_with('Z'));
+    }
+}
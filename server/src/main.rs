@@ -1,50 +1,393 @@
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
 use anyhow::Context;
-use room_manager::RoomManagerBuilder;
-use tokio::{net::TcpListener, signal::ctrl_c, sync::broadcast, task::JoinSet};
+use tokio::{signal::ctrl_c, sync::broadcast, task::JoinSet};
 
-use crate::room_manager::ChatRoomMetadata;
+#[cfg(unix)]
+use server::unix_socket;
+use server::{
+    announcements::Announcements,
+    archival::RoomArchive,
+    auth::Authenticator,
+    chaos::Chaos,
+    config::RuntimeConfig,
+    context::ServerContext,
+    directory::UserDirectory,
+    groups::Groups,
+    healthcheck,
+    invites::RoomInvites,
+    link_previews::LinkPreviewer,
+    listener,
+    logging::{self, LogFormat},
+    message_filters::MessageFilters,
+    mirror::Mirror,
+    moderation::ModerationQueue,
+    names,
+    provisioning::RoomProvisioning,
+    read_markers::ReadMarkers,
+    reminders::Reminders,
+    room_manager::{self, ChatRoomMetadata, RoomManagerBuilder},
+    session::{self, CommandHistory, DuplicateLoginPolicy, SessionRegistry},
+    storage::{DataKey, Storage},
+    webhooks,
+};
 
-mod room_manager;
-mod session;
-
-const PORT: u16 = 8080;
-const CHAT_ROOMS_METADATA: &str = include_str!("../resources/chat_rooms_metadata.json");
+/// The directory the server state is persisted in, unless overridden by the `CHAT_DATA_DIR` environment variable
+const DEFAULT_DATA_DIR: &str = "data";
 
 #[tokio::main]
 async fn main() {
-    let chat_room_metadata: Vec<ChatRoomMetadata> = serde_json::from_str(CHAT_ROOMS_METADATA)
-        .expect("could not parse the chat rooms metadatas");
-    let room_manager = Arc::new(
+    let config = RuntimeConfig::from_env().expect("could not read the runtime configuration");
+
+    // e.g. `server healthcheck` as the health check of a container
+    if std::env::args().nth(1).as_deref() == Some("healthcheck") {
+        healthcheck(&config).await;
+    }
+
+    logging::init(LogFormat::from_env().expect("could not read the log format"));
+
+    let chat_room_metadata: Vec<ChatRoomMetadata> =
+        serde_json::from_str(&config.chat_rooms).expect("could not parse the chat rooms metadatas");
+    names::validate_room_names(
         chat_room_metadata
-            .into_iter()
-            .fold(RoomManagerBuilder::new(), |builder, metadata| {
-                builder.create_room(metadata)
-            })
-            .build(),
-    );
+            .iter()
+            .map(|metadata| metadata.name.as_str()),
+    )
+    .expect("could not accept the chat rooms metadatas");
+    let room_manager = chat_room_metadata
+        .into_iter()
+        .fold(RoomManagerBuilder::new(), |builder, metadata| {
+            builder.create_room(metadata)
+        })
+        .build();
+    let data_key = DataKey::from_env().expect("could not read the data key");
+    let storage = Storage::new(data_dir())
+        .expect("could not open the data directory")
+        .with_data_key(data_key);
+    let room_logs =
+        room_manager::room_logs_from_env().expect("could not read the room logs setting");
+    // before the rooms are provisioned, so their changes are logged as well
+    if room_logs {
+        let room_logs_dir = storage
+            .subdirectory("rooms")
+            .expect("could not open the room logs directory");
+        room_manager
+            .open_room_logs(room_logs_dir, storage.data_key().cloned())
+            .await
+            .expect("could not open the room logs");
+    }
+    let provisioning = RoomProvisioning::from_env();
+    if let Some(provisioning) = provisioning.as_ref() {
+        let report = provisioning
+            .apply(&room_manager)
+            .await
+            .expect("could not provision the rooms");
+        logging::info(format!("Provisioned the rooms: {}", report));
+    }
 
-    let mut join_set: JoinSet<anyhow::Result<()>> = JoinSet::new();
-    let server = TcpListener::bind(format!("0.0.0.0:{}", PORT))
+    let announcements =
+        Announcements::from_env(&room_manager).expect("could not load the announcements");
+    let mirror = Mirror::from_env(&room_manager)
+        .await
+        .expect("could not configure the mirror");
+    let link_previewer = LinkPreviewer::from_env().expect("could not configure the link previewer");
+    let message_filters =
+        MessageFilters::from_env().expect("could not load the message filter plugins");
+    let chaos = Chaos::from_env().expect("could not read the injected faults");
+    let hibernate_after =
+        room_manager::hibernation_from_env().expect("could not read the room hibernation delay");
+
+    let reminders = Reminders::load(storage.clone())
         .await
-        .expect("could not bind to the port");
+        .expect("could not load the reminders");
+    let groups = Groups::load(storage.clone())
+        .await
+        .expect("could not load the groups");
+    let read_markers = ReadMarkers::load(storage.clone())
+        .await
+        .expect("could not load the read markers");
+    let moderation_queue = ModerationQueue::load(storage.clone())
+        .await
+        .expect("could not load the reports");
+    let user_directory = UserDirectory::load(storage.clone())
+        .await
+        .expect("could not load the known users");
+    let room_archive = RoomArchive::load(storage.clone())
+        .await
+        .expect("could not load the archived rooms");
+    let room_invites = RoomInvites::load(storage.clone())
+        .await
+        .expect("could not load the invite codes");
+    let authenticator = Authenticator::load(storage)
+        .await
+        .expect("could not configure the authentication");
+    let webhook_port = webhooks::port_from_env().expect("could not read the webhook port");
+    let duplicate_login_policy =
+        DuplicateLoginPolicy::from_env().expect("could not read the duplicate login policy");
+    let redirect_addr =
+        session::redirect_addr_from_env().expect("could not read the redirect address");
+    // comma separated user ids, e.g. CHAT_ADMINS=abcde,fghij
+    let admins = std::env::var("CHAT_ADMINS")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|user_id| !user_id.is_empty())
+        .map(String::from)
+        .collect();
+
+    let context = Arc::new(ServerContext {
+        authenticator,
+        room_manager,
+        session_registry: SessionRegistry::new(),
+        reminders,
+        groups,
+        read_markers,
+        moderation_queue,
+        user_directory,
+        room_archive,
+        room_invites,
+        message_filters,
+        mirror,
+        chaos,
+        command_history: CommandHistory::new(),
+        duplicate_login_policy,
+        redirect_addr,
+        admins,
+    });
+
+    let mut join_set: JoinSet<anyhow::Result<()>> = JoinSet::new();
+    let listeners = listener::bind(&config.bind_addrs).expect("could not bind to the port");
     let (quit_tx, quit_rx) = broadcast::channel::<()>(1);
 
-    println!("Listening on port {}", PORT);
+    // Deliver the reminders of the users in the background until the server shuts down
+    join_set.spawn({
+        let context = Arc::clone(&context);
+        let quit_rx = quit_rx.resubscribe();
+
+        async move {
+            context
+                .reminders
+                .deliver_until_quit(&context.session_registry, quit_rx)
+                .await;
+
+            Ok(())
+        }
+    });
+
+    // Refresh the expiring tokens of the connected bots in the background until the server shuts down
+    join_set.spawn({
+        let context = Arc::clone(&context);
+        let quit_rx = quit_rx.resubscribe();
+
+        async move {
+            context
+                .authenticator
+                .bot_tokens
+                .refresh_until_quit(&context.session_registry, quit_rx)
+                .await;
+
+            Ok(())
+        }
+    });
+
+    // Archive the rooms scheduled for archival in the background until the server shuts down
+    join_set.spawn({
+        let context = Arc::clone(&context);
+        let quit_rx = quit_rx.resubscribe();
+
+        async move {
+            context
+                .room_archive
+                .archive_until_quit(&context, quit_rx)
+                .await;
+
+            Ok(())
+        }
+    });
+
+    // Broadcast the scheduled announcements in the background until the server shuts down
+    join_set.spawn({
+        let context = Arc::clone(&context);
+        let quit_rx = quit_rx.resubscribe();
+
+        async move {
+            announcements
+                .announce_until_quit(&context.room_manager, quit_rx)
+                .await;
+
+            Ok(())
+        }
+    });
+
+    // Put the idle rooms to hibernation until the server shuts down, only rooms persisted in a log can be reloaded
+    match hibernate_after {
+        Some(hibernate_after) if room_logs => {
+            join_set.spawn({
+                let context = Arc::clone(&context);
+                let quit_rx = quit_rx.resubscribe();
+
+                async move {
+                    context
+                        .room_manager
+                        .hibernate_until_quit(hibernate_after, quit_rx)
+                        .await;
+
+                    Ok(())
+                }
+            });
+        }
+        Some(_) => {
+            logging::warn("Room hibernation needs CHAT_ROOM_LOGS=true, the rooms stay in memory")
+        }
+        None => {}
+    }
+
+    // Apply the provisioned rooms again on every SIGHUP until the server shuts down
+    if let Some(provisioning) = provisioning {
+        join_set.spawn({
+            let context = Arc::clone(&context);
+            let quit_rx = quit_rx.resubscribe();
+
+            async move { provisioning.reload_until_quit(&context, quit_rx).await }
+        });
+    }
+
+    // Tee the messages of the mirrored rooms to their files until the server shuts down
+    if context.mirror.is_some() {
+        join_set.spawn({
+            let context = Arc::clone(&context);
+            let quit_rx = quit_rx.resubscribe();
+
+            async move {
+                let Some(mirror) = context.mirror.as_ref() else {
+                    return Ok(());
+                };
+
+                mirror
+                    .mirror_until_quit(&context.room_manager, quit_rx)
+                    .await
+            }
+        });
+    }
+
+    // Preview the links of the messages sent to the rooms until the server shuts down
+    if let Some(link_previewer) = link_previewer {
+        join_set.spawn({
+            let context = Arc::clone(&context);
+            let quit_rx = quit_rx.resubscribe();
+
+            async move {
+                link_previewer
+                    .preview_until_quit(&context.room_manager, quit_rx)
+                    .await
+            }
+        });
+    }
+
+    // Accept the messages posted by integrations over HTTP until the server shuts down
+    if let Some(webhook_port) = webhook_port {
+        let webhook_addrs = config
+            .bind_addrs
+            .iter()
+            .map(|addr| SocketAddr::new(addr.ip(), webhook_port))
+            .collect();
+        join_set.spawn(webhooks::serve_until_quit(
+            Arc::clone(&context),
+            webhook_addrs,
+            quit_rx.resubscribe(),
+        ));
+        logging::info(format!("Accepting webhooks on port {}", webhook_port));
+    }
+
+    // Accept the sessions of local clients over a Unix domain socket as well until the server shuts down
+    #[cfg(unix)]
+    if let Some(path) = unix_socket::path_from_env() {
+        let listener = unix_socket::bind(&path).expect("could not bind the unix socket");
+        join_set.spawn(unix_socket::serve_until_quit(
+            Arc::clone(&context),
+            listener,
+            quit_rx.resubscribe(),
+        ));
+        logging::info(format!("Listening on unix socket {}", path.display()));
+    }
+
+    for listener in &listeners {
+        if let Ok(addr) = listener.local_addr() {
+            logging::info(format!("Listening on {}", addr));
+        }
+    }
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
     loop {
         tokio::select! {
-            Ok(_) = ctrl_c() => {
-                println!("Server interrupted. Gracefully shutting down.");
+            _ = &mut shutdown => {
+                logging::info("Server interrupted. Gracefully shutting down.");
                 quit_tx.send(()).context("failed to send quit signal").unwrap();
                 break;
             }
-            Ok((socket, _)) = server.accept() => {
-                join_set.spawn(session::handle_user_session(Arc::clone(&room_manager), quit_rx.resubscribe(), socket));
+            Ok((socket, _)) = listener::accept(&listeners) => {
+                join_set.spawn(session::handle_user_session(Arc::clone(&context), quit_rx.resubscribe(), socket));
             }
         }
     }
 
-    while join_set.join_next().await.is_some() {}
-    println!("Server shut down");
+    // No new sessions are accepted while the running ones wind down
+    drop(listeners);
+    let drain = async { while join_set.join_next().await.is_some() {} };
+    if tokio::time::timeout(config.shutdown_timeout, drain)
+        .await
+        .is_err()
+    {
+        logging::warn(format!(
+            "{} tasks were still running after {:?}, aborting them",
+            join_set.len(),
+            config.shutdown_timeout
+        ));
+        join_set.shutdown().await;
+    }
+    context.room_manager.close_room_logs().await;
+    logging::info("Server shut down");
+}
+
+/// Resolves once the server is asked to stop, by Ctrl+C or by a SIGTERM, e.g. from a container runtime
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut terminate =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+                .expect("could not listen for SIGTERM");
+        tokio::select! {
+            _ = ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = ctrl_c().await;
+}
+
+fn data_dir() -> String {
+    std::env::var("CHAT_DATA_DIR").unwrap_or_else(|_| String::from(DEFAULT_DATA_DIR))
+}
+
+/// Check the server running on this host, exiting with a non-zero status if it is unhealthy
+async fn healthcheck(config: &RuntimeConfig) -> ! {
+    let storage = DataKey::from_env().and_then(|data_key| {
+        Storage::new(data_dir()).map(|storage| storage.with_data_key(data_key))
+    });
+    let result = match storage {
+        Ok(storage) => healthcheck::check(config.local_addr(), &storage).await,
+        Err(err) => Err(err),
+    };
+
+    match result {
+        Ok(()) => {
+            println!("healthy");
+            std::process::exit(0);
+        }
+        Err(err) => {
+            eprintln!("unhealthy: {:#}", err);
+            std::process::exit(1);
+        }
+    }
 }
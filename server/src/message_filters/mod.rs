@@ -0,0 +1,126 @@
+mod wasm_plugin;
+
+use std::{fmt::Debug, path::Path};
+
+use anyhow::Context;
+
+pub use self::wasm_plugin::WasmPlugin;
+use crate::logging;
+
+/// The room of a filter applying to every room
+const ALL_ROOMS: &str = "*";
+
+/// [MessageFilter] decides whether a message may be sent to a room, e.g. to keep the language of the room clean
+pub trait MessageFilter: Debug + Send + Sync {
+    /// Returns true if the message of the user may be sent to the room
+    fn allows(&self, room: &str, user_id: &str, content: &str) -> anyhow::Result<bool>;
+}
+
+/// [MessageFilters] holds the filters the messages of every room pass before they are sent
+///
+/// Configured by the `CHAT_MESSAGE_FILTERS` environment variable, a comma separated list of rooms and the
+/// [WasmPlugin]s filtering their messages, e.g. `*=/etc/chat/spam.wasm,general=/etc/chat/language.wasm`.
+/// A filter of the room `*` applies to every room. A message is sent only if every filter of its room allows it,
+/// in the order they are listed. Encrypted rooms are left alone, the server can not read their messages.
+#[derive(Debug, Default)]
+pub struct MessageFilters {
+    /// The filters along with their room, None for the ones applying to every room
+    filters: Vec<(Option<String>, Box<dyn MessageFilter>)>,
+}
+
+impl MessageFilters {
+    /// Load the plugins listed by the environment, there are no filters unless it lists some
+    pub fn from_env() -> anyhow::Result<Self> {
+        let Ok(plugins) = std::env::var("CHAT_MESSAGE_FILTERS") else {
+            return Ok(MessageFilters::default());
+        };
+
+        plugins
+            .split(',')
+            .map(str::trim)
+            .filter(|plugin| !plugin.is_empty())
+            .try_fold(MessageFilters::default(), |filters, plugin| {
+                let (room, path) = plugin.split_once('=').with_context(|| {
+                    format!(
+                        "invalid message filter '{}', expected <room>=<path>",
+                        plugin
+                    )
+                })?;
+                let plugin = WasmPlugin::load(Path::new(path.trim()))?;
+
+                Ok(filters.with(room.trim(), plugin))
+            })
+    }
+
+    /// Filter the messages of the room with the given filter, or the ones of every room if the room is `*`
+    pub fn with(mut self, room: &str, filter: impl MessageFilter + 'static) -> Self {
+        let room = Some(room.trim_start_matches('#'))
+            .filter(|room| *room != ALL_ROOMS)
+            .map(String::from);
+
+        self.filters.push((room, Box::new(filter)));
+        self
+    }
+
+    /// Returns true if every filter of the room allows the message
+    /// A filter which fails, e.g. a plugin running out of fuel, is logged and skipped, so it can not silence the room
+    pub fn allows(&self, room: &str, user_id: &str, content: &str) -> bool {
+        self.filters
+            .iter()
+            .filter(|(filtered_room, _)| {
+                filtered_room
+                    .as_deref()
+                    .is_none_or(|filtered_room| filtered_room == room)
+            })
+            .all(|(_, filter)| match filter.allows(room, user_id, content) {
+                Ok(is_allowed) => is_allowed,
+                Err(err) => {
+                    logging::warn(format!("message filter {:?} has failed: {:#}", filter, err));
+                    true
+                }
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Blocks the messages containing the word
+    #[derive(Debug)]
+    struct BlockedWord(&'static str);
+
+    impl MessageFilter for BlockedWord {
+        fn allows(&self, _room: &str, _user_id: &str, content: &str) -> anyhow::Result<bool> {
+            Ok(!content.contains(self.0))
+        }
+    }
+
+    #[derive(Debug)]
+    struct Failing;
+
+    impl MessageFilter for Failing {
+        fn allows(&self, _room: &str, _user_id: &str, _content: &str) -> anyhow::Result<bool> {
+            anyhow::bail!("out of fuel")
+        }
+    }
+
+    #[test]
+    fn test_filters_apply_to_their_room_only() {
+        let filters = MessageFilters::default()
+            .with("*", BlockedWord("spam"))
+            .with("#general", BlockedWord("heck"));
+
+        assert!(!filters.allows("general", "alice", "buy spam"));
+        assert!(!filters.allows("rust", "alice", "buy spam"));
+        assert!(!filters.allows("general", "alice", "what the heck"));
+        assert!(filters.allows("rust", "alice", "what the heck"));
+    }
+
+    #[test]
+    fn test_failing_filters_let_messages_through() {
+        let filters = MessageFilters::default().with("*", Failing);
+
+        assert!(filters.allows("general", "alice", "hello"));
+    }
+}
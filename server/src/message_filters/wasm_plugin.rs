@@ -0,0 +1,209 @@
+use std::{
+    fmt,
+    path::Path,
+    sync::{Mutex, PoisonError},
+};
+
+use anyhow::Context;
+use wasmi::{
+    Config, Engine, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc,
+};
+
+use super::MessageFilter;
+
+/// The fuel a plugin may burn per message, roughly the number of instructions it runs
+const FUEL_PER_MESSAGE: u64 = 10_000_000;
+/// The most memory a plugin may grow to
+const MAX_MEMORY_BYTES: usize = 16 * 1024 * 1024;
+
+/// [WasmPlugin] is a [MessageFilter] compiled to WebAssembly, loaded when the server starts
+///
+/// The plugin exports its `memory` and two functions:
+/// - `alloc(len: i32) -> i32` reserves `len` bytes of its memory and returns where they start
+/// - `filter_message(room_ptr, room_len, user_ptr, user_len, content_ptr, content_len: i32) -> i32`
+///   returns 0 to let the message through and anything else to block it
+///
+/// The room, the user id and the content of every message are written as UTF-8 to the memory the plugin has
+/// reserved for them. The plugin is given no imports, so it can neither reach the file system nor the network,
+/// and it may only burn a limited amount of fuel per message. It is instantiated once, so it may keep state
+/// between messages, e.g. to count the messages of a user.
+pub struct WasmPlugin {
+    /// The file the plugin has been loaded from, to tell it apart in the logs
+    name: String,
+    instance: Mutex<PluginInstance>,
+}
+
+struct PluginInstance {
+    store: Store<StoreLimits>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    filter_message: TypedFunc<(i32, i32, i32, i32, i32, i32), i32>,
+}
+
+impl WasmPlugin {
+    /// Load the plugin from a `.wasm` file
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let wasm = std::fs::read(path)
+            .with_context(|| format!("could not read the plugin {}", path.display()))?;
+
+        Self::new(path.display().to_string(), &wasm)
+            .with_context(|| format!("could not load the plugin {}", path.display()))
+    }
+
+    /// Compile and instantiate the plugin, its start function runs with the fuel of a message
+    pub fn new(name: String, wasm: &[u8]) -> anyhow::Result<Self> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, wasm)?;
+
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(MAX_MEMORY_BYTES)
+            .build();
+        let mut store = Store::new(&engine, limits);
+        store.limiter(|limits| limits);
+        store
+            .set_fuel(FUEL_PER_MESSAGE)
+            .map_err(|err| anyhow::anyhow!("could not fuel the plugin: {}", err))?;
+        let instance = Linker::new(&engine)
+            .instantiate(&mut store, &module)?
+            .start(&mut store)?;
+
+        let memory = instance
+            .get_memory(&store, "memory")
+            .context("the plugin does not export its memory")?;
+        let alloc = instance.get_typed_func(&store, "alloc")?;
+        let filter_message = instance.get_typed_func(&store, "filter_message")?;
+
+        Ok(WasmPlugin {
+            name,
+            instance: Mutex::new(PluginInstance {
+                store,
+                memory,
+                alloc,
+                filter_message,
+            }),
+        })
+    }
+}
+
+impl PluginInstance {
+    /// Write the text to memory reserved by the plugin, returns where it starts and its length
+    fn write(&mut self, text: &str) -> anyhow::Result<(i32, i32)> {
+        let len = i32::try_from(text.len())?;
+        let ptr = self.alloc.call(&mut self.store, len)?;
+        self.memory
+            .write(&mut self.store, ptr as u32 as usize, text.as_bytes())
+            .map_err(|err| anyhow::anyhow!("the plugin has reserved invalid memory: {}", err))?;
+
+        Ok((ptr, len))
+    }
+}
+
+impl MessageFilter for WasmPlugin {
+    fn allows(&self, room: &str, user_id: &str, content: &str) -> anyhow::Result<bool> {
+        // the instance is left as it was by a panic while it was locked, it can still be called
+        let mut instance = self.instance.lock().unwrap_or_else(PoisonError::into_inner);
+        instance
+            .store
+            .set_fuel(FUEL_PER_MESSAGE)
+            .map_err(|err| anyhow::anyhow!("could not fuel the plugin: {}", err))?;
+
+        let (room_ptr, room_len) = instance.write(room)?;
+        let (user_ptr, user_len) = instance.write(user_id)?;
+        let (content_ptr, content_len) = instance.write(content)?;
+        let PluginInstance {
+            store,
+            filter_message,
+            ..
+        } = &mut *instance;
+        let verdict = filter_message.call(
+            store,
+            (
+                room_ptr,
+                room_len,
+                user_ptr,
+                user_len,
+                content_ptr,
+                content_len,
+            ),
+        )?;
+
+        Ok(verdict == 0)
+    }
+}
+
+impl fmt::Debug for WasmPlugin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("WasmPlugin").field(&self.name).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Blocks the messages starting with `!` sent to rooms starting with `g`, allocating past the first kilobyte
+    const BANG_FILTER: &str = r#"
+        (module
+            (memory (export "memory") 1)
+            (global $next (mut i32) (i32.const 1024))
+            (func (export "alloc") (param $len i32) (result i32)
+                (local $ptr i32)
+                (local.set $ptr (global.get $next))
+                (global.set $next (i32.add (global.get $next) (local.get $len)))
+                (local.get $ptr))
+            (func (export "filter_message")
+                (param $room i32) (param $room_len i32)
+                (param $user i32) (param $user_len i32)
+                (param $content i32) (param $content_len i32)
+                (result i32)
+                ;; the memory is reused for every message
+                (global.set $next (i32.const 1024))
+                (i32.and
+                    (i32.eq (i32.load8_u (local.get $room)) (i32.const 103))
+                    (i32.and
+                        (i32.gt_u (local.get $content_len) (i32.const 0))
+                        (i32.eq (i32.load8_u (local.get $content)) (i32.const 33))))))
+    "#;
+
+    /// Loops forever on every message
+    const LOOPING_FILTER: &str = r#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) (i32.const 0))
+            (func (export "filter_message")
+                (param i32 i32 i32 i32 i32 i32) (result i32)
+                (loop $forever (br $forever))
+                (i32.const 0)))
+    "#;
+
+    fn plugin(wat: &str) -> WasmPlugin {
+        WasmPlugin::new(String::from("test"), &wat::parse_str(wat).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_plugin_reads_the_message() {
+        let plugin = plugin(BANG_FILTER);
+
+        assert!(!plugin.allows("general", "alice", "!spam").unwrap());
+        assert!(plugin.allows("general", "alice", "hello!").unwrap());
+        assert!(plugin.allows("rust", "alice", "!spam").unwrap());
+        assert!(plugin.allows("general", "alice", "").unwrap());
+    }
+
+    #[test]
+    fn test_plugin_runs_out_of_fuel() {
+        let plugin = plugin(LOOPING_FILTER);
+
+        assert!(plugin.allows("general", "alice", "hello").is_err());
+        assert!(plugin.allows("general", "alice", "hello").is_err());
+    }
+
+    #[test]
+    fn test_plugin_must_export_its_functions() {
+        let wasm = wat::parse_str(r#"(module (memory (export "memory") 1))"#).unwrap();
+
+        assert!(WasmPlugin::new(String::from("test"), &wasm).is_err());
+    }
+}
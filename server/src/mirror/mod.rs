@@ -0,0 +1,409 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use comms::{event::Event, transport::server::EncodedEvent};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    fs::{File, OpenOptions},
+    io::AsyncWriteExt,
+    sync::{
+        broadcast::{self, error::RecvError},
+        Mutex,
+    },
+    task::JoinSet,
+};
+
+use crate::{clock::unix_millis_now, logging, room_manager::RoomManager};
+
+/// The size a mirror file grows to before it is rotated, unless overridden by `CHAT_MIRROR_MAX_BYTES`
+const DEFAULT_MAX_FILE_BYTES: u64 = 10 * 1024 * 1024;
+/// The number of rotated files kept next to the one being written, the oldest ones are deleted first
+const KEPT_ROTATED_FILES: usize = 5;
+
+/// A line of a mirror file
+#[derive(Debug, Serialize)]
+struct MirroredMessage<'a> {
+    room: &'a str,
+    id: &'a str,
+    user_id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    display_name: Option<&'a str>,
+    content: &'a str,
+    /// Milliseconds since the unix epoch, when the message was mirrored
+    timestamp: u64,
+}
+
+/// The author of a line of a mirror file, all a purge needs to know about it
+#[derive(Debug, Deserialize)]
+struct MirroredAuthor {
+    user_id: String,
+}
+
+/// [Mirror] tees the messages of some rooms to files on disk, one json object per line, for simple archiving setups
+///
+/// Configured by the environment, nothing is mirrored unless `CHAT_MIRROR_DIR` is set:
+/// - `CHAT_MIRROR_DIR` - the directory the files are written to, one `<room>.jsonl` per room
+/// - `CHAT_MIRROR_ROOMS` - the comma separated rooms to mirror, e.g. `general,ops`
+/// - `CHAT_MIRROR_MAX_BYTES` - the size a file is rotated at, the previous ones are kept as `<room>.jsonl.1` and so on
+///
+/// The files are independent of the history of the rooms, only purging a user removes messages from them.
+/// Encrypted rooms are mirrored as the clients sent their messages, i.e. encrypted.
+#[derive(Debug)]
+pub struct Mirror {
+    /// The file of every mirrored room, shared by its writer and the purges of users
+    files: BTreeMap<String, Arc<Mutex<RotatingFile>>>,
+}
+
+impl Mirror {
+    /// Read the mirror configuration from the environment, checking that its rooms exist
+    pub async fn from_env(room_manager: &RoomManager) -> anyhow::Result<Option<Self>> {
+        let Ok(dir) = std::env::var("CHAT_MIRROR_DIR") else {
+            return Ok(None);
+        };
+        let max_file_bytes = std::env::var("CHAT_MIRROR_MAX_BYTES")
+            .ok()
+            .map(|max_bytes| {
+                max_bytes
+                    .trim()
+                    .parse()
+                    .with_context(|| format!("invalid mirror file size '{}'", max_bytes))
+            })
+            .transpose()?
+            .unwrap_or(DEFAULT_MAX_FILE_BYTES);
+        let rooms = std::env::var("CHAT_MIRROR_ROOMS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|room| !room.is_empty())
+            .map(String::from)
+            .collect::<Vec<_>>();
+
+        if let Some(room) = rooms.iter().find(|room| {
+            !room_manager
+                .chat_room_metadata()
+                .iter()
+                .any(|metadata| &metadata.name == *room)
+        }) {
+            anyhow::bail!("there is no room named '{}' to mirror", room);
+        }
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("could not create the mirror directory {:?}", dir))?;
+
+        Self::open(Path::new(&dir), rooms, max_file_bytes)
+            .await
+            .map(Some)
+    }
+
+    /// Open the files of the rooms in the directory, rotated at the given size
+    async fn open(dir: &Path, rooms: Vec<String>, max_file_bytes: u64) -> anyhow::Result<Self> {
+        let mut files = BTreeMap::new();
+        for room in rooms {
+            let file =
+                RotatingFile::open(dir.join(format!("{room}.jsonl")), max_file_bytes).await?;
+            files.insert(room, Arc::new(Mutex::new(file)));
+        }
+
+        Ok(Mirror { files })
+    }
+
+    /// Write the messages of the rooms to their files as they are sent until the server shuts down
+    pub async fn mirror_until_quit(
+        &self,
+        room_manager: &RoomManager,
+        quit_rx: broadcast::Receiver<()>,
+    ) -> anyhow::Result<()> {
+        let mut join_set = JoinSet::new();
+        for (room, file) in self.files.iter() {
+            let events = room_manager.subscribe(room).await?;
+
+            join_set.spawn(mirror_room(
+                room.clone(),
+                events,
+                Arc::clone(file),
+                quit_rx.resubscribe(),
+            ));
+        }
+
+        while let Some(result) = join_set.join_next().await {
+            result??;
+        }
+
+        Ok(())
+    }
+
+    /// Remove the messages of the user from the files of every mirrored room, the rotated ones included
+    pub async fn purge_user(&self, user_id: &str) -> anyhow::Result<()> {
+        for (room, file) in self.files.iter() {
+            file.lock()
+                .await
+                .retain_lines(|line| {
+                    serde_json::from_slice::<MirroredAuthor>(line)
+                        .map_or(true, |author| author.user_id != user_id)
+                })
+                .await
+                .with_context(|| format!("could not purge the mirror of room '{}'", room))?;
+        }
+
+        Ok(())
+    }
+}
+
+async fn mirror_room(
+    room: String,
+    mut events: broadcast::Receiver<EncodedEvent>,
+    file: Arc<Mutex<RotatingFile>>,
+    mut quit_rx: broadcast::Receiver<()>,
+) -> anyhow::Result<()> {
+    loop {
+        let encoded_event = tokio::select! {
+            result = events.recv() => match result {
+                Ok(encoded_event) => encoded_event,
+                // the messages are gone from the channel, only how many of them can be told
+                Err(RecvError::Lagged(missed_events)) => {
+                    logging::warn(format!("the mirror of room '{}' missed {} events", room, missed_events));
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            },
+            _ = quit_rx.recv() => break,
+        };
+
+        let Ok(Event::UserMessage(message)) = encoded_event.decode() else {
+            continue;
+        };
+        let mut line = serde_json::to_vec(&MirroredMessage {
+            room: &message.room,
+            id: &message.id,
+            user_id: &message.user_id,
+            display_name: message.display_name.as_deref(),
+            content: &message.content,
+            timestamp: unix_millis_now(),
+        })?;
+        line.push(b'\n');
+
+        file.lock()
+            .await
+            .write_line(&line)
+            .await
+            .with_context(|| format!("could not mirror a message of room '{}'", room))?;
+    }
+
+    file.lock().await.flush().await
+}
+
+/// [RotatingFile] appends to a file, moving it aside once it has grown past the maximum size
+#[derive(Debug)]
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    len: u64,
+    max_len: u64,
+}
+
+impl RotatingFile {
+    async fn open(path: PathBuf, max_len: u64) -> anyhow::Result<Self> {
+        let file = Self::open_append(&path).await?;
+        let len = file.metadata().await?.len();
+
+        Ok(RotatingFile {
+            path,
+            file,
+            len,
+            max_len,
+        })
+    }
+
+    async fn open_append(path: &Path) -> anyhow::Result<File> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .with_context(|| format!("could not open the mirror file {:?}", path))
+    }
+
+    /// Append a line, rotating the file first if the line would not fit anymore
+    /// A line longer than the maximum size still gets a file of its own
+    async fn write_line(&mut self, line: &[u8]) -> anyhow::Result<()> {
+        if self.len > 0 && self.len + line.len() as u64 > self.max_len {
+            self.rotate().await?;
+        }
+
+        self.file.write_all(line).await?;
+        self.len += line.len() as u64;
+
+        Ok(())
+    }
+
+    /// Shift the rotated files by one, e.g. `.1` becomes `.2`, and start over with an empty file
+    async fn rotate(&mut self) -> anyhow::Result<()> {
+        self.file.flush().await?;
+
+        for idx in (1..KEPT_ROTATED_FILES).rev() {
+            let from = self.rotated_path(idx);
+            if tokio::fs::try_exists(&from).await? {
+                tokio::fs::rename(&from, self.rotated_path(idx + 1)).await?;
+            }
+        }
+        tokio::fs::rename(&self.path, self.rotated_path(1)).await?;
+
+        self.file = Self::open_append(&self.path).await?;
+        self.len = 0;
+
+        Ok(())
+    }
+
+    /// Rewrite the file and the rotated ones with the lines the predicate keeps, then go on appending
+    /// Every file is replaced at once, a crash leaves either the old or the new one
+    async fn retain_lines(&mut self, keep: impl Fn(&[u8]) -> bool) -> anyhow::Result<()> {
+        self.file.flush().await?;
+
+        let paths = std::iter::once(self.path.clone())
+            .chain((1..=KEPT_ROTATED_FILES).map(|idx| self.rotated_path(idx)))
+            .collect::<Vec<_>>();
+        for path in paths {
+            let content = match tokio::fs::read(&path).await {
+                Ok(content) => content,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err.into()),
+            };
+
+            let kept = content
+                .split_inclusive(|byte| *byte == b'\n')
+                .filter(|line| keep(line))
+                .flatten()
+                .copied()
+                .collect::<Vec<u8>>();
+            if kept.len() == content.len() {
+                continue;
+            }
+
+            let mut temp_path = path.clone().into_os_string();
+            temp_path.push(".tmp");
+            tokio::fs::write(&temp_path, &kept).await?;
+            tokio::fs::rename(&temp_path, &path).await?;
+        }
+
+        self.file = Self::open_append(&self.path).await?;
+        self.len = self.file.metadata().await?.len();
+
+        Ok(())
+    }
+
+    fn rotated_path(&self, idx: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{idx}"));
+
+        PathBuf::from(path)
+    }
+
+    async fn flush(&mut self) -> anyhow::Result<()> {
+        Ok(self.file.flush().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_files_are_rotated_once_full() {
+        let dir = std::env::temp_dir().join(format!("chat-mirror-{}", nanoid::nanoid!()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("general.jsonl");
+
+        let mut file = RotatingFile::open(path.clone(), 10).await.unwrap();
+        for line in ["first\n", "second\n", "third\n"] {
+            file.write_line(line.as_bytes()).await.unwrap();
+        }
+        file.flush().await.unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "third\n");
+        assert_eq!(
+            std::fs::read_to_string(dir.join("general.jsonl.1")).unwrap(),
+            "second\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("general.jsonl.2")).unwrap(),
+            "first\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_purged_users_are_removed_from_every_file() {
+        let dir = std::env::temp_dir().join(format!("chat-mirror-{}", nanoid::nanoid!()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mirror = Mirror::open(&dir, vec![String::from("general")], 100)
+            .await
+            .unwrap();
+        let file = Arc::clone(&mirror.files["general"]);
+
+        let line = |user_id: &str, content: &str| {
+            let mut line = serde_json::to_vec(&MirroredMessage {
+                room: "general",
+                id: content,
+                user_id,
+                display_name: None,
+                content,
+                timestamp: 1,
+            })
+            .unwrap();
+            line.push(b'\n');
+            line
+        };
+        for (user_id, content) in [("alice", "hi"), ("bob", "hello"), ("alice", "bye")] {
+            file.lock()
+                .await
+                .write_line(&line(user_id, content))
+                .await
+                .unwrap();
+        }
+
+        mirror.purge_user("alice").await.unwrap();
+        file.lock()
+            .await
+            .write_line(&line("bob", "still here"))
+            .await
+            .unwrap();
+        file.lock().await.flush().await.unwrap();
+
+        let lines = std::fs::read_dir(&dir)
+            .unwrap()
+            .flat_map(|entry| {
+                let content = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+                content.lines().map(String::from).collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert!(lines.iter().all(|line| !line.contains("alice")));
+        assert_eq!(lines.len(), 2);
+        assert!(std::fs::read_to_string(dir.join("general.jsonl"))
+            .unwrap()
+            .contains("still here"));
+    }
+
+    #[tokio::test]
+    async fn test_only_the_latest_rotated_files_are_kept() {
+        let dir = std::env::temp_dir().join(format!("chat-mirror-{}", nanoid::nanoid!()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("general.jsonl");
+
+        let mut file = RotatingFile::open(path.clone(), 1).await.unwrap();
+        for idx in 0..KEPT_ROTATED_FILES + 3 {
+            file.write_line(format!("{idx}\n").as_bytes())
+                .await
+                .unwrap();
+        }
+        file.flush().await.unwrap();
+
+        assert!(dir
+            .join(format!("general.jsonl.{KEPT_ROTATED_FILES}"))
+            .exists());
+        assert!(!dir
+            .join(format!("general.jsonl.{}", KEPT_ROTATED_FILES + 1))
+            .exists());
+    }
+}
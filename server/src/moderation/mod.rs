@@ -0,0 +1,197 @@
+use comms::event::MessageReport;
+use tokio::sync::Mutex;
+
+use crate::storage::Storage;
+
+/// The name of the storage document the open reports are persisted in
+const REPORTS_DOCUMENT: &str = "reports";
+/// How many reports of a user may be open at once, more are refused until moderators resolve some
+const MAX_OPEN_REPORTS_PER_REPORTER: usize = 5;
+
+/// What filing a report has come to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilingOutcome {
+    /// The report has been put in the queue
+    Filed,
+    /// The user has already reported the message and the report is still open
+    AlreadyReported,
+    /// The user has [MAX_OPEN_REPORTS_PER_REPORTER] open reports already
+    TooManyOpenReports,
+}
+
+/// [ModerationQueue] keeps the messages reported by users until a moderator resolves them
+///
+/// Moderators are the admins, who look after every room, and the owners of the room a message was sent to.
+/// Every change is persisted to the [Storage], which the limit on the open reports of a user keeps small.
+#[derive(Debug)]
+pub struct ModerationQueue {
+    storage: Storage,
+    reports: Mutex<Vec<MessageReport>>,
+}
+
+impl ModerationQueue {
+    /// Load the open reports from the storage
+    pub async fn load(storage: Storage) -> anyhow::Result<Self> {
+        let reports = storage.load(REPORTS_DOCUMENT).await?;
+
+        Ok(ModerationQueue {
+            storage,
+            reports: Mutex::new(reports),
+        })
+    }
+
+    /// Put a report at the end of the queue, unless the user who filed it has too many open reports
+    /// or has already reported the message
+    pub async fn file(&self, report: MessageReport) -> anyhow::Result<FilingOutcome> {
+        let mut reports = self.reports.lock().await;
+
+        let open_reports = reports
+            .iter()
+            .filter(|open_report| open_report.reported_by == report.reported_by)
+            .collect::<Vec<_>>();
+        if open_reports
+            .iter()
+            .any(|open_report| open_report.message_id == report.message_id)
+        {
+            return Ok(FilingOutcome::AlreadyReported);
+        }
+        if open_reports.len() >= MAX_OPEN_REPORTS_PER_REPORTER {
+            return Ok(FilingOutcome::TooManyOpenReports);
+        }
+
+        reports.push(report);
+        self.storage.save(REPORTS_DOCUMENT, &*reports).await?;
+
+        Ok(FilingOutcome::Filed)
+    }
+
+    /// The open reports about the rooms matching the filter, from the oldest to the newest
+    pub async fn open_reports(&self, is_included: impl Fn(&str) -> bool) -> Vec<MessageReport> {
+        self.reports
+            .lock()
+            .await
+            .iter()
+            .filter(|report| is_included(&report.room))
+            .cloned()
+            .collect()
+    }
+
+    /// The room the report is about, if it is still open
+    pub async fn room_of(&self, report_id: &str) -> Option<String> {
+        self.reports
+            .lock()
+            .await
+            .iter()
+            .find(|report| report.id == report_id)
+            .map(|report| report.room.clone())
+    }
+
+    /// Drop the reports about the messages of the user and the ones they have filed, e.g. when their data is deleted
+    pub async fn forget(&self, user_id: &str) -> anyhow::Result<()> {
+        let mut reports = self.reports.lock().await;
+
+        let count = reports.len();
+        reports.retain(|report| report.user_id != user_id && report.reported_by != user_id);
+
+        if reports.len() < count {
+            self.storage.save(REPORTS_DOCUMENT, &*reports).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove the report from the queue
+    /// Returns false if there was no open report with the id
+    pub async fn resolve(&self, report_id: &str) -> anyhow::Result<bool> {
+        let mut reports = self.reports.lock().await;
+
+        let count = reports.len();
+        reports.retain(|report| report.id != report_id);
+        let is_resolved = reports.len() < count;
+
+        if is_resolved {
+            self.storage.save(REPORTS_DOCUMENT, &*reports).await?;
+        }
+
+        Ok(is_resolved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn moderation_queue() -> ModerationQueue {
+        let storage =
+            Storage::new(std::env::temp_dir().join(format!("chat-reports-{}", nanoid::nanoid!())))
+                .unwrap();
+
+        ModerationQueue::load(storage).await.unwrap()
+    }
+
+    fn report(message_id: &str, reported_by: &str) -> MessageReport {
+        MessageReport {
+            id: nanoid::nanoid!(8),
+            room: String::from("general"),
+            message_id: String::from(message_id),
+            user_id: String::from("spammer"),
+            content: String::from("buy now"),
+            reason: String::from("spam"),
+            reported_by: String::from(reported_by),
+            reported_at: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_a_message_is_reported_once_per_reporter() {
+        let moderation_queue = moderation_queue().await;
+
+        assert_eq!(
+            moderation_queue.file(report("m1", "alice")).await.unwrap(),
+            FilingOutcome::Filed
+        );
+        assert_eq!(
+            moderation_queue.file(report("m1", "alice")).await.unwrap(),
+            FilingOutcome::AlreadyReported
+        );
+        assert_eq!(
+            moderation_queue.file(report("m1", "bob")).await.unwrap(),
+            FilingOutcome::Filed
+        );
+        assert_eq!(moderation_queue.open_reports(|_| true).await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_reporters_have_a_limited_number_of_open_reports() {
+        let moderation_queue = moderation_queue().await;
+        for nth in 0..MAX_OPEN_REPORTS_PER_REPORTER {
+            let filed = moderation_queue
+                .file(report(&format!("m{nth}"), "alice"))
+                .await
+                .unwrap();
+            assert_eq!(filed, FilingOutcome::Filed);
+        }
+
+        let first_report_id = moderation_queue.open_reports(|_| true).await[0].id.clone();
+        assert_eq!(
+            moderation_queue
+                .file(report("last", "alice"))
+                .await
+                .unwrap(),
+            FilingOutcome::TooManyOpenReports
+        );
+        // the others are not held back by alice, and alice can report again once a report is resolved
+        assert_eq!(
+            moderation_queue.file(report("last", "bob")).await.unwrap(),
+            FilingOutcome::Filed
+        );
+        assert!(moderation_queue.resolve(&first_report_id).await.unwrap());
+        assert_eq!(
+            moderation_queue
+                .file(report("last", "alice"))
+                .await
+                .unwrap(),
+            FilingOutcome::Filed
+        );
+    }
+}
@@ -0,0 +1,300 @@
+use comms::event::NameRejectionReason;
+
+/// Names shorter than this are refused, a single character is too easy to mistake for another
+const MIN_NAME_LENGTH: usize = 2;
+
+/// Names the server keeps for itself, so nobody can pose as the staff of the server
+/// Compared by their skeleton, e.g. `Adm1n` is reserved as well.
+const RESERVED_NAMES: &[&str] = &[
+    "admin",
+    "administrator",
+    "bot",
+    "everyone",
+    "here",
+    "moderator",
+    "owner",
+    "root",
+    "server",
+    "support",
+    "system",
+    "webhook",
+];
+
+/// What a name is given to, each kind allows its own characters and length
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NameKind {
+    /// The id of a human user, as generated or given by the identity provider, e.g. an email address
+    UserId,
+    /// The name of a bot, its user id is prefixed with `bot:`
+    BotName,
+    /// The name of a chat room, e.g. `rust`
+    RoomName,
+    /// The name shown instead of a user id, e.g. for a message posted through a webhook
+    DisplayName,
+}
+
+impl NameKind {
+    fn max_length(&self) -> usize {
+        match self {
+            NameKind::UserId => 64,
+            NameKind::BotName | NameKind::RoomName | NameKind::DisplayName => 32,
+        }
+    }
+
+    fn allows(&self, character: char) -> bool {
+        if is_invisible(character) {
+            return false;
+        }
+
+        match self {
+            NameKind::UserId => character.is_alphanumeric() || "-_.@|+".contains(character),
+            NameKind::BotName | NameKind::RoomName => {
+                character.is_alphanumeric() || "-_".contains(character)
+            }
+            NameKind::DisplayName => character == ' ' || !character.is_whitespace(),
+        }
+    }
+}
+
+/// Characters which are not seen, or change how the text around them is shown, e.g. right-to-left overrides
+fn is_invisible(character: char) -> bool {
+    character.is_control()
+        || matches!(
+            character,
+            '\u{00AD}'
+                | '\u{034F}'
+                | '\u{180E}'
+                | '\u{200B}'..='\u{200F}'
+                | '\u{202A}'..='\u{202E}'
+                | '\u{2060}'..='\u{206F}'
+                | '\u{FE00}'..='\u{FE0F}'
+                | '\u{FEFF}'
+        )
+}
+
+/// Maps a lowercase character to the latin letter it looks like, e.g. the cyrillic `а` to `a`
+fn unconfuse(character: char) -> char {
+    match character {
+        // letters of other scripts looking like latin ones
+        'а' | 'α' => 'a',
+        'в' | 'β' => 'b',
+        'с' | 'ϲ' => 'c',
+        'ԁ' => 'd',
+        'е' | 'ё' | 'ε' => 'e',
+        'һ' => 'h',
+        'і' | 'ї' | 'ι' | 'ı' => 'l',
+        'ј' => 'j',
+        'к' | 'κ' => 'k',
+        'м' => 'm',
+        'η' => 'n',
+        'о' | 'ο' | 'σ' | '0' => 'o',
+        'р' | 'ρ' => 'p',
+        'ѕ' => 's',
+        'т' | 'τ' => 't',
+        'υ' => 'u',
+        'ν' => 'v',
+        'ѡ' | 'ω' => 'w',
+        'х' | 'χ' => 'x',
+        'у' | 'γ' => 'y',
+        // letters and digits looking like a lowercase l
+        'i' | '1' | '|' | 'ӏ' => 'l',
+        // separators are easily swapped for one another
+        '_' | '.' | ' ' => '-',
+        // fullwidth forms of the ascii characters, e.g. `ａ`, which may look like another letter in turn
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(character as u32 - 0xFEE0)
+            .map(unconfuse)
+            .unwrap_or(character),
+        character => character,
+    }
+}
+
+/// The skeleton of a name, two names with the same skeleton look alike
+/// e.g. `Admin`, `аdmin` with a cyrillic `а` and `adm1n` are all `admln`
+pub fn skeleton(name: &str) -> String {
+    name.chars()
+        .flat_map(char::to_lowercase)
+        .map(unconfuse)
+        .collect::<String>()
+        .replace("rn", "m")
+        .replace("vv", "w")
+}
+
+/// Checks the length and the characters of the name, and that it does not look like a reserved name
+pub fn validate(name: &str, kind: NameKind) -> Result<(), NameRejectionReason> {
+    let length = name.chars().count();
+    if length < MIN_NAME_LENGTH {
+        return Err(NameRejectionReason::TooShort {
+            min_length: MIN_NAME_LENGTH,
+        });
+    }
+    if length > kind.max_length() {
+        return Err(NameRejectionReason::TooLong {
+            max_length: kind.max_length(),
+        });
+    }
+
+    if let Some(character) = name.chars().find(|character| !kind.allows(*character)) {
+        return Err(NameRejectionReason::InvalidCharacter { character });
+    }
+
+    let skeleton = skeleton(name);
+    if RESERVED_NAMES
+        .iter()
+        .any(|reserved_name| self::skeleton(reserved_name) == skeleton)
+    {
+        return Err(NameRejectionReason::Reserved);
+    }
+
+    Ok(())
+}
+
+/// Returns the first of the taken names the name can be mistaken for, the name itself is not a match
+pub fn find_confusable<'a>(
+    name: &str,
+    taken_names: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let skeleton = skeleton(name);
+
+    taken_names
+        .into_iter()
+        .find(|taken_name| *taken_name != name && self::skeleton(taken_name) == skeleton)
+}
+
+/// Validates the name and checks that it can not be mistaken for one of the taken names
+pub fn validate_new<'a>(
+    name: &str,
+    kind: NameKind,
+    taken_names: impl IntoIterator<Item = &'a str>,
+) -> Result<(), NameRejectionReason> {
+    validate(name, kind)?;
+
+    match find_confusable(name, taken_names) {
+        Some(taken_name) => Err(NameRejectionReason::Confusable {
+            name: String::from(taken_name),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// A human readable explanation of the rejection, e.g. for clients which do not word it themselves
+pub fn rejection_message(name: &str, reason: &NameRejectionReason) -> String {
+    match reason {
+        NameRejectionReason::TooShort { min_length } => {
+            format!("'{}' is shorter than {} characters", name, min_length)
+        }
+        NameRejectionReason::TooLong { max_length } => {
+            format!("'{}' is longer than {} characters", name, max_length)
+        }
+        NameRejectionReason::InvalidCharacter { character } => format!(
+            "'{}' contains the character {:?}, which is not allowed",
+            name, character
+        ),
+        NameRejectionReason::Reserved => format!("'{}' is reserved", name),
+        NameRejectionReason::Confusable { name: taken_name } => format!(
+            "'{}' can be mistaken for '{}', which is already taken",
+            name, taken_name
+        ),
+        NameRejectionReason::Taken => format!("'{}' is already taken", name),
+    }
+}
+
+/// Checks the names of the rooms, e.g. read from the metadata, and that no two rooms look alike
+pub fn validate_room_names<'a>(
+    room_names: impl IntoIterator<Item = &'a str>,
+) -> anyhow::Result<()> {
+    let mut valid_names = Vec::new();
+
+    for room_name in room_names {
+        if let Err(reason) =
+            validate_new(room_name, NameKind::RoomName, valid_names.iter().copied())
+        {
+            anyhow::bail!(
+                "invalid room name, {}",
+                rejection_message(room_name, &reason)
+            );
+        }
+        valid_names.push(room_name);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_names() {
+        assert_eq!(validate("alice", NameKind::UserId), Ok(()));
+        assert_eq!(validate("alice@example.com", NameKind::UserId), Ok(()));
+        assert_eq!(validate("auth0|6512bd43", NameKind::UserId), Ok(()));
+        assert_eq!(validate("Zoë", NameKind::UserId), Ok(()));
+        assert_eq!(validate("career-advice", NameKind::RoomName), Ok(()));
+        assert_eq!(validate("Grafana Alerts", NameKind::DisplayName), Ok(()));
+    }
+
+    #[test]
+    fn test_invalid_names() {
+        assert_eq!(
+            validate("a", NameKind::UserId),
+            Err(NameRejectionReason::TooShort { min_length: 2 })
+        );
+        assert_eq!(
+            validate(&"a".repeat(33), NameKind::RoomName),
+            Err(NameRejectionReason::TooLong { max_length: 32 })
+        );
+        assert_eq!(
+            validate("open src", NameKind::RoomName),
+            Err(NameRejectionReason::InvalidCharacter { character: ' ' })
+        );
+        assert_eq!(
+            validate("ali\u{200B}ce", NameKind::UserId),
+            Err(NameRejectionReason::InvalidCharacter {
+                character: '\u{200B}'
+            })
+        );
+        assert_eq!(
+            validate("bob\u{202E}", NameKind::DisplayName),
+            Err(NameRejectionReason::InvalidCharacter {
+                character: '\u{202E}'
+            })
+        );
+    }
+
+    #[test]
+    fn test_reserved_names() {
+        for name in ["admin", "Admin", "аdmin", "adm1n", "ＡＤＭＩＮ", "Ѕуѕtеm"] {
+            assert_eq!(
+                validate(name, NameKind::UserId)
+                    .map_err(|reason| reason == NameRejectionReason::Reserved),
+                Err(true),
+                "{} should be reserved",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_confusable_names() {
+        let taken_names = ["alice", "modern"];
+
+        assert_eq!(find_confusable("alice", taken_names), None);
+        assert_eq!(find_confusable("bob", taken_names), None);
+        assert_eq!(find_confusable("AIice", taken_names), Some("alice"));
+        assert_eq!(find_confusable("аlicе", taken_names), Some("alice"));
+        assert_eq!(find_confusable("modem", taken_names), Some("modern"));
+        assert_eq!(
+            validate_new("al1ce", NameKind::UserId, taken_names),
+            Err(NameRejectionReason::Confusable {
+                name: String::from("alice")
+            })
+        );
+    }
+
+    #[test]
+    fn test_room_names() {
+        assert!(validate_room_names(["rust", "general"]).is_ok());
+        assert!(validate_room_names(["rust", "Rust"]).is_err());
+        assert!(validate_room_names(["rust", "admin"]).is_err());
+    }
+}
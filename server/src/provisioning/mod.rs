@@ -0,0 +1,426 @@
+use std::{
+    collections::HashSet,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use comms::event::{self, Event, LagPolicy};
+use serde::Deserialize;
+use tokio::sync::broadcast;
+
+use crate::{
+    context::ServerContext,
+    logging, names,
+    room_manager::{ChatRoomMetadata, RoomManager},
+};
+
+/// Who can see and join a provisioned room
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Visibility {
+    /// Every user
+    #[default]
+    Public,
+    /// The members of the groups of the room, its owners and the users invited to it
+    Private,
+}
+
+/// A room as listed in the provisioning file
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProvisionedRoom {
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(default)]
+    visibility: Visibility,
+    /// The groups granted access to a private room
+    #[serde(default)]
+    groups: Vec<String>,
+    /// The maximum number of unique users in the room at the same time, unlimited if not set
+    #[serde(default)]
+    capacity: Option<usize>,
+    #[serde(default)]
+    owners: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProvisioningFile {
+    rooms: Vec<ProvisionedRoom>,
+}
+
+impl ProvisionedRoom {
+    /// The metadata of the room once provisioned, the settings the file does not cover are left as they are
+    fn apply_to(&self, metadata: &ChatRoomMetadata) -> ChatRoomMetadata {
+        ChatRoomMetadata {
+            description: self.description.clone(),
+            category: self.category.clone(),
+            capacity: self.capacity.filter(|capacity| *capacity > 0),
+            owners: self.owners.clone(),
+            groups: self.groups.clone(),
+            ..metadata.clone()
+        }
+    }
+
+    /// The metadata of a room created by the provisioning
+    fn to_metadata(&self) -> ChatRoomMetadata {
+        self.apply_to(&ChatRoomMetadata {
+            name: self.name.clone(),
+            description: String::new(),
+            category: None,
+            capacity: None,
+            welcome_message: None,
+            owners: Vec::new(),
+            groups: Vec::new(),
+            record_participation: false,
+            encrypted: false,
+            requires_approval: false,
+            broadcast_capacity: None,
+            lag_policy: LagPolicy::default(),
+        })
+    }
+}
+
+/// The settings which differ between the two versions of the metadata of a room
+fn changed_settings(
+    current: &ChatRoomMetadata,
+    provisioned: &ChatRoomMetadata,
+) -> Vec<&'static str> {
+    [
+        (
+            "description",
+            current.description != provisioned.description,
+        ),
+        ("category", current.category != provisioned.category),
+        ("visibility", current.groups != provisioned.groups),
+        ("capacity", current.capacity != provisioned.capacity),
+        ("owners", current.owners != provisioned.owners),
+    ]
+    .into_iter()
+    .filter(|(_, is_changed)| *is_changed)
+    .map(|(setting, _)| setting)
+    .collect()
+}
+
+/// [ProvisioningReport] tells what applying the provisioning file has changed, logged every time it is applied
+#[derive(Debug, Default, PartialEq)]
+pub struct ProvisioningReport {
+    /// The rooms which did not exist before
+    pub created: Vec<String>,
+    /// The rooms whose settings have changed, along with the settings
+    pub updated: Vec<(String, Vec<&'static str>)>,
+    /// The number of provisioned rooms which were already as the file lists them
+    pub unchanged: usize,
+    /// The rooms which exist but are not listed in the file, they are left as they are
+    pub unlisted: Vec<String>,
+}
+
+impl ProvisioningReport {
+    pub fn has_changes(&self) -> bool {
+        !self.created.is_empty() || !self.updated.is_empty()
+    }
+}
+
+impl fmt::Display for ProvisioningReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if !self.created.is_empty() {
+            parts.push(format!("created {}", self.created.join(", ")));
+        }
+        if !self.updated.is_empty() {
+            let updated = self
+                .updated
+                .iter()
+                .map(|(room, settings)| format!("{} ({})", room, settings.join(", ")))
+                .collect::<Vec<_>>();
+            parts.push(format!("updated {}", updated.join(", ")));
+        }
+        parts.push(format!("{} unchanged", self.unchanged));
+        // there may be many rooms left out of the file, e.g. the ones of `CHAT_ROOMS`
+        if !self.unlisted.is_empty() {
+            parts.push(format!("{} not provisioned", self.unlisted.len()));
+        }
+
+        write!(f, "{}", parts.join("; "))
+    }
+}
+
+/// [RoomProvisioning] creates and configures the rooms listed in a YAML file, e.g. kept in the repository of a team
+///
+/// Read from the file at the `CHAT_PROVISIONED_ROOMS` environment variable, a `rooms` section listing every room
+/// with its `name`, `description`, `category`, `visibility` (`public` or `private`), the `groups` granted access
+/// to a private room, its `capacity` and its `owners`. The file is applied when the server starts, on top of the
+/// rooms of `CHAT_ROOMS`, and again whenever the server receives a SIGHUP. Applying it is idempotent: missing rooms
+/// are created, the listed settings of the others are brought in line with the file, and rooms missing from the
+/// file are left as they are, rooms are never deleted while the server runs. A file which does not parse or
+/// names invalid rooms changes nothing.
+#[derive(Debug)]
+pub struct RoomProvisioning {
+    path: PathBuf,
+}
+
+impl RoomProvisioning {
+    /// The provisioning file given by the environment, None if there is none
+    pub fn from_env() -> Option<Self> {
+        std::env::var("CHAT_PROVISIONED_ROOMS")
+            .ok()
+            .map(|path| RoomProvisioning {
+                path: PathBuf::from(path),
+            })
+    }
+
+    /// Read and apply the provisioning file to the rooms, checking every room before changing any
+    pub async fn apply(&self, room_manager: &RoomManager) -> anyhow::Result<ProvisioningReport> {
+        let rooms = load(&self.path)?;
+        let current = room_manager.chat_room_metadata().clone();
+
+        // the new rooms must not look like each other nor like the existing ones
+        let new_rooms = rooms
+            .iter()
+            .filter(|room| !current.iter().any(|metadata| metadata.name == room.name))
+            .map(|room| room.name.as_str());
+        names::validate_room_names(
+            current
+                .iter()
+                .map(|metadata| metadata.name.as_str())
+                .chain(new_rooms),
+        )?;
+
+        let mut report = ProvisioningReport::default();
+        for room in rooms.iter() {
+            match current.iter().find(|metadata| metadata.name == room.name) {
+                Some(metadata) => {
+                    let provisioned = room.apply_to(metadata);
+                    let changed_settings = changed_settings(metadata, &provisioned);
+                    if changed_settings.is_empty() {
+                        report.unchanged += 1;
+                    } else {
+                        room_manager.replace_metadata(provisioned).await?;
+                        report.updated.push((room.name.clone(), changed_settings));
+                    }
+                }
+                None => {
+                    room_manager.create_room(room.to_metadata())?;
+                    report.created.push(room.name.clone());
+                }
+            }
+        }
+        report.unlisted = current
+            .into_iter()
+            .filter(|metadata| !rooms.iter().any(|room| room.name == metadata.name))
+            .map(|metadata| metadata.name)
+            .collect();
+
+        Ok(report)
+    }
+
+    /// Apply the provisioning file again whenever the server receives a SIGHUP, until the server shuts down
+    /// The connected users are sent their new list of rooms, and the audience of an updated room its new metadata.
+    pub async fn reload_until_quit(
+        &self,
+        context: &ServerContext,
+        mut quit_rx: broadcast::Receiver<()>,
+    ) -> anyhow::Result<()> {
+        #[cfg(unix)]
+        {
+            let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+                .context("could not listen for SIGHUP")?;
+
+            loop {
+                tokio::select! {
+                    _ = hangup.recv() => self.reload(context).await,
+                    _ = quit_rx.recv() => break,
+                }
+            }
+        }
+
+        #[cfg(not(unix))]
+        let _ = (context, quit_rx.recv().await);
+
+        Ok(())
+    }
+
+    async fn reload(&self, context: &ServerContext) {
+        let report = match self.apply(&context.room_manager).await {
+            Ok(report) => report,
+            Err(err) => {
+                logging::error(format!("could not reload the provisioned rooms: {:#}", err));
+                return;
+            }
+        };
+        logging::info(format!("Reloaded the provisioned rooms: {}", report));
+        if !report.has_changes() {
+            return;
+        }
+
+        for (room, settings) in report.updated.iter() {
+            if !settings.contains(&"description") && !settings.contains(&"capacity") {
+                continue;
+            }
+
+            let Some(metadata) = context
+                .room_manager
+                .chat_room_metadata()
+                .iter()
+                .find(|metadata| &metadata.name == room)
+                .cloned()
+            else {
+                continue;
+            };
+            let event = Event::RoomMetadataUpdated(event::RoomMetadataUpdatedBroadcastEvent {
+                room: room.clone(),
+                description: metadata.description,
+                capacity: metadata.capacity,
+            });
+            context.notify_room_audience(room, &event).await;
+        }
+
+        for user_id in context.session_registry.connected_user_ids() {
+            let rooms = context.accessible_rooms(&user_id).await;
+            context.session_registry.send_to_user(
+                &user_id,
+                &Event::AccessibleRoomsChanged(event::AccessibleRoomsChangedReplyEvent { rooms }),
+            );
+        }
+    }
+}
+
+/// Read the rooms of the provisioning file, checking that they can be provisioned
+fn load(path: &Path) -> anyhow::Result<Vec<ProvisionedRoom>> {
+    let bytes = std::fs::read(path)
+        .with_context(|| format!("could not read the provisioned rooms {:?}", path))?;
+    let file: ProvisioningFile = serde_yaml::from_slice(&bytes)
+        .with_context(|| format!("could not parse the provisioned rooms {:?}", path))?;
+
+    validate(&file.rooms)?;
+
+    Ok(file.rooms)
+}
+
+fn validate(rooms: &[ProvisionedRoom]) -> anyhow::Result<()> {
+    let mut names = HashSet::new();
+    for room in rooms {
+        anyhow::ensure!(
+            names.insert(room.name.as_str()),
+            "the room '{}' is provisioned twice",
+            room.name
+        );
+
+        match room.visibility {
+            Visibility::Public => anyhow::ensure!(
+                room.groups.is_empty(),
+                "the public room '{}' can not be limited to groups, make it private",
+                room.name
+            ),
+            // a room without groups is open to everyone, see [ChatRoomMetadata::is_accessible_by]
+            Visibility::Private => anyhow::ensure!(
+                !room.groups.is_empty(),
+                "the private room '{}' grants access to no groups",
+                room.name
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::room_manager::RoomManagerBuilder;
+
+    const ROOMS: &str = r#"
+rooms:
+  - name: general
+    description: Everything else
+  - name: staff
+    description: For the staff only
+    visibility: private
+    groups: [staff]
+    capacity: 20
+    owners: [alice]
+"#;
+
+    fn provisioning(yaml: &str) -> RoomProvisioning {
+        let path = std::env::temp_dir().join(format!("chat-rooms-{}.yaml", nanoid::nanoid!()));
+        std::fs::write(&path, yaml).unwrap();
+
+        RoomProvisioning { path }
+    }
+
+    fn room_manager() -> RoomManager {
+        RoomManagerBuilder::new()
+            .create_room(
+                ProvisionedRoom {
+                    name: String::from("general"),
+                    description: String::from("General discussions"),
+                    category: None,
+                    visibility: Visibility::Public,
+                    groups: Vec::new(),
+                    capacity: None,
+                    owners: Vec::new(),
+                }
+                .to_metadata(),
+            )
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_provisioning_is_idempotent() {
+        let provisioning = provisioning(ROOMS);
+        let room_manager = room_manager();
+
+        let report = provisioning.apply(&room_manager).await.unwrap();
+        assert_eq!(report.created, vec![String::from("staff")]);
+        assert_eq!(
+            report.updated,
+            vec![(String::from("general"), vec!["description"])]
+        );
+        assert_eq!(report.unchanged, 0);
+
+        let staff = room_manager
+            .chat_room_metadata()
+            .iter()
+            .find(|metadata| metadata.name == "staff")
+            .cloned()
+            .unwrap();
+        assert_eq!(staff.groups, vec![String::from("staff")]);
+        assert_eq!(staff.capacity, Some(20));
+        assert_eq!(staff.owners, vec![String::from("alice")]);
+
+        let report = provisioning.apply(&room_manager).await.unwrap();
+        assert!(!report.has_changes());
+        assert_eq!(report.unchanged, 2);
+    }
+
+    #[tokio::test]
+    async fn test_unlisted_rooms_are_left_alone() {
+        let provisioning = provisioning("rooms:\n  - name: rust\n");
+        let room_manager = room_manager();
+
+        let report = provisioning.apply(&room_manager).await.unwrap();
+        assert_eq!(report.created, vec![String::from("rust")]);
+        assert_eq!(report.unlisted, vec![String::from("general")]);
+        assert_eq!(room_manager.chat_room_metadata().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_files_change_nothing() {
+        let room_manager = room_manager();
+
+        for yaml in [
+            "rooms:\n  - name: rust\n  - name: rust\n",
+            "rooms:\n  - name: rust\n  - name: staff\n    visibility: private\n",
+            "rooms:\n  - name: rust\n  - name: general\n    groups: [staff]\n",
+            "rooms:\n  - name: rust\n  - name: Genera1\n",
+            "rooms:\n  - name: rust\n    topic: Rust\n",
+        ] {
+            assert!(provisioning(yaml).apply(&room_manager).await.is_err());
+        }
+        assert_eq!(room_manager.chat_room_metadata().len(), 1);
+    }
+}
@@ -0,0 +1,73 @@
+use std::collections::BTreeMap;
+
+use tokio::sync::Mutex;
+
+use crate::storage::Storage;
+
+/// The name of the storage document the read markers are persisted in
+const READ_MARKERS_DOCUMENT: &str = "read_markers";
+
+/// [ReadMarkers] keeps the time each user has last read each room
+///
+/// The markers are shared by all sessions of a user, so that every device agrees on the unread rooms.
+/// Every change is persisted to the [Storage].
+#[derive(Debug)]
+pub struct ReadMarkers {
+    storage: Storage,
+    user_id_to_markers: Mutex<BTreeMap<String, BTreeMap<String, u64>>>,
+}
+
+impl ReadMarkers {
+    /// Load the read markers from the storage
+    pub async fn load(storage: Storage) -> anyhow::Result<Self> {
+        let user_id_to_markers = storage.load(READ_MARKERS_DOCUMENT).await?;
+
+        Ok(ReadMarkers {
+            storage,
+            user_id_to_markers: Mutex::new(user_id_to_markers),
+        })
+    }
+
+    /// The rooms the user has read alongside the time they have last read them
+    pub async fn markers_of(&self, user_id: &str) -> BTreeMap<String, u64> {
+        self.user_id_to_markers
+            .lock()
+            .await
+            .get(user_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Mark the room as read by the user at the given time
+    /// Returns the resulting marker, which never moves back in time
+    pub async fn mark_read(&self, user_id: &str, room: &str, read_at: u64) -> anyhow::Result<u64> {
+        let mut user_id_to_markers = self.user_id_to_markers.lock().await;
+
+        let marker = user_id_to_markers
+            .entry(String::from(user_id))
+            .or_default()
+            .entry(String::from(room))
+            .or_default();
+        *marker = (*marker).max(read_at);
+        let last_read_at = *marker;
+
+        self.storage
+            .save(READ_MARKERS_DOCUMENT, &*user_id_to_markers)
+            .await?;
+
+        Ok(last_read_at)
+    }
+
+    /// Remove all the markers of the user, e.g. when their data is deleted
+    pub async fn forget(&self, user_id: &str) -> anyhow::Result<()> {
+        let mut user_id_to_markers = self.user_id_to_markers.lock().await;
+
+        if user_id_to_markers.remove(user_id).is_some() {
+            self.storage
+                .save(READ_MARKERS_DOCUMENT, &*user_id_to_markers)
+                .await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,260 @@
+use std::time::Duration;
+
+use comms::event::{self, Event};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, Mutex, Notify};
+
+use crate::{clock::unix_millis_now, logging, session::SessionRegistry, storage::Storage};
+
+pub use self::reminder_command::{parse_reminder_command, ReminderCommand};
+
+mod reminder_command;
+
+/// The name of the storage document the pending reminders are persisted in
+const REMINDERS_DOCUMENT: &str = "reminders";
+
+/// The most reminders a user may have pending at once, every reminder is kept in a single document
+pub const MAX_PENDING_REMINDERS: usize = 50;
+/// How soon a due reminder is sent again if none of the sessions of its user could take it, e.g. as they lag behind
+const DELIVERY_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// A reminder which is waiting to be delivered to a user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reminder {
+    pub user_id: String,
+    /// The room the reminder was requested in
+    pub room: String,
+    pub content: String,
+    /// When the reminder is due, in milliseconds since the unix epoch
+    pub due_at: u64,
+}
+
+/// [Reminders] keeps the pending reminders of all users and delivers them once they are due
+///
+/// Every change is persisted to the [Storage], hence pending reminders survive server restarts.
+/// Reminders of offline users are kept until the user connects again, or their picked name lapses.
+#[derive(Debug)]
+pub struct Reminders {
+    storage: Storage,
+    reminders: Mutex<Vec<Reminder>>,
+    changed: Notify,
+}
+
+impl Reminders {
+    /// Load the pending reminders from the storage
+    pub async fn load(storage: Storage) -> anyhow::Result<Self> {
+        let reminders: Vec<Reminder> = storage.load(REMINDERS_DOCUMENT).await?;
+
+        Ok(Reminders {
+            storage,
+            reminders: Mutex::new(reminders),
+            changed: Notify::new(),
+        })
+    }
+
+    /// Store a new reminder and reschedule the delivery
+    /// Returns false if the user already has [MAX_PENDING_REMINDERS] pending, the reminder is not stored then
+    pub async fn add(&self, reminder: Reminder) -> anyhow::Result<bool> {
+        let mut reminders = self.reminders.lock().await;
+
+        let pending_count = reminders
+            .iter()
+            .filter(|pending| pending.user_id == reminder.user_id)
+            .count();
+        if pending_count >= MAX_PENDING_REMINDERS {
+            return Ok(false);
+        }
+
+        reminders.push(reminder);
+        self.storage.save(REMINDERS_DOCUMENT, &*reminders).await?;
+
+        self.changed.notify_one();
+
+        Ok(true)
+    }
+
+    /// Drop the pending reminders of the user, e.g. when their data is deleted
+    pub async fn forget(&self, user_id: &str) -> anyhow::Result<()> {
+        let mut reminders = self.reminders.lock().await;
+
+        let count = reminders.len();
+        reminders.retain(|reminder| reminder.user_id != user_id);
+
+        if reminders.len() < count {
+            self.storage.save(REMINDERS_DOCUMENT, &*reminders).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Make the delivery check for due reminders right away, e.g. when a user comes online
+    pub fn wake(&self) {
+        self.changed.notify_one();
+    }
+
+    /// Deliver the reminders as they become due until the server shuts down
+    pub async fn deliver_until_quit(
+        &self,
+        session_registry: &SessionRegistry,
+        mut quit_rx: broadcast::Receiver<()>,
+    ) {
+        loop {
+            let next_due_at = match self.deliver_due(session_registry).await {
+                Ok(next_due_at) => next_due_at,
+                Err(err) => {
+                    logging::error(format!("could not deliver reminders: {:?}", err));
+                    None
+                }
+            };
+
+            let sleep = async {
+                match next_due_at {
+                    Some(due_at) => {
+                        let delay = due_at.saturating_sub(unix_millis_now());
+                        tokio::time::sleep(Duration::from_millis(delay)).await
+                    }
+                    None => std::future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                _ = sleep => {}
+                _ = self.changed.notified() => {}
+                _ = quit_rx.recv() => break,
+            }
+        }
+    }
+
+    /// Send the due reminders to the users which are online
+    /// Returns when the next pending reminder is due, if there is any,
+    /// or when to try again if a user is online but none of their sessions could take a reminder
+    async fn deliver_due(&self, session_registry: &SessionRegistry) -> anyhow::Result<Option<u64>> {
+        let now = unix_millis_now();
+        let mut reminders = self.reminders.lock().await;
+        let pending_count = reminders.len();
+        let mut retry_at = None;
+
+        reminders.retain(|reminder| {
+            if reminder.due_at > now {
+                return true;
+            }
+
+            let event = Event::ReminderDue(event::ReminderDueReplyEvent {
+                room: reminder.room.clone(),
+                content: reminder.content.clone(),
+            });
+
+            // keep the reminder around until the user has a session to deliver it to
+            let is_delivered = session_registry.send_to_user(&reminder.user_id, &event) > 0;
+            if !is_delivered && !session_registry.sessions_of(&reminder.user_id).is_empty() {
+                retry_at = Some(now + DELIVERY_RETRY_DELAY.as_millis() as u64);
+            }
+
+            !is_delivered
+        });
+
+        if reminders.len() != pending_count {
+            self.storage.save(REMINDERS_DOCUMENT, &*reminders).await?;
+        }
+
+        Ok(reminders
+            .iter()
+            .map(|reminder| reminder.due_at)
+            .filter(|due_at| *due_at > now)
+            .chain(retry_at)
+            .min())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use comms::event::SessionDetail;
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::{room_manager::SessionAndUserId, session::OutgoingEvent};
+
+    fn reminder(due_at: u64) -> Reminder {
+        Reminder {
+            user_id: String::from("alice"),
+            room: String::from("general"),
+            content: String::from("stand up"),
+            due_at,
+        }
+    }
+
+    #[tokio::test]
+    async fn users_can_only_have_so_many_pending_reminders() {
+        let data_dir = std::env::temp_dir().join(format!("chat-reminders-{}", nanoid::nanoid!()));
+        let reminders = Reminders::load(Storage::new(&data_dir).unwrap())
+            .await
+            .unwrap();
+
+        for _ in 0..MAX_PENDING_REMINDERS {
+            assert!(reminders.add(reminder(u64::MAX)).await.unwrap());
+        }
+        assert!(!reminders.add(reminder(u64::MAX)).await.unwrap());
+
+        // the others are not held to the limit of alice
+        let mut others = reminder(u64::MAX);
+        others.user_id = String::from("bob");
+        assert!(reminders.add(others).await.unwrap());
+        assert_eq!(
+            reminders.reminders.lock().await.len(),
+            MAX_PENDING_REMINDERS + 1
+        );
+    }
+
+    #[tokio::test]
+    async fn reminders_the_sessions_can_not_take_are_retried_soon() {
+        let data_dir = std::env::temp_dir().join(format!("chat-reminders-{}", nanoid::nanoid!()));
+        let reminders = Reminders::load(Storage::new(&data_dir).unwrap())
+            .await
+            .unwrap();
+        let session_registry = SessionRegistry::new();
+        let (event_tx, mut event_rx) = mpsc::channel(1);
+        let _terminate_rx = session_registry.register(
+            &SessionAndUserId {
+                session_id: String::from("s1"),
+                user_id: String::from("alice"),
+            },
+            SessionDetail {
+                session_id: String::from("s1"),
+                address: String::from("127.0.0.1:1234"),
+                connected_at: 0,
+            },
+            event_tx.clone(),
+        );
+        // the session lags behind, its channel is full
+        event_tx
+            .try_send(OutgoingEvent::from(Event::ReminderDue(
+                event::ReminderDueReplyEvent {
+                    room: String::from("general"),
+                    content: String::from("earlier"),
+                },
+            )))
+            .unwrap();
+
+        let now = unix_millis_now();
+        reminders.add(reminder(now)).await.unwrap();
+        let retry_at = reminders
+            .deliver_due(&session_registry)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(retry_at <= unix_millis_now() + DELIVERY_RETRY_DELAY.as_millis() as u64);
+
+        // once the session has caught up, the reminder is delivered and nothing is left to wait for
+        event_rx.recv().await.unwrap();
+        assert_eq!(
+            reminders.deliver_due(&session_registry).await.unwrap(),
+            None
+        );
+        assert!(matches!(
+            event_rx.try_recv(),
+            Ok(OutgoingEvent::Event(Event::ReminderDue(event))) if event.content == "stand up"
+        ));
+
+        let _ = std::fs::remove_dir_all(data_dir);
+    }
+}
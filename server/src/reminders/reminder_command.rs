@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+const USAGE: &str = "usage: /remind me in <duration> <text>, e.g. /remind me in 1h30m stand-up";
+
+/// Reminders can not be set further than this into the future
+const MAX_DELAY: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+/// A reminder request parsed from a message such as `/remind me in 10m stretch your legs`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReminderCommand {
+    /// How long to wait until the reminder is due
+    pub delay: Duration,
+    /// The text to remind the user of
+    pub content: String,
+}
+
+/// Split the first whitespace separated word from the rest of the text
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+
+    text.split_once(char::is_whitespace)
+        .map(|(word, rest)| (word, rest.trim_start()))
+        .unwrap_or((text, ""))
+}
+
+/// Parse a duration made of one or more `<number><unit>` segments such as `10m` or `1h30m`
+/// Supported units are `s`, `m`, `h` and `d`
+fn parse_delay(text: &str) -> Option<Duration> {
+    let mut seconds: u64 = 0;
+    let mut number = String::new();
+
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let unit_seconds = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            _ => return None,
+        };
+        let value: u64 = number.parse().ok()?;
+        seconds = seconds.checked_add(value.checked_mul(unit_seconds)?)?;
+        number.clear();
+    }
+
+    // a trailing number without a unit is ambiguous
+    if !number.is_empty() || seconds == 0 {
+        return None;
+    }
+
+    Some(Duration::from_secs(seconds))
+}
+
+/// Parse a reminder command out of a message
+///
+/// Returns [None] if the message is not a reminder command at all,
+/// or an error with the usage if it is a malformed one.
+pub fn parse_reminder_command(message: &str) -> Option<Result<ReminderCommand, String>> {
+    let (command, rest) = split_word(message);
+    if command != "/remind" {
+        return None;
+    }
+
+    let (me, rest) = split_word(rest);
+    let (preposition, rest) = split_word(rest);
+    let (delay, content) = split_word(rest);
+
+    let result = match parse_delay(delay) {
+        _ if me != "me" || preposition != "in" || content.is_empty() => Err(String::from(USAGE)),
+        Some(delay) if delay <= MAX_DELAY => Ok(ReminderCommand {
+            delay,
+            content: String::from(content.trim_end()),
+        }),
+        Some(_) => Err(String::from("reminders can be set at most a year ahead")),
+        None => Err(String::from(USAGE)),
+    };
+
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_not_a_reminder_command() {
+        assert_eq!(parse_reminder_command("hello there"), None);
+        assert_eq!(parse_reminder_command("/reminders"), None);
+    }
+
+    #[test]
+    fn test_valid_reminder_command() {
+        assert_eq!(
+            parse_reminder_command("/remind me in 10m stretch  your legs "),
+            Some(Ok(ReminderCommand {
+                delay: Duration::from_secs(600),
+                content: "stretch  your legs".into(),
+            }))
+        );
+        assert_eq!(
+            parse_reminder_command("/remind me in 1h30m stand-up"),
+            Some(Ok(ReminderCommand {
+                delay: Duration::from_secs(5400),
+                content: "stand-up".into(),
+            }))
+        );
+    }
+
+    #[test]
+    fn test_invalid_reminder_command() {
+        for message in [
+            "/remind",
+            "/remind me in 10m",
+            "/remind you in 10m text",
+            "/remind me in 10 text",
+            "/remind me in 0m text",
+            "/remind me in 10w text",
+            "/remind me in 400d text",
+        ] {
+            assert!(
+                matches!(parse_reminder_command(message), Some(Err(_))),
+                "{} should be rejected",
+                message
+            );
+        }
+    }
+}
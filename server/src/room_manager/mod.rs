@@ -1,9 +1,13 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
+use anyhow::Context;
 use tokio::sync::Mutex;
 
 use self::room::ChatRoom;
-pub use self::room::{ChatRoomMetadata, SessionAndUserId, UserSessionHandle};
+pub use self::room::{
+    ChatRoomMetadata, JoinApproval, RoomJoinOutcome, RoomJoinResult, SessionAndUserId,
+    UserSessionHandle, WaitingRoomUpdate,
+};
 
 pub use self::room_manager::RoomManager;
 
@@ -11,7 +15,40 @@ mod room;
 #[allow(clippy::module_inception)]
 mod room_manager;
 
-#[derive(Debug)]
+/// Whether the state of the rooms is persisted, see [RoomManager::open_room_logs]
+/// `CHAT_ROOM_LOGS` is either `true` or `false`, the rooms are kept in memory only if it is not set
+pub fn room_logs_from_env() -> anyhow::Result<bool> {
+    let Ok(room_logs) = std::env::var("CHAT_ROOM_LOGS") else {
+        return Ok(false);
+    };
+
+    match room_logs.trim().to_lowercase().as_str() {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => anyhow::bail!(
+            "unknown room logs setting '{}', expected true or false",
+            room_logs
+        ),
+    }
+}
+
+/// How long a room has to be idle before it is put to hibernation, see [RoomManager::hibernate_idle_rooms]
+/// `CHAT_ROOM_HIBERNATE_AFTER` is in seconds, the rooms are never put to hibernation if it is not set
+pub fn hibernation_from_env() -> anyhow::Result<Option<Duration>> {
+    std::env::var("CHAT_ROOM_HIBERNATE_AFTER")
+        .ok()
+        .map(|secs| {
+            secs.trim()
+                .parse()
+                .ok()
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+                .with_context(|| format!("invalid room hibernation delay '{}'", secs))
+        })
+        .transpose()
+}
+
+#[derive(Debug, Default)]
 pub struct RoomManagerBuilder {
     chat_rooms: Vec<(ChatRoomMetadata, Arc<Mutex<ChatRoom>>)>,
 }
@@ -24,8 +61,12 @@ impl RoomManagerBuilder {
     }
 
     /// Add a room to the room manager
-    /// Will panic if a room with the same name already exists
+    /// Will panic if a room with the same name already exists, or if its broadcast capacity is zero
     pub fn create_room(mut self, metadata: ChatRoomMetadata) -> Self {
+        if metadata.broadcast_capacity() == 0 {
+            panic!("the broadcast capacity of a room must be greater than zero");
+        }
+
         let chat_room = Arc::new(Mutex::new(ChatRoom::new(metadata.clone())));
 
         if self
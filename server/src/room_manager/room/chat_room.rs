@@ -1,46 +1,295 @@
-use comms::event::{self, Event};
+use comms::{
+    event::{self, Event, HistoricalMessage, HistoricalMessageKind, LagPolicy},
+    transport::server::EncodedEvent,
+};
+use nanoid::nanoid;
 use serde::{Deserialize, Serialize};
-use tokio::sync::broadcast;
-use std::collections::VecDeque;
+use std::{
+    collections::{HashSet, VecDeque},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::sync::{broadcast, mpsc};
+
+use crate::{clock::unix_millis_now, logging, storage::DataKey};
 
 use super::{
-    user_registry::UserRegistry, user_session_handle::UserSessionHandle, SessionAndUserId,
+    room_log::RoomLog,
+    room_state::{RoomEvent, RoomState},
+    room_stats::RoomStats,
+    user_registry::UserRegistry,
+    user_session_handle::UserSessionHandle,
+    waiting_queue::{WaitingQueue, WaitingRoomUpdate},
+    SessionAndUserId,
 };
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 /// [ChatRoomMetadata] holds the metadata that identifies a chat room
 pub struct ChatRoomMetadata {
     pub name: String,
     pub description: String,
+    /// The category the room is listed under by the clients, e.g. `engineering`
+    #[serde(default)]
+    pub category: Option<String>,
+    /// The maximum number of unique users in the room at the same time, unlimited if not set
+    #[serde(default)]
+    pub capacity: Option<usize>,
+    /// A text sent privately to every user the first time they join the room
+    #[serde(default)]
+    pub welcome_message: Option<String>,
+    /// The ids of the users who are allowed to manage the room, e.g. change its welcome message
+    #[serde(default)]
+    pub owners: Vec<String>,
+    /// The groups granted access to the room, the room is public if there are none
+    #[serde(default)]
+    pub groups: Vec<String>,
+    /// Whether joins and leaves are kept in the history alongside the messages
+    #[serde(default)]
+    pub record_participation: bool,
+    /// Whether the messages are encrypted end-to-end by the clients, the server only relays and keeps their ciphertext
+    #[serde(default)]
+    pub encrypted: bool,
+    /// Whether users need the approval of a moderator before they can join, moderators join right away
+    #[serde(default)]
+    pub requires_approval: bool,
+    /// The number of events a participant may fall behind before they miss some, 100 if not set
+    #[serde(default)]
+    pub broadcast_capacity: Option<usize>,
+    /// What happens to a participant who falls further behind than the broadcast capacity
+    #[serde(default)]
+    pub lag_policy: LagPolicy,
+}
+
+impl ChatRoomMetadata {
+    /// Returns true if the room is public, the user owns it or one of their groups has been granted access
+    pub fn is_accessible_by(&self, user_id: &str, groups: &HashSet<String>) -> bool {
+        self.groups.is_empty()
+            || self.owners.iter().any(|owner| owner == user_id)
+            || self.groups.iter().any(|group| groups.contains(group))
+    }
+
+    pub fn broadcast_capacity(&self) -> usize {
+        self.broadcast_capacity
+            .unwrap_or(DEFAULT_BROADCAST_CHANNEL_CAPACITY)
+    }
+}
+
+/// [JoinApproval] tells whether a user may join a room which requires approval
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JoinApproval {
+    /// The room does not require approval, or the user has been approved
+    Approved,
+    /// The user has to wait for a moderator, `is_new` is false if they had already asked
+    Pending { is_new: bool },
 }
 
-const BROADCAST_CHANNEL_CAPACITY: usize = 100;
+pub type RoomJoinResult = (
+    broadcast::Receiver<EncodedEvent>,
+    UserSessionHandle,
+    Vec<String>,
+    Option<String>,
+    bool,
+);
 
-#[derive(Debug, Clone)]
-pub struct ChatMessage {
-    user_id: String,
-    content: String,
+#[derive(Debug)]
+/// [RoomJoinOutcome] is the result of an attempt to join a room
+pub enum RoomJoinOutcome {
+    /// The user has joined the room
+    Joined(RoomJoinResult),
+    /// The room has no free slots, the position is set if the user is put in the waiting queue
+    Full {
+        capacity: usize,
+        queue_position: Option<usize>,
+    },
 }
 
+const DEFAULT_BROADCAST_CHANNEL_CAPACITY: usize = 100;
+
 #[derive(Debug)]
 /// [ChatRoom] handles the participants of a chat room and the primary broadcast channel
 /// A [UserSessionHandle] is handed out to a user when they join the room
 pub struct ChatRoom {
-    metadata: ChatRoomMetadata,
-    broadcast_tx: broadcast::Sender<Event>,
+    /// The metadata, the users in the room and the history, changed by recording a [RoomEvent] only
+    state: RoomState,
+    /// Where the events of the room are appended to if its state is persisted
+    log: Option<RoomLog>,
+    /// The directory of the log and the key it is encrypted with, kept to reopen it when the room wakes up from hibernation
+    log_dir: Option<(PathBuf, Option<DataKey>)>,
+    /// True while the history and the broadcast channel are released, see [ChatRoom::hibernate]
+    is_hibernating: bool,
+    /// When an event was last recorded in the room, in unix milliseconds
+    last_active_at: u64,
+    /// Carries the events of the room serialized once, instead of once per participant
+    broadcast_tx: broadcast::Sender<EncodedEvent>,
     user_registry: UserRegistry,
-    message_history: VecDeque<ChatMessage>, 
+    waiting_queue: WaitingQueue,
+    /// The users who have already received the welcome message of the room
+    welcomed_user_ids: HashSet<String>,
+    /// The users a moderator has let into the room, if the room requires approval
+    approved_user_ids: HashSet<String>,
+    /// The users waiting for a moderator to decide whether they can join, in the order they asked
+    pending_join_requests: Vec<String>,
+    /// The sessions in the room holding its key if the room is encrypted, see [ChatRoom::hold_room_key]
+    key_holders: HashSet<String>,
+    /// How often the participants have fallen behind the broadcast channel since the server started
+    lag_metrics: LagMetrics,
+    /// The number of handles dropped without leaving the room, shared with every [UserSessionHandle] of it
+    leaked_handles: Arc<AtomicU64>,
+    stats: RoomStats,
+}
+
+#[derive(Debug, Default)]
+struct LagMetrics {
+    lag_count: u64,
+    dropped_events: u64,
+    disconnected_sessions: u64,
 }
 
 impl ChatRoom {
     pub fn new(metadata: ChatRoomMetadata) -> Self {
-        let (broadcast_tx, _) = broadcast::channel(BROADCAST_CHANNEL_CAPACITY);
+        let (broadcast_tx, _) = broadcast::channel(metadata.broadcast_capacity());
 
         ChatRoom {
-            metadata,
+            state: RoomState::new(metadata),
+            log: None,
+            log_dir: None,
+            is_hibernating: false,
+            last_active_at: unix_millis_now(),
             broadcast_tx,
             user_registry: UserRegistry::new(),
-            message_history: VecDeque::with_capacity(10),
+            waiting_queue: WaitingQueue::new(),
+            welcomed_user_ids: HashSet::new(),
+            approved_user_ids: HashSet::new(),
+            pending_join_requests: Vec::new(),
+            key_holders: HashSet::new(),
+            lag_metrics: LagMetrics::default(),
+            leaked_handles: Arc::new(AtomicU64::new(0)),
+            stats: RoomStats::default(),
+        }
+    }
+
+    /// Persist the state of the room in its own directory, rebuilding it from there if it has been persisted before
+    /// The given metadata is kept over the persisted one, and nobody is in the room before the server starts
+    /// The log is read on a blocking thread, the runtime goes on while the room is locked
+    pub async fn open_log(
+        &mut self,
+        dir: PathBuf,
+        data_key: Option<DataKey>,
+    ) -> anyhow::Result<()> {
+        let (log, state) = open_room_log(
+            dir.clone(),
+            self.room_key(data_key.as_ref()),
+            self.state.clone(),
+        )
+        .await?;
+        self.attach_log(dir, data_key, log, state);
+
+        Ok(())
+    }
+
+    /// Same as [ChatRoom::open_log] for a room which is not shared yet, e.g. while it is created
+    pub fn open_log_blocking(
+        &mut self,
+        dir: PathBuf,
+        data_key: Option<DataKey>,
+    ) -> anyhow::Result<()> {
+        let (log, state) =
+            RoomLog::open(dir.clone(), self.room_key(data_key.as_ref()), &self.state)?;
+        self.attach_log(dir, data_key, log, state);
+
+        Ok(())
+    }
+
+    /// The log is encrypted with a key of the room derived from the given one, see [DataKey::derive]
+    fn room_key(&self, data_key: Option<&DataKey>) -> Option<DataKey> {
+        data_key.map(|data_key| data_key.derive(&format!("room {}", self.state.metadata.name)))
+    }
+
+    fn attach_log(
+        &mut self,
+        dir: PathBuf,
+        data_key: Option<DataKey>,
+        log: RoomLog,
+        state: RoomState,
+    ) {
+        let metadata = std::mem::replace(&mut self.state, state).metadata;
+        self.log = Some(log);
+        self.log_dir = Some((dir, data_key));
+
+        for user_id in self.state.members.clone() {
+            self.record(RoomEvent::Left {
+                id: nanoid!(),
+                user_id,
+                at: unix_millis_now(),
+            });
+        }
+        if self.state.metadata != metadata {
+            self.record(RoomEvent::MetadataChanged { metadata });
+        }
+    }
+
+    /// Stop persisting the state of the room, see [RoomLog::close]
+    pub fn take_log(&mut self) -> Option<RoomLog> {
+        self.log_dir = None;
+        self.log.take()
+    }
+
+    /// Returns true if the room has been idle for the given duration and nothing holds on to it in memory,
+    /// i.e. nobody is in the room or waiting for it, nothing subscribes to its events and its state is persisted
+    pub fn can_hibernate(&self, idle_for: Duration, now: u64) -> bool {
+        !self.is_hibernating
+            && self.log.is_some()
+            && self.user_registry.user_count() == 0
+            && self.waiting_queue.is_empty()
+            && self.broadcast_tx.receiver_count() == 0
+            && now.saturating_sub(self.last_active_at) >= idle_for.as_millis() as u64
+    }
+
+    /// Release the history and the broadcast channel of the room until it is needed again
+    /// Returns the log of the room, which has to be closed for the history to be persisted before it is reloaded
+    pub fn hibernate(&mut self) -> Option<RoomLog> {
+        self.is_hibernating = true;
+        self.state.history = VecDeque::new();
+        // nobody is subscribed, the smallest channel stands in until the room wakes up
+        self.broadcast_tx = broadcast::channel(1).0;
+
+        self.log.take()
+    }
+
+    /// Reload the history and recreate the broadcast channel of a hibernating room,
+    /// a room has to be woken up before anything is recorded in it or read from its history
+    /// The room starts over with an empty history if its log can not be reopened
+    pub async fn wake_up(&mut self) {
+        if !self.is_hibernating {
+            return;
+        }
+
+        self.is_hibernating = false;
+        self.broadcast_tx = broadcast::channel(self.state.metadata.broadcast_capacity()).0;
+        if let Some((dir, data_key)) = self.log_dir.clone() {
+            if let Err(err) = self.open_log(dir, data_key).await {
+                logging::error(format!(
+                    "could not wake up room '{}' from hibernation: {:?}",
+                    self.state.metadata.name, err
+                ));
+            }
+        }
+    }
+
+    /// Apply the event to the state of the room and append it to the log if the state is persisted
+    fn record(&mut self, event: RoomEvent) {
+        debug_assert!(!self.is_hibernating, "recorded in a hibernating room");
+        self.last_active_at = unix_millis_now();
+        if let RoomEvent::Message { user_id, at, .. } = &event {
+            self.stats.record_message(user_id, *at);
+        }
+        self.state.apply(&event);
+        if let Some(log) = self.log.as_mut() {
+            log.append(event, &self.state);
         }
     }
 
@@ -48,67 +297,525 @@ impl ChatRoom {
         self.user_registry.get_unique_user_ids()
     }
 
+    /// Returns true if no more new users can join the room
+    fn is_full(&self) -> bool {
+        self.state
+            .metadata
+            .capacity
+            .map(|capacity| self.user_registry.user_count() >= capacity)
+            .unwrap_or(false)
+    }
+
+    /// Add a participant to the room if there is a free slot for them
+    /// Users who already have another session in the room can always join
+    ///
+    /// If the room is full and a `waiting_tx` is given, the session is put in the waiting queue
+    /// and joined automatically once a slot frees up, see [WaitingRoomUpdate]
+    pub fn join(
+        &mut self,
+        session_and_user_id: &SessionAndUserId,
+        waiting_tx: Option<mpsc::UnboundedSender<WaitingRoomUpdate>>,
+    ) -> RoomJoinOutcome {
+        match self.state.metadata.capacity {
+            Some(capacity)
+                if self.is_full()
+                    && !self
+                        .user_registry
+                        .contains_user(&session_and_user_id.user_id) =>
+            {
+                RoomJoinOutcome::Full {
+                    capacity,
+                    queue_position: waiting_tx
+                        .map(|waiting_tx| self.waiting_queue.push(session_and_user_id, waiting_tx)),
+                }
+            }
+            _ => RoomJoinOutcome::Joined(self.admit(session_and_user_id)),
+        }
+    }
+
+    /// Checks whether the user may join the room, asking the moderators if the room requires approval
+    /// The user is put on the list of pending requests until a moderator decides on it
+    pub fn request_join_approval(&mut self, user_id: &str) -> JoinApproval {
+        if !self.state.metadata.requires_approval || self.approved_user_ids.contains(user_id) {
+            return JoinApproval::Approved;
+        }
+
+        let is_new = !self
+            .pending_join_requests
+            .iter()
+            .any(|pending_user_id| pending_user_id == user_id);
+        if is_new {
+            self.pending_join_requests.push(String::from(user_id));
+        }
+
+        JoinApproval::Pending { is_new }
+    }
+
+    /// Approves or denies the pending request of the user to join the room
+    /// Returns false if the user has not asked to join
+    pub fn decide_join_request(&mut self, user_id: &str, is_approved: bool) -> bool {
+        let count = self.pending_join_requests.len();
+        self.pending_join_requests
+            .retain(|pending_user_id| pending_user_id != user_id);
+        if self.pending_join_requests.len() == count {
+            return false;
+        }
+
+        // denied users may ask again, their new request is pending like the first one
+        if is_approved {
+            self.approved_user_ids.insert(String::from(user_id));
+        }
+
+        true
+    }
+
+    /// Remove a session from the waiting queue of the room, e.g. when the user gives up waiting
+    pub fn leave_waiting_queue(&mut self, session_id: &str) {
+        if self.waiting_queue.remove(session_id) {
+            self.waiting_queue
+                .notify_positions(&self.state.metadata.name);
+        }
+    }
+
     /// Add a participant to the room and broadcast that they joined
     ///
     /// # Returns
     ///
     /// - A broadcast receiver for the user to receive messages from the room
     /// - A [UserSessionHandle] for the user to be able to interact with the room
-    pub fn join(
-        &mut self,
-        session_and_user_id: &SessionAndUserId,
-    ) -> (broadcast::Receiver<Event>, UserSessionHandle) {
+    /// - The unique user ids in the room, including the newly joined user
+    /// - The welcome message of the room if the user joins the room for the first time
+    /// - True if the room is encrypted and the session is the one to create its key, since no session holds it
+    fn admit(&mut self, session_and_user_id: &SessionAndUserId) -> RoomJoinResult {
         let broadcast_tx = self.broadcast_tx.clone();
         let broadcast_rx = broadcast_tx.subscribe();
         let user_session_handle = UserSessionHandle::new(
-            self.metadata.name.clone(),
+            self.state.metadata.name.clone(),
             broadcast_tx,
             session_and_user_id.clone(),
+            Arc::clone(&self.leaked_handles),
         );
 
         // If the user is new e.g. they do not have another session with same user id,
         // broadcast that they joined to all users
         if self.user_registry.insert(&user_session_handle) {
-            let _ = self.broadcast_tx.send(Event::RoomParticipation(
-                event::RoomParticipationBroadcastEvent {
-                    user_id: session_and_user_id.user_id.clone(),
-                    room: self.metadata.name.clone(),
-                    status: event::RoomParticipationStatus::Joined,
-                },
-            ));
+            self.stats
+                .record_member_count(self.user_registry.user_count());
+            self.broadcast_participation(
+                &session_and_user_id.user_id,
+                event::RoomParticipationStatus::Joined,
+            );
+        }
+
+        let welcome_message = self
+            .welcomed_user_ids
+            .insert(session_and_user_id.user_id.clone())
+            .then(|| self.state.metadata.welcome_message.clone())
+            .flatten();
+
+        // decided while the room is locked, so sessions joining at once never create two keys
+        let creates_room_key = self.state.metadata.encrypted && self.key_holders.is_empty();
+        if creates_room_key {
+            self.key_holders
+                .insert(session_and_user_id.session_id.clone());
         }
 
-        (broadcast_rx, user_session_handle)
+        (
+            broadcast_rx,
+            user_session_handle,
+            self.get_unique_user_ids(),
+            welcome_message,
+            creates_room_key,
+        )
     }
 
-    /* Add message to queue, pop front if exceed 10 */
-    pub fn add_message_to_history(&mut self, user_id: String, content: String) {
-        let message = ChatMessage { user_id, content };
-        if self.message_history.len() >= 10 {
-            self.message_history.pop_front();
+    /// Count a session in the room among the ones holding the key of the room, once it has opened a shared key
+    /// Returns false if the session is not in the room
+    pub fn hold_room_key(&mut self, session_and_user_id: &SessionAndUserId) -> bool {
+        if !self.user_registry.contains_session(session_and_user_id) {
+            return false;
         }
-        self.message_history.push_back(message);
+
+        self.key_holders
+            .insert(session_and_user_id.session_id.clone());
+
+        true
     }
 
-    /* Return a cloned iterator of the history */
-    pub fn get_message_history(&self) -> Vec<(String, String)> {
-        self.message_history
+    /// Change the welcome message of the room, `None` removes it
+    /// Returns false if the user is not an owner of the room
+    pub fn set_welcome_message(&mut self, user_id: &str, welcome_message: Option<String>) -> bool {
+        if !self
+            .state
+            .metadata
+            .owners
             .iter()
-            .map(|msg| (msg.user_id.clone(), msg.content.clone()))
-            .collect()
+            .any(|owner| owner == user_id)
+        {
+            return false;
+        }
+
+        self.record(RoomEvent::MetadataChanged {
+            metadata: ChatRoomMetadata {
+                welcome_message,
+                ..self.state.metadata.clone()
+            },
+        });
+
+        true
+    }
+
+    /// Change the description or the capacity of the room, `Some(None)` lifts the capacity limit
+    /// Returns the metadata of the room as updated
+    pub fn update_metadata(
+        &mut self,
+        description: Option<String>,
+        capacity: Option<Option<usize>>,
+    ) -> ChatRoomMetadata {
+        let mut metadata = self.state.metadata.clone();
+        if let Some(description) = description {
+            metadata.description = description;
+        }
+        if let Some(capacity) = capacity {
+            metadata.capacity = capacity;
+        }
+        self.record(RoomEvent::MetadataChanged { metadata });
+        if capacity.is_some() {
+            // users already in the room stay if it has shrunk, the waiting ones join if it has grown
+            self.admit_waiters();
+        }
+
+        self.state.metadata.clone()
+    }
+
+    /// Replace the metadata, e.g. with the provisioned one, the broadcast channel keeps its capacity
+    /// Users already in the room stay if it has shrunk, the waiting ones join if it has grown
+    pub fn replace_metadata(&mut self, metadata: ChatRoomMetadata) -> ChatRoomMetadata {
+        self.record(RoomEvent::MetadataChanged {
+            metadata: ChatRoomMetadata {
+                broadcast_capacity: self.state.metadata.broadcast_capacity,
+                ..metadata
+            },
+        });
+        self.admit_waiters();
+
+        self.state.metadata.clone()
+    }
+
+    /// Join the waiting sessions in arrival order while there are free slots
+    /// and let the remaining ones know about their new positions
+    fn admit_waiters(&mut self) {
+        while !self.is_full() {
+            let Some((session_and_user_id, waiting_tx)) = self.waiting_queue.pop_front() else {
+                break;
+            };
+
+            if waiting_tx.is_closed() {
+                continue;
+            }
+
+            let update = WaitingRoomUpdate::Joined {
+                room: self.state.metadata.name.clone(),
+                join_result: self.admit(&session_and_user_id),
+            };
+
+            // the session went away in the meantime, give the slot back
+            if let Err(mpsc::error::SendError(WaitingRoomUpdate::Joined {
+                join_result: (_, user_session_handle, _, _, _),
+                ..
+            })) = waiting_tx.send(update)
+            {
+                self.leave(user_session_handle);
+            }
+        }
+
+        self.waiting_queue
+            .notify_positions(&self.state.metadata.name);
+    }
+
+    /// Returns the id and the timestamp of the message, which are broadcast alongside it
+    pub fn add_message_to_history(
+        &mut self,
+        user_id: String,
+        content: String,
+        content_type: event::ContentType,
+    ) -> (String, u64) {
+        let id = nanoid!();
+        let timestamp = unix_millis_now();
+        self.record(RoomEvent::Message {
+            id: id.clone(),
+            user_id,
+            display_name: None,
+            content,
+            content_type,
+            at: timestamp,
+        });
+
+        (id, timestamp)
+    }
+
+    /// Record a message posted without a session, e.g. through a webhook, and broadcast it to the participants
+    /// Returns the id of the message
+    pub fn post_message(
+        &mut self,
+        user_id: String,
+        display_name: Option<String>,
+        content: String,
+    ) -> String {
+        let id = nanoid!();
+        let timestamp = unix_millis_now();
+        self.record(RoomEvent::Message {
+            id: id.clone(),
+            user_id: user_id.clone(),
+            display_name: display_name.clone(),
+            content: content.clone(),
+            content_type: event::ContentType::Plain,
+            at: timestamp,
+        });
+
+        self.broadcast(Event::UserMessage(event::UserMessageBroadcastEvent {
+            room: self.state.metadata.name.clone(),
+            id: id.clone(),
+            user_id,
+            display_name,
+            content,
+            content_type: event::ContentType::Plain,
+            timestamp,
+            nonce: None,
+        }));
+
+        id
+    }
+
+    /// Remove the entries about the user from the history, e.g. when their data is deleted
+    /// Returns the ids of the removed entries
+    /// The log of a hibernating room is rewritten without waking the room up
+    pub async fn purge_user(&mut self, user_id: &str) -> Vec<String> {
+        // recorded even if the history has nothing about the user, their older messages are redacted from the log
+        let event = RoomEvent::UserPurged {
+            user_id: String::from(user_id),
+        };
+
+        if !self.is_hibernating {
+            let purged_ids = purged_ids(&self.state, user_id);
+            self.record(event);
+            return purged_ids;
+        }
+
+        let Some((dir, data_key)) = self.log_dir.clone() else {
+            return Vec::new();
+        };
+        let room_key = self.room_key(data_key.as_ref());
+        let state = self.state.clone();
+        let user_id = String::from(user_id);
+        let purged = tokio::task::spawn_blocking(move || {
+            let (mut log, mut state) = RoomLog::open(dir, room_key, &state)?;
+            let purged_ids = purged_ids(&state, &user_id);
+            state.apply(&event);
+            log.append(event, &state);
+            anyhow::Ok((log, purged_ids))
+        })
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|purged| purged);
+
+        match purged {
+            Ok((log, purged_ids)) => {
+                // the room is still locked, so the log is not reopened before it is rewritten
+                log.close().await;
+                purged_ids
+            }
+            Err(err) => {
+                logging::error(format!(
+                    "could not purge the log of hibernating room '{}': {:?}",
+                    self.state.metadata.name, err
+                ));
+                Vec::new()
+            }
+        }
+    }
+
+    /* Return a cloned list of the history */
+    pub fn get_history(&self) -> Vec<HistoricalMessage> {
+        debug_assert!(!self.is_hibernating, "read from a hibernating room");
+        self.state.history.iter().cloned().collect()
+    }
+
+    /// The message with the given id, if it is still in the history
+    pub fn find_message(&self, message_id: &str) -> Option<HistoricalMessage> {
+        debug_assert!(!self.is_hibernating, "read from a hibernating room");
+        self.state
+            .history
+            .iter()
+            .find(|entry| entry.id == message_id && entry.kind == HistoricalMessageKind::Message)
+            .cloned()
+    }
+
+    /// Let the participants know that a user has joined or left the room,
+    /// it is kept in the history if the room keeps participation history
+    fn broadcast_participation(&mut self, user_id: &str, status: event::RoomParticipationStatus) {
+        let (id, user_id, at) = (nanoid!(), String::from(user_id), unix_millis_now());
+        self.record(match status {
+            event::RoomParticipationStatus::Joined => RoomEvent::Joined {
+                id,
+                user_id: user_id.clone(),
+                at,
+            },
+            event::RoomParticipationStatus::Left => RoomEvent::Left {
+                id,
+                user_id: user_id.clone(),
+                at,
+            },
+        });
+
+        self.broadcast(Event::RoomParticipation(
+            event::RoomParticipationBroadcastEvent {
+                user_id,
+                room: self.state.metadata.name.clone(),
+                status,
+            },
+        ));
+    }
+
+    /// Returns true if the user is in the room with at least one session
+    pub fn contains_user(&self, user_id: &str) -> bool {
+        self.user_registry.contains_user(user_id)
+    }
+
+    /// The details of the room shown to users looking it up
+    pub fn room_info(&self) -> event::RoomInfoReplyEvent {
+        event::RoomInfoReplyEvent {
+            room: self.state.metadata.name.clone(),
+            description: self.state.metadata.description.clone(),
+            member_count: self.user_registry.user_count(),
+            capacity: self.state.metadata.capacity,
+            owners: self.state.metadata.owners.clone(),
+        }
+    }
+
+    /// Broadcast an announcement of the server to the participants, e.g. a scheduled maintenance
+    pub fn announce(&self, message: &str) {
+        self.broadcast(Event::Announcement(event::AnnouncementBroadcastEvent {
+            room: self.state.metadata.name.clone(),
+            message: String::from(message),
+        }));
+    }
+
+    /// Broadcast the preview of a link in a message of the room, fetched after the message was sent
+    pub fn broadcast_link_preview(&self, link_preview: event::LinkPreviewBroadcastEvent) {
+        self.broadcast(Event::LinkPreview(link_preview));
+    }
+
+    /// Let the participants know that a user in the room has become away or come back online
+    /// Nothing is broadcast if the user is not in the room
+    pub fn broadcast_presence(&self, user_id: &str, status: event::PresenceStatus) {
+        if !self.user_registry.contains_user(user_id) {
+            return;
+        }
+
+        self.broadcast(Event::PresenceChanged(
+            event::PresenceChangedBroadcastEvent {
+                room: self.state.metadata.name.clone(),
+                user_id: String::from(user_id),
+                status,
+            },
+        ));
+    }
+
+    /// Let the participants know that a user in the room has changed or removed their status text
+    /// Nothing is broadcast if the user is not in the room
+    pub fn broadcast_status_text(&self, user_id: &str, text: Option<&str>) {
+        if !self.user_registry.contains_user(user_id) {
+            return;
+        }
+
+        self.broadcast(Event::StatusTextChanged(
+            event::StatusTextChangedBroadcastEvent {
+                room: self.state.metadata.name.clone(),
+                user_id: String::from(user_id),
+                text: text.map(String::from),
+            },
+        ));
+    }
+
+    /// Record that a participant has fallen behind and missed the given number of events
+    /// Returns what to do with the participant according to the lag policy of the room
+    pub fn record_lag(&mut self, missed_events: u64) -> LagPolicy {
+        self.lag_metrics.lag_count += 1;
+        self.lag_metrics.dropped_events += missed_events;
+        if self.state.metadata.lag_policy == LagPolicy::Disconnect {
+            self.lag_metrics.disconnected_sessions += 1;
+        }
+
+        self.state.metadata.lag_policy
+    }
+
+    pub fn broadcast_metrics(&self) -> event::RoomBroadcastMetrics {
+        event::RoomBroadcastMetrics {
+            room: self.state.metadata.name.clone(),
+            capacity: self.state.metadata.broadcast_capacity(),
+            lag_policy: self.state.metadata.lag_policy,
+            lag_count: self.lag_metrics.lag_count,
+            dropped_events: self.lag_metrics.dropped_events,
+            disconnected_sessions: self.lag_metrics.disconnected_sessions,
+            leaked_handles: self.leaked_handles.load(Ordering::Relaxed),
+        }
+    }
+
+    /// How busy the room has been, as of now
+    pub fn stats(&self) -> event::RoomStats {
+        self.stats.snapshot(
+            &self.state.metadata.name,
+            self.user_registry.user_count(),
+            unix_millis_now(),
+        )
+    }
+
+    /// Receive the events of the room without taking part in it, e.g. to mirror its messages
+    pub fn subscribe(&mut self) -> broadcast::Receiver<EncodedEvent> {
+        debug_assert!(!self.is_hibernating, "subscribed to a hibernating room");
+        self.broadcast_tx.subscribe()
+    }
+
+    /// Serialize the event once and send it to every participant of the room
+    fn broadcast(&self, event: Event) {
+        // there may be no one in the room to receive the event, which is fine
+        if let Ok(encoded_event) = EncodedEvent::encode(&event) {
+            let _ = self.broadcast_tx.send(encoded_event);
+        }
     }
 
     /// Remove a participant from the room and broadcast that they left
     /// Consume the [UserSessionHandle] to drop it
-    pub fn leave(&mut self, user_session_handle: UserSessionHandle) {
+    pub fn leave(&mut self, mut user_session_handle: UserSessionHandle) {
+        user_session_handle.release();
+        self.key_holders.remove(user_session_handle.session_id());
         if self.user_registry.remove(&user_session_handle) {
-            let _ = self.broadcast_tx.send(Event::RoomParticipation(
-                event::RoomParticipationBroadcastEvent {
-                    user_id: String::from(user_session_handle.user_id()),
-                    room: self.metadata.name.clone(),
-                    status: event::RoomParticipationStatus::Left,
-                },
-            ));
+            self.broadcast_participation(
+                user_session_handle.user_id(),
+                event::RoomParticipationStatus::Left,
+            );
+
+            self.admit_waiters();
         }
     }
 }
+
+/// Open the log of the room on a blocking thread, see [RoomLog::open]
+async fn open_room_log(
+    dir: PathBuf,
+    room_key: Option<DataKey>,
+    state: RoomState,
+) -> anyhow::Result<(RoomLog, RoomState)> {
+    tokio::task::spawn_blocking(move || RoomLog::open(dir, room_key, &state)).await?
+}
+
+/// The ids of the entries about the user in the history
+fn purged_ids(state: &RoomState, user_id: &str) -> Vec<String> {
+    state
+        .history
+        .iter()
+        .filter(|message| message.user_id == user_id)
+        .map(|message| message.id.clone())
+        .collect()
+}
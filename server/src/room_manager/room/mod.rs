@@ -1,6 +1,13 @@
 mod chat_room;
+mod room_log;
+mod room_state;
+mod room_stats;
 mod user_registry;
 mod user_session_handle;
+mod waiting_queue;
 
-pub use self::chat_room::{ChatRoom, ChatRoomMetadata};
+pub use self::chat_room::{
+    ChatRoom, ChatRoomMetadata, JoinApproval, RoomJoinOutcome, RoomJoinResult,
+};
 pub use self::user_session_handle::{SessionAndUserId, UserSessionHandle};
+pub use self::waiting_queue::WaitingRoomUpdate;
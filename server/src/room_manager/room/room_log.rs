@@ -0,0 +1,571 @@
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    fs::{File, OpenOptions},
+    io::AsyncWriteExt,
+    sync::mpsc,
+    task::JoinHandle,
+};
+
+use crate::{
+    logging,
+    storage::{open_file, open_line, seal_file, seal_line, DataKey},
+};
+
+use super::room_state::{RoomEvent, RoomState};
+
+/// The events logged between two snapshots of the state
+const SNAPSHOT_INTERVAL: u64 = 500;
+const EVENTS_FILE: &str = "events.jsonl";
+const SNAPSHOT_FILE: &str = "snapshot.json";
+
+/// A line of the events file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct LoggedRoomEvent {
+    /// The position of the event in the log, the first event is 1
+    seq: u64,
+    #[serde(flatten)]
+    event: RoomEvent,
+}
+
+/// The state of the room once the events up to `seq` have been applied
+#[derive(Debug, Serialize, Deserialize)]
+struct Snapshot {
+    seq: u64,
+    state: RoomState,
+}
+
+/// What the writer of the log is asked to do
+#[derive(Debug)]
+struct LogEntry {
+    event: RoomEvent,
+    /// The state after the event, if a snapshot of it is due
+    snapshot: Option<RoomState>,
+}
+
+/// [RoomLog] appends the events of a room to its directory, so that its state survives restarts
+///
+/// The directory holds `events.jsonl`, every event on its own line, and `snapshot.json`, the state of the room
+/// after a given event. The state is rebuilt by applying the events logged after the snapshot to it.
+/// A snapshot is taken every 500 events, and whenever a user is purged; their messages are redacted from the log.
+/// The events file starts over once a snapshot is written, the events up to it are kept in the snapshot only.
+/// Both files are encrypted if the room has a key, every event on its own line still.
+///
+/// The events are written by a background task, the room does not wait for the disk.
+#[derive(Debug)]
+pub struct RoomLog {
+    /// The events appended since the last snapshot was asked for
+    events_since_snapshot: u64,
+    entries_tx: mpsc::UnboundedSender<LogEntry>,
+    writer: JoinHandle<()>,
+}
+
+impl RoomLog {
+    /// Open the log in the directory and rebuild the state of the room from it
+    /// A new log is started from the given state if the directory has none
+    pub fn open(
+        dir: PathBuf,
+        data_key: Option<DataKey>,
+        state: &RoomState,
+    ) -> anyhow::Result<(Self, RoomState)> {
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("could not create the room log directory {:?}", dir))?;
+
+        let (state, last_seq, log_len) = match load(&dir, data_key.as_ref())? {
+            Some(loaded) => loaded,
+            None => {
+                write_snapshot(
+                    &dir,
+                    data_key.as_ref(),
+                    &Snapshot {
+                        seq: 0,
+                        state: state.clone(),
+                    },
+                )?;
+                (state.clone(), 0, 0)
+            }
+        };
+
+        let (entries_tx, entries_rx) = mpsc::unbounded_channel();
+        let writer = tokio::spawn(write_until_closed(
+            dir, data_key, last_seq, log_len, entries_rx,
+        ));
+
+        Ok((
+            RoomLog {
+                events_since_snapshot: 0,
+                entries_tx,
+                writer,
+            },
+            state,
+        ))
+    }
+
+    /// Append the event, which has already been applied to the state
+    /// The event is numbered by the writer once it is written, so an event which could not be written leaves no gap
+    pub fn append(&mut self, event: RoomEvent, state: &RoomState) {
+        self.events_since_snapshot += 1;
+
+        let is_snapshot_due = self.events_since_snapshot >= SNAPSHOT_INTERVAL
+            || matches!(event, RoomEvent::UserPurged { .. });
+        if is_snapshot_due {
+            self.events_since_snapshot = 0;
+        }
+        // the writer only stops once the log is closed, there is nothing left to log then
+        let _ = self.entries_tx.send(LogEntry {
+            event,
+            snapshot: is_snapshot_due.then(|| state.clone()),
+        });
+    }
+
+    /// Wait for the events appended so far to be written, e.g. when the server shuts down
+    pub async fn close(self) {
+        drop(self.entries_tx);
+        let _ = self.writer.await;
+    }
+}
+
+/// The state rebuilt from the snapshot and the events after it, the seq of the last event and the size of the log
+/// None if the directory has no snapshot, i.e. no log
+/// The last event is dropped from the log if it can not be read, e.g. if a crash has cut it off while it was written.
+/// The events up to the snapshot are skipped, they are left over if the server has stopped before the log started over.
+fn load(dir: &Path, data_key: Option<&DataKey>) -> anyhow::Result<Option<(RoomState, u64, u64)>> {
+    let snapshot_path = dir.join(SNAPSHOT_FILE);
+    let snapshot: Snapshot = match std::fs::read(&snapshot_path) {
+        Ok(bytes) => open_file(data_key, bytes)
+            .and_then(|bytes| Ok(serde_json::from_slice(&bytes)?))
+            .with_context(|| format!("could not parse the snapshot {:?}", snapshot_path))?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("could not read the snapshot {:?}", snapshot_path))
+        }
+    };
+
+    let events_path = dir.join(EVENTS_FILE);
+    let mut events_file = match std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&events_path)
+    {
+        Ok(events_file) => events_file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(Some((snapshot.state, snapshot.seq, 0)))
+        }
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("could not open the room log {:?}", events_path))
+        }
+    };
+    let mut events = String::new();
+    events_file
+        .read_to_string(&mut events)
+        .with_context(|| format!("could not read the room log {:?}", events_path))?;
+
+    let mut state = snapshot.state;
+    let mut last_seq = snapshot.seq;
+    let mut log_len = 0;
+    let mut lines = events.split_inclusive('\n').peekable();
+    while let Some(line) = lines.next() {
+        let is_last_line = lines.peek().is_none();
+        let logged_event = open_line(data_key, line.trim_end())
+            .and_then(|line| Ok(serde_json::from_slice::<LoggedRoomEvent>(&line)?))
+            .with_context(|| {
+                format!(
+                    "could not parse the event at byte {} of {:?}",
+                    log_len, events_path
+                )
+            })
+            .and_then(|logged_event| {
+                anyhow::ensure!(
+                    logged_event.seq <= snapshot.seq || logged_event.seq == last_seq + 1,
+                    "the room log {:?} skips from event {} to {}",
+                    events_path,
+                    last_seq,
+                    logged_event.seq
+                );
+                Ok(logged_event)
+            });
+
+        let logged_event = match logged_event {
+            Ok(logged_event) if line.ends_with('\n') => logged_event,
+            Err(err) if !is_last_line => return Err(err),
+            _ => {
+                logging::warn(format!(
+                    "Dropping the unreadable last event of the room log {:?}",
+                    events_path
+                ));
+                events_file.set_len(log_len)?;
+                break;
+            }
+        };
+        if logged_event.seq > snapshot.seq {
+            state.apply(&logged_event.event);
+            last_seq = logged_event.seq;
+        }
+        log_len += line.len() as u64;
+    }
+
+    Ok(Some((state, last_seq, log_len)))
+}
+
+fn write_snapshot(
+    dir: &Path,
+    data_key: Option<&DataKey>,
+    snapshot: &Snapshot,
+) -> anyhow::Result<()> {
+    let path = dir.join(SNAPSHOT_FILE);
+    let tmp_path = path.with_extension("json.tmp");
+
+    std::fs::write(
+        &tmp_path,
+        seal_file(data_key, serde_json::to_vec_pretty(snapshot)?)?,
+    )
+    .with_context(|| format!("could not write the snapshot {:?}", tmp_path))?;
+    std::fs::rename(&tmp_path, &path)
+        .with_context(|| format!("could not replace the snapshot {:?}", path))?;
+
+    Ok(())
+}
+
+/// Append the events to the log as they come until the log is closed or dropped along with its room
+/// An event which can not be written is dropped, and its seq is given to the next one
+async fn write_until_closed(
+    dir: PathBuf,
+    data_key: Option<DataKey>,
+    mut last_seq: u64,
+    mut log_len: u64,
+    mut entries_rx: mpsc::UnboundedReceiver<LogEntry>,
+) {
+    let events_path = dir.join(EVENTS_FILE);
+    let mut events_file = match open_for_append(&events_path).await {
+        Ok(events_file) => events_file,
+        Err(err) => {
+            logging::error(format!("{:#}, the room events are not logged", err));
+            return;
+        }
+    };
+
+    while let Some(LogEntry { event, snapshot }) = entries_rx.recv().await {
+        let logged_event = LoggedRoomEvent {
+            seq: last_seq + 1,
+            event,
+        };
+        let written = async {
+            let mut line = seal_line(data_key.as_ref(), serde_json::to_vec(&logged_event)?)?;
+            line.push(b'\n');
+            events_file.write_all(&line).await?;
+            events_file.flush().await?;
+
+            anyhow::Ok(line.len() as u64)
+        }
+        .await;
+        match written {
+            Ok(line_len) => {
+                last_seq = logged_event.seq;
+                log_len += line_len;
+            }
+            Err(err) => {
+                logging::error(format!(
+                    "could not append to the room log {:?}, the event is dropped: {:#}",
+                    events_path, err
+                ));
+                // a line written in part would corrupt the events appended after it
+                if let Err(err) = events_file.set_len(log_len).await {
+                    logging::error(format!(
+                        "could not cut the room log {:?} back: {:#}",
+                        events_path, err
+                    ));
+                }
+                continue;
+            }
+        }
+
+        let result = async {
+            if let RoomEvent::UserPurged { user_id } = &logged_event.event {
+                log_len = redact(&events_path, data_key.as_ref(), user_id).await?;
+                events_file = open_for_append(&events_path).await?;
+            }
+
+            if let Some(state) = snapshot {
+                let snapshot = Snapshot {
+                    seq: logged_event.seq,
+                    state,
+                };
+                let dir = dir.clone();
+                let data_key = data_key.clone();
+                tokio::task::spawn_blocking(move || {
+                    write_snapshot(&dir, data_key.as_ref(), &snapshot)
+                })
+                .await??;
+
+                // the snapshot holds the events up to it, the log starts over
+                events_file.set_len(0).await.with_context(|| {
+                    format!("could not truncate the room log {:?}", events_path)
+                })?;
+                log_len = 0;
+            }
+
+            anyhow::Ok(())
+        }
+        .await;
+
+        if let Err(err) = result {
+            logging::error(format!("{:#}", err));
+        }
+    }
+}
+
+async fn open_for_append(path: &Path) -> anyhow::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .with_context(|| format!("could not open the room log {:?}", path))
+}
+
+/// Blank out the content of the messages of the user in the log, leaving a trace that they were sent
+/// Returns the new size of the log
+async fn redact(
+    events_path: &Path,
+    data_key: Option<&DataKey>,
+    user_id: &str,
+) -> anyhow::Result<u64> {
+    let events = tokio::fs::read_to_string(events_path)
+        .await
+        .with_context(|| format!("could not read the room log {:?}", events_path))?;
+
+    let mut redacted = Vec::with_capacity(events.len());
+    for line in events.lines() {
+        let mut logged_event: LoggedRoomEvent =
+            serde_json::from_slice(&open_line(data_key, line)?)?;
+        if let RoomEvent::Message {
+            user_id: message_user_id,
+            display_name,
+            content,
+            ..
+        } = &mut logged_event.event
+        {
+            if message_user_id == user_id {
+                *display_name = None;
+                content.clear();
+            }
+        }
+        redacted.extend(seal_line(data_key, serde_json::to_vec(&logged_event)?)?);
+        redacted.push(b'\n');
+    }
+
+    let tmp_path = events_path.with_extension("jsonl.tmp");
+    tokio::fs::write(&tmp_path, &redacted)
+        .await
+        .with_context(|| format!("could not write the room log {:?}", tmp_path))?;
+    tokio::fs::rename(&tmp_path, events_path)
+        .await
+        .with_context(|| format!("could not replace the room log {:?}", events_path))?;
+
+    Ok(redacted.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use comms::event::{ContentType, LagPolicy};
+
+    use super::*;
+    use crate::room_manager::ChatRoomMetadata;
+
+    fn log_dir() -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("chat-room-log-{}", nanoid::nanoid!()))
+            .join("test")
+    }
+
+    fn state() -> RoomState {
+        RoomState::new(ChatRoomMetadata {
+            name: String::from("test"),
+            description: String::new(),
+            category: None,
+            capacity: None,
+            welcome_message: None,
+            owners: Vec::new(),
+            groups: Vec::new(),
+            record_participation: true,
+            encrypted: false,
+            requires_approval: false,
+            broadcast_capacity: None,
+            lag_policy: LagPolicy::Resync,
+        })
+    }
+
+    fn message(id: u64, user_id: &str) -> RoomEvent {
+        RoomEvent::Message {
+            id: id.to_string(),
+            user_id: String::from(user_id),
+            display_name: None,
+            content: format!("message {}", id),
+            content_type: ContentType::Plain,
+            at: id,
+        }
+    }
+
+    /// Apply the events to the state and append them to the log, then wait for them to be written
+    async fn append(mut log: RoomLog, state: &mut RoomState, events: Vec<RoomEvent>) {
+        for event in events {
+            state.apply(&event);
+            log.append(event, state);
+        }
+        log.close().await;
+    }
+
+    #[tokio::test]
+    async fn test_state_is_rebuilt_from_the_snapshot_and_the_events_after_it() {
+        let dir = log_dir();
+        let (log, mut state) = RoomLog::open(dir.clone(), None, &state()).unwrap();
+
+        let events = (1..=SNAPSHOT_INTERVAL + 3)
+            .map(|id| message(id, "alice"))
+            .chain([RoomEvent::Joined {
+                id: String::from("joined"),
+                user_id: String::from("bob"),
+                at: 1,
+            }])
+            .collect();
+        append(log, &mut state, events).await;
+
+        let (rebuilt, last_seq, _) = load(&dir, None).unwrap().unwrap();
+        assert_eq!(rebuilt, state);
+        assert_eq!(last_seq, SNAPSHOT_INTERVAL + 4);
+        // the events up to the snapshot are only kept in it
+        let events = std::fs::read_to_string(dir.join(EVENTS_FILE)).unwrap();
+        assert_eq!(events.lines().count(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_events_left_over_from_before_the_snapshot_are_skipped() {
+        let dir = log_dir();
+        let (log, mut state) = RoomLog::open(dir.clone(), None, &state()).unwrap();
+        append(
+            log,
+            &mut state,
+            vec![message(1, "alice"), message(2, "bob")],
+        )
+        .await;
+        let events = std::fs::read(dir.join(EVENTS_FILE)).unwrap();
+
+        // as if the server had stopped between writing the snapshot and starting the log over
+        let (log, mut state) = RoomLog::open(dir.clone(), None, &state).unwrap();
+        append(
+            log,
+            &mut state,
+            vec![RoomEvent::UserPurged {
+                user_id: String::from("carol"),
+            }],
+        )
+        .await;
+        std::fs::write(dir.join(EVENTS_FILE), events).unwrap();
+
+        let (rebuilt, last_seq, _) = load(&dir, None).unwrap().unwrap();
+        assert_eq!(rebuilt, state);
+        assert_eq!(last_seq, 3);
+    }
+
+    #[tokio::test]
+    async fn test_purged_messages_are_redacted_from_the_log() {
+        let dir = log_dir();
+        let (log, mut state) = RoomLog::open(dir.clone(), None, &state()).unwrap();
+
+        let events = vec![
+            message(1, "alice"),
+            message(2, "bob"),
+            RoomEvent::UserPurged {
+                user_id: String::from("alice"),
+            },
+            message(3, "bob"),
+        ];
+        append(log, &mut state, events).await;
+
+        let events = std::fs::read_to_string(dir.join(EVENTS_FILE)).unwrap();
+        assert!(!events.contains("message 1"));
+        assert!(events.contains("message 3"));
+        let snapshot = std::fs::read_to_string(dir.join(SNAPSHOT_FILE)).unwrap();
+        assert!(!snapshot.contains("message 1"));
+        assert!(snapshot.contains("message 2"));
+        let (rebuilt, last_seq, _) = load(&dir, None).unwrap().unwrap();
+        assert_eq!(rebuilt, state);
+        assert_eq!(last_seq, 4);
+    }
+
+    #[tokio::test]
+    async fn test_incomplete_last_event_is_dropped() {
+        let dir = log_dir();
+        let (log, mut state) = RoomLog::open(dir.clone(), None, &state()).unwrap();
+        append(log, &mut state, vec![message(1, "alice")]).await;
+
+        let events_path = dir.join(EVENTS_FILE);
+        let mut events = std::fs::read_to_string(&events_path).unwrap();
+        events.push_str("{\"seq\":2,\"type\":\"mess");
+        std::fs::write(&events_path, events).unwrap();
+
+        let (rebuilt, last_seq, _) = load(&dir, None).unwrap().unwrap();
+        assert_eq!(rebuilt, state);
+        assert_eq!(last_seq, 1);
+        assert!(std::fs::read_to_string(&events_path)
+            .unwrap()
+            .ends_with("}\n"));
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_last_event_is_dropped() {
+        let dir = log_dir();
+        let (log, mut state) = RoomLog::open(dir.clone(), None, &state()).unwrap();
+        append(log, &mut state, vec![message(1, "alice")]).await;
+
+        let events_path = dir.join(EVENTS_FILE);
+        let events = std::fs::read_to_string(&events_path).unwrap();
+        for last_line in [
+            "{\"seq\":2,\"type\":\"mess\n",
+            "{\"seq\":7,\"type\":\"left\"}\n",
+        ] {
+            std::fs::write(&events_path, format!("{}{}", events, last_line)).unwrap();
+
+            let (rebuilt, last_seq, log_len) = load(&dir, None).unwrap().unwrap();
+            assert_eq!(rebuilt, state);
+            assert_eq!(last_seq, 1);
+            assert_eq!(log_len, events.len() as u64);
+            assert_eq!(std::fs::read_to_string(&events_path).unwrap(), events);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_log_is_only_rebuilt_with_its_key() {
+        let dir = log_dir();
+        let data_key = DataKey::decode("BwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwc=").unwrap();
+        let (log, mut state) =
+            RoomLog::open(dir.clone(), Some(data_key.clone()), &state()).unwrap();
+
+        let events = vec![
+            message(1, "alice"),
+            message(2, "bob"),
+            RoomEvent::UserPurged {
+                user_id: String::from("alice"),
+            },
+            message(3, "bob"),
+        ];
+        append(log, &mut state, events).await;
+
+        for file in [EVENTS_FILE, SNAPSHOT_FILE] {
+            let contents = std::fs::read(dir.join(file)).unwrap();
+            assert!(!String::from_utf8_lossy(&contents).contains("message"));
+        }
+        let (rebuilt, last_seq, _) = load(&dir, Some(&data_key)).unwrap().unwrap();
+        assert_eq!(rebuilt, state);
+        assert_eq!(last_seq, 4);
+        assert!(load(&dir, None).is_err());
+        assert!(load(&dir, Some(&data_key.derive("other"))).is_err());
+    }
+}
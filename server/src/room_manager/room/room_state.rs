@@ -0,0 +1,233 @@
+use std::collections::{BTreeSet, VecDeque};
+
+use comms::event::{ContentType, HistoricalMessage, HistoricalMessageKind};
+use serde::{Deserialize, Serialize};
+
+use super::ChatRoomMetadata;
+
+const HISTORY_CAPACITY: usize = 10;
+
+/// [RoomEvent] is a change to the state of a room, the state is the result of applying them in order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RoomEvent {
+    /// The first session of a user has joined the room, `id` is the id of the history entry if it is recorded
+    Joined {
+        id: String,
+        user_id: String,
+        at: u64,
+    },
+    /// The last session of a user has left the room, `id` is the id of the history entry if it is recorded
+    Left {
+        id: String,
+        user_id: String,
+        at: u64,
+    },
+    Message {
+        id: String,
+        user_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        display_name: Option<String>,
+        content: String,
+        #[serde(default, skip_serializing_if = "ContentType::is_plain")]
+        content_type: ContentType,
+        at: u64,
+    },
+    /// The metadata of the room has been changed, e.g. its welcome message or its capacity
+    MetadataChanged { metadata: ChatRoomMetadata },
+    /// The entries about the user have been removed from the history
+    UserPurged { user_id: String },
+}
+
+/// [RoomState] is what a room is made of besides its sessions, rebuilt by applying its [RoomEvent]s
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomState {
+    pub metadata: ChatRoomMetadata,
+    /// The users in the room with at least one session
+    pub members: BTreeSet<String>,
+    /// The last entries of the room, the oldest first
+    pub history: VecDeque<HistoricalMessage>,
+}
+
+impl RoomState {
+    pub fn new(metadata: ChatRoomMetadata) -> Self {
+        RoomState {
+            metadata,
+            members: BTreeSet::new(),
+            history: VecDeque::with_capacity(HISTORY_CAPACITY),
+        }
+    }
+
+    /// Apply the event to the state, the same events applied in the same order always lead to the same state
+    pub fn apply(&mut self, event: &RoomEvent) {
+        match event {
+            RoomEvent::Joined { id, user_id, at } => {
+                self.members.insert(user_id.clone());
+                self.record_participation(id, user_id, *at, HistoricalMessageKind::Joined);
+            }
+            RoomEvent::Left { id, user_id, at } => {
+                self.members.remove(user_id);
+                self.record_participation(id, user_id, *at, HistoricalMessageKind::Left);
+            }
+            RoomEvent::Message {
+                id,
+                user_id,
+                display_name,
+                content,
+                content_type,
+                at,
+            } => self.add_to_history(HistoricalMessage {
+                id: id.clone(),
+                user_id: user_id.clone(),
+                display_name: display_name.clone(),
+                content: content.clone(),
+                content_type: *content_type,
+                timestamp: *at,
+                kind: HistoricalMessageKind::Message,
+            }),
+            RoomEvent::MetadataChanged { metadata } => self.metadata = metadata.clone(),
+            RoomEvent::UserPurged { user_id } => {
+                self.history.retain(|message| &message.user_id != user_id)
+            }
+        }
+    }
+
+    /// Keep joins and leaves in the history if the room keeps participation history
+    fn record_participation(
+        &mut self,
+        id: &str,
+        user_id: &str,
+        at: u64,
+        kind: HistoricalMessageKind,
+    ) {
+        if self.metadata.record_participation {
+            self.add_to_history(HistoricalMessage {
+                id: String::from(id),
+                user_id: String::from(user_id),
+                display_name: None,
+                content: String::new(),
+                content_type: ContentType::Plain,
+                timestamp: at,
+                kind,
+            });
+        }
+    }
+
+    /* Add entry to queue, pop front if exceed the capacity */
+    fn add_to_history(&mut self, message: HistoricalMessage) {
+        if self.history.len() >= HISTORY_CAPACITY {
+            self.history.pop_front();
+        }
+        self.history.push_back(message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use comms::event::LagPolicy;
+
+    use super::*;
+
+    fn metadata(record_participation: bool) -> ChatRoomMetadata {
+        ChatRoomMetadata {
+            name: String::from("test"),
+            description: String::new(),
+            category: None,
+            capacity: None,
+            welcome_message: None,
+            owners: Vec::new(),
+            groups: Vec::new(),
+            record_participation,
+            encrypted: false,
+            requires_approval: false,
+            broadcast_capacity: None,
+            lag_policy: LagPolicy::Resync,
+        }
+    }
+
+    fn message(id: &str, user_id: &str) -> RoomEvent {
+        RoomEvent::Message {
+            id: String::from(id),
+            user_id: String::from(user_id),
+            display_name: None,
+            content: format!("message {}", id),
+            content_type: ContentType::Plain,
+            at: 1,
+        }
+    }
+
+    #[test]
+    fn test_participation_is_recorded_once_turned_on() {
+        let mut state = RoomState::new(metadata(false));
+
+        state.apply(&RoomEvent::Joined {
+            id: String::from("1"),
+            user_id: String::from("alice"),
+            at: 1,
+        });
+        state.apply(&RoomEvent::MetadataChanged {
+            metadata: metadata(true),
+        });
+        state.apply(&RoomEvent::Left {
+            id: String::from("2"),
+            user_id: String::from("alice"),
+            at: 2,
+        });
+
+        assert!(state.members.is_empty());
+        assert_eq!(
+            state
+                .history
+                .iter()
+                .map(|message| (message.id.as_str(), message.kind.clone()))
+                .collect::<Vec<_>>(),
+            vec![("2", HistoricalMessageKind::Left)]
+        );
+    }
+
+    #[test]
+    fn test_history_keeps_the_latest_entries() {
+        let mut state = RoomState::new(metadata(false));
+
+        for id in 0..HISTORY_CAPACITY + 2 {
+            state.apply(&message(&id.to_string(), "alice"));
+        }
+        state.apply(&message("bob", "bob"));
+        state.apply(&RoomEvent::UserPurged {
+            user_id: String::from("alice"),
+        });
+
+        assert_eq!(
+            state
+                .history
+                .iter()
+                .map(|message| message.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["bob"]
+        );
+    }
+
+    #[test]
+    fn test_content_type_is_kept_in_the_history_and_the_log() {
+        let mut state = RoomState::new(metadata(false));
+        let event = RoomEvent::Message {
+            id: String::from("1"),
+            user_id: String::from("alice"),
+            display_name: None,
+            content: String::from("waves"),
+            content_type: ContentType::Action,
+            at: 1,
+        };
+
+        state.apply(&event);
+
+        assert_eq!(state.history[0].content_type, ContentType::Action);
+        let logged = serde_json::to_string(&event).unwrap();
+        assert!(logged.contains(r#""content_type":"action""#));
+        assert_eq!(serde_json::from_str::<RoomEvent>(&logged).unwrap(), event);
+        // plain messages are logged as they were before content types
+        assert!(!serde_json::to_string(&message("2", "alice"))
+            .unwrap()
+            .contains("content_type"));
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::{HashMap, VecDeque};
+
+use comms::event;
+
+const HOUR_MILLIS: u64 = 60 * 60 * 1000;
+/// How far back the activity of a room is kept
+const TRACKED_HOURS: u64 = 24;
+
+/// [RoomStats] keeps track of how busy a room is, e.g. for users deciding which room to join
+#[derive(Debug, Default)]
+pub struct RoomStats {
+    /// The number of messages sent per hour, by the start of the hour, oldest first
+    hourly_messages: VecDeque<(u64, u32)>,
+    /// The last time each user has sent a message
+    last_message_at: HashMap<String, u64>,
+    peak_concurrency: usize,
+}
+
+fn hour_of(timestamp: u64) -> u64 {
+    timestamp - timestamp % HOUR_MILLIS
+}
+
+impl RoomStats {
+    pub fn record_message(&mut self, user_id: &str, timestamp: u64) {
+        let hour = hour_of(timestamp);
+
+        match self.hourly_messages.back_mut() {
+            Some((last_hour, count)) if *last_hour == hour => *count += 1,
+            _ => {
+                self.hourly_messages.push_back((hour, 1));
+                // only pruned once an hour, the users who have not sent a message for a day are no longer active
+                let oldest_hour = hour.saturating_sub((TRACKED_HOURS - 1) * HOUR_MILLIS);
+                let since = timestamp.saturating_sub(TRACKED_HOURS * HOUR_MILLIS);
+                self.hourly_messages
+                    .retain(|(message_hour, _)| *message_hour >= oldest_hour);
+                self.last_message_at
+                    .retain(|_, last_message_at| *last_message_at > since);
+            }
+        }
+
+        self.last_message_at
+            .insert(String::from(user_id), timestamp);
+    }
+
+    /// Record the number of users in the room after someone has joined
+    pub fn record_member_count(&mut self, member_count: usize) {
+        self.peak_concurrency = self.peak_concurrency.max(member_count);
+    }
+
+    /// The stats of the room as of the given time
+    pub fn snapshot(&self, room: &str, member_count: usize, now: u64) -> event::RoomStats {
+        let current_hour = hour_of(now);
+        let messages_per_hour = (0..TRACKED_HOURS)
+            .rev()
+            .map(|hours_ago| {
+                let hour = current_hour.saturating_sub(hours_ago * HOUR_MILLIS);
+
+                self.hourly_messages
+                    .iter()
+                    .find(|(message_hour, _)| *message_hour == hour)
+                    .map(|(_, count)| *count)
+                    .unwrap_or(0)
+            })
+            .collect();
+        let since = now.saturating_sub(TRACKED_HOURS * HOUR_MILLIS);
+
+        event::RoomStats {
+            room: String::from(room),
+            messages_per_hour,
+            active_users: self
+                .last_message_at
+                .values()
+                .filter(|last_message_at| **last_message_at > since)
+                .count(),
+            member_count,
+            peak_concurrency: self.peak_concurrency.max(member_count),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000_000;
+
+    #[test]
+    fn test_messages_are_counted_per_hour() {
+        let mut stats = RoomStats::default();
+        stats.record_message("alice", NOW - 2 * HOUR_MILLIS);
+        stats.record_message("alice", NOW - HOUR_MILLIS);
+        stats.record_message("bob", NOW - HOUR_MILLIS);
+        stats.record_message("bob", NOW);
+
+        let snapshot = stats.snapshot("test", 1, NOW);
+        assert_eq!(snapshot.messages_per_hour.len(), TRACKED_HOURS as usize);
+        assert_eq!(snapshot.messages_per_hour[21..], [1, 2, 1]);
+        assert_eq!(snapshot.active_users, 2);
+    }
+
+    #[test]
+    fn test_old_activity_is_forgotten() {
+        let mut stats = RoomStats::default();
+        stats.record_message("alice", NOW - 30 * HOUR_MILLIS);
+        stats.record_message("bob", NOW);
+
+        let snapshot = stats.snapshot("test", 0, NOW);
+        assert_eq!(snapshot.messages_per_hour.iter().sum::<u32>(), 1);
+        assert_eq!(snapshot.active_users, 1);
+        assert_eq!(stats.hourly_messages.len(), 1);
+    }
+
+    #[test]
+    fn test_peak_concurrency_is_kept() {
+        let mut stats = RoomStats::default();
+        stats.record_member_count(3);
+        stats.record_member_count(5);
+        stats.record_member_count(2);
+
+        assert_eq!(stats.snapshot("test", 1, NOW).peak_concurrency, 5);
+    }
+}
@@ -1,6 +1,6 @@
 use std::collections::{HashMap, HashSet};
 
-use super::user_session_handle::UserSessionHandle;
+use super::{user_session_handle::UserSessionHandle, SessionAndUserId};
 
 #[derive(Debug)]
 pub struct UserRegistry {
@@ -24,10 +24,7 @@ impl UserRegistry {
         let user_id = String::from(user_session_handle.user_id());
         let session_id = String::from(user_session_handle.session_id());
 
-        let sessions = self
-            .user_id_to_sessions
-            .entry(user_id.clone())
-            .or_insert_with(HashSet::new);
+        let sessions = self.user_id_to_sessions.entry(user_id.clone()).or_default();
 
         sessions.insert(session_id);
 
@@ -64,6 +61,23 @@ impl UserRegistry {
         }
     }
 
+    /// Returns true if the user has at least one session in the room
+    pub fn contains_user(&self, user_id: &str) -> bool {
+        self.user_ids.contains(user_id)
+    }
+
+    /// Returns true if the given session of the user is in the room
+    pub fn contains_session(&self, session_and_user_id: &SessionAndUserId) -> bool {
+        self.user_id_to_sessions
+            .get(&session_and_user_id.user_id)
+            .is_some_and(|sessions| sessions.contains(&session_and_user_id.session_id))
+    }
+
+    /// Returns the number of unique users in the room
+    pub fn user_count(&self) -> usize {
+        self.user_ids.len()
+    }
+
     pub fn get_unique_user_ids(&self) -> Vec<String> {
         self.user_ids.iter().cloned().collect()
     }
@@ -1,7 +1,14 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
 use anyhow::Context;
-use comms::event;
+use comms::{event, transport::server::EncodedEvent};
 use tokio::sync::broadcast;
 
+use crate::logging;
+
 #[derive(Debug, Clone)]
 pub struct SessionAndUserId {
     pub session_id: String,
@@ -13,28 +20,48 @@ pub struct SessionAndUserId {
 /// send messages to a specific room.
 ///
 /// It is created when a user joins a room and is handed out to the user.
+/// It is given back to the room when the user leaves it, see [crate::room_manager::RoomManager::drop_user_session_handle].
+/// A handle dropped otherwise leaves the user listed in the room, it is logged and counted in the broadcast
+/// metrics of the room.
 pub struct UserSessionHandle {
     /// The name of the room which is associated with this handle
     room: String,
     /// The channel to use for sending events to the all users of the room
-    broadcast_tx: broadcast::Sender<event::Event>,
+    broadcast_tx: broadcast::Sender<EncodedEvent>,
     /// The session and user id associated with this handle
     session_and_user_id: SessionAndUserId,
+    /// The number of handles of the room dropped without leaving it, shared with the room
+    leaked_handles: Arc<AtomicU64>,
+    /// Whether the handle has been given back to the room, or given up on along with the room
+    is_released: bool,
 }
 
 impl UserSessionHandle {
     pub(super) fn new(
         room: String,
-        broadcast_tx: broadcast::Sender<event::Event>,
+        broadcast_tx: broadcast::Sender<EncodedEvent>,
         session_and_user_id: SessionAndUserId,
+        leaked_handles: Arc<AtomicU64>,
     ) -> Self {
         UserSessionHandle {
             room,
             broadcast_tx,
             session_and_user_id,
+            leaked_handles,
+            is_released: false,
         }
     }
 
+    /// Marks the handle as given back to the room, which has removed the user
+    pub(super) fn release(&mut self) {
+        self.is_released = true;
+    }
+
+    /// Drops the handle without leaving the room, e.g. when the server shuts down and the room goes along with it
+    pub fn abandon(mut self) {
+        self.is_released = true;
+    }
+
     pub fn room(&self) -> &str {
         &self.room
     }
@@ -47,18 +74,60 @@ impl UserSessionHandle {
         &self.session_and_user_id.user_id
     }
 
-    /// Send a message to the room
-    pub fn send_message(&self, content: String) -> anyhow::Result<()> {
+    /// Send a message to the room, the id and the timestamp are the ones of its entry in the history of the room
+    pub fn send_message(
+        &self,
+        id: String,
+        timestamp: u64,
+        content: String,
+        content_type: event::ContentType,
+        nonce: Option<String>,
+    ) -> anyhow::Result<()> {
         self.broadcast_tx
-            .send(event::Event::UserMessage(
+            .send(EncodedEvent::encode(&event::Event::UserMessage(
                 event::UserMessageBroadcastEvent {
                     room: self.room.clone(),
+                    id,
                     user_id: self.session_and_user_id.user_id.clone(),
+                    display_name: None,
                     content,
+                    content_type,
+                    timestamp,
+                    nonce,
+                },
+            ))?)
+            .context("could not write to the broadcast channel")?;
+
+        Ok(())
+    }
+
+    /// Ask the other members of an encrypted room for its key, sealed for the given public key
+    pub fn request_room_key(&self, public_key: String) -> anyhow::Result<()> {
+        self.broadcast_tx
+            .send(EncodedEvent::encode(&event::Event::RoomKeyRequested(
+                event::RoomKeyRequestedBroadcastEvent {
+                    room: self.room.clone(),
+                    user_id: self.session_and_user_id.user_id.clone(),
+                    session_id: self.session_and_user_id.session_id.clone(),
+                    public_key,
                 },
-            ))
+            ))?)
             .context("could not write to the broadcast channel")?;
 
         Ok(())
     }
 }
+
+impl Drop for UserSessionHandle {
+    fn drop(&mut self) {
+        if self.is_released {
+            return;
+        }
+
+        self.leaked_handles.fetch_add(1, Ordering::Relaxed);
+        logging::warn(format!(
+            "the handle of session {} of {} was dropped without leaving room {}, the user is still listed in it",
+            self.session_and_user_id.session_id, self.session_and_user_id.user_id, self.room
+        ));
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::VecDeque;
+
+use tokio::sync::mpsc;
+
+use super::{chat_room::RoomJoinResult, SessionAndUserId};
+
+/// Updates sent to a session which waits for a slot in a full room
+#[derive(Debug)]
+pub enum WaitingRoomUpdate {
+    /// The position of the session in the queue of the room has changed, starting from 1
+    Position { room: String, position: usize },
+    /// A slot was freed up and the session has been joined to the room on its behalf
+    Joined {
+        room: String,
+        join_result: RoomJoinResult,
+    },
+}
+
+#[derive(Debug)]
+struct Waiter {
+    session_and_user_id: SessionAndUserId,
+    update_tx: mpsc::UnboundedSender<WaitingRoomUpdate>,
+}
+
+/// [WaitingQueue] keeps the sessions waiting for a slot in a full room in arrival order
+#[derive(Debug)]
+pub struct WaitingQueue {
+    waiters: VecDeque<Waiter>,
+}
+
+impl WaitingQueue {
+    pub fn new() -> Self {
+        WaitingQueue {
+            waiters: VecDeque::new(),
+        }
+    }
+
+    /// Add a session to the end of the queue, returns the position of the session starting from 1
+    /// A session which is already waiting keeps its original position
+    pub fn push(
+        &mut self,
+        session_and_user_id: &SessionAndUserId,
+        update_tx: mpsc::UnboundedSender<WaitingRoomUpdate>,
+    ) -> usize {
+        if let Some(idx) = self.position_of(&session_and_user_id.session_id) {
+            return idx + 1;
+        }
+
+        self.waiters.push_back(Waiter {
+            session_and_user_id: session_and_user_id.clone(),
+            update_tx,
+        });
+
+        self.waiters.len()
+    }
+
+    /// Remove a session from the queue, returns true if the session was waiting
+    pub fn remove(&mut self, session_id: &str) -> bool {
+        match self.position_of(session_id) {
+            Some(idx) => {
+                self.waiters.remove(idx);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.waiters.is_empty()
+    }
+
+    /// Take the session at the front of the queue alongside the channel to notify it
+    pub fn pop_front(
+        &mut self,
+    ) -> Option<(SessionAndUserId, mpsc::UnboundedSender<WaitingRoomUpdate>)> {
+        self.waiters
+            .pop_front()
+            .map(|waiter| (waiter.session_and_user_id, waiter.update_tx))
+    }
+
+    /// Send the current position to every waiting session, dropping the ones which are gone
+    pub fn notify_positions(&mut self, room: &str) {
+        let mut position = 0;
+
+        self.waiters.retain(|waiter| {
+            let is_alive = waiter
+                .update_tx
+                .send(WaitingRoomUpdate::Position {
+                    room: String::from(room),
+                    position: position + 1,
+                })
+                .is_ok();
+
+            if is_alive {
+                position += 1;
+            }
+
+            is_alive
+        });
+    }
+
+    fn position_of(&self, session_id: &str) -> Option<usize> {
+        self.waiters
+            .iter()
+            .position(|waiter| waiter.session_and_user_id.session_id == session_id)
+    }
+}
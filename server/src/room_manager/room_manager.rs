@@ -1,16 +1,36 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, RwLock, RwLockReadGuard},
+    time::Duration,
+};
 
-use comms::event::Event;
-use tokio::sync::{broadcast, Mutex};
+use comms::event::{
+    ContentType, HistoricalMessage, LagPolicy, LinkPreviewBroadcastEvent, PresenceStatus,
+    RoomBroadcastMetrics, RoomInfoReplyEvent, RoomStats,
+};
+use comms::transport::server::EncodedEvent;
+use tokio::sync::{broadcast, mpsc, Mutex, MutexGuard};
 
-use super::room::{ChatRoom, ChatRoomMetadata, SessionAndUserId, UserSessionHandle};
+use crate::{clock::unix_millis_now, logging, storage::DataKey};
 
-pub type RoomJoinResult = (broadcast::Receiver<Event>, UserSessionHandle, Vec<String>);
+use super::room::{
+    ChatRoom, ChatRoomMetadata, JoinApproval, RoomJoinOutcome, SessionAndUserId, UserSessionHandle,
+    WaitingRoomUpdate,
+};
+
+/// The directory the room logs are kept in and the key they are encrypted with, if the data is encrypted at rest
+type RoomLogsLocation = (PathBuf, Option<DataKey>);
 
 #[derive(Debug, Clone)]
 pub struct RoomManager {
-    chat_rooms: HashMap<String, Arc<Mutex<ChatRoom>>>,
-    chat_room_metadata: Vec<ChatRoomMetadata>,
+    /// Rooms may be created while the server runs, e.g. when the provisioned rooms are reloaded
+    chat_rooms: Arc<RwLock<HashMap<String, Arc<Mutex<ChatRoom>>>>>,
+    /// Owners and admins may change the description and the capacity of the rooms while the server runs
+    /// Kept in the order the rooms were created
+    chat_room_metadata: Arc<RwLock<Vec<ChatRoomMetadata>>>,
+    /// The directory the state of every room is persisted in, one subdirectory per room, see [RoomManager::open_room_logs]
+    room_logs: Arc<RwLock<Option<RoomLogsLocation>>>,
 }
 
 impl RoomManager {
@@ -18,77 +38,1163 @@ impl RoomManager {
         let chat_room_metadata = chat_rooms
             .iter()
             .map(|(metadata, _)| metadata.clone())
-            .collect();
+            .collect::<Vec<_>>();
 
         RoomManager {
-            chat_room_metadata,
-            chat_rooms: chat_rooms
-                .into_iter()
-                .map(|(metadata, chat_room)| (metadata.name.clone(), chat_room))
-                .collect(),
+            chat_room_metadata: Arc::new(RwLock::new(chat_room_metadata)),
+            room_logs: Arc::new(RwLock::new(None)),
+            chat_rooms: Arc::new(RwLock::new(
+                chat_rooms
+                    .into_iter()
+                    .map(|(metadata, chat_room)| (metadata.name.clone(), chat_room))
+                    .collect(),
+            )),
+        }
+    }
+
+    fn chat_room(&self, room_name: &str) -> anyhow::Result<Arc<Mutex<ChatRoom>>> {
+        self.chat_rooms
+            .read()
+            .expect("the room lock has been poisoned")
+            .get(room_name)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("room '{}' not found", room_name))
+    }
+
+    /// Every room along with its name, in the order the rooms were created
+    fn chat_rooms(&self) -> Vec<(String, Arc<Mutex<ChatRoom>>)> {
+        let chat_rooms = self
+            .chat_rooms
+            .read()
+            .expect("the room lock has been poisoned");
+
+        self.chat_room_metadata()
+            .iter()
+            .filter_map(|metadata| {
+                let chat_room = chat_rooms.get(&metadata.name)?;
+                Some((metadata.name.clone(), Arc::clone(chat_room)))
+            })
+            .collect()
+    }
+
+    /// Create a room while the server runs, it is listed after the rooms created before it
+    /// Fails if a room with the same name already exists, or if its broadcast capacity is zero
+    pub fn create_room(&self, metadata: ChatRoomMetadata) -> anyhow::Result<()> {
+        if metadata.broadcast_capacity() == 0 {
+            anyhow::bail!("the broadcast capacity of a room must be greater than zero");
+        }
+
+        let mut chat_rooms = self
+            .chat_rooms
+            .write()
+            .expect("the room lock has been poisoned");
+        if chat_rooms.contains_key(&metadata.name) {
+            anyhow::bail!("room '{}' already exists", metadata.name);
+        }
+
+        let mut chat_room = ChatRoom::new(metadata.clone());
+        if let Some((room_logs_dir, data_key)) = self.room_logs() {
+            // the rooms are locked, which can not be held across an await
+            chat_room.open_log_blocking(room_logs_dir.join(&metadata.name), data_key)?;
+        }
+
+        // the rooms are still locked, so the room can not be listed before it can be found
+        self.chat_room_metadata
+            .write()
+            .expect("the room metadata lock has been poisoned")
+            .push(metadata.clone());
+        chat_rooms.insert(metadata.name.clone(), Arc::new(Mutex::new(chat_room)));
+
+        Ok(())
+    }
+
+    fn room_logs(&self) -> Option<RoomLogsLocation> {
+        self.room_logs
+            .read()
+            .expect("the room logs lock has been poisoned")
+            .clone()
+    }
+
+    /// Persist the state of every room as a log of its events in its own subdirectory of the given directory,
+    /// including the rooms created later on. The rooms persisted before get back their history and their metadata
+    /// changes are kept in the log, while the metadata they have been created with stays in effect.
+    /// The logs are encrypted with the given key, see [DataKey]
+    pub async fn open_room_logs(
+        &self,
+        dir: PathBuf,
+        data_key: Option<DataKey>,
+    ) -> anyhow::Result<()> {
+        for (room_name, room) in self.chat_rooms() {
+            room.lock()
+                .await
+                .open_log(dir.join(&room_name), data_key.clone())
+                .await?;
+        }
+        *self
+            .room_logs
+            .write()
+            .expect("the room logs lock has been poisoned") = Some((dir, data_key));
+
+        Ok(())
+    }
+
+    /// Stop persisting the state of the rooms and wait for their logs to be written, e.g. when the server shuts down
+    pub async fn close_room_logs(&self) {
+        self.room_logs
+            .write()
+            .expect("the room logs lock has been poisoned")
+            .take();
+
+        for (_, room) in self.chat_rooms() {
+            let log = room.lock().await.take_log();
+            if let Some(log) = log {
+                log.close().await;
+            }
+        }
+    }
+
+    /// Release the history and the broadcast channel of the rooms which have been idle for the given duration,
+    /// see [ChatRoom::can_hibernate]. Their history is persisted first, a room wakes up once it is needed again.
+    /// Returns the number of rooms put to hibernation
+    pub async fn hibernate_idle_rooms(&self, idle_for: Duration) -> usize {
+        let mut count = 0;
+
+        for (_, room) in self.chat_rooms() {
+            let mut room = room.lock().await;
+            if !room.can_hibernate(idle_for, unix_millis_now()) {
+                continue;
+            }
+
+            // closed while the room is locked, so it is not reopened before its events are written
+            if let Some(log) = room.hibernate() {
+                log.close().await;
+            }
+            count += 1;
+        }
+
+        count
+    }
+
+    /// Put the idle rooms to hibernation every once in a while until the server shuts down,
+    /// see [RoomManager::hibernate_idle_rooms]
+    pub async fn hibernate_until_quit(
+        &self,
+        idle_for: Duration,
+        mut quit_rx: broadcast::Receiver<()>,
+    ) {
+        // a room is put to hibernation at most half the idle duration late
+        let check_interval = idle_for / 2;
+
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(check_interval) => {}
+                _ = quit_rx.recv() => break,
+            }
+
+            let count = self.hibernate_idle_rooms(idle_for).await;
+            if count > 0 {
+                logging::info(format!("Put {} idle rooms to hibernation", count));
+            }
         }
     }
 
-    pub fn chat_room_metadata(&self) -> &Vec<ChatRoomMetadata> {
-        &self.chat_room_metadata
+    /// The metadata of every room in the order the rooms were created
+    /// Must not be held across an await, the rooms can not be updated meanwhile
+    pub fn chat_room_metadata(&self) -> RwLockReadGuard<'_, Vec<ChatRoomMetadata>> {
+        self.chat_room_metadata
+            .read()
+            .expect("the room metadata lock has been poisoned")
+    }
+
+    /// Returns true if the messages of the room are encrypted end-to-end
+    pub fn is_encrypted(&self, room_name: &str) -> bool {
+        self.chat_room_metadata()
+            .iter()
+            .any(|metadata| metadata.name == room_name && metadata.encrypted)
+    }
+
+    /// Change the description or the capacity of a room, `Some(0)` lifts the capacity limit
+    /// Users waiting for a slot join right away if the capacity has grown
+    /// Returns the updated metadata of the room
+    pub async fn update_metadata(
+        &self,
+        room_name: &str,
+        description: Option<String>,
+        capacity: Option<usize>,
+    ) -> anyhow::Result<ChatRoomMetadata> {
+        let room = self.chat_room(room_name)?;
+
+        let mut room = lock_awake(&room).await;
+        let capacity = capacity.map(|capacity| Some(capacity).filter(|capacity| *capacity > 0));
+        let metadata = room.update_metadata(description, capacity);
+        // the room is still locked, so the two copies of the metadata can not be updated out of order
+        self.store_metadata(&metadata);
+
+        Ok(metadata)
+    }
+
+    /// Replace the metadata of a room, e.g. with the provisioned one, its broadcast capacity stays as it is
+    /// Users waiting for a slot join right away if the capacity has grown
+    pub async fn replace_metadata(&self, metadata: ChatRoomMetadata) -> anyhow::Result<()> {
+        let room = self.chat_room(&metadata.name)?;
+
+        let mut room = lock_awake(&room).await;
+        let metadata = room.replace_metadata(metadata);
+        self.store_metadata(&metadata);
+
+        Ok(())
+    }
+
+    /// Keep the copy of the metadata of a room in line with the room, which must be locked meanwhile
+    fn store_metadata(&self, metadata: &ChatRoomMetadata) {
+        let mut chat_room_metadata = self
+            .chat_room_metadata
+            .write()
+            .expect("the room metadata lock has been poisoned");
+        if let Some(room_metadata) = chat_room_metadata
+            .iter_mut()
+            .find(|room_metadata| room_metadata.name == metadata.name)
+        {
+            *room_metadata = metadata.clone();
+        }
     }
 
     /// Joins to a room given a user session
+    /// If the room is full and a `waiting_tx` is given, the session waits in the queue of the room
     pub async fn join_room(
         &self,
         room_name: &str,
         session_and_user_id: &SessionAndUserId,
-    ) -> anyhow::Result<RoomJoinResult> {
-        let room = self
-            .chat_rooms
-            .get(room_name)
-            .ok_or_else(|| anyhow::anyhow!("room '{}' not found", room_name))?;
+        waiting_tx: Option<mpsc::UnboundedSender<WaitingRoomUpdate>>,
+    ) -> anyhow::Result<RoomJoinOutcome> {
+        let room = self.chat_room(room_name)?;
 
-        let mut room = room.lock().await;
-        let (broadcast_rx, user_session_handle) = room.join(session_and_user_id);
+        let mut room = lock_awake(&room).await;
+
+        Ok(room.join(session_and_user_id, waiting_tx))
+    }
+
+    /// Checks whether the user may join the room, see [ChatRoom::request_join_approval]
+    pub async fn request_join_approval(
+        &self,
+        room_name: &str,
+        user_id: &str,
+    ) -> anyhow::Result<JoinApproval> {
+        let room = self.chat_room(room_name)?;
+
+        let mut room = lock_awake(&room).await;
 
-        Ok((
-            broadcast_rx,
-            user_session_handle,
-            room.get_unique_user_ids().clone(),
-        ))
+        Ok(room.request_join_approval(user_id))
+    }
+
+    /// Approves or denies the pending request of a user to join the room
+    /// Returns false if the user has not asked to join
+    pub async fn decide_join_request(
+        &self,
+        room_name: &str,
+        user_id: &str,
+        is_approved: bool,
+    ) -> anyhow::Result<bool> {
+        let room = self.chat_room(room_name)?;
+
+        let mut room = lock_awake(&room).await;
+
+        Ok(room.decide_join_request(user_id, is_approved))
+    }
+
+    /// Count the session among the ones holding the key of the encrypted room, see [ChatRoom::hold_room_key]
+    pub async fn hold_room_key(
+        &self,
+        room_name: &str,
+        session_and_user_id: &SessionAndUserId,
+    ) -> anyhow::Result<bool> {
+        let room = self.chat_room(room_name)?;
+
+        let mut room = lock_awake(&room).await;
+
+        Ok(room.hold_room_key(session_and_user_id))
+    }
+
+    /// Stops waiting for a slot in a full room
+    pub async fn leave_waiting_queue(
+        &self,
+        room_name: &str,
+        session_id: &str,
+    ) -> anyhow::Result<()> {
+        let room = self.chat_room(room_name)?;
+
+        let mut room = lock_awake(&room).await;
+
+        room.leave_waiting_queue(session_id);
+
+        Ok(())
+    }
+
+    /// Change the welcome message of a room on behalf of a user
+    /// Returns false if the user is not an owner of the room
+    pub async fn set_welcome_message(
+        &self,
+        room_name: &str,
+        user_id: &str,
+        welcome_message: Option<String>,
+    ) -> anyhow::Result<bool> {
+        let room = self.chat_room(room_name)?;
+
+        let mut room = lock_awake(&room).await;
+
+        Ok(room.set_welcome_message(user_id, welcome_message))
+    }
+
+    /// The names of the rooms the user is in, in the order the rooms were created
+    pub async fn rooms_of_user(&self, user_id: &str) -> Vec<String> {
+        let mut rooms = Vec::new();
+
+        for (room_name, room) in self.chat_rooms() {
+            if room.lock().await.contains_user(user_id) {
+                rooms.push(room_name);
+            }
+        }
+
+        rooms
+    }
+
+    /// The details of a room, e.g. its description and how many users are in it
+    pub async fn get_room_info(&self, room_name: &str) -> anyhow::Result<RoomInfoReplyEvent> {
+        let room = self.chat_room(room_name)?;
+
+        let room = room.lock().await;
+
+        Ok(room.room_info())
+    }
+
+    /// How busy a room has been, e.g. the number of messages per hour
+    pub async fn room_stats(&self, room_name: &str) -> anyhow::Result<RoomStats> {
+        let room = self.chat_room(room_name)?;
+
+        let room = room.lock().await;
+
+        Ok(room.stats())
+    }
+
+    /// How busy every room has been, in the order the rooms were created
+    pub async fn all_room_stats(&self) -> Vec<RoomStats> {
+        let mut stats = Vec::new();
+
+        for (_, room) in self.chat_rooms() {
+            stats.push(room.lock().await.stats());
+        }
+
+        stats
+    }
+
+    /// Broadcast the new presence of a user to every room they are in
+    pub async fn broadcast_presence(&self, user_id: &str, status: PresenceStatus) {
+        for (_, room) in self.chat_rooms() {
+            room.lock().await.broadcast_presence(user_id, status);
+        }
+    }
+
+    /// Broadcast the new status text of a user to the given room, or to every room they are in if none is given
+    pub async fn broadcast_status_text(
+        &self,
+        room: Option<&str>,
+        user_id: &str,
+        text: Option<&str>,
+    ) {
+        for (name, chat_room) in self.chat_rooms() {
+            if room.is_none_or(|room| room == name) {
+                chat_room.lock().await.broadcast_status_text(user_id, text);
+            }
+        }
+    }
+
+    /// Broadcast an announcement of the server to the given rooms, or to every room if none are given
+    pub async fn announce(&self, rooms: &[String], message: &str) {
+        for (name, room) in self.chat_rooms() {
+            if rooms.is_empty() || rooms.contains(&name) {
+                room.lock().await.announce(message);
+            }
+        }
+    }
+
+    /// Broadcast the preview of a link in a message to the participants of its room
+    pub async fn broadcast_link_preview(
+        &self,
+        link_preview: LinkPreviewBroadcastEvent,
+    ) -> anyhow::Result<()> {
+        let room = self.chat_room(&link_preview.room)?;
+
+        room.lock().await.broadcast_link_preview(link_preview);
+
+        Ok(())
+    }
+
+    /// Receive the events of a room without taking part in it, e.g. to mirror its messages
+    pub async fn subscribe(
+        &self,
+        room_name: &str,
+    ) -> anyhow::Result<broadcast::Receiver<EncodedEvent>> {
+        let room = self.chat_room(room_name)?;
+        let mut room = lock_awake(&room).await;
+
+        Ok(room.subscribe())
+    }
+
+    /// Record a message posted without a session in the history of the room and broadcast it to the participants
+    /// Returns the id of the message
+    pub async fn post_message(
+        &self,
+        room_name: &str,
+        user_id: String,
+        display_name: Option<String>,
+        content: String,
+    ) -> anyhow::Result<String> {
+        let room = self.chat_room(room_name)?;
+
+        let mut room = lock_awake(&room).await;
+
+        Ok(room.post_message(user_id, display_name, content))
     }
 
     pub async fn drop_user_session_handle(&self, handle: UserSessionHandle) -> anyhow::Result<()> {
-        let room = self
-            .chat_rooms
-            .get(handle.room())
-            .ok_or_else(|| anyhow::anyhow!("room '{}' not found", handle.room()))?;
+        let room = self.chat_room(handle.room())?;
 
-        let mut room = room.lock().await;
+        let mut room = lock_awake(&room).await;
 
         room.leave(handle);
 
         Ok(())
     }
 
-    pub async fn add_room_history(&self, handle: &UserSessionHandle, content: String)-> anyhow::Result<()> {
-        let room = self
-            .chat_rooms
-            .get(handle.room())
-            .ok_or_else(|| anyhow::anyhow!("room '{}' not found", handle.room()))?;
+    /// Record a message in the history of the room and broadcast it to the participants
+    /// Both happen while holding the room lock, so the history keeps the order the messages were broadcast in
+    /// The nonce of the sending session is broadcast along with the message, it is not kept in the history.
+    pub async fn send_message(
+        &self,
+        handle: &UserSessionHandle,
+        content: String,
+        content_type: ContentType,
+        nonce: Option<String>,
+    ) -> anyhow::Result<()> {
+        let room = self.chat_room(handle.room())?;
 
-        let mut room = room.lock().await;
+        let mut room = lock_awake(&room).await;
 
-        room.add_message_to_history(handle.user_id().to_string(), content);
+        let (id, timestamp) = room.add_message_to_history(
+            handle.user_id().to_string(),
+            content.clone(),
+            content_type,
+        );
+        // there may be no one left to receive the message, which is fine
+        let _ = handle.send_message(id, timestamp, content, content_type, nonce);
 
         Ok(())
     }
 
     /// Get specific room (immutable borrow)
-    pub async fn get_room_history(&self, handle: &UserSessionHandle) -> anyhow::Result<Vec<(String, String)>> {
-        let room = self
-            .chat_rooms
-            .get(handle.room())
-            .ok_or_else(|| anyhow::anyhow!("room '{}' not found", handle.room()))?;
+    pub async fn get_room_history(
+        &self,
+        handle: &UserSessionHandle,
+    ) -> anyhow::Result<Vec<HistoricalMessage>> {
+        self.history_of(handle.room()).await
+    }
 
-        let room = room.lock().await;
+    /// The history of the room, e.g. for a participant who has fallen behind its events
+    pub async fn history_of(&self, room_name: &str) -> anyhow::Result<Vec<HistoricalMessage>> {
+        let room = self.chat_room(room_name)?;
+
+        let room = lock_awake(&room).await;
+
+        Ok(room.get_history())
+    }
+
+    /// The message with the given id in the history of the room, e.g. to report it
+    pub async fn find_message(
+        &self,
+        room_name: &str,
+        message_id: &str,
+    ) -> anyhow::Result<Option<HistoricalMessage>> {
+        let room = self.chat_room(room_name)?;
+
+        let room = lock_awake(&room).await;
+
+        Ok(room.find_message(message_id))
+    }
+
+    /// Remove the entries about the user from the history of every room
+    /// Returns the rooms which had any alongside the ids of the removed entries, in the order the rooms were created
+    pub async fn purge_user(&self, user_id: &str) -> Vec<(String, Vec<String>)> {
+        let mut purged = Vec::new();
+
+        for (room_name, room) in self.chat_rooms() {
+            let purged_ids = room.lock().await.purge_user(user_id).await;
+            if !purged_ids.is_empty() {
+                purged.push((room_name, purged_ids));
+            }
+        }
+
+        purged
+    }
+
+    /// Record that a participant has fallen behind the events of the room and missed some of them
+    /// Returns the lag policy of the room, which tells what to do with the participant
+    pub async fn record_lag(
+        &self,
+        room_name: &str,
+        missed_events: u64,
+    ) -> anyhow::Result<LagPolicy> {
+        let room = self.chat_room(room_name)?;
+
+        let mut room = room.lock().await;
+
+        Ok(room.record_lag(missed_events))
+    }
+
+    /// How the broadcast channels have kept up, in the order the rooms were created
+    pub async fn broadcast_metrics(&self) -> Vec<RoomBroadcastMetrics> {
+        let mut metrics = Vec::new();
+
+        for (_, room) in self.chat_rooms() {
+            metrics.push(room.lock().await.broadcast_metrics());
+        }
+
+        metrics
+    }
+}
+
+/// Lock the room to use it, waking it up first if it is hibernating, see [ChatRoom::wake_up]
+async fn lock_awake(room: &Mutex<ChatRoom>) -> MutexGuard<'_, ChatRoom> {
+    let mut room = room.lock().await;
+    room.wake_up().await;
+    room
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use comms::{
+        event::{Event, RoomParticipationStatus},
+        transport::server::EncodedEvent,
+    };
+    use tokio::{sync::broadcast, task::JoinSet};
+
+    use super::*;
+    use crate::room_manager::RoomManagerBuilder;
+
+    const ROOM: &str = "test";
+    const TASK_COUNT: usize = 40;
+
+    fn room_manager() -> Arc<RoomManager> {
+        Arc::new(
+            RoomManagerBuilder::new()
+                .create_room(ChatRoomMetadata {
+                    name: String::from(ROOM),
+                    description: String::new(),
+                    category: None,
+                    capacity: None,
+                    welcome_message: None,
+                    owners: Vec::new(),
+                    groups: Vec::new(),
+                    record_participation: false,
+                    encrypted: false,
+                    requires_approval: false,
+                    broadcast_capacity: None,
+                    lag_policy: LagPolicy::Resync,
+                })
+                .build(),
+        )
+    }
+
+    fn session(session_id: &str, user_id: &str) -> SessionAndUserId {
+        SessionAndUserId {
+            session_id: String::from(session_id),
+            user_id: String::from(user_id),
+        }
+    }
+
+    async fn join(
+        room_manager: &RoomManager,
+        session_and_user_id: &SessionAndUserId,
+    ) -> (
+        broadcast::Receiver<EncodedEvent>,
+        UserSessionHandle,
+        Vec<String>,
+    ) {
+        match room_manager
+            .join_room(ROOM, session_and_user_id, None)
+            .await
+            .unwrap()
+        {
+            RoomJoinOutcome::Joined((broadcast_rx, handle, user_ids, _, _)) => {
+                (broadcast_rx, handle, user_ids)
+            }
+            RoomJoinOutcome::Full { .. } => panic!("a room without a capacity is never full"),
+        }
+    }
+
+    /// Collect the participation events of the other users of the room as (user id, status) pairs
+    fn participations(
+        broadcast_rx: &mut broadcast::Receiver<EncodedEvent>,
+    ) -> Vec<(String, RoomParticipationStatus)> {
+        let mut participations = Vec::new();
+
+        loop {
+            match broadcast_rx.try_recv().map(|event| event.decode().unwrap()) {
+                Ok(Event::RoomParticipation(event)) if event.user_id != "observer" => {
+                    participations.push((event.user_id, event.status))
+                }
+                Ok(_) => {}
+                Err(broadcast::error::TryRecvError::Empty) => break,
+                Err(err) => panic!("participation events were lost: {err:?}"),
+            }
+        }
+
+        participations
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_joins_and_leaves_broadcast_every_participation() {
+        let room_manager = room_manager();
+        let (mut observer_rx, _observer_handle, _) =
+            join(&room_manager, &session("observer", "observer")).await;
+
+        let mut join_set = JoinSet::new();
+        for idx in 0..TASK_COUNT {
+            let room_manager = Arc::clone(&room_manager);
+            join_set.spawn(async move {
+                let (_, handle, _) = join(
+                    &room_manager,
+                    &session(&format!("s{idx}"), &format!("u{idx}")),
+                )
+                .await;
+                tokio::task::yield_now().await;
+                room_manager.drop_user_session_handle(handle).await.unwrap();
+            });
+        }
+        while let Some(result) = join_set.join_next().await {
+            result.unwrap();
+        }
+
+        let participations = participations(&mut observer_rx);
+        assert_eq!(participations.len(), TASK_COUNT * 2);
+
+        // every user joins exactly once and leaves exactly once, in that order
+        for idx in 0..TASK_COUNT {
+            let user_id = format!("u{idx}");
+            let statuses = participations
+                .iter()
+                .filter(|(participant, _)| *participant == user_id)
+                .map(|(_, status)| status.clone())
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                statuses,
+                vec![
+                    RoomParticipationStatus::Joined,
+                    RoomParticipationStatus::Left
+                ]
+            );
+        }
+
+        let (_, _, user_ids) = join(&room_manager, &session("late", "late")).await;
+        assert_eq!(
+            user_ids.into_iter().collect::<HashSet<_>>(),
+            HashSet::from([String::from("observer"), String::from("late")])
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_sessions_of_the_same_user_are_registered_once() {
+        let room_manager = room_manager();
+        let (mut observer_rx, _observer_handle, _) =
+            join(&room_manager, &session("observer", "observer")).await;
+
+        let mut join_set = JoinSet::new();
+        for idx in 0..TASK_COUNT {
+            let room_manager = Arc::clone(&room_manager);
+            join_set.spawn(async move {
+                let (_, handle, user_ids) =
+                    join(&room_manager, &session(&format!("s{idx}"), "user")).await;
+
+                // the user is never listed twice, no matter how many of their sessions joined
+                let unique_user_ids = user_ids.iter().collect::<HashSet<_>>();
+                assert_eq!(unique_user_ids.len(), user_ids.len());
+                assert_eq!(user_ids.len(), 2);
+
+                handle
+            });
+        }
+        let mut handles = Vec::new();
+        while let Some(result) = join_set.join_next().await {
+            handles.push(result.unwrap());
+        }
+
+        assert_eq!(
+            participations(&mut observer_rx),
+            vec![(String::from("user"), RoomParticipationStatus::Joined)]
+        );
+
+        let mut join_set = JoinSet::new();
+        for handle in handles {
+            let room_manager = Arc::clone(&room_manager);
+            join_set.spawn(async move {
+                room_manager.drop_user_session_handle(handle).await.unwrap();
+            });
+        }
+        while let Some(result) = join_set.join_next().await {
+            result.unwrap();
+        }
+
+        assert_eq!(
+            participations(&mut observer_rx),
+            vec![(String::from("user"), RoomParticipationStatus::Left)]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_messages_are_kept_in_broadcast_order() {
+        const MESSAGE_COUNT: usize = 5;
+
+        let room_manager = room_manager();
+        let (mut observer_rx, observer_handle, _) =
+            join(&room_manager, &session("observer", "observer")).await;
+
+        let mut join_set = JoinSet::new();
+        for idx in 0..TASK_COUNT / 4 {
+            let room_manager = Arc::clone(&room_manager);
+            join_set.spawn(async move {
+                let (_, handle, _) = join(
+                    &room_manager,
+                    &session(&format!("s{idx}"), &format!("u{idx}")),
+                )
+                .await;
+                for message_idx in 0..MESSAGE_COUNT {
+                    room_manager
+                        .send_message(&handle, format!("{message_idx}"), ContentType::Plain, None)
+                        .await
+                        .unwrap();
+                }
+                room_manager.drop_user_session_handle(handle).await.unwrap();
+            });
+        }
+        while let Some(result) = join_set.join_next().await {
+            result.unwrap();
+        }
+
+        let mut broadcast_messages = Vec::new();
+        while let Ok(event) = observer_rx.try_recv() {
+            if let Event::UserMessage(event) = event.decode().unwrap() {
+                broadcast_messages.push((event.user_id, event.content));
+            }
+        }
+        assert_eq!(broadcast_messages.len(), TASK_COUNT / 4 * MESSAGE_COUNT);
+
+        // the messages of every user arrive in the order they were sent
+        for idx in 0..TASK_COUNT / 4 {
+            let user_id = format!("u{idx}");
+            let contents = broadcast_messages
+                .iter()
+                .filter(|(sender, _)| *sender == user_id)
+                .map(|(_, content)| content.clone())
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                contents,
+                (0..MESSAGE_COUNT)
+                    .map(|idx| idx.to_string())
+                    .collect::<Vec<_>>()
+            );
+        }
+
+        // the history holds the latest messages in the order they were broadcast
+        let history = room_manager
+            .get_room_history(&observer_handle)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|message| (message.user_id, message.content))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            history,
+            broadcast_messages[broadcast_messages.len() - history.len()..]
+        );
+        assert_eq!(history.len(), 10);
+    }
+
+    #[tokio::test]
+    async fn lagging_receivers_are_recorded_with_the_policy_of_the_room() {
+        const CAPACITY: usize = 4;
+
+        let room_manager = RoomManagerBuilder::new()
+            .create_room(ChatRoomMetadata {
+                name: String::from(ROOM),
+                description: String::new(),
+                category: None,
+                capacity: None,
+                welcome_message: None,
+                owners: Vec::new(),
+                groups: Vec::new(),
+                record_participation: false,
+                encrypted: false,
+                requires_approval: false,
+                broadcast_capacity: Some(CAPACITY),
+                lag_policy: LagPolicy::Disconnect,
+            })
+            .build();
+        let (mut slow_rx, handle, _) = join(&room_manager, &session("slow", "slow")).await;
+
+        for message_idx in 0..CAPACITY + 3 {
+            room_manager
+                .send_message(&handle, format!("{message_idx}"), ContentType::Plain, None)
+                .await
+                .unwrap();
+        }
+
+        // the join of the slow receiver and the first three messages have been dropped
+        let Err(broadcast::error::RecvError::Lagged(missed_events)) = slow_rx.recv().await else {
+            panic!("the receiver should have lagged behind");
+        };
+        assert_eq!(missed_events, 4);
+        assert_eq!(
+            room_manager.record_lag(ROOM, missed_events).await.unwrap(),
+            LagPolicy::Disconnect
+        );
+
+        let metrics = room_manager.broadcast_metrics().await;
+        assert_eq!(
+            metrics,
+            vec![RoomBroadcastMetrics {
+                room: String::from(ROOM),
+                capacity: CAPACITY,
+                lag_policy: LagPolicy::Disconnect,
+                lag_count: 1,
+                dropped_events: 4,
+                disconnected_sessions: 1,
+                leaked_handles: 0,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn handles_dropped_without_leaving_are_counted_as_leaked() {
+        let room_manager = room_manager();
+        let (_alice_rx, alice, _) = join(&room_manager, &session("alice", "alice")).await;
+        let (_bob_rx, bob, _) = join(&room_manager, &session("bob", "bob")).await;
+        let (_carol_rx, carol, _) = join(&room_manager, &session("carol", "carol")).await;
+
+        room_manager.drop_user_session_handle(alice).await.unwrap();
+        carol.abandon();
+        drop(bob);
+
+        let metrics = room_manager.broadcast_metrics().await;
+        assert_eq!(metrics[0].leaked_handles, 1);
+    }
+
+    #[tokio::test]
+    async fn purging_a_user_removes_their_entries_from_the_history() {
+        let room_manager = room_manager();
+        let (_alice_rx, alice, _) = join(&room_manager, &session("alice", "alice")).await;
+        let (_bob_rx, bob, _) = join(&room_manager, &session("bob", "bob")).await;
+
+        room_manager
+            .send_message(&alice, String::from("a"), ContentType::Plain, None)
+            .await
+            .unwrap();
+        room_manager
+            .send_message(&bob, String::from("b"), ContentType::Plain, None)
+            .await
+            .unwrap();
+        room_manager
+            .send_message(&alice, String::from("c"), ContentType::Plain, None)
+            .await
+            .unwrap();
+
+        let purged = room_manager.purge_user("alice").await;
+        assert_eq!(purged.len(), 1);
+        assert_eq!(purged[0].0, ROOM);
+        assert_eq!(purged[0].1.len(), 2);
+
+        let history = room_manager.history_of(ROOM).await.unwrap();
+        assert_eq!(
+            history
+                .iter()
+                .map(|message| message.content.as_str())
+                .collect::<Vec<_>>(),
+            vec!["b"]
+        );
+        assert!(room_manager.purge_user("alice").await.is_empty());
+    }
+
+    fn room_logs_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("chat-room-manager-{}", nanoid::nanoid!()))
+    }
+
+    #[tokio::test]
+    async fn idle_rooms_hibernate_and_wake_up_with_their_history() {
+        let room_manager = room_manager();
+        room_manager
+            .open_room_logs(room_logs_dir(), None)
+            .await
+            .unwrap();
+        let (alice_rx, alice, _) = join(&room_manager, &session("alice", "alice")).await;
+        room_manager
+            .send_message(&alice, String::from("hello"), ContentType::Plain, None)
+            .await
+            .unwrap();
+
+        // alice is still in the room
+        assert_eq!(room_manager.hibernate_idle_rooms(Duration::ZERO).await, 0);
+
+        room_manager.drop_user_session_handle(alice).await.unwrap();
+        drop(alice_rx);
+        assert_eq!(room_manager.hibernate_idle_rooms(Duration::ZERO).await, 1);
+        assert_eq!(room_manager.hibernate_idle_rooms(Duration::ZERO).await, 0);
+
+        let (mut bob_rx, bob, _) = join(&room_manager, &session("bob", "bob")).await;
+        let history = room_manager.history_of(ROOM).await.unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].content, "hello");
+
+        room_manager
+            .send_message(&bob, String::from("back"), ContentType::Plain, None)
+            .await
+            .unwrap();
+        // the channel of the room has been recreated, bob gets his own join and message over it
+        assert!(matches!(
+            bob_rx.try_recv().unwrap().decode().unwrap(),
+            Event::RoomParticipation(event) if event.user_id == "bob"
+        ));
+        assert!(matches!(
+            bob_rx.try_recv().unwrap().decode().unwrap(),
+            Event::UserMessage(event) if event.content == "back"
+        ));
+
+        room_manager.drop_user_session_handle(bob).await.unwrap();
+        room_manager.close_room_logs().await;
+    }
+
+    #[tokio::test]
+    async fn purging_a_user_rewrites_the_log_of_a_hibernating_room_without_waking_it_up() {
+        let room_manager = room_manager();
+        room_manager
+            .open_room_logs(room_logs_dir(), None)
+            .await
+            .unwrap();
+        let (alice_rx, alice, _) = join(&room_manager, &session("alice", "alice")).await;
+        room_manager
+            .send_message(&alice, String::from("a"), ContentType::Plain, None)
+            .await
+            .unwrap();
+        room_manager.drop_user_session_handle(alice).await.unwrap();
+        drop(alice_rx);
+        assert_eq!(room_manager.hibernate_idle_rooms(Duration::ZERO).await, 1);
+
+        let purged = room_manager.purge_user("alice").await;
+        assert_eq!(purged.len(), 1);
+        assert_eq!(purged[0].1.len(), 1);
+        // a room woken up by the purge would be put to hibernation again
+        assert_eq!(room_manager.hibernate_idle_rooms(Duration::ZERO).await, 0);
+
+        let history = room_manager.history_of(ROOM).await.unwrap();
+        assert!(history.iter().all(|message| message.user_id != "alice"));
+        room_manager.close_room_logs().await;
+    }
+
+    #[tokio::test]
+    async fn rooms_in_use_or_kept_in_memory_do_not_hibernate() {
+        let room_manager = room_manager();
+        assert_eq!(room_manager.hibernate_idle_rooms(Duration::ZERO).await, 0);
+
+        room_manager
+            .open_room_logs(room_logs_dir(), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            room_manager
+                .hibernate_idle_rooms(Duration::from_secs(60))
+                .await,
+            0
+        );
+
+        let mirror_rx = room_manager.subscribe(ROOM).await.unwrap();
+        assert_eq!(room_manager.hibernate_idle_rooms(Duration::ZERO).await, 0);
+
+        drop(mirror_rx);
+        assert_eq!(room_manager.hibernate_idle_rooms(Duration::ZERO).await, 1);
+
+        room_manager.close_room_logs().await;
+    }
+
+    /// A room manager whose room takes a single user, alice has taken the slot
+    async fn full_room_manager() -> (RoomManager, UserSessionHandle) {
+        let room_manager = RoomManagerBuilder::new()
+            .create_room(ChatRoomMetadata {
+                name: String::from(ROOM),
+                description: String::new(),
+                category: None,
+                capacity: Some(1),
+                welcome_message: None,
+                owners: Vec::new(),
+                groups: Vec::new(),
+                record_participation: false,
+                encrypted: false,
+                requires_approval: false,
+                broadcast_capacity: None,
+                lag_policy: LagPolicy::Resync,
+            })
+            .build();
+        let (_, alice, _) = join(&room_manager, &session("alice", "alice")).await;
+
+        (room_manager, alice)
+    }
+
+    /// Joins the full room, waiting for a slot, returns the position in the queue
+    async fn wait_for_slot(
+        room_manager: &RoomManager,
+        session_and_user_id: &SessionAndUserId,
+    ) -> (usize, mpsc::UnboundedReceiver<WaitingRoomUpdate>) {
+        let (waiting_tx, waiting_rx) = mpsc::unbounded_channel();
+        match room_manager
+            .join_room(ROOM, session_and_user_id, Some(waiting_tx))
+            .await
+            .unwrap()
+        {
+            RoomJoinOutcome::Full {
+                queue_position: Some(queue_position),
+                ..
+            } => (queue_position, waiting_rx),
+            _ => panic!("the room should be full"),
+        }
+    }
+
+    /// Takes the handle of the session joined on its behalf, skipping the position updates before it
+    fn joined(waiting_rx: &mut mpsc::UnboundedReceiver<WaitingRoomUpdate>) -> UserSessionHandle {
+        loop {
+            match waiting_rx.try_recv() {
+                Ok(WaitingRoomUpdate::Joined {
+                    join_result: (_, handle, _, _, _),
+                    ..
+                }) => return handle,
+                Ok(WaitingRoomUpdate::Position { .. }) => {}
+                Err(err) => panic!("the session has not been joined: {err:?}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn waiting_sessions_are_admitted_in_arrival_order() {
+        let (room_manager, alice) = full_room_manager().await;
+        let (bob_position, mut bob_rx) = wait_for_slot(&room_manager, &session("bob", "bob")).await;
+        let (carol_position, mut carol_rx) =
+            wait_for_slot(&room_manager, &session("carol", "carol")).await;
+        assert_eq!((bob_position, carol_position), (1, 2));
+
+        room_manager.drop_user_session_handle(alice).await.unwrap();
+        let bob = joined(&mut bob_rx);
+        assert_eq!(bob.user_id(), "bob");
+        // carol moves up, but the room is full again
+        assert!(matches!(
+            carol_rx.try_recv(),
+            Ok(WaitingRoomUpdate::Position { position: 1, .. })
+        ));
+        assert!(carol_rx.try_recv().is_err());
+
+        room_manager.drop_user_session_handle(bob).await.unwrap();
+        assert_eq!(joined(&mut carol_rx).user_id(), "carol");
+    }
+
+    #[tokio::test]
+    async fn disconnected_sessions_leave_the_waiting_queue() {
+        let (room_manager, alice) = full_room_manager().await;
+        let (_, bob_rx) = wait_for_slot(&room_manager, &session("bob", "bob")).await;
+        let (_, mut carol_rx) = wait_for_slot(&room_manager, &session("carol", "carol")).await;
+        let (dave_position, mut dave_rx) =
+            wait_for_slot(&room_manager, &session("dave", "dave")).await;
+        assert_eq!(dave_position, 3);
+
+        // bob's connection is gone without a word, carol stops waiting as her session ends
+        drop(bob_rx);
+        room_manager
+            .leave_waiting_queue(ROOM, "carol")
+            .await
+            .unwrap();
+        assert!(matches!(
+            dave_rx.try_recv(),
+            Ok(WaitingRoomUpdate::Position { position: 1, .. })
+        ));
+
+        // the slot goes to dave, rather than to any of the sessions which are gone
+        room_manager.drop_user_session_handle(alice).await.unwrap();
+        assert_eq!(joined(&mut dave_rx).user_id(), "dave");
+        assert!(!matches!(
+            carol_rx.try_recv(),
+            Ok(WaitingRoomUpdate::Joined { .. })
+        ));
+        assert_eq!(
+            room_manager.get_room_info(ROOM).await.unwrap().member_count,
+            1
+        );
+    }
+
+    /// Joins an encrypted room, returns whether the session is the one to create the key of the room
+    async fn join_encrypted(
+        room_manager: &RoomManager,
+        session_and_user_id: &SessionAndUserId,
+    ) -> (UserSessionHandle, bool) {
+        match room_manager
+            .join_room(ROOM, session_and_user_id, None)
+            .await
+            .unwrap()
+        {
+            RoomJoinOutcome::Joined((_, handle, _, _, creates_room_key)) => {
+                (handle, creates_room_key)
+            }
+            RoomJoinOutcome::Full { .. } => panic!("a room without a capacity is never full"),
+        }
+    }
+
+    #[tokio::test]
+    async fn only_a_session_joining_an_encrypted_room_nobody_holds_the_key_of_creates_it() {
+        let room_manager = RoomManagerBuilder::new()
+            .create_room(ChatRoomMetadata {
+                name: String::from(ROOM),
+                description: String::new(),
+                category: None,
+                capacity: None,
+                welcome_message: None,
+                owners: Vec::new(),
+                groups: Vec::new(),
+                record_participation: false,
+                encrypted: true,
+                requires_approval: false,
+                broadcast_capacity: None,
+                lag_policy: LagPolicy::Resync,
+            })
+            .build();
+
+        let (alice, alice_creates) = join_encrypted(&room_manager, &session("a1", "alice")).await;
+        assert!(alice_creates);
+        // another session of the same user asks for the key like anyone else
+        let (_, second_session_creates) =
+            join_encrypted(&room_manager, &session("a2", "alice")).await;
+        assert!(!second_session_creates);
+        let (_, bob_creates) = join_encrypted(&room_manager, &session("b1", "bob")).await;
+        assert!(!bob_creates);
+
+        // bob opens the key alice shares, and keeps it for the room once alice is gone
+        assert!(room_manager
+            .hold_room_key(ROOM, &session("b1", "bob"))
+            .await
+            .unwrap());
+        room_manager.drop_user_session_handle(alice).await.unwrap();
+        let (_, carol_creates) = join_encrypted(&room_manager, &session("c1", "carol")).await;
+        assert!(!carol_creates);
 
-        Ok(room.get_message_history())
+        // sessions which are not in the room can not claim to hold its key
+        assert!(!room_manager
+            .hold_room_key(ROOM, &session("d1", "dave"))
+            .await
+            .unwrap());
     }
 }
@@ -1,118 +1,1489 @@
-use std::{collections::HashMap, string, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
 
 use anyhow::Context;
 use comms::{
-    command::UserCommand,
-    event::{self, Event},
+    command::{
+        ArchiveRoomCommand, BotScope, CreateInviteCodeCommand, GroupMembershipCommand,
+        JoinRequestDecisionCommand, JoinRoomCommand, JoinWithCodeCommand, PurgeUserCommand,
+        ReopenRoomCommand, ReportMessageCommand, SetStatusTextCommand, UpdateRoomMetadataCommand,
+        UserCommand, ENCRYPTED_CONTENT_PREFIX,
+    },
+    event::{self, Event, LagPolicy, SessionDetail, SessionTerminationCause},
+    transport::server::EncodedEvent,
 };
+use nanoid::nanoid;
 use tokio::{
-    sync::mpsc,
+    sync::{
+        broadcast::{self, error::RecvError},
+        mpsc, oneshot,
+    },
     task::{AbortHandle, JoinSet},
 };
 
-use crate::room_manager::{self, RoomManager, SessionAndUserId, UserSessionHandle};
+use crate::{
+    archival::ReopenOutcome,
+    auth::{bot_user_id, Identity},
+    clock::unix_millis_now,
+    context::ServerContext,
+    moderation::FilingOutcome,
+    names::{self, NameKind},
+    reminders::{self, Reminder},
+    room_manager::{
+        JoinApproval, RoomJoinOutcome, RoomJoinResult, SessionAndUserId, UserSessionHandle,
+        WaitingRoomUpdate,
+    },
+};
+
+use super::{
+    interceptors::{self, CommandOrigin, Interception, InterceptorChain},
+    OutgoingEvent, MAX_MESSAGE_LENGTH,
+};
+
+/// The maximum number of characters the description of a room can have
+const MAX_DESCRIPTION_LENGTH: usize = 200;
+/// The maximum number of characters the status text of a user can have
+const MAX_STATUS_TEXT_LENGTH: usize = 100;
+/// How long an invite code can be redeemed for unless its creator says otherwise, and at most
+const DEFAULT_INVITE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const MAX_INVITE_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+/// How many users can redeem a single invite code at most
+const MAX_INVITE_USES: u32 = 100;
+/// The most history entries sent in a single chunk, however many the user asks for
+const MAX_HISTORY_CHUNK_SIZE: usize = 500;
+/// The most rooms checked by a single command, the others are left out of the reply
+const MAX_CHECKED_ROOMS: usize = 50;
 
 pub(super) struct ChatSession {
     session_and_user_id: SessionAndUserId,
-    room_manager: Arc<RoomManager>,
+    /// The capabilities of the session if it belongs to a bot
+    bot_scope: Option<BotScope>,
+    /// Whether the session belongs to a guest, who is restricted to chatting
+    is_guest: bool,
+    context: Arc<ServerContext>,
     joined_rooms: HashMap<String, (UserSessionHandle, AbortHandle)>,
+    waiting_rooms: HashSet<String>,
     join_set: JoinSet<()>,
-    mpsc_tx: mpsc::Sender<Event>,
-    mpsc_rx: mpsc::Receiver<Event>,
+    mpsc_tx: mpsc::Sender<OutgoingEvent>,
+    mpsc_rx: mpsc::Receiver<OutgoingEvent>,
+    waiting_tx: mpsc::UnboundedSender<WaitingRoomUpdate>,
+    waiting_rx: mpsc::UnboundedReceiver<WaitingRoomUpdate>,
+    terminate_rx: Option<oneshot::Receiver<Event>>,
+    /// The interceptors every command passes before it is handled
+    interceptors: InterceptorChain,
 }
 
 impl ChatSession {
-    pub fn new(session_id: &str, user_id: &str, room_manager: Arc<RoomManager>) -> Self {
+    pub fn new(
+        session_detail: SessionDetail,
+        identity: Identity,
+        context: Arc<ServerContext>,
+    ) -> Self {
         let (mpsc_tx, mpsc_rx) = mpsc::channel(100);
+        let (waiting_tx, waiting_rx) = mpsc::unbounded_channel();
         let session_and_user_id = SessionAndUserId {
-            session_id: String::from(session_id),
-            user_id: String::from(user_id),
+            session_id: session_detail.session_id.clone(),
+            user_id: identity.user_id,
         };
 
+        // make the session reachable by server wide services, e.g. for delivering reminders
+        let terminate_rx = context.session_registry.register(
+            &session_and_user_id,
+            session_detail,
+            mpsc_tx.clone(),
+        );
+        context.reminders.wake();
+
         ChatSession {
             session_and_user_id,
-            room_manager,
+            bot_scope: identity.bot_scope,
+            is_guest: identity.is_guest,
+            context,
             joined_rooms: HashMap::new(),
+            waiting_rooms: HashSet::new(),
             join_set: JoinSet::new(),
             mpsc_tx,
             mpsc_rx,
+            waiting_tx,
+            waiting_rx,
+            terminate_rx: Some(terminate_rx),
+            interceptors: interceptors::session_interceptors(MAX_MESSAGE_LENGTH),
         }
     }
 
     /// Handle a user command related to room management such as; join, leave, send message
-    pub async fn handle_user_command(&mut self, cmd: UserCommand) -> anyhow::Result<()> {
+    pub async fn handle_user_command(&mut self, mut cmd: UserCommand) -> anyhow::Result<()> {
+        let origin = CommandOrigin {
+            session_and_user_id: &self.session_and_user_id,
+            bot_scope: self.bot_scope.as_ref(),
+            is_guest: self.is_guest,
+            context: &self.context,
+        };
+        let interception = self.interceptors.intercept(&origin, &mut cmd);
+
+        // only what was sent and when is kept, the content of the command stays private
+        self.context.command_history.record(
+            &self.session_and_user_id.user_id,
+            event::CommandHistoryEntry {
+                command: String::from(cmd.name()),
+                session_id: self.session_and_user_id.session_id.clone(),
+                received_at: unix_millis_now(),
+                rejected: matches!(interception, Interception::Reject(_)),
+            },
+        );
+
+        if let Interception::Reject(event) = interception {
+            self.mpsc_tx.send(event.into()).await?;
+
+            return Ok(());
+        }
+
         match cmd {
             UserCommand::JoinRoom(cmd) => {
-                if self.joined_rooms.contains_key(&cmd.room) {
-                    return Err(anyhow::anyhow!("already joined room '{}'", &cmd.room));
+                self.join_room(cmd).await?;
+            }
+            UserCommand::SendMessage(cmd) => {
+                // the server can not read the messages of encrypted rooms, so it must not be sent any plain text
+                if self.context.room_manager.is_encrypted(&cmd.room)
+                    && !cmd.content.starts_with(ENCRYPTED_CONTENT_PREFIX)
+                {
+                    self.mpsc_tx
+                        .send(
+                            Event::MessageRejected(event::MessageRejectedReplyEvent {
+                                room: cmd.room,
+                                reason: event::MessageRejectionReason::NotEncrypted,
+                                nonce: cmd.nonce,
+                            })
+                            .into(),
+                        )
+                        .await?;
+
+                    return Ok(());
+                }
+
+                // reminder commands are handled by the server and never reach the room
+                if let Some(reminder_command) = reminders::parse_reminder_command(&cmd.content) {
+                    return self
+                        .handle_reminder_command(cmd.room, cmd.nonce, reminder_command)
+                        .await;
                 }
 
-                let (mut broadcast_rx, user_session_handle, user_ids) = self
+                if let Some((user_session_handle, _)) = self.joined_rooms.get(&cmd.room) {
+                    self.context
+                        .room_manager
+                        .send_message(
+                            user_session_handle,
+                            cmd.content,
+                            cmd.content_type,
+                            cmd.nonce,
+                        )
+                        .await?;
+                }
+            }
+            UserCommand::LeaveRoom(cmd) => {
+                // remove the room from joined rooms and drop user session handle for the room
+                if let Some(urp) = self.joined_rooms.remove(&cmd.room) {
+                    self.cleanup_room(urp).await?;
+                } else if self.waiting_rooms.remove(&cmd.room) {
+                    self.context
+                        .room_manager
+                        .leave_waiting_queue(&cmd.room, &self.session_and_user_id.session_id)
+                        .await?;
+                }
+            }
+            UserCommand::SetWelcomeMessage(cmd) => {
+                let is_owner = self
+                    .context
                     .room_manager
-                    .join_room(&cmd.room, &self.session_and_user_id)
+                    .set_welcome_message(
+                        &cmd.room,
+                        &self.session_and_user_id.user_id,
+                        cmd.message.clone(),
+                    )
                     .await?;
 
-                // spawn a task to forward broadcast messages to the users' mpsc channel
-                // hence the user can receive messages from different rooms via single channel
-                let abort_handle = self.join_set.spawn({
-                    let mpsc_tx = self.mpsc_tx.clone();
+                let event = if is_owner {
+                    Event::WelcomeMessageUpdated(event::WelcomeMessageUpdatedReplyEvent {
+                        room: cmd.room,
+                        message: cmd.message,
+                    })
+                } else {
+                    Event::CommandRejected(event::CommandRejectedReplyEvent {
+                        command: String::from("set_welcome_message"),
+                        reason: format!(
+                            "only the owners of '{}' can change its welcome message",
+                            cmd.room
+                        ),
+                    })
+                };
 
-                    // start with sending the user joined room event as a reply to the user
-                    mpsc_tx
-                        .send(Event::UserJoinedRoom(event::UserJoinedRoomReplyEvent {
-                            room: cmd.room.clone(),
-                            users: user_ids,
-                        }))
+                self.mpsc_tx.send(event.into()).await?;
+            }
+            UserCommand::AddGroupMember(cmd) => {
+                self.handle_group_membership_command(cmd, true).await?;
+            }
+            UserCommand::RemoveGroupMember(cmd) => {
+                self.handle_group_membership_command(cmd, false).await?;
+            }
+            UserCommand::ApproveJoinRequest(cmd) => {
+                self.handle_join_request_decision(cmd, true).await?;
+            }
+            UserCommand::DenyJoinRequest(cmd) => {
+                self.handle_join_request_decision(cmd, false).await?;
+            }
+            UserCommand::IssueBotToken(cmd) => {
+                // a new token for an existing bot replaces its previous one, only other bots are taken
+                let bot_names = self.context.authenticator.bot_tokens.bot_names().await;
+                let validation = names::validate_new(
+                    &cmd.bot_name,
+                    NameKind::BotName,
+                    bot_names.iter().map(String::as_str),
+                );
+
+                let event = if let Err(reason) = validation {
+                    Event::NameRejected(event::NameRejectedReplyEvent {
+                        command: String::from("issue_bot_token"),
+                        name: cmd.bot_name,
+                        reason,
+                    })
+                } else {
+                    let issued_token = self
+                        .context
+                        .authenticator
+                        .bot_tokens
+                        .issue(&cmd.bot_name, cmd.scope)
+                        .await?;
+
+                    Event::BotTokenIssued(event::BotTokenIssuedReplyEvent {
+                        user_id: bot_user_id(&cmd.bot_name),
+                        bot_name: cmd.bot_name,
+                        token: issued_token.token,
+                        expires_at: issued_token.expires_at,
+                    })
+                };
+
+                self.mpsc_tx.send(event.into()).await?;
+            }
+            UserCommand::RevokeBotToken(cmd) => {
+                let revoked = self
+                    .context
+                    .authenticator
+                    .bot_tokens
+                    .revoke(&cmd.bot_name)
+                    .await?;
+
+                self.mpsc_tx
+                    .send(
+                        Event::BotTokenRevoked(event::BotTokenRevokedReplyEvent {
+                            bot_name: cmd.bot_name,
+                            revoked,
+                        })
+                        .into(),
+                    )
+                    .await?;
+            }
+            UserCommand::ListSessions(_) => {
+                let sessions = self
+                    .context
+                    .session_registry
+                    .sessions_of(&self.session_and_user_id.user_id);
+
+                self.mpsc_tx
+                    .send(Event::SessionList(event::SessionListReplyEvent { sessions }).into())
+                    .await?;
+            }
+            UserCommand::DisconnectSession(cmd) => {
+                // users can only reach their own sessions, since the registry is looked up by their user id
+                let is_terminated = self.context.session_registry.terminate(
+                    &self.session_and_user_id.user_id,
+                    &cmd.session_id,
+                    Event::SessionTerminated(event::SessionTerminatedReplyEvent {
+                        reason: format!(
+                            "disconnected from another session ({})",
+                            self.session_and_user_id.session_id
+                        ),
+                        cause: Some(SessionTerminationCause::DisconnectedByUser),
+                    }),
+                );
+
+                if !is_terminated {
+                    self.mpsc_tx
+                        .send(
+                            Event::CommandRejected(event::CommandRejectedReplyEvent {
+                                command: String::from("disconnect_session"),
+                                reason: format!(
+                                    "you have no session with the id '{}'",
+                                    cmd.session_id
+                                ),
+                            })
+                            .into(),
+                        )
+                        .await?;
+                }
+            }
+            UserCommand::MarkRoomRead(cmd) => {
+                if !self
+                    .context
+                    .can_access_room(&cmd.room, &self.session_and_user_id.user_id)
+                    .await
+                {
+                    self.mpsc_tx
+                        .send(
+                            Event::CommandRejected(event::CommandRejectedReplyEvent {
+                                command: String::from("mark_room_read"),
+                                reason: format!("there is no room named '{}'", cmd.room),
+                            })
+                            .into(),
+                        )
+                        .await?;
+
+                    return Ok(());
+                }
+
+                let last_read_at = self
+                    .context
+                    .read_markers
+                    .mark_read(
+                        &self.session_and_user_id.user_id,
+                        &cmd.room,
+                        unix_millis_now(),
+                    )
+                    .await?;
+
+                // every session of the user is notified, including this one
+                self.context.session_registry.send_to_user(
+                    &self.session_and_user_id.user_id,
+                    &Event::ReadStateSynced(event::ReadStateSyncedReplyEvent {
+                        room: cmd.room,
+                        last_read_at,
+                    }),
+                );
+            }
+            UserCommand::SetPresence(cmd) => {
+                if let Some(status) = self
+                    .context
+                    .session_registry
+                    .set_presence(&self.session_and_user_id, cmd.status)
+                {
+                    self.context
+                        .room_manager
+                        .broadcast_presence(&self.session_and_user_id.user_id, status)
+                        .await;
+                }
+            }
+            UserCommand::SetStatusText(cmd) => {
+                self.handle_set_status_text(cmd).await?;
+            }
+            UserCommand::GetUserInfo(cmd) => {
+                let own_rooms = self
+                    .context
+                    .room_manager
+                    .rooms_of_user(&self.session_and_user_id.user_id)
+                    .await;
+                // only the rooms the user is in themselves are revealed
+                let shared_rooms = self
+                    .context
+                    .room_manager
+                    .rooms_of_user(&cmd.user_id)
+                    .await
+                    .into_iter()
+                    .filter(|room| own_rooms.contains(room))
+                    .collect();
+
+                self.mpsc_tx
+                    .send(
+                        Event::UserInfo(event::UserInfoReplyEvent {
+                            presence: self.context.session_registry.presence_of_user(&cmd.user_id),
+                            status_text: self.context.session_registry.status_text_of(&cmd.user_id),
+                            user_id: cmd.user_id,
+                            shared_rooms,
+                        })
+                        .into(),
+                    )
+                    .await?;
+            }
+            UserCommand::SearchUsers(cmd) => {
+                let query = cmd.query.trim();
+                if query.is_empty() {
+                    self.mpsc_tx
+                        .send(
+                            Event::CommandRejected(event::CommandRejectedReplyEvent {
+                                command: String::from("search_users"),
+                                reason: String::from("the search query is empty"),
+                            })
+                            .into(),
+                        )
                         .await?;
 
-                    async move {
-                        while let Ok(event) = broadcast_rx.recv().await {
-                            let _ = mpsc_tx.send(event).await;
+                    return Ok(());
+                }
+
+                let users = self
+                    .context
+                    .user_directory
+                    .search(query)
+                    .await
+                    .into_iter()
+                    .map(|user_id| event::UserSearchResult {
+                        presence: self.context.session_registry.presence_of_user(&user_id),
+                        user_id,
+                    })
+                    .collect();
+
+                self.mpsc_tx
+                    .send(
+                        Event::UserSearchResults(event::UserSearchResultsReplyEvent {
+                            query: String::from(query),
+                            users,
+                        })
+                        .into(),
+                    )
+                    .await?;
+            }
+            UserCommand::GetRoomInfo(cmd) => {
+                let event = if self
+                    .context
+                    .can_access_room(&cmd.room, &self.session_and_user_id.user_id)
+                    .await
+                {
+                    Event::RoomInfo(self.context.room_manager.get_room_info(&cmd.room).await?)
+                } else {
+                    Event::CommandRejected(event::CommandRejectedReplyEvent {
+                        command: String::from("get_room_info"),
+                        reason: format!("there is no room named '{}'", cmd.room),
+                    })
+                };
+
+                self.mpsc_tx.send(event.into()).await?;
+            }
+            UserCommand::CheckRooms(cmd) => {
+                let mut existing = Vec::new();
+                let mut missing = Vec::new();
+                for room in cmd.rooms.into_iter().take(MAX_CHECKED_ROOMS) {
+                    // rooms the user can not access are not told apart from the ones which do not exist
+                    if self
+                        .context
+                        .can_access_room(&room, &self.session_and_user_id.user_id)
+                        .await
+                    {
+                        existing.push(room);
+                    } else {
+                        missing.push(room);
+                    }
+                }
+
+                self.mpsc_tx
+                    .send(
+                        Event::RoomsChecked(event::RoomsCheckedReplyEvent { existing, missing })
+                            .into(),
+                    )
+                    .await?;
+            }
+            UserCommand::ListRooms(_) => {
+                let rooms = self
+                    .context
+                    .accessible_rooms(&self.session_and_user_id.user_id)
+                    .await;
+
+                self.mpsc_tx
+                    .send(Event::RoomList(event::RoomListReplyEvent { rooms }).into())
+                    .await?;
+            }
+            UserCommand::GetRoomStats(cmd) => {
+                let rooms = match cmd.room {
+                    Some(room) => {
+                        if !self
+                            .context
+                            .can_access_room(&room, &self.session_and_user_id.user_id)
+                            .await
+                        {
+                            self.mpsc_tx
+                                .send(
+                                    Event::CommandRejected(event::CommandRejectedReplyEvent {
+                                        command: String::from("get_room_stats"),
+                                        reason: format!("there is no room named '{}'", room),
+                                    })
+                                    .into(),
+                                )
+                                .await?;
+
+                            return Ok(());
                         }
+
+                        vec![self.context.room_manager.room_stats(&room).await?]
                     }
-                });
+                    // only admins get here, the interceptors reject everyone else
+                    None => self.context.room_manager.all_room_stats().await,
+                };
 
-                // store references to the user session handle and abort handle
-                // this is used to send messages to the room and to cancel the task when user leaves the room
-                self.joined_rooms
-                    .insert(cmd.room.clone(), (user_session_handle, abort_handle));
+                self.mpsc_tx
+                    .send(Event::RoomStats(event::RoomStatsReplyEvent { rooms }).into())
+                    .await?;
             }
-            UserCommand::SendMessage(cmd) => {
+            UserCommand::ArchiveRoom(cmd) => {
+                self.handle_archive_room(cmd).await?;
+            }
+            UserCommand::ReopenRoom(cmd) => {
+                self.handle_reopen_room(cmd).await?;
+            }
+            UserCommand::UpdateRoomMetadata(cmd) => {
+                self.handle_update_room_metadata(cmd).await?;
+            }
+            UserCommand::PurgeUser(cmd) => {
+                self.handle_purge_user(cmd).await?;
+            }
+            UserCommand::GetCommandHistory(cmd) => {
+                // only admins get here, the interceptors reject everyone else
+                let entries = self.context.command_history.entries_of(&cmd.user_id);
+
+                self.mpsc_tx
+                    .send(
+                        Event::CommandHistory(event::CommandHistoryReplyEvent {
+                            user_id: cmd.user_id,
+                            entries,
+                        })
+                        .into(),
+                    )
+                    .await?;
+            }
+            UserCommand::CreateInviteCode(cmd) => {
+                self.handle_create_invite_code(cmd).await?;
+            }
+            UserCommand::JoinWithCode(cmd) => {
+                self.handle_join_with_code(cmd).await?;
+            }
+            UserCommand::RequestRoomKey(cmd) => {
+                if !self.context.room_manager.is_encrypted(&cmd.room) {
+                    self.mpsc_tx
+                        .send(
+                            Event::CommandRejected(event::CommandRejectedReplyEvent {
+                                command: String::from("request_room_key"),
+                                reason: format!("'{}' is not an encrypted room", cmd.room),
+                            })
+                            .into(),
+                        )
+                        .await?;
+
+                    return Ok(());
+                }
+
                 if let Some((user_session_handle, _)) = self.joined_rooms.get(&cmd.room) {
-                    self.room_manager.add_room_history(
-                        user_session_handle, 
-                        cmd.content.clone()
-                    ).await?;
-                    let _ = user_session_handle.send_message(cmd.content);
+                    user_session_handle.request_room_key(cmd.public_key)?;
                 }
             }
-            UserCommand::LeaveRoom(cmd) => {
-                // remove the room from joined rooms and drop user session handle for the room
-                if let Some(urp) = self.joined_rooms.remove(&cmd.room) {
-                    self.cleanup_room(urp).await?;
+            UserCommand::HoldRoomKey(cmd) if self.joined_rooms.contains_key(&cmd.room) => {
+                self.context
+                    .room_manager
+                    .hold_room_key(&cmd.room, &self.session_and_user_id)
+                    .await?;
+            }
+            // only members of the room can hand out its key, the key itself stays sealed for the server
+            UserCommand::ShareRoomKey(cmd) if self.joined_rooms.contains_key(&cmd.room) => {
+                self.context.session_registry.send_to_session(
+                    &cmd.user_id,
+                    &cmd.session_id,
+                    &Event::RoomKeyShared(event::RoomKeySharedReplyEvent {
+                        room: cmd.room,
+                        user_id: self.session_and_user_id.user_id.clone(),
+                        public_key: cmd.public_key,
+                        sealed_key: cmd.sealed_key,
+                    }),
+                );
+            }
+            UserCommand::ReportMessage(cmd) => {
+                let report = match self.file_report(cmd).await? {
+                    Ok(report) => report,
+                    Err(reason) => {
+                        self.mpsc_tx
+                            .send(
+                                Event::CommandRejected(event::CommandRejectedReplyEvent {
+                                    command: String::from("report_message"),
+                                    reason,
+                                })
+                                .into(),
+                            )
+                            .await?;
+
+                        return Ok(());
+                    }
+                };
+
+                self.mpsc_tx
+                    .send(
+                        Event::ReportFiled(event::ReportFiledReplyEvent {
+                            report_id: report.id.clone(),
+                            room: report.room.clone(),
+                        })
+                        .into(),
+                    )
+                    .await?;
+
+                let moderators = self.context.moderators_of(&report.room);
+                let event = Event::MessageReported(event::MessageReportedEvent { report });
+                for moderator in moderators {
+                    self.context
+                        .session_registry
+                        .send_to_user(&moderator, &event);
                 }
             }
+            UserCommand::ListReports(_) => {
+                let user_id = &self.session_and_user_id.user_id;
+                if !self.context.is_any_moderator(user_id) {
+                    self.mpsc_tx
+                        .send(
+                            Event::CommandRejected(event::CommandRejectedReplyEvent {
+                                command: String::from("list_reports"),
+                                reason: String::from("only moderators can list reports"),
+                            })
+                            .into(),
+                        )
+                        .await?;
+
+                    return Ok(());
+                }
+
+                let reports = self
+                    .context
+                    .moderation_queue
+                    .open_reports(|room| self.context.is_moderator(room, user_id))
+                    .await;
+
+                self.mpsc_tx
+                    .send(Event::ReportList(event::ReportListReplyEvent { reports }).into())
+                    .await?;
+            }
+            // only admins get here, the interceptors reject everyone else
+            UserCommand::GetBroadcastMetrics(_) => {
+                let rooms = self.context.room_manager.broadcast_metrics().await;
+
+                self.mpsc_tx
+                    .send(
+                        Event::BroadcastMetrics(event::BroadcastMetricsReplyEvent { rooms }).into(),
+                    )
+                    .await?;
+            }
+            UserCommand::ResolveReport(cmd) => {
+                let room = self.context.moderation_queue.room_of(&cmd.report_id).await;
+
+                // reports about rooms the user does not moderate are treated as if they did not exist
+                let event = match room {
+                    Some(room)
+                        if self
+                            .context
+                            .is_moderator(&room, &self.session_and_user_id.user_id) =>
+                    {
+                        let resolved = self
+                            .context
+                            .moderation_queue
+                            .resolve(&cmd.report_id)
+                            .await?;
+
+                        Event::ReportResolved(event::ReportResolvedReplyEvent {
+                            report_id: cmd.report_id,
+                            resolved,
+                        })
+                    }
+                    _ => Event::CommandRejected(event::CommandRejectedReplyEvent {
+                        command: String::from("resolve_report"),
+                        reason: format!("there is no open report with the id '{}'", cmd.report_id),
+                    }),
+                };
+
+                self.mpsc_tx.send(event.into()).await?;
+            }
             UserCommand::GetHistory(cmd) => {
                 if let Some((user_session_handle, _)) = self.joined_rooms.get(&cmd.room) {
                     // Fetch room history using borrowed handle
-                    let history = self.room_manager.get_room_history(&user_session_handle).await?;
+                    let history = self
+                        .context
+                        .room_manager
+                        .get_room_history(user_session_handle)
+                        .await?;
+                    match cmd.chunk_size {
+                        Some(chunk_size) => {
+                            self.stream_history(cmd.room, history, chunk_size).await?;
+                        }
+                        None => {
+                            self.mpsc_tx
+                                .send(
+                                    Event::HistoryResponse(event::HistoryResponseEvent {
+                                        room: cmd.room,
+                                        history,
+                                    })
+                                    .into(),
+                                )
+                                .await?;
+                        }
+                    }
+                }
+            }
+            // only members of the room hold or share its key
+            UserCommand::HoldRoomKey(_) | UserCommand::ShareRoomKey(_) => {}
+            // handled by the session before it gets here
+            UserCommand::Login(_) | UserCommand::Ping(_) | UserCommand::Quit(_) => {}
+        }
+
+        Ok(())
+    }
+
+    /// Send the history of a room in chunks followed by a done event, so large histories are not sent as one frame
+    /// The chunks wait for room in the queue of outgoing events, a slow client holds back the rest of the history
+    async fn stream_history(
+        &mut self,
+        room: String,
+        history: Vec<event::HistoricalMessage>,
+        chunk_size: usize,
+    ) -> anyhow::Result<()> {
+        let chunk_size = chunk_size.clamp(1, MAX_HISTORY_CHUNK_SIZE);
+        let total_count = history.len();
+
+        let mut history = history.into_iter();
+        loop {
+            let chunk = history.by_ref().take(chunk_size).collect::<Vec<_>>();
+            if chunk.is_empty() {
+                break;
+            }
+
+            self.mpsc_tx
+                .send(
+                    Event::HistoryChunk(event::HistoryChunkEvent {
+                        room: room.clone(),
+                        history: chunk,
+                        total_count,
+                    })
+                    .into(),
+                )
+                .await?;
+        }
+
+        self.mpsc_tx
+            .send(Event::HistoryDone(event::HistoryDoneEvent { room }).into())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Add a user to or remove a user from a group on behalf of an admin
+    /// The affected user is sent their new set of accessible rooms on all of their sessions
+    async fn handle_group_membership_command(
+        &mut self,
+        cmd: GroupMembershipCommand,
+        is_member: bool,
+    ) -> anyhow::Result<()> {
+        let is_changed = if is_member {
+            self.context
+                .groups
+                .add_member(&cmd.group, &cmd.user_id)
+                .await?
+        } else {
+            self.context
+                .groups
+                .remove_member(&cmd.group, &cmd.user_id)
+                .await?
+        };
+
+        if is_changed {
+            let rooms = self.context.accessible_rooms(&cmd.user_id).await;
+            self.context.session_registry.send_to_user(
+                &cmd.user_id,
+                &Event::AccessibleRoomsChanged(event::AccessibleRoomsChangedReplyEvent { rooms }),
+            );
+        }
+
+        self.mpsc_tx
+            .send(
+                Event::GroupMembershipChanged(event::GroupMembershipChangedReplyEvent {
+                    group: cmd.group,
+                    user_id: cmd.user_id,
+                    is_member,
+                })
+                .into(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Let a user into a room requiring approval or turn them away on behalf of one of its moderators
+    /// The user and the moderators of the room are told about the decision on all of their sessions
+    async fn handle_join_request_decision(
+        &mut self,
+        cmd: JoinRequestDecisionCommand,
+        is_approved: bool,
+    ) -> anyhow::Result<()> {
+        let command = if is_approved {
+            "approve_join_request"
+        } else {
+            "deny_join_request"
+        };
+        let moderator = &self.session_and_user_id.user_id;
+
+        let rejection = if !self.context.is_moderator(&cmd.room, moderator) {
+            Some(format!(
+                "only the moderators of '{}' can decide on join requests",
+                cmd.room
+            ))
+        } else if !self
+            .context
+            .room_manager
+            .decide_join_request(&cmd.room, &cmd.user_id, is_approved)
+            .await
+            // admins moderate every room, including the ones which do not exist
+            .unwrap_or(false)
+        {
+            Some(format!(
+                "'{}' has not asked to join '{}'",
+                cmd.user_id, cmd.room
+            ))
+        } else {
+            None
+        };
+
+        if let Some(reason) = rejection {
+            self.mpsc_tx
+                .send(
+                    Event::CommandRejected(event::CommandRejectedReplyEvent {
+                        command: String::from(command),
+                        reason,
+                    })
+                    .into(),
+                )
+                .await?;
+
+            return Ok(());
+        }
+
+        let event = Event::JoinRequestDecided(event::JoinRequestDecidedEvent {
+            room: cmd.room.clone(),
+            user_id: cmd.user_id.clone(),
+            approved: is_approved,
+            decided_by: moderator.clone(),
+        });
+        self.context
+            .session_registry
+            .send_to_user(&cmd.user_id, &event);
+        for moderator in self.context.moderators_of(&cmd.room) {
+            self.context
+                .session_registry
+                .send_to_user(&moderator, &event);
+        }
+
+        Ok(())
+    }
+
+    /// Join the room if the user can access it, once a moderator approves if the room requires approval
+    async fn join_room(&mut self, cmd: JoinRoomCommand) -> anyhow::Result<()> {
+        if self.joined_rooms.contains_key(&cmd.room) {
+            return Err(anyhow::anyhow!("already joined room '{}'", &cmd.room));
+        }
+
+        if !self
+            .context
+            .can_access_room(&cmd.room, &self.session_and_user_id.user_id)
+            .await
+        {
+            self.mpsc_tx
+                .send(
+                    Event::CommandRejected(event::CommandRejectedReplyEvent {
+                        command: String::from("join_room"),
+                        reason: format!("you do not have access to '{}'", cmd.room),
+                    })
+                    .into(),
+                )
+                .await?;
+
+            return Ok(());
+        }
+
+        // the moderators of a room requiring approval let users in, they join right away themselves
+        let user_id = &self.session_and_user_id.user_id;
+        if !self.context.is_moderator(&cmd.room, user_id) {
+            let approval = self
+                .context
+                .room_manager
+                .request_join_approval(&cmd.room, user_id)
+                .await?;
+
+            if let JoinApproval::Pending { is_new } = approval {
+                let event = Event::JoinRequestPending(event::JoinRequestPendingEvent {
+                    room: cmd.room.clone(),
+                    user_id: user_id.clone(),
+                });
+                self.mpsc_tx.send(event.clone().into()).await?;
+
+                // asking again does not bother the moderators again
+                if is_new {
+                    for moderator in self.context.moderators_of(&cmd.room) {
+                        self.context
+                            .session_registry
+                            .send_to_user(&moderator, &event);
+                    }
+                }
+
+                return Ok(());
+            }
+        }
+
+        let waiting_tx = cmd.wait_if_full.then(|| self.waiting_tx.clone());
+
+        match self
+            .context
+            .room_manager
+            .join_room(&cmd.room, &self.session_and_user_id, waiting_tx)
+            .await?
+        {
+            RoomJoinOutcome::Joined(join_result) => {
+                self.register_joined_room(cmd.room, join_result);
+            }
+            RoomJoinOutcome::Full {
+                capacity,
+                queue_position,
+            } => {
+                self.mpsc_tx
+                    .send(
+                        Event::RoomFull(event::RoomFullReplyEvent {
+                            room: cmd.room.clone(),
+                            capacity,
+                        })
+                        .into(),
+                    )
+                    .await?;
+
+                if let Some(position) = queue_position {
+                    self.waiting_rooms.insert(cmd.room.clone());
                     self.mpsc_tx
-                        .send(Event::HistoryResponse(event::HistoryResponseEvent {
+                        .send(
+                            Event::RoomQueuePosition(event::RoomQueuePositionReplyEvent {
+                                room: cmd.room,
+                                position,
+                            })
+                            .into(),
+                        )
+                        .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Create a code letting whoever redeems it into a private room, only its owners and the admins may
+    async fn handle_create_invite_code(
+        &mut self,
+        cmd: CreateInviteCodeCommand,
+    ) -> anyhow::Result<()> {
+        let is_public = self
+            .context
+            .room_manager
+            .chat_room_metadata()
+            .iter()
+            .any(|metadata| metadata.name == cmd.room && metadata.groups.is_empty());
+        let ttl = cmd
+            .ttl_secs
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_INVITE_TTL);
+        let max_uses = cmd.max_uses.unwrap_or(1);
+
+        let rejection = if let Some(reason) = self.management_rejection(&cmd.room, "invite to it") {
+            Some(reason)
+        } else if is_public {
+            Some(format!("anyone can join '{}' without an invite", cmd.room))
+        } else if ttl.is_zero() || ttl > MAX_INVITE_TTL {
+            Some(format!(
+                "an invite code can be valid for up to {} days",
+                MAX_INVITE_TTL.as_secs() / (24 * 60 * 60)
+            ))
+        } else if !(1..=MAX_INVITE_USES).contains(&max_uses) {
+            Some(format!(
+                "an invite code can be used by 1 to {} users",
+                MAX_INVITE_USES
+            ))
+        } else {
+            None
+        };
+        if let Some(reason) = rejection {
+            self.mpsc_tx
+                .send(
+                    Event::CommandRejected(event::CommandRejectedReplyEvent {
+                        command: String::from("create_invite_code"),
+                        reason,
+                    })
+                    .into(),
+                )
+                .await?;
+
+            return Ok(());
+        }
+
+        let created_invite = self
+            .context
+            .room_invites
+            .create(&cmd.room, &self.session_and_user_id.user_id, ttl, max_uses)
+            .await?;
+
+        self.mpsc_tx
+            .send(
+                Event::InviteCodeCreated(event::InviteCodeCreatedReplyEvent {
+                    room: cmd.room,
+                    code: created_invite.code,
+                    expires_at: created_invite.expires_at,
+                    max_uses,
+                })
+                .into(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Redeem an invite code and join its room, the user can access the room from then on
+    async fn handle_join_with_code(&mut self, cmd: JoinWithCodeCommand) -> anyhow::Result<()> {
+        let user_id = self.session_and_user_id.user_id.clone();
+        let Some(room) = self
+            .context
+            .room_invites
+            .redeem(&cmd.code, &user_id)
+            .await?
+        else {
+            self.mpsc_tx
+                .send(
+                    Event::CommandRejected(event::CommandRejectedReplyEvent {
+                        command: String::from("join_with_code"),
+                        reason: String::from("the invite code is not valid or has expired"),
+                    })
+                    .into(),
+                )
+                .await?;
+
+            return Ok(());
+        };
+
+        // every session of the user can see the room from now on
+        let rooms = self.context.accessible_rooms(&user_id).await;
+        self.context.session_registry.send_to_user(
+            &user_id,
+            &Event::AccessibleRoomsChanged(event::AccessibleRoomsChangedReplyEvent { rooms }),
+        );
+
+        if self.joined_rooms.contains_key(&room) {
+            return Ok(());
+        }
+
+        self.join_room(JoinRoomCommand {
+            room,
+            wait_if_full: false,
+        })
+        .await
+    }
+
+    /// Why the user may not manage the room, e.g. archive it, if they may not
+    fn management_rejection(&self, room: &str, action: &str) -> Option<String> {
+        let exists = self
+            .context
+            .room_manager
+            .chat_room_metadata()
+            .iter()
+            .any(|metadata| metadata.name == room);
+
+        if !self
+            .context
+            .is_moderator(room, &self.session_and_user_id.user_id)
+        {
+            Some(format!("only the owners of '{}' can {}", room, action))
+        } else if !exists {
+            // admins moderate every room, including the ones which do not exist
+            Some(format!("there is no room named '{}'", room))
+        } else {
+            None
+        }
+    }
+
+    /// Archive a room right away, or schedule its archival, and let its audience know
+    async fn handle_archive_room(&mut self, cmd: ArchiveRoomCommand) -> anyhow::Result<()> {
+        if let Some(reason) = self.management_rejection(&cmd.room, "archive or reopen it") {
+            self.mpsc_tx
+                .send(
+                    Event::CommandRejected(event::CommandRejectedReplyEvent {
+                        command: String::from("archive_room"),
+                        reason,
+                    })
+                    .into(),
+                )
+                .await?;
+
+            return Ok(());
+        }
+
+        match cmd.archive_at {
+            Some(archive_at) if archive_at > unix_millis_now() => {
+                self.context
+                    .room_archive
+                    .schedule(&cmd.room, archive_at)
+                    .await?;
+
+                self.mpsc_tx
+                    .send(
+                        Event::RoomArchivalScheduled(event::RoomArchivalScheduledReplyEvent {
                             room: cmd.room,
-                            history: history,
-                        }
-                    )).await?;
+                            archive_at,
+                        })
+                        .into(),
+                    )
+                    .await?;
+            }
+            _ => {
+                if self.context.room_archive.archive(&cmd.room).await? {
+                    let event = Event::RoomArchived(event::RoomArchivedBroadcastEvent {
+                        room: cmd.room.clone(),
+                    });
+                    self.context.notify_room_audience(&cmd.room, &event).await;
                 }
             }
-            _ => {}
         }
 
         Ok(())
     }
 
+    /// Change or remove the status text of the user and let the rooms they are in know
+    async fn handle_set_status_text(&mut self, cmd: SetStatusTextCommand) -> anyhow::Result<()> {
+        // an empty status text removes it, the same as none at all
+        let text = cmd
+            .text
+            .map(|text| String::from(text.trim()))
+            .filter(|text| !text.is_empty());
+
+        let rejection = text.as_ref().and_then(|text| {
+            if text.chars().count() > MAX_STATUS_TEXT_LENGTH {
+                Some(format!(
+                    "the status text can not be longer than {} characters",
+                    MAX_STATUS_TEXT_LENGTH
+                ))
+            } else if text.chars().any(char::is_control) {
+                Some(String::from("the status text must be a single line"))
+            } else {
+                None
+            }
+        });
+        if let Some(reason) = rejection {
+            self.mpsc_tx
+                .send(
+                    Event::CommandRejected(event::CommandRejectedReplyEvent {
+                        command: String::from("set_status_text"),
+                        reason,
+                    })
+                    .into(),
+                )
+                .await?;
+
+            return Ok(());
+        }
+
+        let user_id = &self.session_and_user_id.user_id;
+        if self
+            .context
+            .session_registry
+            .set_status_text(user_id, text.clone())
+        {
+            self.context
+                .room_manager
+                .broadcast_status_text(None, user_id, text.as_deref())
+                .await;
+        }
+
+        Ok(())
+    }
+
+    /// Delete everything kept about a user, closing their sessions and letting the audience of their rooms know
+    /// Only admins get here, the interceptors reject everyone else
+    async fn handle_purge_user(&mut self, cmd: PurgeUserCommand) -> anyhow::Result<()> {
+        for session in self.context.session_registry.sessions_of(&cmd.user_id) {
+            self.context.session_registry.terminate(
+                &cmd.user_id,
+                &session.session_id,
+                Event::SessionTerminated(event::SessionTerminatedReplyEvent {
+                    reason: String::from("your data has been deleted by an admin"),
+                    cause: Some(SessionTerminationCause::UserPurged),
+                }),
+            );
+        }
+
+        self.context.user_directory.forget(&cmd.user_id).await?;
+        self.context.read_markers.forget(&cmd.user_id).await?;
+        self.context.reminders.forget(&cmd.user_id).await?;
+        self.context.groups.forget(&cmd.user_id).await?;
+        self.context.moderation_queue.forget(&cmd.user_id).await?;
+        self.context.command_history.forget(&cmd.user_id);
+        self.context.room_invites.forget(&cmd.user_id).await?;
+        if let Some(mirror) = self.context.mirror.as_ref() {
+            mirror.purge_user(&cmd.user_id).await?;
+        }
+
+        let mut redacted_count = 0;
+        for (room, message_ids) in self.context.room_manager.purge_user(&cmd.user_id).await {
+            redacted_count += message_ids.len();
+
+            let event = Event::MessagesRedacted(event::MessagesRedactedBroadcastEvent {
+                room: room.clone(),
+                user_id: cmd.user_id.clone(),
+                message_ids,
+            });
+            self.context.notify_room_audience(&room, &event).await;
+        }
+
+        self.mpsc_tx
+            .send(
+                Event::UserPurged(event::UserPurgedReplyEvent {
+                    user_id: cmd.user_id,
+                    redacted_count,
+                })
+                .into(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Reopen an archived room and let its audience know, or call off its scheduled archival
+    async fn handle_reopen_room(&mut self, cmd: ReopenRoomCommand) -> anyhow::Result<()> {
+        let rejection = match self.management_rejection(&cmd.room, "archive or reopen it") {
+            Some(reason) => Some(reason),
+            None => match self.context.room_archive.reopen(&cmd.room).await? {
+                ReopenOutcome::Reopened => {
+                    let event = Event::RoomReopened(event::RoomReopenedBroadcastEvent {
+                        room: cmd.room.clone(),
+                    });
+                    self.context.notify_room_audience(&cmd.room, &event).await;
+
+                    None
+                }
+                // the room has been open all along, only the owner needs to know
+                ReopenOutcome::ScheduleCancelled => {
+                    self.mpsc_tx
+                        .send(
+                            Event::RoomReopened(event::RoomReopenedBroadcastEvent {
+                                room: cmd.room.clone(),
+                            })
+                            .into(),
+                        )
+                        .await?;
+
+                    None
+                }
+                ReopenOutcome::NotArchived => Some(format!("'{}' is not archived", cmd.room)),
+            },
+        };
+
+        if let Some(reason) = rejection {
+            self.mpsc_tx
+                .send(
+                    Event::CommandRejected(event::CommandRejectedReplyEvent {
+                        command: String::from("reopen_room"),
+                        reason,
+                    })
+                    .into(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Change the description or the capacity of a room and let its audience know
+    async fn handle_update_room_metadata(
+        &mut self,
+        cmd: UpdateRoomMetadataCommand,
+    ) -> anyhow::Result<()> {
+        let description = cmd
+            .description
+            .map(|description| description.trim().to_string());
+        let rejection = self
+            .management_rejection(&cmd.room, "change its description or capacity")
+            .or_else(|| {
+                description
+                    .as_ref()
+                    .filter(|description| description.chars().count() > MAX_DESCRIPTION_LENGTH)
+                    .map(|_| {
+                        format!(
+                            "the description can not be longer than {} characters",
+                            MAX_DESCRIPTION_LENGTH
+                        )
+                    })
+            });
+
+        if let Some(reason) = rejection {
+            self.mpsc_tx
+                .send(
+                    Event::CommandRejected(event::CommandRejectedReplyEvent {
+                        command: String::from("update_room_metadata"),
+                        reason,
+                    })
+                    .into(),
+                )
+                .await?;
+
+            return Ok(());
+        }
+
+        let metadata = self
+            .context
+            .room_manager
+            .update_metadata(&cmd.room, description, cmd.capacity)
+            .await?;
+        let event = Event::RoomMetadataUpdated(event::RoomMetadataUpdatedBroadcastEvent {
+            room: cmd.room.clone(),
+            description: metadata.description,
+            capacity: metadata.capacity,
+        });
+        self.context.notify_room_audience(&cmd.room, &event).await;
+
+        Ok(())
+    }
+
+    /// Store a parsed reminder, or tell the user why it could not be parsed
+    async fn handle_reminder_command(
+        &mut self,
+        room: String,
+        nonce: Option<String>,
+        reminder_command: Result<reminders::ReminderCommand, String>,
+    ) -> anyhow::Result<()> {
+        let event = match reminder_command {
+            Ok(reminder_command) => {
+                let due_at = unix_millis_now() + reminder_command.delay.as_millis() as u64;
+
+                let is_added = self
+                    .context
+                    .reminders
+                    .add(Reminder {
+                        user_id: self.session_and_user_id.user_id.clone(),
+                        room: room.clone(),
+                        content: reminder_command.content,
+                        due_at,
+                    })
+                    .await?;
+
+                if is_added {
+                    Event::ReminderSet(event::ReminderSetReplyEvent { room, due_at })
+                } else {
+                    Event::MessageRejected(event::MessageRejectedReplyEvent {
+                        room,
+                        reason: event::MessageRejectionReason::TooManyReminders {
+                            max_reminders: reminders::MAX_PENDING_REMINDERS,
+                        },
+                        nonce,
+                    })
+                }
+            }
+            Err(message) => Event::MessageRejected(event::MessageRejectedReplyEvent {
+                room,
+                reason: event::MessageRejectionReason::InvalidCommand { message },
+                nonce,
+            }),
+        };
+
+        self.mpsc_tx.send(event.into()).await?;
+
+        Ok(())
+    }
+
+    /// Store the result of joining a room and start forwarding the events of the room to the user
+    ///
+    /// Does not await, hence it can be called from cancel-safe contexts
+    fn register_joined_room(&mut self, room: String, join_result: RoomJoinResult) {
+        let (broadcast_rx, user_session_handle, user_ids, welcome_message, creates_room_key) =
+            join_result;
+
+        self.waiting_rooms.remove(&room);
+
+        // spawn a task to forward broadcast messages to the users' mpsc channel
+        // hence the user can receive messages from different rooms via single channel
+        let abort_handle = self.join_set.spawn({
+            let mpsc_tx = self.mpsc_tx.clone();
+            let room = room.clone();
+            let context = Arc::clone(&self.context);
+            let session_and_user_id = self.session_and_user_id.clone();
+
+            async move {
+                let status_texts = user_ids
+                    .iter()
+                    .filter_map(|user_id| {
+                        let status_text = context.session_registry.status_text_of(user_id)?;
+                        Some((user_id.clone(), status_text))
+                    })
+                    .collect();
+
+                // start with sending the user joined room event as a reply to the user
+                let _ = mpsc_tx
+                    .send(
+                        Event::UserJoinedRoom(event::UserJoinedRoomReplyEvent {
+                            room: room.clone(),
+                            users: user_ids,
+                            status_texts,
+                            creates_room_key,
+                        })
+                        .into(),
+                    )
+                    .await;
+
+                // the others in the room learn about the status text of the user along with their arrival
+                if let Some(status_text) = context
+                    .session_registry
+                    .status_text_of(&session_and_user_id.user_id)
+                {
+                    context
+                        .room_manager
+                        .broadcast_status_text(
+                            Some(&room),
+                            &session_and_user_id.user_id,
+                            Some(&status_text),
+                        )
+                        .await;
+                }
+
+                // greet the user privately if it is their first time in the room
+                if let Some(message) = welcome_message {
+                    let _ = mpsc_tx
+                        .send(
+                            Event::RoomWelcome(event::RoomWelcomeReplyEvent {
+                                room: room.clone(),
+                                message,
+                            })
+                            .into(),
+                        )
+                        .await;
+                }
+
+                forward_room_events(room, broadcast_rx, mpsc_tx, context, session_and_user_id)
+                    .await;
+            }
+        });
+
+        // store references to the user session handle and abort handle
+        // this is used to send messages to the room and to cancel the task when user leaves the room
+        self.joined_rooms
+            .insert(room, (user_session_handle, abort_handle));
+    }
+
     /// Leave all the rooms the user is currently participating in
     pub async fn leave_all_rooms(&mut self) -> anyhow::Result<()> {
-         // Collect all the room names (keys) the user is currently part of
+        // Stop waiting for full rooms first, so no more rooms are joined on behalf of the user
+        for room in self.waiting_rooms.drain().collect::<Vec<String>>() {
+            self.context
+                .room_manager
+                .leave_waiting_queue(&room, &self.session_and_user_id.session_id)
+                .await?;
+        }
+
+        // A room may have joined the user before they left its queue
+        while let Ok(update) = self.waiting_rx.try_recv() {
+            if let WaitingRoomUpdate::Joined { room, join_result } = update {
+                self.register_joined_room(room, join_result);
+            }
+        }
+
+        // Collect all the room names (keys) the user is currently part of
         let rooms_to_leave = self.joined_rooms.keys().cloned().collect::<Vec<String>>();
 
         // Iterate over the room names to leave them
@@ -121,17 +1492,118 @@ impl ChatSession {
                 self.cleanup_room(urp).await?;
             }
         }
-        
+
         Ok(())
     }
 
+    /// Put a report about a message of a joined room in the moderation queue
+    /// The author and the content are taken from the history of the room, never from the reporter
+    ///
+    /// Returns why the report is refused, e.g. if the message is no longer in the history
+    async fn file_report(
+        &mut self,
+        cmd: ReportMessageCommand,
+    ) -> anyhow::Result<Result<event::MessageReport, String>> {
+        if !self.joined_rooms.contains_key(&cmd.room) {
+            return Ok(Err(format!("you have not joined '{}'", cmd.room)));
+        }
+        if cmd.reason.trim().is_empty() {
+            return Ok(Err(String::from(
+                "the reason of the report can not be empty",
+            )));
+        }
+        let Some(message) = self
+            .context
+            .room_manager
+            .find_message(&cmd.room, &cmd.message_id)
+            .await?
+        else {
+            return Ok(Err(format!(
+                "there is no message with the id '{}' in the history of '{}'",
+                cmd.message_id, cmd.room
+            )));
+        };
+
+        let report = event::MessageReport {
+            // short enough for moderators to type when resolving the report
+            id: nanoid!(8),
+            room: cmd.room,
+            message_id: message.id,
+            user_id: message.user_id,
+            content: message.content,
+            reason: cmd.reason,
+            reported_by: self.session_and_user_id.user_id.clone(),
+            reported_at: unix_millis_now(),
+        };
+
+        Ok(
+            match self.context.moderation_queue.file(report.clone()).await? {
+                FilingOutcome::Filed => Ok(report),
+                FilingOutcome::AlreadyReported => {
+                    Err(String::from("you have already reported the message"))
+                }
+                FilingOutcome::TooManyOpenReports => Err(String::from(
+                    "you have too many open reports, wait for the moderators to resolve them",
+                )),
+            },
+        )
+    }
+
+    /// Leave the rooms which are not among the accessible ones anymore, and stop waiting for them
+    /// e.g. once the user is removed from the group a private room is granted to, or the room is turned private
+    pub async fn leave_inaccessible_rooms(
+        &mut self,
+        accessible_rooms: &[event::RoomDetail],
+    ) -> anyhow::Result<()> {
+        let is_inaccessible =
+            |room: &String| !accessible_rooms.iter().any(|detail| &detail.name == room);
+
+        let waiting_rooms = self
+            .waiting_rooms
+            .iter()
+            .filter(|room| is_inaccessible(room))
+            .cloned()
+            .collect::<Vec<String>>();
+        for room in waiting_rooms {
+            self.waiting_rooms.remove(&room);
+            self.context
+                .room_manager
+                .leave_waiting_queue(&room, &self.session_and_user_id.session_id)
+                .await?;
+        }
+
+        let joined_rooms = self
+            .joined_rooms
+            .keys()
+            .filter(|room| is_inaccessible(room))
+            .cloned()
+            .collect::<Vec<String>>();
+        for room in joined_rooms {
+            if let Some(urp) = self.joined_rooms.remove(&room) {
+                self.cleanup_room(urp).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stop taking part in the rooms without leaving them, e.g. when the server shuts down
+    /// The other users are not told, the rooms go away along with the server.
+    pub fn abandon_all_rooms(&mut self) {
+        for (_, (user_session_handle, abort_handle)) in self.joined_rooms.drain() {
+            user_session_handle.abandon();
+            abort_handle.abort();
+        }
+    }
+
     /// Cleanup the room by removing the user from the room and
     /// aborting the task that forwards broadcast messages to the user
     async fn cleanup_room(
         &mut self,
         (user_session_handle, abort_handle): (UserSessionHandle, AbortHandle),
     ) -> anyhow::Result<()> {
-        self.room_manager
+        self.context
+            .room_manager
             .drop_user_session_handle(user_session_handle)
             .await?;
 
@@ -140,11 +1612,111 @@ impl ChatSession {
         Ok(())
     }
 
+    /// Take the receiver which yields the last event to send to the user once the session is terminated remotely,
+    /// e.g. when the user disconnects it from another device. It can only be taken once.
+    pub fn take_termination(&mut self) -> Option<oneshot::Receiver<Event>> {
+        self.terminate_rx.take()
+    }
+
     /// Receive an event that may have originated from any of the rooms the user is actively participating in
-    pub async fn recv(&mut self) -> anyhow::Result<Event> {
-        self.mpsc_rx
-            .recv()
-            .await
-            .context("could not recv from the broadcast channel")
+    /// Rooms the user waits for are joined here as soon as they free up a slot
+    ///
+    /// # Cancel Safety
+    ///
+    /// This method is cancel-safe, both channels are cancel-safe and joining a room does not await.
+    pub async fn recv(&mut self) -> anyhow::Result<OutgoingEvent> {
+        loop {
+            tokio::select! {
+                event = self.mpsc_rx.recv() => {
+                    return event.context("could not recv from the broadcast channel");
+                }
+                Some(update) = self.waiting_rx.recv() => match update {
+                    WaitingRoomUpdate::Position { room, position } => {
+                        return Ok(Event::RoomQueuePosition(event::RoomQueuePositionReplyEvent {
+                            room,
+                            position,
+                        }).into());
+                    }
+                    WaitingRoomUpdate::Joined { room, join_result } => {
+                        self.register_joined_room(room, join_result);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Forward the events of a room to the user until the user leaves the room
+/// The events are already serialized, they are written to the user as they are
+///
+/// If the user falls so far behind that some events are dropped, the lag policy of the room decides
+/// whether they catch up from the history of the room or their session is closed
+async fn forward_room_events(
+    room: String,
+    broadcast_rx: broadcast::Receiver<EncodedEvent>,
+    mpsc_tx: mpsc::Sender<OutgoingEvent>,
+    context: Arc<ServerContext>,
+    session_and_user_id: SessionAndUserId,
+) {
+    let mut broadcast_rx = context.chaos.disturb(broadcast_rx);
+    loop {
+        let missed_events = match broadcast_rx.recv().await {
+            Ok(encoded_event) => {
+                let _ = mpsc_tx.send(OutgoingEvent::Encoded(encoded_event)).await;
+                continue;
+            }
+            Err(RecvError::Lagged(missed_events)) => missed_events,
+            Err(RecvError::Closed) => return,
+        };
+
+        match context.room_manager.record_lag(&room, missed_events).await {
+            Ok(LagPolicy::Resync) => {
+                let history = context
+                    .room_manager
+                    .history_of(&room)
+                    .await
+                    .unwrap_or_default();
+
+                let _ = mpsc_tx
+                    .send(
+                        Event::RoomResynced(event::RoomResyncedReplyEvent {
+                            room: room.clone(),
+                            missed_events,
+                        })
+                        .into(),
+                    )
+                    .await;
+                let _ = mpsc_tx
+                    .send(
+                        Event::HistoryResponse(event::HistoryResponseEvent {
+                            room: room.clone(),
+                            history,
+                        })
+                        .into(),
+                    )
+                    .await;
+            }
+            Ok(LagPolicy::Disconnect) => {
+                context.session_registry.terminate(
+                    &session_and_user_id.user_id,
+                    &session_and_user_id.session_id,
+                    Event::SessionTerminated(event::SessionTerminatedReplyEvent {
+                        reason: format!("could not keep up with the events of '{}'", room),
+                        cause: Some(SessionTerminationCause::Lagged),
+                    }),
+                );
+
+                return;
+            }
+            Err(_) => return,
+        }
+    }
+}
+
+impl Drop for ChatSession {
+    fn drop(&mut self) {
+        self.context
+            .session_registry
+            .unregister(&self.session_and_user_id);
     }
 }
@@ -0,0 +1,95 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+use comms::event::CommandHistoryEntry;
+
+/// The number of commands kept per user, the oldest ones are dropped first
+const COMMAND_HISTORY_CAPACITY: usize = 50;
+
+/// [CommandHistory] keeps the last commands every user has sent, so admins can look into misbehaving clients
+///
+/// Only the kind of each command and when it was received are kept, never its content.
+/// The history lives in memory and starts over when the server restarts.
+#[derive(Debug, Default)]
+pub struct CommandHistory {
+    user_id_to_entries: Mutex<HashMap<String, VecDeque<CommandHistoryEntry>>>,
+}
+
+impl CommandHistory {
+    pub fn new() -> Self {
+        CommandHistory {
+            user_id_to_entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record a command received from the user, dropping their oldest one if the history is full
+    pub fn record(&self, user_id: &str, entry: CommandHistoryEntry) {
+        let mut user_id_to_entries = self.user_id_to_entries.lock().unwrap();
+        let entries = user_id_to_entries
+            .entry(String::from(user_id))
+            .or_insert_with(|| VecDeque::with_capacity(COMMAND_HISTORY_CAPACITY));
+
+        if entries.len() >= COMMAND_HISTORY_CAPACITY {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// The commands the user has recently sent, the oldest first
+    pub fn entries_of(&self, user_id: &str) -> Vec<CommandHistoryEntry> {
+        self.user_id_to_entries
+            .lock()
+            .unwrap()
+            .get(user_id)
+            .map(|entries| entries.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Drop the history of the user, e.g. when their data is deleted
+    pub fn forget(&self, user_id: &str) {
+        self.user_id_to_entries.lock().unwrap().remove(user_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(command: &str, received_at: u64) -> CommandHistoryEntry {
+        CommandHistoryEntry {
+            command: String::from(command),
+            session_id: String::from("session"),
+            received_at,
+            rejected: false,
+        }
+    }
+
+    #[test]
+    fn test_oldest_commands_are_dropped_first() {
+        let history = CommandHistory::new();
+        for idx in 0..COMMAND_HISTORY_CAPACITY as u64 + 2 {
+            history.record("alice", entry("send_message", idx));
+        }
+        history.record("bob", entry("join_room", 0));
+
+        let entries = history.entries_of("alice");
+        assert_eq!(entries.len(), COMMAND_HISTORY_CAPACITY);
+        assert_eq!(entries[0].received_at, 2);
+        assert_eq!(
+            entries.last().unwrap().received_at,
+            COMMAND_HISTORY_CAPACITY as u64 + 1
+        );
+        assert_eq!(history.entries_of("bob").len(), 1);
+    }
+
+    #[test]
+    fn test_forgotten_users_have_no_history() {
+        let history = CommandHistory::new();
+        history.record("alice", entry("join_room", 0));
+        history.forget("alice");
+
+        assert!(history.entries_of("alice").is_empty());
+    }
+}
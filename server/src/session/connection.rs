@@ -0,0 +1,39 @@
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+};
+
+/// [Connection] is a freshly accepted connection a user session can be held over
+pub trait Connection: AsyncRead + AsyncWrite + Send + Unpin + 'static {
+    /// The address of the client, as shown to the user in the list of their sessions
+    fn peer_address(&self) -> String;
+}
+
+impl Connection for TcpStream {
+    fn peer_address(&self) -> String {
+        self.peer_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_default()
+    }
+}
+
+/// An in-memory connection, e.g. one end of [comms::transport::memory::stream_pair] in a test
+impl Connection for tokio::io::DuplexStream {
+    fn peer_address(&self) -> String {
+        String::from("memory")
+    }
+}
+
+#[cfg(unix)]
+impl Connection for tokio::net::UnixStream {
+    fn peer_address(&self) -> String {
+        // the clients of a Unix domain socket are rarely bound to a path of their own
+        match self.peer_addr() {
+            Ok(addr) => match addr.as_pathname() {
+                Some(path) => format!("unix:{}", path.display()),
+                None => String::from("unix"),
+            },
+            Err(_) => String::default(),
+        }
+    }
+}
@@ -0,0 +1,541 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use comms::{
+    command::{self, BotScope, UserCommand, ENCRYPTED_CONTENT_PREFIX},
+    event::{self, Event},
+};
+
+use crate::{context::ServerContext, logging, room_manager::SessionAndUserId};
+
+/// The session a command has been sent by, handed to every [CommandInterceptor]
+pub struct CommandOrigin<'a> {
+    pub session_and_user_id: &'a SessionAndUserId,
+    /// The capabilities of the session if it belongs to a bot
+    pub bot_scope: Option<&'a BotScope>,
+    /// Whether the session belongs to a guest
+    pub is_guest: bool,
+    pub context: &'a ServerContext,
+}
+
+/// What a [CommandInterceptor] decides about a command
+pub enum Interception {
+    /// Hand the command on to the next interceptor, and eventually to the chat session
+    Proceed,
+    /// Drop the command, the session receives the given event instead
+    Reject(Event),
+}
+
+/// [CommandInterceptor] looks at every command of a session before the chat session handles it
+///
+/// Interceptors take care of the concerns shared by many commands, e.g. who may send them,
+/// so the chat session only deals with what the commands do. They may rewrite the command as well.
+pub trait CommandInterceptor: Send {
+    fn intercept(&mut self, origin: &CommandOrigin, command: &mut UserCommand) -> Interception;
+}
+
+/// [InterceptorChain] runs the interceptors in the order they have been added, until one of them rejects the command
+pub struct InterceptorChain {
+    interceptors: Vec<Box<dyn CommandInterceptor>>,
+}
+
+impl InterceptorChain {
+    pub fn new() -> Self {
+        InterceptorChain {
+            interceptors: Vec::new(),
+        }
+    }
+
+    pub fn with(mut self, interceptor: impl CommandInterceptor + 'static) -> Self {
+        self.interceptors.push(Box::new(interceptor));
+        self
+    }
+
+    pub fn intercept(&mut self, origin: &CommandOrigin, command: &mut UserCommand) -> Interception {
+        for interceptor in self.interceptors.iter_mut() {
+            if let Interception::Reject(event) = interceptor.intercept(origin, command) {
+                return Interception::Reject(event);
+            }
+        }
+
+        Interception::Proceed
+    }
+}
+
+/// The interceptors every chat session runs its commands through
+pub fn session_interceptors(max_message_length: usize) -> InterceptorChain {
+    InterceptorChain::new()
+        .with(NonceLimit)
+        .with(AuditLog)
+        .with(AdminOnly)
+        .with(BotScopeGuard)
+        .with(GuestRestrictions::default())
+        .with(ArchivedRoomGuard)
+        .with(MessageLengthLimit {
+            max_length: max_message_length,
+        })
+        .with(MessageFilterGuard)
+}
+
+fn command_rejected(command: &UserCommand, reason: impl Into<String>) -> Interception {
+    Interception::Reject(Event::CommandRejected(event::CommandRejectedReplyEvent {
+        command: String::from(command.name()),
+        reason: reason.into(),
+    }))
+}
+
+/// The most characters of a message nonce the server echoes
+const MAX_NONCE_LENGTH: usize = 64;
+
+/// Drops the nonces of messages which are too long to be echoed to every member of the room
+/// The message is sent nonetheless, the sending session only can not tell it apart by its nonce.
+struct NonceLimit;
+
+impl CommandInterceptor for NonceLimit {
+    fn intercept(&mut self, _origin: &CommandOrigin, command: &mut UserCommand) -> Interception {
+        if let UserCommand::SendMessage(cmd) = command {
+            if cmd
+                .nonce
+                .as_ref()
+                .is_some_and(|nonce| nonce.chars().count() > MAX_NONCE_LENGTH)
+            {
+                cmd.nonce = None;
+            }
+        }
+
+        Interception::Proceed
+    }
+}
+
+/// Logs every administrative command alongside who has sent it, whether it is permitted or not
+struct AuditLog;
+
+impl CommandInterceptor for AuditLog {
+    fn intercept(&mut self, origin: &CommandOrigin, command: &mut UserCommand) -> Interception {
+        if matches!(
+            command,
+            UserCommand::IssueBotToken(_)
+                | UserCommand::RevokeBotToken(_)
+                | UserCommand::AddGroupMember(_)
+                | UserCommand::RemoveGroupMember(_)
+                | UserCommand::ResolveReport(_)
+                | UserCommand::ApproveJoinRequest(_)
+                | UserCommand::DenyJoinRequest(_)
+                | UserCommand::ArchiveRoom(_)
+                | UserCommand::ReopenRoom(_)
+                | UserCommand::UpdateRoomMetadata(_)
+                | UserCommand::PurgeUser(_)
+                | UserCommand::GetCommandHistory(_)
+                | UserCommand::CreateInviteCode(_)
+        ) {
+            logging::info(format!(
+                "audit: user '{}' (session {}) sent {:?}",
+                origin.session_and_user_id.user_id, origin.session_and_user_id.session_id, command
+            ));
+        }
+
+        Interception::Proceed
+    }
+}
+
+/// Rejects the commands only admins may send, e.g. issuing bot tokens, from everyone else
+struct AdminOnly;
+
+impl CommandInterceptor for AdminOnly {
+    fn intercept(&mut self, origin: &CommandOrigin, command: &mut UserCommand) -> Interception {
+        let reason = match command {
+            UserCommand::IssueBotToken(_) => "only admins can issue bot tokens",
+            UserCommand::RevokeBotToken(_) => "only admins can revoke bot tokens",
+            UserCommand::AddGroupMember(_) | UserCommand::RemoveGroupMember(_) => {
+                "only admins can manage groups"
+            }
+            UserCommand::GetBroadcastMetrics(_) => "only admins can see the broadcast metrics",
+            UserCommand::PurgeUser(_) => "only admins can purge users",
+            UserCommand::GetCommandHistory(_) => "only admins can see the commands of other users",
+            UserCommand::GetRoomStats(cmd) if cmd.room.is_none() => {
+                "only admins can see the stats of every room"
+            }
+            _ => return Interception::Proceed,
+        };
+
+        if origin.context.is_admin(&origin.session_and_user_id.user_id) {
+            Interception::Proceed
+        } else {
+            command_rejected(command, reason)
+        }
+    }
+}
+
+/// Keeps bots within their scope, read-only bots can not send messages and bots limited to some rooms can not join others
+struct BotScopeGuard;
+
+impl CommandInterceptor for BotScopeGuard {
+    fn intercept(&mut self, origin: &CommandOrigin, command: &mut UserCommand) -> Interception {
+        let Some(bot_scope) = origin.bot_scope else {
+            return Interception::Proceed;
+        };
+        let is_out_of_scope = |room: &String| {
+            bot_scope
+                .rooms
+                .as_ref()
+                .is_some_and(|rooms| !rooms.contains(room))
+        };
+
+        match command {
+            // webhooks post without joining the room, hence the room is checked for every message
+            UserCommand::SendMessage(cmd) if bot_scope.read_only || is_out_of_scope(&cmd.room) => {
+                Interception::Reject(Event::MessageRejected(event::MessageRejectedReplyEvent {
+                    room: cmd.room.clone(),
+                    reason: event::MessageRejectionReason::NotPermitted,
+                    nonce: cmd.nonce.clone(),
+                }))
+            }
+            UserCommand::JoinRoom(cmd) if is_out_of_scope(&cmd.room) => {
+                let reason = format!("you do not have access to '{}'", cmd.room);
+                command_rejected(command, reason)
+            }
+            // the room of the code is only known once it is redeemed
+            UserCommand::JoinWithCode(_) if bot_scope.rooms.is_some() => command_rejected(
+                command,
+                "bots limited to some rooms can not redeem invite codes",
+            ),
+            _ => Interception::Proceed,
+        }
+    }
+}
+
+/// The number of messages a guest may send within [GUEST_MESSAGE_WINDOW]
+const GUEST_MESSAGE_LIMIT: usize = 10;
+const GUEST_MESSAGE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Lets guests chat, but nothing beyond, and limits how many messages they may send in a while
+#[derive(Default)]
+struct GuestRestrictions {
+    /// When the messages within the window have been sent, the oldest first
+    sent_at: VecDeque<Instant>,
+}
+
+impl CommandInterceptor for GuestRestrictions {
+    fn intercept(&mut self, origin: &CommandOrigin, command: &mut UserCommand) -> Interception {
+        if !origin.is_guest {
+            return Interception::Proceed;
+        }
+
+        match command {
+            UserCommand::SendMessage(cmd) => {
+                let now = Instant::now();
+                while self
+                    .sent_at
+                    .front()
+                    .is_some_and(|sent_at| now.duration_since(*sent_at) >= GUEST_MESSAGE_WINDOW)
+                {
+                    self.sent_at.pop_front();
+                }
+
+                if let Some(oldest) = self
+                    .sent_at
+                    .front()
+                    .filter(|_| self.sent_at.len() >= GUEST_MESSAGE_LIMIT)
+                {
+                    let retry_after = GUEST_MESSAGE_WINDOW - now.duration_since(*oldest);
+                    return Interception::Reject(Event::MessageRejected(
+                        event::MessageRejectedReplyEvent {
+                            room: cmd.room.clone(),
+                            reason: event::MessageRejectionReason::RateLimited {
+                                retry_after: retry_after.as_millis() as u64,
+                            },
+                            nonce: cmd.nonce.clone(),
+                        },
+                    ));
+                }
+
+                self.sent_at.push_back(now);
+                Interception::Proceed
+            }
+            UserCommand::JoinRoom(_)
+            | UserCommand::LeaveRoom(_)
+            | UserCommand::GetHistory(_)
+            | UserCommand::MarkRoomRead(_)
+            | UserCommand::SetPresence(_)
+            | UserCommand::GetUserInfo(_)
+            | UserCommand::SearchUsers(_)
+            | UserCommand::GetRoomInfo(_)
+            | UserCommand::CheckRooms(_)
+            | UserCommand::ListRooms(_)
+            | UserCommand::RequestRoomKey(_)
+            | UserCommand::HoldRoomKey(_)
+            | UserCommand::ShareRoomKey(_)
+            | UserCommand::ListSessions(_) => Interception::Proceed,
+            _ => command_rejected(
+                command,
+                "guests can only chat, log in with an account to do more",
+            ),
+        }
+    }
+}
+
+/// Keeps archived rooms read-only, their history can still be read but no messages are sent to them
+struct ArchivedRoomGuard;
+
+impl CommandInterceptor for ArchivedRoomGuard {
+    fn intercept(&mut self, origin: &CommandOrigin, command: &mut UserCommand) -> Interception {
+        match command {
+            UserCommand::SendMessage(cmd) if origin.context.room_archive.is_archived(&cmd.room) => {
+                Interception::Reject(Event::MessageRejected(event::MessageRejectedReplyEvent {
+                    room: cmd.room.clone(),
+                    reason: event::MessageRejectionReason::Archived,
+                    nonce: cmd.nonce.clone(),
+                }))
+            }
+            _ => Interception::Proceed,
+        }
+    }
+}
+
+/// Rejects messages longer than the server accepts with a structured rejection instead of silently dropping them
+/// Welcome messages are held to the same limit, since every user joining the room receives them
+/// The plain text of encrypted messages can not be counted, their ciphertext is held to the length the limit allows
+struct MessageLengthLimit {
+    max_length: usize,
+}
+
+impl CommandInterceptor for MessageLengthLimit {
+    fn intercept(&mut self, _origin: &CommandOrigin, command: &mut UserCommand) -> Interception {
+        let cmd = match command {
+            UserCommand::SendMessage(cmd) => cmd,
+            UserCommand::SetWelcomeMessage(cmd) => {
+                let length = cmd
+                    .message
+                    .as_ref()
+                    .map(|message| message.chars().count())
+                    .unwrap_or_default();
+                if length <= self.max_length {
+                    return Interception::Proceed;
+                }
+
+                let reason = format!(
+                    "the welcome message is {length} characters long, at most {} are allowed",
+                    self.max_length
+                );
+                return command_rejected(command, reason);
+            }
+            _ => return Interception::Proceed,
+        };
+
+        let length = cmd.content.chars().count();
+        let max_length = if cmd.content.starts_with(ENCRYPTED_CONTENT_PREFIX) {
+            command::max_encrypted_length(self.max_length)
+        } else {
+            self.max_length
+        };
+        if length <= max_length {
+            return Interception::Proceed;
+        }
+
+        Interception::Reject(Event::MessageRejected(event::MessageRejectedReplyEvent {
+            room: cmd.room.clone(),
+            reason: event::MessageRejectionReason::TooLong { length, max_length },
+            nonce: cmd.nonce.clone(),
+        }))
+    }
+}
+
+/// Rejects the messages blocked by a filter of their room, e.g. a moderation plugin of the operator
+struct MessageFilterGuard;
+
+impl CommandInterceptor for MessageFilterGuard {
+    fn intercept(&mut self, origin: &CommandOrigin, command: &mut UserCommand) -> Interception {
+        let UserCommand::SendMessage(cmd) = command else {
+            return Interception::Proceed;
+        };
+
+        // the server can not read the messages of encrypted rooms
+        if origin.context.room_manager.is_encrypted(&cmd.room)
+            || origin.context.message_filters.allows(
+                &cmd.room,
+                &origin.session_and_user_id.user_id,
+                &cmd.content,
+            )
+        {
+            return Interception::Proceed;
+        }
+
+        Interception::Reject(Event::MessageRejected(event::MessageRejectedReplyEvent {
+            room: cmd.room.clone(),
+            reason: event::MessageRejectionReason::Blocked,
+            nonce: cmd.nonce.clone(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use comms::command::{SendMessageCommand, SetWelcomeMessageCommand};
+
+    use super::*;
+    use crate::{
+        archival::RoomArchive,
+        auth::Authenticator,
+        chaos::Chaos,
+        directory::UserDirectory,
+        groups::Groups,
+        invites::RoomInvites,
+        message_filters::MessageFilters,
+        moderation::ModerationQueue,
+        read_markers::ReadMarkers,
+        reminders::Reminders,
+        room_manager::RoomManagerBuilder,
+        session::{CommandHistory, DuplicateLoginPolicy, SessionRegistry},
+        storage::Storage,
+    };
+
+    const MAX_MESSAGE_LENGTH: usize = 8;
+
+    /// The context of a server without any rooms, its documents are kept in a fresh directory
+    async fn server_context() -> ServerContext {
+        let storage = Storage::new(
+            std::env::temp_dir().join(format!("chat-interceptors-{}", nanoid::nanoid!())),
+        )
+        .unwrap();
+
+        ServerContext {
+            authenticator: Authenticator::load(storage.clone()).await.unwrap(),
+            room_manager: RoomManagerBuilder::new().build(),
+            session_registry: SessionRegistry::new(),
+            reminders: Reminders::load(storage.clone()).await.unwrap(),
+            groups: Groups::load(storage.clone()).await.unwrap(),
+            read_markers: ReadMarkers::load(storage.clone()).await.unwrap(),
+            moderation_queue: ModerationQueue::load(storage.clone()).await.unwrap(),
+            user_directory: UserDirectory::load(storage.clone()).await.unwrap(),
+            room_archive: RoomArchive::load(storage.clone()).await.unwrap(),
+            room_invites: RoomInvites::load(storage).await.unwrap(),
+            message_filters: MessageFilters::default(),
+            mirror: None,
+            chaos: Chaos::default(),
+            command_history: CommandHistory::new(),
+            duplicate_login_policy: DuplicateLoginPolicy::Allow,
+            redirect_addr: None,
+            admins: Default::default(),
+        }
+    }
+
+    fn session() -> SessionAndUserId {
+        SessionAndUserId {
+            session_id: String::from("session"),
+            user_id: String::from("alice"),
+        }
+    }
+
+    fn intercept(
+        context: &ServerContext,
+        bot_scope: Option<&BotScope>,
+        mut command: UserCommand,
+    ) -> Interception {
+        let session_and_user_id = session();
+        let origin = CommandOrigin {
+            session_and_user_id: &session_and_user_id,
+            bot_scope,
+            is_guest: false,
+            context,
+        };
+
+        session_interceptors(MAX_MESSAGE_LENGTH).intercept(&origin, &mut command)
+    }
+
+    fn set_welcome_message(message: &str) -> UserCommand {
+        UserCommand::SetWelcomeMessage(SetWelcomeMessageCommand {
+            room: String::from("general"),
+            message: Some(String::from(message)),
+        })
+    }
+
+    #[tokio::test]
+    async fn welcome_messages_are_held_to_the_message_length_limit() {
+        let context = server_context().await;
+
+        assert!(matches!(
+            intercept(&context, None, set_welcome_message("welcome")),
+            Interception::Proceed
+        ));
+        assert!(matches!(
+            intercept(&context, None, set_welcome_message("welcome everyone")),
+            Interception::Reject(Event::CommandRejected(event))
+                if event.command == "set_welcome_message"
+        ));
+    }
+
+    fn send_message(room: &str) -> UserCommand {
+        send_content(room, "hi")
+    }
+
+    fn send_content(room: &str, content: &str) -> UserCommand {
+        UserCommand::SendMessage(SendMessageCommand {
+            room: String::from(room),
+            content: String::from(content),
+            content_type: event::ContentType::Plain,
+            nonce: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn encrypted_messages_are_held_to_the_length_of_their_ciphertext() {
+        let context = server_context().await;
+        let max_length = command::max_encrypted_length(MAX_MESSAGE_LENGTH);
+        let ciphertext = "A".repeat(max_length - ENCRYPTED_CONTENT_PREFIX.len());
+
+        assert!(matches!(
+            intercept(
+                &context,
+                None,
+                send_content(
+                    "general",
+                    &format!("{ENCRYPTED_CONTENT_PREFIX}{ciphertext}")
+                )
+            ),
+            Interception::Proceed
+        ));
+        assert!(matches!(
+            intercept(
+                &context,
+                None,
+                send_content("general", &format!("{ENCRYPTED_CONTENT_PREFIX}{ciphertext}A"))
+            ),
+            Interception::Reject(Event::MessageRejected(event::MessageRejectedReplyEvent {
+                reason: event::MessageRejectionReason::TooLong { max_length: limit, .. },
+                ..
+            })) if limit == max_length
+        ));
+    }
+
+    #[tokio::test]
+    async fn bots_only_send_messages_to_the_rooms_of_their_scope() {
+        let context = server_context().await;
+        let bot_scope = BotScope {
+            rooms: Some(vec![String::from("ops")]),
+            read_only: false,
+        };
+
+        assert!(matches!(
+            intercept(&context, Some(&bot_scope), send_message("ops")),
+            Interception::Proceed
+        ));
+        // a webhook posts without joining, so the message itself is checked
+        assert!(matches!(
+            intercept(&context, Some(&bot_scope), send_message("general")),
+            Interception::Reject(Event::MessageRejected(event::MessageRejectedReplyEvent {
+                reason: event::MessageRejectionReason::NotPermitted,
+                ..
+            }))
+        ));
+        assert!(matches!(
+            intercept(
+                &context,
+                Some(&BotScope::default()),
+                send_message("general")
+            ),
+            Interception::Proceed
+        ));
+    }
+}
@@ -0,0 +1,35 @@
+/// [DuplicateLoginPolicy] decides what happens when a user logs in while they already have a session
+///
+/// Read from the `CHAT_DUPLICATE_LOGIN` environment variable, one of `allow`, `replace` or `reject`.
+/// Users may have several sessions at once if the variable is not set.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum DuplicateLoginPolicy {
+    /// Every login opens another session, e.g. one per device
+    #[default]
+    Allow,
+    /// The new session takes over, the previous sessions are closed with a `SessionReplaced` event
+    Replace,
+    /// The new login is rejected as long as the user has a session
+    Reject,
+}
+
+impl DuplicateLoginPolicy {
+    pub fn from_env() -> anyhow::Result<Self> {
+        match std::env::var("CHAT_DUPLICATE_LOGIN") {
+            Ok(policy) => Self::parse(&policy),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    fn parse(policy: &str) -> anyhow::Result<Self> {
+        match policy.trim().to_lowercase().as_str() {
+            "allow" => Ok(DuplicateLoginPolicy::Allow),
+            "replace" => Ok(DuplicateLoginPolicy::Replace),
+            "reject" => Ok(DuplicateLoginPolicy::Reject),
+            _ => anyhow::bail!(
+                "unknown duplicate login policy '{}', expected allow, replace or reject",
+                policy
+            ),
+        }
+    }
+}
@@ -1,88 +1,487 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Context;
 
 use comms::{
-    command::UserCommand,
-    event::{self, RoomDetail},
-    transport,
+    command::{LoginCommand, UserCommand},
+    event::{self, SessionDetail},
+    transport::{
+        self,
+        server::{CommandStream, EncodedEvent, EventWriter},
+    },
 };
 use nanoid::nanoid;
-use tokio::{net::TcpStream, sync::broadcast};
+use tokio::sync::broadcast;
 use tokio_stream::StreamExt;
 
-use crate::room_manager::RoomManager;
+use crate::{
+    auth::{Identity, GUEST_USER_ID_PREFIX},
+    clock::unix_millis_now,
+    context::ServerContext,
+    logging,
+    names::{self, NameKind},
+};
 
 use self::chat_session::ChatSession;
+pub use self::command_history::CommandHistory;
+pub use self::connection::Connection;
+pub use self::interceptors::{session_interceptors, CommandOrigin, Interception};
+pub use self::login_policy::DuplicateLoginPolicy;
+pub use self::session_registry::{SessionRegistry, UserIdClaim};
 
 mod chat_session;
+mod command_history;
+mod connection;
+mod interceptors;
+mod login_policy;
+mod session_registry;
+
+/// The maximum number of characters a single message can have, communicated to the user at login
+pub const MAX_MESSAGE_LENGTH: usize = 512;
+/// How long a picked name stays with its user once they are gone, whoever picks it later starts from scratch
+const PICKED_NAME_LAPSE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Reads the address the sessions are handed to when the server shuts down from `CHAT_REDIRECT_ADDR`, e.g.
+/// `chat-2.example.com:8080` while migrating to another server. The sessions are simply closed if it is not set.
+pub fn redirect_addr_from_env() -> anyhow::Result<Option<String>> {
+    let Ok(addr) = std::env::var("CHAT_REDIRECT_ADDR") else {
+        return Ok(None);
+    };
+
+    let addr = addr.trim();
+    let is_valid = addr
+        .rsplit_once(':')
+        .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok());
+    anyhow::ensure!(
+        is_valid,
+        "invalid redirect address '{}', expected <host>:<port>",
+        addr
+    );
+
+    Ok(Some(String::from(addr)))
+}
+
+/// Closes the connection as the server shuts down, handing the client to another server if one is configured
+async fn close_for_shutdown(context: &ServerContext, event_writer: &mut EventWriter) {
+    if let Some(addr) = context.redirect_addr.as_ref() {
+        let _ = event_writer
+            .write(&event::Event::Redirect(event::RedirectReplyEvent {
+                addr: addr.clone(),
+            }))
+            .await;
+    }
+    let _ = event_writer.shutdown().await;
+}
+
+/// [OutgoingEvent] is an event on its way to the user
+/// The events of the rooms arrive already serialized, since the same frame is shared by every participant
+#[derive(Debug, Clone)]
+pub enum OutgoingEvent {
+    Event(event::Event),
+    Encoded(EncodedEvent),
+}
+
+impl From<event::Event> for OutgoingEvent {
+    fn from(event: event::Event) -> Self {
+        OutgoingEvent::Event(event)
+    }
+}
+
+/// Writes the event to the user, serializing it first unless it already is
+async fn write_outgoing(
+    event_writer: &mut EventWriter,
+    outgoing_event: &OutgoingEvent,
+) -> anyhow::Result<()> {
+    match outgoing_event {
+        OutgoingEvent::Event(event) => event_writer.write(event).await,
+        OutgoingEvent::Encoded(encoded_event) => event_writer.write_encoded(encoded_event).await,
+    }
+}
+
+/// The number of logins a connection may send before the server gives up on it
+const MAX_LOGIN_ATTEMPTS: usize = 5;
+/// How long a connection may take to log in before the server gives up on it
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Why a login was refused, along with the structured reason if the user id is not a valid name
+type LoginRejection = (String, Option<event::NameRejectionReason>);
+
+/// Tells the client why it can not log in, the client may try again on the same connection
+async fn reject_login(
+    event_writer: &mut EventWriter,
+    (reason, name_rejection): LoginRejection,
+) -> anyhow::Result<()> {
+    event_writer
+        .write(&event::Event::LoginRejected(
+            event::LoginRejectedReplyEvent {
+                reason,
+                name_rejection,
+            },
+        ))
+        .await
+}
+
+/// [Login] is who a connection has logged in as
+struct Login<'a> {
+    identity: Identity,
+    /// Set if the user id is only theirs while they are connected, e.g. a guest or a picked username
+    /// Kept until the session is registered, so nobody else logs in with the same user id meanwhile
+    claim: Option<UserIdClaim<'a>>,
+}
+
+/// Finds out who the user is, refusing the login if they can not be authenticated
+async fn check_login<'a>(
+    context: &'a ServerContext,
+    login: &LoginCommand,
+) -> Result<Login<'a>, LoginRejection> {
+    // Guests are given a random user id, drawn again in the unlikely case that another guest has it
+    if login.guest {
+        loop {
+            let identity = context
+                .authenticator
+                .authenticate_guest()
+                .map_err(|err| (format!("{:#}", err), None))?;
+            if let Some(claim) = context.session_registry.claim(&identity.user_id) {
+                return Ok(Login {
+                    identity,
+                    claim: Some(claim),
+                });
+            }
+        }
+    }
+
+    let identity = context
+        .authenticator
+        .authenticate(login.token.as_deref(), login.username.as_deref())
+        .await
+        .map_err(|err| (format!("{:#}", err), None))?;
+
+    // Bot names are checked when their token is issued, the names users pick when they log in
+    // The identity provider's user ids are taken as they are, the user had no say in them
+    if identity.bot_scope.is_none() && context.authenticator.takes_usernames() {
+        let validation = match names::validate(&identity.user_id, NameKind::UserId) {
+            Ok(()) => {
+                context
+                    .user_directory
+                    .check_confusable(&identity.user_id)
+                    .await
+            }
+            Err(reason) => Err(reason),
+        };
+        if let Err(reason) = validation {
+            return Err((
+                names::rejection_message(&identity.user_id, &reason),
+                Some(reason),
+            ));
+        }
+    }
+
+    // Without an identity provider nobody proves who they are, so nobody may pick the name of a moderator
+    // or a guest, or share a name with someone connected or logging in
+    if identity.bot_scope.is_none() && context.authenticator.takes_usernames() {
+        let claim = if context.is_any_moderator(&identity.user_id)
+            || identity.user_id.starts_with(GUEST_USER_ID_PREFIX)
+        {
+            Err(event::NameRejectionReason::Reserved)
+        } else {
+            context
+                .session_registry
+                .claim(&identity.user_id)
+                .ok_or(event::NameRejectionReason::Taken)
+        };
+
+        return match claim {
+            Ok(claim) => Ok(Login {
+                identity,
+                claim: Some(claim),
+            }),
+            Err(reason) => Err((
+                names::rejection_message(&identity.user_id, &reason),
+                Some(reason),
+            )),
+        };
+    }
+
+    let is_connected = !context
+        .session_registry
+        .sessions_of(&identity.user_id)
+        .is_empty();
+    if is_connected && context.duplicate_login_policy == DuplicateLoginPolicy::Reject {
+        return Err((
+            String::from("you are already logged in from another session"),
+            None,
+        ));
+    }
+
+    Ok(Login {
+        identity,
+        claim: None,
+    })
+}
 
-/// Given a tcp stream and a room manager, handles the user session
-/// until the user quits the session, or the tcp stream is closed for some reason, or the server shuts down
+/// Whether the user who picked the name has not been seen for [PICKED_NAME_LAPSE]
+/// A name nobody has logged in with yet has not lapsed, e.g. an admin may add it to a group beforehand
+async fn has_lapsed(context: &ServerContext, user_id: &str) -> bool {
+    context
+        .user_directory
+        .last_seen_at(user_id)
+        .await
+        .is_some_and(|last_seen_at| {
+            unix_millis_now().saturating_sub(last_seen_at) > PICKED_NAME_LAPSE.as_millis() as u64
+        })
+}
+
+/// Forgets what has been left behind for a picked name whose user has been gone for too long
+/// Whoever picks the name next does not inherit the groups, invites, read markers or reminders of its former user
+async fn forget_lapsed_state(context: &ServerContext, user_id: &str) -> anyhow::Result<()> {
+    context.groups.forget(user_id).await?;
+    context.room_invites.forget(user_id).await?;
+    context.read_markers.forget(user_id).await?;
+    context.reminders.forget(user_id).await
+}
+
+/// Waits for the user to log in, answering every refused login so the user can try again
+/// Returns [None] if the connection is closed, the server shuts down or the user has run out of attempts
+async fn wait_for_login<'a>(
+    context: &'a ServerContext,
+    commands: &mut CommandStream,
+    event_writer: &mut EventWriter,
+    quit_rx: &mut broadcast::Receiver<()>,
+) -> anyhow::Result<Option<Login<'a>>> {
+    for _ in 0..MAX_LOGIN_ATTEMPTS {
+        let rejection = tokio::select! {
+            cmd = commands.next() => match cmd {
+                Some(Ok(UserCommand::Login(login))) => match check_login(context, &login).await {
+                    Ok(login) => return Ok(Some(login)),
+                    Err(rejection) => rejection,
+                },
+                None | Some(Ok(UserCommand::Quit(_))) => return Ok(None),
+                // nothing else is handled before the user has logged in
+                Some(_) => (String::from("log in before sending any other command"), None),
+            },
+            Ok(_) = quit_rx.recv() => {
+                close_for_shutdown(context, event_writer).await;
+                return Ok(None);
+            }
+        };
+
+        reject_login(event_writer, rejection).await?;
+    }
+
+    let _ = event_writer.shutdown().await;
+
+    Ok(None)
+}
+
+/// Given a connection, e.g. a tcp stream, and the server context, handles the user session
+/// until the user quits the session, or the connection is closed for some reason, or the server shuts down
 pub async fn handle_user_session(
-    room_manager: Arc<RoomManager>,
+    context: Arc<ServerContext>,
     mut quit_rx: broadcast::Receiver<()>,
-    stream: TcpStream,
+    stream: impl Connection,
 ) -> anyhow::Result<()> {
-    let session_id = nanoid!();
-    // Generate a random id for the user, since we don't have a login system
-    let user_id = String::from(&nanoid!()[0..5]);
-    // Split the tcp stream into a command stream and an event writer with better ergonomics
-    let (mut commands, mut event_writer) = transport::server::split_tcp_stream(stream);
+    let session_detail = SessionDetail {
+        session_id: nanoid!(),
+        address: stream.peer_address(),
+        connected_at: unix_millis_now(),
+    };
+    // Negotiate the connection options with the client and split the connection
+    // into a command stream and an event writer with better ergonomics
+    let (mut commands, mut event_writer) = transport::server::accept_stream(stream).await?;
+
+    // The user logs in with the first command, the connection is closed if they never manage to in time
+    let login = tokio::time::timeout(
+        LOGIN_TIMEOUT,
+        wait_for_login(&context, &mut commands, &mut event_writer, &mut quit_rx),
+    )
+    .await;
+    let Login { identity, claim } = match login {
+        Ok(login) => match login? {
+            Some(login) => login,
+            None => return Ok(()),
+        },
+        Err(_) => {
+            let _ = event_writer.shutdown().await;
+            return Ok(());
+        }
+    };
+    if claim.is_some() && !identity.is_guest && has_lapsed(&context, &identity.user_id).await {
+        forget_lapsed_state(&context, &identity.user_id).await?;
+    }
+
+    let session_id = session_detail.session_id.clone();
+    let user_id = identity.user_id.clone();
+    let is_guest = identity.is_guest;
+
+    // Replace the sessions the user already has, before the new one is registered alongside them
+    if context.duplicate_login_policy == DuplicateLoginPolicy::Replace {
+        for previous_session in context.session_registry.sessions_of(&user_id) {
+            context.session_registry.terminate(
+                &user_id,
+                &previous_session.session_id,
+                event::Event::SessionReplaced(event::SessionReplacedReplyEvent {
+                    session: session_detail.clone(),
+                }),
+            );
+        }
+    }
+
+    // Create a chat session with the given server context
+    // Chat Session will abstract the user session handling logic for multiple rooms
+    let mut chat_session = ChatSession::new(session_detail, identity, Arc::clone(&context));
+    // the session is registered, nobody else can log in as the user from now on
+    drop(claim);
+    let termination_rx = chat_session
+        .take_termination()
+        .context("termination of a new chat session is always available")?;
+    let termination = async move {
+        match termination_rx.await {
+            Ok(final_event) => final_event,
+            // the session can no longer be terminated remotely
+            Err(_) => std::future::pending().await,
+        }
+    };
+    tokio::pin!(termination);
 
     // Welcoming the user with a login successful event and necessary information about the server
     event_writer
         .write(&event::Event::LoginSuccessful(
             event::LoginSuccessfulReplyEvent {
-                session_id: session_id.clone(),
+                session_id,
+                rooms: context.accessible_rooms(&user_id).await,
+                max_message_length: MAX_MESSAGE_LENGTH,
+                session_count: context.session_registry.sessions_of(&user_id).len(),
                 user_id: user_id.clone(),
-                rooms: room_manager
-                    .chat_room_metadata()
-                    .iter()
-                    .map(|metadata| RoomDetail {
-                        name: metadata.name.clone(),
-                        description: metadata.description.clone(),
-                    })
-                    .collect(),
             },
         ))
         .await?;
 
-    // Create a chat session with the given room manager
-    // Chat Session will abstract the user session handling logic for multiple rooms
-    let mut chat_session = ChatSession::new(&session_id, &user_id, room_manager);
+    context
+        .user_directory
+        .record_seen(&user_id, unix_millis_now())
+        .await?;
 
-    loop {
-        tokio::select! {
-            cmd = commands.next() => match cmd {
-                // If the user closes the tcp stream, or sends a quit cmd
-                // We need to clean up resources in a way that the other users are notified about the user's departure
-                None | Some(Ok(UserCommand::Quit(_))) => {
+    // Catch the new session up with the rooms the user has read on their other devices
+    for (room, last_read_at) in context.read_markers.markers_of(&user_id).await {
+        event_writer
+            .write(&event::Event::ReadStateSynced(
+                event::ReadStateSyncedReplyEvent { room, last_read_at },
+            ))
+            .await?;
+    }
+
+    let outcome = async {
+        loop {
+            tokio::select! {
+                cmd = commands.next() => match cmd {
+                    // If the user closes the tcp stream, or sends a quit cmd
+                    // We need to clean up resources in a way that the other users are notified about the user's departure
+                    None | Some(Ok(UserCommand::Quit(_))) => {
+                        chat_session.leave_all_rooms().await?;
+                        // finish the compression stream, so a client still reading sees the connection end cleanly
+                        let _ = event_writer.shutdown().await;
+                        break;
+                    }
+                    // Handle a valid user command
+                    Some(Ok(cmd)) => match cmd {
+                        // answered right here, so the round trip only measures the connection and not the chat session
+                        UserCommand::Ping(cmd) => {
+                            event_writer.write(&event::Event::Pong(event::PongReplyEvent { sent_at: cmd.sent_at })).await?;
+                        }
+                        // the session has logged in before it got here, and a quit has ended it above
+                        UserCommand::Login(_) | UserCommand::Quit(_) => {}
+                        // For user session related commands, we need to handle them in the chat session
+                        UserCommand::JoinRoom(_)
+                        | UserCommand::SendMessage(_)
+                        | UserCommand::LeaveRoom(_)
+                        | UserCommand::GetHistory(_)
+                        | UserCommand::SetWelcomeMessage(_)
+                        | UserCommand::AddGroupMember(_)
+                        | UserCommand::RemoveGroupMember(_)
+                        | UserCommand::IssueBotToken(_)
+                        | UserCommand::RevokeBotToken(_)
+                        | UserCommand::ListSessions(_)
+                        | UserCommand::DisconnectSession(_)
+                        | UserCommand::MarkRoomRead(_)
+                        | UserCommand::SetPresence(_)
+                        | UserCommand::SetStatusText(_)
+                        | UserCommand::GetUserInfo(_)
+                        | UserCommand::SearchUsers(_)
+                        | UserCommand::GetRoomInfo(_)
+                        | UserCommand::CheckRooms(_)
+                        | UserCommand::ListRooms(_)
+                        | UserCommand::RequestRoomKey(_)
+                        | UserCommand::HoldRoomKey(_)
+                        | UserCommand::ShareRoomKey(_)
+                        | UserCommand::ReportMessage(_)
+                        | UserCommand::ListReports(_)
+                        | UserCommand::ResolveReport(_)
+                        | UserCommand::ApproveJoinRequest(_)
+                        | UserCommand::DenyJoinRequest(_)
+                        | UserCommand::GetBroadcastMetrics(_)
+                        | UserCommand::GetRoomStats(_)
+                        | UserCommand::ArchiveRoom(_)
+                        | UserCommand::ReopenRoom(_)
+                        | UserCommand::UpdateRoomMetadata(_)
+                        | UserCommand::PurgeUser(_)
+                        | UserCommand::GetCommandHistory(_)
+                        | UserCommand::CreateInviteCode(_)
+                        | UserCommand::JoinWithCode(_) => {
+                            chat_session.handle_user_command(cmd).await?;
+                        }
+                    }
+                    _ => {}
+                },
+                // Aggregated events from the chat session are sent to the user
+                Ok(outgoing_event) = chat_session.recv() => {
+                    write_outgoing(&mut event_writer, &outgoing_event).await?;
+                    // the rooms the user may no longer access are left, e.g. once they are removed from a group
+                    if let OutgoingEvent::Event(event::Event::AccessibleRoomsChanged(event)) = &outgoing_event {
+                        chat_session.leave_inaccessible_rooms(&event.rooms).await?;
+                    }
+                }
+                // The user has closed this session from another one of their sessions
+                final_event = &mut termination => {
+                    event_writer.write(&final_event).await?;
                     chat_session.leave_all_rooms().await?;
+                    let _ = event_writer.shutdown().await;
                     break;
                 }
-                // Handle a valid user command
-                Some(Ok(cmd)) => match cmd {
-                    // For user session related commands, we need to handle them in the chat session
-                    UserCommand::JoinRoom(_) | UserCommand::SendMessage(_) | UserCommand::LeaveRoom(_) | UserCommand::GetHistory(_) => {
-                        chat_session.handle_user_command(cmd).await?;
-                    }
-                    _ => {}
+                // If the server is shutting down, we can just close the tcp streams
+                // and exit the session handler. Since the server is shutting down,
+                // we don't need to notify other users about the user's departure or cleanup resources
+                Ok(_) = quit_rx.recv() => {
+                    chat_session.abandon_all_rooms();
+                    close_for_shutdown(&context, &mut event_writer).await;
+                    logging::info("Gracefully shutting down user tcp stream.");
+                    break;
                 }
-                _ => {}
-            },
-            // Aggregated events from the chat session are sent to the user
-            Ok(event) = chat_session.recv() => {
-                event_writer.write(&event).await?;
-            }
-            // If the server is shutting down, we can just close the tcp streams
-            // and exit the session handler. Since the server is shutting down,
-            // we don't need to notify other users about the user's departure or cleanup resources
-            Ok(_) = quit_rx.recv() => {
-                drop(event_writer);
-                println!("Gracefully shutting down user tcp stream.");
-                break;
             }
         }
+
+        anyhow::Ok(())
+    }
+    .await;
+
+    // The loop ends early if the connection breaks down, e.g. while writing to a client which has vanished
+    // The other users still need to be notified about the user's departure
+    if outcome.is_err() {
+        let _ = chat_session.leave_all_rooms().await;
+    }
+
+    // Guests expire along with their session, nothing about them is kept for a later session
+    if is_guest {
+        context.user_directory.forget(&user_id).await?;
+        context.read_markers.forget(&user_id).await?;
+        context.reminders.forget(&user_id).await?;
+        context.command_history.forget(&user_id);
+    } else {
+        // a picked name lapses from the moment its user is gone, not from the moment they logged in
+        context
+            .user_directory
+            .refresh_seen(&user_id, unix_millis_now())
+            .await?;
     }
 
-    Ok(())
+    outcome
 }
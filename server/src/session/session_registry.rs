@@ -0,0 +1,274 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
+
+use comms::event::{self, Event, PresenceStatus, SessionDetail};
+use tokio::sync::{mpsc, oneshot};
+
+use super::OutgoingEvent;
+use crate::room_manager::SessionAndUserId;
+
+#[derive(Debug)]
+struct RegisteredSession {
+    detail: SessionDetail,
+    event_tx: mpsc::Sender<OutgoingEvent>,
+    /// Closes the session remotely with a final event, taken once used
+    terminate_tx: Option<oneshot::Sender<Event>>,
+    presence: PresenceStatus,
+}
+
+/// [SessionRegistry] keeps track of the connected sessions of every user
+///
+/// Allows server wide services to reach a user regardless of the rooms they are in,
+/// and lets the sessions of a user know about each other, e.g. when they connect from another device
+#[derive(Debug, Default)]
+pub struct SessionRegistry {
+    user_id_to_sessions: Mutex<HashMap<String, HashMap<String, RegisteredSession>>>,
+    /// What the connected users are up to in their own words, kept until their last session disconnects
+    user_id_to_status_text: Mutex<HashMap<String, String>>,
+    /// The users someone is logging in as, until their session is registered
+    claimed_user_ids: Mutex<HashSet<String>>,
+}
+
+/// [UserIdClaim] keeps anyone else from logging in as the user until it is dropped, see [SessionRegistry::claim]
+#[derive(Debug)]
+pub struct UserIdClaim<'a> {
+    session_registry: &'a SessionRegistry,
+    user_id: String,
+}
+
+impl Drop for UserIdClaim<'_> {
+    fn drop(&mut self) {
+        self.session_registry
+            .claimed_user_ids
+            .lock()
+            .unwrap()
+            .remove(&self.user_id);
+    }
+}
+
+/// Send an event to the given sessions without waiting, returns the number of sessions it was delivered to
+fn send_to_sessions<'a>(
+    sessions: impl Iterator<Item = &'a RegisteredSession>,
+    event: &Event,
+) -> usize {
+    sessions
+        .filter(|session| session.event_tx.try_send(event.clone().into()).is_ok())
+        .count()
+}
+
+/// A user is online as long as one of their sessions is
+fn presence_of<'a>(mut sessions: impl Iterator<Item = &'a RegisteredSession>) -> PresenceStatus {
+    if sessions.any(|session| session.presence == PresenceStatus::Online) {
+        PresenceStatus::Online
+    } else {
+        PresenceStatus::Away
+    }
+}
+
+impl SessionRegistry {
+    pub fn new() -> Self {
+        SessionRegistry {
+            user_id_to_sessions: Mutex::new(HashMap::new()),
+            user_id_to_status_text: Mutex::new(HashMap::new()),
+            claimed_user_ids: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Claim the user id for a new session, the claim should be kept until the session is registered
+    /// Returns None if the user is connected, or someone else is logging in as them
+    pub fn claim(&self, user_id: &str) -> Option<UserIdClaim<'_>> {
+        let user_id_to_sessions = self.user_id_to_sessions.lock().unwrap();
+        let mut claimed_user_ids = self.claimed_user_ids.lock().unwrap();
+
+        if user_id_to_sessions.contains_key(user_id)
+            || !claimed_user_ids.insert(String::from(user_id))
+        {
+            return None;
+        }
+
+        Some(UserIdClaim {
+            session_registry: self,
+            user_id: String::from(user_id),
+        })
+    }
+
+    /// Register a session alongside the channel used to deliver events to it
+    /// The other sessions of the user are notified about the new device
+    ///
+    /// Returns a receiver which resolves with a final event once the session is terminated remotely
+    pub fn register(
+        &self,
+        session_and_user_id: &SessionAndUserId,
+        detail: SessionDetail,
+        event_tx: mpsc::Sender<OutgoingEvent>,
+    ) -> oneshot::Receiver<Event> {
+        let (terminate_tx, terminate_rx) = oneshot::channel();
+        let mut user_id_to_sessions = self.user_id_to_sessions.lock().unwrap();
+        let sessions = user_id_to_sessions
+            .entry(session_and_user_id.user_id.clone())
+            .or_default();
+
+        send_to_sessions(
+            sessions.values(),
+            &Event::DeviceConnected(event::DeviceConnectedReplyEvent {
+                session: detail.clone(),
+                session_count: sessions.len() + 1,
+            }),
+        );
+
+        sessions.insert(
+            session_and_user_id.session_id.clone(),
+            RegisteredSession {
+                detail,
+                event_tx,
+                terminate_tx: Some(terminate_tx),
+                presence: PresenceStatus::Online,
+            },
+        );
+
+        terminate_rx
+    }
+
+    /// Remove a session, e.g. when the user disconnects
+    /// The remaining sessions of the user are notified about the disconnected device
+    pub fn unregister(&self, session_and_user_id: &SessionAndUserId) {
+        let mut user_id_to_sessions = self.user_id_to_sessions.lock().unwrap();
+
+        if let Some(sessions) = user_id_to_sessions.get_mut(&session_and_user_id.user_id) {
+            if sessions.remove(&session_and_user_id.session_id).is_none() {
+                return;
+            }
+
+            if sessions.is_empty() {
+                user_id_to_sessions.remove(&session_and_user_id.user_id);
+                self.user_id_to_status_text
+                    .lock()
+                    .unwrap()
+                    .remove(&session_and_user_id.user_id);
+            } else {
+                send_to_sessions(
+                    sessions.values(),
+                    &Event::DeviceDisconnected(event::DeviceDisconnectedReplyEvent {
+                        session_id: session_and_user_id.session_id.clone(),
+                        session_count: sessions.len(),
+                    }),
+                );
+            }
+        }
+    }
+
+    /// The connected sessions of a user, ordered by their connection time
+    pub fn sessions_of(&self, user_id: &str) -> Vec<SessionDetail> {
+        let mut sessions = self
+            .user_id_to_sessions
+            .lock()
+            .unwrap()
+            .get(user_id)
+            .map(|sessions| {
+                sessions
+                    .values()
+                    .map(|session| session.detail.clone())
+                    .collect::<Vec<SessionDetail>>()
+            })
+            .unwrap_or_default();
+
+        sessions.sort_by_key(|session| session.connected_at);
+
+        sessions
+    }
+
+    /// The ids of the users with at least one connected session
+    pub fn connected_user_ids(&self) -> Vec<String> {
+        self.user_id_to_sessions
+            .lock()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    /// Close a session of the user, which receives the given event as its last one
+    /// Returns false if the user has no such session
+    pub fn terminate(&self, user_id: &str, session_id: &str, final_event: Event) -> bool {
+        self.user_id_to_sessions
+            .lock()
+            .unwrap()
+            .get_mut(user_id)
+            .and_then(|sessions| sessions.get_mut(session_id))
+            .and_then(|session| session.terminate_tx.take())
+            .map(|terminate_tx| terminate_tx.send(final_event).is_ok())
+            .unwrap_or(false)
+    }
+
+    /// Change the presence of a session, e.g. when the user switches away from its terminal
+    /// Returns the new presence of the user if it has changed, considering all of their sessions
+    pub fn set_presence(
+        &self,
+        session_and_user_id: &SessionAndUserId,
+        status: PresenceStatus,
+    ) -> Option<PresenceStatus> {
+        let mut user_id_to_sessions = self.user_id_to_sessions.lock().unwrap();
+        let sessions = user_id_to_sessions.get_mut(&session_and_user_id.user_id)?;
+
+        let previous = presence_of(sessions.values());
+        sessions.get_mut(&session_and_user_id.session_id)?.presence = status;
+        let current = presence_of(sessions.values());
+
+        (previous != current).then_some(current)
+    }
+
+    /// The presence of a user considering all of their sessions, not set if they are offline
+    pub fn presence_of_user(&self, user_id: &str) -> Option<PresenceStatus> {
+        self.user_id_to_sessions
+            .lock()
+            .unwrap()
+            .get(user_id)
+            .map(|sessions| presence_of(sessions.values()))
+    }
+
+    /// Change the status text of a user, None removes it
+    /// Returns false if the status text is the same as before
+    pub fn set_status_text(&self, user_id: &str, text: Option<String>) -> bool {
+        let mut user_id_to_status_text = self.user_id_to_status_text.lock().unwrap();
+
+        match text {
+            Some(text) => {
+                user_id_to_status_text.insert(String::from(user_id), text.clone()) != Some(text)
+            }
+            None => user_id_to_status_text.remove(user_id).is_some(),
+        }
+    }
+
+    /// The status text of a user, not set if they have none
+    pub fn status_text_of(&self, user_id: &str) -> Option<String> {
+        self.user_id_to_status_text
+            .lock()
+            .unwrap()
+            .get(user_id)
+            .cloned()
+    }
+
+    /// Send an event to a single session of a user without waiting
+    /// Returns false if the user has no such session or it could not take the event
+    pub fn send_to_session(&self, user_id: &str, session_id: &str, event: &Event) -> bool {
+        self.user_id_to_sessions
+            .lock()
+            .unwrap()
+            .get(user_id)
+            .and_then(|sessions| sessions.get(session_id))
+            .is_some_and(|session| session.event_tx.try_send(event.clone().into()).is_ok())
+    }
+
+    /// Send an event to all the sessions of a user without waiting
+    /// Returns the number of sessions the event was delivered to
+    pub fn send_to_user(&self, user_id: &str, event: &Event) -> usize {
+        self.user_id_to_sessions
+            .lock()
+            .unwrap()
+            .get(user_id)
+            .map(|sessions| send_to_sessions(sessions.values(), event))
+            .unwrap_or(0)
+    }
+}
@@ -0,0 +1,207 @@
+use std::process::Command;
+
+use anyhow::Context;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+/// The length of the nonce preceding every ciphertext
+const NONCE_LENGTH: usize = 12;
+/// Precedes the contents of an encrypted file, the files written without a key are plain json
+const ENCRYPTED_FILE_HEADER: &[u8] = b"chat-encrypted-v1\n";
+/// Binds the keys derived from the data key to their purpose
+const DERIVATION_INFO: &str = "rust-chat-server at rest";
+
+/// [DataKey] encrypts the state the server persists, so a leaked data directory does not expose it
+///
+/// Every document and every room log is encrypted with ChaCha20-Poly1305 under its own key, derived from the
+/// configured one with HKDF-SHA256, see [DataKey::derive].
+#[derive(Clone)]
+pub struct DataKey {
+    key: Key,
+}
+
+impl std::fmt::Debug for DataKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // the key itself never ends up in the logs
+        f.write_str("DataKey(..)")
+    }
+}
+
+impl DataKey {
+    /// Reads the key from `CHAT_DATA_KEY`, 32 base64 encoded bytes, or runs `CHAT_DATA_KEY_COMMAND` which prints it,
+    /// e.g. a command fetching it from a key management service. The data is not encrypted if neither is set.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let encoded = match (
+            std::env::var("CHAT_DATA_KEY"),
+            std::env::var("CHAT_DATA_KEY_COMMAND"),
+        ) {
+            (Ok(encoded), _) => encoded,
+            (Err(_), Ok(command)) => run_key_command(&command)?,
+            (Err(_), Err(_)) => return Ok(None),
+        };
+
+        DataKey::decode(encoded.trim()).map(Some)
+    }
+
+    /// Parse a base64 encoded key of 32 bytes
+    pub fn decode(encoded: &str) -> anyhow::Result<Self> {
+        let bytes = STANDARD
+            .decode(encoded)
+            .context("the data key is not valid base64")?;
+        anyhow::ensure!(
+            bytes.len() == 32,
+            "the data key must be 32 bytes long, it is {} bytes long",
+            bytes.len()
+        );
+
+        Ok(DataKey {
+            key: *Key::from_slice(&bytes),
+        })
+    }
+
+    /// The key of a single document or room, e.g. `room general`, none of them share a key
+    pub fn derive(&self, purpose: &str) -> DataKey {
+        let mut key = Key::default();
+        Hkdf::<Sha256>::new(None, &self.key)
+            .expand(
+                format!("{} {}", DERIVATION_INFO, purpose).as_bytes(),
+                &mut key,
+            )
+            .expect("32 bytes is a valid length for HKDF-SHA256 to expand to");
+
+        DataKey { key }
+    }
+
+    fn seal(&self, plain_text: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = ChaCha20Poly1305::new(&self.key)
+            .encrypt(&nonce, plain_text)
+            .map_err(|_| anyhow::anyhow!("could not encrypt"))?;
+
+        Ok([nonce.as_slice(), &ciphertext].concat())
+    }
+
+    /// Decrypts what [DataKey::seal] has produced
+    fn open(&self, sealed: &[u8]) -> anyhow::Result<Vec<u8>> {
+        anyhow::ensure!(sealed.len() > NONCE_LENGTH, "the ciphertext is too short");
+
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LENGTH);
+        ChaCha20Poly1305::new(&self.key)
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow::anyhow!("could not decrypt, the data key may have changed"))
+    }
+}
+
+/// Runs the command which prints the data key, through the shell so it may be a pipeline
+fn run_key_command(command: &str) -> anyhow::Result<String> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .output()
+        .with_context(|| format!("could not run the data key command '{}'", command))?;
+    anyhow::ensure!(
+        output.status.success(),
+        "the data key command '{}' has failed with {}",
+        command,
+        output.status
+    );
+
+    String::from_utf8(output.stdout).context("the data key command has not printed text")
+}
+
+/// Encrypt the contents of a file if there is a key, the contents are kept as they are otherwise
+pub fn seal_file(data_key: Option<&DataKey>, contents: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    let Some(data_key) = data_key else {
+        return Ok(contents);
+    };
+
+    Ok([ENCRYPTED_FILE_HEADER, &data_key.seal(&contents)?].concat())
+}
+
+/// Decrypt the contents of a file written by [seal_file]
+/// Plain files are returned as they are, e.g. the ones written before the key was configured
+pub fn open_file(data_key: Option<&DataKey>, contents: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    let Some(sealed) = contents.strip_prefix(ENCRYPTED_FILE_HEADER) else {
+        return Ok(contents);
+    };
+
+    data_key
+        .context("the file is encrypted, but no data key is configured")?
+        .open(sealed)
+}
+
+/// Encrypt a line of a log if there is a key, base64 encoded so it stays on its own line
+pub fn seal_line(data_key: Option<&DataKey>, line: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    let Some(data_key) = data_key else {
+        return Ok(line);
+    };
+
+    Ok(STANDARD.encode(data_key.seal(&line)?).into_bytes())
+}
+
+/// Decrypt a line written by [seal_line], without its line break
+/// Lines of plain json are returned as they are, e.g. the ones written before the key was configured
+pub fn open_line(data_key: Option<&DataKey>, line: &str) -> anyhow::Result<Vec<u8>> {
+    if line.starts_with('{') {
+        return Ok(Vec::from(line));
+    }
+
+    let sealed = STANDARD
+        .decode(line)
+        .context("could not decode the encrypted line")?;
+
+    data_key
+        .context("the line is encrypted, but no data key is configured")?
+        .open(&sealed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data_key() -> DataKey {
+        DataKey::decode(&STANDARD.encode([7; 32])).unwrap()
+    }
+
+    #[test]
+    fn test_sealed_files_and_lines_open_with_the_same_key_only() {
+        let data_key = data_key().derive("document groups");
+
+        let sealed = seal_file(Some(&data_key), Vec::from("{\"secret\":1}")).unwrap();
+        assert!(!String::from_utf8_lossy(&sealed).contains("secret"));
+        assert_eq!(
+            open_file(Some(&data_key), sealed.clone()).unwrap(),
+            b"{\"secret\":1}"
+        );
+        assert!(open_file(Some(&data_key.derive("other")), sealed.clone()).is_err());
+        assert!(open_file(None, sealed).is_err());
+
+        let sealed = seal_line(Some(&data_key), Vec::from("{\"secret\":1}")).unwrap();
+        assert!(!sealed.contains(&b'\n'));
+        let sealed = String::from_utf8(sealed).unwrap();
+        assert_eq!(
+            open_line(Some(&data_key), &sealed).unwrap(),
+            b"{\"secret\":1}"
+        );
+        assert!(open_line(None, &sealed).is_err());
+    }
+
+    #[test]
+    fn test_plain_files_and_lines_are_read_as_they_are() {
+        let data_key = data_key();
+
+        assert_eq!(open_file(Some(&data_key), Vec::from("{}")).unwrap(), b"{}");
+        assert_eq!(open_line(Some(&data_key), "{}").unwrap(), b"{}");
+    }
+
+    #[test]
+    fn test_keys_must_be_32_bytes_long() {
+        assert!(DataKey::decode(&STANDARD.encode([7; 16])).is_err());
+        assert!(DataKey::decode("not base64!").is_err());
+    }
+}
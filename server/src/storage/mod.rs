@@ -0,0 +1,116 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::clock::unix_millis_now;
+
+pub use self::encryption::DataKey;
+pub(crate) use self::encryption::{open_file, open_line, seal_file, seal_line};
+
+mod encryption;
+
+/// The document written by [Storage::probe], it holds nothing the server uses
+const PROBE_DOCUMENT: &str = "healthcheck";
+
+/// [Storage] persists server state as json documents inside a data directory
+///
+/// Every document is written to a temporary file first and renamed afterwards,
+/// hence a crash while saving never leaves a half written document behind.
+/// The documents are encrypted if a [DataKey] is configured, see [Storage::with_data_key].
+#[derive(Debug, Clone)]
+pub struct Storage {
+    data_dir: PathBuf,
+    data_key: Option<DataKey>,
+}
+
+impl Storage {
+    /// Create a storage backed by the given directory, creating the directory if necessary
+    pub fn new(data_dir: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let data_dir = data_dir.into();
+        std::fs::create_dir_all(&data_dir)
+            .with_context(|| format!("could not create the data directory {:?}", data_dir))?;
+
+        Ok(Storage {
+            data_dir,
+            data_key: None,
+        })
+    }
+
+    /// Encrypt the documents saved from now on with the key, the plain documents saved before are still read
+    pub fn with_data_key(mut self, data_key: Option<DataKey>) -> Self {
+        self.data_key = data_key;
+        self
+    }
+
+    /// The key the data is encrypted with at rest, if it is
+    pub fn data_key(&self) -> Option<&DataKey> {
+        self.data_key.as_ref()
+    }
+
+    fn document_key(&self, name: &str) -> Option<DataKey> {
+        self.data_key
+            .as_ref()
+            .map(|data_key| data_key.derive(&format!("document {}", name)))
+    }
+
+    /// A directory inside the data directory for state which does not fit a document, created if necessary
+    pub fn subdirectory(&self, name: &str) -> anyhow::Result<PathBuf> {
+        let dir = self.data_dir.join(name);
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("could not create the directory {:?}", dir))?;
+
+        Ok(dir)
+    }
+
+    fn document_path(&self, name: &str) -> PathBuf {
+        self.data_dir.join(format!("{}.json", name))
+    }
+
+    /// Load a document, returns the default value if the document was never saved
+    pub async fn load<T: DeserializeOwned + Default>(&self, name: &str) -> anyhow::Result<T> {
+        let path = self.document_path(name);
+
+        match tokio::fs::read(&path).await {
+            Ok(bytes) => open_file(self.document_key(name).as_ref(), bytes)
+                .and_then(|bytes| Ok(serde_json::from_slice(&bytes)?))
+                .with_context(|| format!("could not parse the document {:?}", path)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+            Err(err) => Err(err).with_context(|| format!("could not read the document {:?}", path)),
+        }
+    }
+
+    /// Save and load back a throwaway document, e.g. for a health check
+    /// Fails if the data directory can not be written to or read from
+    pub async fn probe(&self) -> anyhow::Result<()> {
+        let written = unix_millis_now();
+        self.save(PROBE_DOCUMENT, &written).await?;
+        let read: u64 = self.load(PROBE_DOCUMENT).await?;
+        anyhow::ensure!(
+            read == written,
+            "the probe document {:?} was not read back as written",
+            self.document_path(PROBE_DOCUMENT)
+        );
+
+        Ok(())
+    }
+
+    /// Save a document, replacing the previous version of it
+    pub async fn save<T: Serialize>(&self, name: &str, value: &T) -> anyhow::Result<()> {
+        let path = self.document_path(name);
+        let tmp_path = path.with_extension("json.tmp");
+
+        let contents = seal_file(
+            self.document_key(name).as_ref(),
+            serde_json::to_vec_pretty(value)?,
+        )?;
+        tokio::fs::write(&tmp_path, contents)
+            .await
+            .with_context(|| format!("could not write the document {:?}", tmp_path))?;
+        tokio::fs::rename(&tmp_path, &path)
+            .await
+            .with_context(|| format!("could not replace the document {:?}", path))?;
+
+        Ok(())
+    }
+}
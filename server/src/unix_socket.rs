@@ -0,0 +1,76 @@
+use std::{
+    os::unix::fs::FileTypeExt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use tokio::{net::UnixListener, sync::broadcast, task::JoinSet};
+
+use crate::{context::ServerContext, session};
+
+/// Reads the path of the Unix domain socket to accept sessions on from the `CHAT_UNIX_SOCKET` environment variable
+/// Returns None if it is not set, the server only listens on TCP then
+pub fn path_from_env() -> Option<PathBuf> {
+    std::env::var("CHAT_UNIX_SOCKET")
+        .ok()
+        .filter(|path| !path.trim().is_empty())
+        .map(PathBuf::from)
+}
+
+/// Binds the Unix domain socket at the path
+///
+/// A socket file left behind by a server which did not shut down gracefully is replaced,
+/// but neither a socket another server still listens on nor any other kind of file is.
+pub fn bind(path: &Path) -> anyhow::Result<UnixListener> {
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            anyhow::bail!("{} exists and is not a socket", path.display());
+        }
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            anyhow::bail!("{} is already in use", path.display());
+        }
+        std::fs::remove_file(path)
+            .with_context(|| format!("could not remove the stale socket {}", path.display()))?;
+    }
+
+    UnixListener::bind(path).with_context(|| format!("could not bind to {}", path.display()))
+}
+
+/// Accepts user sessions over the Unix domain socket until the server shuts down
+///
+/// Lets local bots, or a reverse proxy on the same host, connect without going through the network.
+/// The sessions are held exactly like the ones over TCP, including the handshake and the authentication.
+/// The socket file is removed once the sessions accepted over it have ended.
+pub async fn serve_until_quit(
+    context: Arc<ServerContext>,
+    listener: UnixListener,
+    mut quit_rx: broadcast::Receiver<()>,
+) -> anyhow::Result<()> {
+    let mut sessions: JoinSet<anyhow::Result<()>> = JoinSet::new();
+
+    loop {
+        tokio::select! {
+            _ = quit_rx.recv() => break,
+            Ok((stream, _)) = listener.accept() => {
+                sessions.spawn(session::handle_user_session(
+                    Arc::clone(&context),
+                    quit_rx.resubscribe(),
+                    stream,
+                ));
+            }
+        }
+    }
+
+    while sessions.join_next().await.is_some() {}
+
+    if let Some(path) = listener
+        .local_addr()
+        .ok()
+        .and_then(|addr| addr.as_pathname().map(PathBuf::from))
+    {
+        let _ = std::fs::remove_file(path);
+    }
+
+    Ok(())
+}
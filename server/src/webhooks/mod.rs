@@ -0,0 +1,182 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use anyhow::Context;
+use axum::{
+    extract::{Path, State},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use comms::{
+    command::{self, UserCommand},
+    event::ContentType,
+};
+use nanoid::nanoid;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::{sync::broadcast, task::JoinSet};
+
+use crate::{
+    context::ServerContext,
+    listener,
+    names::{self, NameKind},
+    room_manager::SessionAndUserId,
+    session::{self, CommandOrigin, Interception},
+};
+
+/// Reads the port to accept webhooks on from the `CHAT_WEBHOOK_PORT` environment variable
+/// Returns None if it is not set, webhooks are disabled then
+pub fn port_from_env() -> anyhow::Result<Option<u16>> {
+    std::env::var("CHAT_WEBHOOK_PORT")
+        .ok()
+        .map(|port| {
+            port.trim()
+                .parse()
+                .with_context(|| format!("invalid webhook port '{}'", port))
+        })
+        .transpose()
+}
+
+/// The body of a message posted through a webhook
+#[derive(Debug, Deserialize)]
+struct PostMessageRequest {
+    content: String,
+    /// Shown instead of the user id of the bot, e.g. the name of the alerting system
+    #[serde(default)]
+    display_name: Option<String>,
+}
+
+/// Accepts messages posted over HTTP until the server shuts down
+///
+/// Lets integrations such as alerting systems post to a room without keeping a connection open.
+/// Every request is authenticated with the token of a bot, and the message passes the same
+/// checks as the messages of a session of the bot, e.g. read-only bots can not post.
+/// Webhooks are accepted on the same addresses as the sessions, see [listener::bind].
+pub async fn serve_until_quit(
+    context: Arc<ServerContext>,
+    bind_addrs: Vec<SocketAddr>,
+    quit_rx: broadcast::Receiver<()>,
+) -> anyhow::Result<()> {
+    let app = Router::new()
+        .route("/rooms/{room}/messages", post(post_message))
+        .with_state(context);
+    let listeners = listener::bind(&bind_addrs).context("could not bind to the webhook port")?;
+
+    let mut servers = JoinSet::new();
+    for listener in listeners {
+        let mut quit_rx = quit_rx.resubscribe();
+        let server = axum::serve(listener, app.clone()).with_graceful_shutdown(async move {
+            let _ = quit_rx.recv().await;
+        });
+        servers.spawn(async move { server.await });
+    }
+    while let Some(result) = servers.join_next().await {
+        result?.context("could not serve the webhooks")?;
+    }
+
+    Ok(())
+}
+
+fn error_response(status: StatusCode, reason: impl Into<String>) -> Response {
+    (status, Json(json!({ "error": reason.into() }))).into_response()
+}
+
+async fn post_message(
+    State(context): State<Arc<ServerContext>>,
+    Path(room): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<PostMessageRequest>,
+) -> Response {
+    let bearer_token = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    // only bots may post through webhooks, without a token anyone would be given an anonymous identity
+    let identity = match context.authenticator.authenticate(bearer_token, None).await {
+        Ok(identity) if identity.bot_scope.is_some() => identity,
+        _ => return error_response(StatusCode::UNAUTHORIZED, "a valid bot token is required"),
+    };
+
+    if !context.can_access_room(&room, &identity.user_id).await {
+        return error_response(
+            StatusCode::NOT_FOUND,
+            format!("there is no room named '{}'", room),
+        );
+    }
+
+    // the server can not encrypt for the members of an encrypted room
+    if context.room_manager.is_encrypted(&room) {
+        return error_response(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("'{}' is end-to-end encrypted", room),
+        );
+    }
+
+    let display_name = request
+        .display_name
+        .map(|display_name| display_name.trim().to_string())
+        .filter(|display_name| !display_name.is_empty());
+    if let Some(display_name) = display_name.as_ref() {
+        // the display name is shown instead of the bot name, it can not pass for the staff of the server
+        if let Err(reason) = names::validate(display_name, NameKind::DisplayName) {
+            let error = names::rejection_message(display_name, &reason);
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(json!({ "error": error, "name_rejection": reason })),
+            )
+                .into_response();
+        }
+    }
+
+    if request.content.trim().is_empty() {
+        return error_response(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "the message can not be empty",
+        );
+    }
+
+    // the request is handled like a command of a short lived session of the bot
+    let session_and_user_id = SessionAndUserId {
+        session_id: format!("webhook-{}", nanoid!()),
+        user_id: identity.user_id,
+    };
+    let mut command = UserCommand::SendMessage(command::SendMessageCommand {
+        room,
+        content: request.content,
+        content_type: ContentType::Plain,
+        nonce: None,
+    });
+    let origin = CommandOrigin {
+        session_and_user_id: &session_and_user_id,
+        bot_scope: identity.bot_scope.as_ref(),
+        is_guest: identity.is_guest,
+        context: &context,
+    };
+    if let Interception::Reject(event) =
+        session::session_interceptors(session::MAX_MESSAGE_LENGTH).intercept(&origin, &mut command)
+    {
+        return (StatusCode::UNPROCESSABLE_ENTITY, Json(event)).into_response();
+    }
+    let UserCommand::SendMessage(command) = command else {
+        return error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "the message has been rewritten into another command",
+        );
+    };
+
+    match context
+        .room_manager
+        .post_message(
+            &command.room,
+            session_and_user_id.user_id,
+            display_name,
+            command.content,
+        )
+        .await
+    {
+        Ok(id) => (StatusCode::CREATED, Json(json!({ "id": id }))).into_response(),
+        Err(err) => error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", err)),
+    }
+}
@@ -0,0 +1,131 @@
+//! A TCP proxy sitting between a client and the server, injecting faults into the traffic it relays
+
+use std::{net::SocketAddr, time::Duration};
+
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// The faults injected into the bytes flowing in one direction, nothing is injected by default
+#[derive(Debug, Clone, Default)]
+pub struct Faults {
+    /// How long every chunk is held back before it is forwarded
+    pub delay: Duration,
+    /// The largest number of bytes forwarded at once, frames are split across several writes if set
+    pub chunk_size: Option<usize>,
+    /// The number of bytes forwarded before both ends of the connection are reset, e.g. in the middle of a frame
+    pub reset_after: Option<usize>,
+}
+
+/// How relaying one direction of a connection has ended
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RelayEnd {
+    /// The sender has closed its side, which has been passed on
+    Closed,
+    /// The connection has to be reset on both ends
+    Reset,
+}
+
+/// [FaultProxy] relays every connection it accepts to the upstream address
+pub struct FaultProxy {
+    addr: SocketAddr,
+}
+
+impl FaultProxy {
+    /// Start relaying connections in the background, with the given faults towards the server and towards the client
+    pub async fn start(upstream: SocketAddr, to_server: Faults, to_client: Faults) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("could not bind the proxy");
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            while let Ok((client, _)) = listener.accept().await {
+                let server = TcpStream::connect(upstream)
+                    .await
+                    .expect("could not connect to the server");
+
+                tokio::spawn(relay(client, server, to_server.clone(), to_client.clone()));
+            }
+        });
+
+        FaultProxy { addr }
+    }
+
+    /// The address clients connect to instead of the server
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+async fn relay(mut client: TcpStream, mut server: TcpStream, to_server: Faults, to_client: Faults) {
+    let is_reset = {
+        let (client_reader, client_writer) = client.split();
+        let (server_reader, server_writer) = server.split();
+        let upstream = relay_direction(client_reader, server_writer, &to_server);
+        let downstream = relay_direction(server_reader, client_writer, &to_client);
+        tokio::pin!(upstream, downstream);
+
+        let (mut is_upstream_closed, mut is_downstream_closed) = (false, false);
+        loop {
+            let end = tokio::select! {
+                end = &mut upstream, if !is_upstream_closed => {
+                    is_upstream_closed = true;
+                    end
+                }
+                end = &mut downstream, if !is_downstream_closed => {
+                    is_downstream_closed = true;
+                    end
+                }
+            };
+
+            if end == RelayEnd::Reset {
+                break true;
+            }
+            if is_upstream_closed && is_downstream_closed {
+                break false;
+            }
+        }
+    };
+
+    if is_reset {
+        // without lingering, closing the sockets sends a reset instead of finishing the streams
+        #[allow(deprecated)]
+        {
+            let _ = client.set_linger(Some(Duration::ZERO));
+            let _ = server.set_linger(Some(Duration::ZERO));
+        }
+    }
+}
+
+async fn relay_direction<R, W>(mut reader: R, mut writer: W, faults: &Faults) -> RelayEnd
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut buf = vec![0; faults.chunk_size.unwrap_or(4096)];
+    let mut remaining = faults.reset_after.unwrap_or(usize::MAX);
+
+    loop {
+        let len = match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => {
+                let _ = writer.shutdown().await;
+                return RelayEnd::Closed;
+            }
+            Ok(len) => len.min(remaining),
+        };
+
+        if !faults.delay.is_zero() {
+            tokio::time::sleep(faults.delay).await;
+        }
+        if writer.write_all(&buf[..len]).await.is_err() || writer.flush().await.is_err() {
+            return RelayEnd::Reset;
+        }
+
+        remaining -= len;
+        if remaining == 0 {
+            return RelayEnd::Reset;
+        }
+    }
+}
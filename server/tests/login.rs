@@ -0,0 +1,359 @@
+//! Logs users in with the name they pick, letting them try another one on the same connection if it is refused,
+//! or as guests with a temporary identity
+
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use comms::{
+    command::{self, UserCommand},
+    event::{ContentType, Event, MessageRejectionReason, NameRejectionReason},
+    handshake::Compression,
+    transport::{
+        self,
+        client::{CommandWriter, EventStream},
+    },
+};
+use server::{
+    archival::RoomArchive,
+    auth::Authenticator,
+    chaos::Chaos,
+    context::ServerContext,
+    directory::UserDirectory,
+    groups::Groups,
+    invites::RoomInvites,
+    message_filters::MessageFilters,
+    moderation::ModerationQueue,
+    read_markers::ReadMarkers,
+    reminders::Reminders,
+    room_manager::{ChatRoomMetadata, RoomManagerBuilder},
+    session::{self, CommandHistory, DuplicateLoginPolicy, SessionRegistry},
+    storage::Storage,
+};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::broadcast,
+};
+use tokio_stream::StreamExt;
+
+const ROOM: &str = "general";
+/// How long a test waits for something to happen before it fails
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The context of a server with a single room and without an identity provider
+async fn server_context(allows_guests: bool) -> Arc<ServerContext> {
+    let storage =
+        Storage::new(std::env::temp_dir().join(format!("chat-login-{}", nanoid::nanoid!())))
+            .unwrap();
+    let mut authenticator = Authenticator::load(storage.clone()).await.unwrap();
+    authenticator.allows_guests = allows_guests;
+    let room_manager = RoomManagerBuilder::new()
+        .create_room(ChatRoomMetadata {
+            name: String::from(ROOM),
+            description: String::new(),
+            category: None,
+            capacity: None,
+            welcome_message: None,
+            owners: Vec::new(),
+            groups: Vec::new(),
+            record_participation: false,
+            encrypted: false,
+            requires_approval: false,
+            broadcast_capacity: None,
+            lag_policy: Default::default(),
+        })
+        .build();
+
+    Arc::new(ServerContext {
+        authenticator,
+        room_manager,
+        session_registry: SessionRegistry::new(),
+        reminders: Reminders::load(storage.clone()).await.unwrap(),
+        groups: Groups::load(storage.clone()).await.unwrap(),
+        read_markers: ReadMarkers::load(storage.clone()).await.unwrap(),
+        moderation_queue: ModerationQueue::load(storage.clone()).await.unwrap(),
+        user_directory: UserDirectory::load(storage.clone()).await.unwrap(),
+        room_archive: RoomArchive::load(storage.clone()).await.unwrap(),
+        room_invites: RoomInvites::load(storage).await.unwrap(),
+        message_filters: MessageFilters::default(),
+        mirror: None,
+        chaos: Chaos::default(),
+        command_history: CommandHistory::new(),
+        duplicate_login_policy: DuplicateLoginPolicy::Allow,
+        redirect_addr: None,
+        admins: Default::default(),
+    })
+}
+
+/// Start a server with a single room and without an identity provider on a free port
+async fn start_server(allows_guests: bool) -> SocketAddr {
+    let context = server_context(allows_guests).await;
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        // kept alive along with the accept loop, the server is never shut down
+        let (_quit_tx, quit_rx) = broadcast::channel::<()>(1);
+        while let Ok((socket, _)) = listener.accept().await {
+            tokio::spawn(session::handle_user_session(
+                Arc::clone(&context),
+                quit_rx.resubscribe(),
+                socket,
+            ));
+        }
+    });
+
+    addr
+}
+
+async fn connect(addr: SocketAddr) -> (EventStream, CommandWriter) {
+    let stream = TcpStream::connect(addr).await.unwrap();
+
+    transport::client::connect_tcp_stream(stream, &[Compression::None])
+        .await
+        .unwrap()
+}
+
+/// Send a login and wait for the answer of the server
+async fn log_in(
+    events: &mut EventStream,
+    commands: &mut CommandWriter,
+    username: Option<&str>,
+) -> Event {
+    send_login(
+        events,
+        commands,
+        command::LoginCommand {
+            username: username.map(String::from),
+            token: None,
+            guest: false,
+        },
+    )
+    .await
+}
+
+async fn log_in_as_guest(events: &mut EventStream, commands: &mut CommandWriter) -> Event {
+    send_login(
+        events,
+        commands,
+        command::LoginCommand {
+            username: None,
+            token: None,
+            guest: true,
+        },
+    )
+    .await
+}
+
+async fn send_login(
+    events: &mut EventStream,
+    commands: &mut CommandWriter,
+    login: command::LoginCommand,
+) -> Event {
+    commands.write(&UserCommand::Login(login)).await.unwrap();
+
+    next_event(events).await
+}
+
+async fn next_event(events: &mut EventStream) -> Event {
+    tokio::time::timeout(TIMEOUT, events.next())
+        .await
+        .expect("the event should have arrived in time")
+        .expect("the connection should still be open")
+        .unwrap()
+}
+
+/// Wait for an event matching the predicate, skipping the others
+async fn expect(events: &mut EventStream, predicate: impl Fn(&Event) -> bool) -> Event {
+    loop {
+        let event = next_event(events).await;
+        if predicate(&event) {
+            return event;
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_users_chat_as_the_name_they_pick() {
+    let addr = start_server(false).await;
+    let (mut events, mut commands) = connect(addr).await;
+
+    let Event::LoginSuccessful(login) = log_in(&mut events, &mut commands, Some("alice")).await
+    else {
+        panic!("the login should have succeeded");
+    };
+    assert_eq!(login.user_id, "alice");
+}
+
+#[tokio::test]
+async fn test_users_log_in_over_an_in_memory_connection() {
+    let context = server_context(false).await;
+    let (_quit_tx, quit_rx) = broadcast::channel::<()>(1);
+    let (client_stream, server_stream) = transport::memory::stream_pair();
+    tokio::spawn(session::handle_user_session(
+        context,
+        quit_rx,
+        server_stream,
+    ));
+    let (mut events, mut commands) =
+        transport::client::connect_stream(client_stream, &[Compression::Deflate])
+            .await
+            .unwrap();
+
+    let Event::LoginSuccessful(login) = log_in(&mut events, &mut commands, Some("alice")).await
+    else {
+        panic!("the login should have succeeded");
+    };
+    assert_eq!(login.user_id, "alice");
+}
+
+#[tokio::test]
+async fn test_server_picks_a_name_if_the_user_does_not() {
+    let addr = start_server(false).await;
+    let (mut events, mut commands) = connect(addr).await;
+
+    let Event::LoginSuccessful(login) = log_in(&mut events, &mut commands, None).await else {
+        panic!("the login should have succeeded");
+    };
+    assert!(!login.user_id.is_empty());
+}
+
+#[tokio::test]
+async fn test_refused_login_can_be_retried_on_the_same_connection() {
+    let addr = start_server(false).await;
+    let (mut alice_events, mut alice_commands) = connect(addr).await;
+    log_in(&mut alice_events, &mut alice_commands, Some("alice")).await;
+
+    let (mut events, mut commands) = connect(addr).await;
+    let Event::LoginRejected(rejection) = log_in(&mut events, &mut commands, Some("alice")).await
+    else {
+        panic!("a name in use should have been refused");
+    };
+    assert_eq!(rejection.name_rejection, Some(NameRejectionReason::Taken));
+
+    let Event::LoginRejected(rejection) = log_in(&mut events, &mut commands, Some("a b")).await
+    else {
+        panic!("an invalid name should have been refused");
+    };
+    assert_eq!(
+        rejection.name_rejection,
+        Some(NameRejectionReason::InvalidCharacter { character: ' ' })
+    );
+
+    let Event::LoginSuccessful(login) = log_in(&mut events, &mut commands, Some("bob")).await
+    else {
+        panic!("the login should have succeeded");
+    };
+    assert_eq!(login.user_id, "bob");
+}
+
+#[tokio::test]
+async fn test_commands_before_the_login_are_refused() {
+    let addr = start_server(false).await;
+    let (mut events, mut commands) = connect(addr).await;
+
+    commands
+        .write(&UserCommand::JoinRoom(command::JoinRoomCommand {
+            room: String::from("general"),
+            wait_if_full: false,
+        }))
+        .await
+        .unwrap();
+    let Some(Ok(Event::LoginRejected(_))) = events.next().await else {
+        panic!("the command should have been refused");
+    };
+
+    let Event::LoginSuccessful(_) = log_in(&mut events, &mut commands, Some("alice")).await else {
+        panic!("the login should have succeeded");
+    };
+}
+
+#[tokio::test]
+async fn test_guests_are_given_a_temporary_identity() {
+    let addr = start_server(true).await;
+    let (mut events, mut commands) = connect(addr).await;
+
+    let Event::LoginSuccessful(login) = log_in_as_guest(&mut events, &mut commands).await else {
+        panic!("the guest should have logged in");
+    };
+    assert!(login.user_id.starts_with("guest-"));
+}
+
+#[tokio::test]
+async fn test_guests_are_refused_unless_the_server_allows_them() {
+    let addr = start_server(false).await;
+    let (mut events, mut commands) = connect(addr).await;
+
+    let Event::LoginRejected(_) = log_in_as_guest(&mut events, &mut commands).await else {
+        panic!("the guest should have been refused");
+    };
+}
+
+#[tokio::test]
+async fn test_picked_names_can_not_pose_as_guests() {
+    let addr = start_server(true).await;
+    let (mut events, mut commands) = connect(addr).await;
+
+    let Event::LoginRejected(rejection) =
+        log_in(&mut events, &mut commands, Some("guest-abcd")).await
+    else {
+        panic!("the name of a guest should have been refused");
+    };
+    assert_eq!(
+        rejection.name_rejection,
+        Some(NameRejectionReason::Reserved)
+    );
+}
+
+#[tokio::test]
+async fn test_guests_can_only_chat_at_a_limited_rate() {
+    let addr = start_server(true).await;
+    let (mut events, mut commands) = connect(addr).await;
+    log_in_as_guest(&mut events, &mut commands).await;
+
+    commands
+        .write(&UserCommand::JoinRoom(command::JoinRoomCommand {
+            room: String::from(ROOM),
+            wait_if_full: false,
+        }))
+        .await
+        .unwrap();
+    expect(&mut events, |event| {
+        matches!(event, Event::UserJoinedRoom(_))
+    })
+    .await;
+
+    commands
+        .write(&UserCommand::SetWelcomeMessage(
+            command::SetWelcomeMessageCommand {
+                room: String::from(ROOM),
+                message: Some(String::from("welcome")),
+            },
+        ))
+        .await
+        .unwrap();
+    expect(&mut events, |event| {
+        matches!(event, Event::CommandRejected(_))
+    })
+    .await;
+
+    for idx in 0..11 {
+        commands
+            .write(&UserCommand::SendMessage(command::SendMessageCommand {
+                room: String::from(ROOM),
+                content: format!("message {idx}"),
+                content_type: ContentType::Plain,
+                nonce: None,
+            }))
+            .await
+            .unwrap();
+    }
+    let Event::MessageRejected(rejection) = expect(&mut events, |event| {
+        matches!(event, Event::MessageRejected(_))
+    })
+    .await
+    else {
+        unreachable!();
+    };
+    assert!(matches!(
+        rejection.reason,
+        MessageRejectionReason::RateLimited { .. }
+    ));
+}
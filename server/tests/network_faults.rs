@@ -0,0 +1,380 @@
+//! Runs the server behind a [FaultProxy] to check that delayed, split and abruptly closed connections are handled
+
+use std::{future::Future, net::SocketAddr, sync::Arc, time::Duration};
+
+use comms::{
+    command::{self, BotScope, UserCommand},
+    event::{self, Event},
+    handshake::{ClientHello, Compression},
+    transport::{
+        self,
+        client::{CommandWriter, EventStream},
+    },
+};
+use server::{
+    archival::RoomArchive,
+    auth::Authenticator,
+    chaos::Chaos,
+    context::ServerContext,
+    directory::UserDirectory,
+    groups::Groups,
+    invites::RoomInvites,
+    message_filters::MessageFilters,
+    moderation::ModerationQueue,
+    read_markers::ReadMarkers,
+    reminders::Reminders,
+    room_manager::{ChatRoomMetadata, RoomManagerBuilder},
+    session::{self, CommandHistory, DuplicateLoginPolicy, SessionRegistry},
+    storage::Storage,
+};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::broadcast,
+};
+use tokio_stream::StreamExt;
+
+use fault_proxy::{FaultProxy, Faults};
+
+mod fault_proxy;
+
+const ROOM: &str = "general";
+/// How long a test waits for something to happen before it fails
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Start a server with a single room on a free port, its state is kept in a fresh temporary directory
+async fn start_server() -> (SocketAddr, Arc<ServerContext>) {
+    let storage = Storage::new(
+        std::env::temp_dir().join(format!("chat-network-faults-{}", nanoid::nanoid!())),
+    )
+    .unwrap();
+    let room_manager = RoomManagerBuilder::new()
+        .create_room(ChatRoomMetadata {
+            name: String::from(ROOM),
+            description: String::new(),
+            category: None,
+            capacity: None,
+            welcome_message: None,
+            owners: Vec::new(),
+            groups: Vec::new(),
+            record_participation: false,
+            encrypted: false,
+            requires_approval: false,
+            broadcast_capacity: None,
+            lag_policy: Default::default(),
+        })
+        .build();
+
+    let context = Arc::new(ServerContext {
+        authenticator: Authenticator::load(storage.clone()).await.unwrap(),
+        room_manager,
+        session_registry: SessionRegistry::new(),
+        reminders: Reminders::load(storage.clone()).await.unwrap(),
+        groups: Groups::load(storage.clone()).await.unwrap(),
+        read_markers: ReadMarkers::load(storage.clone()).await.unwrap(),
+        moderation_queue: ModerationQueue::load(storage.clone()).await.unwrap(),
+        user_directory: UserDirectory::load(storage.clone()).await.unwrap(),
+        room_archive: RoomArchive::load(storage.clone()).await.unwrap(),
+        room_invites: RoomInvites::load(storage).await.unwrap(),
+        message_filters: MessageFilters::default(),
+        mirror: None,
+        chaos: Chaos::default(),
+        command_history: CommandHistory::new(),
+        duplicate_login_policy: DuplicateLoginPolicy::Allow,
+        redirect_addr: None,
+        admins: Default::default(),
+    });
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn({
+        let context = Arc::clone(&context);
+        let (quit_tx, quit_rx) = broadcast::channel::<()>(1);
+
+        async move {
+            // kept alive along with the accept loop, the server is never shut down
+            let _quit_tx = quit_tx;
+            while let Ok((socket, _)) = listener.accept().await {
+                tokio::spawn(session::handle_user_session(
+                    Arc::clone(&context),
+                    quit_rx.resubscribe(),
+                    socket,
+                ));
+            }
+        }
+    });
+
+    (addr, context)
+}
+
+/// A logged in client, along with the user id the server has given it
+struct Client {
+    user_id: String,
+    events: EventStream,
+    commands: CommandWriter,
+}
+
+impl Client {
+    async fn connect(addr: SocketAddr, compression: Compression, token: Option<&str>) -> Self {
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (mut events, mut commands) =
+            transport::client::connect_tcp_stream(stream, &[compression])
+                .await
+                .unwrap();
+        commands.write(&login(token)).await.unwrap();
+
+        let Some(Ok(Event::LoginSuccessful(login))) = events.next().await else {
+            panic!("the client should have logged in");
+        };
+
+        Client {
+            user_id: login.user_id,
+            events,
+            commands,
+        }
+    }
+
+    async fn send(&mut self, command: UserCommand) {
+        self.commands.write(&command).await.unwrap();
+    }
+
+    async fn join(&mut self) {
+        self.send(UserCommand::JoinRoom(command::JoinRoomCommand {
+            room: String::from(ROOM),
+            wait_if_full: false,
+        }))
+        .await;
+        self.expect(|event| matches!(event, Event::UserJoinedRoom(_)))
+            .await;
+    }
+
+    async fn send_message(&mut self, content: &str) {
+        self.send(UserCommand::SendMessage(command::SendMessageCommand {
+            room: String::from(ROOM),
+            content: String::from(content),
+            content_type: event::ContentType::Plain,
+            nonce: None,
+        }))
+        .await;
+    }
+
+    /// Skip the events until one matches, fails if the connection ends or nothing matches in time
+    async fn expect(&mut self, is_expected: impl Fn(&Event) -> bool) -> Event {
+        tokio::time::timeout(TIMEOUT, async {
+            loop {
+                match self.events.next().await {
+                    Some(Ok(event)) if is_expected(&event) => return event,
+                    Some(Ok(_)) => {}
+                    Some(Err(err)) => panic!("could not read an event: {:?}", err),
+                    None => panic!("the connection has ended before the expected event"),
+                }
+            }
+        })
+        .await
+        .expect("the expected event has not arrived in time")
+    }
+
+    /// Wait until the connection has ended, whether cleanly or not
+    async fn expect_end(&mut self) {
+        tokio::time::timeout(TIMEOUT, async {
+            while let Some(Ok(_)) = self.events.next().await {}
+        })
+        .await
+        .expect("the connection should have ended");
+    }
+}
+
+/// Wait until the condition holds, fails if it does not in time
+async fn eventually<F: Future<Output = bool>>(condition: impl Fn() -> F) {
+    tokio::time::timeout(TIMEOUT, async {
+        while !condition().await {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("the condition should have held eventually");
+}
+
+fn is_message(content: &'static str) -> impl Fn(&Event) -> bool {
+    move |event| matches!(event, Event::UserMessage(message) if message.content == content)
+}
+
+fn login(token: Option<&str>) -> UserCommand {
+    UserCommand::Login(command::LoginCommand {
+        username: None,
+        token: token.map(String::from),
+        guest: false,
+    })
+}
+
+/// The number of bytes a plain connection sends before its first command after logging in:
+/// the hello and the login, each followed by a line break
+fn login_len(token: Option<&str>) -> usize {
+    let hello = ClientHello {
+        compression: vec![Compression::None],
+    };
+
+    serde_json::to_vec(&hello).unwrap().len()
+        + 2
+        + serde_json::to_vec(&login(token)).unwrap().len()
+        + 2
+}
+
+/// Reset the connection in the middle of the first command following the join
+fn reset_after_join(token: Option<&str>) -> Faults {
+    let join = serde_json::to_vec(&UserCommand::JoinRoom(command::JoinRoomCommand {
+        room: String::from(ROOM),
+        wait_if_full: false,
+    }))
+    .unwrap();
+
+    Faults {
+        reset_after: Some(login_len(token) + join.len() + 2 + 10),
+        ..Default::default()
+    }
+}
+
+async fn member_count(context: &ServerContext) -> usize {
+    context
+        .room_manager
+        .get_room_info(ROOM)
+        .await
+        .unwrap()
+        .member_count
+}
+
+#[tokio::test]
+async fn frames_split_into_single_bytes_are_reassembled() {
+    let (addr, _) = start_server().await;
+    let single_bytes = Faults {
+        chunk_size: Some(1),
+        ..Default::default()
+    };
+    let proxy = FaultProxy::start(addr, single_bytes.clone(), single_bytes).await;
+
+    for compression in [Compression::None, Compression::Deflate] {
+        let mut client = Client::connect(proxy.addr(), compression, None).await;
+        client.join().await;
+        client.send_message("split into pieces").await;
+
+        client.expect(is_message("split into pieces")).await;
+    }
+}
+
+#[tokio::test]
+async fn delayed_frames_arrive_in_order() {
+    let (addr, _) = start_server().await;
+    let delayed = Faults {
+        delay: Duration::from_millis(20),
+        chunk_size: Some(7),
+        ..Default::default()
+    };
+    let proxy = FaultProxy::start(addr, delayed.clone(), delayed).await;
+
+    let mut client = Client::connect(proxy.addr(), Compression::Deflate, None).await;
+    client.join().await;
+    for content in ["one", "two", "three"] {
+        client.send_message(content).await;
+    }
+
+    for content in ["one", "two", "three"] {
+        client.expect(is_message(content)).await;
+    }
+}
+
+#[tokio::test]
+async fn reset_in_the_middle_of_a_command_removes_the_user_from_the_room() {
+    let (addr, context) = start_server().await;
+    let proxy = FaultProxy::start(addr, reset_after_join(None), Faults::default()).await;
+
+    let mut observer = Client::connect(addr, Compression::None, None).await;
+    observer.join().await;
+    let mut client = Client::connect(proxy.addr(), Compression::None, None).await;
+    client.join().await;
+    assert_eq!(member_count(&context).await, 2);
+
+    client.send_message("this message is cut short").await;
+    client.expect_end().await;
+
+    let left = observer
+        .expect(|event| {
+            matches!(event, Event::RoomParticipation(participation)
+                if participation.status == event::RoomParticipationStatus::Left)
+        })
+        .await;
+    let Event::RoomParticipation(left) = left else {
+        unreachable!()
+    };
+    assert_eq!(left.user_id, client.user_id);
+    eventually(|| async {
+        context
+            .session_registry
+            .sessions_of(&client.user_id)
+            .is_empty()
+    })
+    .await;
+    assert_eq!(member_count(&context).await, 1);
+}
+
+#[tokio::test]
+async fn reset_while_events_are_written_removes_the_user_from_the_room() {
+    let (addr, context) = start_server().await;
+    let to_client = Faults {
+        reset_after: Some(2048),
+        ..Default::default()
+    };
+    let proxy = FaultProxy::start(addr, Faults::default(), to_client).await;
+
+    let mut observer = Client::connect(addr, Compression::None, None).await;
+    observer.join().await;
+    let mut client = Client::connect(proxy.addr(), Compression::Deflate, None).await;
+    client.join().await;
+
+    // the events of the room keep coming while the connection of the client breaks down
+    for idx in 0..200 {
+        observer.send_message(&format!("message {idx}")).await;
+    }
+    client.expect_end().await;
+
+    eventually(|| async { member_count(&context).await == 1 }).await;
+    eventually(|| async {
+        context
+            .session_registry
+            .sessions_of(&client.user_id)
+            .is_empty()
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn reconnecting_after_a_reset_starts_a_fresh_session() {
+    let (addr, context) = start_server().await;
+    let token = context
+        .authenticator
+        .bot_tokens
+        .issue("probe", BotScope::default())
+        .await
+        .unwrap()
+        .token;
+    let proxy = FaultProxy::start(addr, reset_after_join(Some(&token)), Faults::default()).await;
+
+    // the bot token makes the client the same user on both connections, unlike anonymous logins
+    let mut client = Client::connect(proxy.addr(), Compression::None, Some(&token)).await;
+    client.join().await;
+    client.send_message("this message is cut short").await;
+    client.expect_end().await;
+    eventually(|| async {
+        context
+            .session_registry
+            .sessions_of(&client.user_id)
+            .is_empty()
+    })
+    .await;
+
+    let mut reconnected = Client::connect(addr, Compression::None, Some(&token)).await;
+    assert_eq!(reconnected.user_id, client.user_id);
+    reconnected.join().await;
+    reconnected.send_message("back again").await;
+
+    reconnected.expect(is_message("back again")).await;
+    assert_eq!(member_count(&context).await, 1);
+}
@@ -0,0 +1,343 @@
+//! Keeps the users who lose access to a private room out of it, even if they have already joined it
+
+use std::{
+    collections::HashSet,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use comms::{
+    command::{self, BotScope, UserCommand},
+    event::{ContentType, Event, RoomParticipationStatus},
+    handshake::Compression,
+    transport::{
+        self,
+        client::{CommandWriter, EventStream},
+    },
+};
+use server::{
+    archival::RoomArchive,
+    auth::{self, Authenticator},
+    chaos::Chaos,
+    context::ServerContext,
+    directory::UserDirectory,
+    groups::Groups,
+    invites::RoomInvites,
+    message_filters::MessageFilters,
+    moderation::ModerationQueue,
+    read_markers::ReadMarkers,
+    reminders::Reminders,
+    room_manager::{ChatRoomMetadata, RoomManagerBuilder},
+    session::{self, CommandHistory, DuplicateLoginPolicy, SessionRegistry},
+    storage::Storage,
+};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::broadcast,
+};
+use tokio_stream::StreamExt;
+
+const ROOM: &str = "staff";
+const GROUP: &str = "staff";
+/// The bot managing the groups, nobody may pick the name of an admin without an identity provider
+const ADMIN_BOT: &str = "ops";
+/// How long a test waits for something to happen before it fails
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Start a server with a single room granted to a group on a free port
+/// Returns the token of the admin bot managing the group and the context of the server alongside the address
+async fn start_server() -> (SocketAddr, String, Arc<ServerContext>) {
+    let storage =
+        Storage::new(std::env::temp_dir().join(format!("chat-room-access-{}", nanoid::nanoid!())))
+            .unwrap();
+    let room_manager = RoomManagerBuilder::new()
+        .create_room(ChatRoomMetadata {
+            name: String::from(ROOM),
+            description: String::new(),
+            category: None,
+            capacity: None,
+            welcome_message: None,
+            owners: Vec::new(),
+            groups: vec![String::from(GROUP)],
+            record_participation: false,
+            encrypted: false,
+            requires_approval: false,
+            broadcast_capacity: None,
+            lag_policy: Default::default(),
+        })
+        .build();
+    let authenticator = Authenticator::load(storage.clone()).await.unwrap();
+    let admin_token = authenticator
+        .bot_tokens
+        .issue(ADMIN_BOT, BotScope::default())
+        .await
+        .unwrap()
+        .token;
+    let context = Arc::new(ServerContext {
+        authenticator,
+        room_manager,
+        session_registry: SessionRegistry::new(),
+        reminders: Reminders::load(storage.clone()).await.unwrap(),
+        groups: Groups::load(storage.clone()).await.unwrap(),
+        read_markers: ReadMarkers::load(storage.clone()).await.unwrap(),
+        moderation_queue: ModerationQueue::load(storage.clone()).await.unwrap(),
+        user_directory: UserDirectory::load(storage.clone()).await.unwrap(),
+        room_archive: RoomArchive::load(storage.clone()).await.unwrap(),
+        room_invites: RoomInvites::load(storage).await.unwrap(),
+        message_filters: MessageFilters::default(),
+        mirror: None,
+        chaos: Chaos::default(),
+        command_history: CommandHistory::new(),
+        duplicate_login_policy: DuplicateLoginPolicy::Allow,
+        redirect_addr: None,
+        admins: HashSet::from([auth::bot_user_id(ADMIN_BOT)]),
+    });
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server_context = Arc::clone(&context);
+    tokio::spawn(async move {
+        // kept alive along with the accept loop, the server is never shut down
+        let (_quit_tx, quit_rx) = broadcast::channel::<()>(1);
+        while let Ok((socket, _)) = listener.accept().await {
+            tokio::spawn(session::handle_user_session(
+                Arc::clone(&context),
+                quit_rx.resubscribe(),
+                socket,
+            ));
+        }
+    });
+
+    (addr, admin_token, server_context)
+}
+
+/// Connect and log in with the given name, or with the token of a bot
+async fn log_in(addr: SocketAddr, login: command::LoginCommand) -> (EventStream, CommandWriter) {
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (mut events, mut commands) =
+        transport::client::connect_tcp_stream(stream, &[Compression::None])
+            .await
+            .unwrap();
+
+    commands.write(&UserCommand::Login(login)).await.unwrap();
+    expect(&mut events, |event| {
+        matches!(event, Event::LoginSuccessful(_))
+    })
+    .await;
+
+    (events, commands)
+}
+
+/// Wait for an event matching the predicate, skipping the others
+async fn expect(events: &mut EventStream, predicate: impl Fn(&Event) -> bool) -> Event {
+    loop {
+        let event = tokio::time::timeout(TIMEOUT, events.next())
+            .await
+            .expect("the event should have arrived in time")
+            .expect("the connection should still be open")
+            .unwrap();
+        if predicate(&event) {
+            return event;
+        }
+    }
+}
+
+fn username(username: &str) -> command::LoginCommand {
+    command::LoginCommand {
+        username: Some(String::from(username)),
+        token: None,
+        guest: false,
+    }
+}
+
+fn token(token: &str) -> command::LoginCommand {
+    command::LoginCommand {
+        username: None,
+        token: Some(String::from(token)),
+        guest: false,
+    }
+}
+
+async fn join(events: &mut EventStream, commands: &mut CommandWriter) {
+    commands
+        .write(&UserCommand::JoinRoom(command::JoinRoomCommand {
+            room: String::from(ROOM),
+            wait_if_full: false,
+        }))
+        .await
+        .unwrap();
+    expect(events, |event| matches!(event, Event::UserJoinedRoom(_))).await;
+}
+
+async fn change_membership(commands: &mut CommandWriter, user_id: &str, is_member: bool) {
+    let membership = command::GroupMembershipCommand {
+        group: String::from(GROUP),
+        user_id: String::from(user_id),
+    };
+    let command = if is_member {
+        UserCommand::AddGroupMember(membership)
+    } else {
+        UserCommand::RemoveGroupMember(membership)
+    };
+
+    commands.write(&command).await.unwrap();
+}
+
+async fn send_message(commands: &mut CommandWriter, content: &str) {
+    commands
+        .write(&UserCommand::SendMessage(command::SendMessageCommand {
+            room: String::from(ROOM),
+            content: String::from(content),
+            content_type: ContentType::Plain,
+            nonce: None,
+        }))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_users_removed_from_the_group_leave_its_private_rooms() {
+    let (addr, admin_token, _) = start_server().await;
+    let (mut admin_events, mut admin_commands) = log_in(addr, token(&admin_token)).await;
+    let (mut bob_events, mut bob_commands) = log_in(addr, username("bob")).await;
+
+    for user_id in [auth::bot_user_id(ADMIN_BOT).as_str(), "bob"] {
+        change_membership(&mut admin_commands, user_id, true).await;
+    }
+    expect(
+        &mut bob_events,
+        |event| matches!(event, Event::AccessibleRoomsChanged(event) if event.rooms.len() == 1),
+    )
+    .await;
+    join(&mut admin_events, &mut admin_commands).await;
+    join(&mut bob_events, &mut bob_commands).await;
+
+    change_membership(&mut admin_commands, "bob", false).await;
+    expect(
+        &mut bob_events,
+        |event| matches!(event, Event::AccessibleRoomsChanged(event) if event.rooms.is_empty()),
+    )
+    .await;
+    // the others see bob leave the room, and bob gets nothing more of it
+    expect(&mut admin_events, |event| {
+        matches!(event, Event::RoomParticipation(event)
+            if event.user_id == "bob" && event.status == RoomParticipationStatus::Left)
+    })
+    .await;
+    send_message(&mut admin_commands, "staff only").await;
+    expect(&mut admin_events, |event| {
+        matches!(event, Event::UserMessage(_))
+    })
+    .await;
+
+    // bob's own messages no longer reach the room either
+    send_message(&mut bob_commands, "still here?").await;
+    send_message(&mut admin_commands, "done").await;
+    let Event::UserMessage(message) = expect(&mut admin_events, |event| {
+        matches!(event, Event::UserMessage(_))
+    })
+    .await
+    else {
+        unreachable!();
+    };
+    assert_eq!(message.content, "done");
+
+    // bob is still connected, only to what does not concern the room
+    bob_commands
+        .write(&UserCommand::Ping(command::PingCommand { sent_at: 1 }))
+        .await
+        .unwrap();
+    let event = expect(&mut bob_events, |event| {
+        matches!(event, Event::Pong(_) | Event::UserMessage(_))
+    })
+    .await;
+    assert!(matches!(event, Event::Pong(_)));
+}
+
+/// Log in with the given name once its previous session has ended, returns the accessible rooms
+async fn log_in_again(addr: SocketAddr, name: &str) -> Vec<String> {
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (mut events, mut commands) =
+        transport::client::connect_tcp_stream(stream, &[Compression::None])
+            .await
+            .unwrap();
+
+    loop {
+        commands
+            .write(&UserCommand::Login(username(name)))
+            .await
+            .unwrap();
+        match expect(&mut events, |event| {
+            matches!(event, Event::LoginSuccessful(_) | Event::LoginRejected(_))
+        })
+        .await
+        {
+            Event::LoginSuccessful(login_successful) => {
+                return login_successful
+                    .rooms
+                    .into_iter()
+                    .map(|room| room.name)
+                    .collect()
+            }
+            // the previous session may not have ended yet
+            _ => tokio::time::sleep(Duration::from_millis(50)).await,
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_picked_names_keep_their_groups_until_they_lapse() {
+    let (addr, admin_token, context) = start_server().await;
+    let (mut admin_events, mut admin_commands) = log_in(addr, token(&admin_token)).await;
+
+    // bob was last seen long ago, whoever picks the name now starts from scratch
+    let long_ago = SystemTime::now() - Duration::from_secs(31 * 24 * 60 * 60);
+    context
+        .user_directory
+        .record_seen(
+            "bob",
+            long_ago.duration_since(UNIX_EPOCH).unwrap().as_millis() as u64,
+        )
+        .await
+        .unwrap();
+    change_membership(&mut admin_commands, "bob", true).await;
+    expect(&mut admin_events, |event| {
+        matches!(event, Event::GroupMembershipChanged(_))
+    })
+    .await;
+    assert!(log_in_again(addr, "bob").await.is_empty());
+
+    // bob coming back in time keeps what was granted to him meanwhile
+    change_membership(&mut admin_commands, "bob", true).await;
+    expect(&mut admin_events, |event| {
+        matches!(event, Event::GroupMembershipChanged(_))
+    })
+    .await;
+    assert_eq!(log_in_again(addr, "bob").await, vec![String::from(ROOM)]);
+}
+
+#[tokio::test]
+async fn test_purged_users_lose_their_groups() {
+    let (addr, admin_token, context) = start_server().await;
+    let (mut admin_events, mut admin_commands) = log_in(addr, token(&admin_token)).await;
+
+    change_membership(&mut admin_commands, "bob", true).await;
+    expect(&mut admin_events, |event| {
+        matches!(event, Event::GroupMembershipChanged(_))
+    })
+    .await;
+    assert_eq!(context.accessible_rooms("bob").await.len(), 1);
+
+    admin_commands
+        .write(&UserCommand::PurgeUser(command::PurgeUserCommand {
+            user_id: String::from("bob"),
+        }))
+        .await
+        .unwrap();
+    expect(&mut admin_events, |event| {
+        matches!(event, Event::UserPurged(_))
+    })
+    .await;
+    assert!(context.accessible_rooms("bob").await.is_empty());
+}
@@ -0,0 +1,214 @@
+//! Holds user sessions over a Unix domain socket, next to TCP
+#![cfg(unix)]
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use comms::{
+    command::{self, UserCommand},
+    event::{ContentType, Event},
+    handshake::Compression,
+    transport::{
+        self,
+        client::{CommandWriter, EventStream},
+    },
+};
+use server::{
+    archival::RoomArchive,
+    auth::Authenticator,
+    chaos::Chaos,
+    context::ServerContext,
+    directory::UserDirectory,
+    groups::Groups,
+    invites::RoomInvites,
+    message_filters::MessageFilters,
+    moderation::ModerationQueue,
+    read_markers::ReadMarkers,
+    reminders::Reminders,
+    room_manager::{ChatRoomMetadata, RoomManagerBuilder},
+    session::{CommandHistory, DuplicateLoginPolicy, SessionRegistry},
+    storage::Storage,
+    unix_socket,
+};
+use tokio::{net::UnixStream, sync::broadcast, task::JoinHandle};
+use tokio_stream::StreamExt;
+
+const ROOM: &str = "general";
+/// How long a test waits for something to happen before it fails
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("chat-unix-socket-{}", nanoid::nanoid!()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
+
+/// A server with a single room accepting sessions over the socket at the path, until the quit signal is sent
+async fn start_server(
+    socket_path: PathBuf,
+) -> (broadcast::Sender<()>, JoinHandle<anyhow::Result<()>>) {
+    let storage = Storage::new(temp_dir().join("data")).unwrap();
+    let room_manager = RoomManagerBuilder::new()
+        .create_room(ChatRoomMetadata {
+            name: String::from(ROOM),
+            description: String::new(),
+            category: None,
+            capacity: None,
+            welcome_message: None,
+            owners: Vec::new(),
+            groups: Vec::new(),
+            record_participation: false,
+            encrypted: false,
+            requires_approval: false,
+            broadcast_capacity: None,
+            lag_policy: Default::default(),
+        })
+        .build();
+
+    let context = Arc::new(ServerContext {
+        authenticator: Authenticator::load(storage.clone()).await.unwrap(),
+        room_manager,
+        session_registry: SessionRegistry::new(),
+        reminders: Reminders::load(storage.clone()).await.unwrap(),
+        groups: Groups::load(storage.clone()).await.unwrap(),
+        read_markers: ReadMarkers::load(storage.clone()).await.unwrap(),
+        moderation_queue: ModerationQueue::load(storage.clone()).await.unwrap(),
+        user_directory: UserDirectory::load(storage.clone()).await.unwrap(),
+        room_archive: RoomArchive::load(storage.clone()).await.unwrap(),
+        room_invites: RoomInvites::load(storage).await.unwrap(),
+        message_filters: MessageFilters::default(),
+        mirror: None,
+        chaos: Chaos::default(),
+        command_history: CommandHistory::new(),
+        duplicate_login_policy: DuplicateLoginPolicy::Allow,
+        redirect_addr: None,
+        admins: Default::default(),
+    });
+
+    let listener = unix_socket::bind(&socket_path).unwrap();
+    let (quit_tx, quit_rx) = broadcast::channel::<()>(1);
+    let server = tokio::spawn(unix_socket::serve_until_quit(context, listener, quit_rx));
+
+    (quit_tx, server)
+}
+
+async fn connect(socket_path: &Path) -> (EventStream, CommandWriter) {
+    let stream = UnixStream::connect(socket_path).await.unwrap();
+    let (mut events, mut commands) =
+        transport::client::connect_stream(stream, &[Compression::Deflate])
+            .await
+            .unwrap();
+    commands
+        .write(&UserCommand::Login(command::LoginCommand {
+            username: None,
+            token: None,
+            guest: false,
+        }))
+        .await
+        .unwrap();
+
+    let Some(Ok(Event::LoginSuccessful(_))) = events.next().await else {
+        panic!("the client should have logged in");
+    };
+
+    (events, commands)
+}
+
+async fn join(events: &mut EventStream, commands: &mut CommandWriter) {
+    commands
+        .write(&UserCommand::JoinRoom(command::JoinRoomCommand {
+            room: String::from(ROOM),
+            wait_if_full: false,
+        }))
+        .await
+        .unwrap();
+    expect(events, |event| matches!(event, Event::UserJoinedRoom(_))).await;
+}
+
+/// Wait for an event matching the predicate, skipping the others
+async fn expect(events: &mut EventStream, predicate: impl Fn(&Event) -> bool) -> Event {
+    tokio::time::timeout(TIMEOUT, async {
+        loop {
+            match events.next().await {
+                Some(Ok(event)) if predicate(&event) => return event,
+                Some(Ok(_)) => continue,
+                other => panic!("the connection has ended with {:?}", other),
+            }
+        }
+    })
+    .await
+    .expect("the event should have arrived in time")
+}
+
+#[tokio::test]
+async fn test_messages_are_exchanged_over_the_socket() {
+    let socket_path = temp_dir().join("chat.sock");
+    let (_quit_tx, _server) = start_server(socket_path.clone()).await;
+
+    let (mut alice_events, mut alice_commands) = connect(&socket_path).await;
+    let (mut bob_events, mut bob_commands) = connect(&socket_path).await;
+    join(&mut alice_events, &mut alice_commands).await;
+    join(&mut bob_events, &mut bob_commands).await;
+
+    alice_commands
+        .write(&UserCommand::SendMessage(command::SendMessageCommand {
+            room: String::from(ROOM),
+            content: String::from("hello over the socket"),
+            content_type: ContentType::Plain,
+            nonce: None,
+        }))
+        .await
+        .unwrap();
+
+    let Event::UserMessage(message) = expect(&mut bob_events, |event| {
+        matches!(event, Event::UserMessage(_))
+    })
+    .await
+    else {
+        unreachable!();
+    };
+    assert_eq!(message.content, "hello over the socket");
+}
+
+#[tokio::test]
+async fn test_socket_is_removed_on_shutdown() {
+    let socket_path = temp_dir().join("chat.sock");
+    let (quit_tx, server) = start_server(socket_path.clone()).await;
+    let (mut events, mut commands) = connect(&socket_path).await;
+    join(&mut events, &mut commands).await;
+
+    quit_tx.send(()).unwrap();
+    tokio::time::timeout(TIMEOUT, server)
+        .await
+        .expect("the server should have shut down in time")
+        .unwrap()
+        .unwrap();
+
+    assert!(!socket_path.exists());
+}
+
+#[tokio::test]
+async fn test_stale_socket_is_replaced() {
+    let socket_path = temp_dir().join("chat.sock");
+    // a socket nobody listens on anymore, as left behind by a server which has crashed
+    drop(std::os::unix::net::UnixListener::bind(&socket_path).unwrap());
+
+    let (_quit_tx, _server) = start_server(socket_path.clone()).await;
+    let _client = connect(&socket_path).await;
+}
+
+#[tokio::test]
+async fn test_socket_in_use_or_other_files_are_kept() {
+    let socket_path = temp_dir().join("chat.sock");
+    let (_quit_tx, _server) = start_server(socket_path.clone()).await;
+    assert!(unix_socket::bind(&socket_path).is_err());
+
+    let file_path = temp_dir().join("chat.sock");
+    std::fs::write(&file_path, "not a socket").unwrap();
+    assert!(unix_socket::bind(&file_path).is_err());
+    assert_eq!(std::fs::read_to_string(&file_path).unwrap(), "not a socket");
+}
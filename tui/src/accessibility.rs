@@ -0,0 +1,83 @@
+use ratatui::{
+    symbols,
+    widgets::{Block, Borders},
+};
+use serde::{Deserialize, Serialize};
+
+/// Borders drawn with plain ASCII characters, for terminals and fonts without box drawing characters
+const ASCII_BORDER_SET: symbols::border::Set = symbols::border::Set {
+    top_left: "+",
+    top_right: "+",
+    bottom_left: "+",
+    bottom_right: "+",
+    vertical_left: "|",
+    vertical_right: "|",
+    horizontal_top: "-",
+    horizontal_bottom: "-",
+};
+
+/// [Accessibility] holds the options that adapt the user interface to the needs of the user
+///
+/// Read from the `CHAT_TUI_ACCESSIBILITY` environment variable as a comma separated list,
+/// e.g. `high-contrast,ascii`. Every option is off if the variable is not set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Accessibility {
+    /// Signal states with text markers instead of colors and blinking alone, e.g. `(unread)`
+    pub high_contrast: bool,
+    /// Draw borders and key hints with ASCII characters only
+    pub ascii: bool,
+    /// Render the pages as a single column of plain text without borders, friendlier to screen readers
+    pub linear: bool,
+}
+
+impl Accessibility {
+    pub fn from_env() -> Self {
+        std::env::var("CHAT_TUI_ACCESSIBILITY")
+            .map(|options| Self::parse(&options))
+            .unwrap_or_default()
+    }
+
+    fn parse(options: &str) -> Self {
+        let mut accessibility = Accessibility::default();
+
+        for option in options.split(',').map(str::trim) {
+            match option {
+                "high-contrast" => accessibility.high_contrast = true,
+                "ascii" => accessibility.ascii = true,
+                "linear" => accessibility.linear = true,
+                _ => {}
+            }
+        }
+
+        accessibility
+    }
+
+    /// A block surrounding a widget, borderless in linear mode where only its title is kept
+    pub fn block<'a>(&self) -> Block<'a> {
+        if self.linear {
+            Block::default()
+        } else if self.ascii {
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(ASCII_BORDER_SET)
+        } else {
+            Block::default().borders(Borders::ALL)
+        }
+    }
+
+    /// The name of a key shown in the usage hints, arrows are spelled out in ASCII mode
+    pub fn key_name(&self, key: &str) -> String {
+        if !self.ascii {
+            return String::from(key);
+        }
+
+        String::from(match key {
+            "←" => "Left",
+            "→" => "Right",
+            "↑" => "Up",
+            "↓" => "Down",
+            key => key,
+        })
+    }
+}
@@ -0,0 +1,90 @@
+/// Stands for the words typed after an alias in its expansion
+const ARGUMENTS_PLACEHOLDER: &str = "$*";
+
+/// [Aliases] are the shortcuts the user defines for the texts they type often
+///
+/// Aliases are read from the `CHAT_TUI_ALIASES` environment variable as `;` separated `name=expansion`
+/// entries, e.g. `brb=be right back;shrug=¯\_(ツ)_/¯ $*`. Typing `/brb` sends the expansion instead, which
+/// may be a message or a slash command. The words typed after the alias replace `$*`, or are appended
+/// if the expansion has no `$*`.
+///
+/// Key macros are read from `CHAT_TUI_KEYS` the same way, e.g. `F5=/join standup;F6=/brb`. Pressing the
+/// function key sends its text as if it had been typed, aliases included.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Aliases {
+    /// The name of the alias without its leading `/` and its expansion
+    aliases: Vec<(String, String)>,
+    /// The number of the function key and the text it sends
+    key_macros: Vec<(u8, String)>,
+}
+
+/// Splits `;` separated `name=value` entries, entries without a name or a value are ignored
+fn parse_entries(entries: &str) -> impl Iterator<Item = (&str, &str)> {
+    entries
+        .split(';')
+        .filter_map(|entry| entry.split_once('='))
+        .map(|(name, value)| (name.trim(), value.trim()))
+        .filter(|(name, value)| !name.is_empty() && !value.is_empty())
+}
+
+impl Aliases {
+    pub fn from_env() -> Self {
+        let aliases = std::env::var("CHAT_TUI_ALIASES").unwrap_or_default();
+        let key_macros = std::env::var("CHAT_TUI_KEYS").unwrap_or_default();
+
+        Aliases {
+            aliases: parse_entries(&aliases)
+                .map(|(name, expansion)| {
+                    (
+                        String::from(name.trim_start_matches('/')),
+                        String::from(expansion),
+                    )
+                })
+                .filter(|(name, _)| !name.is_empty() && !name.contains(char::is_whitespace))
+                .collect(),
+            key_macros: parse_entries(&key_macros)
+                .filter_map(|(key, text)| {
+                    let number = key.strip_prefix(['F', 'f'])?.parse().ok()?;
+
+                    Some((number, String::from(text)))
+                })
+                .collect(),
+        }
+    }
+
+    /// The text to send for the typed text, the expansion if it starts with an alias
+    pub fn expand(&self, text: &str) -> String {
+        let Some(command) = text.strip_prefix('/') else {
+            return String::from(text);
+        };
+        let (name, arguments) = command
+            .split_once(' ')
+            .map(|(name, arguments)| (name, arguments.trim()))
+            .unwrap_or((command, ""));
+
+        let Some((_, expansion)) = self.aliases.iter().find(|(alias, _)| alias == name) else {
+            return String::from(text);
+        };
+
+        if expansion.contains(ARGUMENTS_PLACEHOLDER) {
+            expansion.replace(ARGUMENTS_PLACEHOLDER, arguments)
+        } else if arguments.is_empty() {
+            expansion.clone()
+        } else {
+            format!("{} {}", expansion, arguments)
+        }
+    }
+
+    /// The names of the aliases, without their leading `/`
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.aliases.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// The text sent by the function key with the given number, if the user has bound it
+    pub fn key_macro(&self, number: u8) -> Option<&str> {
+        self.key_macros
+            .iter()
+            .find(|(key, _)| *key == number)
+            .map(|(_, text)| text.as_str())
+    }
+}
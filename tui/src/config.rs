@@ -0,0 +1,160 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use anyhow::Context;
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    accessibility::Accessibility, keymap::Keymap, locale::Locale, state_store::NoticeKind,
+    time_format::TimeFormat,
+};
+
+/// The directory within the config directory of the user the files of the client are kept in
+const CONFIG_DIR_NAME: &str = "chat-tui";
+const CONFIG_FILE_NAME: &str = "config.json";
+/// The directory next to the config file the scripts of the user are kept in
+const SCRIPTS_DIR_NAME: &str = "scripts";
+
+/// [ClientConfig] holds the settings the user has changed on the settings page
+///
+/// Kept as JSON at the path of the `CHAT_TUI_CONFIG` environment variable, or in the config directory of the
+/// user, i.e. `$XDG_CONFIG_HOME/chat-tui/config.json` (`~/.config` by default) on Linux and
+/// `%APPDATA%\chat-tui\config\config.json` on Windows. The settings saved to the file take precedence over the
+/// environment variables, settings missing from it are read from the environment.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClientConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<Locale>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accessibility: Option<Accessibility>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keymap: Option<Keymap>,
+    /// Whether the user is shown as away to the others while the terminal is unfocused
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_away: Option<bool>,
+    /// How many seconds apart the consecutive messages of a user may be to be grouped under their name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grouping_window_secs: Option<u64>,
+    /// How the times of the messages, the days between them and the clock are shown
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_format: Option<TimeFormat>,
+    /// The words besides mentions that highlight a message and run the notify command
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub watch_words: Option<Vec<String>>,
+    /// The words masked in the messages shown, e.g. while sharing the screen
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub masked_words: Option<Vec<String>>,
+    /// The kinds of notices kept out of the message pane, e.g. in busy rooms
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hidden_notices: Option<Vec<NoticeKind>>,
+    /// Whether users joining and leaving are shown, by room, regardless of the hidden notices
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub room_participation: Option<BTreeMap<String, bool>>,
+    /// The address the connect page starts with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_address: Option<String>,
+    /// The name the user has picked for themselves
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    /// The rooms joined right after logging in, the first one is opened
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_join_rooms: Option<Vec<String>>,
+    /// The rooms the user has left lately, the latest first
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recent_rooms: Option<Vec<String>>,
+}
+
+fn config_path() -> Option<PathBuf> {
+    if let Ok(path) = std::env::var("CHAT_TUI_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+
+    let path = ProjectDirs::from("", "", CONFIG_DIR_NAME)?
+        .config_dir()
+        .join(CONFIG_FILE_NAME);
+
+    // macOS keeps the config under Application Support, the file written to ~/.config by earlier versions is kept
+    if !path.exists() {
+        let legacy_path = std::env::var("HOME").ok().map(|home| {
+            PathBuf::from(home)
+                .join(".config")
+                .join(CONFIG_DIR_NAME)
+                .join(CONFIG_FILE_NAME)
+        });
+        if let Some(legacy_path) = legacy_path.filter(|legacy_path| legacy_path.exists()) {
+            return Some(legacy_path);
+        }
+    }
+
+    Some(path)
+}
+
+/// The directory the Lua scripts of the user are loaded from, see [crate::scripting::Scripts]
+/// The `scripts` directory next to the config file, unless `CHAT_TUI_SCRIPTS_DIR` is set
+pub fn scripts_dir() -> Option<PathBuf> {
+    if let Ok(dir) = std::env::var("CHAT_TUI_SCRIPTS_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+
+    Some(config_path()?.parent()?.join(SCRIPTS_DIR_NAME))
+}
+
+impl ClientConfig {
+    /// Reads the config file, None if it does not exist since the client is run for the first time
+    /// Fails if the file can not be read or is malformed, rather than overwriting it later
+    pub fn load() -> anyhow::Result<Option<Self>> {
+        let Some(path) = config_path() else {
+            return Ok(None);
+        };
+
+        match std::fs::read(&path) {
+            Ok(bytes) => Self::parse(&bytes)
+                .map(Some)
+                .with_context(|| format!("could not parse the config file {}", path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err)
+                .with_context(|| format!("could not read the config file {}", path.display())),
+        }
+    }
+
+    /// Parses the contents of a config file, the settings which are not valid fall back to their defaults
+    fn parse(bytes: &[u8]) -> serde_json::Result<Self> {
+        let mut config: ClientConfig = serde_json::from_slice(bytes)?;
+        config.time_format = config.time_format.map(TimeFormat::validated);
+
+        Ok(config)
+    }
+
+    /// Writes the config file, creating its directory if needed
+    /// The file is written to a temporary file and renamed over the previous one, so it is never left half written
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = config_path().context("there is no config directory, set CHAT_TUI_CONFIG")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("could not create {}", dir.display()))?;
+        }
+
+        let json = serde_json::to_vec_pretty(self)?;
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, json)
+            .with_context(|| format!("could not write the config file {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, &path)
+            .with_context(|| format!("could not replace the config file {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_time_formats_fall_back_to_the_default() {
+        let config =
+            ClientConfig::parse(br#"{"time_format":{"pattern":"%Q","relative":true}}"#).unwrap();
+
+        let time_format = config.time_format.unwrap();
+        assert_eq!(time_format.pattern, TimeFormat::default().pattern);
+        assert!(time_format.relative);
+    }
+}
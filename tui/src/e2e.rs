@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use comms::{command::ENCRYPTED_CONTENT_PREFIX, event};
+use hkdf::Hkdf;
+use rand_core::OsRng;
+use sha2::{Digest, Sha256};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+/// The length of the nonce preceding every ciphertext
+const NONCE_LENGTH: usize = 12;
+/// Binds the keys derived for sealing room keys to their purpose
+const SEALING_INFO: &[u8] = b"rust-chat-server room key sealing";
+/// The number of bytes of the hashed public key shown as its fingerprint
+const FINGERPRINT_LENGTH: usize = 8;
+
+/// [E2eKeys] holds the keys of the encrypted rooms the user is in
+///
+/// Every session has its own X25519 key pair, created at startup and never stored. Room keys are
+/// symmetric ChaCha20-Poly1305 keys, created by the first session in a room and sealed by the members
+/// holding them for every session their users confirm, so the server only ever relays ciphertext.
+/// Users confirm a session by comparing the [fingerprint] of its public key with the one it shows.
+pub struct E2eKeys {
+    secret: StaticSecret,
+    public_key: PublicKey,
+    room_keys: HashMap<String, Key>,
+}
+
+impl E2eKeys {
+    pub fn new() -> Self {
+        let secret = StaticSecret::random_from_rng(OsRng);
+
+        E2eKeys {
+            public_key: PublicKey::from(&secret),
+            secret,
+            room_keys: HashMap::new(),
+        }
+    }
+
+    /// The base64 encoded public key of the session, room keys are sealed for it
+    pub fn public_key(&self) -> String {
+        STANDARD.encode(self.public_key.as_bytes())
+    }
+
+    /// The fingerprint of the public key of the session, for the members to compare before sharing a key
+    pub fn fingerprint(&self) -> String {
+        fingerprint(&self.public_key())
+    }
+
+    pub fn has_room_key(&self, room: &str) -> bool {
+        self.room_keys.contains_key(room)
+    }
+
+    /// Create a new key for a room nobody holds a key for yet
+    pub fn generate_room_key(&mut self, room: &str) {
+        self.room_keys.insert(
+            String::from(room),
+            ChaCha20Poly1305::generate_key(&mut OsRng),
+        );
+    }
+
+    /// Seal the key of a room for the session with the given public key
+    /// Returns None if the user does not hold the key of the room
+    pub fn seal_room_key(&self, room: &str, public_key: &str) -> anyhow::Result<Option<String>> {
+        let Some(room_key) = self.room_keys.get(room) else {
+            return Ok(None);
+        };
+
+        let sealing_key = self.sealing_key(room, public_key)?;
+
+        seal(&sealing_key, room_key).map(Some)
+    }
+
+    /// Open and keep a room key sealed for this session by the session with the given public key
+    pub fn open_room_key(
+        &mut self,
+        room: &str,
+        public_key: &str,
+        sealed_key: &str,
+    ) -> anyhow::Result<()> {
+        let sealing_key = self.sealing_key(room, public_key)?;
+        let room_key = open(&sealing_key, sealed_key)?;
+        anyhow::ensure!(room_key.len() == 32, "the room key has an invalid length");
+
+        self.room_keys
+            .insert(String::from(room), *Key::from_slice(&room_key));
+
+        Ok(())
+    }
+
+    /// Encrypt the content of a message, returns None if the user does not hold the key of the room
+    pub fn encrypt(&self, room: &str, content: &str) -> anyhow::Result<Option<String>> {
+        let Some(room_key) = self.room_keys.get(room) else {
+            return Ok(None);
+        };
+
+        let ciphertext = seal(room_key, content.as_bytes())?;
+
+        Ok(Some(format!("{ENCRYPTED_CONTENT_PREFIX}{ciphertext}")))
+    }
+
+    /// Replace the encrypted contents of an event with their plain text
+    /// Contents which can not be decrypted, e.g. because the room key is missing, are left as they are
+    pub fn decrypt_event(&self, event: &mut event::Event) {
+        match event {
+            event::Event::UserMessage(event) => {
+                self.decrypt_content(&event.room, &mut event.content);
+            }
+            event::Event::HistoryResponse(event) => {
+                for entry in event.history.iter_mut() {
+                    self.decrypt_content(&event.room, &mut entry.content);
+                }
+            }
+            event::Event::HistoryChunk(event) => {
+                for entry in event.history.iter_mut() {
+                    self.decrypt_content(&event.room, &mut entry.content);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn decrypt_content(&self, room: &str, content: &mut String) {
+        let Some(ciphertext) = content.strip_prefix(ENCRYPTED_CONTENT_PREFIX) else {
+            return;
+        };
+
+        let plain_text = self
+            .room_keys
+            .get(room)
+            .and_then(|room_key| open(room_key, ciphertext).ok())
+            .and_then(|plain_text| String::from_utf8(plain_text).ok());
+
+        if let Some(plain_text) = plain_text {
+            *content = plain_text;
+        }
+    }
+
+    /// The key shared by this session and the session with the given public key, bound to the room
+    fn sealing_key(&self, room: &str, public_key: &str) -> anyhow::Result<Key> {
+        let public_key: [u8; 32] = STANDARD
+            .decode(public_key)
+            .context("could not decode the public key")?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("the public key has an invalid length"))?;
+
+        let shared_secret = self.secret.diffie_hellman(&PublicKey::from(public_key));
+        let mut sealing_key = Key::default();
+        Hkdf::<Sha256>::new(Some(room.as_bytes()), shared_secret.as_bytes())
+            .expand(SEALING_INFO, &mut sealing_key)
+            .map_err(|_| anyhow::anyhow!("could not derive the sealing key"))?;
+
+        Ok(sealing_key)
+    }
+}
+
+/// A short digest of a base64 encoded public key, in groups of four hex digits, e.g. "1a2b 3c4d 5e6f 7a8b"
+pub fn fingerprint(public_key: &str) -> String {
+    Sha256::digest(public_key.as_bytes())[..FINGERPRINT_LENGTH]
+        .chunks(2)
+        .map(|group| group.iter().map(|byte| format!("{byte:02x}")).collect())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Encrypts the plain text with a random nonce, returns the base64 encoded nonce followed by the ciphertext
+fn seal(key: &Key, plain_text: &[u8]) -> anyhow::Result<String> {
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = ChaCha20Poly1305::new(key)
+        .encrypt(&nonce, plain_text)
+        .map_err(|_| anyhow::anyhow!("could not encrypt"))?;
+
+    Ok(STANDARD.encode([nonce.as_slice(), &ciphertext].concat()))
+}
+
+/// Decrypts what [seal] has produced
+fn open(key: &Key, sealed: &str) -> anyhow::Result<Vec<u8>> {
+    let sealed = STANDARD
+        .decode(sealed)
+        .context("could not decode the ciphertext")?;
+    anyhow::ensure!(sealed.len() > NONCE_LENGTH, "the ciphertext is too short");
+
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LENGTH);
+    ChaCha20Poly1305::new(key)
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow::anyhow!("could not decrypt"))
+}
+
+#[cfg(test)]
+mod tests {
+    use comms::command;
+
+    use super::*;
+
+    const ROOM: &str = "secrets";
+    const MAX_LENGTH: usize = 512;
+
+    #[test]
+    fn test_a_sealed_room_key_opens_for_the_requesting_session() {
+        let mut holder = E2eKeys::new();
+        holder.generate_room_key(ROOM);
+        let mut requester = E2eKeys::new();
+
+        let sealed_key = holder
+            .seal_room_key(ROOM, &requester.public_key())
+            .unwrap()
+            .unwrap();
+        requester
+            .open_room_key(ROOM, &holder.public_key(), &sealed_key)
+            .unwrap();
+
+        let mut event = event::Event::UserMessage(event::UserMessageBroadcastEvent {
+            room: String::from(ROOM),
+            id: String::from("m1"),
+            user_id: String::from("alice"),
+            display_name: None,
+            content: holder.encrypt(ROOM, "hello").unwrap().unwrap(),
+            content_type: event::ContentType::Plain,
+            timestamp: 1,
+            nonce: None,
+        });
+        requester.decrypt_event(&mut event);
+
+        let event::Event::UserMessage(message) = event else {
+            unreachable!();
+        };
+        assert_eq!(message.content, "hello");
+    }
+
+    #[test]
+    fn test_the_longest_message_fits_the_length_the_server_allows_encrypted() {
+        let mut keys = E2eKeys::new();
+        keys.generate_room_key(ROOM);
+
+        // the plain text is limited in characters, the longest ones take up 4 bytes
+        let content = "😀".repeat(MAX_LENGTH);
+        let encrypted = keys.encrypt(ROOM, &content).unwrap().unwrap();
+
+        assert!(content.chars().count() < encrypted.chars().count());
+        assert!(encrypted.chars().count() <= command::max_encrypted_length(MAX_LENGTH));
+    }
+
+    #[test]
+    fn test_a_room_key_sealed_for_another_session_does_not_open() {
+        let mut holder = E2eKeys::new();
+        holder.generate_room_key(ROOM);
+        let mut requester = E2eKeys::new();
+        let mut eavesdropper = E2eKeys::new();
+
+        let sealed_key = holder
+            .seal_room_key(ROOM, &requester.public_key())
+            .unwrap()
+            .unwrap();
+
+        assert!(eavesdropper
+            .open_room_key(ROOM, &holder.public_key(), &sealed_key)
+            .is_err());
+        // nor for the right session in another room, the sealing key is bound to the room
+        assert!(requester
+            .open_room_key("elsewhere", &holder.public_key(), &sealed_key)
+            .is_err());
+        assert!(!eavesdropper.has_room_key(ROOM));
+    }
+
+    #[test]
+    fn test_messages_encrypted_with_another_key_are_left_encrypted() {
+        let mut alice = E2eKeys::new();
+        alice.generate_room_key(ROOM);
+        let mut bob = E2eKeys::new();
+        bob.generate_room_key(ROOM);
+
+        let encrypted = alice.encrypt(ROOM, "hello").unwrap().unwrap();
+        let mut content = encrypted.clone();
+        bob.decrypt_content(ROOM, &mut content);
+
+        assert_eq!(content, encrypted);
+    }
+
+    #[test]
+    fn test_fingerprints_tell_public_keys_apart() {
+        let alice = E2eKeys::new();
+        let bob = E2eKeys::new();
+
+        assert_eq!(alice.fingerprint(), fingerprint(&alice.public_key()));
+        assert_ne!(alice.fingerprint(), bob.fingerprint());
+        assert_eq!(alice.fingerprint().len(), "1a2b 3c4d 5e6f 7a8b".len());
+    }
+}
@@ -0,0 +1,71 @@
+/// [Highlights] decides which messages deserve the attention of the user
+///
+/// Messages mentioning the user with `@<user id>` are always highlighted. Further watch words are read from the
+/// `CHAT_TUI_WATCH_WORDS` environment variable as a comma separated list, e.g. `release,outage`, and matched
+/// as whole words regardless of their case. Highlighted messages are styled apart and run the notify command.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Highlights {
+    /// Lowercased, so they can be matched against the lowercased content of a message
+    watch_words: Vec<String>,
+}
+
+impl Highlights {
+    pub fn from_env() -> Self {
+        std::env::var("CHAT_TUI_WATCH_WORDS")
+            .map(|watch_words| Self::parse(&watch_words))
+            .unwrap_or_default()
+    }
+
+    /// Reads the comma separated watch words, e.g. as typed by the user
+    pub fn parse(watch_words: &str) -> Self {
+        Highlights {
+            watch_words: watch_words
+                .split(',')
+                .map(str::trim)
+                .filter(|watch_word| !watch_word.is_empty())
+                .map(str::to_lowercase)
+                .collect(),
+        }
+    }
+
+    pub fn watch_words(&self) -> &[String] {
+        &self.watch_words
+    }
+
+    /// Whether a message sent by the author should be highlighted for the user, their own messages never are
+    pub fn is_highlighted(&self, user_id: &str, author: &str, content: &str) -> bool {
+        if author == user_id {
+            return false;
+        }
+
+        if contains_word(content, &format!("@{user_id}")) {
+            return true;
+        }
+
+        let content = content.to_lowercase();
+        self.watch_words
+            .iter()
+            .any(|watch_word| contains_word(&content, watch_word))
+    }
+}
+
+/// Whether the character continues a word, ids such as `jane-doe_2` count as a single word
+pub(crate) fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Whether the word occurs in the text, not counting occurrences within longer words
+fn contains_word(text: &str, word: &str) -> bool {
+    text.match_indices(word).any(|(index, _)| {
+        let is_word_start = text[..index]
+            .chars()
+            .next_back()
+            .is_none_or(|c| !is_word_char(c));
+        let is_word_end = text[index + word.len()..]
+            .chars()
+            .next()
+            .is_none_or(|c| !is_word_char(c));
+
+        is_word_start && is_word_end
+    })
+}
@@ -0,0 +1,180 @@
+use std::time::Duration;
+
+use comms::event;
+use reqwest::Url;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// The most columns an image takes up in the message pane
+const MAX_IMAGE_COLUMNS: u32 = 32;
+/// The most rows an image takes up in the message pane, every row shows two lines of pixels
+const MAX_IMAGE_ROWS: u32 = 12;
+/// How long fetching an image may take
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+/// Larger images are left as links
+const MAX_IMAGE_BYTES: usize = 4 * 1024 * 1024;
+/// The extensions of the links rendered as images, in lowercase
+const IMAGE_EXTENSIONS: [&str; 5] = ["png", "jpg", "jpeg", "gif", "webp"];
+
+/// A pixel of an image, None if it is transparent
+pub type Pixel = Option<[u8; 3]>;
+
+/// [ImageArt] is an image scaled down to fit a few cells of the terminal, shown below the message linking it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageArt {
+    width: usize,
+    pixels: Vec<Pixel>,
+}
+
+impl ImageArt {
+    /// The rows of cells from the top, every cell with the pixel of its upper and of its lower half
+    pub fn rows(&self) -> impl Iterator<Item = Vec<(Pixel, Pixel)>> + '_ {
+        self.pixels.chunks(self.width * 2).map(|pixels| {
+            let (upper, lower) = pixels.split_at(self.width.min(pixels.len()));
+            // the last row has no lower half if the image is an odd number of pixels high
+            upper
+                .iter()
+                .enumerate()
+                .map(|(x, upper)| (*upper, lower.get(x).copied().flatten()))
+                .collect()
+        })
+    }
+
+    /// Roughly how many bytes the pixels of the image take up
+    pub fn estimated_size(&self) -> usize {
+        self.pixels.len() * std::mem::size_of::<Pixel>()
+    }
+}
+
+/// An image linked in a message, ready to be shown below it
+#[derive(Debug)]
+pub struct RenderedImage {
+    pub room: String,
+    pub message_id: String,
+    pub art: ImageArt,
+}
+
+/// [ImageRenderer] fetches the images linked in messages, so they can be viewed without leaving the terminal
+///
+/// Only available if the client is built with the `images` feature, and turned off by setting the
+/// `CHAT_TUI_IMAGES` environment variable to `off`. Links to png, jpeg, gif and webp files are rendered,
+/// as well as links to giphy pages. The images are fetched by the client, revealing its address to their hosts.
+#[derive(Debug, Clone)]
+pub struct ImageRenderer {
+    http_client: reqwest::Client,
+}
+
+impl ImageRenderer {
+    pub fn from_env() -> Option<Self> {
+        let is_turned_off =
+            std::env::var("CHAT_TUI_IMAGES").is_ok_and(|images| images.trim() == "off");
+        if !cfg!(feature = "images") || is_turned_off {
+            return None;
+        }
+
+        let http_client = reqwest::Client::builder()
+            .timeout(FETCH_TIMEOUT)
+            .build()
+            .ok()?;
+
+        Some(ImageRenderer { http_client })
+    }
+
+    /// Fetch and render the first image linked in a new message in the background, it is sent once rendered
+    /// Images which can not be fetched or decoded are left as links
+    pub fn handle_server_event(
+        &self,
+        event: &event::Event,
+        rendered_tx: &UnboundedSender<RenderedImage>,
+    ) {
+        let event::Event::UserMessage(event) = event else {
+            return;
+        };
+        let Some(url) = image_link(&event.content) else {
+            return;
+        };
+
+        let http_client = self.http_client.clone();
+        let rendered_tx = rendered_tx.clone();
+        let room = event.room.clone();
+        let message_id = event.id.clone();
+        tokio::spawn(async move {
+            if let Ok(art) = fetch_art(&http_client, url).await {
+                let _ = rendered_tx.send(RenderedImage {
+                    room,
+                    message_id,
+                    art,
+                });
+            }
+        });
+    }
+}
+
+async fn fetch_art(http_client: &reqwest::Client, url: Url) -> anyhow::Result<ImageArt> {
+    let mut response = http_client.get(url).send().await?.error_for_status()?;
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() > MAX_IMAGE_BYTES {
+            anyhow::bail!("the image is too large");
+        }
+    }
+
+    // decoding is cpu bound, it is kept off the threads handling the events and the user interface
+    let (width, pixels) =
+        tokio::task::spawn_blocking(move || decode(&bytes, MAX_IMAGE_COLUMNS, MAX_IMAGE_ROWS * 2))
+            .await??;
+    if width == 0 || pixels.is_empty() {
+        anyhow::bail!("the image is empty");
+    }
+
+    Ok(ImageArt { width, pixels })
+}
+
+/// Decode the image, the first frame of an animation, and scale it down to fit the given number of pixels
+/// Returns the width of the scaled image and its pixels row by row
+#[cfg(feature = "images")]
+fn decode(bytes: &[u8], max_width: u32, max_height: u32) -> anyhow::Result<(usize, Vec<Pixel>)> {
+    let image = image::load_from_memory(bytes)?
+        .thumbnail(max_width, max_height)
+        .into_rgba8();
+    let pixels = image
+        .pixels()
+        .map(|pixel| {
+            let [red, green, blue, alpha] = pixel.0;
+            (alpha >= 128).then_some([red, green, blue])
+        })
+        .collect();
+
+    Ok((image.width() as usize, pixels))
+}
+
+#[cfg(not(feature = "images"))]
+fn decode(_bytes: &[u8], _max_width: u32, _max_height: u32) -> anyhow::Result<(usize, Vec<Pixel>)> {
+    anyhow::bail!("the client has been built without the images feature")
+}
+
+/// The first link of the text to an image
+fn image_link(text: &str) -> Option<Url> {
+    text.split_whitespace()
+        .map(|word| word.trim_start_matches(['(', '<', '"', '\'']))
+        .filter(|word| word.starts_with("https://") || word.starts_with("http://"))
+        .map(|word| word.trim_end_matches(['.', ',', ';', ':', '!', '?', ')', '>', '"', '\'']))
+        .filter_map(|word| Url::parse(word).ok())
+        .find_map(image_url)
+}
+
+/// Where the image of the link is, the gif of a giphy page, e.g. `https://giphy.com/gifs/happy-dance-l0MYt5jPR6QX5pnqM`
+fn image_url(url: Url) -> Option<Url> {
+    if matches!(url.host_str(), Some("giphy.com" | "www.giphy.com")) {
+        let slug = url.path().strip_prefix("/gifs/")?.trim_end_matches('/');
+        let id = slug.rsplit('-').next().filter(|id| !id.is_empty())?;
+
+        return Url::parse(&format!("https://media.giphy.com/media/{id}/giphy.gif")).ok();
+    }
+
+    let extension = url.path().rsplit_once('.')?.1.to_lowercase();
+    IMAGE_EXTENSIONS
+        .contains(&extension.as_str())
+        .then_some(url)
+}
@@ -0,0 +1,95 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use serde::{Deserialize, Serialize};
+
+/// [Keymap] is the preset of keys the user navigates the lists and sections with
+///
+/// Read from the `CHAT_TUI_KEYMAP` environment variable, e.g. `vim`, the arrow keys are used if it is not set.
+/// The keys of a preset only apply where no text is typed, the arrow keys keep working everywhere.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Keymap {
+    #[default]
+    Arrows,
+    /// `h`, `j`, `k` and `l` move left, down, up and right
+    Vim,
+}
+
+impl Keymap {
+    pub const ALL: [Keymap; 2] = [Keymap::Arrows, Keymap::Vim];
+
+    pub fn from_env() -> Self {
+        std::env::var("CHAT_TUI_KEYMAP")
+            .ok()
+            .and_then(|name| Self::parse(&name))
+            .unwrap_or_default()
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|keymap| keymap.name() == name.trim().to_lowercase())
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Keymap::Arrows => "arrows",
+            Keymap::Vim => "vim",
+        }
+    }
+
+    /// The arrow key the key of the preset stands for, other keys are returned as they are
+    pub fn translate(&self, key: KeyEvent) -> KeyEvent {
+        let code = match (self, key.code) {
+            (Keymap::Vim, KeyCode::Char('h')) => KeyCode::Left,
+            (Keymap::Vim, KeyCode::Char('j')) => KeyCode::Down,
+            (Keymap::Vim, KeyCode::Char('k')) => KeyCode::Up,
+            (Keymap::Vim, KeyCode::Char('l')) => KeyCode::Right,
+            (_, code) => code,
+        };
+
+        KeyEvent { code, ..key }
+    }
+}
+
+/// The key as the pages expect it, None for key releases which are only reported by some terminals
+///
+/// Windows terminals report the release of every key along with its press, may send control characters
+/// instead of `Ctrl` combinations and report `AltGr` as `Ctrl` + `Alt`, e.g. for `@` on German keyboards.
+pub fn normalize(key: KeyEvent) -> Option<KeyEvent> {
+    if key.kind == KeyEventKind::Release {
+        return None;
+    }
+
+    let (code, modifiers) = match key.code {
+        KeyCode::Char('\t') => (KeyCode::Tab, key.modifiers),
+        KeyCode::Char('\r' | '\n') => (KeyCode::Enter, key.modifiers),
+        KeyCode::Char('\u{8}' | '\u{7f}') => (KeyCode::Backspace, key.modifiers),
+        KeyCode::Char('\u{1b}') => (KeyCode::Esc, key.modifiers),
+        // Ctrl + A to Ctrl + Z sent as the control characters 0x01 to 0x1a
+        KeyCode::Char(c @ '\u{1}'..='\u{1a}') => (
+            KeyCode::Char((b'a' + c as u8 - 1) as char),
+            key.modifiers | KeyModifiers::CONTROL,
+        ),
+        KeyCode::Char(c)
+            if key
+                .modifiers
+                .contains(KeyModifiers::CONTROL | KeyModifiers::ALT)
+                && !c.is_ascii_alphabetic() =>
+        {
+            (
+                KeyCode::Char(c),
+                key.modifiers - KeyModifiers::CONTROL - KeyModifiers::ALT,
+            )
+        }
+        KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            (KeyCode::Char(c.to_ascii_lowercase()), key.modifiers)
+        }
+        code => (code, key.modifiers),
+    };
+
+    Some(KeyEvent {
+        code,
+        modifiers,
+        ..key
+    })
+}
@@ -0,0 +1,987 @@
+use comms::event::PresenceStatus;
+
+use super::Strings;
+
+pub struct German;
+
+impl Strings for German {
+    fn status_uninitialized(&self) -> &'static str {
+        "Nicht verbunden"
+    }
+
+    fn status_connecting(&self) -> &'static str {
+        "Verbinde"
+    }
+
+    fn status_authorizing(&self) -> &'static str {
+        "Anmeldung läuft"
+    }
+
+    fn status_logging_in(&self) -> &'static str {
+        "Anmeldung"
+    }
+
+    fn status_connected(&self, addr: &str) -> String {
+        format!("Verbunden mit {addr}")
+    }
+
+    fn status_connected_via(&self, addr: &str, endpoint: &str) -> String {
+        format!("Verbunden mit {addr} ({endpoint})")
+    }
+
+    fn status_replaying(&self, path: &str) -> String {
+        format!("Wiedergabe von {path}")
+    }
+
+    fn status_errored(&self, err: &str) -> String {
+        format!("Fehler: {err}")
+    }
+
+    fn login_rejected(&self, reason: &str) -> String {
+        format!("Anmeldung abgelehnt, {reason}")
+    }
+
+    fn user_id_rejected(&self, reason: &str) -> String {
+        format!("deine Benutzerkennung {reason}")
+    }
+
+    fn session_closed(&self, reason: &str) -> String {
+        format!("Sitzung beendet, {reason}")
+    }
+
+    fn session_replaced(&self, address: &str) -> String {
+        format!("Sitzung beendet, du hast dich erneut von {address} angemeldet")
+    }
+
+    fn redirected(&self, addr: &str) -> String {
+        format!("Der Server hat die Sitzung nach {addr} verlegt, neu verbunden")
+    }
+
+    fn server_address_title(&self) -> &'static str {
+        "Server-Host und Port"
+    }
+
+    fn log_in_parts(&self) -> [&'static str; 2] {
+        ["Melde dich auf ", " mit dem Code an: "]
+    }
+
+    fn press_to_connect_parts(&self) -> [&'static str; 2] {
+        ["Drücke ", " zum Verbinden"]
+    }
+
+    fn connecting_parts(&self) -> [&'static str; 2] {
+        ["Verbinde, drücke ", " zum Abbrechen"]
+    }
+
+    fn address_missing_host(&self) -> &'static str {
+        "Gib den Host vor dem Port ein, z.B. localhost:8080"
+    }
+
+    fn address_missing_port(&self) -> &'static str {
+        "Gib den Port nach dem Host ein, z.B. localhost:8080"
+    }
+
+    fn address_invalid_port(&self) -> &'static str {
+        "Der Port ist eine Zahl von 1 bis 65535"
+    }
+
+    fn address_unbracketed_ipv6(&self) -> &'static str {
+        "Setze IPv6-Adressen in eckige Klammern, z.B. [::1]:8080"
+    }
+
+    fn address_invalid_ipv6(&self) -> &'static str {
+        "Die Klammern enthalten keine gültige IPv6-Adresse"
+    }
+
+    fn error(&self, err: &str) -> String {
+        format!("Fehler: {err}")
+    }
+
+    fn terminal_too_small(
+        &self,
+        width: u16,
+        height: u16,
+        min_width: u16,
+        min_height: u16,
+    ) -> String {
+        format!("Das Terminal ist zu klein ({width}x{height}), vergrößere es auf mindestens {min_width}x{min_height}")
+    }
+
+    fn press_to_log_in_parts(&self) -> [&'static str; 3] {
+        [
+            "Drücke ",
+            " zum Anmelden, ohne Namen vergibt der Server einen, oder ",
+            " um den Server als Gast auszuprobieren",
+        ]
+    }
+
+    fn waiting_for_login(&self) -> &'static str {
+        "Anmeldung läuft..."
+    }
+
+    fn user_information_title(&self) -> &'static str {
+        "Benutzer"
+    }
+
+    fn user(&self, user_id: &str, session_count: usize) -> String {
+        if session_count > 1 {
+            format!("Benutzer: @{user_id} ({session_count} Sitzungen)")
+        } else {
+            format!("Benutzer: @{user_id}")
+        }
+    }
+
+    fn chatting_for(&self, secs: usize) -> String {
+        format!("Im Chat seit: {secs} s")
+    }
+
+    fn server(&self, status: &str) -> String {
+        format!("Server: {status}")
+    }
+
+    fn latency(&self, latency_ms: Option<u64>) -> String {
+        match latency_ms {
+            Some(latency_ms) => format!("{latency_ms} ms"),
+            None => String::from("– ms"),
+        }
+    }
+
+    fn unread_rooms(&self, count: usize) -> String {
+        match count {
+            0 => String::from("keine ungelesenen Räume"),
+            1 => String::from("1 ungelesener Raum"),
+            count => format!("{count} ungelesene Räume"),
+        }
+    }
+
+    fn active_room_title(&self) -> &'static str {
+        "Aktiver Raum"
+    }
+
+    fn active_room_parts(&self) -> [&'static str; 2] {
+        ["in ", " über "]
+    }
+
+    fn no_room_selected(&self) -> &'static str {
+        "Tritt mindestens einem Raum bei, um zu chatten!"
+    }
+
+    fn messages_title(&self) -> &'static str {
+        "Nachrichten"
+    }
+
+    fn messages_loading_title(&self, received_count: usize, total_count: usize) -> String {
+        format!("Nachrichten (Verlauf wird geladen {received_count}/{total_count})")
+    }
+
+    fn room_users_title(&self, count: usize) -> String {
+        format!("Teilnehmer ({count})")
+    }
+
+    fn usage_title(&self) -> &'static str {
+        "Bedienung"
+    }
+
+    fn rooms_title(&self) -> &'static str {
+        "Räume"
+    }
+
+    fn recent_rooms_title(&self) -> &'static str {
+        "Zuletzt verlassen"
+    }
+
+    fn archived_rooms_title(&self) -> &'static str {
+        "Archiviert"
+    }
+
+    fn message_input_title(&self) -> &'static str {
+        "Nachricht"
+    }
+
+    fn welcome_prefix(&self) -> &'static str {
+        "Willkommen! "
+    }
+
+    fn notice_marker(&self) -> &'static str {
+        "[Hinweis] "
+    }
+
+    fn warning_marker(&self) -> &'static str {
+        "[Warnung] "
+    }
+
+    fn error_marker(&self) -> &'static str {
+        "[Fehler] "
+    }
+
+    fn active_section_marker(&self) -> &'static str {
+        " [aktiv]"
+    }
+
+    fn selected_section_marker(&self) -> &'static str {
+        " [ausgewählt]"
+    }
+
+    fn too_long_marker(&self) -> &'static str {
+        " [zu lang]"
+    }
+
+    fn active_room_marker(&self) -> &'static str {
+        " (aktiv)"
+    }
+
+    fn unread_room_marker(&self) -> &'static str {
+        " (ungelesen)"
+    }
+
+    fn encrypted_room_marker(&self) -> &'static str {
+        " [e2e]"
+    }
+
+    fn archived_room_marker(&self) -> &'static str {
+        " (archiviert)"
+    }
+
+    fn removed_room_marker(&self) -> &'static str {
+        " (entfernt)"
+    }
+
+    fn recent_room_marker(&self) -> &'static str {
+        " (verlassen)"
+    }
+
+    fn encryption_status(&self, has_room_key: bool) -> &'static str {
+        if has_room_key {
+            " (Ende-zu-Ende-verschlüsselt)"
+        } else {
+            " (Ende-zu-Ende-verschlüsselt, warte auf den Raumschlüssel)"
+        }
+    }
+
+    fn away_user_marker(&self) -> &'static str {
+        " (abwesend)"
+    }
+
+    fn highlight_marker(&self) -> &'static str {
+        "[!] "
+    }
+
+    fn selected_message_marker(&self) -> &'static str {
+        "> "
+    }
+
+    fn masked_marker(&self) -> &'static str {
+        " [maskiert, /reveal]"
+    }
+
+    fn sending_marker(&self) -> &'static str {
+        " [wird gesendet]"
+    }
+
+    fn time_ago(&self, secs: u64) -> String {
+        match secs {
+            0..60 => String::from("gerade eben"),
+            60..3600 => format!("vor {} Min.", secs / 60),
+            _ => format!("vor {} Std.", secs / 3600),
+        }
+    }
+
+    fn day(&self, month: u32, day: u32, year: Option<i32>) -> String {
+        const MONTHS: [&str; 12] = [
+            "Januar",
+            "Februar",
+            "März",
+            "April",
+            "Mai",
+            "Juni",
+            "Juli",
+            "August",
+            "September",
+            "Oktober",
+            "November",
+            "Dezember",
+        ];
+        let month = MONTHS[(month as usize - 1) % MONTHS.len()];
+
+        match year {
+            Some(year) => format!("{day}. {month} {year}"),
+            None => format!("{day}. {month}"),
+        }
+    }
+
+    fn room_list_name(&self) -> &'static str {
+        "Raumliste"
+    }
+
+    fn message_input_name(&self) -> &'static str {
+        "Nachrichteneingabe"
+    }
+
+    fn select_widget(&self) -> &'static str {
+        "Wähle einen Bereich"
+    }
+
+    fn to_exit(&self) -> &'static str {
+        "zum Beenden"
+    }
+
+    fn to_hover_widgets(&self) -> &'static str {
+        "zum Wechseln der Bereiche"
+    }
+
+    fn to_activate(&self, name: &str) -> String {
+        format!("um {name} zu aktivieren")
+    }
+
+    fn to_cancel(&self) -> &'static str {
+        "zum Abbrechen"
+    }
+
+    fn select_room(&self) -> &'static str {
+        "Wähle den Raum, in dem du schreiben möchtest"
+    }
+
+    fn to_navigate(&self) -> &'static str {
+        "zum Navigieren"
+    }
+
+    fn to_join_room(&self) -> &'static str {
+        "zum Beitreten"
+    }
+
+    fn to_leave_room(&self) -> &'static str {
+        "zum Verlassen"
+    }
+
+    fn to_rejoin_room(&self) -> &'static str {
+        "zum Wiederbeitreten des zuletzt verlassenen Raums"
+    }
+
+    fn to_toggle_category(&self) -> &'static str {
+        "zum Ein- oder Ausklappen der Kategorie"
+    }
+
+    fn enter_room_first(&self) -> &'static str {
+        "Du kannst erst Nachrichten senden, wenn du einen Raum betreten hast."
+    }
+
+    fn shorten_message(&self) -> &'static str {
+        "Deine Nachricht ist zu lang, kürze sie, um sie senden zu können"
+    }
+
+    fn type_message(&self) -> &'static str {
+        "Schreibe eine Nachricht an den aktiven Raum"
+    }
+
+    fn to_complete(&self) -> &'static str {
+        "um Benutzer, Räume oder Befehle zu vervollständigen"
+    }
+
+    fn to_browse_rooms(&self) -> &'static str {
+        "um die Räume zu durchsuchen"
+    }
+
+    fn select_message(&self) -> &'static str {
+        "Wähle eine Nachricht des aktiven Raums"
+    }
+
+    fn to_select_messages(&self) -> &'static str {
+        "um Nachrichten auszuwählen"
+    }
+
+    fn to_open_settings(&self) -> &'static str {
+        "um die Einstellungen zu ändern"
+    }
+
+    fn to_open_debug(&self) -> &'static str {
+        "um die Debug-Informationen zu zeigen"
+    }
+
+    fn to_go_back(&self) -> &'static str {
+        "zum Zurückgehen"
+    }
+
+    fn room_browser_title(&self) -> &'static str {
+        "Räume durchsuchen"
+    }
+
+    fn room_filter_title(&self) -> &'static str {
+        "Filter"
+    }
+
+    fn filter_rooms(&self) -> &'static str {
+        "Tippe, um die Räume nach Name oder Beschreibung zu filtern"
+    }
+
+    fn no_rooms_found(&self) -> &'static str {
+        "Kein Raum passt zum Filter"
+    }
+
+    fn joined_room_marker(&self) -> &'static str {
+        " (beigetreten)"
+    }
+
+    fn to_open_room(&self) -> &'static str {
+        "um den Raum zu öffnen"
+    }
+
+    fn settings_title(&self) -> &'static str {
+        "Einstellungen"
+    }
+
+    fn settings_saved_to_config(&self) -> &'static str {
+        "Änderungen gelten sofort und werden in der Konfigurationsdatei gespeichert"
+    }
+
+    fn type_setting(&self) -> &'static str {
+        "Tippe den neuen Wert, Wörter werden durch Kommas getrennt"
+    }
+
+    fn to_change_setting(&self) -> &'static str {
+        "um die Einstellung zu ändern"
+    }
+
+    fn to_save_setting(&self) -> &'static str {
+        "um den Wert zu speichern"
+    }
+
+    fn read_only_marker(&self) -> &'static str {
+        " (über die Umgebung festgelegt)"
+    }
+
+    fn language_setting(&self) -> &'static str {
+        "Sprache"
+    }
+
+    fn high_contrast_setting(&self) -> &'static str {
+        "Hoher Kontrast"
+    }
+
+    fn ascii_setting(&self) -> &'static str {
+        "Nur ASCII"
+    }
+
+    fn linear_setting(&self) -> &'static str {
+        "Lineares Layout"
+    }
+
+    fn keymap_setting(&self) -> &'static str {
+        "Tastenbelegung"
+    }
+
+    fn auto_away_setting(&self) -> &'static str {
+        "Abwesend ohne Fokus"
+    }
+
+    fn grouping_window_setting(&self) -> &'static str {
+        "Gruppierung (Sekunden)"
+    }
+
+    fn time_format_setting(&self) -> &'static str {
+        "Zeitformat (24h, 12h oder %H:%M)"
+    }
+
+    fn time_zone_setting(&self) -> &'static str {
+        "Zeitzone"
+    }
+
+    fn relative_times_setting(&self) -> &'static str {
+        "Relative Zeiten"
+    }
+
+    fn watch_words_setting(&self) -> &'static str {
+        "Beobachtete Wörter"
+    }
+
+    fn masked_words_setting(&self) -> &'static str {
+        "Maskierte Wörter"
+    }
+
+    fn hidden_notices_setting(&self) -> &'static str {
+        "Ausgeblendete Hinweise"
+    }
+
+    fn server_address_setting(&self) -> &'static str {
+        "Serveradresse"
+    }
+
+    fn username_setting(&self) -> &'static str {
+        "Benutzername"
+    }
+
+    fn auto_join_rooms_setting(&self) -> &'static str {
+        "Automatisch betretene Räume"
+    }
+
+    fn status_bar_setting(&self) -> &'static str {
+        "Statusleiste"
+    }
+
+    fn aliases_setting(&self) -> &'static str {
+        "Aliase"
+    }
+
+    fn none_setting(&self) -> &'static str {
+        "keine"
+    }
+
+    fn on_off(&self, is_on: bool) -> &'static str {
+        if is_on {
+            "an"
+        } else {
+            "aus"
+        }
+    }
+
+    fn welcome_title(&self) -> &'static str {
+        "Willkommen"
+    }
+
+    fn onboarding_intro(&self) -> &'static str {
+        "Richte den Client vor der ersten Verbindung ein, alles lässt sich später auf der Einstellungsseite ändern"
+    }
+
+    fn onboarding_step(&self, step: usize, step_count: usize) -> String {
+        format!("Schritt {step} von {step_count}")
+    }
+
+    fn pick_username(&self) -> &'static str {
+        "Der Name, unter dem du chatten möchtest, leer lassen um einen vom Server zu erhalten"
+    }
+
+    fn pick_server_address(&self) -> &'static str {
+        "Host und Port des Chat-Servers"
+    }
+
+    fn pick_theme(&self) -> &'static str {
+        "Wie die Oberfläche aussieht, hoher Kontrast kommt ohne Farben aus"
+    }
+
+    fn pick_auto_join_rooms(&self) -> &'static str {
+        "Die Räume, die direkt nach dem Verbinden betreten werden, durch Kommas getrennt, der erste wird geöffnet"
+    }
+
+    fn theme_setting(&self) -> &'static str {
+        "Design"
+    }
+
+    fn regular_theme(&self) -> &'static str {
+        "Normal"
+    }
+
+    fn to_continue(&self) -> &'static str {
+        "um fortzufahren"
+    }
+
+    fn to_save_and_connect(&self) -> &'static str {
+        "um die Einstellungen zu speichern und zu verbinden"
+    }
+
+    fn debug_title(&self) -> &'static str {
+        "Debug"
+    }
+
+    fn terminal_details(&self, width: u16, height: u16, is_focused: bool) -> String {
+        let focus = if is_focused {
+            "im Fokus"
+        } else {
+            "nicht im Fokus"
+        };
+        format!("Terminal: {width}x{height}, {focus}")
+    }
+
+    fn joined_rooms(&self, joined_count: usize, room_count: usize) -> String {
+        format!("{joined_count} von {room_count} Räumen beigetreten")
+    }
+
+    fn room_details(&self, room: &str, user_count: usize, stored_message_count: usize) -> String {
+        format!("#{room}: {user_count} Benutzer, {stored_message_count} Nachrichten gespeichert")
+    }
+
+    fn traffic_title(&self, frame_count: usize) -> String {
+        format!("Datenverkehr, {frame_count} Frames, neueste zuerst")
+    }
+
+    fn traffic_frame(&self, is_inbound: bool, at: &str, size: usize) -> String {
+        if is_inbound {
+            format!("empfangen um {at}, {size} Bytes")
+        } else {
+            format!("gesendet um {at}, {size} Bytes")
+        }
+    }
+
+    fn traffic_exported(&self, path: &str) -> String {
+        format!("Datenverkehr nach {path} exportiert")
+    }
+
+    fn traffic_not_exported(&self, err: &str) -> String {
+        format!("Datenverkehr konnte nicht exportiert werden: {err}")
+    }
+
+    fn to_toggle_traffic(&self) -> &'static str {
+        "um den Datenverkehr mit dem Server aufzuzeichnen oder damit aufzuhören"
+    }
+
+    fn to_export_traffic(&self) -> &'static str {
+        "um den aufgezeichneten Datenverkehr in eine Datei zu exportieren"
+    }
+
+    fn to_scroll_traffic(&self) -> &'static str {
+        "um durch den Datenverkehr zu blättern"
+    }
+
+    fn to_send_message(&self) -> &'static str {
+        "zum Senden"
+    }
+
+    fn participation(&self, user_id: &str, has_joined: bool) -> String {
+        if has_joined {
+            format!("{user_id} hat den Raum betreten")
+        } else {
+            format!("{user_id} hat den Raum verlassen")
+        }
+    }
+
+    fn room_full(&self, capacity: usize) -> String {
+        format!("Der Raum ist voll, er hat Platz für höchstens {capacity} Teilnehmer")
+    }
+
+    fn queue_position(&self, position: usize) -> String {
+        format!("Du bist Nummer {position} in der Warteschlange und trittst dem Raum bei, sobald ein Platz frei wird")
+    }
+
+    fn message_too_long(&self, length: usize, max_length: usize) -> String {
+        format!(
+            "Deine Nachricht wurde nicht gesendet, sie hat {length} Zeichen, erlaubt sind höchstens {max_length}"
+        )
+    }
+
+    fn command_not_understood(&self, message: &str) -> String {
+        format!("Dein Befehl wurde nicht verstanden, {message}")
+    }
+
+    fn message_not_permitted(&self) -> &'static str {
+        "Deine Nachricht wurde nicht gesendet, du darfst hier keine Nachrichten senden"
+    }
+
+    fn message_not_encrypted(&self) -> &'static str {
+        "Deine Nachricht wurde nicht gesendet, dieser Raum nimmt nur Ende-zu-Ende-verschlüsselte Nachrichten an"
+    }
+
+    fn message_to_archived_room(&self) -> &'static str {
+        "Deine Nachricht wurde nicht gesendet, der Raum wurde archiviert und kann nur noch gelesen werden"
+    }
+
+    fn message_rate_limited(&self, retry_after_secs: u64) -> String {
+        format!("Deine Nachricht wurde nicht gesendet, du sendest zu viele Nachrichten, versuche es in {retry_after_secs}s erneut")
+    }
+
+    fn message_blocked(&self) -> &'static str {
+        "Deine Nachricht wurde nicht gesendet, sie verstößt gegen die Regeln des Raums"
+    }
+
+    fn too_many_reminders(&self, max_reminders: usize) -> String {
+        format!("Deine Erinnerung wurde nicht gesetzt, du hast schon {max_reminders} ausstehende Erinnerungen")
+    }
+
+    fn undecryptable_message(&self, user_id: &str) -> String {
+        format!("@{user_id} hat eine Nachricht gesendet, die du nicht entschlüsseln kannst")
+    }
+
+    fn room_key_missing(&self) -> &'static str {
+        "Deine Nachricht wurde nicht gesendet, noch hat kein Mitglied den Schlüssel dieses Raums mit dir geteilt"
+    }
+
+    fn room_key_awaited(&self, fingerprint: &str) -> String {
+        format!("Warte darauf, dass ein Mitglied den Schlüssel dieses Raums teilt, nenne ihm den Fingerabdruck dieser Sitzung: {fingerprint}")
+    }
+
+    fn room_key_requested(&self, user_id: &str, fingerprint: &str) -> String {
+        format!("@{user_id} bittet von der Sitzung mit dem Fingerabdruck {fingerprint} aus um den Schlüssel dieses Raums, vergleiche ihn mit ihrem und teile den Schlüssel mit /share-key {fingerprint}")
+    }
+
+    fn room_key_shared(&self, user_id: &str) -> String {
+        format!("Du hast den Schlüssel dieses Raums mit @{user_id} geteilt")
+    }
+
+    fn no_room_key_request(&self, fingerprint: &str) -> String {
+        format!("Keine Sitzung mit dem Fingerabdruck {fingerprint} hat um den Schlüssel dieses Raums gebeten")
+    }
+
+    fn message_removed(&self) -> &'static str {
+        "Diese Nachricht wurde entfernt"
+    }
+
+    fn welcome_message_updated(&self, message: Option<&str>) -> String {
+        match message {
+            Some(message) => format!("Die Willkommensnachricht lautet jetzt „{message}“"),
+            None => String::from("Die Willkommensnachricht wurde entfernt"),
+        }
+    }
+
+    fn command_refused(&self, reason: &str) -> String {
+        format!("Befehl abgelehnt, {reason}")
+    }
+
+    fn group_membership_changed(&self, user_id: &str, group: &str, is_member: bool) -> String {
+        if is_member {
+            format!("{user_id} ist jetzt Mitglied der Gruppe {group}")
+        } else {
+            format!("{user_id} ist nicht mehr Mitglied der Gruppe {group}")
+        }
+    }
+
+    fn device_connected(&self, session_id: &str, address: &str) -> String {
+        format!("Eine neue Sitzung {session_id} wurde von {address} geöffnet")
+    }
+
+    fn device_disconnected(&self, session_id: &str) -> String {
+        format!("Die Sitzung {session_id} wurde beendet")
+    }
+
+    fn open_sessions(&self, sessions: &str) -> String {
+        format!("Deine offenen Sitzungen: {sessions}")
+    }
+
+    fn bot_token_issued(&self, bot_name: &str, user_id: &str, token: &str) -> String {
+        format!("Der Bot {bot_name} handelt als {user_id}, sein Token ist {token} und wird nicht erneut angezeigt")
+    }
+
+    fn bot_token_revoked(&self, bot_name: &str, is_revoked: bool) -> String {
+        if is_revoked {
+            format!("Das Token des Bots {bot_name} wurde widerrufen")
+        } else {
+            format!("Der Bot {bot_name} hat kein Token zum Widerrufen")
+        }
+    }
+
+    fn reminder_set(&self, remaining_time: &str) -> String {
+        format!("Alles klar, ich erinnere dich in {remaining_time}")
+    }
+
+    fn reminder_due(&self, content: &str) -> String {
+        format!("Erinnerung: {content}")
+    }
+
+    fn announcement(&self, message: &str) -> String {
+        format!("Ankündigung: {message}")
+    }
+
+    fn a_moment(&self) -> &'static str {
+        "einem Moment"
+    }
+
+    fn user_info(
+        &self,
+        user_id: &str,
+        presence: &str,
+        status_text: Option<&str>,
+        shared_rooms: &str,
+    ) -> String {
+        let status_text = status_text
+            .map(|status_text| format!("\n  Statusmeldung: {status_text}"))
+            .unwrap_or_default();
+
+        format!(
+            "Benutzer @{user_id}\n  Status: {presence}{status_text}\n  Gemeinsame Räume: {shared_rooms}"
+        )
+    }
+
+    fn user_search_results(&self, query: &str, users: &str) -> String {
+        format!("Benutzer passend zu '{query}': {users}")
+    }
+
+    fn room_info(&self, room: &str, description: &str, members: &str, owners: &str) -> String {
+        format!(
+            "Raum #{room}\n  Thema: {description}\n  Mitglieder: {members}\n  Besitzer: {owners}"
+        )
+    }
+
+    fn presence(&self, presence: Option<PresenceStatus>) -> &'static str {
+        match presence {
+            Some(PresenceStatus::Online) => "online",
+            Some(PresenceStatus::Away) => "abwesend",
+            None => "offline",
+        }
+    }
+
+    fn room_members(&self, count: usize, capacity: Option<usize>) -> String {
+        match capacity {
+            Some(capacity) => format!("{count} von {capacity}"),
+            None => count.to_string(),
+        }
+    }
+
+    fn room_stats(
+        &self,
+        room: &str,
+        activity: &str,
+        message_count: u32,
+        active_users: usize,
+        member_count: usize,
+        peak_concurrency: usize,
+    ) -> String {
+        let messages = match message_count {
+            1 => String::from("1 Nachricht"),
+            count => format!("{count} Nachrichten"),
+        };
+
+        format!("Raum #{room}\n  Letzte 24 Stunden: {activity} {messages}\n  Aktive Nutzer: {active_users}\n  Mitglieder: {member_count}, höchstens {peak_concurrency} gleichzeitig")
+    }
+
+    fn nobody(&self) -> &'static str {
+        "keine"
+    }
+
+    fn report_filed(&self, room: &str) -> String {
+        format!("Deine Meldung wurde an die Moderatoren von #{room} geschickt")
+    }
+
+    fn no_message_to_report(&self, user_id: &str) -> String {
+        format!("In diesem Raum gibt es keine Nachricht von @{user_id} zum Melden")
+    }
+
+    fn nothing_to_reveal(&self) -> &'static str {
+        "In diesem Raum gibt es keine Nachricht mit maskierten Wörtern zum Aufdecken"
+    }
+
+    fn message_report(
+        &self,
+        report_id: &str,
+        room: &str,
+        user_id: &str,
+        content: &str,
+        reason: &str,
+        reported_by: &str,
+    ) -> String {
+        format!("[{report_id}] #{room} @{user_id}: \"{content}\", gemeldet von @{reported_by}: {reason}")
+    }
+
+    fn message_reported(&self, report: &str) -> String {
+        format!("Neue Meldung {report}")
+    }
+
+    fn open_reports(&self, count: usize) -> String {
+        match count {
+            0 => String::from("Es gibt keine offenen Meldungen"),
+            1 => String::from("1 offene Meldung:"),
+            count => format!("{count} offene Meldungen:"),
+        }
+    }
+
+    fn report_resolved(&self, report_id: &str, is_resolved: bool) -> String {
+        if is_resolved {
+            format!("Die Meldung {report_id} wurde erledigt")
+        } else {
+            format!("Die Meldung {report_id} war bereits erledigt")
+        }
+    }
+
+    fn join_request_awaiting_approval(&self) -> &'static str {
+        "Dieser Raum braucht die Zustimmung eines Moderators, du kommst hinein, sobald deine Anfrage angenommen ist"
+    }
+
+    fn join_requested(&self, user_id: &str) -> String {
+        format!("@{user_id} möchte beitreten, lass sie mit /approve {user_id} hinein oder lehne mit /deny {user_id} ab")
+    }
+
+    fn own_join_request_decided(&self, is_approved: bool, decided_by: &str) -> String {
+        if is_approved {
+            format!("@{decided_by} hat deine Beitrittsanfrage angenommen")
+        } else {
+            format!("@{decided_by} hat deine Beitrittsanfrage abgelehnt")
+        }
+    }
+
+    fn join_request_decided(&self, user_id: &str, is_approved: bool, decided_by: &str) -> String {
+        if is_approved {
+            format!("@{decided_by} hat die Beitrittsanfrage von @{user_id} angenommen")
+        } else {
+            format!("@{decided_by} hat die Beitrittsanfrage von @{user_id} abgelehnt")
+        }
+    }
+
+    fn name_rejected(&self, name: &str, reason: &str) -> String {
+        format!("Name abgelehnt, '{name}' {reason}")
+    }
+
+    fn name_too_short(&self, min_length: usize) -> String {
+        format!("ist kürzer als {min_length} Zeichen")
+    }
+
+    fn name_too_long(&self, max_length: usize) -> String {
+        format!("ist länger als {max_length} Zeichen")
+    }
+
+    fn name_invalid_character(&self, character: &str) -> String {
+        format!("enthält das nicht erlaubte Zeichen {character}")
+    }
+
+    fn name_reserved(&self) -> &'static str {
+        "ist reserviert"
+    }
+
+    fn name_confusable(&self, taken_name: &str) -> String {
+        format!("ist mit dem bereits vergebenen Namen '{taken_name}' zu verwechseln")
+    }
+
+    fn name_taken(&self) -> &'static str {
+        "wird gerade von jemand anderem verwendet"
+    }
+
+    fn room_resynced(&self, missed_events: u64) -> String {
+        format!("{missed_events} Ereignisse verpasst, die neuesten Nachrichten wurden nachgeholt")
+    }
+
+    fn room_left(&self) -> &'static str {
+        "Du hast den Raum verlassen, wähle ihn aus, um wieder beizutreten"
+    }
+
+    fn room_archived(&self) -> &'static str {
+        "Der Raum wurde archiviert, sein Verlauf bleibt erhalten, aber er nimmt keine Nachrichten mehr an"
+    }
+
+    fn room_reopened(&self) -> &'static str {
+        "Der Raum ist offen, es können Nachrichten gesendet werden"
+    }
+
+    fn room_removed(&self) -> &'static str {
+        "Der Raum ist nicht mehr verfügbar, die erhaltenen Nachrichten bleiben zum Lesen erhalten"
+    }
+
+    fn room_description_changed(&self, description: &str) -> String {
+        format!("Die Beschreibung des Raums lautet jetzt „{description}“")
+    }
+
+    fn room_archival_scheduled(&self, remaining_time: &str) -> String {
+        format!("Der Raum wird in {remaining_time} archiviert")
+    }
+
+    fn invite_code_created(&self, code: &str, remaining_time: &str, max_uses: u32) -> String {
+        format!("Mit dem Einladungscode {code} können bis zu {max_uses} Nutzer innerhalb von {remaining_time} per /join-with-code beitreten")
+    }
+
+    fn participation_toggled(&self, is_shown: bool) -> &'static str {
+        if is_shown {
+            "Beitritte und Austritte werden in diesem Raum angezeigt, /joins blendet sie aus"
+        } else {
+            "Beitritte und Austritte werden in diesem Raum ausgeblendet, /joins zeigt sie an"
+        }
+    }
+
+    fn hidden_participation(&self, count: usize) -> String {
+        format!("{count} Beitritte/Austritte")
+    }
+
+    fn script_failed(&self, error: &str) -> String {
+        format!("Ein Skript ist fehlgeschlagen: {error}")
+    }
+
+    fn replay_ended(&self) -> &'static str {
+        "Die Wiedergabe ist beendet"
+    }
+
+    fn replay_read_only(&self) -> &'static str {
+        "Während einer Wiedergabe kannst du nur den Raum wechseln"
+    }
+}
@@ -0,0 +1,983 @@
+use comms::event::PresenceStatus;
+
+use super::Strings;
+
+pub struct English;
+
+impl Strings for English {
+    fn status_uninitialized(&self) -> &'static str {
+        "Uninitialized"
+    }
+
+    fn status_connecting(&self) -> &'static str {
+        "Connecting"
+    }
+
+    fn status_authorizing(&self) -> &'static str {
+        "Authorizing"
+    }
+
+    fn status_logging_in(&self) -> &'static str {
+        "Logging in"
+    }
+
+    fn status_connected(&self, addr: &str) -> String {
+        format!("Connected to {addr}")
+    }
+
+    fn status_connected_via(&self, addr: &str, endpoint: &str) -> String {
+        format!("Connected to {addr} ({endpoint})")
+    }
+
+    fn status_replaying(&self, path: &str) -> String {
+        format!("Replaying {path}")
+    }
+
+    fn status_errored(&self, err: &str) -> String {
+        format!("Errored: {err}")
+    }
+
+    fn login_rejected(&self, reason: &str) -> String {
+        format!("login rejected, {reason}")
+    }
+
+    fn user_id_rejected(&self, reason: &str) -> String {
+        format!("your user id {reason}")
+    }
+
+    fn session_closed(&self, reason: &str) -> String {
+        format!("session closed, {reason}")
+    }
+
+    fn session_replaced(&self, address: &str) -> String {
+        format!("session closed, you have logged in again from {address}")
+    }
+
+    fn redirected(&self, addr: &str) -> String {
+        format!("The server has moved the session to {addr}, reconnected")
+    }
+
+    fn server_address_title(&self) -> &'static str {
+        "Server Host and Port"
+    }
+
+    fn log_in_parts(&self) -> [&'static str; 2] {
+        ["Log in by visiting ", " and entering the code "]
+    }
+
+    fn press_to_connect_parts(&self) -> [&'static str; 2] {
+        ["Press ", " to connect"]
+    }
+
+    fn connecting_parts(&self) -> [&'static str; 2] {
+        ["Connecting, press ", " to cancel"]
+    }
+
+    fn address_missing_host(&self) -> &'static str {
+        "Enter the host before the port, e.g. localhost:8080"
+    }
+
+    fn address_missing_port(&self) -> &'static str {
+        "Enter the port after the host, e.g. localhost:8080"
+    }
+
+    fn address_invalid_port(&self) -> &'static str {
+        "The port is a number from 1 to 65535"
+    }
+
+    fn address_unbracketed_ipv6(&self) -> &'static str {
+        "Put IPv6 addresses in brackets, e.g. [::1]:8080"
+    }
+
+    fn address_invalid_ipv6(&self) -> &'static str {
+        "The brackets hold no valid IPv6 address"
+    }
+
+    fn error(&self, err: &str) -> String {
+        format!("Error: {err}")
+    }
+
+    fn terminal_too_small(
+        &self,
+        width: u16,
+        height: u16,
+        min_width: u16,
+        min_height: u16,
+    ) -> String {
+        format!("The terminal is too small ({width}x{height}), resize it to at least {min_width}x{min_height}")
+    }
+
+    fn press_to_log_in_parts(&self) -> [&'static str; 3] {
+        [
+            "Press ",
+            " to log in, leave the name empty to be given one by the server, or ",
+            " to try the server as a guest",
+        ]
+    }
+
+    fn waiting_for_login(&self) -> &'static str {
+        "Logging in..."
+    }
+
+    fn user_information_title(&self) -> &'static str {
+        "User Information"
+    }
+
+    fn user(&self, user_id: &str, session_count: usize) -> String {
+        if session_count > 1 {
+            format!("User: @{user_id} ({session_count} sessions)")
+        } else {
+            format!("User: @{user_id}")
+        }
+    }
+
+    fn chatting_for(&self, secs: usize) -> String {
+        format!("Chatting for: {secs} secs")
+    }
+
+    fn server(&self, status: &str) -> String {
+        format!("Server: {status}")
+    }
+
+    fn latency(&self, latency_ms: Option<u64>) -> String {
+        match latency_ms {
+            Some(latency_ms) => format!("{latency_ms} ms"),
+            None => String::from("– ms"),
+        }
+    }
+
+    fn unread_rooms(&self, count: usize) -> String {
+        match count {
+            0 => String::from("no unread rooms"),
+            1 => String::from("1 unread room"),
+            count => format!("{count} unread rooms"),
+        }
+    }
+
+    fn active_room_title(&self) -> &'static str {
+        "Active Room Information"
+    }
+
+    fn active_room_parts(&self) -> [&'static str; 2] {
+        ["on ", " for "]
+    }
+
+    fn no_room_selected(&self) -> &'static str {
+        "Join at least one room to start chatting!"
+    }
+
+    fn messages_title(&self) -> &'static str {
+        "Messages"
+    }
+
+    fn messages_loading_title(&self, received_count: usize, total_count: usize) -> String {
+        format!("Messages (loading history {received_count}/{total_count})")
+    }
+
+    fn room_users_title(&self, count: usize) -> String {
+        format!("Room Users ({count})")
+    }
+
+    fn usage_title(&self) -> &'static str {
+        "Usage"
+    }
+
+    fn rooms_title(&self) -> &'static str {
+        "Rooms"
+    }
+
+    fn recent_rooms_title(&self) -> &'static str {
+        "Recently left"
+    }
+
+    fn archived_rooms_title(&self) -> &'static str {
+        "Archived"
+    }
+
+    fn message_input_title(&self) -> &'static str {
+        "Message Input"
+    }
+
+    fn welcome_prefix(&self) -> &'static str {
+        "Welcome! "
+    }
+
+    fn notice_marker(&self) -> &'static str {
+        "[notice] "
+    }
+
+    fn warning_marker(&self) -> &'static str {
+        "[warning] "
+    }
+
+    fn error_marker(&self) -> &'static str {
+        "[error] "
+    }
+
+    fn active_section_marker(&self) -> &'static str {
+        " [active]"
+    }
+
+    fn selected_section_marker(&self) -> &'static str {
+        " [selected]"
+    }
+
+    fn too_long_marker(&self) -> &'static str {
+        " [too long]"
+    }
+
+    fn active_room_marker(&self) -> &'static str {
+        " (active)"
+    }
+
+    fn unread_room_marker(&self) -> &'static str {
+        " (unread)"
+    }
+
+    fn encrypted_room_marker(&self) -> &'static str {
+        " [e2e]"
+    }
+
+    fn archived_room_marker(&self) -> &'static str {
+        " (archived)"
+    }
+
+    fn removed_room_marker(&self) -> &'static str {
+        " (removed)"
+    }
+
+    fn recent_room_marker(&self) -> &'static str {
+        " (left)"
+    }
+
+    fn encryption_status(&self, has_room_key: bool) -> &'static str {
+        if has_room_key {
+            " (end-to-end encrypted)"
+        } else {
+            " (end-to-end encrypted, waiting for the room key)"
+        }
+    }
+
+    fn away_user_marker(&self) -> &'static str {
+        " (away)"
+    }
+
+    fn highlight_marker(&self) -> &'static str {
+        "[!] "
+    }
+
+    fn selected_message_marker(&self) -> &'static str {
+        "> "
+    }
+
+    fn masked_marker(&self) -> &'static str {
+        " [masked, /reveal]"
+    }
+
+    fn sending_marker(&self) -> &'static str {
+        " [sending]"
+    }
+
+    fn time_ago(&self, secs: u64) -> String {
+        match secs {
+            0..60 => String::from("just now"),
+            60..3600 => format!("{}m ago", secs / 60),
+            _ => format!("{}h ago", secs / 3600),
+        }
+    }
+
+    fn day(&self, month: u32, day: u32, year: Option<i32>) -> String {
+        const MONTHS: [&str; 12] = [
+            "January",
+            "February",
+            "March",
+            "April",
+            "May",
+            "June",
+            "July",
+            "August",
+            "September",
+            "October",
+            "November",
+            "December",
+        ];
+        let month = MONTHS[(month as usize - 1) % MONTHS.len()];
+
+        match year {
+            Some(year) => format!("{month} {day}, {year}"),
+            None => format!("{month} {day}"),
+        }
+    }
+
+    fn room_list_name(&self) -> &'static str {
+        "Room List"
+    }
+
+    fn message_input_name(&self) -> &'static str {
+        "Message Input"
+    }
+
+    fn select_widget(&self) -> &'static str {
+        "Select a widget"
+    }
+
+    fn to_exit(&self) -> &'static str {
+        "to exit"
+    }
+
+    fn to_hover_widgets(&self) -> &'static str {
+        "to hover widgets"
+    }
+
+    fn to_activate(&self, name: &str) -> String {
+        format!("to activate {name}")
+    }
+
+    fn to_cancel(&self) -> &'static str {
+        "to cancel"
+    }
+
+    fn select_room(&self) -> &'static str {
+        "Select the room to talk in"
+    }
+
+    fn to_navigate(&self) -> &'static str {
+        "to navigate"
+    }
+
+    fn to_join_room(&self) -> &'static str {
+        "to join room"
+    }
+
+    fn to_leave_room(&self) -> &'static str {
+        "to leave room"
+    }
+
+    fn to_rejoin_room(&self) -> &'static str {
+        "to rejoin the room left last"
+    }
+
+    fn to_toggle_category(&self) -> &'static str {
+        "to collapse or expand category"
+    }
+
+    fn enter_room_first(&self) -> &'static str {
+        "You can not send a message until you enter a room."
+    }
+
+    fn shorten_message(&self) -> &'static str {
+        "Your message is too long, shorten it to be able to send it"
+    }
+
+    fn type_message(&self) -> &'static str {
+        "Type your message to send a message to the active room"
+    }
+
+    fn to_complete(&self) -> &'static str {
+        "to complete a user, room or command"
+    }
+
+    fn to_browse_rooms(&self) -> &'static str {
+        "to browse the rooms"
+    }
+
+    fn select_message(&self) -> &'static str {
+        "Select a message of the active room"
+    }
+
+    fn to_select_messages(&self) -> &'static str {
+        "to select messages"
+    }
+
+    fn to_open_settings(&self) -> &'static str {
+        "to change the settings"
+    }
+
+    fn to_open_debug(&self) -> &'static str {
+        "to show the debug information"
+    }
+
+    fn to_go_back(&self) -> &'static str {
+        "to go back"
+    }
+
+    fn room_browser_title(&self) -> &'static str {
+        "Browse Rooms"
+    }
+
+    fn room_filter_title(&self) -> &'static str {
+        "Filter"
+    }
+
+    fn filter_rooms(&self) -> &'static str {
+        "Type to filter the rooms by their name or description"
+    }
+
+    fn no_rooms_found(&self) -> &'static str {
+        "No room matches the filter"
+    }
+
+    fn joined_room_marker(&self) -> &'static str {
+        " (joined)"
+    }
+
+    fn to_open_room(&self) -> &'static str {
+        "to open the room"
+    }
+
+    fn settings_title(&self) -> &'static str {
+        "Settings"
+    }
+
+    fn settings_saved_to_config(&self) -> &'static str {
+        "Changes take effect right away and are saved to the config file"
+    }
+
+    fn type_setting(&self) -> &'static str {
+        "Type the new value, words are separated by commas"
+    }
+
+    fn to_change_setting(&self) -> &'static str {
+        "to change the setting"
+    }
+
+    fn to_save_setting(&self) -> &'static str {
+        "to save the value"
+    }
+
+    fn read_only_marker(&self) -> &'static str {
+        " (set through the environment)"
+    }
+
+    fn language_setting(&self) -> &'static str {
+        "Language"
+    }
+
+    fn high_contrast_setting(&self) -> &'static str {
+        "High contrast"
+    }
+
+    fn ascii_setting(&self) -> &'static str {
+        "ASCII only"
+    }
+
+    fn linear_setting(&self) -> &'static str {
+        "Linear layout"
+    }
+
+    fn keymap_setting(&self) -> &'static str {
+        "Keymap"
+    }
+
+    fn auto_away_setting(&self) -> &'static str {
+        "Away while unfocused"
+    }
+
+    fn grouping_window_setting(&self) -> &'static str {
+        "Grouping window (seconds)"
+    }
+
+    fn time_format_setting(&self) -> &'static str {
+        "Time format (24h, 12h or %H:%M)"
+    }
+
+    fn time_zone_setting(&self) -> &'static str {
+        "Time zone"
+    }
+
+    fn relative_times_setting(&self) -> &'static str {
+        "Relative times"
+    }
+
+    fn watch_words_setting(&self) -> &'static str {
+        "Watch words"
+    }
+
+    fn masked_words_setting(&self) -> &'static str {
+        "Masked words"
+    }
+
+    fn hidden_notices_setting(&self) -> &'static str {
+        "Hidden notices"
+    }
+
+    fn server_address_setting(&self) -> &'static str {
+        "Server address"
+    }
+
+    fn username_setting(&self) -> &'static str {
+        "Username"
+    }
+
+    fn auto_join_rooms_setting(&self) -> &'static str {
+        "Auto-join rooms"
+    }
+
+    fn status_bar_setting(&self) -> &'static str {
+        "Status bar"
+    }
+
+    fn aliases_setting(&self) -> &'static str {
+        "Aliases"
+    }
+
+    fn none_setting(&self) -> &'static str {
+        "none"
+    }
+
+    fn on_off(&self, is_on: bool) -> &'static str {
+        if is_on {
+            "on"
+        } else {
+            "off"
+        }
+    }
+
+    fn welcome_title(&self) -> &'static str {
+        "Welcome"
+    }
+
+    fn onboarding_intro(&self) -> &'static str {
+        "Set up the client before connecting for the first time, everything can be changed later on the settings page"
+    }
+
+    fn onboarding_step(&self, step: usize, step_count: usize) -> String {
+        format!("Step {step} of {step_count}")
+    }
+
+    fn pick_username(&self) -> &'static str {
+        "The name you would like to chat as, leave it empty to be given one by the server"
+    }
+
+    fn pick_server_address(&self) -> &'static str {
+        "The host and port of the chat server"
+    }
+
+    fn pick_theme(&self) -> &'static str {
+        "How the interface looks, high contrast does not rely on colors"
+    }
+
+    fn pick_auto_join_rooms(&self) -> &'static str {
+        "The rooms to join right after connecting, separated by commas, the first one is opened"
+    }
+
+    fn theme_setting(&self) -> &'static str {
+        "Theme"
+    }
+
+    fn regular_theme(&self) -> &'static str {
+        "Regular"
+    }
+
+    fn to_continue(&self) -> &'static str {
+        "to continue"
+    }
+
+    fn to_save_and_connect(&self) -> &'static str {
+        "to save the settings and connect"
+    }
+
+    fn debug_title(&self) -> &'static str {
+        "Debug"
+    }
+
+    fn terminal_details(&self, width: u16, height: u16, is_focused: bool) -> String {
+        let focus = if is_focused { "focused" } else { "unfocused" };
+        format!("Terminal: {width}x{height}, {focus}")
+    }
+
+    fn joined_rooms(&self, joined_count: usize, room_count: usize) -> String {
+        format!("Joined {joined_count} of {room_count} rooms")
+    }
+
+    fn room_details(&self, room: &str, user_count: usize, stored_message_count: usize) -> String {
+        format!("#{room}: {user_count} users, {stored_message_count} messages kept")
+    }
+
+    fn traffic_title(&self, frame_count: usize) -> String {
+        format!("Traffic, {frame_count} frames, newest first")
+    }
+
+    fn traffic_frame(&self, is_inbound: bool, at: &str, size: usize) -> String {
+        if is_inbound {
+            format!("received at {at}, {size} bytes")
+        } else {
+            format!("sent at {at}, {size} bytes")
+        }
+    }
+
+    fn traffic_exported(&self, path: &str) -> String {
+        format!("Exported the traffic to {path}")
+    }
+
+    fn traffic_not_exported(&self, err: &str) -> String {
+        format!("Could not export the traffic: {err}")
+    }
+
+    fn to_toggle_traffic(&self) -> &'static str {
+        "to record the traffic with the server, or to stop"
+    }
+
+    fn to_export_traffic(&self) -> &'static str {
+        "to export the recorded traffic to a file"
+    }
+
+    fn to_scroll_traffic(&self) -> &'static str {
+        "to scroll through the traffic"
+    }
+
+    fn to_send_message(&self) -> &'static str {
+        "to send your message"
+    }
+
+    fn participation(&self, user_id: &str, has_joined: bool) -> String {
+        if has_joined {
+            format!("{user_id} has joined the room")
+        } else {
+            format!("{user_id} has left the room")
+        }
+    }
+
+    fn room_full(&self, capacity: usize) -> String {
+        format!("The room is full, it can have at most {capacity} users")
+    }
+
+    fn queue_position(&self, position: usize) -> String {
+        format!("You are #{position} in the queue, you will join the room once a slot frees up")
+    }
+
+    fn message_too_long(&self, length: usize, max_length: usize) -> String {
+        format!(
+            "Your message was not sent, it has {length} characters while at most {max_length} are allowed"
+        )
+    }
+
+    fn command_not_understood(&self, message: &str) -> String {
+        format!("Your command was not understood, {message}")
+    }
+
+    fn message_not_permitted(&self) -> &'static str {
+        "Your message was not sent, you are not allowed to send messages here"
+    }
+
+    fn message_not_encrypted(&self) -> &'static str {
+        "Your message was not sent, this room only accepts end-to-end encrypted messages"
+    }
+
+    fn message_to_archived_room(&self) -> &'static str {
+        "Your message was not sent, the room has been archived and is read-only"
+    }
+
+    fn message_rate_limited(&self, retry_after_secs: u64) -> String {
+        format!("Your message was not sent, you are sending messages too quickly, try again in {retry_after_secs}s")
+    }
+
+    fn message_blocked(&self) -> &'static str {
+        "Your message was not sent, it goes against the rules of the room"
+    }
+
+    fn too_many_reminders(&self, max_reminders: usize) -> String {
+        format!("Your reminder was not set, you already have {max_reminders} pending reminders")
+    }
+
+    fn undecryptable_message(&self, user_id: &str) -> String {
+        format!("@{user_id} sent a message you can not decrypt")
+    }
+
+    fn room_key_missing(&self) -> &'static str {
+        "Your message was not sent, no member has shared the key of this room with you yet"
+    }
+
+    fn room_key_awaited(&self, fingerprint: &str) -> String {
+        format!("Waiting for a member to share the key of this room, tell them the fingerprint of this session: {fingerprint}")
+    }
+
+    fn room_key_requested(&self, user_id: &str, fingerprint: &str) -> String {
+        format!("@{user_id} asks for the key of this room from the session with the fingerprint {fingerprint}, compare it with theirs and share the key with /share-key {fingerprint}")
+    }
+
+    fn room_key_shared(&self, user_id: &str) -> String {
+        format!("You have shared the key of this room with @{user_id}")
+    }
+
+    fn no_room_key_request(&self, fingerprint: &str) -> String {
+        format!("No session with the fingerprint {fingerprint} has asked for the key of this room")
+    }
+
+    fn message_removed(&self) -> &'static str {
+        "This message has been removed"
+    }
+
+    fn welcome_message_updated(&self, message: Option<&str>) -> String {
+        match message {
+            Some(message) => format!("The welcome message is now \"{message}\""),
+            None => String::from("The welcome message has been removed"),
+        }
+    }
+
+    fn command_refused(&self, reason: &str) -> String {
+        format!("Command refused, {reason}")
+    }
+
+    fn group_membership_changed(&self, user_id: &str, group: &str, is_member: bool) -> String {
+        if is_member {
+            format!("{user_id} is now a member of the group {group}")
+        } else {
+            format!("{user_id} is no longer a member of the group {group}")
+        }
+    }
+
+    fn device_connected(&self, session_id: &str, address: &str) -> String {
+        format!("A new session {session_id} has been opened from {address}")
+    }
+
+    fn device_disconnected(&self, session_id: &str) -> String {
+        format!("The session {session_id} has been closed")
+    }
+
+    fn open_sessions(&self, sessions: &str) -> String {
+        format!("Your open sessions: {sessions}")
+    }
+
+    fn bot_token_issued(&self, bot_name: &str, user_id: &str, token: &str) -> String {
+        format!("The bot {bot_name} acts as {user_id}, its token is {token} and will not be shown again")
+    }
+
+    fn bot_token_revoked(&self, bot_name: &str, is_revoked: bool) -> String {
+        if is_revoked {
+            format!("The token of the bot {bot_name} has been revoked")
+        } else {
+            format!("The bot {bot_name} has no token to revoke")
+        }
+    }
+
+    fn reminder_set(&self, remaining_time: &str) -> String {
+        format!("Got it, I will remind you in {remaining_time}")
+    }
+
+    fn reminder_due(&self, content: &str) -> String {
+        format!("Reminder: {content}")
+    }
+
+    fn announcement(&self, message: &str) -> String {
+        format!("Announcement: {message}")
+    }
+
+    fn a_moment(&self) -> &'static str {
+        "a moment"
+    }
+
+    fn user_info(
+        &self,
+        user_id: &str,
+        presence: &str,
+        status_text: Option<&str>,
+        shared_rooms: &str,
+    ) -> String {
+        let status_text = status_text
+            .map(|status_text| format!("\n  Status: {status_text}"))
+            .unwrap_or_default();
+
+        format!(
+            "User @{user_id}\n  Presence: {presence}{status_text}\n  Shared rooms: {shared_rooms}"
+        )
+    }
+
+    fn user_search_results(&self, query: &str, users: &str) -> String {
+        format!("Users matching '{query}': {users}")
+    }
+
+    fn room_info(&self, room: &str, description: &str, members: &str, owners: &str) -> String {
+        format!("Room #{room}\n  Topic: {description}\n  Members: {members}\n  Owners: {owners}")
+    }
+
+    fn presence(&self, presence: Option<PresenceStatus>) -> &'static str {
+        match presence {
+            Some(PresenceStatus::Online) => "online",
+            Some(PresenceStatus::Away) => "away",
+            None => "offline",
+        }
+    }
+
+    fn room_members(&self, count: usize, capacity: Option<usize>) -> String {
+        match capacity {
+            Some(capacity) => format!("{count} of {capacity}"),
+            None => count.to_string(),
+        }
+    }
+
+    fn room_stats(
+        &self,
+        room: &str,
+        activity: &str,
+        message_count: u32,
+        active_users: usize,
+        member_count: usize,
+        peak_concurrency: usize,
+    ) -> String {
+        let messages = match message_count {
+            1 => String::from("1 message"),
+            count => format!("{count} messages"),
+        };
+
+        format!("Room #{room}\n  Last 24 hours: {activity} {messages}\n  Active users: {active_users}\n  Members: {member_count}, at most {peak_concurrency} at once")
+    }
+
+    fn nobody(&self) -> &'static str {
+        "none"
+    }
+
+    fn report_filed(&self, room: &str) -> String {
+        format!("Your report has been sent to the moderators of #{room}")
+    }
+
+    fn no_message_to_report(&self, user_id: &str) -> String {
+        format!("There is no message of @{user_id} in this room to report")
+    }
+
+    fn nothing_to_reveal(&self) -> &'static str {
+        "There is no message with masked words in this room to reveal"
+    }
+
+    fn message_report(
+        &self,
+        report_id: &str,
+        room: &str,
+        user_id: &str,
+        content: &str,
+        reason: &str,
+        reported_by: &str,
+    ) -> String {
+        format!(
+            "[{report_id}] #{room} @{user_id}: \"{content}\", reported by @{reported_by}: {reason}"
+        )
+    }
+
+    fn message_reported(&self, report: &str) -> String {
+        format!("New report {report}")
+    }
+
+    fn open_reports(&self, count: usize) -> String {
+        match count {
+            0 => String::from("There are no open reports"),
+            1 => String::from("1 open report:"),
+            count => format!("{count} open reports:"),
+        }
+    }
+
+    fn report_resolved(&self, report_id: &str, is_resolved: bool) -> String {
+        if is_resolved {
+            format!("The report {report_id} has been resolved")
+        } else {
+            format!("The report {report_id} had already been resolved")
+        }
+    }
+
+    fn join_request_awaiting_approval(&self) -> &'static str {
+        "This room needs the approval of a moderator, you are let in once your request is approved"
+    }
+
+    fn join_requested(&self, user_id: &str) -> String {
+        format!("@{user_id} asks to join, let them in with /approve {user_id} or refuse with /deny {user_id}")
+    }
+
+    fn own_join_request_decided(&self, is_approved: bool, decided_by: &str) -> String {
+        if is_approved {
+            format!("@{decided_by} approved your request to join")
+        } else {
+            format!("@{decided_by} denied your request to join")
+        }
+    }
+
+    fn join_request_decided(&self, user_id: &str, is_approved: bool, decided_by: &str) -> String {
+        if is_approved {
+            format!("@{decided_by} approved the request of @{user_id} to join")
+        } else {
+            format!("@{decided_by} denied the request of @{user_id} to join")
+        }
+    }
+
+    fn name_rejected(&self, name: &str, reason: &str) -> String {
+        format!("Name refused, '{name}' {reason}")
+    }
+
+    fn name_too_short(&self, min_length: usize) -> String {
+        format!("is shorter than {min_length} characters")
+    }
+
+    fn name_too_long(&self, max_length: usize) -> String {
+        format!("is longer than {max_length} characters")
+    }
+
+    fn name_invalid_character(&self, character: &str) -> String {
+        format!("contains the character {character}, which is not allowed")
+    }
+
+    fn name_reserved(&self) -> &'static str {
+        "is reserved"
+    }
+
+    fn name_confusable(&self, taken_name: &str) -> String {
+        format!("can be mistaken for '{taken_name}', which is already taken")
+    }
+
+    fn name_taken(&self) -> &'static str {
+        "is in use by someone who is connected right now"
+    }
+
+    fn room_resynced(&self, missed_events: u64) -> String {
+        format!("Fell behind and missed {missed_events} events, caught up with the latest messages")
+    }
+
+    fn room_left(&self) -> &'static str {
+        "You have left the room, select it to join again"
+    }
+
+    fn room_archived(&self) -> &'static str {
+        "The room has been archived, its history is kept but it takes no more messages"
+    }
+
+    fn room_reopened(&self) -> &'static str {
+        "The room is open, messages can be sent to it"
+    }
+
+    fn room_removed(&self) -> &'static str {
+        "The room is no longer available, the messages received are kept to be read"
+    }
+
+    fn room_description_changed(&self, description: &str) -> String {
+        format!("The description of the room is now \"{description}\"")
+    }
+
+    fn room_archival_scheduled(&self, remaining_time: &str) -> String {
+        format!("The room will be archived in {remaining_time}")
+    }
+
+    fn invite_code_created(&self, code: &str, remaining_time: &str, max_uses: u32) -> String {
+        format!("Invite code {code} lets up to {max_uses} users join with /join-with-code within {remaining_time}")
+    }
+
+    fn participation_toggled(&self, is_shown: bool) -> &'static str {
+        if is_shown {
+            "Users joining and leaving are shown in this room, /joins hides them"
+        } else {
+            "Users joining and leaving are hidden in this room, /joins shows them"
+        }
+    }
+
+    fn hidden_participation(&self, count: usize) -> String {
+        format!("{count} joins/leaves")
+    }
+
+    fn script_failed(&self, error: &str) -> String {
+        format!("A script has failed: {error}")
+    }
+
+    fn replay_ended(&self) -> &'static str {
+        "The replay has ended"
+    }
+
+    fn replay_read_only(&self) -> &'static str {
+        "Only switching rooms is possible while a recording is replayed"
+    }
+}
@@ -0,0 +1,359 @@
+use comms::event::PresenceStatus;
+use serde::{Deserialize, Serialize};
+
+use self::{de::German, en::English};
+
+mod de;
+mod en;
+
+/// [Locale] is the language the user interface is shown in
+///
+/// Read from the `CHAT_TUI_LANGUAGE` environment variable, e.g. `de`, falling back to the language
+/// of the `LANG` environment variable, e.g. `de_DE.UTF-8`, and English if neither is supported.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    English,
+    German,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::English, Locale::German];
+
+    pub fn from_env() -> Self {
+        ["CHAT_TUI_LANGUAGE", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find_map(|value| Self::parse(&value))
+            .unwrap_or_default()
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        // only the language matters, e.g. `de` of `de_DE.UTF-8`
+        let language = value
+            .split(['_', '-', '.'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        match language.as_str() {
+            "en" => Some(Locale::English),
+            "de" => Some(Locale::German),
+            _ => None,
+        }
+    }
+
+    /// The name of the language in the language itself
+    pub fn name(&self) -> &'static str {
+        match self {
+            Locale::English => "English",
+            Locale::German => "Deutsch",
+        }
+    }
+
+    /// The translations of the user facing texts
+    pub fn strings(&self) -> &'static dyn Strings {
+        match self {
+            Locale::English => &English,
+            Locale::German => &German,
+        }
+    }
+}
+
+/// [Strings] holds every user facing text of the user interface in one language
+pub trait Strings: Sync {
+    // connection
+    fn status_uninitialized(&self) -> &'static str;
+    fn status_connecting(&self) -> &'static str;
+    fn status_authorizing(&self) -> &'static str;
+    fn status_logging_in(&self) -> &'static str;
+    fn status_connected(&self, addr: &str) -> String;
+    fn status_connected_via(&self, addr: &str, endpoint: &str) -> String;
+    fn status_replaying(&self, path: &str) -> String;
+    fn status_errored(&self, err: &str) -> String;
+    fn login_rejected(&self, reason: &str) -> String;
+    /// Why the server does not accept the user id, completed by one of the name rejections below
+    fn user_id_rejected(&self, reason: &str) -> String;
+    fn session_closed(&self, reason: &str) -> String;
+    /// The user has logged in elsewhere and the server only keeps their newest session
+    fn session_replaced(&self, address: &str) -> String;
+    /// The server has handed the session to another one, the client has followed it
+    fn redirected(&self, addr: &str) -> String;
+
+    // connect page
+    fn server_address_title(&self) -> &'static str;
+    /// The parts around the page to visit and the code to enter while logging in
+    fn log_in_parts(&self) -> [&'static str; 2];
+    /// The parts around the key to press for connecting
+    fn press_to_connect_parts(&self) -> [&'static str; 2];
+    /// The parts around the key to press for giving up on the connection being established
+    fn connecting_parts(&self) -> [&'static str; 2];
+    /// Why the address entered can not be connected to, shown instead of how to connect
+    fn address_missing_host(&self) -> &'static str;
+    fn address_missing_port(&self) -> &'static str;
+    fn address_invalid_port(&self) -> &'static str;
+    fn address_unbracketed_ipv6(&self) -> &'static str;
+    fn address_invalid_ipv6(&self) -> &'static str;
+    fn error(&self, err: &str) -> String;
+    /// Shown instead of the pages while the terminal is smaller than they need
+    fn terminal_too_small(
+        &self,
+        width: u16,
+        height: u16,
+        min_width: u16,
+        min_height: u16,
+    ) -> String;
+
+    // login page
+    /// The parts around the keys to press for logging in, and for logging in as a guest
+    fn press_to_log_in_parts(&self) -> [&'static str; 3];
+    /// Shown while the server has not answered the login yet
+    fn waiting_for_login(&self) -> &'static str;
+
+    // chat page
+    fn user_information_title(&self) -> &'static str;
+    fn user(&self, user_id: &str, session_count: usize) -> String;
+    fn chatting_for(&self, secs: usize) -> String;
+    fn server(&self, status: &str) -> String;
+    /// The round trip time to the server, None until it has been measured
+    fn latency(&self, latency_ms: Option<u64>) -> String;
+    fn unread_rooms(&self, count: usize) -> String;
+    fn active_room_title(&self) -> &'static str;
+    /// The parts before the name and the description of the active room
+    fn active_room_parts(&self) -> [&'static str; 2];
+    fn no_room_selected(&self) -> &'static str;
+    fn messages_title(&self) -> &'static str;
+    /// The title of the messages while the history of the room streams in
+    fn messages_loading_title(&self, received_count: usize, total_count: usize) -> String;
+    fn room_users_title(&self, count: usize) -> String;
+    fn usage_title(&self) -> &'static str;
+    fn rooms_title(&self) -> &'static str;
+    /// Heads the archived rooms at the end of the room list
+    fn archived_rooms_title(&self) -> &'static str;
+    /// Heads the rooms the user has left lately at the start of the room list
+    fn recent_rooms_title(&self) -> &'static str;
+    fn message_input_title(&self) -> &'static str;
+    fn welcome_prefix(&self) -> &'static str;
+    fn notice_marker(&self) -> &'static str;
+    fn warning_marker(&self) -> &'static str;
+    fn error_marker(&self) -> &'static str;
+    fn active_section_marker(&self) -> &'static str;
+    fn selected_section_marker(&self) -> &'static str;
+    fn too_long_marker(&self) -> &'static str;
+    fn active_room_marker(&self) -> &'static str;
+    fn unread_room_marker(&self) -> &'static str;
+    fn encrypted_room_marker(&self) -> &'static str;
+    fn archived_room_marker(&self) -> &'static str;
+    /// Follows the rooms the server no longer lists
+    fn removed_room_marker(&self) -> &'static str;
+    fn recent_room_marker(&self) -> &'static str;
+    fn encryption_status(&self, has_room_key: bool) -> &'static str;
+    fn away_user_marker(&self) -> &'static str;
+    fn highlight_marker(&self) -> &'static str;
+    fn selected_message_marker(&self) -> &'static str;
+    /// Appended to a message with masked words, until the user reveals it
+    fn masked_marker(&self) -> &'static str;
+    /// Appended to a message the user has sent, until the server has broadcast it
+    fn sending_marker(&self) -> &'static str;
+    /// A day as shown between the messages of two days, e.g. `March 3`, the year is given for other years
+    fn day(&self, month: u32, day: u32, year: Option<i32>) -> String;
+    /// How long ago a message was sent, e.g. `2m ago`, for times shown relative to now
+    fn time_ago(&self, secs: u64) -> String;
+
+    // usage
+    fn room_list_name(&self) -> &'static str;
+    fn message_input_name(&self) -> &'static str;
+    fn select_widget(&self) -> &'static str;
+    fn to_exit(&self) -> &'static str;
+    fn to_hover_widgets(&self) -> &'static str;
+    fn to_activate(&self, name: &str) -> String;
+    fn to_cancel(&self) -> &'static str;
+    fn select_room(&self) -> &'static str;
+    fn to_navigate(&self) -> &'static str;
+    fn to_join_room(&self) -> &'static str;
+    fn to_leave_room(&self) -> &'static str;
+    fn to_toggle_category(&self) -> &'static str;
+    fn to_rejoin_room(&self) -> &'static str;
+    fn enter_room_first(&self) -> &'static str;
+    fn shorten_message(&self) -> &'static str;
+    fn type_message(&self) -> &'static str;
+    fn to_send_message(&self) -> &'static str;
+    fn to_complete(&self) -> &'static str;
+    fn to_browse_rooms(&self) -> &'static str;
+    fn select_message(&self) -> &'static str;
+    fn to_select_messages(&self) -> &'static str;
+    fn to_open_settings(&self) -> &'static str;
+    fn to_open_debug(&self) -> &'static str;
+    fn to_go_back(&self) -> &'static str;
+
+    // room browser page
+    fn room_browser_title(&self) -> &'static str;
+    fn room_filter_title(&self) -> &'static str;
+    fn filter_rooms(&self) -> &'static str;
+    fn no_rooms_found(&self) -> &'static str;
+    fn joined_room_marker(&self) -> &'static str;
+    fn to_open_room(&self) -> &'static str;
+
+    // settings page
+    fn settings_title(&self) -> &'static str;
+    /// Where the changed settings are kept
+    fn settings_saved_to_config(&self) -> &'static str;
+    fn type_setting(&self) -> &'static str;
+    fn to_change_setting(&self) -> &'static str;
+    fn to_save_setting(&self) -> &'static str;
+    /// Follows the settings which are only set up through environment variables
+    fn read_only_marker(&self) -> &'static str;
+    fn language_setting(&self) -> &'static str;
+    fn high_contrast_setting(&self) -> &'static str;
+    fn ascii_setting(&self) -> &'static str;
+    fn linear_setting(&self) -> &'static str;
+    fn keymap_setting(&self) -> &'static str;
+    fn auto_away_setting(&self) -> &'static str;
+    fn grouping_window_setting(&self) -> &'static str;
+    fn time_format_setting(&self) -> &'static str;
+    fn time_zone_setting(&self) -> &'static str;
+    fn relative_times_setting(&self) -> &'static str;
+    fn watch_words_setting(&self) -> &'static str;
+    fn masked_words_setting(&self) -> &'static str;
+    fn hidden_notices_setting(&self) -> &'static str;
+    fn server_address_setting(&self) -> &'static str;
+    fn username_setting(&self) -> &'static str;
+    fn auto_join_rooms_setting(&self) -> &'static str;
+    fn status_bar_setting(&self) -> &'static str;
+    fn aliases_setting(&self) -> &'static str;
+    /// The value of a setting listing nothing, e.g. no aliases
+    fn none_setting(&self) -> &'static str;
+    fn on_off(&self, is_on: bool) -> &'static str;
+
+    // onboarding page
+    fn welcome_title(&self) -> &'static str;
+    /// What the wizard is for, shown along with the keys
+    fn onboarding_intro(&self) -> &'static str;
+    fn onboarding_step(&self, step: usize, step_count: usize) -> String;
+    fn pick_username(&self) -> &'static str;
+    fn pick_server_address(&self) -> &'static str;
+    fn pick_theme(&self) -> &'static str;
+    fn pick_auto_join_rooms(&self) -> &'static str;
+    fn theme_setting(&self) -> &'static str;
+    fn regular_theme(&self) -> &'static str;
+    fn to_continue(&self) -> &'static str;
+    fn to_save_and_connect(&self) -> &'static str;
+
+    // debug page
+    fn debug_title(&self) -> &'static str;
+    fn terminal_details(&self, width: u16, height: u16, is_focused: bool) -> String;
+    fn joined_rooms(&self, joined_count: usize, room_count: usize) -> String;
+    fn room_details(&self, room: &str, user_count: usize, stored_message_count: usize) -> String;
+    fn traffic_title(&self, frame_count: usize) -> String;
+    fn traffic_frame(&self, is_inbound: bool, at: &str, size: usize) -> String;
+    fn traffic_exported(&self, path: &str) -> String;
+    fn traffic_not_exported(&self, err: &str) -> String;
+    fn to_toggle_traffic(&self) -> &'static str;
+    fn to_export_traffic(&self) -> &'static str;
+    fn to_scroll_traffic(&self) -> &'static str;
+
+    // notifications
+    fn participation(&self, user_id: &str, has_joined: bool) -> String;
+    fn room_full(&self, capacity: usize) -> String;
+    fn queue_position(&self, position: usize) -> String;
+    fn message_too_long(&self, length: usize, max_length: usize) -> String;
+    fn command_not_understood(&self, message: &str) -> String;
+    fn message_not_permitted(&self) -> &'static str;
+    fn message_not_encrypted(&self) -> &'static str;
+    fn message_to_archived_room(&self) -> &'static str;
+    fn message_rate_limited(&self, retry_after_secs: u64) -> String;
+    fn message_blocked(&self) -> &'static str;
+    fn too_many_reminders(&self, max_reminders: usize) -> String;
+    fn undecryptable_message(&self, user_id: &str) -> String;
+    fn room_key_missing(&self) -> &'static str;
+    fn room_key_awaited(&self, fingerprint: &str) -> String;
+    fn room_key_requested(&self, user_id: &str, fingerprint: &str) -> String;
+    fn room_key_shared(&self, user_id: &str) -> String;
+    fn no_room_key_request(&self, fingerprint: &str) -> String;
+    fn message_removed(&self) -> &'static str;
+    fn welcome_message_updated(&self, message: Option<&str>) -> String;
+    fn command_refused(&self, reason: &str) -> String;
+    fn group_membership_changed(&self, user_id: &str, group: &str, is_member: bool) -> String;
+    fn device_connected(&self, session_id: &str, address: &str) -> String;
+    fn device_disconnected(&self, session_id: &str) -> String;
+    fn open_sessions(&self, sessions: &str) -> String;
+    fn bot_token_issued(&self, bot_name: &str, user_id: &str, token: &str) -> String;
+    fn bot_token_revoked(&self, bot_name: &str, is_revoked: bool) -> String;
+    fn reminder_set(&self, remaining_time: &str) -> String;
+    fn reminder_due(&self, content: &str) -> String;
+    fn announcement(&self, message: &str) -> String;
+    fn a_moment(&self) -> &'static str;
+    /// The lines describing a user looked up with `/whois`
+    fn user_info(
+        &self,
+        user_id: &str,
+        presence: &str,
+        status_text: Option<&str>,
+        shared_rooms: &str,
+    ) -> String;
+    /// The users found with `/users`, listed with their presence
+    fn user_search_results(&self, query: &str, users: &str) -> String;
+    /// The lines describing a room looked up with `/roominfo`
+    fn room_info(&self, room: &str, description: &str, members: &str, owners: &str) -> String;
+    fn presence(&self, presence: Option<PresenceStatus>) -> &'static str;
+    fn room_members(&self, count: usize, capacity: Option<usize>) -> String;
+    /// The activity is a sparkline of the messages per hour in the last 24 hours
+    fn room_stats(
+        &self,
+        room: &str,
+        activity: &str,
+        message_count: u32,
+        active_users: usize,
+        member_count: usize,
+        peak_concurrency: usize,
+    ) -> String;
+    fn nobody(&self) -> &'static str;
+    fn report_filed(&self, room: &str) -> String;
+    fn no_message_to_report(&self, user_id: &str) -> String;
+    fn nothing_to_reveal(&self) -> &'static str;
+    /// A single line describing a report, shown to moderators
+    fn message_report(
+        &self,
+        report_id: &str,
+        room: &str,
+        user_id: &str,
+        content: &str,
+        reason: &str,
+        reported_by: &str,
+    ) -> String;
+    fn message_reported(&self, report: &str) -> String;
+    /// The heading of the reports listed with `/reports`
+    fn open_reports(&self, count: usize) -> String;
+    fn report_resolved(&self, report_id: &str, is_resolved: bool) -> String;
+    // join requests
+    fn join_request_awaiting_approval(&self) -> &'static str;
+    fn join_requested(&self, user_id: &str) -> String;
+    fn own_join_request_decided(&self, is_approved: bool, decided_by: &str) -> String;
+    fn join_request_decided(&self, user_id: &str, is_approved: bool, decided_by: &str) -> String;
+    // names, worded to follow the name they are about
+    fn name_rejected(&self, name: &str, reason: &str) -> String;
+    fn name_too_short(&self, min_length: usize) -> String;
+    fn name_too_long(&self, max_length: usize) -> String;
+    fn name_invalid_character(&self, character: &str) -> String;
+    fn name_reserved(&self) -> &'static str;
+    fn name_confusable(&self, taken_name: &str) -> String;
+    fn name_taken(&self) -> &'static str;
+    /// The user fell behind the events of a room, e.g. on a slow connection
+    fn room_resynced(&self, missed_events: u64) -> String;
+    fn room_left(&self) -> &'static str;
+    fn room_archived(&self) -> &'static str;
+    fn room_reopened(&self) -> &'static str;
+    fn room_removed(&self) -> &'static str;
+    fn room_description_changed(&self, description: &str) -> String;
+    fn room_archival_scheduled(&self, remaining_time: &str) -> String;
+    fn invite_code_created(&self, code: &str, remaining_time: &str, max_uses: u32) -> String;
+    fn participation_toggled(&self, is_shown: bool) -> &'static str;
+    /// Stands in for a run of joins and leaves hidden in the room
+    fn hidden_participation(&self, count: usize) -> String;
+    // scripts
+    fn script_failed(&self, error: &str) -> String;
+    // replay
+    fn replay_ended(&self) -> &'static str;
+    fn replay_read_only(&self) -> &'static str;
+}
@@ -2,8 +2,26 @@ use state_store::StateStore;
 use termination::create_termination;
 use ui_management::UiManager;
 
+mod accessibility;
+mod aliases;
+mod config;
+mod e2e;
+mod highlights;
+mod inline_images;
+mod keymap;
+mod locale;
+mod notice_filter;
+mod notify_hook;
+mod oidc;
+mod presentation;
+mod profanity;
+mod replay;
+mod scripting;
+mod server_address;
 mod state_store;
+mod status_bar;
 mod termination;
+mod time_format;
 mod ui_management;
 
 use termination::{Interrupted, Terminator};
@@ -11,7 +29,7 @@ use termination::{Interrupted, Terminator};
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let (terminator, mut interrupt_rx) = create_termination();
-    let (state_store, state_rx) = StateStore::new();
+    let (state_store, state_rx) = StateStore::new()?;
     let (ui_manager, action_rx) = UiManager::new();
 
     tokio::try_join!(
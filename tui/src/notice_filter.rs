@@ -0,0 +1,83 @@
+use std::collections::BTreeMap;
+
+use crate::state_store::{MessageBoxItem, NoticeKind};
+
+/// [NoticeFilter] decides which kinds of notices are kept out of the message pane, e.g. users joining busy rooms
+///
+/// Read from the `CHAT_TUI_HIDDEN_NOTICES` environment variable as a comma separated list of [NoticeKind]s,
+/// e.g. `join,leave`, unknown kinds are ignored. Every notice is shown if the variable is not set.
+/// Whether users joining and leaving are shown can be switched for every room, regardless of the hidden kinds.
+/// Hidden notices are still received, e.g. the list of users in a room keeps up with them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NoticeFilter {
+    hidden_kinds: Vec<NoticeKind>,
+    /// Whether joins and leaves are shown, by room, for the rooms the user has switched them in
+    room_participation: BTreeMap<String, bool>,
+}
+
+impl NoticeFilter {
+    pub fn from_env() -> Self {
+        std::env::var("CHAT_TUI_HIDDEN_NOTICES")
+            .map(|hidden_kinds| Self::parse(&hidden_kinds))
+            .unwrap_or_default()
+    }
+
+    /// Reads the comma separated kinds, e.g. as typed by the user
+    pub fn parse(hidden_kinds: &str) -> Self {
+        let mut filter = NoticeFilter::default();
+        for kind in hidden_kinds.split(',').filter_map(NoticeKind::parse) {
+            if !filter.hidden_kinds.contains(&kind) {
+                filter.hidden_kinds.push(kind);
+            }
+        }
+
+        filter
+    }
+
+    pub fn hidden_kinds(&self) -> &[NoticeKind] {
+        &self.hidden_kinds
+    }
+
+    pub fn set_hidden_kinds(&mut self, hidden_kinds: Vec<NoticeKind>) {
+        self.hidden_kinds = hidden_kinds;
+    }
+
+    pub fn room_participation(&self) -> &BTreeMap<String, bool> {
+        &self.room_participation
+    }
+
+    pub fn set_room_participation(&mut self, room_participation: BTreeMap<String, bool>) {
+        self.room_participation = room_participation;
+    }
+
+    /// Whether users joining and leaving the room are shown, they are not if either kind is hidden
+    pub fn shows_participation(&self, room: &str) -> bool {
+        self.room_participation
+            .get(room)
+            .copied()
+            .unwrap_or_else(|| {
+                !self.hidden_kinds.contains(&NoticeKind::Join)
+                    && !self.hidden_kinds.contains(&NoticeKind::Leave)
+            })
+    }
+
+    /// Hides the users joining and leaving the room if they are shown, or shows them, returns whether they are shown
+    pub fn toggle_participation(&mut self, room: &str) -> bool {
+        let is_shown = !self.shows_participation(room);
+        self.room_participation.insert(String::from(room), is_shown);
+
+        is_shown
+    }
+
+    /// Whether the item of the room is a notice of a hidden kind, messages are always shown
+    pub fn is_hidden(&self, room: &str, item: &MessageBoxItem) -> bool {
+        let MessageBoxItem::Notification(notice) = item else {
+            return false;
+        };
+
+        match (notice.kind(), self.room_participation.get(room)) {
+            (NoticeKind::Join | NoticeKind::Leave, Some(is_shown)) => !is_shown,
+            (kind, _) => self.hidden_kinds.contains(&kind),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::process::Stdio;
+
+use comms::event;
+use tokio::process::Command;
+
+use crate::highlights::Highlights;
+
+/// [NotifyHook] is an external command run for every highlighted message, e.g. to play a sound when mentioned
+///
+/// Read from the `CHAT_TUI_NOTIFY_COMMAND` environment variable and run by the shell,
+/// with the metadata of the message in the `CHAT_NOTIFY_ROOM`, `CHAT_NOTIFY_USER` and
+/// `CHAT_NOTIFY_CONTENT` environment variables. No command is run if the variable is not set.
+#[derive(Debug, Clone)]
+pub struct NotifyHook {
+    command: String,
+}
+
+impl NotifyHook {
+    pub fn from_env() -> Option<Self> {
+        std::env::var("CHAT_TUI_NOTIFY_COMMAND")
+            .ok()
+            .filter(|command| !command.trim().is_empty())
+            .map(|command| NotifyHook { command })
+    }
+
+    /// Run the command if the event is a message highlighted for the given user, see [Highlights]
+    /// The command runs in the background, its output is discarded since the terminal belongs to the user interface
+    pub fn handle_server_event(
+        &self,
+        event: &event::Event,
+        user_id: &str,
+        highlights: &Highlights,
+    ) {
+        let event::Event::UserMessage(event) = event else {
+            return;
+        };
+
+        if !highlights.is_highlighted(user_id, &event.user_id, &event.content) {
+            return;
+        }
+
+        let mut command = shell_command(&self.command);
+        command
+            .env("CHAT_NOTIFY_ROOM", &event.room)
+            .env("CHAT_NOTIFY_USER", &event.user_id)
+            .env("CHAT_NOTIFY_CONTENT", &event.content)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+
+        if let Ok(mut child) = command.spawn() {
+            // wait for the command in the background, so it does not linger as a zombie process
+            tokio::spawn(async move {
+                let _ = child.wait().await;
+            });
+        }
+    }
+}
+
+/// The command run by the shell of the platform
+#[cfg(unix)]
+pub(crate) fn shell_command(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+pub(crate) fn shell_command(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
@@ -0,0 +1,153 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use serde::Deserialize;
+
+/// The grant type of the OAuth 2.0 device authorization flow, see RFC 8628
+const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+/// The polling interval suggested by RFC 8628 if the provider does not tell one
+const DEFAULT_POLL_INTERVAL_SECS: u64 = 5;
+
+/// [OidcConfig] holds the identity provider the client obtains its tokens from
+///
+/// Read from the `CHAT_OIDC_ISSUER` and `CHAT_OIDC_CLIENT_ID` environment variables,
+/// the client connects without a token if they are not set.
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    issuer: String,
+    client_id: String,
+}
+
+impl OidcConfig {
+    pub fn from_env() -> Option<Self> {
+        Some(OidcConfig {
+            issuer: std::env::var("CHAT_OIDC_ISSUER").ok()?,
+            client_id: std::env::var("CHAT_OIDC_CLIENT_ID").ok()?,
+        })
+    }
+}
+
+/// The part of the OIDC discovery document the client needs
+#[derive(Debug, Deserialize)]
+struct ProviderMetadata {
+    device_authorization_endpoint: String,
+    token_endpoint: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeviceAuthorizationResponse {
+    device_code: String,
+    user_code: String,
+    // some providers predate the RFC and call it verification_url
+    #[serde(alias = "verification_url")]
+    verification_uri: String,
+    interval: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum TokenResponse {
+    Token {
+        id_token: String,
+    },
+    Error {
+        error: String,
+        error_description: Option<String>,
+    },
+}
+
+/// [DeviceAuthorization] is a pending login, completed by the user on another device
+#[derive(Debug)]
+pub struct DeviceAuthorization {
+    /// The page the user has to visit
+    pub verification_uri: String,
+    /// The code the user has to enter on the page
+    pub user_code: String,
+    device_code: String,
+    token_endpoint: String,
+    interval: Duration,
+}
+
+/// Start a device authorization with the identity provider
+pub async fn start_device_authorization(
+    http_client: &reqwest::Client,
+    config: &OidcConfig,
+) -> anyhow::Result<DeviceAuthorization> {
+    let provider_metadata: ProviderMetadata = http_client
+        .get(format!(
+            "{}/.well-known/openid-configuration",
+            config.issuer.trim_end_matches('/')
+        ))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .context("could not read the discovery document of the identity provider")?;
+
+    let response: DeviceAuthorizationResponse = http_client
+        .post(&provider_metadata.device_authorization_endpoint)
+        .form(&[
+            ("client_id", config.client_id.as_str()),
+            ("scope", "openid"),
+        ])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .context("could not start the device authorization")?;
+
+    Ok(DeviceAuthorization {
+        verification_uri: response.verification_uri,
+        user_code: response.user_code,
+        device_code: response.device_code,
+        token_endpoint: provider_metadata.token_endpoint,
+        interval: Duration::from_secs(response.interval.unwrap_or(DEFAULT_POLL_INTERVAL_SECS)),
+    })
+}
+
+/// Poll the identity provider until the user completes the authorization
+/// Returns the id token to present to the server
+pub async fn wait_for_id_token(
+    http_client: &reqwest::Client,
+    config: &OidcConfig,
+    authorization: &DeviceAuthorization,
+) -> anyhow::Result<String> {
+    let mut interval = authorization.interval;
+
+    loop {
+        tokio::time::sleep(interval).await;
+
+        // error responses come with a 400 status, hence the status is not checked
+        let response: TokenResponse = http_client
+            .post(&authorization.token_endpoint)
+            .form(&[
+                ("grant_type", DEVICE_CODE_GRANT_TYPE),
+                ("device_code", authorization.device_code.as_str()),
+                ("client_id", config.client_id.as_str()),
+            ])
+            .send()
+            .await?
+            .json()
+            .await
+            .context("could not read the token response")?;
+
+        match response {
+            TokenResponse::Token { id_token } => return Ok(id_token),
+            TokenResponse::Error { error, .. } if error == "authorization_pending" => {}
+            TokenResponse::Error { error, .. } if error == "slow_down" => {
+                interval += Duration::from_secs(DEFAULT_POLL_INTERVAL_SECS);
+            }
+            TokenResponse::Error {
+                error,
+                error_description,
+            } => {
+                return Err(anyhow::anyhow!(
+                    "authorization failed: {}",
+                    error_description.unwrap_or(error)
+                ));
+            }
+        }
+    }
+}
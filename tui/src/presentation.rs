@@ -0,0 +1,798 @@
+use chrono::{DateTime, Datelike, Local, NaiveDate};
+use ratatui::{
+    style::{Color, Style, Stylize},
+    text::{Line, Span, Text},
+};
+
+use crate::{
+    accessibility::Accessibility,
+    inline_images::{ImageArt, Pixel},
+    locale::{Locale, Strings},
+    profanity::ProfanityFilter,
+    server_address::AddressProblem,
+    state_store::{
+        ConnectionError, LinkPreview, MessageBoxItem, Notice, ServerConnectionStatus, Severity,
+        TrafficFrame,
+    },
+    time_format::TimeFormat,
+};
+use comms::{event, transport::client::FrameDirection};
+
+/// [Theme] holds the styles of the messages and users shown on the chat page
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub message: Style,
+    pub notice: Style,
+    /// Notices of something that did not go as expected
+    pub warning_notice: Style,
+    /// Notices of something the user asked for having failed
+    pub error_notice: Style,
+    pub welcome_prefix: Style,
+    pub welcome: Style,
+    pub away_user: Style,
+    /// Messages mentioning the user or one of their watch words
+    pub highlighted_message: Style,
+    /// Messages the user has sent
+    pub own_message: Style,
+    /// Messages the user is sending, until the server has broadcast them
+    pub pending_message: Style,
+    /// The message the user has selected in the message pane
+    pub selected_message: Style,
+    /// The lines of a message sent as code
+    pub code: Style,
+    /// Patched onto the style of a message sent as an action, e.g. `/me waves`
+    pub action: Style,
+    /// The preview of a link shown below its message
+    pub link_preview: Style,
+    /// The line between the messages of two days
+    pub day_divider: Style,
+}
+
+impl Theme {
+    /// The default theme, telling system messages apart by italics and colors
+    pub fn regular() -> Self {
+        Theme {
+            message: Style::new(),
+            notice: Style::new().italic(),
+            warning_notice: Style::new().italic().yellow(),
+            error_notice: Style::new().italic().red(),
+            welcome_prefix: Style::new().bold().green(),
+            welcome: Style::new().green(),
+            away_user: Style::new().dark_gray(),
+            highlighted_message: Style::new().bold().yellow(),
+            own_message: Style::new().cyan(),
+            pending_message: Style::new().dark_gray(),
+            selected_message: Style::new().reversed(),
+            code: Style::new().magenta(),
+            action: Style::new().italic(),
+            link_preview: Style::new().dark_gray(),
+            day_divider: Style::new().dark_gray(),
+        }
+    }
+
+    /// A theme without italics and colors, system messages are told apart by text markers instead
+    pub fn high_contrast() -> Self {
+        Theme {
+            message: Style::new(),
+            notice: Style::new(),
+            warning_notice: Style::new(),
+            error_notice: Style::new().bold(),
+            welcome_prefix: Style::new().bold(),
+            welcome: Style::new(),
+            away_user: Style::new(),
+            highlighted_message: Style::new().bold(),
+            own_message: Style::new(),
+            pending_message: Style::new(),
+            selected_message: Style::new().reversed(),
+            code: Style::new(),
+            action: Style::new(),
+            link_preview: Style::new(),
+            day_divider: Style::new(),
+        }
+    }
+}
+
+/// The levels of a sparkline from the lowest to the highest
+const SPARKLINE_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+/// The levels of a sparkline for terminals and fonts without block elements
+const ASCII_SPARKLINE_LEVELS: [char; 8] = ['_', '.', ':', '-', '=', '+', '*', '#'];
+
+/// A chart of the values one character wide each, scaled to the highest value
+pub fn sparkline(values: &[u32], ascii: bool) -> String {
+    let levels = if ascii {
+        ASCII_SPARKLINE_LEVELS
+    } else {
+        SPARKLINE_LEVELS
+    };
+    let max = values.iter().copied().max().unwrap_or(0).max(1) as u64;
+
+    values
+        .iter()
+        .map(|value| {
+            // any activity shows above the lowest level, which is left for none at all
+            let level = (*value as u64 * (levels.len() as u64 - 1)).div_ceil(max);
+            levels[level as usize]
+        })
+        .collect()
+}
+
+/// The characters an image is drawn with in ASCII mode, from the darkest to the brightest pixel
+const ASCII_IMAGE_LEVELS: [char; 10] = [' ', '.', ':', '-', '=', '+', '*', '#', '%', '@'];
+
+/// [Presenter] decides how the state of the application is shown to the user
+///
+/// The state only keeps the facts, e.g. which user joined a room, while the presenter
+/// words them with the templates of the [Locale] and styles them with a [Theme].
+#[derive(Clone, Copy)]
+pub struct Presenter {
+    strings: &'static dyn Strings,
+    accessibility: Accessibility,
+    theme: Theme,
+}
+
+impl Presenter {
+    pub fn new(locale: Locale, accessibility: Accessibility) -> Self {
+        Presenter {
+            strings: locale.strings(),
+            accessibility,
+            theme: if accessibility.high_contrast {
+                Theme::high_contrast()
+            } else {
+                Theme::regular()
+            },
+        }
+    }
+
+    /// The lines of a link preview, indented below its message
+    /// The masking returns the text to show instead of the given one, if any
+    fn link_preview(
+        &self,
+        link_preview: &LinkPreview,
+        mask: impl Fn(&str) -> Option<String>,
+    ) -> Vec<Line<'static>> {
+        let indent = if self.accessibility.ascii {
+            "  | "
+        } else {
+            "  ┃ "
+        };
+        let title = link_preview.title.as_ref().unwrap_or(&link_preview.url);
+        let title = mask(title).unwrap_or_else(|| title.clone());
+
+        let mut lines = vec![Line::from(vec![
+            Span::styled(indent, self.theme.link_preview),
+            Span::styled(title, self.theme.link_preview.bold()),
+        ])];
+        if let Some(description) = link_preview.description.as_ref() {
+            let description = mask(description).unwrap_or_else(|| description.clone());
+            lines.push(Line::styled(
+                format!("{indent}{description}"),
+                self.theme.link_preview,
+            ));
+        }
+
+        lines
+    }
+
+    /// The lines of an image, indented below the message linking it
+    ///
+    /// Every cell is drawn as a half block showing two pixels in color, or as a single character
+    /// as bright as both pixels together in ASCII mode, colored unless the contrast is high.
+    /// Nothing is drawn in linear mode, the image would mean nothing to a screen reader.
+    fn image_art(&self, art: &ImageArt) -> Vec<Line<'static>> {
+        if self.accessibility.linear {
+            return Vec::new();
+        }
+
+        art.rows()
+            .map(|cells| {
+                let mut spans = vec![Span::raw("  ")];
+                spans.extend(cells.into_iter().map(|(upper, lower)| {
+                    if self.accessibility.ascii {
+                        self.ascii_image_cell(upper, lower)
+                    } else {
+                        image_cell(upper, lower)
+                    }
+                }));
+                Line::from(spans)
+            })
+            .collect()
+    }
+
+    fn ascii_image_cell(&self, upper: Pixel, lower: Pixel) -> Span<'static> {
+        // transparent pixels are left blank, as dark as the darkest pixel
+        let [red, green, blue] = match (upper, lower) {
+            (Some(upper), Some(lower)) => {
+                [0, 1, 2].map(|channel| ((upper[channel] as u16 + lower[channel] as u16) / 2) as u8)
+            }
+            (Some(pixel), None) | (None, Some(pixel)) => pixel,
+            (None, None) => return Span::raw(" "),
+        };
+        let luma = (red as u32 * 299 + green as u32 * 587 + blue as u32 * 114) / 1000;
+        let level = ASCII_IMAGE_LEVELS[luma as usize * (ASCII_IMAGE_LEVELS.len() - 1) / 255];
+
+        if self.accessibility.high_contrast {
+            Span::raw(level.to_string())
+        } else {
+            Span::styled(
+                level.to_string(),
+                Style::new().fg(Color::Rgb(red, green, blue)),
+            )
+        }
+    }
+
+    /// The presented item of the message box, marked as the one the user has selected
+    /// The time it was sent at is shown as the user has set it, relative ones as of now
+    pub fn selected(
+        &self,
+        mut text: Text<'static>,
+        timestamp: u64,
+        time_format: &TimeFormat,
+        now: u64,
+    ) -> Text<'static> {
+        if let Some(line) = text.lines.first_mut() {
+            // the time of a message is only shown once it is selected, to keep the pane quiet
+            let separator = if self.accessibility.ascii { "-" } else { "·" };
+            let sent_at = time_format.message_time(timestamp, now, self.strings);
+            if !sent_at.is_empty() {
+                line.spans
+                    .push(Span::raw(format!(" {separator} {sent_at}")));
+            }
+            if self.accessibility.high_contrast {
+                line.spans
+                    .insert(0, Span::raw(self.strings.selected_message_marker()));
+            }
+        }
+
+        text.patch_style(self.theme.selected_message)
+    }
+
+    /// An item of the message box of a room, notifications may span several lines
+    /// The masked words of the filter are hidden in messages the user has not revealed
+    /// Messages grouped with the one before them leave out their author, unless the pane is read line by line
+    pub fn message_box_item(
+        &self,
+        item: &MessageBoxItem,
+        profanity_filter: &ProfanityFilter,
+        is_grouped: bool,
+    ) -> Text<'static> {
+        match item {
+            MessageBoxItem::Message {
+                user_id,
+                display_name,
+                content,
+                content_type,
+                is_highlighted,
+                is_revealed,
+                link_preview,
+                image,
+                is_own,
+                pending_nonce,
+                ..
+            } => {
+                // without colors, highlighted messages are told apart from the others by a prefix
+                let (marker, style) = match is_highlighted {
+                    true if self.accessibility.high_contrast => (
+                        self.strings.highlight_marker(),
+                        self.theme.highlighted_message,
+                    ),
+                    true => ("", self.theme.highlighted_message),
+                    false if *is_own => ("", self.theme.own_message),
+                    false => ("", self.theme.message),
+                };
+                // messages still being sent are dimmed, or marked without colors
+                let (sending_marker, style) = match pending_nonce {
+                    Some(_) if self.accessibility.high_contrast => {
+                        (self.strings.sending_marker(), style)
+                    }
+                    Some(_) => ("", self.theme.pending_message),
+                    None => ("", style),
+                };
+                let is_masked = !is_revealed && profanity_filter.mask(content).is_some();
+                let content = match profanity_filter.mask(content) {
+                    Some(masked) if !is_revealed => {
+                        format!("{}{}", masked, self.strings.masked_marker())
+                    }
+                    _ => content.clone(),
+                };
+
+                let author = format!("{}: ", self.author(user_id, display_name.as_deref()));
+                let author = if is_grouped && !self.accessibility.linear {
+                    // the content lines up with the one of the message naming the author
+                    " ".repeat(Span::raw(author).width())
+                } else {
+                    author
+                };
+
+                let mut text = match content_type {
+                    // the author is part of the sentence, so they are named in a group as well
+                    event::ContentType::Action => Text::from(Span::styled(
+                        format!(
+                            "{}* {} {}{}",
+                            marker,
+                            self.author(user_id, display_name.as_deref()),
+                            content,
+                            sending_marker
+                        ),
+                        style.patch(self.theme.action),
+                    )),
+                    event::ContentType::Code => self.code(
+                        format!("{}{}", marker, author),
+                        &content,
+                        sending_marker,
+                        style,
+                    ),
+                    event::ContentType::Plain => Text::from(Span::styled(
+                        format!("{}{}{}{}", marker, author, content, sending_marker),
+                        style,
+                    )),
+                };
+                if let Some(link_preview) = link_preview {
+                    // the page may use the masked words as well
+                    text.extend(self.link_preview(link_preview, |text| {
+                        if *is_revealed {
+                            None
+                        } else {
+                            profanity_filter.mask(text)
+                        }
+                    }));
+                }
+                // the image may show what the masked words say, it is hidden along with them
+                if let Some(image) = image.as_ref().filter(|_| !is_masked) {
+                    text.extend(self.image_art(image));
+                }
+
+                text
+            }
+            MessageBoxItem::Notification(notice) => {
+                let (marker, style) = match notice.severity() {
+                    Severity::Info => (self.strings.notice_marker(), self.theme.notice),
+                    Severity::Warning => (self.strings.warning_marker(), self.theme.warning_notice),
+                    Severity::Error => (self.strings.error_marker(), self.theme.error_notice),
+                };
+                // without italics and colors, notifications are told apart from messages and each other by a prefix
+                let marker = if self.accessibility.high_contrast {
+                    marker
+                } else {
+                    ""
+                };
+
+                Text::styled(format!("{}{}", marker, self.notice(notice)), style)
+            }
+            MessageBoxItem::Welcome(content) => Text::from(Line::from(vec![
+                Span::styled(self.strings.welcome_prefix(), self.theme.welcome_prefix),
+                Span::styled(content.clone(), self.theme.welcome),
+            ])),
+        }
+    }
+
+    /// The line standing in for a run of joins and leaves hidden in the room
+    pub fn hidden_participation(&self, count: usize) -> Text<'static> {
+        let marker = if self.accessibility.high_contrast {
+            self.strings.notice_marker()
+        } else {
+            ""
+        };
+
+        Text::styled(
+            format!("{}{}", marker, self.strings.hidden_participation(count)),
+            self.theme.notice.dim(),
+        )
+    }
+
+    /// The line setting the messages of a day apart from the older ones, e.g. `── March 3 ──`
+    /// The year is only given for days of other years than today's
+    pub fn day_divider(&self, day: NaiveDate, today: Option<NaiveDate>) -> Text<'static> {
+        let rule = if self.accessibility.ascii {
+            "--"
+        } else {
+            "──"
+        };
+        let year = Some(day.year()).filter(|year| Some(*year) != today.map(|today| today.year()));
+
+        Text::from(
+            Line::styled(
+                format!(
+                    "{rule} {} {rule}",
+                    self.strings.day(day.month(), day.day(), year)
+                ),
+                self.theme.day_divider,
+            )
+            .centered(),
+        )
+    }
+
+    /// A message sent as code, its lines are set off by a gutter and line up after the author
+    fn code(
+        &self,
+        author: String,
+        content: &str,
+        sending_marker: &str,
+        style: Style,
+    ) -> Text<'static> {
+        let gutter = if self.accessibility.ascii {
+            "| "
+        } else {
+            "│ "
+        };
+        let indent = " ".repeat(Span::raw(author.as_str()).width());
+        let mut lines = content.lines();
+
+        let mut text = Text::from(Line::from(vec![
+            Span::styled(author, style),
+            Span::styled(
+                format!("{}{}", gutter, lines.next().unwrap_or_default()),
+                self.theme.code,
+            ),
+            Span::styled(String::from(sending_marker), style),
+        ]));
+        text.extend(lines.map(|line| {
+            Line::from(vec![
+                Span::raw(indent.clone()),
+                Span::styled(format!("{}{}", gutter, line), self.theme.code),
+            ])
+        }));
+
+        text
+    }
+
+    /// Who a message is from, the user id follows the display name so it can not be used to pose as someone else
+    fn author(&self, user_id: &str, display_name: Option<&str>) -> String {
+        match display_name {
+            Some(display_name) => format!("{} (@{})", display_name, user_id),
+            None => format!("@{}", user_id),
+        }
+    }
+
+    /// A line of the list of users in a room, followed by their status text if they have one
+    pub fn room_user(
+        &self,
+        user_id: &str,
+        is_away: bool,
+        status_text: Option<&str>,
+    ) -> Line<'static> {
+        let mut line = if is_away {
+            Line::from(Span::styled(
+                format!("@{}{}", user_id, self.strings.away_user_marker()),
+                self.theme.away_user,
+            ))
+        } else {
+            Line::from(Span::raw(format!("@{user_id}")))
+        };
+
+        if let Some(status_text) = status_text {
+            line.push_span(Span::styled(format!(": {status_text}"), self.theme.notice));
+        }
+
+        line
+    }
+
+    /// The text of a system message
+    pub fn notice(&self, notice: &Notice) -> String {
+        let strings = self.strings;
+
+        match notice {
+            Notice::Participation {
+                user_id,
+                has_joined,
+            } => strings.participation(user_id, *has_joined),
+            Notice::RoomFull { capacity } => strings.room_full(*capacity),
+            Notice::QueuePosition { position } => strings.queue_position(*position),
+            Notice::MessageRejected(reason) => match reason {
+                event::MessageRejectionReason::TooLong { length, max_length } => {
+                    strings.message_too_long(*length, *max_length)
+                }
+                event::MessageRejectionReason::InvalidCommand { message } => {
+                    strings.command_not_understood(message)
+                }
+                event::MessageRejectionReason::NotPermitted => {
+                    String::from(strings.message_not_permitted())
+                }
+                event::MessageRejectionReason::NotEncrypted => {
+                    String::from(strings.message_not_encrypted())
+                }
+                event::MessageRejectionReason::Archived => {
+                    String::from(strings.message_to_archived_room())
+                }
+                // rounded up, waiting for 0 seconds would not be enough
+                event::MessageRejectionReason::RateLimited { retry_after } => {
+                    strings.message_rate_limited(retry_after.div_ceil(1000))
+                }
+                event::MessageRejectionReason::Blocked => String::from(strings.message_blocked()),
+                event::MessageRejectionReason::TooManyReminders { max_reminders } => {
+                    strings.too_many_reminders(*max_reminders)
+                }
+            },
+            Notice::WelcomeMessageUpdated { message } => {
+                strings.welcome_message_updated(message.as_deref())
+            }
+            Notice::CommandRefused { reason } => strings.command_refused(reason),
+            Notice::GroupMembershipChanged {
+                user_id,
+                group,
+                is_member,
+            } => strings.group_membership_changed(user_id, group, *is_member),
+            Notice::DeviceConnected {
+                session_id,
+                address,
+            } => strings.device_connected(session_id, address),
+            Notice::DeviceDisconnected { session_id } => strings.device_disconnected(session_id),
+            Notice::OpenSessions { sessions } => strings.open_sessions(
+                &sessions
+                    .iter()
+                    .map(|session| format!("{} ({})", session.session_id, session.address))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            Notice::BotTokenIssued {
+                bot_name,
+                user_id,
+                token,
+            } => strings.bot_token_issued(bot_name, user_id, token),
+            Notice::BotTokenRevoked { bot_name, revoked } => {
+                strings.bot_token_revoked(bot_name, *revoked)
+            }
+            Notice::ReminderSet { remaining_ms } => {
+                strings.reminder_set(&self.remaining_time(*remaining_ms))
+            }
+            Notice::ReminderDue { content } => strings.reminder_due(content),
+            Notice::Announcement { message } => strings.announcement(message),
+            Notice::UndecryptableMessage { user_id } => strings.undecryptable_message(user_id),
+            Notice::RoomKeyMissing => String::from(strings.room_key_missing()),
+            Notice::RoomKeyAwaited { fingerprint } => strings.room_key_awaited(fingerprint),
+            Notice::RoomKeyRequested {
+                user_id,
+                fingerprint,
+            } => strings.room_key_requested(user_id, fingerprint),
+            Notice::RoomKeyShared { user_id } => strings.room_key_shared(user_id),
+            Notice::NoRoomKeyRequest { fingerprint } => strings.no_room_key_request(fingerprint),
+            Notice::MessageRemoved => String::from(strings.message_removed()),
+            Notice::UserInfo(user_info) => strings.user_info(
+                &user_info.user_id,
+                strings.presence(user_info.presence),
+                user_info.status_text.as_deref(),
+                &self.list(user_info.shared_rooms.iter().map(|room| format!("#{room}"))),
+            ),
+            Notice::UserSearchResults(results) => strings.user_search_results(
+                &results.query,
+                &self.list(results.users.iter().map(|user| {
+                    format!("@{} ({})", user.user_id, strings.presence(user.presence))
+                })),
+            ),
+            Notice::RoomInfo(room_info) => strings.room_info(
+                &room_info.room,
+                &room_info.description,
+                &strings.room_members(room_info.member_count, room_info.capacity),
+                &self.list(room_info.owners.iter().map(|owner| format!("@{owner}"))),
+            ),
+            Notice::RoomStats(room_stats) => room_stats
+                .rooms
+                .iter()
+                .map(|stats| {
+                    strings.room_stats(
+                        &stats.room,
+                        &sparkline(&stats.messages_per_hour, self.accessibility.ascii),
+                        stats.messages_per_hour.iter().sum(),
+                        stats.active_users,
+                        stats.member_count,
+                        stats.peak_concurrency,
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Notice::ReportFiled { room } => strings.report_filed(room),
+            Notice::NoMessageToReport { user_id } => strings.no_message_to_report(user_id),
+            Notice::NothingToReveal => String::from(strings.nothing_to_reveal()),
+            Notice::MessageReported(report) => {
+                strings.message_reported(&self.message_report(report))
+            }
+            Notice::OpenReports { reports } => {
+                // one report per line below the heading
+                let mut text = strings.open_reports(reports.len());
+                for report in reports {
+                    text.push_str("\n  ");
+                    text.push_str(&self.message_report(report));
+                }
+                text
+            }
+            Notice::ReportResolved {
+                report_id,
+                resolved,
+            } => strings.report_resolved(report_id, *resolved),
+            Notice::JoinRequestAwaitingApproval => {
+                String::from(strings.join_request_awaiting_approval())
+            }
+            Notice::JoinRequested { user_id } => strings.join_requested(user_id),
+            Notice::OwnJoinRequestDecided {
+                approved,
+                decided_by,
+            } => strings.own_join_request_decided(*approved, decided_by),
+            Notice::JoinRequestDecided {
+                user_id,
+                approved,
+                decided_by,
+            } => strings.join_request_decided(user_id, *approved, decided_by),
+            Notice::NameRejected { name, reason } => {
+                strings.name_rejected(name, &self.name_rejection(reason))
+            }
+            Notice::RoomResynced { missed_events } => strings.room_resynced(*missed_events),
+            Notice::RoomLeft => String::from(strings.room_left()),
+            Notice::RoomArchived => String::from(strings.room_archived()),
+            Notice::RoomReopened => String::from(strings.room_reopened()),
+            Notice::RoomRemoved => String::from(strings.room_removed()),
+            Notice::RoomDescriptionChanged { description } => {
+                strings.room_description_changed(description)
+            }
+            Notice::RoomArchivalScheduled { remaining_ms } => {
+                strings.room_archival_scheduled(&self.remaining_time(*remaining_ms))
+            }
+            Notice::InviteCodeCreated {
+                code,
+                remaining_ms,
+                max_uses,
+            } => strings.invite_code_created(code, &self.remaining_time(*remaining_ms), *max_uses),
+            Notice::ParticipationToggled { is_shown } => {
+                String::from(strings.participation_toggled(*is_shown))
+            }
+            Notice::ScriptNotification { text } => text.clone(),
+            Notice::ScriptFailed { error } => strings.script_failed(error),
+            Notice::Redirected { addr } => strings.redirected(addr),
+            Notice::ReplayEnded => String::from(strings.replay_ended()),
+            Notice::ReplayReadOnly => String::from(strings.replay_read_only()),
+        }
+    }
+
+    /// Why the address entered on the connect page can not be connected to
+    pub fn address_problem(&self, problem: AddressProblem) -> &'static str {
+        let strings = self.strings;
+
+        match problem {
+            AddressProblem::MissingHost => strings.address_missing_host(),
+            AddressProblem::MissingPort => strings.address_missing_port(),
+            AddressProblem::InvalidPort => strings.address_invalid_port(),
+            AddressProblem::UnbracketedIpv6 => strings.address_unbracketed_ipv6(),
+            AddressProblem::InvalidIpv6 => strings.address_invalid_ipv6(),
+        }
+    }
+
+    /// Why a name was not accepted, worded to follow the name
+    fn name_rejection(&self, reason: &event::NameRejectionReason) -> String {
+        let strings = self.strings;
+
+        match reason {
+            event::NameRejectionReason::TooShort { min_length } => {
+                strings.name_too_short(*min_length)
+            }
+            event::NameRejectionReason::TooLong { max_length } => {
+                strings.name_too_long(*max_length)
+            }
+            // escaped, the character may be invisible
+            event::NameRejectionReason::InvalidCharacter { character } => {
+                strings.name_invalid_character(&format!("{:?}", character))
+            }
+            event::NameRejectionReason::Reserved => String::from(strings.name_reserved()),
+            event::NameRejectionReason::Confusable { name } => strings.name_confusable(name),
+            event::NameRejectionReason::Taken => String::from(strings.name_taken()),
+        }
+    }
+
+    fn message_report(&self, report: &event::MessageReport) -> String {
+        self.strings.message_report(
+            &report.id,
+            &report.room,
+            &report.user_id,
+            &report.content,
+            &report.reason,
+            &report.reported_by,
+        )
+    }
+
+    /// Joins the items with commas, an empty list is spelled out
+    fn list(&self, items: impl Iterator<Item = String>) -> String {
+        let list = items.collect::<Vec<_>>().join(", ");
+
+        if list.is_empty() {
+            String::from(self.strings.nobody())
+        } else {
+            list
+        }
+    }
+
+    /// The status of the connection to the server
+    pub fn connection_status(&self, status: &ServerConnectionStatus) -> String {
+        let strings = self.strings;
+
+        match status {
+            ServerConnectionStatus::Uninitialized => strings.status_uninitialized().into(),
+            ServerConnectionStatus::Connecting => strings.status_connecting().into(),
+            ServerConnectionStatus::Authorizing { .. } => strings.status_authorizing().into(),
+            ServerConnectionStatus::LoggingIn { .. } => strings.status_logging_in().into(),
+            // the address the host resolved to is only worth telling if it was not given as one
+            ServerConnectionStatus::Connected { addr, endpoint }
+                if *addr != endpoint.to_string() =>
+            {
+                strings.status_connected_via(addr, &endpoint.to_string())
+            }
+            ServerConnectionStatus::Connected { addr, .. } => strings.status_connected(addr),
+            ServerConnectionStatus::Replaying { path } => strings.status_replaying(path),
+            ServerConnectionStatus::Errored { err } => {
+                strings.status_errored(&self.connection_error(err))
+            }
+        }
+    }
+
+    /// A frame recorded by the traffic inspector, headed by which way it went, when and how large it was
+    pub fn traffic_frame(&self, frame: &TrafficFrame) -> Text<'static> {
+        let is_inbound = frame.direction == FrameDirection::Inbound;
+        let arrow = match (is_inbound, self.accessibility.ascii) {
+            (true, true) => "<-",
+            (true, false) => "←",
+            (false, true) => "->",
+            (false, false) => "→",
+        };
+        let at = DateTime::from_timestamp_millis(frame.at as i64)
+            .map(|at| at.with_timezone(&Local).format("%H:%M:%S%.3f").to_string())
+            .unwrap_or_default();
+
+        let mut text = Text::from(Line::from(format!(
+            "{} {}",
+            arrow,
+            self.strings.traffic_frame(is_inbound, &at, frame.size())
+        )))
+        .bold();
+        text.extend(Text::raw(frame.pretty()));
+
+        text
+    }
+
+    /// Why the connection to the server was refused or lost
+    pub fn connection_error(&self, err: &ConnectionError) -> String {
+        match err {
+            ConnectionError::Failed(err) => err.clone(),
+            ConnectionError::LoginRejected {
+                name_rejection: Some(name_rejection),
+                ..
+            } => self.strings.login_rejected(
+                &self
+                    .strings
+                    .user_id_rejected(&self.name_rejection(name_rejection)),
+            ),
+            ConnectionError::LoginRejected { reason, .. } => self.strings.login_rejected(reason),
+            ConnectionError::SessionClosed { reason } => self.strings.session_closed(reason),
+            ConnectionError::SessionReplaced { address } => self.strings.session_replaced(address),
+        }
+    }
+
+    /// Formats a duration in milliseconds, e.g. `1h 30m`
+    fn remaining_time(&self, remaining_ms: u64) -> String {
+        // round up, so a reminder set for 10 minutes is not shown as 9m 59s
+        let mut seconds = remaining_ms.div_ceil(1000);
+
+        let mut parts = Vec::new();
+        for (unit, unit_seconds) in [("d", 24 * 60 * 60), ("h", 60 * 60), ("m", 60), ("s", 1)] {
+            if seconds >= unit_seconds {
+                parts.push(format!("{}{}", seconds / unit_seconds, unit));
+                seconds %= unit_seconds;
+            }
+        }
+
+        if parts.is_empty() {
+            String::from(self.strings.a_moment())
+        } else {
+            parts.join(" ")
+        }
+    }
+}
+
+/// A cell showing two pixels of an image, the upper one in the foreground of a half block and the lower one in its background
+fn image_cell(upper: Pixel, lower: Pixel) -> Span<'static> {
+    let color = |[red, green, blue]: [u8; 3]| Color::Rgb(red, green, blue);
+
+    match (upper, lower) {
+        (Some(upper), Some(lower)) => {
+            Span::styled("▀", Style::new().fg(color(upper)).bg(color(lower)))
+        }
+        (Some(upper), None) => Span::styled("▀", Style::new().fg(color(upper))),
+        (None, Some(lower)) => Span::styled("▄", Style::new().fg(color(lower))),
+        (None, None) => Span::raw(" "),
+    }
+}
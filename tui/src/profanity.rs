@@ -0,0 +1,68 @@
+use crate::highlights::is_word_char;
+
+/// The character each letter of a masked word is replaced with
+const MASK_CHAR: char = '*';
+
+/// [ProfanityFilter] masks words the user would rather not have on their screen, e.g. while sharing it
+///
+/// The masked words are read from the `CHAT_TUI_MASKED_WORDS` environment variable as a comma separated list and
+/// matched as whole words regardless of their case. Only the rendered messages are masked, the user can reveal
+/// a message to read it as it was sent.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProfanityFilter {
+    /// Lowercased, so they can be compared with the lowercased words of a message
+    masked_words: Vec<String>,
+}
+
+impl ProfanityFilter {
+    pub fn from_env() -> Self {
+        std::env::var("CHAT_TUI_MASKED_WORDS")
+            .map(|masked_words| Self::parse(&masked_words))
+            .unwrap_or_default()
+    }
+
+    /// Reads the comma separated masked words, e.g. as typed by the user
+    pub fn parse(masked_words: &str) -> Self {
+        ProfanityFilter {
+            masked_words: masked_words
+                .split(',')
+                .map(str::trim)
+                .filter(|masked_word| !masked_word.is_empty())
+                .map(str::to_lowercase)
+                .collect(),
+        }
+    }
+
+    pub fn masked_words(&self) -> &[String] {
+        &self.masked_words
+    }
+
+    /// The content with every masked word replaced by `*`s of the same length, None if it has none of them
+    pub fn mask(&self, content: &str) -> Option<String> {
+        if self.masked_words.is_empty() {
+            return None;
+        }
+
+        let mut masked = String::with_capacity(content.len());
+        let mut is_masked = false;
+        let mut rest = content;
+        while !rest.is_empty() {
+            // the text alternates between words and what separates them
+            let is_word = rest.starts_with(is_word_char);
+            let len = rest
+                .find(|c| is_word_char(c) != is_word)
+                .unwrap_or(rest.len());
+            let (part, remaining) = rest.split_at(len);
+
+            if is_word && self.masked_words.contains(&part.to_lowercase()) {
+                masked.extend(std::iter::repeat_n(MASK_CHAR, part.chars().count()));
+                is_masked = true;
+            } else {
+                masked.push_str(part);
+            }
+            rest = remaining;
+        }
+
+        is_masked.then_some(masked)
+    }
+}
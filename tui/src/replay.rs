@@ -0,0 +1,132 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    iter::Peekable,
+    path::Path,
+    vec::IntoIter,
+};
+
+use anyhow::Context;
+use comms::event;
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+/// A line of a replay file, an event received from the server and when it arrived
+///
+/// Replay files are json lines, so they can be read, trimmed or shared as they are, e.g. in a bug report.
+#[derive(Debug, Serialize, Deserialize)]
+struct ReplayEntry {
+    /// Milliseconds since the recording started
+    at_ms: u64,
+    event: event::Event,
+}
+
+/// [Recorder] writes every event received from the server to a replay file
+///
+/// Enabled by setting the `CHAT_TUI_RECORD` environment variable to the path of the file.
+/// The events are recorded once decrypted, so the recording of an encrypted room holds its messages in plain text.
+pub struct Recorder {
+    file: BufWriter<File>,
+    started_at: Instant,
+}
+
+impl Recorder {
+    /// Creates the replay file read from the environment, an existing file is overwritten
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Some(path) = std::env::var("CHAT_TUI_RECORD")
+            .ok()
+            .filter(|path| !path.trim().is_empty())
+        else {
+            return Ok(None);
+        };
+
+        let file = File::create(&path)
+            .with_context(|| format!("could not create the replay file '{}'", path))?;
+
+        Ok(Some(Recorder {
+            file: BufWriter::new(file),
+            started_at: Instant::now(),
+        }))
+    }
+
+    pub fn record(&mut self, event: &event::Event) -> anyhow::Result<()> {
+        let entry = ReplayEntry {
+            at_ms: self.started_at.elapsed().as_millis() as u64,
+            event: event.clone(),
+        };
+
+        serde_json::to_writer(&mut self.file, &entry).context("could not record the event")?;
+        writeln!(self.file).context("could not record the event")?;
+        // flushed right away, so the recording survives the terminal being closed
+        self.file.flush().context("could not record the event")
+    }
+}
+
+/// [Replay] plays the events of a replay file back, keeping the time between them
+///
+/// Enabled by setting the `CHAT_TUI_REPLAY` environment variable to the path of the file,
+/// the user interface shows the recorded conversation instead of connecting to a server.
+pub struct Replay {
+    path: String,
+    entries: Peekable<IntoIter<ReplayEntry>>,
+    started_at: Instant,
+    /// The time of the first event, the playback starts with it instead of the time spent before connecting
+    first_at_ms: u64,
+}
+
+impl Replay {
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        std::env::var("CHAT_TUI_REPLAY")
+            .ok()
+            .filter(|path| !path.trim().is_empty())
+            .map(|path| Self::open(&path))
+            .transpose()
+    }
+
+    fn open(path: &str) -> anyhow::Result<Self> {
+        let file = File::open(Path::new(path))
+            .with_context(|| format!("could not open the replay file '{}'", path))?;
+
+        let mut entries = Vec::new();
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.with_context(|| format!("could not read '{}'", path))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let entry: ReplayEntry = serde_json::from_str(&line)
+                .with_context(|| format!("invalid event on line {} of '{}'", index + 1, path))?;
+            entries.push(entry);
+        }
+
+        Ok(Replay {
+            path: String::from(path),
+            first_at_ms: entries.first().map(|entry| entry.at_ms).unwrap_or(0),
+            entries: entries.into_iter().peekable(),
+            started_at: Instant::now(),
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Starts the playback, the first event is due right away
+    pub fn start(&mut self) {
+        self.started_at = Instant::now();
+    }
+
+    pub fn is_finished(&mut self) -> bool {
+        self.entries.peek().is_none()
+    }
+
+    /// Waits until the next event is due, returns None once every event has been played
+    ///
+    /// Cancel safe, an event is only taken once it is due.
+    pub async fn next_event(&mut self) -> Option<event::Event> {
+        let at_ms = self.entries.peek()?.at_ms.saturating_sub(self.first_at_ms);
+        tokio::time::sleep_until(self.started_at + std::time::Duration::from_millis(at_ms)).await;
+
+        self.entries.next().map(|entry| entry.event)
+    }
+}
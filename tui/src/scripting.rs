@@ -0,0 +1,276 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use comms::event;
+use mlua::{Function, HookTriggers, Lua, Table};
+
+use crate::{config, highlights::Highlights};
+
+/// How long a script may run for a single event or command, the events wait for it meanwhile
+const SCRIPT_TIMEOUT: Duration = Duration::from_millis(500);
+/// How often a running script is checked for having run for too long, in Lua instructions
+const TIMEOUT_CHECK_INSTRUCTIONS: u32 = 10_000;
+/// The registry table of the handlers of every hook, by hook
+const HOOKS_REGISTRY: &str = "chat_hooks";
+/// The registry table of the handlers of the slash commands, by command
+const COMMANDS_REGISTRY: &str = "chat_commands";
+/// The events the scripts can hook into with `chat.on`
+const HOOKS: [&str; 3] = ["message", "mention", "connect"];
+
+/// What running the scripts has come to, carried out by the state store
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptOutput {
+    /// Send the message to the room, the user must have joined it
+    Send { room: String, content: String },
+    /// Show the text as a notification in the active room
+    Notify { text: String },
+    /// A script has failed, e.g. it has raised an error or run for too long
+    Failed { error: String },
+}
+
+/// When the script running now has to be done by
+struct Deadline(Instant);
+
+/// [Scripts] are the Lua scripts of the user, run on the events of the chat to automate responses and notifications
+///
+/// Loaded from the `.lua` files of the `scripts` directory next to the config file, see [config::scripts_dir],
+/// in the order of their names. The scripts register what they handle with the `chat` table:
+/// - `chat.on(hook, function(arg))` runs the function on `message` (every message of the others), `mention`
+///   (the messages highlighted for the user) or `connect` (once logged in), see [Scripts::handle_server_event]
+/// - `chat.command(name, function(args, room))` adds the slash command `/<name>`, a string returned is sent
+/// - `chat.send(room, text)` sends a message to a room the user has joined
+/// - `chat.notify(text)` shows a notification in the active room
+///
+/// Scripts run on the task handling the events, each for half a second at most.
+pub struct Scripts {
+    lua: Lua,
+}
+
+impl Scripts {
+    /// Load the scripts of the user, None if they have no scripts directory
+    /// Fails if a script can not be read or fails while it is loaded, so mistakes show right away
+    pub fn load() -> anyhow::Result<Option<Self>> {
+        let Some(dir) = config::scripts_dir().filter(|dir| dir.is_dir()) else {
+            return Ok(None);
+        };
+
+        let mut paths = std::fs::read_dir(&dir)
+            .with_context(|| format!("could not read the scripts in {}", dir.display()))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|extension| extension == "lua"))
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        let scripts = Scripts::new().context("could not set up the scripts")?;
+        for path in paths {
+            let source = std::fs::read_to_string(&path)
+                .with_context(|| format!("could not read the script {}", path.display()))?;
+            scripts
+                .run(|lua| {
+                    lua.load(&source)
+                        .set_name(path.display().to_string())
+                        .exec()
+                })
+                .with_context(|| format!("could not load the script {}", path.display()))?;
+        }
+
+        Ok(Some(scripts))
+    }
+
+    fn new() -> mlua::Result<Self> {
+        let lua = Lua::new();
+        lua.set_named_registry_value(HOOKS_REGISTRY, lua.create_table()?)?;
+        lua.set_named_registry_value(COMMANDS_REGISTRY, lua.create_table()?)?;
+        lua.set_app_data(Vec::<ScriptOutput>::new());
+        lua.set_app_data(Deadline(Instant::now()));
+        lua.set_hook(
+            HookTriggers::new().every_nth_instruction(TIMEOUT_CHECK_INSTRUCTIONS),
+            |lua, _debug| match lua.app_data_ref::<Deadline>() {
+                Some(deadline) if Instant::now() > deadline.0 => {
+                    Err(mlua::Error::runtime("the script has run for too long"))
+                }
+                _ => Ok(()),
+            },
+        );
+
+        let chat = lua.create_table()?;
+        chat.set(
+            "on",
+            lua.create_function(|lua, (hook, handler): (String, Function)| {
+                if !HOOKS.contains(&hook.as_str()) {
+                    return Err(mlua::Error::runtime(format!(
+                        "unknown hook '{}', expected one of {}",
+                        hook,
+                        HOOKS.join(", ")
+                    )));
+                }
+
+                let hooks: Table = lua.named_registry_value(HOOKS_REGISTRY)?;
+                let handlers = match hooks.get::<_, Option<Table>>(hook.as_str())? {
+                    Some(handlers) => handlers,
+                    None => {
+                        let handlers = lua.create_table()?;
+                        hooks.set(hook, handlers.clone())?;
+                        handlers
+                    }
+                };
+                handlers.push(handler)
+            })?,
+        )?;
+        chat.set(
+            "command",
+            lua.create_function(|lua, (name, handler): (String, Function)| {
+                let commands: Table = lua.named_registry_value(COMMANDS_REGISTRY)?;
+                commands.set(format!("/{}", name.trim_start_matches('/')), handler)
+            })?,
+        )?;
+        chat.set(
+            "send",
+            lua.create_function(|lua, (room, content): (String, String)| {
+                push_output(
+                    lua,
+                    ScriptOutput::Send {
+                        room: String::from(room.trim_start_matches('#')),
+                        content,
+                    },
+                );
+                Ok(())
+            })?,
+        )?;
+        chat.set(
+            "notify",
+            lua.create_function(|lua, text: String| {
+                push_output(lua, ScriptOutput::Notify { text });
+                Ok(())
+            })?,
+        )?;
+        lua.globals().set("chat", chat)?;
+
+        Ok(Scripts { lua })
+    }
+
+    /// Run the hooks of the event, i.e. `connect` once logged in, and `message` and `mention` for new messages
+    /// The hooks are given the message as a table with its `room`, `id`, `user_id` and `content`.
+    /// The messages of the user themselves are left alone, so scripts answering messages do not answer their own.
+    pub fn handle_server_event(
+        &self,
+        event: &event::Event,
+        user_id: &str,
+        highlights: &Highlights,
+    ) -> Vec<ScriptOutput> {
+        match event {
+            event::Event::LoginSuccessful(event) => self.call_hooks("connect", |lua| {
+                let login = lua.create_table()?;
+                login.set("user_id", event.user_id.as_str())?;
+                Ok(login)
+            }),
+            event::Event::UserMessage(event) if event.user_id != user_id => {
+                self.call_hooks("message", |lua| message_table(lua, event));
+                if highlights.is_highlighted(user_id, &event.user_id, &event.content) {
+                    self.call_hooks("mention", |lua| message_table(lua, event));
+                }
+            }
+            _ => {}
+        }
+
+        self.take_outputs()
+    }
+
+    /// Run the slash command if a script has added it, e.g. `/shrug`, None if no script has
+    /// The command is given its arguments and the active room, a string it returns is sent to the active room.
+    pub fn run_command(&self, input: &str, room: Option<&str>) -> Option<Vec<ScriptOutput>> {
+        let (name, args) = input.split_once(' ').unwrap_or((input, ""));
+        let commands: Table = self.lua.named_registry_value(COMMANDS_REGISTRY).ok()?;
+        let handler = commands.get::<_, Option<Function>>(name).ok()??;
+
+        match self.run(|_| handler.call::<_, Option<String>>((args.trim(), room))) {
+            Ok(Some(content)) => {
+                if let Some(room) = room {
+                    push_output(
+                        &self.lua,
+                        ScriptOutput::Send {
+                            room: String::from(room),
+                            content,
+                        },
+                    );
+                }
+            }
+            Ok(None) => {}
+            Err(err) => push_output(&self.lua, failed(err)),
+        }
+
+        Some(self.take_outputs())
+    }
+
+    /// Call every handler of the hook with its own copy of the argument, a failing handler does not stop the others
+    fn call_hooks(
+        &self,
+        hook: &str,
+        arg: impl for<'lua> Fn(&'lua Lua) -> mlua::Result<Table<'lua>>,
+    ) {
+        let result: mlua::Result<()> = (|| {
+            let hooks: Table = self.lua.named_registry_value(HOOKS_REGISTRY)?;
+            let Some(handlers) = hooks.get::<_, Option<Table>>(hook)? else {
+                return Ok(());
+            };
+
+            for handler in handlers.sequence_values::<Function>() {
+                let handler = handler?;
+                let arg = arg(&self.lua)?;
+                if let Err(err) = self.run(|_| handler.call::<_, ()>(arg)) {
+                    push_output(&self.lua, failed(err));
+                }
+            }
+
+            Ok(())
+        })();
+
+        if let Err(err) = result {
+            push_output(&self.lua, failed(err));
+        }
+    }
+
+    /// Run the scripts until they are done, or stop them once they have run for too long
+    fn run<R>(&self, run: impl FnOnce(&Lua) -> mlua::Result<R>) -> mlua::Result<R> {
+        self.lua
+            .set_app_data(Deadline(Instant::now() + SCRIPT_TIMEOUT));
+
+        run(&self.lua)
+    }
+
+    fn take_outputs(&self) -> Vec<ScriptOutput> {
+        self.lua
+            .app_data_mut::<Vec<ScriptOutput>>()
+            .map(|mut outputs| std::mem::take(&mut *outputs))
+            .unwrap_or_default()
+    }
+}
+
+/// The message as the hooks are given it
+fn message_table<'lua>(
+    lua: &'lua Lua,
+    event: &event::UserMessageBroadcastEvent,
+) -> mlua::Result<Table<'lua>> {
+    let message = lua.create_table()?;
+    message.set("room", event.room.as_str())?;
+    message.set("id", event.id.as_str())?;
+    message.set("user_id", event.user_id.as_str())?;
+    message.set("content", event.content.as_str())?;
+
+    Ok(message)
+}
+
+fn push_output(lua: &Lua, output: ScriptOutput) {
+    if let Some(mut outputs) = lua.app_data_mut::<Vec<ScriptOutput>>() {
+        outputs.push(output);
+    }
+}
+
+/// The error of a script without its stack traceback, which would not fit a notification
+fn failed(err: mlua::Error) -> ScriptOutput {
+    let error = err.to_string();
+
+    ScriptOutput::Failed {
+        error: String::from(error.lines().next().unwrap_or_default()),
+    }
+}
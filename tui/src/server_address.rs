@@ -0,0 +1,47 @@
+use std::net::Ipv6Addr;
+
+/// Why an address entered on the connect page can not be connected to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AddressProblem {
+    MissingHost,
+    MissingPort,
+    InvalidPort,
+    /// An IPv6 address is given without the brackets setting it apart from the port, e.g. `::1:8080`
+    UnbracketedIpv6,
+    /// The brackets hold something other than an IPv6 address
+    InvalidIpv6,
+}
+
+/// Checks that the address is made of a host and a port, e.g. `localhost:8080`, `10.0.0.1:8080` or `[::1]:8080`
+pub fn check(addr: &str) -> Result<(), AddressProblem> {
+    let addr = addr.trim();
+    let (host, port) = match addr.strip_prefix('[') {
+        Some(bracketed) => {
+            let (ip, rest) = bracketed
+                .split_once(']')
+                .ok_or(AddressProblem::InvalidIpv6)?;
+            ip.parse::<Ipv6Addr>()
+                .map_err(|_| AddressProblem::InvalidIpv6)?;
+
+            (ip, rest.strip_prefix(':').unwrap_or_default())
+        }
+        None => {
+            let (host, port) = addr.rsplit_once(':').ok_or(AddressProblem::MissingPort)?;
+            if host.contains(':') {
+                return Err(AddressProblem::UnbracketedIpv6);
+            }
+
+            (host, port)
+        }
+    };
+
+    if host.is_empty() {
+        Err(AddressProblem::MissingHost)
+    } else if port.is_empty() {
+        Err(AddressProblem::MissingPort)
+    } else if !matches!(port.parse::<u16>(), Ok(port) if port != 0) {
+        Err(AddressProblem::InvalidPort)
+    } else {
+        Ok(())
+    }
+}
@@ -1,7 +1,121 @@
+use comms::event::ContentType;
+
+use crate::config::ClientConfig;
+
 #[derive(Debug, Clone)]
 pub enum Action {
-    ConnectToServerRequest { addr: String },
-    SendMessage { content: String },
-    SelectRoom { room: String },
+    /// Connect to the server at the given address, logging in right away if the user has picked a name
+    ConnectToServerRequest {
+        addr: String,
+    },
+    /// Give up on the connection being established, e.g. to an address nothing answers at
+    CancelConnection,
+    /// Log in to the connected server, None lets the server pick a name
+    Login {
+        username: Option<String>,
+    },
+    /// Log in to the connected server with a temporary identity, if the server allows guests
+    LoginAsGuest,
+    /// Send a message to the active room
+    SendMessage {
+        content: String,
+        content_type: ContentType,
+    },
+    /// Change the welcome message of the active room, None removes it
+    SetWelcomeMessage {
+        message: Option<String>,
+    },
+    /// Change the description or the maximum number of users of the active room, a capacity of 0 lifts the limit
+    UpdateRoomMetadata {
+        description: Option<String>,
+        capacity: Option<usize>,
+    },
+    /// List the sessions of the user on other devices
+    ListSessions,
+    DisconnectSession {
+        session_id: String,
+    },
+    /// Change the status text of the user, None removes it
+    SetStatusText {
+        text: Option<String>,
+    },
+    /// Look up another user, e.g. whether they are online
+    GetUserInfo {
+        user_id: String,
+    },
+    /// Find the users whose id contains the query
+    SearchUsers {
+        query: String,
+    },
+    /// Look up the details of the active room
+    GetRoomInfo,
+    /// Look up how busy the active room has been, or every room if the user is an admin
+    GetRoomStats {
+        all_rooms: bool,
+    },
+    /// Report the latest message of the user in the active room to its moderators
+    ReportMessage {
+        user_id: String,
+        reason: String,
+    },
+    ListReports,
+    /// Reveal the `nth` newest message with masked words in the active room, or mask it again
+    ToggleRevealed {
+        nth: usize,
+    },
+    ResolveReport {
+        report_id: String,
+    },
+    /// Hide the users joining and leaving the active room if they are shown, or show them
+    ToggleParticipation,
+    /// Let the user into the active room, which requires the approval of a moderator
+    ApproveJoinRequest {
+        user_id: String,
+    },
+    DenyJoinRequest {
+        user_id: String,
+    },
+    /// Share the key of the active encrypted room with the session which asked for it and has the given fingerprint
+    ShareRoomKey {
+        fingerprint: String,
+    },
+    /// Open the room, joining it if the user has not joined it yet
+    SelectRoom {
+        room: String,
+    },
+    /// Leave the given room, or the active room if None, the user no longer receives its messages
+    LeaveRoom {
+        room: Option<String>,
+    },
+    /// Archive the given room, or the active room if None, after the delay if one is given
+    ArchiveRoom {
+        room: Option<String>,
+        delay_ms: Option<u64>,
+    },
+    /// Reopen the given archived room, or the active room if None
+    ReopenRoom {
+        room: Option<String>,
+    },
+    /// Create a code letting other users into the active room, the server picks what is not given
+    CreateInviteCode {
+        ttl_secs: Option<u64>,
+        max_uses: Option<u32>,
+    },
+    /// Join the room of an invite code handed out by one of its owners
+    JoinWithCode {
+        code: String,
+    },
+    /// Change the settings of the user interface and save them to the config file
+    UpdateSettings {
+        config: Box<ClientConfig>,
+    },
+    /// The terminal has gained or lost the focus of the user
+    FocusChanged {
+        is_focused: bool,
+    },
+    /// Start recording the frames going over the connection for the debug page, or stop and forget them
+    ToggleTrafficInspector,
+    /// Write the recorded frames to a file, e.g. to attach to a bug report
+    ExportTraffic,
     Exit,
 }
@@ -0,0 +1,124 @@
+use std::{future::Future, net::SocketAddr, time::Duration};
+
+use anyhow::Context;
+use comms::{
+    handshake::Compression,
+    transport::{
+        self,
+        client::{CommandWriter, EventStream, FrameTap},
+    },
+};
+use tokio::{
+    net::{lookup_host, TcpStream},
+    task::JoinSet,
+};
+
+/// How long an attempt is given before the next address is tried alongside it
+const ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// [Connector] opens the connections of the state store to the server
+///
+/// The state store connects over TCP with [TcpConnector], the tests hand it in-memory connections instead.
+pub trait Connector: Send + Sync + 'static {
+    /// Connects to the server and shakes hands with it, returning the address of the host it has connected to as well
+    /// Every frame going over the connection is shown to the tap.
+    fn connect(
+        &self,
+        addr: &str,
+        frame_tap: FrameTap,
+    ) -> impl Future<Output = anyhow::Result<((EventStream, CommandWriter), SocketAddr)>> + Send;
+}
+
+/// Connects to the server over TCP, see [connect]
+pub struct TcpConnector;
+
+impl Connector for TcpConnector {
+    async fn connect(
+        &self,
+        addr: &str,
+        frame_tap: FrameTap,
+    ) -> anyhow::Result<((EventStream, CommandWriter), SocketAddr)> {
+        let (stream, endpoint) = connect(addr).await?;
+        let connection = transport::client::connect_tcp_stream_with_tap(
+            stream,
+            &[Compression::Deflate, Compression::None],
+            frame_tap,
+        )
+        .await?;
+
+        Ok((connection, endpoint))
+    }
+}
+
+/// Connects to the first address of the host which answers, returning the address it has connected to
+///
+/// The addresses are tried the happy eyeballs way (RFC 8305): IPv6 and IPv4 addresses take turns, each attempt
+/// starts once the previous one has failed or has been running for [ATTEMPT_DELAY], and the first connection
+/// established wins while the attempts still running are dropped.
+async fn connect(addr: &str) -> anyhow::Result<(TcpStream, SocketAddr)> {
+    let endpoints = lookup_host(addr)
+        .await
+        .with_context(|| format!("could not resolve {}", addr))?
+        .collect();
+    let mut endpoints = interleave(endpoints).into_iter();
+    let mut attempts = JoinSet::new();
+    let mut last_err = None;
+
+    loop {
+        if attempts.is_empty() {
+            match endpoints.next() {
+                Some(endpoint) => {
+                    attempts.spawn(attempt(endpoint));
+                }
+                None => break,
+            }
+        }
+
+        tokio::select! {
+            Some(result) = attempts.join_next() => match result? {
+                (endpoint, Ok(stream)) => return Ok((stream, endpoint)),
+                (_, Err(err)) => {
+                    last_err = Some(err);
+                    if let Some(endpoint) = endpoints.next() {
+                        attempts.spawn(attempt(endpoint));
+                    }
+                }
+            },
+            _ = tokio::time::sleep(ATTEMPT_DELAY), if endpoints.len() > 0 => {
+                if let Some(endpoint) = endpoints.next() {
+                    attempts.spawn(attempt(endpoint));
+                }
+            }
+        }
+    }
+
+    match last_err {
+        Some(err) => Err(err.into()),
+        None => Err(anyhow::anyhow!("{} has no address", addr)),
+    }
+}
+
+async fn attempt(endpoint: SocketAddr) -> (SocketAddr, std::io::Result<TcpStream>) {
+    (endpoint, TcpStream::connect(endpoint).await)
+}
+
+/// Orders the addresses so that the family of the first one and the other family take turns
+fn interleave(endpoints: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let Some(prefers_ipv6) = endpoints.first().map(SocketAddr::is_ipv6) else {
+        return endpoints;
+    };
+    let (preferred, others): (Vec<_>, Vec<_>) = endpoints
+        .into_iter()
+        .partition(|endpoint| endpoint.is_ipv6() == prefers_ipv6);
+    let mut interleaved = Vec::with_capacity(preferred.len() + others.len());
+    let (mut preferred, mut others) = (preferred.into_iter(), others.into_iter());
+
+    loop {
+        match (preferred.next(), others.next()) {
+            (None, None) => break,
+            (first, second) => interleaved.extend(first.into_iter().chain(second)),
+        }
+    }
+
+    interleaved
+}
@@ -0,0 +1,66 @@
+use std::mem::size_of;
+
+use super::{MessageBoxItem, Notice};
+
+/// How many mebibytes the messages of all rooms may take up, unless the user has picked another budget
+const DEFAULT_BUDGET_MIB: usize = 16;
+
+/// [MemoryBudget] bounds the memory the messages of all rooms take up, so long sessions in many rooms stay lean
+///
+/// Read from the `CHAT_TUI_MEMORY_BUDGET` environment variable in mebibytes, 0 lifts the bound.
+/// The sizes are estimates, counting the text and the images of the messages rather than every allocation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MemoryBudget {
+    /// None if the messages may take up as much memory as they need
+    max_bytes: Option<usize>,
+}
+
+impl MemoryBudget {
+    pub fn from_env() -> Self {
+        let budget_mib = std::env::var("CHAT_TUI_MEMORY_BUDGET")
+            .ok()
+            .and_then(|budget_mib| budget_mib.trim().parse().ok())
+            .unwrap_or(DEFAULT_BUDGET_MIB);
+
+        MemoryBudget {
+            max_bytes: (budget_mib > 0).then(|| budget_mib.saturating_mul(1024 * 1024)),
+        }
+    }
+
+    pub fn is_exceeded_by(&self, bytes: usize) -> bool {
+        self.max_bytes.is_some_and(|max_bytes| bytes > max_bytes)
+    }
+}
+
+/// Roughly how many bytes the item takes up, along with its text, link preview and image
+pub fn estimated_size(item: &MessageBoxItem) -> usize {
+    let heap_size = match item {
+        MessageBoxItem::Message {
+            id,
+            user_id,
+            display_name,
+            content,
+            link_preview,
+            image,
+            ..
+        } => {
+            let link_preview_size = link_preview.as_ref().map_or(0, |link_preview| {
+                link_preview.url.len()
+                    + link_preview.title.as_ref().map_or(0, String::len)
+                    + link_preview.description.as_ref().map_or(0, String::len)
+            });
+
+            id.len()
+                + user_id.len()
+                + display_name.as_ref().map_or(0, String::len)
+                + content.len()
+                + link_preview_size
+                + image.as_ref().map_or(0, |image| image.estimated_size())
+        }
+        // notices only hold a few words, e.g. the id of a user joining the room
+        MessageBoxItem::Notification(_) => size_of::<Notice>(),
+        MessageBoxItem::Welcome(message) => message.len(),
+    };
+
+    size_of::<MessageBoxItem>() + heap_size
+}
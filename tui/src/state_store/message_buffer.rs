@@ -0,0 +1,64 @@
+use std::collections::VecDeque;
+
+/// [MessageBuffer] keeps the messages of a room, bounded to a capacity
+///
+/// New messages are pushed at the newest end, while pages of older messages, e.g. from the history,
+/// are prepended at the oldest end. Once the capacity is exceeded, messages are evicted from the end
+/// opposite to the one inserted at, so the messages just inserted are always kept.
+#[derive(Debug, Clone)]
+pub struct MessageBuffer<T> {
+    items: VecDeque<T>,
+    capacity: usize,
+}
+
+impl<T> MessageBuffer<T> {
+    pub fn with_capacity(capacity: usize) -> Self {
+        MessageBuffer {
+            items: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Add the newest message, evicting the oldest one if the buffer is full
+    pub fn push(&mut self, item: T) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.items.len() == self.capacity {
+            self.items.pop_front();
+        }
+        self.items.push_back(item);
+    }
+
+    /// Add a page of older messages, ordered from the oldest to the newest, in front of the buffered ones
+    /// The newest buffered messages are evicted to make room, or the oldest of the page if it exceeds the capacity
+    pub fn prepend(&mut self, page: Vec<T>) {
+        // going from the newest to the oldest message of the page, only the buffered messages are evicted
+        for item in page.into_iter().rev().take(self.capacity) {
+            if self.items.len() == self.capacity {
+                self.items.pop_back();
+            }
+            self.items.push_front(item);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Iterate from the newest to the oldest message
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.items.iter().rev()
+    }
+
+    /// Iterate mutably from the newest to the oldest message, e.g. to replace removed ones
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.items.iter_mut().rev()
+    }
+
+    /// Keep only the messages for which the predicate holds, in their order
+    pub fn retain(&mut self, predicate: impl FnMut(&T) -> bool) {
+        self.items.retain(predicate);
+    }
+}
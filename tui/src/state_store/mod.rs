@@ -1,7 +1,14 @@
 pub use self::state::*;
 pub use self::state_store::StateStore;
+pub use self::traffic_log::{TrafficFrame, TrafficLog};
 
 pub mod action;
+mod connector;
+mod memory_budget;
+mod message_buffer;
+mod queued_writer;
+mod room_activity;
 mod state;
 #[allow(clippy::module_inception)]
 mod state_store;
+mod traffic_log;
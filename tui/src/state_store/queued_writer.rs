@@ -0,0 +1,145 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use comms::{command, transport::client::CommandWriter};
+use tokio::{
+    sync::mpsc::{self, error::TrySendError},
+    task::JoinHandle,
+};
+
+/// How many commands may wait to be written before the connection is taken for stalled
+const QUEUE_CAPACITY: usize = 256;
+
+/// How long writing a command may take before the connection is taken for stalled
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// [QueuedCommandWriter] hands the commands to a task writing them to the server
+///
+/// Writing only queues the command, so a connection the server has stopped reading from
+/// never holds up the state store. The task gives up on the connection once a command
+/// has not been written within [WRITE_TIMEOUT], or once [QUEUE_CAPACITY] commands are waiting,
+/// see [QueuedCommandWriter::stopped].
+pub struct QueuedCommandWriter {
+    command_tx: mpsc::Sender<command::UserCommand>,
+    writer: JoinHandle<anyhow::Result<()>>,
+    /// Set once the queue has been full, the task has been stopped then
+    has_overflowed: bool,
+    /// The commands which could not be queued, they are reported once the task has stopped
+    dropped_count: usize,
+}
+
+impl QueuedCommandWriter {
+    pub fn new(command_writer: CommandWriter) -> Self {
+        let (command_tx, command_rx) = mpsc::channel(QUEUE_CAPACITY);
+
+        QueuedCommandWriter {
+            command_tx,
+            writer: tokio::spawn(write_until_closed(command_writer, command_rx)),
+            has_overflowed: false,
+            dropped_count: 0,
+        }
+    }
+
+    /// Queues the command to be written after the ones queued before it
+    ///
+    /// A command which can not be queued is dropped, because too many commands are waiting already
+    /// or the task has given up on the connection. The task is stopped if the queue is full, so the
+    /// connection is given up on as well. The dropped commands are reported by [QueuedCommandWriter::stopped].
+    pub fn write(&mut self, command: &command::UserCommand) -> anyhow::Result<()> {
+        match self.command_tx.try_send(command.clone()) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                self.writer.abort();
+                self.has_overflowed = true;
+                self.dropped_count += 1;
+            }
+            Err(TrySendError::Closed(_)) => {
+                self.dropped_count += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resolves once the task has given up on the connection, with the reason why
+    /// and the number of commands which could not be sent, if any
+    ///
+    /// # Cancel Safety
+    ///
+    /// This method is cancel-safe, but must not be awaited again once it has resolved.
+    pub async fn stopped(&mut self) -> anyhow::Error {
+        let err = match (&mut self.writer).await {
+            Ok(Ok(())) => anyhow::anyhow!("the connection to the server has been closed"),
+            Ok(Err(err)) => err,
+            Err(err) if err.is_cancelled() && self.has_overflowed => anyhow::anyhow!(
+                "the server has not taken the last {} commands",
+                QUEUE_CAPACITY
+            ),
+            Err(err) => err.into(),
+        };
+
+        match self.dropped_count {
+            0 => err,
+            1 => err.context("a command could not be sent"),
+            count => err.context(format!("{} commands could not be sent", count)),
+        }
+    }
+
+    /// Writes the commands still queued and closes the connection
+    pub async fn shutdown(self) -> anyhow::Result<()> {
+        drop(self.command_tx);
+
+        self.writer.await?
+    }
+}
+
+async fn write_until_closed(
+    mut command_writer: CommandWriter,
+    mut command_rx: mpsc::Receiver<command::UserCommand>,
+) -> anyhow::Result<()> {
+    while let Some(command) = command_rx.recv().await {
+        tokio::time::timeout(WRITE_TIMEOUT, command_writer.write(&command))
+            .await
+            .with_context(|| {
+                format!(
+                    "the server has not taken a command within {:?}",
+                    WRITE_TIMEOUT
+                )
+            })??;
+    }
+
+    // shutting down the writer flushes whatever the compression stream still holds
+    tokio::time::timeout(WRITE_TIMEOUT, command_writer.shutdown())
+        .await
+        .context("the server has not taken the last commands")?
+}
+
+#[cfg(test)]
+mod tests {
+    use comms::transport::memory;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_a_full_queue_gives_up_on_the_connection() {
+        // the server end is never read from
+        let ((_events, command_writer), _server_end) = memory::pair();
+        let mut queued_writer = QueuedCommandWriter::new(command_writer);
+
+        let ping = command::UserCommand::Ping(command::PingCommand { sent_at: 1 });
+        for _ in 0..QUEUE_CAPACITY + 2 {
+            queued_writer.write(&ping).unwrap();
+        }
+
+        let err = tokio::time::timeout(Duration::from_secs(5), queued_writer.stopped())
+            .await
+            .expect("the writer should have stopped right away");
+        assert_eq!(
+            format!("{:#}", err),
+            format!(
+                "2 commands could not be sent: the server has not taken the last {} commands",
+                QUEUE_CAPACITY
+            )
+        );
+    }
+}
@@ -0,0 +1,70 @@
+use std::collections::VecDeque;
+
+/// The length of the period each bar of the activity indicator stands for
+const PERIOD_MILLIS: u64 = 60 * 1000;
+/// The number of periods shown, the latest one last
+const PERIODS: usize = 8;
+
+/// [RoomActivity] counts the messages of a room over the last minutes, e.g. to show which rooms are busy
+#[derive(Debug, Clone, Default)]
+pub struct RoomActivity {
+    /// The number of messages by the start of the period they were sent in, oldest first
+    message_counts: VecDeque<(u64, u32)>,
+}
+
+impl RoomActivity {
+    /// Count a message sent at the given time, messages older than the shown periods are ignored
+    pub fn record_message(&mut self, timestamp: u64, now: u64) {
+        let period = timestamp - timestamp % PERIOD_MILLIS;
+        let oldest_period = Self::oldest_period(now);
+        if period < oldest_period {
+            return;
+        }
+
+        // the history of a room may arrive after its live messages, the periods are kept sorted nonetheless
+        match self
+            .message_counts
+            .binary_search_by_key(&period, |(count_period, _)| *count_period)
+        {
+            Ok(idx) => self.message_counts[idx].1 += 1,
+            Err(idx) => self.message_counts.insert(idx, (period, 1)),
+        }
+
+        while self
+            .message_counts
+            .front()
+            .is_some_and(|(count_period, _)| *count_period < oldest_period)
+        {
+            self.message_counts.pop_front();
+        }
+    }
+
+    /// The number of messages in each of the shown periods, the latest one last
+    /// Empty if no message has been sent in any of them
+    pub fn rates(&self, now: u64) -> Vec<u32> {
+        let oldest_period = Self::oldest_period(now);
+        if self
+            .message_counts
+            .iter()
+            .all(|(period, _)| *period < oldest_period)
+        {
+            return Vec::new();
+        }
+
+        (0..PERIODS as u64)
+            .map(|idx| {
+                let period = oldest_period + idx * PERIOD_MILLIS;
+
+                self.message_counts
+                    .iter()
+                    .find(|(count_period, _)| *count_period == period)
+                    .map(|(_, count)| *count)
+                    .unwrap_or(0)
+            })
+            .collect()
+    }
+
+    fn oldest_period(now: u64) -> u64 {
+        (now - now % PERIOD_MILLIS).saturating_sub((PERIODS as u64 - 1) * PERIOD_MILLIS)
+    }
+}
@@ -1,13 +1,349 @@
+use comms::{command::ENCRYPTED_CONTENT_PREFIX, event};
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
-use std::fmt;
-use std::fmt::Formatter;
-use circular_queue::CircularQueue;
-use comms::event;
+use std::mem::size_of;
+use std::net::SocketAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{
+    memory_budget::{self, MemoryBudget},
+    message_buffer::MessageBuffer,
+    room_activity::RoomActivity,
+    traffic_log::{TrafficFrame, TrafficLog},
+};
+use crate::{
+    accessibility::Accessibility,
+    aliases::Aliases,
+    config::ClientConfig,
+    e2e,
+    highlights::Highlights,
+    inline_images::{ImageArt, RenderedImage},
+    keymap::Keymap,
+    locale::Locale,
+    notice_filter::NoticeFilter,
+    profanity::ProfanityFilter,
+    status_bar::StatusBar,
+    time_format::TimeFormat,
+};
 
 #[derive(Debug, Clone)]
 pub enum MessageBoxItem {
-    Message { user_id: String, content: String },
-    Notification(String),
+    Message {
+        /// The id the server has given the message, e.g. to remove it when it is redacted
+        id: String,
+        user_id: String,
+        /// The name the message has been posted under, e.g. by an integration
+        display_name: Option<String>,
+        content: String,
+        /// How the content is meant to be shown, e.g. as something the author does
+        content_type: event::ContentType,
+        /// The time the message was sent at, in milliseconds since the unix epoch
+        timestamp: u64,
+        /// Whether the message mentions the user or one of their watch words
+        is_highlighted: bool,
+        /// Whether the user has asked to read the message without its masked words
+        is_revealed: bool,
+        /// The preview of a link in the message, once the server has fetched it
+        link_preview: Option<LinkPreview>,
+        /// The image linked in the message, once the client has fetched it
+        image: Option<ImageArt>,
+        /// Whether the user has sent the message, from this session or another one
+        is_own: bool,
+        /// The nonce the message has been sent with from this session, until the server has broadcast it
+        pending_nonce: Option<String>,
+    },
+    Notification(Notice),
+    /// The welcome message of a room, rendered apart from the other notifications
+    Welcome(String),
+}
+
+/// The title and description of a page linked in a message, as fetched by the server
+#[derive(Debug, Clone)]
+pub struct LinkPreview {
+    pub url: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+}
+
+/// A system message shown among the messages of a room
+///
+/// Only the facts are kept, the [crate::presentation] module words and styles them.
+#[derive(Debug, Clone)]
+pub enum Notice {
+    Participation {
+        user_id: String,
+        has_joined: bool,
+    },
+    RoomFull {
+        capacity: usize,
+    },
+    QueuePosition {
+        position: usize,
+    },
+    MessageRejected(event::MessageRejectionReason),
+    WelcomeMessageUpdated {
+        message: Option<String>,
+    },
+    CommandRefused {
+        reason: String,
+    },
+    GroupMembershipChanged {
+        user_id: String,
+        group: String,
+        is_member: bool,
+    },
+    DeviceConnected {
+        session_id: String,
+        address: String,
+    },
+    DeviceDisconnected {
+        session_id: String,
+    },
+    OpenSessions {
+        sessions: Vec<event::SessionDetail>,
+    },
+    BotTokenIssued {
+        bot_name: String,
+        user_id: String,
+        token: String,
+    },
+    BotTokenRevoked {
+        bot_name: String,
+        revoked: bool,
+    },
+    /// The remaining time is taken once the reminder is set, so the notice does not count down
+    ReminderSet {
+        remaining_ms: u64,
+    },
+    ReminderDue {
+        content: String,
+    },
+    /// A scheduled announcement of the server
+    Announcement {
+        message: String,
+    },
+    /// A message of an encrypted room arrived before its key, or was encrypted with another key
+    UndecryptableMessage {
+        user_id: String,
+    },
+    /// A message was not sent, since the key of the encrypted room has not arrived yet
+    RoomKeyMissing,
+    /// The user has joined an encrypted room and waits for a member to share its key with the session
+    RoomKeyAwaited {
+        fingerprint: String,
+    },
+    /// A session asks for the key of the room, the user shares it once they have compared the fingerprint
+    RoomKeyRequested {
+        user_id: String,
+        fingerprint: String,
+    },
+    RoomKeyShared {
+        user_id: String,
+    },
+    /// The user asked to share the key of a room with a session which has not asked for it
+    NoRoomKeyRequest {
+        fingerprint: String,
+    },
+    /// A message has been removed by the server, e.g. when an admin has deleted the data of its author
+    MessageRemoved,
+    UserInfo(event::UserInfoReplyEvent),
+    UserSearchResults(event::UserSearchResultsReplyEvent),
+    RoomInfo(event::RoomInfoReplyEvent),
+    RoomStats(event::RoomStatsReplyEvent),
+    ReportFiled {
+        room: String,
+    },
+    /// The user asked to report a user who has no message in the active room
+    NoMessageToReport {
+        user_id: String,
+    },
+    /// The user asked to reveal a message, but the active room has no message with masked words
+    NothingToReveal,
+    MessageReported(event::MessageReport),
+    OpenReports {
+        reports: Vec<event::MessageReport>,
+    },
+    ReportResolved {
+        report_id: String,
+        resolved: bool,
+    },
+    /// The room requires approval, the user waits for a moderator to let them in
+    JoinRequestAwaitingApproval,
+    /// Shown to moderators, a user asks to join the room
+    JoinRequested {
+        user_id: String,
+    },
+    /// A moderator has decided on the request of the user to join the room
+    OwnJoinRequestDecided {
+        approved: bool,
+        decided_by: String,
+    },
+    /// Shown to moderators, a moderator has decided on the request of another user
+    JoinRequestDecided {
+        user_id: String,
+        approved: bool,
+        decided_by: String,
+    },
+    /// A name given in a command was not accepted, e.g. the name of a new bot
+    NameRejected {
+        name: String,
+        reason: event::NameRejectionReason,
+    },
+    /// The user fell behind the events of the room, the missed messages are caught up from its history
+    RoomResynced {
+        missed_events: u64,
+    },
+    /// The user has left the room, selecting it joins it again
+    RoomLeft,
+    /// The room has been archived, its history is kept but it takes no more messages
+    RoomArchived,
+    /// The room takes messages again, or is no longer going to be archived
+    RoomReopened,
+    /// The server no longer lists the room, e.g. the user can not access it anymore, its messages are kept
+    RoomRemoved,
+    /// An owner of the room has changed its description
+    RoomDescriptionChanged {
+        description: String,
+    },
+    /// The remaining time is taken once the archival is scheduled, so the notice does not count down
+    RoomArchivalScheduled {
+        remaining_ms: u64,
+    },
+    /// The remaining time is taken once the code is created, so the notice does not count down
+    InviteCodeCreated {
+        code: String,
+        remaining_ms: u64,
+        max_uses: u32,
+    },
+    /// The user has switched whether users joining and leaving the active room are shown
+    ParticipationToggled {
+        is_shown: bool,
+    },
+    /// Shown by a script of the user, see [crate::scripting::Scripts]
+    ScriptNotification {
+        text: String,
+    },
+    /// A script of the user has raised an error or run for too long
+    ScriptFailed {
+        error: String,
+    },
+    /// The server has handed the session to another one, which the client has connected to
+    Redirected {
+        addr: String,
+    },
+    /// Every event of the recording being played back has been shown
+    ReplayEnded,
+    /// The user tried to do something which needs a server while a recording is played back
+    ReplayReadOnly,
+}
+
+/// How much a notice calls for the attention of the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    /// Something did not go as expected, e.g. the room is full
+    Warning,
+    /// Something the user asked for has failed, e.g. a message was rejected
+    Error,
+}
+
+/// The class of a notice, e.g. to hide the users joining and leaving busy rooms
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NoticeKind {
+    /// Another user has joined the room
+    Join,
+    /// Another user has left the room
+    Leave,
+    /// The description or the welcome message of the room has changed
+    Topic,
+    /// Reports, join requests, group memberships and removed messages
+    Moderation,
+    /// Everything else, mostly answers to the commands of the user
+    System,
+}
+
+impl NoticeKind {
+    pub const ALL: [NoticeKind; 5] = [
+        NoticeKind::Join,
+        NoticeKind::Leave,
+        NoticeKind::Topic,
+        NoticeKind::Moderation,
+        NoticeKind::System,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NoticeKind::Join => "join",
+            NoticeKind::Leave => "leave",
+            NoticeKind::Topic => "topic",
+            NoticeKind::Moderation => "moderation",
+            NoticeKind::System => "system",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.name() == name.trim().to_lowercase())
+    }
+}
+
+impl Notice {
+    pub fn kind(&self) -> NoticeKind {
+        match self {
+            Notice::Participation {
+                has_joined: true, ..
+            } => NoticeKind::Join,
+            Notice::Participation {
+                has_joined: false, ..
+            } => NoticeKind::Leave,
+            Notice::WelcomeMessageUpdated { .. } | Notice::RoomDescriptionChanged { .. } => {
+                NoticeKind::Topic
+            }
+            Notice::GroupMembershipChanged { .. }
+            | Notice::MessageRemoved
+            | Notice::ReportFiled { .. }
+            | Notice::NoMessageToReport { .. }
+            | Notice::MessageReported(_)
+            | Notice::OpenReports { .. }
+            | Notice::ReportResolved { .. }
+            | Notice::JoinRequestAwaitingApproval
+            | Notice::JoinRequested { .. }
+            | Notice::OwnJoinRequestDecided { .. }
+            | Notice::JoinRequestDecided { .. } => NoticeKind::Moderation,
+            _ => NoticeKind::System,
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            Notice::MessageRejected(_)
+            | Notice::CommandRefused { .. }
+            | Notice::NameRejected { .. }
+            | Notice::UndecryptableMessage { .. }
+            | Notice::RoomKeyMissing
+            | Notice::ScriptFailed { .. } => Severity::Error,
+            Notice::RoomFull { .. }
+            | Notice::DeviceConnected { .. }
+            | Notice::BotTokenRevoked { revoked: false, .. }
+            | Notice::NoMessageToReport { .. }
+            | Notice::NothingToReveal
+            | Notice::NoRoomKeyRequest { .. }
+            | Notice::ReportResolved {
+                resolved: false, ..
+            }
+            | Notice::OwnJoinRequestDecided {
+                approved: false, ..
+            }
+            | Notice::RoomResynced { .. }
+            | Notice::RoomArchived
+            | Notice::RoomRemoved
+            | Notice::RoomArchivalScheduled { .. }
+            | Notice::ReplayReadOnly => Severity::Warning,
+            _ => Severity::Info,
+        }
+    }
 }
 
 const MAX_MESSAGES_TO_STORE_PER_ROOM: usize = 100;
@@ -21,14 +357,38 @@ pub struct RoomData {
     pub description: String,
     /// List of users in the room
     pub users: HashSet<String>,
+    /// The users in the room who are away
+    pub away_users: HashSet<String>,
+    /// The status texts of the users in the room who have one
+    pub status_texts: HashMap<String, String>,
     /// History of recorded messages
-    pub messages: CircularQueue<MessageBoxItem>,
+    pub messages: MessageBuffer<MessageBoxItem>,
+    /// The ids of the latest messages and history entries, the same message may arrive live and with the history
+    message_ids: MessageBuffer<String>,
     /// Has joined the room
     pub has_joined: bool,
     /// Has unread messages
     pub has_unread: bool,
     /// First time joining room
     pub first_time: bool,
+    /// Whether the messages of the room are encrypted end-to-end
+    pub encrypted: bool,
+    /// Whether the key of the encrypted room is known, messages can neither be read nor sent without it
+    pub has_room_key: bool,
+    /// Whether the room has been archived, it keeps its history but takes no more messages
+    pub archived: bool,
+    /// The category the room is listed under, if any
+    pub category: Option<String>,
+    /// Whether the server no longer lists the room, its messages are kept to be read
+    pub defunct: bool,
+    /// The number of messages sent to the room over the last minutes
+    activity: RoomActivity,
+    /// How many entries of the history have arrived and how many there are, while the server streams it
+    pub history_progress: Option<(usize, usize)>,
+    /// The entries of the history streamed so far, shown at once when the last one has arrived
+    history_page: Vec<MessageBoxItem>,
+    /// When the user has last opened or left the room, in milliseconds since the unix epoch, 0 if never
+    last_viewed_at: u64,
 }
 
 impl Default for RoomData {
@@ -37,41 +397,182 @@ impl Default for RoomData {
             name: String::new(),
             description: String::new(),
             users: HashSet::new(),
-            messages: CircularQueue::with_capacity(MAX_MESSAGES_TO_STORE_PER_ROOM),
+            away_users: HashSet::new(),
+            status_texts: HashMap::new(),
+            messages: MessageBuffer::with_capacity(MAX_MESSAGES_TO_STORE_PER_ROOM),
+            message_ids: MessageBuffer::with_capacity(MAX_MESSAGES_TO_STORE_PER_ROOM),
             has_joined: false,
             has_unread: false,
             first_time: true,
+            encrypted: false,
+            has_room_key: false,
+            archived: false,
+            category: None,
+            defunct: false,
+            activity: RoomActivity::default(),
+            history_progress: None,
+            history_page: Vec::new(),
+            last_viewed_at: 0,
         }
     }
 }
 
 impl RoomData {
-    pub fn new(name: String, description: String) -> Self {
+    pub fn new(name: String, description: String, encrypted: bool) -> Self {
         RoomData {
             name,
             description,
+            encrypted,
             ..Default::default()
         }
     }
+
+    /// The number of messages sent to the room in each of the last minutes, the latest one last
+    /// Empty if the room has been quiet, or the user has not joined it
+    pub fn recent_activity(&self) -> Vec<u32> {
+        self.activity.rates(now_millis())
+    }
+
+    /// Records the id of a message or history entry
+    /// Returns false if it has already been received, e.g. when the history is fetched again
+    fn record_message_id(&mut self, id: &str) -> bool {
+        if self.message_ids.iter().any(|message_id| message_id == id) {
+            return false;
+        }
+
+        self.message_ids.push(String::from(id));
+        true
+    }
+
+    /// The message sent with the nonce from this session which the server has not broadcast yet
+    fn pending_message_mut(&mut self, nonce: &str) -> Option<&mut MessageBoxItem> {
+        self.messages.iter_mut().find(|item| {
+            matches!(item, MessageBoxItem::Message { pending_nonce: Some(pending_nonce), .. } if pending_nonce == nonce)
+        })
+    }
+
+    /// Removes the message sent with the nonce from this session, e.g. once the server has rejected it
+    fn remove_pending_message(&mut self, nonce: &str) {
+        self.messages.retain(|item| {
+            !matches!(item, MessageBoxItem::Message { pending_nonce: Some(pending_nonce), .. } if pending_nonce == nonce)
+        });
+    }
+
+    /// Roughly how many bytes the messages of the room take up, see [MemoryBudget]
+    fn estimated_size(&self) -> usize {
+        let messages_size = self
+            .messages
+            .iter()
+            .chain(self.history_page.iter())
+            .map(memory_budget::estimated_size)
+            .sum::<usize>();
+        let message_ids_size = self
+            .message_ids
+            .iter()
+            .map(|message_id| size_of::<String>() + message_id.len())
+            .sum::<usize>();
+
+        messages_size + message_ids_size
+    }
+
+    /// Drops the messages of the room to free their memory, they are fetched with the history once the room is opened
+    fn drop_messages(&mut self) {
+        self.messages = MessageBuffer::with_capacity(MAX_MESSAGES_TO_STORE_PER_ROOM);
+        self.message_ids = MessageBuffer::with_capacity(MAX_MESSAGES_TO_STORE_PER_ROOM);
+        self.first_time = true;
+    }
+
+    /// Converts the history entries to [MessageBoxItem]s, the same way as the live events
+    /// Entries which have already been received are skipped, so fetching the history again is harmless
+    fn history_items(
+        &mut self,
+        history: Vec<event::HistoricalMessage>,
+        highlights: &Highlights,
+        own_user_id: &str,
+    ) -> Vec<MessageBoxItem> {
+        let mut items = Vec::new();
+        let now = now_millis();
+        for entry in history {
+            if !self.record_message_id(&entry.id) {
+                continue;
+            }
+            if entry.kind == event::HistoricalMessageKind::Message {
+                self.activity.record_message(entry.timestamp, now);
+            }
+
+            items.push(match entry.kind {
+                event::HistoricalMessageKind::Message => {
+                    message_box_item(highlights, own_user_id, entry)
+                }
+                event::HistoricalMessageKind::Joined => {
+                    MessageBoxItem::Notification(Notice::Participation {
+                        user_id: entry.user_id,
+                        has_joined: true,
+                    })
+                }
+                event::HistoricalMessageKind::Left => {
+                    MessageBoxItem::Notification(Notice::Participation {
+                        user_id: entry.user_id,
+                        has_joined: false,
+                    })
+                }
+            });
+        }
+
+        items
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum ServerConnectionStatus {
     Uninitialized,
     Connecting,
-    Connected { addr: String },
-    Errored { err: String },
+    /// Waiting for the user to log in with the identity provider on another device
+    Authorizing {
+        verification_uri: String,
+        user_code: String,
+    },
+    /// Connected to the server but not logged in yet, the user picks the name to log in with
+    LoggingIn {
+        addr: String,
+        /// The address of the host the client has connected to, one of the ones `addr` resolves to
+        endpoint: SocketAddr,
+        /// Whether a login has been sent and the server has not answered it yet
+        is_pending: bool,
+        /// Why the server has refused the last login, the user may try again with another name
+        rejection: Option<ConnectionError>,
+    },
+    Connected {
+        addr: String,
+        /// The address of the host the client has connected to, one of the ones `addr` resolves to
+        endpoint: SocketAddr,
+    },
+    /// Playing a recording back, see [crate::replay::Replay]
+    Replaying {
+        path: String,
+    },
+    Errored {
+        err: ConnectionError,
+    },
 }
 
-impl fmt::Display for ServerConnectionStatus {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        match self {
-            ServerConnectionStatus::Uninitialized => write!(f, "Uninitialized"),
-            ServerConnectionStatus::Connecting => write!(f, "Connecting"),
-            ServerConnectionStatus::Connected { addr } => write!(f, "Connected to {}", addr),
-            ServerConnectionStatus::Errored { err } => write!(f, "Errored: {}", err),
-        }
-    }
+/// Why the connection to the server was refused or lost
+#[derive(Debug, Clone)]
+pub enum ConnectionError {
+    /// The connection could not be established, e.g. the server is not reachable
+    Failed(String),
+    LoginRejected {
+        reason: String,
+        /// Set if the user id is not a valid name, worded by the user interface instead of the server
+        name_rejection: Option<event::NameRejectionReason>,
+    },
+    SessionClosed {
+        reason: String,
+    },
+    /// The user has logged in again from elsewhere, which took over
+    SessionReplaced {
+        address: String,
+    },
 }
 
 /// State holds the state of the application
@@ -82,10 +583,93 @@ pub struct State {
     pub active_room: Option<String>,
     /// The id of the user
     pub user_id: String,
+    /// The maximum number of characters a message can have, known once logged in
+    pub max_message_length: Option<usize>,
+    /// The number of sessions the user has open, including this one
+    ///
+    /// A snapshot taken at login, kept up to date by the device events the server sends as the other
+    /// sessions connect and drop. The server skips those events for a session lagging behind,
+    /// listing the sessions takes a fresh snapshot then. Reset along with the rest of the state on disconnect.
+    pub session_count: usize,
     /// Storage of room data
     pub room_data_map: HashMap<String, RoomData>,
     /// Timer since app was opened
     pub timer: usize,
+    /// Whether the terminal has the focus of the user, messages are not read while it does not
+    pub is_focused: bool,
+    /// How the user interface adapts to the needs of the user
+    pub accessibility: Accessibility,
+    /// The language of the user interface
+    pub locale: Locale,
+    /// Which messages are highlighted for the user
+    pub highlights: Highlights,
+    /// Which words are masked in the messages shown to the user
+    pub profanity_filter: ProfanityFilter,
+    /// Which kinds of notices are kept out of the message pane
+    pub notice_filter: NoticeFilter,
+    /// Which segments the status bar shows
+    pub status_bar: StatusBar,
+    /// The shortcuts the user has defined for slash commands and function keys
+    pub aliases: Aliases,
+    /// The round trip time to the server measured last, in milliseconds
+    pub latency_ms: Option<u64>,
+    /// The line printed by the command of the status bar when it last ran
+    pub status_command_output: Option<String>,
+    /// The keys the user navigates with besides the arrow keys
+    pub keymap: Keymap,
+    /// Whether the user is shown as away to the others while the terminal is unfocused
+    pub auto_away: bool,
+    /// How many seconds apart the consecutive messages of a user may be to be grouped under their name, 0 for never
+    pub grouping_window_secs: u64,
+    /// How the times of the messages, the days between them and the clock are shown
+    pub time_format: TimeFormat,
+    /// How much memory the messages of all rooms may take up before the ones of rooms not viewed lately are dropped
+    pub memory_budget: MemoryBudget,
+    /// The address the connect page starts with
+    pub server_address: String,
+    /// Why the settings could not be saved to the config file the last time they were changed
+    pub config_error: Option<String>,
+    /// The name the user has picked for themselves
+    pub username: Option<String>,
+    /// The rooms joined right after logging in
+    pub auto_join_rooms: Vec<String>,
+    /// The rooms the user has left lately, the latest first, kept across restarts to rejoin them quickly
+    pub recent_rooms: Vec<String>,
+    /// Whether there is no config file yet, new users are guided through the settings before connecting
+    pub is_first_run: bool,
+    /// The frames recorded by the traffic inspector of the debug page, None while it is off
+    pub traffic_log: Option<TrafficLog>,
+    /// The rooms to join again once logged in to the server the session has been handed to, see [State::mark_redirected]
+    pub rooms_to_resume: Option<Vec<String>>,
+    /// Whether the server has mentioned rooms the client does not know of, e.g. created since the login,
+    /// the rooms are listed again to learn about them
+    pub has_unknown_rooms: bool,
+    /// The sessions asking for the keys of encrypted rooms, until the user shares the key with them
+    pub room_key_requests: Vec<event::RoomKeyRequestedBroadcastEvent>,
+}
+
+/// The address the connect page starts with if the user has not picked another one
+const DEFAULT_SERVER_ADDR: &str = "localhost:8080";
+
+/// How many of the rooms the user has left lately are remembered
+const MAX_RECENT_ROOMS: usize = 5;
+
+/// How long a user may pause between messages for them to be grouped, unless the user has picked another window
+const DEFAULT_GROUPING_WINDOW_SECS: u64 = 5 * 60;
+
+/// Reads the message grouping window in seconds from the `CHAT_TUI_GROUPING_WINDOW` environment variable
+fn grouping_window_from_env() -> u64 {
+    std::env::var("CHAT_TUI_GROUPING_WINDOW")
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(DEFAULT_GROUPING_WINDOW_SECS)
+}
+
+/// Reads whether auto-away is enabled from the `CHAT_TUI_AUTO_AWAY` environment variable, on by default
+fn auto_away_from_env() -> bool {
+    std::env::var("CHAT_TUI_AUTO_AWAY")
+        .map(|value| !matches!(value.to_lowercase().as_str(), "0" | "false" | "off"))
+        .unwrap_or(true)
 }
 
 impl Default for State {
@@ -94,23 +678,152 @@ impl Default for State {
             server_connection_status: ServerConnectionStatus::Uninitialized,
             active_room: None,
             user_id: String::new(),
+            max_message_length: None,
+            session_count: 0,
             room_data_map: HashMap::new(),
             timer: 0,
+            is_focused: true,
+            accessibility: Accessibility::from_env(),
+            locale: Locale::from_env(),
+            highlights: Highlights::from_env(),
+            profanity_filter: ProfanityFilter::from_env(),
+            notice_filter: NoticeFilter::from_env(),
+            status_bar: StatusBar::from_env(),
+            aliases: Aliases::from_env(),
+            latency_ms: None,
+            status_command_output: None,
+            keymap: Keymap::from_env(),
+            auto_away: auto_away_from_env(),
+            grouping_window_secs: grouping_window_from_env(),
+            time_format: TimeFormat::from_env(),
+            memory_budget: MemoryBudget::from_env(),
+            server_address: String::from(DEFAULT_SERVER_ADDR),
+            config_error: None,
+            username: None,
+            auto_join_rooms: Vec::new(),
+            recent_rooms: Vec::new(),
+            is_first_run: false,
+            traffic_log: None,
+            rooms_to_resume: None,
+            has_unknown_rooms: false,
+            room_key_requests: Vec::new(),
         }
     }
 }
 
+/// A message of a room as seen by the user, messages left encrypted could not be decrypted by the state store
+fn message_box_item(
+    highlights: &Highlights,
+    own_user_id: &str,
+    message: event::HistoricalMessage,
+) -> MessageBoxItem {
+    if message.content.starts_with(ENCRYPTED_CONTENT_PREFIX) {
+        MessageBoxItem::Notification(Notice::UndecryptableMessage {
+            user_id: message.user_id,
+        })
+    } else {
+        MessageBoxItem::Message {
+            is_highlighted: highlights.is_highlighted(
+                own_user_id,
+                &message.user_id,
+                &message.content,
+            ),
+            is_revealed: false,
+            link_preview: None,
+            image: None,
+            is_own: message.user_id == own_user_id,
+            pending_nonce: None,
+            id: message.id,
+            user_id: message.user_id,
+            display_name: message.display_name,
+            content: message.content,
+            content_type: message.content_type,
+            timestamp: message.timestamp,
+        }
+    }
+}
+
+/// The current unix timestamp in milliseconds
+pub(super) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
 impl State {
     pub fn handle_server_event(&mut self, event: &event::Event) {
         match event {
             event::Event::LoginSuccessful(event) => {
+                if let ServerConnectionStatus::LoggingIn { addr, endpoint, .. } =
+                    &self.server_connection_status
+                {
+                    self.server_connection_status = ServerConnectionStatus::Connected {
+                        addr: addr.clone(),
+                        endpoint: *endpoint,
+                    };
+                }
                 self.user_id = event.user_id.clone();
+                self.max_message_length = Some(event.max_message_length);
+                self.session_count = event.session_count;
+                // the rooms are kept with their messages after a redirect, only the users in them have to be learnt again
+                let mut previous_room_data_map = std::mem::take(&mut self.room_data_map);
                 self.room_data_map = event
                     .rooms
                     .clone()
                     .into_iter()
-                    .map(|r: event::RoomDetail| (r.name.clone(), RoomData::new(r.name, r.description)))
+                    .map(|r: event::RoomDetail| {
+                        let room_data = match previous_room_data_map.remove(&r.name) {
+                            Some(room_data) => RoomData {
+                                description: r.description,
+                                encrypted: r.encrypted,
+                                users: HashSet::new(),
+                                away_users: HashSet::new(),
+                                status_texts: HashMap::new(),
+                                has_joined: false,
+                                ..room_data
+                            },
+                            None => RoomData::new(r.name.clone(), r.description, r.encrypted),
+                        };
+
+                        (
+                            r.name,
+                            RoomData {
+                                archived: r.archived,
+                                category: r.category,
+                                ..room_data
+                            },
+                        )
+                    })
                     .collect();
+                if self
+                    .active_room
+                    .as_ref()
+                    .is_some_and(|active_room| !self.room_data_map.contains_key(active_room))
+                {
+                    self.active_room = None;
+                }
+            }
+            event::Event::Pong(event) => {
+                self.latency_ms = Some(now_millis().saturating_sub(event.sent_at));
+            }
+            event::Event::LoginRejected(event) => {
+                let err = ConnectionError::LoginRejected {
+                    reason: event.reason.clone(),
+                    name_rejection: event.name_rejection.clone(),
+                };
+                // the reason is shown on the login page, where the user may try another name
+                if let ServerConnectionStatus::LoggingIn {
+                    is_pending,
+                    rejection,
+                    ..
+                } = &mut self.server_connection_status
+                {
+                    *is_pending = false;
+                    *rejection = Some(err);
+                } else {
+                    self.server_connection_status = ServerConnectionStatus::Errored { err };
+                }
             }
             event::Event::RoomParticipation(event) => {
                 if let Some(room_data) = self.room_data_map.get_mut(&event.room) {
@@ -123,6 +836,8 @@ impl State {
                         }
                         event::RoomParticipationStatus::Left => {
                             room_data.users.remove(&event.user_id);
+                            room_data.away_users.remove(&event.user_id);
+                            room_data.status_texts.remove(&event.user_id);
                             if event.user_id == self.user_id {
                                 room_data.has_joined = false;
                             }
@@ -131,74 +846,978 @@ impl State {
 
                     room_data
                         .messages
-                        .push(MessageBoxItem::Notification(format!(
-                            "{} has {} the room",
-                            event.user_id,
-                            match event.status {
-                                event::RoomParticipationStatus::Joined => "joined",
-                                event::RoomParticipationStatus::Left => "left",
-                            }
-                        )));
+                        .push(MessageBoxItem::Notification(Notice::Participation {
+                            user_id: event.user_id.clone(),
+                            has_joined: event.status == event::RoomParticipationStatus::Joined,
+                        }));
                 }
             }
             event::Event::UserJoinedRoom(event) => {
-                self.room_data_map.get_mut(&event.room).unwrap().users =
-                    event.users.clone().into_iter().collect();                
+                self.add_placeholder_if_unknown(&event.room);
+                if let Some(room_data) = self.room_data_map.get_mut(&event.room) {
+                    room_data.users = event.users.clone().into_iter().collect();
+                    room_data.status_texts = event.status_texts.clone().into_iter().collect();
+                }
+            }
+            event::Event::RoomFull(event) => {
+                if let Some(room_data) = self.room_data_map.get_mut(&event.room) {
+                    room_data
+                        .messages
+                        .push(MessageBoxItem::Notification(Notice::RoomFull {
+                            capacity: event.capacity,
+                        }));
+                }
+            }
+            event::Event::RoomQueuePosition(event) => {
+                if let Some(room_data) = self.room_data_map.get_mut(&event.room) {
+                    room_data
+                        .messages
+                        .push(MessageBoxItem::Notification(Notice::QueuePosition {
+                            position: event.position,
+                        }));
+                }
             }
             event::Event::UserMessage(event) => {
-                let room_data = self.room_data_map.get_mut(&event.room).unwrap();
+                self.add_placeholder_if_unknown(&event.room);
+                let Some(room_data) = self.room_data_map.get_mut(&event.room) else {
+                    return;
+                };
+                if !room_data.record_message_id(&event.id) {
+                    return;
+                }
+                let now = now_millis();
+                room_data.activity.record_message(now, now);
 
-                room_data.messages.push(MessageBoxItem::Message {
-                    user_id: event.user_id.clone(),
-                    content: event.content.clone(),
-                });
+                // shown the same as the entry the message has in the history of the room
+                let mut item = message_box_item(
+                    &self.highlights,
+                    &self.user_id,
+                    event::HistoricalMessage {
+                        id: event.id.clone(),
+                        user_id: event.user_id.clone(),
+                        display_name: event.display_name.clone(),
+                        content: event.content.clone(),
+                        content_type: event.content_type,
+                        // the time it has been received at for recordings made before servers sent it
+                        timestamp: if event.timestamp > 0 {
+                            event.timestamp
+                        } else {
+                            now
+                        },
+                        kind: event::HistoricalMessageKind::Message,
+                    },
+                );
+                // the message this session has shown while sending it is replaced rather than shown twice
+                match event
+                    .nonce
+                    .as_ref()
+                    .and_then(|nonce| room_data.pending_message_mut(nonce))
+                {
+                    Some(pending_message) => {
+                        if let MessageBoxItem::Message { is_own, .. } = &mut item {
+                            // the user id of the session may have changed since, e.g. for guests reconnecting
+                            *is_own = true;
+                        }
+                        *pending_message = item;
+                    }
+                    None => room_data.messages.push(item),
+                }
 
                 if let Some(active_room) = self.active_room.as_ref() {
-                    if !active_room.eq(&event.room) {
+                    // messages of the active room are not read either while the terminal is unfocused
+                    if !active_room.eq(&event.room) || !self.is_focused {
                         room_data.has_unread = true;
                     }
                 }
             }
-            event::Event::HistoryResponse(event) => {
+            event::Event::Announcement(event) => {
                 if let Some(room_data) = self.room_data_map.get_mut(&event.room) {
-                    // Convert each (user_id, content) pair to MessageBoxItem
-                    for (user_id, content) in event.history.clone() {
-                        room_data.messages.push(MessageBoxItem::Message {
-                            user_id,
-                            content,
-                        });
+                    room_data
+                        .messages
+                        .push(MessageBoxItem::Notification(Notice::Announcement {
+                            message: event.message.clone(),
+                        }));
+
+                    if self.active_room.as_ref() != Some(&event.room) || !self.is_focused {
+                        room_data.has_unread = true;
                     }
+                }
+            }
+            event::Event::HistoryResponse(event) => {
+                if let Some(room_data) = self.room_data_map.get_mut(&event.room) {
+                    let page = room_data.history_items(
+                        event.history.clone(),
+                        &self.highlights,
+                        &self.user_id,
+                    );
+                    // the history is older than the messages received since joining the room
+                    room_data.messages.prepend(page);
                     room_data.first_time = false;
                 }
+            }
+            event::Event::HistoryChunk(event) => {
+                if let Some(room_data) = self.room_data_map.get_mut(&event.room) {
+                    let received_count = room_data
+                        .history_progress
+                        .map_or(0, |(received_count, _)| received_count)
+                        + event.history.len();
+                    // the history may have been asked for again while it streams, its entries are skipped
+                    let received_count = received_count.min(event.total_count);
+                    let mut items = room_data.history_items(
+                        event.history.clone(),
+                        &self.highlights,
+                        &self.user_id,
+                    );
+                    room_data.history_page.append(&mut items);
+                    room_data.history_progress = Some((received_count, event.total_count));
+                }
+            }
+            event::Event::HistoryDone(event) => {
+                if let Some(room_data) = self.room_data_map.get_mut(&event.room) {
+                    // the chunks are kept until the last one, prepending each would put them in reverse order
+                    let page = std::mem::take(&mut room_data.history_page);
+                    room_data.messages.prepend(page);
+                    room_data.history_progress = None;
+                    room_data.first_time = false;
+                }
+            }
+            event::Event::MessageRejected(event) => {
+                if let Some(room_data) = self.room_data_map.get_mut(&event.room) {
+                    if let Some(nonce) = event.nonce.as_ref() {
+                        room_data.remove_pending_message(nonce);
+                    }
+                    room_data
+                        .messages
+                        .push(MessageBoxItem::Notification(Notice::MessageRejected(
+                            event.reason.clone(),
+                        )));
+                }
+            }
+            event::Event::RoomWelcome(event) => {
+                if let Some(room_data) = self.room_data_map.get_mut(&event.room) {
+                    room_data
+                        .messages
+                        .push(MessageBoxItem::Welcome(event.message.clone()));
+                }
+            }
+            event::Event::WelcomeMessageUpdated(event) => {
+                if let Some(room_data) = self.room_data_map.get_mut(&event.room) {
+                    room_data.messages.push(MessageBoxItem::Notification(
+                        Notice::WelcomeMessageUpdated {
+                            message: event.message.clone(),
+                        },
+                    ));
+                }
+            }
+            event::Event::CommandRejected(event) => {
+                self.push_notification_to_active_room(Notice::CommandRefused {
+                    reason: event.reason.clone(),
+                });
+            }
+            event::Event::AccessibleRoomsChanged(event) => self.reconcile_rooms(&event.rooms),
+            event::Event::RoomList(event) => self.reconcile_rooms(&event.rooms),
+            event::Event::GroupMembershipChanged(event) => {
+                self.push_notification_to_active_room(Notice::GroupMembershipChanged {
+                    user_id: event.user_id.clone(),
+                    group: event.group.clone(),
+                    is_member: event.is_member,
+                });
+            }
+            event::Event::DeviceConnected(event) => {
+                self.session_count = event.session_count;
+                self.push_notification_to_active_room(Notice::DeviceConnected {
+                    session_id: event.session.session_id.clone(),
+                    address: event.session.address.clone(),
+                });
+            }
+            event::Event::DeviceDisconnected(event) => {
+                self.session_count = event.session_count;
+                self.push_notification_to_active_room(Notice::DeviceDisconnected {
+                    session_id: event.session_id.clone(),
+                });
+            }
+            event::Event::SessionList(event) => {
+                self.session_count = event.sessions.len();
+                self.push_notification_to_active_room(Notice::OpenSessions {
+                    sessions: event.sessions.clone(),
+                });
+            }
+            event::Event::ReadStateSynced(event) => {
+                if let Some(room_data) = self.room_data_map.get_mut(&event.room) {
+                    room_data.has_unread = false;
+                }
+            }
+            event::Event::PresenceChanged(event) => {
+                if let Some(room_data) = self.room_data_map.get_mut(&event.room) {
+                    match event.status {
+                        event::PresenceStatus::Online => {
+                            room_data.away_users.remove(&event.user_id);
+                        }
+                        event::PresenceStatus::Away => {
+                            room_data.away_users.insert(event.user_id.clone());
+                        }
+                    }
+                }
+            }
+            event::Event::StatusTextChanged(event) => {
+                if let Some(room_data) = self.room_data_map.get_mut(&event.room) {
+                    match event.text.as_ref() {
+                        Some(text) => {
+                            room_data
+                                .status_texts
+                                .insert(event.user_id.clone(), text.clone());
+                        }
+                        None => {
+                            room_data.status_texts.remove(&event.user_id);
+                        }
+                    }
+                }
+            }
+            event::Event::UserInfo(event) => {
+                self.push_notification_to_active_room(Notice::UserInfo(event.clone()));
+            }
+            event::Event::UserSearchResults(event) => {
+                self.push_notification_to_active_room(Notice::UserSearchResults(event.clone()));
+            }
+            event::Event::RoomInfo(event) => {
+                if let Some(room_data) = self.room_data_map.get_mut(&event.room) {
+                    room_data
+                        .messages
+                        .push(MessageBoxItem::Notification(Notice::RoomInfo(
+                            event.clone(),
+                        )));
+                }
+            }
+            event::Event::RoomsChecked(event) => {
+                self.recent_rooms
+                    .retain(|recent_room| !event.missing.contains(recent_room));
+            }
+            event::Event::RoomStats(event) => {
+                self.push_notification_to_active_room(Notice::RoomStats(event.clone()));
+            }
+            event::Event::ReportFiled(event) => {
+                if let Some(room_data) = self.room_data_map.get_mut(&event.room) {
+                    room_data
+                        .messages
+                        .push(MessageBoxItem::Notification(Notice::ReportFiled {
+                            room: event.room.clone(),
+                        }));
+                }
+            }
+            event::Event::MessageReported(event) => {
+                // shown in the room the message was sent to, so the moderator sees it in context
+                if let Some(room_data) = self.room_data_map.get_mut(&event.report.room) {
+                    room_data
+                        .messages
+                        .push(MessageBoxItem::Notification(Notice::MessageReported(
+                            event.report.clone(),
+                        )));
+
+                    if self.active_room.as_ref() != Some(&event.report.room) || !self.is_focused {
+                        room_data.has_unread = true;
+                    }
+                }
+            }
+            event::Event::JoinRequestPending(event) => {
+                let notice = if event.user_id == self.user_id {
+                    Notice::JoinRequestAwaitingApproval
+                } else {
+                    Notice::JoinRequested {
+                        user_id: event.user_id.clone(),
+                    }
+                };
+                self.push_notification_to_room(&event.room, notice);
+            }
+            event::Event::JoinRequestDecided(event) => {
+                let notice = if event.user_id == self.user_id {
+                    Notice::OwnJoinRequestDecided {
+                        approved: event.approved,
+                        decided_by: event.decided_by.clone(),
+                    }
+                } else {
+                    Notice::JoinRequestDecided {
+                        user_id: event.user_id.clone(),
+                        approved: event.approved,
+                        decided_by: event.decided_by.clone(),
+                    }
+                };
+                self.push_notification_to_room(&event.room, notice);
+            }
+            event::Event::NameRejected(event) => {
+                self.push_notification_to_active_room(Notice::NameRejected {
+                    name: event.name.clone(),
+                    reason: event.reason.clone(),
+                });
+            }
+            event::Event::RoomResynced(event) => {
+                self.push_notification_to_room(
+                    &event.room,
+                    Notice::RoomResynced {
+                        missed_events: event.missed_events,
+                    },
+                );
+            }
+            // only asked for by admin tooling
+            event::Event::BroadcastMetrics(_)
+            | event::Event::UserPurged(_)
+            | event::Event::CommandHistory(_) => {}
+            // only sent to bots, people log in with the tokens of their identity provider
+            event::Event::TokenRefreshed(_) => {}
+            event::Event::MessagesRedacted(event) => {
+                if let Some(room_data) = self.room_data_map.get_mut(&event.room) {
+                    // the content is dropped, the placeholder keeps the conversation around it readable
+                    for item in room_data.messages.iter_mut() {
+                        let is_removed = match item {
+                            MessageBoxItem::Message { id, .. } => event.message_ids.contains(id),
+                            _ => false,
+                        };
+                        if is_removed {
+                            *item = MessageBoxItem::Notification(Notice::MessageRemoved);
+                        }
+                    }
+                }
+            }
+            event::Event::ReportList(event) => {
+                self.push_notification_to_active_room(Notice::OpenReports {
+                    reports: event.reports.clone(),
+                });
+            }
+            event::Event::ReportResolved(event) => {
+                self.push_notification_to_active_room(Notice::ReportResolved {
+                    report_id: event.report_id.clone(),
+                    resolved: event.resolved,
+                });
+            }
+            // the keys of encrypted rooms are handled by the state store, which holds them
+            event::Event::RoomKeyRequested(_) | event::Event::RoomKeyShared(_) => {}
+            event::Event::SessionTerminated(event) => {
+                // the server closes the connection right after, the reason is shown on the connect page
+                self.server_connection_status = ServerConnectionStatus::Errored {
+                    err: ConnectionError::SessionClosed {
+                        reason: event.reason.clone(),
+                    },
+                };
+            }
+            event::Event::SessionReplaced(event) => {
+                self.server_connection_status = ServerConnectionStatus::Errored {
+                    err: ConnectionError::SessionReplaced {
+                        address: event.session.address.clone(),
+                    },
+                };
+            }
+            // followed by the state store, which holds the connection, see [State::mark_redirected]
+            event::Event::Redirect(_) => {}
+            event::Event::BotTokenIssued(event) => {
+                self.push_notification_to_active_room(Notice::BotTokenIssued {
+                    bot_name: event.bot_name.clone(),
+                    user_id: event.user_id.clone(),
+                    token: event.token.clone(),
+                });
+            }
+            event::Event::BotTokenRevoked(event) => {
+                self.push_notification_to_active_room(Notice::BotTokenRevoked {
+                    bot_name: event.bot_name.clone(),
+                    revoked: event.revoked,
+                });
+            }
+            event::Event::RoomArchived(event) => {
+                // the received messages are kept, archived rooms are only moved to their own section
+                if let Some(room_data) = self.room_data_map.get_mut(&event.room) {
+                    room_data.archived = true;
+                }
+                self.push_notification_to_room(&event.room, Notice::RoomArchived);
+            }
+            event::Event::RoomReopened(event) => {
+                if let Some(room_data) = self.room_data_map.get_mut(&event.room) {
+                    room_data.archived = false;
+                }
+                self.push_notification_to_room(&event.room, Notice::RoomReopened);
+            }
+            event::Event::RoomMetadataUpdated(event) => {
+                // the capacity is only of interest to those who join, it is asked for with the room info
+                if let Some(room_data) = self
+                    .room_data_map
+                    .get_mut(&event.room)
+                    .filter(|room_data| room_data.description != event.description)
+                {
+                    room_data.description = event.description.clone();
+                    self.push_notification_to_room(
+                        &event.room,
+                        Notice::RoomDescriptionChanged {
+                            description: event.description.clone(),
+                        },
+                    );
+                }
+            }
+            event::Event::RoomArchivalScheduled(event) => {
+                self.push_notification_to_room(
+                    &event.room,
+                    Notice::RoomArchivalScheduled {
+                        remaining_ms: event.archive_at.saturating_sub(now_millis()),
+                    },
+                );
+            }
+            event::Event::LinkPreview(event) => {
+                let Some(room_data) = self.room_data_map.get_mut(&event.room) else {
+                    return;
+                };
 
+                // the message may have scrolled out of the buffer already
+                if let Some(link_preview) =
+                    room_data.messages.iter_mut().find_map(|item| match item {
+                        MessageBoxItem::Message {
+                            id, link_preview, ..
+                        } if *id == event.message_id => Some(link_preview),
+                        _ => None,
+                    })
+                {
+                    *link_preview = Some(LinkPreview {
+                        url: event.url.clone(),
+                        title: event.title.clone(),
+                        description: event.description.clone(),
+                    });
+                }
+            }
+            event::Event::InviteCodeCreated(event) => {
+                self.push_notification_to_room(
+                    &event.room,
+                    Notice::InviteCodeCreated {
+                        code: event.code.clone(),
+                        remaining_ms: event.expires_at.saturating_sub(now_millis()),
+                        max_uses: event.max_uses,
+                    },
+                );
+            }
+            event::Event::ReminderSet(event) => {
+                if let Some(room_data) = self.room_data_map.get_mut(&event.room) {
+                    room_data
+                        .messages
+                        .push(MessageBoxItem::Notification(Notice::ReminderSet {
+                            remaining_ms: event.due_at.saturating_sub(now_millis()),
+                        }));
+                }
+            }
+            event::Event::ReminderDue(event) => {
+                if let Some(room_data) = self.room_data_map.get_mut(&event.room) {
+                    room_data
+                        .messages
+                        .push(MessageBoxItem::Notification(Notice::ReminderDue {
+                            content: event.content.clone(),
+                        }));
+
+                    if self.active_room.as_ref() != Some(&event.room) || !self.is_focused {
+                        room_data.has_unread = true;
+                    }
+                }
+            }
+        }
+
+        self.enforce_memory_budget();
+    }
+
+    /// Drops the messages of the rooms viewed least recently until the messages of all rooms fit the memory budget
+    ///
+    /// The messages of the active room are kept, as well as the ones of rooms whose history is still streaming in.
+    /// A room whose messages have been dropped fetches its history again once it is opened.
+    fn enforce_memory_budget(&mut self) {
+        let mut used_bytes = self
+            .room_data_map
+            .values()
+            .map(RoomData::estimated_size)
+            .sum::<usize>();
+        if !self.memory_budget.is_exceeded_by(used_bytes) {
+            return;
+        }
+
+        let mut rooms = self
+            .room_data_map
+            .iter()
+            .filter(|(room, room_data)| {
+                self.active_room.as_ref() != Some(*room)
+                    && room_data.history_progress.is_none()
+                    && room_data.messages.len() > 0
+            })
+            .map(|(room, room_data)| (room_data.last_viewed_at, room.clone()))
+            .collect::<Vec<_>>();
+        rooms.sort();
+
+        for (_, room) in rooms {
+            if !self.memory_budget.is_exceeded_by(used_bytes) {
+                break;
+            }
+            if let Some(room_data) = self.room_data_map.get_mut(&room) {
+                used_bytes -= room_data.estimated_size();
+                room_data.drop_messages();
             }
         }
     }
 
+    /// Show a notification in the room it is about, marking the room as unread if the user is not looking at it
+    /// Catches up with the rooms the server lists while logged in, e.g. rooms created or made private meanwhile
+    ///
+    /// The rooms still listed keep their data, e.g. the received messages. The rooms no longer listed are
+    /// kept as defunct if they have messages to read, and forgotten otherwise.
+    fn reconcile_rooms(&mut self, rooms: &[event::RoomDetail]) {
+        let mut room_data_map = std::mem::take(&mut self.room_data_map);
+        let mut restored_rooms = Vec::new();
+        self.room_data_map = rooms
+            .iter()
+            .map(|r| {
+                let mut room_data = room_data_map.remove(&r.name).unwrap_or_else(|| {
+                    RoomData::new(r.name.clone(), r.description.clone(), r.encrypted)
+                });
+                // a placeholder learns about the room here, see [State::add_placeholder_if_unknown]
+                room_data.description = r.description.clone();
+                room_data.encrypted = r.encrypted;
+                room_data.archived = r.archived;
+                room_data.category = r.category.clone();
+                if room_data.defunct {
+                    room_data.defunct = false;
+                    restored_rooms.push(r.name.clone());
+                }
+
+                (r.name.clone(), room_data)
+            })
+            .collect();
+
+        for (name, mut room_data) in room_data_map {
+            if room_data.messages.len() == 0 {
+                continue;
+            }
+            let is_removed_now = !room_data.defunct;
+            room_data.defunct = true;
+            room_data.has_joined = false;
+            room_data.users.clear();
+            room_data.away_users.clear();
+            room_data.status_texts.clear();
+            self.room_data_map.insert(name.clone(), room_data);
+            if is_removed_now {
+                self.push_notification_to_room(&name, Notice::RoomRemoved);
+            }
+        }
+        for room in restored_rooms {
+            self.push_notification_to_room(&room, Notice::RoomReopened);
+        }
+
+        if let Some(active_room) = self.active_room.as_ref() {
+            if !self.room_data_map.contains_key(active_room) {
+                self.active_room = None;
+            }
+        }
+    }
+
+    /// Adds a placeholder for a room the client does not know of, e.g. one created since the login
+    /// The placeholder is filled in once the rooms have been listed again, see [State::has_unknown_rooms]
+    fn add_placeholder_if_unknown(&mut self, room: &str) {
+        if !self.room_data_map.contains_key(room) {
+            self.room_data_map.insert(
+                String::from(room),
+                RoomData::new(String::from(room), String::new(), false),
+            );
+            self.has_unknown_rooms = true;
+        }
+    }
+
+    pub(super) fn push_notification_to_room(&mut self, room: &str, notice: Notice) {
+        if let Some(room_data) = self.room_data_map.get_mut(room) {
+            room_data
+                .messages
+                .push(MessageBoxItem::Notification(notice));
+
+            if self.active_room.as_deref() != Some(room) || !self.is_focused {
+                room_data.has_unread = true;
+            }
+        }
+    }
+
+    /// Show a notification which is not bound to a room where the user currently is
+    pub(super) fn push_notification_to_active_room(&mut self, notice: Notice) {
+        if let Some(room_data) = self
+            .active_room
+            .as_ref()
+            .and_then(|active_room| self.room_data_map.get_mut(active_room))
+        {
+            room_data
+                .messages
+                .push(MessageBoxItem::Notification(notice));
+        }
+    }
+
+    /// Applies the settings saved to the config file, taking precedence over the environment variables
+    pub fn apply_config(&mut self, config: &ClientConfig) {
+        if let Some(language) = config.language {
+            self.locale = language;
+        }
+        if let Some(accessibility) = config.accessibility {
+            self.accessibility = accessibility;
+        }
+        if let Some(keymap) = config.keymap {
+            self.keymap = keymap;
+        }
+        if let Some(auto_away) = config.auto_away {
+            self.auto_away = auto_away;
+        }
+        if let Some(grouping_window_secs) = config.grouping_window_secs {
+            self.grouping_window_secs = grouping_window_secs;
+        }
+        if let Some(time_format) = config.time_format.as_ref() {
+            self.time_format = time_format.clone();
+        }
+        if let Some(watch_words) = config.watch_words.as_ref() {
+            self.highlights = Highlights::parse(&watch_words.join(","));
+        }
+        if let Some(masked_words) = config.masked_words.as_ref() {
+            self.profanity_filter = ProfanityFilter::parse(&masked_words.join(","));
+        }
+        if let Some(hidden_notices) = config.hidden_notices.as_ref() {
+            self.notice_filter.set_hidden_kinds(hidden_notices.clone());
+        }
+        if let Some(room_participation) = config.room_participation.as_ref() {
+            self.notice_filter
+                .set_room_participation(room_participation.clone());
+        }
+        if let Some(server_address) = config.server_address.as_ref() {
+            self.server_address = server_address.clone();
+        }
+        if let Some(username) = config.username.as_ref() {
+            self.username = Some(username.clone());
+        }
+        if let Some(auto_join_rooms) = config.auto_join_rooms.as_ref() {
+            self.auto_join_rooms = auto_join_rooms.clone();
+        }
+        if let Some(recent_rooms) = config.recent_rooms.as_ref() {
+            self.recent_rooms = recent_rooms.clone();
+        }
+    }
+
+    /// The settings currently in effect, to be changed and saved to the config file
+    pub fn config(&self) -> ClientConfig {
+        ClientConfig {
+            language: Some(self.locale),
+            accessibility: Some(self.accessibility),
+            keymap: Some(self.keymap),
+            auto_away: Some(self.auto_away),
+            grouping_window_secs: Some(self.grouping_window_secs),
+            time_format: Some(self.time_format.clone()),
+            watch_words: Some(self.highlights.watch_words().to_vec()),
+            masked_words: Some(self.profanity_filter.masked_words().to_vec()),
+            hidden_notices: Some(self.notice_filter.hidden_kinds().to_vec()),
+            room_participation: Some(self.notice_filter.room_participation().clone()),
+            server_address: Some(self.server_address.clone()),
+            username: self.username.clone(),
+            auto_join_rooms: Some(self.auto_join_rooms.clone()),
+            recent_rooms: Some(self.recent_rooms.clone()),
+        }
+    }
+
     pub fn mark_connection_request_start(&mut self) {
         self.server_connection_status = ServerConnectionStatus::Connecting;
     }
 
+    /// Goes back to entering the address to connect to, nothing has gone wrong
+    pub fn mark_connection_cancelled(&mut self) {
+        self.server_connection_status = ServerConnectionStatus::Uninitialized;
+    }
+
+    /// Shows the user where to log in while the connection waits for the identity provider
+    pub fn mark_authorization_pending(&mut self, verification_uri: String, user_code: String) {
+        self.server_connection_status = ServerConnectionStatus::Authorizing {
+            verification_uri,
+            user_code,
+        };
+    }
+
     /// Processes the result of a connection request to change the state of the application
-    pub fn process_connection_request_result(&mut self, result: anyhow::Result<String>) {
+    /// A connected user still has to log in
+    pub fn process_connection_request_result(
+        &mut self,
+        result: anyhow::Result<(String, SocketAddr)>,
+    ) {
         self.server_connection_status = match result {
-            Ok(addr) => ServerConnectionStatus::Connected { addr: addr.clone() },
+            Ok((addr, endpoint)) => ServerConnectionStatus::LoggingIn {
+                addr,
+                endpoint,
+                is_pending: false,
+                rejection: None,
+            },
             Err(err) => ServerConnectionStatus::Errored {
-                err: err.to_string(),
+                err: ConnectionError::Failed(err.to_string()),
             },
         }
     }
 
+    /// Waits for the server to answer the login which has just been sent
+    pub fn mark_login_pending(&mut self) {
+        if let ServerConnectionStatus::LoggingIn { is_pending, .. } =
+            &mut self.server_connection_status
+        {
+            *is_pending = true;
+        }
+    }
+
+    /// Follows the session to the server it has been handed to, the rooms stay on screen with their messages
+    /// The rooms the user was in are joined again once logged in there, see [State::rooms_to_resume]
+    /// Returns whether to log in again, i.e. the user had logged in or was logging in
+    pub fn mark_redirected(&mut self, addr: String, endpoint: SocketAddr) -> bool {
+        self.push_notification_to_active_room(Notice::Redirected { addr: addr.clone() });
+
+        match self.server_connection_status {
+            ServerConnectionStatus::Connected { .. } => {
+                self.rooms_to_resume = Some(
+                    self.room_data_map
+                        .values()
+                        .filter(|room_data| room_data.has_joined)
+                        .map(|room_data| room_data.name.clone())
+                        .collect(),
+                );
+                self.server_connection_status =
+                    ServerConnectionStatus::Connected { addr, endpoint };
+
+                true
+            }
+            ServerConnectionStatus::LoggingIn { is_pending, .. } => {
+                self.server_connection_status = ServerConnectionStatus::LoggingIn {
+                    addr,
+                    endpoint,
+                    is_pending: false,
+                    rejection: None,
+                };
+
+                is_pending
+            }
+            _ => false,
+        }
+    }
+
+    /// Shows the chat page for a recording, there is no server to connect to
+    pub fn mark_replaying(&mut self, path: String) {
+        self.server_connection_status = ServerConnectionStatus::Replaying { path };
+    }
+
     /// Tries to set the active room as the given room. Returns the [RoomData] associated to the room.
     pub fn try_set_active_room(&mut self, room: &str) -> Option<&RoomData> {
+        let now = now_millis();
+        // the room left is the one viewed last, until the user opens yet another room
+        if let Some(active_room_data) = self
+            .active_room
+            .as_ref()
+            .and_then(|active_room| self.room_data_map.get_mut(active_room))
+        {
+            active_room_data.last_viewed_at = now;
+        }
+
         let room_data = self.room_data_map.get_mut(room)?;
         room_data.has_unread = false;
+        room_data.last_viewed_at = now;
 
         self.active_room = Some(String::from(room));
 
         Some(room_data)
     }
 
+    /// Records whether the terminal has the focus of the user
+    /// Returns the active room if it has been read by regaining the focus
+    pub fn set_focus(&mut self, is_focused: bool) -> Option<String> {
+        let has_regained_focus = is_focused && !self.is_focused;
+        self.is_focused = is_focused;
+
+        if !has_regained_focus {
+            return None;
+        }
+
+        let active_room = self.active_room.clone()?;
+        let room_data = self.room_data_map.get_mut(&active_room)?;
+        if !room_data.has_unread {
+            return None;
+        }
+        room_data.has_unread = false;
+
+        Some(active_room)
+    }
+
+    /// Records that the key of an encrypted room is known, so its messages can be read and sent
+    pub fn mark_room_key_available(&mut self, room: &str) {
+        if let Some(room_data) = self.room_data_map.get_mut(room) {
+            room_data.has_room_key = true;
+        }
+    }
+
+    /// Keeps a request for the key of a room until the user shares it, and asks the user to
+    /// A session asking again replaces its earlier request
+    pub fn push_room_key_request(&mut self, request: event::RoomKeyRequestedBroadcastEvent) {
+        self.room_key_requests
+            .retain(|known| known.room != request.room || known.session_id != request.session_id);
+        self.push_notification_to_room(
+            &request.room,
+            Notice::RoomKeyRequested {
+                user_id: request.user_id.clone(),
+                fingerprint: e2e::fingerprint(&request.public_key),
+            },
+        );
+        self.room_key_requests.push(request);
+    }
+
+    /// Takes the request of the session with the given fingerprint for the key of the room
+    /// The fingerprint may be typed without the spaces between its groups
+    pub fn take_room_key_request(
+        &mut self,
+        room: &str,
+        fingerprint: &str,
+    ) -> Option<event::RoomKeyRequestedBroadcastEvent> {
+        let without_spaces = |fingerprint: &str| fingerprint.replace(' ', "").to_lowercase();
+        let position = self.room_key_requests.iter().position(|request| {
+            request.room == room
+                && without_spaces(&e2e::fingerprint(&request.public_key))
+                    == without_spaces(fingerprint)
+        })?;
+
+        Some(self.room_key_requests.remove(position))
+    }
+
+    /// Returns true if the room is encrypted but its key is not known yet
+    pub fn is_waiting_for_room_key(&self, room: &str) -> bool {
+        self.room_data_map
+            .get(room)
+            .is_some_and(|room_data| room_data.encrypted && !room_data.has_room_key)
+    }
+
+    /// Shows the message the user is sending to the room right away, until the server broadcasts it with the nonce
+    /// Messages starting with a slash are left out, the server may handle them as commands instead of sending them.
+    pub fn push_pending_message(
+        &mut self,
+        room: &str,
+        nonce: String,
+        content: String,
+        content_type: event::ContentType,
+    ) {
+        if content_type.is_plain() && content.starts_with('/') {
+            return;
+        }
+        let Some(room_data) = self.room_data_map.get_mut(room) else {
+            return;
+        };
+
+        room_data.messages.push(MessageBoxItem::Message {
+            id: String::new(),
+            user_id: self.user_id.clone(),
+            display_name: None,
+            content,
+            content_type,
+            timestamp: now_millis(),
+            is_highlighted: false,
+            is_revealed: false,
+            link_preview: None,
+            image: None,
+            is_own: true,
+            pending_nonce: Some(nonce),
+        });
+    }
+
+    /// The id of the latest message the user has sent to the room, e.g. for reporting it
+    /// Messages the server has not broadcast yet have no id, and are skipped
+    pub fn last_message_id_of(&self, room: &str, user_id: &str) -> Option<String> {
+        // the buffer is iterated from the newest to the oldest message
+        self.room_data_map
+            .get(room)?
+            .messages
+            .iter()
+            .find_map(|item| match item {
+                MessageBoxItem::Message {
+                    id,
+                    user_id: author,
+                    pending_nonce: None,
+                    ..
+                } if author == user_id => Some(id.clone()),
+                _ => None,
+            })
+    }
+
+    /// Reveals the `nth` newest message of the active room which has masked words, or masks it again
+    /// The user is told if the room has fewer such messages
+    pub fn toggle_revealed(&mut self, nth: usize) {
+        let Some(room_data) = self
+            .active_room
+            .as_ref()
+            .and_then(|active_room| self.room_data_map.get_mut(active_room))
+        else {
+            return;
+        };
+
+        // the buffer is iterated from the newest to the oldest message
+        let profanity_filter = &self.profanity_filter;
+        let masked_message = room_data
+            .messages
+            .iter_mut()
+            .filter_map(|item| match item {
+                MessageBoxItem::Message {
+                    content,
+                    is_revealed,
+                    ..
+                } if profanity_filter.mask(content).is_some() => Some(is_revealed),
+                _ => None,
+            })
+            .nth(nth.saturating_sub(1));
+
+        match masked_message {
+            Some(is_revealed) => *is_revealed = !*is_revealed,
+            None => self.push_notification_to_active_room(Notice::NothingToReveal),
+        }
+    }
+
+    /// Hides the users joining and leaving the active room if they are shown, or shows them
+    pub fn toggle_participation(&mut self) {
+        let Some(active_room) = self.active_room.clone() else {
+            return;
+        };
+
+        let is_shown = self.notice_filter.toggle_participation(&active_room);
+        self.push_notification_to_active_room(Notice::ParticipationToggled { is_shown });
+    }
+
+    /// Shows the image below the message linking it, unless the message has scrolled out of the buffer already
+    pub fn attach_image(&mut self, rendered_image: RenderedImage) {
+        let Some(room_data) = self.room_data_map.get_mut(&rendered_image.room) else {
+            return;
+        };
+
+        if let Some(image) = room_data.messages.iter_mut().find_map(|item| match item {
+            MessageBoxItem::Message { id, image, .. } if *id == rendered_image.message_id => {
+                Some(image)
+            }
+            _ => None,
+        }) {
+            *image = Some(rendered_image.art);
+        }
+
+        self.enforce_memory_budget();
+    }
+
+    /// Records that the user has left the room, returns false if they had not joined it
+    ///
+    /// The server stops sending the events of the room, so whatever is known about its users goes stale.
+    pub fn mark_room_left(&mut self, room: &str) -> bool {
+        let Some(room_data) = self.room_data_map.get_mut(room) else {
+            return false;
+        };
+        if !room_data.has_joined {
+            return false;
+        }
+
+        room_data.has_joined = false;
+        room_data.users.clear();
+        room_data.away_users.clear();
+        room_data.status_texts.clear();
+        room_data
+            .messages
+            .push(MessageBoxItem::Notification(Notice::RoomLeft));
+
+        self.recent_rooms.retain(|recent_room| recent_room != room);
+        self.recent_rooms.insert(0, String::from(room));
+        self.recent_rooms.truncate(MAX_RECENT_ROOMS);
+
+        true
+    }
+
     /// Check if it's the first time entering the room
     pub fn is_room_first_time(&mut self, room: &str) -> Option<bool> {
         let room_data = self.room_data_map.get_mut(room)?;
@@ -208,4 +1827,169 @@ impl State {
     pub fn tick_timer(&mut self) {
         self.timer += 1;
     }
+
+    /// Turn the traffic inspector on with no frames recorded yet, or off, returns whether it is on
+    pub fn toggle_traffic_inspector(&mut self) -> bool {
+        self.traffic_log = match self.traffic_log {
+            Some(_) => None,
+            None => Some(TrafficLog::default()),
+        };
+
+        self.traffic_log.is_some()
+    }
+
+    /// Record the frame if the traffic inspector is on
+    pub fn record_traffic(&mut self, frame: TrafficFrame) {
+        if let Some(traffic_log) = self.traffic_log.as_mut() {
+            traffic_log.push(frame);
+        }
+    }
+
+    /// Write the recorded frames to a file, the traffic inspector tells where or why they could not be
+    pub fn export_traffic(&mut self) {
+        if let Some(traffic_log) = self.traffic_log.as_mut() {
+            traffic_log.export_result = Some(
+                traffic_log
+                    .export(now_millis())
+                    .map(|path| path.display().to_string())
+                    .map_err(|err| format!("{err:#}")),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROOM: &str = "announcements";
+
+    fn message_to(room: &str) -> event::Event {
+        event::Event::UserMessage(event::UserMessageBroadcastEvent {
+            room: String::from(room),
+            id: String::from("m1"),
+            user_id: String::from("bob"),
+            display_name: None,
+            content: String::from("hello"),
+            content_type: event::ContentType::Plain,
+            timestamp: 1,
+            nonce: None,
+        })
+    }
+
+    #[test]
+    fn test_pending_actions_are_shown_with_their_content_type() {
+        let mut state = State::default();
+        state.handle_server_event(&message_to(ROOM));
+
+        state.push_pending_message(
+            ROOM,
+            String::from("n1"),
+            String::from("waves"),
+            event::ContentType::Action,
+        );
+
+        assert!(matches!(
+            state.room_data_map[ROOM].messages.iter().next(),
+            Some(MessageBoxItem::Message {
+                content_type: event::ContentType::Action,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_session_count_follows_the_other_devices() {
+        let mut state = State::default();
+        let session = event::SessionDetail {
+            session_id: String::from("s2"),
+            address: String::from("127.0.0.1:4000"),
+            connected_at: 1,
+        };
+
+        state.handle_server_event(&event::Event::DeviceConnected(
+            event::DeviceConnectedReplyEvent {
+                session: session.clone(),
+                session_count: 2,
+            },
+        ));
+        assert_eq!(state.session_count, 2);
+
+        state.handle_server_event(&event::Event::DeviceDisconnected(
+            event::DeviceDisconnectedReplyEvent {
+                session_id: session.session_id.clone(),
+                session_count: 1,
+            },
+        ));
+        assert_eq!(state.session_count, 1);
+
+        // listing the sessions takes a fresh snapshot, e.g. after having missed a device event
+        state.handle_server_event(&event::Event::SessionList(event::SessionListReplyEvent {
+            sessions: vec![session.clone(), session],
+        }));
+        assert_eq!(state.session_count, 2);
+    }
+
+    #[test]
+    fn test_message_to_an_unknown_room_adds_a_placeholder() {
+        let mut state = State::default();
+
+        state.handle_server_event(&message_to(ROOM));
+
+        let room_data = &state.room_data_map[ROOM];
+        assert_eq!(room_data.name, ROOM);
+        assert_eq!(room_data.messages.len(), 1);
+        assert!(state.has_unknown_rooms);
+    }
+
+    #[test]
+    fn test_users_of_an_unknown_room_are_kept_in_a_placeholder() {
+        let mut state = State::default();
+
+        state.handle_server_event(&event::Event::UserJoinedRoom(
+            event::UserJoinedRoomReplyEvent {
+                room: String::from(ROOM),
+                users: vec![String::from("alice"), String::from("bob")],
+                status_texts: Default::default(),
+                creates_room_key: false,
+            },
+        ));
+
+        assert_eq!(state.room_data_map[ROOM].users.len(), 2);
+        assert!(state.has_unknown_rooms);
+    }
+
+    #[test]
+    fn test_known_rooms_are_not_looked_up() {
+        let mut state = State::default();
+        state.room_data_map.insert(
+            String::from(ROOM),
+            RoomData::new(String::from(ROOM), String::new(), false),
+        );
+
+        state.handle_server_event(&message_to(ROOM));
+
+        assert!(!state.has_unknown_rooms);
+    }
+
+    #[test]
+    fn test_listing_the_rooms_fills_the_placeholder_in() {
+        let mut state = State::default();
+        state.handle_server_event(&message_to(ROOM));
+
+        state.handle_server_event(&event::Event::RoomList(event::RoomListReplyEvent {
+            rooms: vec![event::RoomDetail {
+                name: String::from(ROOM),
+                description: String::from("News of the company"),
+                encrypted: false,
+                archived: false,
+                category: None,
+            }],
+        }));
+
+        let room_data = &state.room_data_map[ROOM];
+        assert_eq!(room_data.description, "News of the company");
+        assert!(!room_data.defunct);
+        assert_eq!(room_data.messages.len(), 1);
+    }
 }
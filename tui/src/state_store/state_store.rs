@@ -1,115 +1,1008 @@
-use std::time::Duration;
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use anyhow::Context;
 use comms::{
-    command,
-    transport::{
-        self,
-        client::{CommandWriter, EventStream},
-    },
+    command, event,
+    transport::client::{EventStream, FrameTap},
 };
+use rand_core::{OsRng, RngCore};
 use tokio::{
-    net::TcpStream,
     sync::{
         broadcast,
-        mpsc::{self, UnboundedReceiver, UnboundedSender},
+        mpsc::{self, UnboundedReceiver},
+        watch,
     },
+    time::MissedTickBehavior,
 };
 use tokio_stream::StreamExt;
 
-use crate::{Interrupted, Terminator};
+use crate::{
+    config::ClientConfig,
+    e2e::E2eKeys,
+    inline_images::ImageRenderer,
+    notify_hook::NotifyHook,
+    oidc::{self, OidcConfig},
+    replay::{Recorder, Replay},
+    scripting::{ScriptOutput, Scripts},
+    status_bar::{self, SegmentKind},
+    Interrupted, Terminator,
+};
 
-use super::{action::Action, State};
+use super::{
+    action::Action,
+    connector::{Connector, TcpConnector},
+    queued_writer::QueuedCommandWriter,
+    state::now_millis,
+    Notice, ServerConnectionStatus, State, TrafficFrame,
+};
 
-pub struct StateStore {
-    state_tx: UnboundedSender<State>,
+pub struct StateStore<C = TcpConnector> {
+    /// Holds the latest state only, the user interface skips the states it had no time to render
+    state_tx: watch::Sender<State>,
+    /// The identity provider to log in with, the client connects without a token if not set
+    oidc_config: Option<OidcConfig>,
+    /// The settings saved to the config file when the client was started, None on the first run
+    config: Option<ClientConfig>,
+    /// The command to run when the user is mentioned, nothing is run if not set
+    notify_hook: Option<NotifyHook>,
+    /// What fetches the images linked in messages, they are left as links if not set
+    image_renderer: Option<ImageRenderer>,
+    /// The Lua scripts of the user, nothing is automated if they have none
+    scripts: Option<Scripts>,
+    /// Where the events received from the server are recorded to, nothing is recorded if not set
+    recorder: Option<Recorder>,
+    /// The recording to play back instead of connecting to a server
+    replay: Option<Replay>,
+    /// How long connecting to the server may take before it is given up on
+    connect_timeout: Duration,
+    /// What the connections to the server are opened with
+    connector: C,
 }
 
 impl StateStore {
-    pub fn new() -> (Self, UnboundedReceiver<State>) {
-        let (state_tx, state_rx) = mpsc::unbounded_channel::<State>();
+    /// Fails if the replay file to record to or to play back can not be opened, the config file can not be read,
+    /// or a script of the user fails to load
+    pub fn new() -> anyhow::Result<(Self, watch::Receiver<State>)> {
+        // the user interface waits for the first state sent by the main loop, the default is never shown
+        let (state_tx, state_rx) = watch::channel(State::default());
 
-        (StateStore { state_tx }, state_rx)
+        Ok((
+            StateStore {
+                state_tx,
+                oidc_config: OidcConfig::from_env(),
+                config: ClientConfig::load()?,
+                notify_hook: NotifyHook::from_env(),
+                image_renderer: ImageRenderer::from_env(),
+                scripts: Scripts::load()?,
+                recorder: Recorder::from_env()?,
+                replay: Replay::from_env()?,
+                connect_timeout: connect_timeout_from_env(),
+                connector: TcpConnector,
+            },
+            state_rx,
+        ))
     }
 }
 
-type ServerHandle = (EventStream, CommandWriter);
+type ServerHandle = (EventStream, QueuedCommandWriter);
+
+/// Applies the changed settings right away and saves them to the config file
+/// The settings stay in effect if they can not be saved, the state tells why.
+fn update_settings(state: &mut State, config: ClientConfig) {
+    state.apply_config(&config);
+    state.config_error = config.save().err().map(|err| format!("{err:#}"));
+    state.is_first_run = false;
+}
 
-async fn create_server_handle(addr: &str) -> anyhow::Result<ServerHandle> {
-    let stream = TcpStream::connect(addr).await?;
-    let (event_stream, command_writer) = transport::client::split_tcp_stream(stream);
+/// The state once the connection to the server is gone, only the settings and the traffic log are kept
+/// The reason of a refused login is kept as well, to show it on the connect page
+fn disconnected_state(state: State) -> State {
+    let server_connection_status = match state.server_connection_status.clone() {
+        ServerConnectionStatus::LoggingIn {
+            rejection: Some(err),
+            ..
+        } => ServerConnectionStatus::Errored { err },
+        server_connection_status => server_connection_status,
+    };
 
-    Ok((event_stream, command_writer))
+    let mut disconnected_state = State {
+        is_focused: state.is_focused,
+        // the frames leading up to the disconnection are the interesting ones
+        traffic_log: state.traffic_log.clone(),
+        ..State::default()
+    };
+    disconnected_state.apply_config(&state.config());
+    if let ServerConnectionStatus::Errored { .. } = server_connection_status {
+        disconnected_state.server_connection_status = server_connection_status;
+    }
+
+    disconnected_state
 }
 
-impl StateStore {
+/// The state the client starts with, set up by the config file if there is one
+fn initial_state(config: Option<&ClientConfig>) -> State {
+    let mut state = State::default();
+    match config {
+        Some(config) => state.apply_config(config),
+        None => state.is_first_run = true,
+    }
+
+    state
+}
+
+/// Sends the message to the room, encrypted if the room is, the user is told if the key of the room is missing
+/// The message is shown right away, and replaced by the one the server broadcasts, see [State::push_pending_message]
+fn send_message(
+    command_writer: &mut QueuedCommandWriter,
+    state: &mut State,
+    e2e_keys: &E2eKeys,
+    room: String,
+    content: String,
+    content_type: event::ContentType,
+) -> anyhow::Result<()> {
+    let sent_content = if state
+        .room_data_map
+        .get(&room)
+        .is_some_and(|room_data| room_data.encrypted)
+    {
+        e2e_keys.encrypt(&room, &content)?
+    } else {
+        Some(content.clone())
+    };
+
+    let Some(sent_content) = sent_content else {
+        state.push_notification_to_active_room(Notice::RoomKeyMissing);
+        return Ok(());
+    };
+
+    let nonce = format!("{:016x}", OsRng.next_u64());
+    state.push_pending_message(&room, nonce.clone(), content, content_type);
+
+    command_writer
+        .write(&command::UserCommand::SendMessage(
+            command::SendMessageCommand {
+                room,
+                content: sent_content,
+                content_type,
+                nonce: Some(nonce),
+            },
+        ))
+        .context("could not send message")
+}
+
+/// Carries out what the scripts of the user have come to, see [Scripts]
+fn handle_script_outputs(
+    outputs: Vec<ScriptOutput>,
+    state: &mut State,
+    e2e_keys: &E2eKeys,
+    command_writer: &mut QueuedCommandWriter,
+) -> anyhow::Result<()> {
+    for output in outputs {
+        match output {
+            ScriptOutput::Send { room, content } => {
+                send_message(
+                    command_writer,
+                    state,
+                    e2e_keys,
+                    room,
+                    content,
+                    event::ContentType::Plain,
+                )?;
+            }
+            ScriptOutput::Notify { text } => {
+                state.push_notification_to_active_room(Notice::ScriptNotification { text });
+            }
+            ScriptOutput::Failed { error } => {
+                state.push_notification_to_active_room(Notice::ScriptFailed { error });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// How many history entries the server sends at once, the history of busy rooms arrives over several events
+const HISTORY_CHUNK_SIZE: usize = 100;
+
+/// Asks the server for the history of the room, streamed in chunks so its progress can be shown
+fn request_history(command_writer: &mut QueuedCommandWriter, room: String) -> anyhow::Result<()> {
+    command_writer
+        .write(&command::UserCommand::GetHistory(
+            command::GetHistoryCommand {
+                room,
+                chunk_size: Some(HISTORY_CHUNK_SIZE),
+            },
+        ))
+        .context("could not request history")
+}
+
+/// How long quitting waits for the server to take the last commands before giving up on it
+const FAREWELL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How often the latency to the server is measured while the status bar shows it
+const PING_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often the rooms are listed again while logged in, in case a change of the rooms has not been pushed
+const ROOM_LIST_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// How long connecting to the server may take, unless the user has picked another timeout
+const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+
+/// Reads how long connecting to the server may take in seconds from the `CHAT_TUI_CONNECT_TIMEOUT` environment variable
+fn connect_timeout_from_env() -> Duration {
+    let secs = std::env::var("CHAT_TUI_CONNECT_TIMEOUT")
+        .ok()
+        .and_then(|secs| secs.trim().parse().ok())
+        .unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECS);
+
+    Duration::from_secs(secs)
+}
+
+/// Connects to the server, returning the address of the host it has connected to as well
+/// Gives up once the timeout has passed, e.g. on an address nothing answers at
+async fn create_server_handle(
+    connector: &impl Connector,
+    addr: &str,
+    frame_tap: FrameTap,
+    timeout: Duration,
+) -> anyhow::Result<(ServerHandle, SocketAddr)> {
+    let connect = async {
+        let ((event_stream, command_writer), endpoint) = connector.connect(addr, frame_tap).await?;
+
+        anyhow::Ok((
+            (event_stream, QueuedCommandWriter::new(command_writer)),
+            endpoint,
+        ))
+    };
+
+    tokio::time::timeout(timeout, connect)
+        .await
+        .with_context(|| format!("{} did not answer within {:?}", addr, timeout))?
+}
+
+/// Logs in with the name the user has picked, or as a guest, the server answers with a login successful or rejected event
+fn log_in(
+    command_writer: &mut QueuedCommandWriter,
+    state: &mut State,
+    login: command::LoginCommand,
+) -> anyhow::Result<()> {
+    command_writer
+        .write(&command::UserCommand::Login(login))
+        .context("could not log in")?;
+    state.mark_login_pending();
+
+    Ok(())
+}
+
+/// Tells the server the user is quitting and closes the connection
+///
+/// The server ends the session right away instead of waiting for the connection to drop,
+/// and shutting down the writer writes the commands still queued before closing it.
+async fn say_farewell(mut command_writer: QueuedCommandWriter) -> anyhow::Result<()> {
+    command_writer
+        .write(&command::UserCommand::Quit(command::QuitCommand))
+        .context("could not quit the session")?;
+    command_writer
+        .shutdown()
+        .await
+        .context("could not close the connection")
+}
+
+/// Takes part in sharing the keys of encrypted rooms
+///
+/// A session joining an encrypted room either creates its key if the server tells it no session holds one,
+/// or asks the other members for it. Members holding the key are asked to compare the fingerprint of the
+/// asking session with the one it shows, and seal the key for it once they share it, see [share_room_key].
+/// The session then opens the key and tells the server it holds it as well.
+fn handle_room_keys(
+    event: &event::Event,
+    state: &mut State,
+    e2e_keys: &mut E2eKeys,
+    command_writer: &mut QueuedCommandWriter,
+) -> anyhow::Result<()> {
+    match event {
+        event::Event::LoginSuccessful(_) | event::Event::AccessibleRoomsChanged(_) => {
+            // the keys outlive the state of a connection, the new state has to learn about them again
+            let rooms = state.room_data_map.keys().cloned().collect::<Vec<_>>();
+            for room in rooms.iter().filter(|room| e2e_keys.has_room_key(room)) {
+                state.mark_room_key_available(room);
+            }
+        }
+        event::Event::UserJoinedRoom(event) if state.is_waiting_for_room_key(&event.room) => {
+            if event.creates_room_key {
+                e2e_keys.generate_room_key(&event.room);
+                state.mark_room_key_available(&event.room);
+            } else {
+                command_writer
+                    .write(&command::UserCommand::RequestRoomKey(
+                        command::RequestRoomKeyCommand {
+                            room: event.room.clone(),
+                            public_key: e2e_keys.public_key(),
+                        },
+                    ))
+                    .context("could not request the room key")?;
+                state.push_notification_to_room(
+                    &event.room,
+                    Notice::RoomKeyAwaited {
+                        fingerprint: e2e_keys.fingerprint(),
+                    },
+                );
+            }
+        }
+        // only the members holding the key are asked, the others could not share it anyway
+        event::Event::RoomKeyRequested(event)
+            if event.public_key != e2e_keys.public_key() && e2e_keys.has_room_key(&event.room) =>
+        {
+            state.push_room_key_request(event.clone());
+        }
+        event::Event::RoomKeyShared(event) if state.is_waiting_for_room_key(&event.room) => {
+            // a key which can not be opened is ignored, every other member holding the key shares it as well
+            let Ok(()) = e2e_keys.open_room_key(&event.room, &event.public_key, &event.sealed_key)
+            else {
+                return Ok(());
+            };
+            state.mark_room_key_available(&event.room);
+            command_writer
+                .write(&command::UserCommand::HoldRoomKey(
+                    command::HoldRoomKeyCommand {
+                        room: event.room.clone(),
+                    },
+                ))
+                .context("could not tell the server about the room key")?;
+
+            // the history could not be read without the key, hence it has not been fetched yet
+            if let Some(true) = state.is_room_first_time(&event.room) {
+                request_history(command_writer, event.room.clone())?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Seals the key of the room for the session which asked for it, once the user has compared its fingerprint
+fn share_room_key(
+    command_writer: &mut QueuedCommandWriter,
+    state: &mut State,
+    e2e_keys: &E2eKeys,
+    room: &str,
+    fingerprint: &str,
+) -> anyhow::Result<()> {
+    let Some(request) = state.take_room_key_request(room, fingerprint) else {
+        state.push_notification_to_active_room(Notice::NoRoomKeyRequest {
+            fingerprint: String::from(fingerprint),
+        });
+        return Ok(());
+    };
+
+    // a malformed public key is the problem of the requesting session, not of this one
+    let Ok(Some(sealed_key)) = e2e_keys.seal_room_key(room, &request.public_key) else {
+        return Ok(());
+    };
+    command_writer
+        .write(&command::UserCommand::ShareRoomKey(
+            command::ShareRoomKeyCommand {
+                room: request.room,
+                user_id: request.user_id.clone(),
+                session_id: request.session_id,
+                public_key: e2e_keys.public_key(),
+                sealed_key,
+            },
+        ))
+        .context("could not share the room key")?;
+    state.push_notification_to_active_room(Notice::RoomKeyShared {
+        user_id: request.user_id,
+    });
+
+    Ok(())
+}
+
+/// Joins the rooms the user has picked to join right after logging in, opening the first one
+/// After a redirect, the rooms the user was in are joined instead and the room they were looking at stays open
+fn handle_auto_join(
+    event: &event::Event,
+    state: &mut State,
+    command_writer: &mut QueuedCommandWriter,
+) -> anyhow::Result<()> {
+    let event::Event::LoginSuccessful(_) = event else {
+        return Ok(());
+    };
+
+    let rooms_to_resume = state.rooms_to_resume.take();
+    let is_resuming = rooms_to_resume.is_some();
+    // rooms which no longer exist, or are not accessible to the user, are skipped
+    let rooms = rooms_to_resume
+        .unwrap_or_else(|| state.auto_join_rooms.clone())
+        .into_iter()
+        .filter(|room| state.room_data_map.contains_key(room))
+        .collect::<Vec<_>>();
+    for room in rooms.iter() {
+        command_writer
+            .write(&command::UserCommand::JoinRoom(command::JoinRoomCommand {
+                room: room.clone(),
+                wait_if_full: true,
+            }))
+            .context("could not join room")?;
+
+        // the history of encrypted rooms is fetched once their key has arrived
+        // the rooms kept after a redirect already show their history
+        if state.is_room_first_time(room) == Some(true) && !state.is_waiting_for_room_key(room) {
+            request_history(command_writer, room.clone())?;
+        }
+    }
+
+    if let Some(room) = rooms.first().filter(|_| !is_resuming) {
+        state.try_set_active_room(room);
+    }
+
+    Ok(())
+}
+
+/// Asks the server whether the rooms the user has left lately still exist once logged in,
+/// and forgets the ones which do not once it has answered
+fn handle_recent_rooms(
+    event: &event::Event,
+    state: &mut State,
+    command_writer: &mut QueuedCommandWriter,
+) -> anyhow::Result<()> {
+    match event {
+        event::Event::LoginSuccessful(_) if !state.recent_rooms.is_empty() => {
+            command_writer
+                .write(&command::UserCommand::CheckRooms(
+                    command::CheckRoomsCommand {
+                        rooms: state.recent_rooms.clone(),
+                    },
+                ))
+                .context("could not check the recent rooms")?;
+        }
+        // the rooms have been forgotten by the state already
+        event::Event::RoomsChecked(event) if !event.missing.is_empty() => {
+            let config = state.config();
+            update_settings(state, config);
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Lists the rooms again once the server has mentioned one the client does not know of
+/// The client only knows of the rooms it has been told about at the login or since.
+fn handle_unknown_rooms(
+    state: &mut State,
+    command_writer: &mut QueuedCommandWriter,
+) -> anyhow::Result<()> {
+    if std::mem::take(&mut state.has_unknown_rooms) {
+        command_writer
+            .write(&command::UserCommand::ListRooms(command::ListRoomsCommand))
+            .context("could not list the rooms")?;
+    }
+
+    Ok(())
+}
+
+/// Joins the room once a moderator has approved the request of the user to join it
+fn handle_join_approval(
+    event: &event::Event,
+    state: &mut State,
+    command_writer: &mut QueuedCommandWriter,
+) -> anyhow::Result<()> {
+    let event::Event::JoinRequestDecided(event) = event else {
+        return Ok(());
+    };
+    let has_joined = state
+        .room_data_map
+        .get(&event.room)
+        .map(|room_data| room_data.has_joined);
+    if !event.approved || event.user_id != state.user_id || has_joined != Some(false) {
+        return Ok(());
+    }
+
+    command_writer
+        .write(&command::UserCommand::JoinRoom(command::JoinRoomCommand {
+            room: event.room.clone(),
+            wait_if_full: true,
+        }))
+        .context("could not join room")?;
+
+    // the history was refused while the request was pending
+    if state.is_room_first_time(&event.room) == Some(true)
+        && !state.is_waiting_for_room_key(&event.room)
+    {
+        request_history(command_writer, event.room.clone())?;
+    }
+
+    Ok(())
+}
+
+impl<C: Connector> StateStore<C> {
+    /// Log in with the identity provider through the device authorization flow
+    /// The user is shown where to log in while the flow waits for them
+    async fn authorize(
+        &self,
+        oidc_config: &OidcConfig,
+        state: &mut State,
+    ) -> anyhow::Result<String> {
+        let http_client = reqwest::Client::new();
+        let authorization = oidc::start_device_authorization(&http_client, oidc_config).await?;
+
+        state.mark_authorization_pending(
+            authorization.verification_uri.clone(),
+            authorization.user_code.clone(),
+        );
+        self.state_tx.send(state.clone())?;
+
+        oidc::wait_for_id_token(&http_client, oidc_config, &authorization).await
+    }
+}
+
+impl<C: Connector> StateStore<C> {
+    /// Plays a recording back instead of connecting to a server
+    ///
+    /// The user can switch between the rooms of the recording, everything else would need a server.
+    async fn replay_loop(
+        mut replay: Replay,
+        config: Option<ClientConfig>,
+        state_tx: watch::Sender<State>,
+        mut terminator: Terminator,
+        mut action_rx: UnboundedReceiver<Action>,
+        mut interrupt_rx: broadcast::Receiver<Interrupted>,
+    ) -> anyhow::Result<Interrupted> {
+        let mut state = initial_state(config.as_ref());
+        state.mark_replaying(replay.path().to_string());
+        state_tx.send(state.clone())?;
+
+        let mut ticker = tokio::time::interval(Duration::from_secs(1));
+        replay.start();
+
+        let result = loop {
+            let is_finished = replay.is_finished();
+
+            tokio::select! {
+                Some(event) = replay.next_event(), if !is_finished => {
+                    state.handle_server_event(&event);
+
+                    if replay.is_finished() {
+                        state.push_notification_to_active_room(Notice::ReplayEnded);
+                    }
+                },
+                Some(action) = action_rx.recv() => match action {
+                    Action::SelectRoom { room } => {
+                        state.try_set_active_room(&room);
+                    },
+                    Action::FocusChanged { is_focused } => {
+                        state.set_focus(is_focused);
+                    },
+                    Action::UpdateSettings { config } => {
+                        update_settings(&mut state, *config);
+                    },
+                    Action::ToggleRevealed { nth } => {
+                        state.toggle_revealed(nth);
+                    },
+                    Action::ToggleParticipation => {
+                        state.toggle_participation();
+                        // saved along with the settings, so the room stays as the user left it
+                        let config = state.config();
+                        update_settings(&mut state, config);
+                    },
+                    Action::Exit => {
+                        let _ = terminator.terminate(Interrupted::UserInt);
+
+                        break Interrupted::UserInt;
+                    },
+                    _ => state.push_notification_to_active_room(Notice::ReplayReadOnly),
+                },
+                _ = ticker.tick() => {
+                    state.tick_timer();
+                },
+                Ok(interrupted) = interrupt_rx.recv() => {
+                    break interrupted;
+                }
+            }
+
+            state_tx.send(state.clone())?;
+        };
+
+        Ok(result)
+    }
+
     pub async fn main_loop(
-        self,
+        mut self,
         mut terminator: Terminator,
         mut action_rx: UnboundedReceiver<Action>,
         mut interrupt_rx: broadcast::Receiver<Interrupted>,
     ) -> anyhow::Result<Interrupted> {
+        if let Some(replay) = self.replay.take() {
+            return Self::replay_loop(
+                replay,
+                self.config,
+                self.state_tx,
+                terminator,
+                action_rx,
+                interrupt_rx,
+            )
+            .await;
+        }
+
         let mut opt_server_handle: Option<ServerHandle> = None;
-        let mut state = State::default();
+        // the token of the identity provider, sent along with every login on the connection
+        let mut login_token: Option<String> = None;
+        // sent again to the server the session is redirected to, see [event::RedirectReplyEvent]
+        let mut last_login: Option<command::LoginCommand> = None;
+        let mut redirect_addr: Option<String> = None;
+        let mut state = initial_state(self.config.as_ref());
+        // kept across reconnections, so the keys of encrypted rooms do not have to be shared again
+        let mut e2e_keys = E2eKeys::new();
 
         // the initial state once
         self.state_tx.send(state.clone())?;
 
         let mut ticker = tokio::time::interval(Duration::from_secs(1));
+        let mut ping_ticker = tokio::time::interval(PING_INTERVAL);
+        // no burst of pings after having been disconnected for a while
+        ping_ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let shows_latency = state.status_bar.has_segment(SegmentKind::Latency);
+        let mut room_list_ticker = tokio::time::interval(ROOM_LIST_INTERVAL);
+        room_list_ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
 
-        let result = loop {
+        // the command of the status bar runs in the background, so a slow command does not hold up the events
+        let (status_command_tx, mut status_command_rx) = watch::channel(None);
+        if let Some(command) = state.status_bar.segment_command() {
+            tokio::spawn(status_bar::run_segment_command(
+                command.to_string(),
+                status_command_tx.clone(),
+            ));
+        }
+
+        // the images are fetched in the background, and shown once they have been rendered
+        let (rendered_image_tx, mut rendered_image_rx) = mpsc::unbounded_channel();
+
+        // the frames going over the connection are only passed on while the traffic inspector is on
+        let (traffic_frame_tx, mut traffic_frame_rx) = mpsc::unbounded_channel();
+        let inspects_traffic = Arc::new(AtomicBool::new(false));
+        let frame_tap: FrameTap = {
+            let inspects_traffic = Arc::clone(&inspects_traffic);
+            Arc::new(move |direction, line| {
+                if inspects_traffic.load(Ordering::Relaxed) {
+                    let _ =
+                        traffic_frame_tx.send(TrafficFrame::capture(direction, now_millis(), line));
+                }
+            })
+        };
+
+        let result = 'session: loop {
             if let Some((event_stream, command_writer)) = opt_server_handle.as_mut() {
                 tokio::select! {
                     // Handle the server events as they come in
                     maybe_event = event_stream.next() => match maybe_event {
-                        Some(Ok(event)) => {
+                        Some(Ok(mut event)) => {
+                            e2e_keys.decrypt_event(&mut event);
+                            if let Some(recorder) = self.recorder.as_mut() {
+                                recorder.record(&event)?;
+                            }
                             state.handle_server_event(&event);
+                            // followed once the event has been handled, the connection is closed by the server right after
+                            if let event::Event::Redirect(redirect) = &event {
+                                redirect_addr = Some(redirect.addr.clone());
+                            }
+                            if let Some(notify_hook) = self.notify_hook.as_ref() {
+                                notify_hook.handle_server_event(&event, &state.user_id, &state.highlights);
+                            }
+                            if let Some(image_renderer) = self.image_renderer.as_ref() {
+                                image_renderer.handle_server_event(&event, &rendered_image_tx);
+                            }
+                            handle_room_keys(&event, &mut state, &mut e2e_keys, command_writer)?;
+                            handle_join_approval(&event, &mut state, command_writer)?;
+                            handle_auto_join(&event, &mut state, command_writer)?;
+                            handle_recent_rooms(&event, &mut state, command_writer)?;
+                            handle_unknown_rooms(&mut state, command_writer)?;
+                            // after the rooms have been joined, so the connect hook can send messages to them
+                            if let Some(scripts) = self.scripts.as_ref() {
+                                let outputs = scripts.handle_server_event(&event, &state.user_id, &state.highlights);
+                                handle_script_outputs(outputs, &mut state, &e2e_keys, command_writer)?;
+                            }
                         },
                         // server disconnected, we need to reset the state
                         None => {
                             opt_server_handle = None;
-                            state = State::default();
+                            state = disconnected_state(state);
                         },
                         _ => (),
                     },
+                    // the server has stopped taking the commands, or the connection broke while writing them
+                    err = command_writer.stopped() => {
+                        opt_server_handle = None;
+                        state = disconnected_state(state);
+                        state.process_connection_request_result(Err(err));
+                    },
                     // Handle the actions coming from the UI
                     // and process them to do async operations
                     Some(action) = action_rx.recv() => match action {
-                        Action::SendMessage { content } => {
+                        Action::SendMessage { content, content_type } => {
+                            // the slash commands added by the scripts are run instead of being sent
+                            let outputs = self
+                                .scripts
+                                .as_ref()
+                                .and_then(|scripts| scripts.run_command(&content, state.active_room.as_deref()));
+                            if let Some(outputs) = outputs {
+                                handle_script_outputs(outputs, &mut state, &e2e_keys, command_writer)?;
+                                continue;
+                            }
+
+                            if let Some(active_room) = state.active_room.clone() {
+                                send_message(command_writer, &mut state, &e2e_keys, active_room, content, content_type)?;
+                            }
+                        },
+                        Action::Login { username } => {
+                            let login = command::LoginCommand { username, token: login_token.clone(), guest: false };
+                            last_login = Some(login.clone());
+                            log_in(command_writer, &mut state, login)?;
+                        },
+                        Action::LoginAsGuest => {
+                            let login = command::LoginCommand { username: None, token: None, guest: true };
+                            last_login = Some(login.clone());
+                            log_in(command_writer, &mut state, login)?;
+                        },
+                        Action::SetWelcomeMessage { message } => {
                             if let Some(active_room) = state.active_room.as_ref() {
                                 command_writer
-                                    .write(&command::UserCommand::SendMessage(
-                                        command::SendMessageCommand {
+                                    .write(&command::UserCommand::SetWelcomeMessage(
+                                        command::SetWelcomeMessageCommand {
                                             room: active_room.clone(),
-                                            content,
+                                            message,
                                         },
                                     ))
-                                    .await
-                                    .context("could not send message")?;
+                                    .context("could not set the welcome message")?;
+                            }
+                        },
+                        Action::UpdateRoomMetadata { description, capacity } => {
+                            if let Some(active_room) = state.active_room.as_ref() {
+                                command_writer
+                                    .write(&command::UserCommand::UpdateRoomMetadata(
+                                        command::UpdateRoomMetadataCommand {
+                                            room: active_room.clone(),
+                                            description,
+                                            capacity,
+                                        },
+                                    ))
+                                    .context("could not update the room metadata")?;
+                            }
+                        },
+                        Action::ListSessions => {
+                            command_writer
+                                .write(&command::UserCommand::ListSessions(command::ListSessionsCommand))
+                                .context("could not list the sessions")?;
+                        },
+                        Action::DisconnectSession { session_id } => {
+                            command_writer
+                                .write(&command::UserCommand::DisconnectSession(
+                                    command::DisconnectSessionCommand { session_id },
+                                ))
+                                .context("could not disconnect the session")?;
+                        },
+                        Action::SetStatusText { text } => {
+                            command_writer
+                                .write(&command::UserCommand::SetStatusText(command::SetStatusTextCommand { text }))
+                                .context("could not set the status text")?;
+                        },
+                        Action::GetUserInfo { user_id } => {
+                            command_writer
+                                .write(&command::UserCommand::GetUserInfo(command::GetUserInfoCommand { user_id }))
+                                .context("could not look up the user")?;
+                        },
+                        Action::SearchUsers { query } => {
+                            command_writer
+                                .write(&command::UserCommand::SearchUsers(command::SearchUsersCommand { query }))
+                                .context("could not search the users")?;
+                        },
+                        Action::GetRoomInfo => {
+                            if let Some(active_room) = state.active_room.as_ref() {
+                                command_writer
+                                    .write(&command::UserCommand::GetRoomInfo(command::GetRoomInfoCommand {
+                                        room: active_room.clone(),
+                                    }))
+                                    .context("could not look up the room")?;
                             }
                         },
+                        Action::GetRoomStats { all_rooms } => {
+                            let room = if all_rooms {
+                                None
+                            } else if let Some(active_room) = state.active_room.as_ref() {
+                                Some(active_room.clone())
+                            } else {
+                                continue;
+                            };
+
+                            command_writer
+                                .write(&command::UserCommand::GetRoomStats(command::GetRoomStatsCommand { room }))
+                                .context("could not look up the stats")?;
+                        },
+                        Action::ReportMessage { user_id, reason } => {
+                            let Some(active_room) = state.active_room.clone() else {
+                                continue;
+                            };
+
+                            match state.last_message_id_of(&active_room, &user_id) {
+                                Some(message_id) => {
+                                    command_writer
+                                        .write(&command::UserCommand::ReportMessage(command::ReportMessageCommand {
+                                            room: active_room,
+                                            message_id,
+                                            reason,
+                                        }))
+                                        .context("could not report the message")?;
+                                }
+                                None => state.push_notification_to_active_room(Notice::NoMessageToReport { user_id }),
+                            }
+                        },
+                        Action::ToggleRevealed { nth } => {
+                            state.toggle_revealed(nth);
+                        },
+                        Action::ToggleParticipation => {
+                            state.toggle_participation();
+                            // saved along with the settings, so the room stays as the user left it
+                            let config = state.config();
+                            update_settings(&mut state, config);
+                        },
+                        Action::ListReports => {
+                            command_writer
+                                .write(&command::UserCommand::ListReports(command::ListReportsCommand))
+                                .context("could not list the reports")?;
+                        },
+                        Action::ResolveReport { report_id } => {
+                            command_writer
+                                .write(&command::UserCommand::ResolveReport(command::ResolveReportCommand { report_id }))
+                                .context("could not resolve the report")?;
+                        },
+                        Action::ApproveJoinRequest { ref user_id } | Action::DenyJoinRequest { ref user_id } => {
+                            let Some(active_room) = state.active_room.clone() else {
+                                continue;
+                            };
+                            let decision = command::JoinRequestDecisionCommand { room: active_room, user_id: user_id.clone() };
+
+                            command_writer
+                                .write(&match action {
+                                    Action::ApproveJoinRequest { .. } => command::UserCommand::ApproveJoinRequest(decision),
+                                    _ => command::UserCommand::DenyJoinRequest(decision),
+                                })
+                                .context("could not decide on the join request")?;
+                        },
+                        Action::ShareRoomKey { fingerprint } => {
+                            let Some(active_room) = state.active_room.clone() else {
+                                continue;
+                            };
+
+                            share_room_key(command_writer, &mut state, &e2e_keys, &active_room, &fingerprint)?;
+                        },
                         Action::SelectRoom { room } => {
                             let room_cloned = room.clone();
-                            if let Some(false) = state.try_set_active_room(room.as_str()).map(|room_data| room_data.has_joined) {
+                            // rooms the server no longer lists are only opened, to read the messages received before
+                            let is_defunct = state.room_data_map.get(&room).is_some_and(|room_data| room_data.defunct);
+                            let has_joined = state
+                                .try_set_active_room(room.as_str())
+                                .filter(|_| !is_defunct)
+                                .map(|room_data| room_data.has_joined);
+
+                            // Opening a room reads it, the other sessions of the user clear its unread badge too
+                            if has_joined.is_some() {
+                                command_writer
+                                    .write(&command::UserCommand::MarkRoomRead(command::MarkRoomReadCommand {
+                                        room: room.clone(),
+                                }))
+                                .context("could not mark the room as read")?;
+                            }
+
+                            if let Some(false) = has_joined {
                                 // Handle room joining
                                 command_writer
                                     .write(&command::UserCommand::JoinRoom(command::JoinRoomCommand {
-                                        room: room,
+                                        room,
+                                        // wait in the queue of full rooms, the user is joined once a slot frees up
+                                        wait_if_full: true,
                                 }))
-                                .await
                                 .context("could not join room")?;
                             }
 
                             // Handle history fetching (first time only)
-                            if let Some(true) = state.is_room_first_time(room_cloned.as_str()) {
-                                command_writer
-                                    .write(&command::UserCommand::GetHistory(command::GetHistoryCommand {
-                                        room: room_cloned,
+                            // The history of encrypted rooms is fetched once their key has arrived
+                            if has_joined.is_some() && state.is_room_first_time(room_cloned.as_str()) == Some(true) && !state.is_waiting_for_room_key(&room_cloned) {
+                                request_history(command_writer, room_cloned)?;
+                            }
+                        },
+                        Action::LeaveRoom { room } => {
+                            let Some(room) = room.or_else(|| state.active_room.clone()) else {
+                                continue;
+                            };
+
+                            // rooms the user has not joined, or only waits for, are left as well
+                            // the rooms left are remembered along with the settings, to rejoin them quickly
+                            if state.mark_room_left(&room) {
+                                let config = state.config();
+                                update_settings(&mut state, config);
+                            }
+                            command_writer
+                                .write(&command::UserCommand::LeaveRoom(command::LeaveRoomCommand { room }))
+                                .context("could not leave the room")?;
+                        },
+                        Action::ArchiveRoom { room, delay_ms } => {
+                            let Some(room) = room.or_else(|| state.active_room.clone()) else {
+                                continue;
+                            };
+
+                            command_writer
+                                .write(&command::UserCommand::ArchiveRoom(command::ArchiveRoomCommand {
+                                    room,
+                                    archive_at: delay_ms.map(|delay_ms| now_millis() + delay_ms),
                                 }))
-                                .await
-                                .context("could not request history")?;
+                                .context("could not archive the room")?;
+                        },
+                        Action::ReopenRoom { room } => {
+                            let Some(room) = room.or_else(|| state.active_room.clone()) else {
+                                continue;
+                            };
+
+                            command_writer
+                                .write(&command::UserCommand::ReopenRoom(command::ReopenRoomCommand { room }))
+                                .context("could not reopen the room")?;
+                        },
+                        Action::CreateInviteCode { ttl_secs, max_uses } => {
+                            let Some(room) = state.active_room.clone() else {
+                                continue;
+                            };
+
+                            command_writer
+                                .write(&command::UserCommand::CreateInviteCode(command::CreateInviteCodeCommand {
+                                    room,
+                                    ttl_secs,
+                                    max_uses,
+                                }))
+                                .context("could not create an invite code")?;
+                        },
+                        Action::JoinWithCode { code } => {
+                            command_writer
+                                .write(&command::UserCommand::JoinWithCode(command::JoinWithCodeCommand { code }))
+                                .context("could not join with the invite code")?;
+                        },
+                        Action::FocusChanged { is_focused } => {
+                            let read_room = state.set_focus(is_focused);
+
+                            if state.auto_away {
+                                command_writer
+                                    .write(&command::UserCommand::SetPresence(command::SetPresenceCommand {
+                                        status: if is_focused {
+                                            event::PresenceStatus::Online
+                                        } else {
+                                            event::PresenceStatus::Away
+                                        },
+                                    }))
+                                    .context("could not set the presence")?;
+                            }
+
+                            // Coming back to the terminal reads the messages that arrived in the active room meanwhile
+                            if let Some(room) = read_room {
+                                command_writer
+                                    .write(&command::UserCommand::MarkRoomRead(command::MarkRoomReadCommand { room }))
+                                    .context("could not mark the room as read")?;
                             }
                         },
+                        Action::UpdateSettings { config } => {
+                            update_settings(&mut state, *config);
+                        },
+                        Action::ToggleTrafficInspector => {
+                            let inspects = state.toggle_traffic_inspector();
+                            inspects_traffic.store(inspects, Ordering::Relaxed);
+                        },
+                        Action::ExportTraffic => {
+                            state.export_traffic();
+                        },
                         Action::Exit => {
                             let _ = terminator.terminate(Interrupted::UserInt);
 
@@ -117,10 +1010,30 @@ impl StateStore {
                         },
                         _ => (),
                     },
+                    Some(traffic_frame) = traffic_frame_rx.recv() => {
+                        state.record_traffic(traffic_frame);
+                    },
                     // Tick to terminate the select every N milliseconds
                     _ = ticker.tick() => {
                         state.tick_timer();
                     },
+                    _ = ping_ticker.tick(), if shows_latency => {
+                        command_writer
+                            .write(&command::UserCommand::Ping(command::PingCommand { sent_at: now_millis() }))
+                            .context("could not ping the server")?;
+                    },
+                    // the first tick is right away, the rooms are known from the login by then
+                    _ = room_list_ticker.tick(), if matches!(state.server_connection_status, ServerConnectionStatus::Connected { .. }) => {
+                        command_writer
+                            .write(&command::UserCommand::ListRooms(command::ListRoomsCommand))
+                            .context("could not list the rooms")?;
+                    },
+                    Ok(()) = status_command_rx.changed() => {
+                        state.status_command_output = status_command_rx.borrow_and_update().clone();
+                    },
+                    Some(rendered_image) = rendered_image_rx.recv() => {
+                        state.attach_image(rendered_image);
+                    },
                     // Catch and handle interrupt signal to gracefully shutdown
                     Ok(interrupted) = interrupt_rx.recv() => {
                         break interrupted;
@@ -134,19 +1047,75 @@ impl StateStore {
                             // emit event to re-render any part depending on the connection status
                             self.state_tx.send(state.clone())?;
 
-                            match create_server_handle(&addr).await {
-                                Ok(server_handle) => {
+                            let connection_result = {
+                                let connection = async {
+                                    let bearer_token = match self.oidc_config.as_ref() {
+                                        Some(oidc_config) => Some(self.authorize(oidc_config, &mut state).await?),
+                                        None => None,
+                                    };
+                                    let server_handle = create_server_handle(&self.connector, &addr, frame_tap.clone(), self.connect_timeout).await?;
+
+                                    anyhow::Ok((server_handle, bearer_token))
+                                };
+                                tokio::pin!(connection);
+
+                                // logging in may take a while, the user can still cancel or quit in the meantime
+                                loop {
+                                    tokio::select! {
+                                        result = &mut connection => break Some(result),
+                                        Some(action) = action_rx.recv() => match action {
+                                            Action::CancelConnection => break None,
+                                            Action::Exit => {
+                                                let _ = terminator.terminate(Interrupted::UserInt);
+
+                                                break 'session Interrupted::UserInt;
+                                            },
+                                            // the connection holds on to the state until it is done, nothing else is acted on
+                                            _ => (),
+                                        },
+                                        Ok(interrupted) = interrupt_rx.recv() => {
+                                            break 'session interrupted;
+                                        }
+                                    }
+                                }
+                            };
+
+                            match connection_result {
+                                None => {
+                                    state.mark_connection_cancelled();
+                                },
+                                Some(Ok(((mut server_handle, endpoint), bearer_token))) => {
+                                    state.process_connection_request_result(Ok((addr, endpoint)));
+                                    // users who have picked a name, or are known to the identity provider, are logged in right away
+                                    // the others pick one on the login page
+                                    if state.username.is_some() || bearer_token.is_some() {
+                                        let login = command::LoginCommand {
+                                            username: state.username.clone(),
+                                            token: bearer_token.clone(),
+                                            guest: false,
+                                        };
+                                        last_login = Some(login.clone());
+                                        log_in(&mut server_handle.1, &mut state, login)?;
+                                    }
+                                    login_token = bearer_token;
                                     // set the server handle and change status for further processing
                                     let _ = opt_server_handle.insert(server_handle);
-                                    state.process_connection_request_result(Ok(addr));
+                                    // the state has been reset while disconnected, the command may not run again for a while
+                                    state.status_command_output = status_command_rx.borrow().clone();
                                     // ticker needs to be reset to avoid showing time spent inputting and connecting to the server address
                                     ticker.reset();
                                 },
-                                Err(err) => {
+                                Some(Err(err)) => {
                                     state.process_connection_request_result(Err(err));
                                 }
                             }
                         },
+                        Action::FocusChanged { is_focused } => {
+                            state.set_focus(is_focused);
+                        },
+                        Action::UpdateSettings { config } => {
+                            update_settings(&mut state, *config);
+                        },
                         Action::Exit => {
                             let _ = terminator.terminate(Interrupted::UserInt);
 
@@ -161,9 +1130,412 @@ impl StateStore {
                 }
             }
 
+            // the session has been handed to another server, the old connection is dropped without being read again
+            if let Some(addr) = redirect_addr.take() {
+                opt_server_handle = None;
+
+                match create_server_handle(
+                    &self.connector,
+                    &addr,
+                    frame_tap.clone(),
+                    self.connect_timeout,
+                )
+                .await
+                {
+                    Ok((mut server_handle, endpoint)) => {
+                        let logs_in_again = state.mark_redirected(addr, endpoint);
+                        if let Some(login) = last_login.clone().filter(|_| logs_in_again) {
+                            log_in(&mut server_handle.1, &mut state, login)?;
+                        }
+                        opt_server_handle = Some(server_handle);
+                    }
+                    Err(err) => {
+                        state = disconnected_state(state);
+                        state.process_connection_request_result(Err(err));
+                    }
+                }
+            }
+
             self.state_tx.send(state.clone())?;
         };
 
+        // the user interface restores the terminal meanwhile, an unresponsive server only delays the exit briefly
+        if let Some((_, command_writer)) = opt_server_handle {
+            let _ = tokio::time::timeout(FAREWELL_TIMEOUT, say_farewell(command_writer)).await;
+        }
+
         Ok(result)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use comms::transport::{
+        client::CommandWriter,
+        memory::{self, ServerEnd},
+        server::{CommandStream, EventWriter},
+    };
+    use tokio::sync::oneshot;
+
+    use super::*;
+
+    const ADDR: &str = "chat.example:8080";
+    const ROOM: &str = "general";
+    /// How long a test waits for something to happen before it fails
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Hands the server end of every connection to the test, the connections are refused if it has none
+    struct MemoryConnector {
+        server_tx: Option<mpsc::UnboundedSender<ServerEnd>>,
+    }
+
+    impl Connector for MemoryConnector {
+        async fn connect(
+            &self,
+            addr: &str,
+            _frame_tap: FrameTap,
+        ) -> anyhow::Result<((EventStream, CommandWriter), SocketAddr)> {
+            let server_tx = self
+                .server_tx
+                .as_ref()
+                .with_context(|| format!("{} refused the connection", addr))?;
+            let (client_end, server_end) = memory::pair();
+            server_tx
+                .send(server_end)
+                .map_err(|_| anyhow::anyhow!("the test no longer takes connections"))?;
+
+            Ok((client_end, "127.0.0.1:8080".parse()?))
+        }
+    }
+
+    /// A state store running in the background, driven by the test the way the user interface drives it
+    struct Client {
+        action_tx: mpsc::UnboundedSender<Action>,
+        state_rx: watch::Receiver<State>,
+        main_loop: oneshot::Receiver<anyhow::Result<Interrupted>>,
+    }
+
+    impl Client {
+        fn start(connector: MemoryConnector) -> Self {
+            let (state_tx, state_rx) = watch::channel(State::default());
+            let state_store = StateStore {
+                state_tx,
+                oidc_config: None,
+                config: None,
+                notify_hook: None,
+                image_renderer: None,
+                scripts: None,
+                recorder: None,
+                replay: None,
+                connect_timeout: TIMEOUT,
+                connector,
+            };
+            let (interrupt_tx, interrupt_rx) = broadcast::channel(1);
+            let (action_tx, action_rx) = mpsc::unbounded_channel();
+            let (result_tx, result_rx) = oneshot::channel();
+
+            // the main loop holds on to the scripts across awaits, which can not be sent to another thread,
+            // so it runs on a thread of its own, as it does alongside the user interface
+            std::thread::spawn(move || {
+                let runtime = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .unwrap();
+                let result = runtime.block_on(state_store.main_loop(
+                    Terminator::new(interrupt_tx),
+                    action_rx,
+                    interrupt_rx,
+                ));
+                let _ = result_tx.send(result);
+            });
+
+            Client {
+                action_tx,
+                state_rx,
+                main_loop: result_rx,
+            }
+        }
+
+        fn act(&self, action: Action) {
+            self.action_tx.send(action).unwrap();
+        }
+
+        /// Waits for a state matching the predicate, skipping the others
+        async fn wait_for(&mut self, predicate: impl FnMut(&State) -> bool) -> State {
+            tokio::time::timeout(TIMEOUT, self.state_rx.wait_for(predicate))
+                .await
+                .expect("the state should have changed in time")
+                .expect("the main loop should still be running")
+                .clone()
+        }
+
+        async fn exit(self) -> Interrupted {
+            self.act(Action::Exit);
+
+            tokio::time::timeout(TIMEOUT, self.main_loop)
+                .await
+                .expect("the main loop should have ended in time")
+                .unwrap()
+                .unwrap()
+        }
+    }
+
+    fn is_connected(state: &State) -> bool {
+        matches!(
+            state.server_connection_status,
+            ServerConnectionStatus::Connected { .. }
+        )
+    }
+
+    async fn next_command(commands: &mut CommandStream) -> Option<command::UserCommand> {
+        tokio::time::timeout(TIMEOUT, commands.next())
+            .await
+            .expect("the command should have arrived in time")
+            .map(Result::unwrap)
+    }
+
+    /// Waits for a command matching the predicate, returning it along with the ones skipped before it
+    async fn expect_command(
+        commands: &mut CommandStream,
+        predicate: impl Fn(&command::UserCommand) -> bool,
+    ) -> (command::UserCommand, Vec<command::UserCommand>) {
+        let mut skipped = Vec::new();
+        loop {
+            let command = next_command(commands)
+                .await
+                .expect("the connection should still be open");
+            if predicate(&command) {
+                return (command, skipped);
+            }
+            skipped.push(command);
+        }
+    }
+
+    /// Connects the client and logs it in as alice, returning the server end of the connection
+    async fn connect_and_log_in(
+        client: &mut Client,
+        server_rx: &mut mpsc::UnboundedReceiver<ServerEnd>,
+    ) -> (CommandStream, EventWriter) {
+        client.act(Action::ConnectToServerRequest {
+            addr: String::from(ADDR),
+        });
+        let (mut commands, mut event_writer) = tokio::time::timeout(TIMEOUT, server_rx.recv())
+            .await
+            .expect("the client should have connected in time")
+            .unwrap();
+
+        client.act(Action::Login {
+            username: Some(String::from("alice")),
+        });
+        expect_command(&mut commands, |command| {
+            matches!(command, command::UserCommand::Login(_))
+        })
+        .await;
+        event_writer.write(&login_successful()).await.unwrap();
+        client.wait_for(is_connected).await;
+
+        (commands, event_writer)
+    }
+
+    fn login_successful() -> event::Event {
+        event::Event::LoginSuccessful(event::LoginSuccessfulReplyEvent {
+            session_id: String::from("session"),
+            user_id: String::from("alice"),
+            rooms: vec![event::RoomDetail {
+                name: String::from(ROOM),
+                description: String::new(),
+                encrypted: false,
+                archived: false,
+                category: None,
+            }],
+            max_message_length: 1000,
+            session_count: 1,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_failed_connection_is_shown_and_the_client_keeps_running() {
+        let mut client = Client::start(MemoryConnector { server_tx: None });
+
+        client.act(Action::ConnectToServerRequest {
+            addr: String::from(ADDR),
+        });
+        let state = client
+            .wait_for(|state| {
+                matches!(
+                    state.server_connection_status,
+                    ServerConnectionStatus::Errored { .. }
+                )
+            })
+            .await;
+        assert!(state.room_data_map.is_empty());
+
+        assert!(matches!(client.exit().await, Interrupted::UserInt));
+    }
+
+    #[tokio::test]
+    async fn test_server_disconnecting_mid_session_resets_the_state() {
+        let (server_tx, mut server_rx) = mpsc::unbounded_channel();
+        let mut client = Client::start(MemoryConnector {
+            server_tx: Some(server_tx),
+        });
+        let (commands, event_writer) = connect_and_log_in(&mut client, &mut server_rx).await;
+        assert!(client.state_rx.borrow().room_data_map.contains_key(ROOM));
+
+        drop((commands, event_writer));
+        let state = client
+            .wait_for(|state| {
+                matches!(
+                    state.server_connection_status,
+                    ServerConnectionStatus::Uninitialized
+                )
+            })
+            .await;
+        assert!(state.room_data_map.is_empty());
+        assert!(state.user_id.is_empty());
+
+        // the client can connect again right away
+        let (_commands, _event_writer) = connect_and_log_in(&mut client, &mut server_rx).await;
+    }
+
+    #[tokio::test]
+    async fn test_redirected_client_reconnects_and_joins_its_rooms_again() {
+        let (server_tx, mut server_rx) = mpsc::unbounded_channel();
+        let mut client = Client::start(MemoryConnector {
+            server_tx: Some(server_tx),
+        });
+        let (mut commands, mut event_writer) =
+            connect_and_log_in(&mut client, &mut server_rx).await;
+
+        client.act(Action::SelectRoom {
+            room: String::from(ROOM),
+        });
+        expect_command(
+            &mut commands,
+            |command| matches!(command, command::UserCommand::JoinRoom(join) if join.room == ROOM),
+        )
+        .await;
+        event_writer
+            .write(&event::Event::RoomParticipation(
+                event::RoomParticipationBroadcastEvent {
+                    room: String::from(ROOM),
+                    user_id: String::from("alice"),
+                    status: event::RoomParticipationStatus::Joined,
+                },
+            ))
+            .await
+            .unwrap();
+        client
+            .wait_for(|state| {
+                state
+                    .room_data_map
+                    .get(ROOM)
+                    .is_some_and(|room_data| room_data.has_joined)
+            })
+            .await;
+
+        event_writer
+            .write(&event::Event::Redirect(event::RedirectReplyEvent {
+                addr: String::from("chat-2.example.com:8080"),
+            }))
+            .await
+            .unwrap();
+        let (mut commands, mut event_writer) = tokio::time::timeout(TIMEOUT, server_rx.recv())
+            .await
+            .expect("the client should have reconnected in time")
+            .unwrap();
+
+        expect_command(&mut commands, |command| {
+            matches!(command, command::UserCommand::Login(login) if login.username.as_deref() == Some("alice"))
+        })
+        .await;
+        event_writer.write(&login_successful()).await.unwrap();
+        expect_command(
+            &mut commands,
+            |command| matches!(command, command::UserCommand::JoinRoom(join) if join.room == ROOM),
+        )
+        .await;
+        client
+            .wait_for(|state| {
+                matches!(
+                    &state.server_connection_status,
+                    ServerConnectionStatus::Connected { addr, .. } if addr == "chat-2.example.com:8080"
+                )
+            })
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_history_is_requested_after_joining_and_only_once() {
+        let (server_tx, mut server_rx) = mpsc::unbounded_channel();
+        let mut client = Client::start(MemoryConnector {
+            server_tx: Some(server_tx),
+        });
+        let (mut commands, mut event_writer) =
+            connect_and_log_in(&mut client, &mut server_rx).await;
+
+        client.act(Action::SelectRoom {
+            room: String::from(ROOM),
+        });
+        let (_, skipped) = expect_command(&mut commands, |command| {
+            matches!(command, command::UserCommand::GetHistory(history) if history.room == ROOM)
+        })
+        .await;
+        assert!(skipped.iter().any(
+            |command| matches!(command, command::UserCommand::JoinRoom(join) if join.room == ROOM)
+        ));
+
+        for event in [
+            event::Event::RoomParticipation(event::RoomParticipationBroadcastEvent {
+                room: String::from(ROOM),
+                user_id: String::from("alice"),
+                status: event::RoomParticipationStatus::Joined,
+            }),
+            event::Event::HistoryResponse(event::HistoryResponseEvent {
+                room: String::from(ROOM),
+                history: Vec::new(),
+            }),
+        ] {
+            event_writer.write(&event).await.unwrap();
+        }
+        client
+            .wait_for(|state| {
+                state
+                    .room_data_map
+                    .get(ROOM)
+                    .is_some_and(|room_data| room_data.has_joined && !room_data.first_time)
+            })
+            .await;
+
+        // opening the room again neither joins it nor fetches the history again
+        client.act(Action::SelectRoom {
+            room: String::from(ROOM),
+        });
+        client.act(Action::ListSessions);
+        let (_, skipped) = expect_command(&mut commands, |command| {
+            matches!(command, command::UserCommand::ListSessions(_))
+        })
+        .await;
+        assert!(!skipped.iter().any(|command| matches!(
+            command,
+            command::UserCommand::JoinRoom(_) | command::UserCommand::GetHistory(_)
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_exit_says_farewell_to_the_server() {
+        let (server_tx, mut server_rx) = mpsc::unbounded_channel();
+        let mut client = Client::start(MemoryConnector {
+            server_tx: Some(server_tx),
+        });
+        let (mut commands, _event_writer) = connect_and_log_in(&mut client, &mut server_rx).await;
+
+        assert!(matches!(client.exit().await, Interrupted::UserInt));
+
+        expect_command(&mut commands, |command| {
+            matches!(command, command::UserCommand::Quit(_))
+        })
+        .await;
+        assert_eq!(next_command(&mut commands).await, None);
+    }
+}
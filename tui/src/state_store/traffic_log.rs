@@ -0,0 +1,167 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+};
+
+use anyhow::Context;
+use comms::transport::client::FrameDirection;
+use serde::Serialize;
+
+use super::message_buffer::MessageBuffer;
+
+/// The most frames kept, the oldest ones are dropped first
+const MAX_FRAMES: usize = 500;
+/// Shown in place of the token of a login, the frames end up in exported files and bug reports
+const REDACTED_TOKEN: &str = "<redacted>";
+
+/// A frame that has gone over the connection
+#[derive(Debug, Clone)]
+pub struct TrafficFrame {
+    pub direction: FrameDirection,
+    /// Milliseconds since the unix epoch, when the frame was sent or received
+    pub at: u64,
+    /// The line of json the frame has been sent as
+    pub line: String,
+}
+
+impl TrafficFrame {
+    /// Capture a frame as it goes over the connection, the token of a login is never kept
+    pub fn capture(direction: FrameDirection, at: u64, line: &str) -> Self {
+        TrafficFrame {
+            direction,
+            at,
+            line: redact_token(line).unwrap_or_else(|| String::from(line)),
+        }
+    }
+
+    /// The bytes of the frame before it is compressed
+    pub fn size(&self) -> usize {
+        self.line.len()
+    }
+
+    /// The frame as indented json, or as it is if it is not valid json
+    pub fn pretty(&self) -> String {
+        serde_json::from_str::<serde_json::Value>(&self.line)
+            .and_then(|frame| serde_json::to_string_pretty(&frame))
+            .unwrap_or_else(|_| self.line.clone())
+    }
+}
+
+/// The line with the token of the login replaced, None if it is not a login with a token
+fn redact_token(line: &str) -> Option<String> {
+    let mut frame = serde_json::from_str::<serde_json::Value>(line).ok()?;
+    if frame.get("_ct")?.as_str()? != "login" {
+        return None;
+    }
+
+    let token = frame.get_mut("t")?;
+    *token = serde_json::Value::String(String::from(REDACTED_TOKEN));
+
+    serde_json::to_string(&frame).ok()
+}
+
+/// A line of an exported traffic file
+#[derive(Serialize)]
+struct ExportedFrame {
+    at: u64,
+    direction: &'static str,
+    size: usize,
+    /// The frame itself, or the line as a string if it is not valid json
+    frame: serde_json::Value,
+}
+
+/// [TrafficLog] keeps the latest frames that have gone over the connection while the traffic inspector is on
+///
+/// Turned on and off on the debug page, nothing is recorded while it is off.
+#[derive(Debug, Clone)]
+pub struct TrafficLog {
+    frames: MessageBuffer<TrafficFrame>,
+    /// The file the frames have been exported to last, or why they could not be
+    pub export_result: Option<Result<String, String>>,
+}
+
+impl Default for TrafficLog {
+    fn default() -> Self {
+        TrafficLog {
+            frames: MessageBuffer::with_capacity(MAX_FRAMES),
+            export_result: None,
+        }
+    }
+}
+
+impl TrafficLog {
+    pub fn push(&mut self, frame: TrafficFrame) {
+        self.frames.push(frame);
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Iterate from the newest to the oldest frame
+    pub fn frames(&self) -> impl Iterator<Item = &TrafficFrame> {
+        self.frames.iter()
+    }
+
+    /// Write the frames to a new json lines file in the working directory, the oldest first, returns its path
+    pub fn export(&self, now: u64) -> anyhow::Result<PathBuf> {
+        let path = std::env::current_dir()
+            .context("could not find the working directory")?
+            .join(format!("chat-traffic-{}.jsonl", now));
+        let file = File::create_new(&path)
+            .with_context(|| format!("could not create {}", path.display()))?;
+        let mut file = BufWriter::new(file);
+
+        let frames = self.frames.iter().collect::<Vec<_>>();
+        for frame in frames.into_iter().rev() {
+            let exported_frame = ExportedFrame {
+                at: frame.at,
+                direction: match frame.direction {
+                    FrameDirection::Inbound => "in",
+                    FrameDirection::Outbound => "out",
+                },
+                size: frame.size(),
+                frame: serde_json::from_str(&frame.line)
+                    .unwrap_or_else(|_| serde_json::Value::String(frame.line.clone())),
+            };
+            serde_json::to_writer(&mut file, &exported_frame)?;
+            writeln!(file)?;
+        }
+        file.flush()
+            .with_context(|| format!("could not write {}", path.display()))?;
+
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use comms::command::{LoginCommand, UserCommand};
+
+    use super::*;
+
+    #[test]
+    fn test_login_tokens_are_not_captured() {
+        let login = serde_json::to_string(&UserCommand::Login(LoginCommand {
+            username: None,
+            token: Some(String::from("bot_secret")),
+            guest: false,
+        }))
+        .unwrap();
+
+        let frame = TrafficFrame::capture(FrameDirection::Outbound, 1, &login);
+
+        assert!(!frame.line.contains("bot_secret"));
+        assert!(frame.line.contains(REDACTED_TOKEN));
+    }
+
+    #[test]
+    fn test_other_frames_are_captured_as_they_are() {
+        let line = r#"{"_ct":"send_message","r":"general","c":"t"}"#;
+
+        let frame = TrafficFrame::capture(FrameDirection::Outbound, 1, line);
+
+        assert_eq!(frame.line, line);
+    }
+}
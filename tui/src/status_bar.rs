@@ -0,0 +1,164 @@
+use std::{process::Stdio, str::FromStr, time::Duration};
+
+use ratatui::style::{Color, Modifier, Style};
+use tokio::sync::watch;
+
+use crate::notify_hook::shell_command;
+
+/// The segments shown if `CHAT_TUI_STATUS_BAR` is not set
+const DEFAULT_SEGMENTS: &str = "connection,latency,unread,clock";
+
+/// How often the command of the `command` segment is run
+const COMMAND_INTERVAL: Duration = Duration::from_secs(10);
+
+/// What a segment of the status bar shows
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SegmentKind {
+    /// The status of the connection to the server
+    Connection,
+    /// The round trip time to the server
+    Latency,
+    /// The number of rooms with unread messages
+    Unread,
+    /// The local time
+    Clock,
+    /// A fixed text, read from `CHAT_TUI_STATUS_TEXT`
+    Text,
+    /// The first line printed by a command, read from `CHAT_TUI_STATUS_COMMAND`
+    Command,
+}
+
+/// The names of the segments as written in `CHAT_TUI_STATUS_BAR`
+const SEGMENT_NAMES: [(&str, SegmentKind); 6] = [
+    ("connection", SegmentKind::Connection),
+    ("latency", SegmentKind::Latency),
+    ("unread", SegmentKind::Unread),
+    ("clock", SegmentKind::Clock),
+    ("text", SegmentKind::Text),
+    ("command", SegmentKind::Command),
+];
+
+impl SegmentKind {
+    fn parse(name: &str) -> Option<Self> {
+        SEGMENT_NAMES
+            .iter()
+            .find(|(segment_name, _)| *segment_name == name)
+            .map(|(_, kind)| *kind)
+    }
+
+    pub fn name(&self) -> &'static str {
+        SEGMENT_NAMES
+            .iter()
+            .find(|(_, kind)| kind == self)
+            .map(|(name, _)| *name)
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub kind: SegmentKind,
+    pub style: Style,
+}
+
+/// [StatusBar] is the line at the bottom of the chat page, made of the segments the user picks
+///
+/// Read from the `CHAT_TUI_STATUS_BAR` environment variable as a comma separated list of segments
+/// in the order they are shown, each optionally followed by its style, e.g. `clock@yellow+bold,unread`.
+/// Segments and styles which are not understood are ignored, an empty list hides the status bar.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StatusBar {
+    pub segments: Vec<Segment>,
+    /// The text of the `text` segment
+    pub text: Option<String>,
+    /// The shell command run for the `command` segment
+    pub command: Option<String>,
+}
+
+impl StatusBar {
+    pub fn from_env() -> Self {
+        let segments =
+            std::env::var("CHAT_TUI_STATUS_BAR").unwrap_or_else(|_| String::from(DEFAULT_SEGMENTS));
+        let non_empty_var = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+        };
+
+        StatusBar {
+            segments: Self::parse(&segments),
+            text: non_empty_var("CHAT_TUI_STATUS_TEXT"),
+            command: non_empty_var("CHAT_TUI_STATUS_COMMAND"),
+        }
+    }
+
+    fn parse(segments: &str) -> Vec<Segment> {
+        segments
+            .split(',')
+            .filter_map(|segment| {
+                let (name, style) = segment.split_once('@').unwrap_or((segment, ""));
+
+                Some(Segment {
+                    kind: SegmentKind::parse(name.trim())?,
+                    style: parse_style(style),
+                })
+            })
+            .collect()
+    }
+
+    pub fn has_segment(&self, kind: SegmentKind) -> bool {
+        self.segments.iter().any(|segment| segment.kind == kind)
+    }
+
+    /// The command to run for the `command` segment, None if the segment is not shown
+    pub fn segment_command(&self) -> Option<&str> {
+        self.command
+            .as_deref()
+            .filter(|_| self.has_segment(SegmentKind::Command))
+    }
+}
+
+/// Parses a style such as `yellow+bold`, colors are named as by ratatui, e.g. `lightblue` or `#ff8800`
+fn parse_style(style: &str) -> Style {
+    style
+        .split('+')
+        .map(str::trim)
+        .fold(Style::default(), |style, part| match part {
+            "bold" => style.add_modifier(Modifier::BOLD),
+            "dim" => style.add_modifier(Modifier::DIM),
+            "italic" => style.add_modifier(Modifier::ITALIC),
+            "underlined" => style.add_modifier(Modifier::UNDERLINED),
+            "reversed" => style.add_modifier(Modifier::REVERSED),
+            color => match Color::from_str(color) {
+                Ok(color) => style.fg(color),
+                Err(_) => style,
+            },
+        })
+}
+
+/// Runs the command of the `command` segment every few seconds, sending the first line it prints
+/// Stops once nobody receives the output anymore.
+pub async fn run_segment_command(command: String, output_tx: watch::Sender<Option<String>>) {
+    let mut interval = tokio::time::interval(COMMAND_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let output = shell_command(&command)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .await;
+        // a failing command leaves the segment empty, the terminal belongs to the user interface
+        let line = output.ok().and_then(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .next()
+                .map(|line| line.trim().to_string())
+        });
+
+        if output_tx.send(line).is_err() {
+            break;
+        }
+    }
+}
@@ -37,6 +37,17 @@ async fn terminate_by_unix_signal(mut terminator: Terminator) {
         .expect("failed to send interrupt signal");
 }
 
+#[cfg(windows)]
+async fn terminate_by_ctrl_c(mut terminator: Terminator) {
+    let mut ctrl_c = tokio::signal::windows::ctrl_c().expect("failed to create ctrl-c stream");
+
+    ctrl_c.recv().await;
+
+    terminator
+        .terminate(Interrupted::OsSigInt)
+        .expect("failed to send interrupt signal");
+}
+
 // create a broadcast channel for retrieving the application kill signal
 pub fn create_termination() -> (Terminator, broadcast::Receiver<Interrupted>) {
     let (tx, rx) = broadcast::channel(1);
@@ -44,6 +55,8 @@ pub fn create_termination() -> (Terminator, broadcast::Receiver<Interrupted>) {
 
     #[cfg(unix)]
     tokio::spawn(terminate_by_unix_signal(terminator.clone()));
+    #[cfg(windows)]
+    tokio::spawn(terminate_by_ctrl_c(terminator.clone()));
 
     (terminator, rx)
 }
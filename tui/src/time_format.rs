@@ -0,0 +1,229 @@
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, Local, NaiveDate,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::locale::Strings;
+
+/// The time of day on the 24-hour clock, e.g. `17:05`
+const CLOCK_24H: &str = "%H:%M";
+/// The time of day on the 12-hour clock, e.g. `5:05 PM`
+const CLOCK_12H: &str = "%-I:%M %p";
+/// How old a message may be for its time to be shown relative to now, the time of day is shown for older ones
+const RELATIVE_WINDOW_SECS: u64 = 24 * 60 * 60;
+
+/// The time zone the times are shown in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeZone {
+    /// The time zone of the system
+    #[default]
+    Local,
+    Utc,
+}
+
+impl TimeZone {
+    pub const ALL: [TimeZone; 2] = [TimeZone::Local, TimeZone::Utc];
+
+    fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|time_zone| time_zone.name() == name.trim().to_lowercase())
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimeZone::Local => "local",
+            TimeZone::Utc => "utc",
+        }
+    }
+}
+
+/// [TimeFormat] holds how the times of the messages, the days between them and the clock are shown
+///
+/// Read from the environment, the settings saved to the config file take precedence:
+/// - `CHAT_TUI_TIME_FORMAT` - `24h`, `12h` or a chrono format string such as `%H:%M:%S`, `24h` by default
+/// - `CHAT_TUI_TIME_ZONE` - `local` or `utc`, `local` by default
+/// - `CHAT_TUI_RELATIVE_TIMES` - `on` to show the times of the last day relative to now, e.g. `2m ago`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeFormat {
+    /// The chrono format string of a time of day
+    pub pattern: String,
+    pub time_zone: TimeZone,
+    /// Whether the times of the last day are shown as how long ago they were, refreshed every second
+    pub relative: bool,
+}
+
+impl Default for TimeFormat {
+    fn default() -> Self {
+        TimeFormat {
+            pattern: String::from(CLOCK_24H),
+            time_zone: TimeZone::Local,
+            relative: false,
+        }
+    }
+}
+
+impl TimeFormat {
+    pub fn from_env() -> Self {
+        let mut time_format = TimeFormat::default();
+
+        if let Some(pattern) = std::env::var("CHAT_TUI_TIME_FORMAT")
+            .ok()
+            .and_then(|pattern| Self::parse_pattern(&pattern))
+        {
+            time_format.pattern = pattern;
+        }
+        if let Some(time_zone) = std::env::var("CHAT_TUI_TIME_ZONE")
+            .ok()
+            .and_then(|name| TimeZone::parse(&name))
+        {
+            time_format.time_zone = time_zone;
+        }
+        time_format.relative = std::env::var("CHAT_TUI_RELATIVE_TIMES")
+            .is_ok_and(|value| matches!(value.to_lowercase().as_str(), "1" | "true" | "on"));
+
+        time_format
+    }
+
+    /// The format string of `24h`, `12h` or of a chrono format string, None if it is not a valid one
+    pub fn parse_pattern(value: &str) -> Option<String> {
+        match value.trim() {
+            "24h" => Some(String::from(CLOCK_24H)),
+            "12h" => Some(String::from(CLOCK_12H)),
+            // formatting with an invalid format string would panic
+            pattern
+                if !pattern.is_empty()
+                    && !StrftimeItems::new(pattern).any(|item| matches!(item, Item::Error)) =>
+            {
+                Some(String::from(pattern))
+            }
+            _ => None,
+        }
+    }
+
+    /// The time format with its format string checked, e.g. when it is read from the config file
+    /// A format string which is not valid is replaced by the default one, formatting with it would panic
+    pub fn validated(mut self) -> Self {
+        self.pattern =
+            Self::parse_pattern(&self.pattern).unwrap_or_else(|| String::from(CLOCK_24H));
+
+        self
+    }
+
+    /// The format string as the user would type it, `24h` and `12h` for the presets
+    pub fn pattern_name(&self) -> &str {
+        match self.pattern.as_str() {
+            CLOCK_24H => "24h",
+            CLOCK_12H => "12h",
+            pattern => pattern,
+        }
+    }
+
+    /// The time of a message sent at the timestamp in milliseconds, as the time of day or as how long ago it was
+    pub fn message_time(&self, timestamp: u64, now: u64, strings: &dyn Strings) -> String {
+        let age_secs = now.saturating_sub(timestamp) / 1000;
+        if self.relative && age_secs < RELATIVE_WINDOW_SECS {
+            return strings.time_ago(age_secs);
+        }
+
+        self.clock(timestamp)
+    }
+
+    /// The time of day at the timestamp in milliseconds, never relative, e.g. for the clock of the status bar
+    pub fn clock(&self, timestamp: u64) -> String {
+        let Some(at) = DateTime::from_timestamp_millis(timestamp as i64) else {
+            return String::new();
+        };
+
+        match self.time_zone {
+            TimeZone::Local => at.with_timezone(&Local).format(&self.pattern).to_string(),
+            TimeZone::Utc => at.format(&self.pattern).to_string(),
+        }
+    }
+
+    /// The day of the timestamp in milliseconds in the time zone of the times, e.g. to set the days apart
+    pub fn day(&self, timestamp: u64) -> Option<NaiveDate> {
+        let at = DateTime::from_timestamp_millis(timestamp as i64)?;
+
+        Some(match self.time_zone {
+            TimeZone::Local => at.with_timezone(&Local).date_naive(),
+            TimeZone::Utc => at.date_naive(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale::Locale;
+
+    /// 2025-01-01 00:00:00 UTC
+    const NEW_YEAR: u64 = 1735689600000;
+
+    fn utc(pattern: &str) -> TimeFormat {
+        TimeFormat {
+            pattern: TimeFormat::parse_pattern(pattern).unwrap(),
+            time_zone: TimeZone::Utc,
+            relative: false,
+        }
+    }
+
+    #[test]
+    fn test_presets_and_format_strings_are_parsed() {
+        assert_eq!(utc("24h").clock(NEW_YEAR + 13 * 3600 * 1000), "13:00");
+        assert_eq!(utc("12h").clock(NEW_YEAR + 13 * 3600 * 1000), "1:00 PM");
+        assert_eq!(utc("%H:%M:%S").clock(NEW_YEAR + 5000), "00:00:05");
+        assert_eq!(utc("12h").pattern_name(), "12h");
+
+        assert_eq!(TimeFormat::parse_pattern("%Q"), None);
+        assert_eq!(TimeFormat::parse_pattern(" "), None);
+    }
+
+    #[test]
+    fn test_invalid_format_strings_are_replaced_by_the_default_one() {
+        let mut time_format = utc("%H:%M:%S");
+        assert_eq!(time_format.clone().validated(), time_format);
+
+        time_format.pattern = String::from("%Q");
+        let time_format = time_format.validated();
+        assert_eq!(time_format.pattern, TimeFormat::default().pattern);
+        assert_eq!(time_format.clock(NEW_YEAR), "00:00");
+    }
+
+    #[test]
+    fn test_recent_times_are_relative_if_turned_on() {
+        let strings = Locale::English.strings();
+        let mut time_format = utc("24h");
+        let sent_at = NEW_YEAR + 12 * 3600 * 1000;
+
+        assert_eq!(
+            time_format.message_time(sent_at, sent_at + 120_000, strings),
+            "12:00"
+        );
+
+        time_format.relative = true;
+        assert_eq!(
+            time_format.message_time(sent_at, sent_at + 120_000, strings),
+            "2m ago"
+        );
+        assert_eq!(
+            time_format.message_time(sent_at, sent_at + 2 * 24 * 3600 * 1000, strings),
+            "12:00"
+        );
+    }
+
+    #[test]
+    fn test_days_follow_the_time_zone() {
+        assert_eq!(
+            utc("24h").day(NEW_YEAR - 1),
+            NaiveDate::from_ymd_opt(2024, 12, 31)
+        );
+        assert_eq!(
+            utc("24h").day(NEW_YEAR),
+            NaiveDate::from_ymd_opt(2025, 1, 1)
+        );
+    }
+}
@@ -1,14 +1,17 @@
 use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use ratatui::prelude::Position;
 use ratatui::{
     prelude::Rect,
     style::{Color, Style, Stylize},
-    widgets::{Block, Borders, Paragraph},
+    widgets::Paragraph,
     Frame,
 };
-use ratatui::prelude::Position;
 use tokio::sync::mpsc::UnboundedSender;
 
-use crate::state_store::{action::Action, State};
+use crate::{
+    accessibility::Accessibility,
+    state_store::{action::Action, State},
+};
 
 use super::{Component, ComponentRender};
 
@@ -29,6 +32,22 @@ impl InputBox {
         self.cursor_position = self.text.len();
     }
 
+    pub fn cursor_position(&self) -> usize {
+        self.cursor_position
+    }
+
+    /// Replaces the characters from the given index up to the cursor, e.g. to complete a word
+    pub fn replace_before_cursor(&mut self, start: usize, replacement: &str) {
+        let before_start = self.text.chars().take(start);
+        let after_cursor = self.text.chars().skip(self.cursor_position);
+
+        self.text = before_start
+            .chain(replacement.chars())
+            .chain(after_cursor)
+            .collect();
+        self.cursor_position = start + replacement.chars().count();
+    }
+
     pub fn reset(&mut self) {
         self.cursor_position = 0;
         self.text.clear();
@@ -129,18 +148,25 @@ pub struct RenderProps {
     pub area: Rect,
     pub border_color: Color,
     pub show_cursor: bool,
+    pub accessibility: Accessibility,
 }
 
 impl ComponentRender<RenderProps> for InputBox {
     fn render(&self, frame: &mut Frame, props: RenderProps) {
+        let block = props
+            .accessibility
+            .block()
+            .fg(props.border_color)
+            .title(props.title);
+        let text_area = block.inner(props.area);
+
         let input = Paragraph::new(self.text.as_str())
-            .style(Style::default().fg(Color::Yellow))
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .fg(props.border_color)
-                    .title(props.title),
-            );
+            .style(if props.accessibility.high_contrast {
+                Style::default()
+            } else {
+                Style::default().fg(Color::Yellow)
+            })
+            .block(block);
         frame.render_widget(input, props.area);
 
         // Cursor is hidden by default, so we need to make it visible if the input box is selected
@@ -150,9 +176,9 @@ impl ComponentRender<RenderProps> for InputBox {
             frame.set_cursor_position(Position::new(
                 // Draw the cursor at the current position in the input field.
                 // This position is can be controlled via the left and right arrow key
-                props.area.x + self.cursor_position as u16 + 1,
-                // Move one line down, from the border to the input line
-                props.area.y + 1,
+                text_area.x + self.cursor_position as u16,
+                // The input line is right below the border or the title
+                text_area.y,
             ))
         }
     }
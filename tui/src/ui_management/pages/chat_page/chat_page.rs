@@ -1,22 +1,40 @@
 use std::collections::HashMap;
 
+use chrono::NaiveDate;
+
 use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use ratatui::{prelude::*, widgets::*, Frame};
 use tokio::sync::mpsc::UnboundedSender;
 
-use crate::state_store::{action::Action, MessageBoxItem, RoomData, ServerConnectionStatus, State};
+use crate::{
+    accessibility::Accessibility,
+    keymap::Keymap,
+    locale::Locale,
+    notice_filter::NoticeFilter,
+    presentation::Presenter,
+    profanity::ProfanityFilter,
+    state_store::{
+        action::Action, MessageBoxItem, Notice, RoomData, ServerConnectionStatus, State,
+    },
+    status_bar::{SegmentKind, StatusBar},
+    time_format::TimeFormat,
+};
 
 use super::{
     components::{
         message_input_box::{self, MessageInputBox},
         room_list::{self, RoomList},
     },
+    message_selection::MessageSelection,
     section::{
         usage::{widget_usage_to_text, HasUsageInfo, UsageInfo, UsageInfoLine},
         SectionActivation,
     },
 };
-use crate::ui_management::components::{Component, ComponentRender};
+use crate::ui_management::{
+    components::{Component, ComponentRender},
+    pages::{Navigation, Page, PageKind},
+};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Section {
@@ -50,6 +68,8 @@ impl TryFrom<usize> for Section {
 struct Props {
     /// The logged-in user
     user_id: String,
+    /// The number of sessions the user has open, including this one
+    session_count: usize,
     /// The currently active room
     active_room: Option<String>,
     /// The timer for the chat page
@@ -58,22 +78,87 @@ struct Props {
     room_data_map: HashMap<String, RoomData>,
     /// Connection status for the current connection
     connection_status: ServerConnectionStatus,
+    accessibility: Accessibility,
+    keymap: Keymap,
+    locale: Locale,
+    status_bar: StatusBar,
+    profanity_filter: ProfanityFilter,
+    notice_filter: NoticeFilter,
+    /// How many seconds apart the consecutive messages of a user may be to be grouped, 0 for never
+    grouping_window_secs: u64,
+    time_format: TimeFormat,
+    /// The round trip time to the server in milliseconds, once measured
+    latency_ms: Option<u64>,
+    status_command_output: Option<String>,
 }
 
 impl From<&State> for Props {
     fn from(state: &State) -> Self {
         Props {
             user_id: state.user_id.clone(),
+            session_count: state.session_count,
             active_room: state.active_room.clone(),
             timer: state.timer,
             room_data_map: state.room_data_map.clone(),
             connection_status: state.server_connection_status.clone(),
+            accessibility: state.accessibility,
+            keymap: state.keymap,
+            locale: state.locale,
+            status_bar: state.status_bar.clone(),
+            profanity_filter: state.profanity_filter.clone(),
+            notice_filter: state.notice_filter.clone(),
+            grouping_window_secs: state.grouping_window_secs,
+            time_format: state.time_format.clone(),
+            latency_ms: state.latency_ms,
+            status_command_output: state.status_command_output.clone(),
         }
     }
 }
 
 const DEFAULT_HOVERED_SECTION: Section = Section::MessageInput;
 
+/// What a line or a few of the message pane show
+enum PaneEntry<'a> {
+    /// An item of the room at its position in the buffer, the newest one being 0
+    Item {
+        position: usize,
+        item: &'a MessageBoxItem,
+        /// Whether the item is a message continuing the one of the same author before it
+        is_grouped: bool,
+    },
+    /// A run of joins and leaves hidden in the room
+    HiddenParticipation { count: usize },
+    /// Sets the messages of a day apart from the older ones
+    DayDivider { day: NaiveDate },
+}
+
+/// The day the message was sent on in the time zone the times are shown in, None for the other items
+fn sent_on(item: &MessageBoxItem, time_format: &TimeFormat) -> Option<NaiveDate> {
+    let MessageBoxItem::Message { timestamp, .. } = item else {
+        return None;
+    };
+
+    time_format.day(*timestamp)
+}
+
+/// The current time in milliseconds since the epoch, as the timestamps of the messages are given
+fn now_ms() -> u64 {
+    chrono::Utc::now().timestamp_millis() as u64
+}
+
+/// The author of the message shown by the entry and when it was sent, None for the other entries
+fn sent_by<'a>(entry: &PaneEntry<'a>) -> Option<(&'a str, u64)> {
+    match entry {
+        PaneEntry::Item {
+            item: MessageBoxItem::Message {
+                user_id, timestamp, ..
+            },
+            ..
+        } => Some((user_id.as_str(), *timestamp)),
+        _ => None,
+    }
+}
+
 /// ChatPage handles the UI and the state of the chat page
 pub struct ChatPage {
     /// Action sender
@@ -85,6 +170,8 @@ pub struct ChatPage {
     pub active_section: Option<Section>,
     /// Section that is currently hovered
     pub last_hovered_section: Section,
+    /// The message selected in the message pane, while no section takes the keys
+    pub message_selection: Option<MessageSelection>,
     // Child Components
     /// The room list widget that handles the listing of the rooms
     pub room_list: RoomList,
@@ -145,6 +232,39 @@ impl ChatPage {
         }
     }
 
+    fn active_room_data(&self) -> Option<&RoomData> {
+        self.props
+            .active_room
+            .as_ref()
+            .and_then(|active_room| self.get_room_data(active_room))
+    }
+
+    /// Move the selection in the message pane, returns false if the key is not one of the selection mode
+    fn handle_selection_key_event(&mut self, key: KeyEvent) -> bool {
+        let Some(mut selection) = self.message_selection.take() else {
+            return false;
+        };
+
+        // j and k move the selection whichever keymap the user has picked
+        match self.props.keymap.translate(key).code {
+            KeyCode::Esc => return true,
+            KeyCode::Up | KeyCode::Char('k') => {
+                if let Some(room_data) = self.active_room_data() {
+                    selection.select_older(room_data);
+                }
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                if let Some(room_data) = self.active_room_data() {
+                    selection.select_newer(room_data);
+                }
+            }
+            _ => {}
+        }
+        self.message_selection = Some(selection);
+
+        true
+    }
+
     fn disable_section(&mut self, section: &Section) {
         self.get_section_activation_for_section(section)
             .deactivate();
@@ -165,6 +285,7 @@ impl Component for ChatPage {
             // internal component state
             active_section: None,
             last_hovered_section: DEFAULT_HOVERED_SECTION,
+            message_selection: None,
             // child components
             room_list: RoomList::new(state, action_tx.clone()),
             message_input_box: MessageInputBox::new(state, action_tx),
@@ -178,6 +299,10 @@ impl Component for ChatPage {
     {
         ChatPage {
             props: Props::from(state),
+            // the selection is left once another room is opened
+            message_selection: self
+                .message_selection
+                .filter(|selection| state.active_room.as_deref() == Some(selection.room())),
             // propagate the update to the child components
             room_list: self.room_list.move_with_state(state),
             message_input_box: self.message_input_box.move_with_state(state),
@@ -194,10 +319,27 @@ impl Component for ChatPage {
             return;
         }
 
+        // function keys run the macros of the user, whichever section is active
+        if let KeyCode::F(number) = key.code {
+            if self.message_input_box.send_key_macro(number) {
+                return;
+            }
+        }
+
+        let is_exit =
+            key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+        if !is_exit && self.handle_selection_key_event(key) {
+            return;
+        }
+
         let active_section = self.active_section.clone();
 
         match active_section {
-            None => match key.code {
+            None => match self.props.keymap.translate(key).code {
+                KeyCode::Esc => {
+                    self.message_selection =
+                        self.active_room_data().and_then(MessageSelection::newest);
+                }
                 KeyCode::Enter => {
                     let last_hovered_section = self.last_hovered_section.clone();
 
@@ -221,9 +363,12 @@ impl Component for ChatPage {
 
                 // disable the section according to the action taken
                 // the section is disabled when escape is pressed
-                // or when enter is pressed on the room list
+                // or when enter is pressed on a room of the room list
                 match section {
-                    Section::RoomList if key.code == KeyCode::Enter => {
+                    Section::RoomList
+                        if key.code == KeyCode::Enter
+                            && !self.room_list.has_category_selected() =>
+                    {
                         self.disable_section(&section)
                     }
                     _ if key.code == KeyCode::Esc => self.disable_section(&section),
@@ -234,15 +379,58 @@ impl Component for ChatPage {
     }
 }
 
-const NO_ROOM_SELECTED_MESSAGE: &str = "Join at least one room to start chatting!";
+impl Page for ChatPage {
+    fn handle_page_key_event(&mut self, key: KeyEvent) -> Navigation {
+        // the other pages are opened while no section takes the keys, like quitting
+        if self.active_section.is_none()
+            && self.message_selection.is_none()
+            && key.kind == KeyEventKind::Press
+        {
+            let page = match key.code {
+                KeyCode::Char('b') => Some(PageKind::RoomBrowser),
+                KeyCode::Char('s') => Some(PageKind::Settings),
+                KeyCode::Char('d') => Some(PageKind::Debug),
+                _ => None,
+            };
+            if let Some(page) = page {
+                return Navigation::Open(page);
+            }
+        }
+
+        self.handle_key_event(key);
+
+        Navigation::Stay
+    }
+}
+
+/// The number of lines lists may take up in the linear layout, longer lists scroll
+const LINEAR_LIST_HEIGHT: usize = 8;
+
+/// Terminals narrower than this get the compact layout, the sidebars would leave too little room for the chat
+const COMPACT_LAYOUT_WIDTH: u16 = 100;
 
 fn calculate_list_offset(height: u16, items_len: usize) -> usize {
-    // go back by (container height + 2 for borders) to get the offset
-    items_len.saturating_sub(height as usize - 2)
+    // go back by the height of the list without its borders to get the offset
+    items_len.saturating_sub(height as usize)
 }
 
-impl ComponentRender<()> for ChatPage {
-    fn render(&self, frame: &mut Frame, _props: ()) {
+/// The areas of the chat page the widgets are rendered to
+/// Sections left out of a layout are given an empty area
+struct ChatPageAreas {
+    room_list: Rect,
+    user_info: Rect,
+    highlight: Rect,
+    messages: Rect,
+    input: Rect,
+    room_users: Rect,
+    usage: Rect,
+    /// Whether the rooms are shown as a line of tabs instead of a list
+    room_tabs: bool,
+}
+
+impl ChatPage {
+    /// Three columns, the rooms on the left, the chat in the middle and the room users on the right
+    fn regular_layout(area: Rect) -> ChatPageAreas {
         let [left, middle, right] = *Layout::default()
             .direction(Direction::Horizontal)
             .constraints(
@@ -253,12 +441,12 @@ impl ComponentRender<()> for ChatPage {
                 ]
                 .as_ref(),
             )
-            .split(frame.area())
+            .split(area)
         else {
             panic!("The main layout should have 3 chunks")
         };
 
-        let [container_room_list, container_user_info] = *Layout::default()
+        let [room_list, user_info] = *Layout::default()
             .direction(Direction::Vertical)
             .constraints([Constraint::Min(1), Constraint::Length(6)].as_ref())
             .split(left)
@@ -266,27 +454,7 @@ impl ComponentRender<()> for ChatPage {
             panic!("The left layout should have 2 chunks")
         };
 
-        self.room_list.render(
-            frame,
-            room_list::RenderProps {
-                border_color: self.calculate_border_color(Section::RoomList),
-                area: container_room_list,
-            },
-        );
-
-        let user_info = Paragraph::new(Text::from(vec![
-            Line::from(format!("User: @{}", self.props.user_id)),
-            Line::from(format!("Chatting for: {} secs", self.props.timer)),
-            Line::from(format!("Server: {}", self.props.connection_status)),
-        ])).wrap(Wrap { trim: false })
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title("User Information"),
-        );
-        frame.render_widget(user_info, container_user_info);
-
-        let [container_highlight, container_messages, container_input] = *Layout::default()
+        let [highlight, messages, input] = *Layout::default()
             .direction(Direction::Vertical)
             .constraints(
                 [
@@ -301,84 +469,434 @@ impl ComponentRender<()> for ChatPage {
             panic!("The middle layout should have 3 chunks")
         };
 
+        let [room_users, usage] = *Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(10)].as_ref())
+            .split(right)
+        else {
+            panic!("The right layout should have 2 chunks")
+        };
+
+        ChatPageAreas {
+            room_list,
+            user_info,
+            highlight,
+            messages,
+            input,
+            room_users,
+            usage,
+            room_tabs: false,
+        }
+    }
+
+    /// The chat alone, below a tab strip of the rooms, for terminals too narrow for the sidebars
+    fn compact_layout(area: Rect) -> ChatPageAreas {
+        let [room_list, highlight, messages, input] = *Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(3),
+                    Constraint::Length(3),
+                    Constraint::Min(1),
+                    Constraint::Length(3),
+                ]
+                .as_ref(),
+            )
+            .split(area)
+        else {
+            panic!("The compact layout should have 4 chunks")
+        };
+
+        ChatPageAreas {
+            room_list,
+            user_info: Rect::default(),
+            highlight,
+            messages,
+            input,
+            room_users: Rect::default(),
+            usage: Rect::default(),
+            room_tabs: true,
+        }
+    }
+
+    /// A single column of borderless sections, read from top to bottom by screen readers
+    fn linear_layout(&self, area: Rect) -> ChatPageAreas {
+        let room_users_len = self
+            .props
+            .active_room
+            .as_ref()
+            .and_then(|active_room| self.get_room_data(active_room))
+            .map(|room_data| room_data.users.len())
+            .unwrap_or(0);
+        let usage_info = self.usage_info();
+        // every section has a title line above its content
+        let list_height = |items_len: usize| {
+            Constraint::Length(items_len.clamp(1, LINEAR_LIST_HEIGHT) as u16 + 1)
+        };
+
+        let [user_info, highlight, room_list, messages, input, room_users, usage] =
+            *Layout::default()
+                .direction(Direction::Vertical)
+                .constraints(
+                    [
+                        Constraint::Length(4),
+                        Constraint::Length(2),
+                        list_height(self.props.room_data_map.len()),
+                        Constraint::Min(2),
+                        Constraint::Length(2),
+                        list_height(room_users_len),
+                        Constraint::Length(
+                            (usage_info.lines.len() + usage_info.description.iter().count()) as u16
+                                + 1,
+                        ),
+                    ]
+                    .as_ref(),
+                )
+                .split(area)
+        else {
+            panic!("The linear layout should have 7 chunks")
+        };
+
+        ChatPageAreas {
+            room_list,
+            user_info,
+            highlight,
+            messages,
+            input,
+            room_users,
+            usage,
+            room_tabs: false,
+        }
+    }
+
+    /// The line at the bottom of the page, made of the segments picked by the user in their order
+    fn status_bar_line(&self, presenter: &Presenter) -> Line<'static> {
+        let strings = self.props.locale.strings();
+        let separator = if self.props.accessibility.ascii {
+            " | "
+        } else {
+            " │ "
+        };
+
+        let mut spans = Vec::new();
+        for segment in self.props.status_bar.segments.iter() {
+            let text = match segment.kind {
+                SegmentKind::Connection => {
+                    presenter.connection_status(&self.props.connection_status)
+                }
+                SegmentKind::Latency => strings.latency(self.props.latency_ms),
+                SegmentKind::Unread => strings.unread_rooms(
+                    self.props
+                        .room_data_map
+                        .values()
+                        .filter(|room_data| room_data.has_unread)
+                        .count(),
+                ),
+                SegmentKind::Clock => self.props.time_format.clock(now_ms()),
+                SegmentKind::Text => self.props.status_bar.text.clone().unwrap_or_default(),
+                SegmentKind::Command => {
+                    self.props.status_command_output.clone().unwrap_or_default()
+                }
+            };
+            // segments without anything to show yet take no space
+            if text.is_empty() {
+                continue;
+            }
+
+            if !spans.is_empty() {
+                spans.push(Span::raw(separator));
+            }
+            spans.push(Span::styled(text, segment.style));
+        }
+
+        Line::from(spans)
+    }
+
+    /// The items of the room shown in a message pane of the given height, from the oldest to the newest
+    ///
+    /// The latest items are shown, unless the user has selected an older message, which is then kept in view.
+    fn message_pane_texts(
+        &self,
+        presenter: &Presenter,
+        room_data: &RoomData,
+        height: usize,
+    ) -> Vec<Text<'static>> {
+        let selected_position = self
+            .message_selection
+            .as_ref()
+            .and_then(|selection| selection.position(room_data));
+        let entries = self.pane_entries(room_data);
+        // relative times are worked out anew with every render, which the ticker brings about every second
+        let now = now_ms();
+        let today = self.props.time_format.day(now);
+        let present = |entry: &PaneEntry, names_author: bool| match entry {
+            PaneEntry::Item {
+                position,
+                item,
+                is_grouped,
+            } => {
+                let text = presenter.message_box_item(
+                    item,
+                    &self.props.profanity_filter,
+                    *is_grouped && !names_author,
+                );
+                match sent_by(entry) {
+                    Some((_, timestamp)) if selected_position == Some(*position) => {
+                        presenter.selected(text, timestamp, &self.props.time_format, now)
+                    }
+                    _ => text,
+                }
+            }
+            PaneEntry::HiddenParticipation { count } => presenter.hidden_participation(*count),
+            PaneEntry::DayDivider { day } => presenter.day_divider(*day, today),
+        };
+
+        // go back from the given entry until the pane is full, notifications may span several lines
+        let window = |offset: usize, lines: &mut usize| {
+            let mut texts = entries
+                .iter()
+                .skip(offset)
+                .map(|entry| present(entry, false))
+                .take_while(|text| {
+                    // the first entry is always shown, even if it is cut off
+                    let fits = *lines == 0 || *lines + text.height() <= height;
+                    *lines += text.height();
+                    fits
+                })
+                .collect::<Vec<_>>();
+            // the oldest message shown names its author, even if the start of its group is out of view
+            if let Some(oldest) = texts.len().checked_sub(1) {
+                texts[oldest] = present(&entries[offset + oldest], true);
+            }
+
+            texts
+        };
+
+        let mut lines = 0;
+        let mut texts = window(0, &mut lines);
+        let selected_entry = selected_position.and_then(|selected_position| {
+            entries.iter().position(|entry| {
+                matches!(entry, PaneEntry::Item { position, .. } if *position == selected_position)
+            })
+        });
+        if let Some(selected_entry) = selected_entry.filter(|entry| *entry >= texts.len()) {
+            // scrolled back for the selected message to be the newest one shown, as far as older ones fill the pane
+            lines = 0;
+            texts = window(selected_entry, &mut lines);
+            for entry in entries[..selected_entry].iter().rev() {
+                let text = present(entry, false);
+                if lines + text.height() > height {
+                    break;
+                }
+                lines += text.height();
+                texts.insert(0, text);
+            }
+        }
+        texts.reverse();
+
+        texts
+    }
+
+    /// The items of the room left to show from the newest to the oldest, without the hidden notices
+    /// A run of hidden joins and leaves is counted in a single entry, the other hidden notices leave no trace.
+    /// Messages sent on different days are set apart by a divider with the day of the newer ones.
+    /// A message is grouped with the one before it if both are from the same user and sent within the grouping window.
+    fn pane_entries<'a>(&self, room_data: &'a RoomData) -> Vec<PaneEntry<'a>> {
+        let mut entries = Vec::new();
+        // the day of the newer message seen last
+        let mut newer_day = None;
+        for (position, item) in room_data.messages.iter().enumerate() {
+            if let Some(day) = sent_on(item, &self.props.time_format) {
+                if let Some(newer_day) = newer_day.filter(|newer_day| *newer_day != day) {
+                    entries.push(PaneEntry::DayDivider { day: newer_day });
+                }
+                newer_day = Some(day);
+            }
+
+            if !self.props.notice_filter.is_hidden(&room_data.name, item) {
+                entries.push(PaneEntry::Item {
+                    position,
+                    item,
+                    is_grouped: false,
+                });
+                continue;
+            }
+            if !matches!(
+                item,
+                MessageBoxItem::Notification(Notice::Participation { .. })
+            ) {
+                continue;
+            }
+
+            match entries.last_mut() {
+                Some(PaneEntry::HiddenParticipation { count }) => *count += 1,
+                _ => entries.push(PaneEntry::HiddenParticipation { count: 1 }),
+            }
+        }
+
+        // anything between two messages, e.g. a notice or a day divider, starts a new group
+        let grouping_window_ms = self.props.grouping_window_secs.saturating_mul(1000);
+        for newer in 0..entries.len().saturating_sub(1) {
+            let continues_older = match (sent_by(&entries[newer]), sent_by(&entries[newer + 1])) {
+                (Some((user_id, sent_at)), Some((older_user_id, older_sent_at))) => {
+                    user_id == older_user_id
+                        && sent_at.saturating_sub(older_sent_at) < grouping_window_ms
+                }
+                _ => false,
+            };
+            if let PaneEntry::Item { is_grouped, .. } = &mut entries[newer] {
+                *is_grouped = continues_older;
+            }
+        }
+
+        entries
+    }
+
+    /// Tells whether the section is active or hovered in text, for users who can not tell the border colors apart
+    fn section_marker(&self, section: Section) -> &'static str {
+        if !self.props.accessibility.high_contrast {
+            return "";
+        }
+
+        let strings = self.props.locale.strings();
+        match (self.active_section.as_ref(), &self.last_hovered_section) {
+            (Some(active_section), _) if active_section.eq(&section) => {
+                strings.active_section_marker()
+            }
+            (_, last_hovered_section) if last_hovered_section.eq(&section) => {
+                strings.selected_section_marker()
+            }
+            _ => "",
+        }
+    }
+}
+
+impl ComponentRender<()> for ChatPage {
+    fn render(&self, frame: &mut Frame, _props: ()) {
+        let accessibility = self.props.accessibility;
+        let strings = self.props.locale.strings();
+        let presenter = Presenter::new(self.props.locale, accessibility);
+        let status_bar_height = if self.props.status_bar.segments.is_empty() {
+            0
+        } else {
+            1
+        };
+        let [page, status_bar] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(status_bar_height)])
+                .areas(frame.area());
+
+        // laid out anew on every render, so resizing the terminal switches between the layouts right away
+        let areas = if accessibility.linear {
+            self.linear_layout(page)
+        } else if page.width < COMPACT_LAYOUT_WIDTH {
+            Self::compact_layout(page)
+        } else {
+            Self::regular_layout(page)
+        };
+
+        frame.render_widget(Paragraph::new(self.status_bar_line(&presenter)), status_bar);
+
+        self.room_list.render(
+            frame,
+            room_list::RenderProps {
+                border_color: self.calculate_border_color(Section::RoomList),
+                area: areas.room_list,
+                title_marker: self.section_marker(Section::RoomList),
+                as_tabs: areas.room_tabs,
+            },
+        );
+
+        let user_info = Paragraph::new(Text::from(vec![
+            Line::from(strings.user(&self.props.user_id, self.props.session_count)),
+            Line::from(strings.chatting_for(self.props.timer)),
+            Line::from(strings.server(&presenter.connection_status(&self.props.connection_status))),
+        ]))
+        .wrap(Wrap { trim: false })
+        .block(
+            accessibility
+                .block()
+                .title(strings.user_information_title()),
+        );
+        frame.render_widget(user_info, areas.user_info);
+
         let top_line = if let Some(room_data) = self
             .props
             .active_room
             .as_ref()
             .and_then(|active_room| self.get_room_data(active_room))
         {
-            Line::from(vec![
-                "on ".into(),
+            let [before_name, before_description] = strings.active_room_parts();
+            let mut spans = vec![
+                before_name.into(),
                 Span::from(format!("#{}", room_data.name)).bold(),
-                " for ".into(),
+                before_description.into(),
                 Span::from(format!(r#""{}""#, room_data.description)).italic(),
-            ])
+            ];
+            if room_data.encrypted {
+                spans.push(Span::from(
+                    strings.encryption_status(room_data.has_room_key),
+                ));
+            }
+            Line::from(spans)
         } else {
-            Line::from(NO_ROOM_SELECTED_MESSAGE)
+            Line::from(strings.no_room_selected())
         };
         let text = Text::from(top_line);
 
-        let help_message = Paragraph::new(text).block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title("Active Room Information"),
-        );
-        frame.render_widget(help_message, container_highlight);
+        let help_message =
+            Paragraph::new(text).block(accessibility.block().title(strings.active_room_title()));
+        frame.render_widget(help_message, areas.highlight);
 
+        let history_progress = self
+            .props
+            .active_room
+            .as_ref()
+            .and_then(|active_room| self.get_room_data(active_room))
+            .and_then(|room_data| room_data.history_progress);
+        let messages_title = match history_progress {
+            Some((received_count, total_count)) => {
+                strings.messages_loading_title(received_count, total_count)
+            }
+            None => String::from(strings.messages_title()),
+        };
+        let mut messages_block = accessibility.block().title(messages_title);
+        if self.message_selection.is_some() {
+            messages_block = messages_block.border_style(Style::new().fg(Color::Yellow));
+        }
         let messages = if let Some(active_room) = self.props.active_room.as_ref() {
             self.get_room_data(active_room)
                 .map(|room_data| {
-                    let message_offset =
-                        calculate_list_offset(container_messages.height, room_data.messages.len());
-
-                    room_data
-                        .messages
-                        .asc_iter()
-                        .skip(message_offset)
-                        .map(|mbi| {
-                            let line = match mbi {
-                                MessageBoxItem::Message { user_id, content } => {
-                                    Line::from(Span::raw(format!("@{}: {}", user_id, content)))
-                                }
-                                MessageBoxItem::Notification(content) => {
-                                    Line::from(Span::raw(content.clone()).italic())
-                                }
-                            };
-
-                            ListItem::new(line)
-                        })
+                    let height = messages_block.inner(areas.messages).height as usize;
+
+                    self.message_pane_texts(&presenter, room_data, height)
+                        .into_iter()
+                        .map(ListItem::new)
                         .collect::<Vec<ListItem>>()
                 })
                 .unwrap_or_default()
         } else {
-            vec![ListItem::new(Line::from(NO_ROOM_SELECTED_MESSAGE))]
+            vec![ListItem::new(Line::from(strings.no_room_selected()))]
         };
 
-        let messages =
-            List::new(messages).block(Block::default().borders(Borders::ALL).title("Messages"));
-        frame.render_widget(messages, container_messages);
+        let messages = List::new(messages).block(messages_block);
+        frame.render_widget(messages, areas.messages);
 
         self.message_input_box.render(
             frame,
             message_input_box::RenderProps {
                 border_color: self.calculate_border_color(Section::MessageInput),
-                area: container_input,
+                area: areas.input,
                 show_cursor: self
                     .active_section
                     .as_ref()
                     .map(|active_section| active_section.eq(&Section::MessageInput))
                     .unwrap_or(false),
+                title_marker: self.section_marker(Section::MessageInput),
             },
         );
 
-        let [container_room_users, container_usage] = *Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([Constraint::Min(1), Constraint::Length(10)].as_ref())
-            .split(right)
-        else {
-            panic!("The left layout should have 2 chunks")
-        };
-
+        let room_users_block = accessibility.block();
+        let room_users_height = room_users_block.inner(areas.room_users).height;
         let (room_users_list_items, room_users_len) = self
             .props
             .active_room
@@ -386,8 +904,7 @@ impl ComponentRender<()> for ChatPage {
             .and_then(|active_room| {
                 self.get_room_data(active_room).map(|room_data| {
                     let room_users_len = room_data.users.len();
-                    let users_offset =
-                        calculate_list_offset(container_room_users.height, room_users_len);
+                    let users_offset = calculate_list_offset(room_users_height, room_users_len);
 
                     (
                         room_data
@@ -395,7 +912,11 @@ impl ComponentRender<()> for ChatPage {
                             .iter()
                             .skip(users_offset)
                             .map(|user_id| {
-                                ListItem::new(Line::from(Span::raw(format!("@{user_id}"))))
+                                ListItem::new(presenter.room_user(
+                                    user_id,
+                                    room_data.away_users.contains(user_id),
+                                    room_data.status_texts.get(user_id).map(String::as_str),
+                                ))
                             })
                             .collect::<Vec<ListItem<'_>>>(),
                         room_users_len,
@@ -404,26 +925,39 @@ impl ComponentRender<()> for ChatPage {
             })
             .unwrap_or_else(|| (vec![], 0));
 
-        let room_users_list = List::new(room_users_list_items).block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title(format!("Room Users ({})", room_users_len)),
-        );
+        let room_users_list = List::new(room_users_list_items)
+            .block(room_users_block.title(strings.room_users_title(room_users_len)));
 
-        frame.render_widget(room_users_list, container_room_users);
+        frame.render_widget(room_users_list, areas.room_users);
 
-        let mut usage_text: Text = widget_usage_to_text(self.usage_info());
+        let mut usage_text: Text = widget_usage_to_text(self.usage_info(), &accessibility);
         usage_text = usage_text.patch_style(Style::default());
         let usage = Paragraph::new(usage_text)
             .wrap(Wrap { trim: true })
-            .block(Block::default().borders(Borders::ALL).title("Usage"));
-        frame.render_widget(usage, container_usage);
+            .block(accessibility.block().title(strings.usage_title()));
+        frame.render_widget(usage, areas.usage);
     }
 }
 
 impl HasUsageInfo for ChatPage {
     fn usage_info(&self) -> UsageInfo {
-        if let Some(section) = self.active_section.as_ref() {
+        let strings = self.props.locale.strings();
+
+        if self.message_selection.is_some() {
+            UsageInfo {
+                description: Some(strings.select_message().into()),
+                lines: vec![
+                    UsageInfoLine {
+                        keys: vec!["Esc".into()],
+                        description: strings.to_cancel().into(),
+                    },
+                    UsageInfoLine {
+                        keys: vec!["k".into(), "j".into()],
+                        description: strings.to_navigate().into(),
+                    },
+                ],
+            }
+        } else if let Some(section) = self.active_section.as_ref() {
             let handler: &dyn HasUsageInfo = match section {
                 Section::RoomList => &self.room_list,
                 Section::MessageInput => &self.message_input_box,
@@ -432,24 +966,39 @@ impl HasUsageInfo for ChatPage {
             handler.usage_info()
         } else {
             UsageInfo {
-                description: Some("Select a widget".into()),
+                description: Some(strings.select_widget().into()),
                 lines: vec![
                     UsageInfoLine {
                         keys: vec!["q".into()],
-                        description: "to exit".into(),
+                        description: strings.to_exit().into(),
                     },
                     UsageInfoLine {
                         keys: vec!["←".into(), "→".into()],
-                        description: "to hover widgets".into(),
+                        description: strings.to_hover_widgets().into(),
                     },
                     UsageInfoLine {
                         keys: vec!["Enter".into()],
-                        description: format!(
-                            "to activate {}",
+                        description: strings.to_activate(
                             self.get_component_for_section(&self.last_hovered_section)
-                                .name()
+                                .name(),
                         ),
                     },
+                    UsageInfoLine {
+                        keys: vec!["Esc".into()],
+                        description: strings.to_select_messages().into(),
+                    },
+                    UsageInfoLine {
+                        keys: vec!["b".into()],
+                        description: strings.to_browse_rooms().into(),
+                    },
+                    UsageInfoLine {
+                        keys: vec!["s".into()],
+                        description: strings.to_open_settings().into(),
+                    },
+                    UsageInfoLine {
+                        keys: vec!["d".into()],
+                        description: strings.to_open_debug().into(),
+                    },
                 ],
             }
         }
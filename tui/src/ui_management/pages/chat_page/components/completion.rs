@@ -0,0 +1,96 @@
+/// What the word being completed refers to, told by its first character
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CompletionKind {
+    /// `@` followed by the id of a user in the active room
+    User,
+    /// `#` followed by the name of a room
+    Room,
+    /// `/` followed by a slash command, only at the start of the input
+    Command,
+}
+
+impl CompletionKind {
+    fn of(word: &str, is_first_word: bool) -> Option<Self> {
+        match word.chars().next()? {
+            '@' => Some(CompletionKind::User),
+            '#' => Some(CompletionKind::Room),
+            '/' if is_first_word => Some(CompletionKind::Command),
+            _ => None,
+        }
+    }
+}
+
+/// What can be completed, taken from the state when Tab is pressed
+pub struct CompletionSource<'a> {
+    /// The users in the active room
+    pub users: &'a [String],
+    pub rooms: &'a [String],
+    /// The slash commands and the aliases of the user, with their leading `/`
+    pub commands: &'a [String],
+}
+
+/// [Completion] is the list of candidates for the word before the cursor, cycled by pressing Tab
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    /// The index of the character the completed word starts at
+    pub start: usize,
+    /// The words the typed word can be completed to, including its prefix, e.g. `@alice`
+    pub candidates: Vec<String>,
+    /// The candidate currently in the input box
+    pub selected: usize,
+}
+
+impl Completion {
+    /// Starts completing the word ending at the cursor, None if there is nothing to complete it to
+    pub fn start(text: &str, cursor_position: usize, source: &CompletionSource) -> Option<Self> {
+        let before_cursor = text.chars().take(cursor_position).collect::<String>();
+        let word_start = before_cursor
+            .rfind(char::is_whitespace)
+            .map(|index| index + 1)
+            .unwrap_or(0);
+        let word = &before_cursor[word_start..];
+        let is_first_word = before_cursor[..word_start].trim().is_empty();
+
+        let (prefix, names) = match CompletionKind::of(word, is_first_word)? {
+            CompletionKind::User => ("@", source.users),
+            CompletionKind::Room => ("#", source.rooms),
+            CompletionKind::Command => ("", source.commands),
+        };
+        let typed = word[prefix.len()..].to_lowercase();
+
+        let mut candidates = names
+            .iter()
+            .filter(|name| name.to_lowercase().starts_with(&typed))
+            .map(|name| format!("{}{}", prefix, name))
+            .collect::<Vec<_>>();
+        candidates.sort();
+        candidates.dedup();
+
+        if candidates.is_empty() {
+            return None;
+        }
+
+        Some(Completion {
+            start: before_cursor[..word_start].chars().count(),
+            candidates,
+            selected: 0,
+        })
+    }
+
+    pub fn current(&self) -> &str {
+        &self.candidates[self.selected]
+    }
+
+    /// Whether the completion is settled, a single candidate needs no list to pick from
+    pub fn is_unique(&self) -> bool {
+        self.candidates.len() == 1
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1) % self.candidates.len();
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = (self.selected + self.candidates.len() - 1) % self.candidates.len();
+    }
+}
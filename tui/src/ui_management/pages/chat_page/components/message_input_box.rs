@@ -1,30 +1,151 @@
+use comms::event::ContentType;
 use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
 use ratatui::{
     prelude::Rect,
-    style::Color,
+    style::{Color, Style, Stylize},
+    widgets::{Clear, List, ListState},
     Frame,
 };
 use tokio::sync::mpsc::UnboundedSender;
 
 use super::super::section::usage::{HasUsageInfo, UsageInfo, UsageInfoLine};
+use super::completion::{Completion, CompletionSource};
 use crate::ui_management::components::{
     input_box::{self, InputBox},
     Component, ComponentRender,
 };
 use crate::{
+    accessibility::Accessibility,
+    aliases::Aliases,
+    locale::Locale,
     state_store::{action::Action, State},
     ui_management::pages::chat_page::section::SectionActivation,
 };
 
+/// Typed as a message, changes the welcome message of the active room instead of sending a message
+const WELCOME_COMMAND: &str = "/welcome";
+/// Typed as a message, lists the sessions of the user on other devices
+const SESSIONS_COMMAND: &str = "/sessions";
+/// Typed as a message, closes another session of the user by its id
+const DISCONNECT_COMMAND: &str = "/disconnect";
+/// Typed as a message, looks up another user, e.g. whether they are online
+const WHOIS_COMMAND: &str = "/whois";
+/// Typed as a message, finds the users whose id contains the given text, e.g. to invite them
+const USERS_COMMAND: &str = "/users";
+/// Typed as a message, shows the details of the active room
+const ROOM_INFO_COMMAND: &str = "/roominfo";
+/// Typed as a message, reports the latest message of a user in the active room, e.g. "/report @spammer ads"
+const REPORT_COMMAND: &str = "/report";
+/// Typed as a message, lists the open reports of the rooms the user moderates
+const REPORTS_COMMAND: &str = "/reports";
+/// Typed as a message, closes a report by its id
+const RESOLVE_COMMAND: &str = "/resolve";
+/// Typed as a message, lets a user into the active room once they asked to join it
+const APPROVE_COMMAND: &str = "/approve";
+/// Typed as a message, refuses the request of a user to join the active room
+const DENY_COMMAND: &str = "/deny";
+/// Typed as a message, shares the key of the active encrypted room with the session of the given fingerprint
+/// once the user has compared it with the one the session shows, e.g. "/share-key 1a2b 3c4d 5e6f 7a8b"
+const SHARE_KEY_COMMAND: &str = "/share-key";
+/// Typed as a message, opens a room and joins it, e.g. "/join #rust"
+const JOIN_COMMAND: &str = "/join";
+/// Typed as a message, leaves the active room or the given one, e.g. "/leave #rust"
+const LEAVE_COMMAND: &str = "/leave";
+/// Typed as a message, shows how busy the active room has been, "/stats all" shows every room to admins
+const STATS_COMMAND: &str = "/stats";
+/// Typed as a message, archives the active room or the given one, optionally later, e.g. "/archive #old in 2d"
+const ARCHIVE_COMMAND: &str = "/archive";
+/// Typed as a message, reopens the active archived room or the given one, e.g. "/reopen #old"
+const REOPEN_COMMAND: &str = "/reopen";
+/// Typed as a message, changes the description of the active room
+const DESCRIBE_COMMAND: &str = "/describe";
+/// Typed as a message, changes the maximum number of users in the active room, "/capacity 0" lifts the limit
+const CAPACITY_COMMAND: &str = "/capacity";
+/// Typed as a message, reveals the newest message with masked words in the active room, "/reveal 2" the one before
+const REVEAL_COMMAND: &str = "/reveal";
+/// Typed as a message, sets what the user is up to for the others to see, e.g. "/status in a meeting"
+/// Without a text the status text is removed
+const STATUS_COMMAND: &str = "/status";
+/// Typed as a message, creates a code letting others into the active private room, e.g. "/invite-link 2d 5"
+/// The code is valid for a day and a single user unless given otherwise
+const INVITE_LINK_COMMAND: &str = "/invite-link";
+/// Typed as a message, joins the room of an invite code
+const JOIN_WITH_CODE_COMMAND: &str = "/join-with-code";
+/// Typed as a message, hides the users joining and leaving the active room, or shows them again
+const JOINS_COMMAND: &str = "/joins";
+/// Typed as a message, sends what the user does, shown after their name, e.g. "/me waves"
+const ME_COMMAND: &str = "/me";
+/// Wrapped around a message, sends it as a snippet of code, e.g. "```cargo build --release```"
+const CODE_FENCE: &str = "```";
+
+/// The slash commands offered when completing a word starting with `/`
+const COMMANDS: &[&str] = &[
+    WELCOME_COMMAND,
+    SESSIONS_COMMAND,
+    DISCONNECT_COMMAND,
+    WHOIS_COMMAND,
+    USERS_COMMAND,
+    ROOM_INFO_COMMAND,
+    REPORT_COMMAND,
+    REPORTS_COMMAND,
+    RESOLVE_COMMAND,
+    APPROVE_COMMAND,
+    DENY_COMMAND,
+    SHARE_KEY_COMMAND,
+    JOIN_COMMAND,
+    LEAVE_COMMAND,
+    STATS_COMMAND,
+    ARCHIVE_COMMAND,
+    REOPEN_COMMAND,
+    DESCRIBE_COMMAND,
+    CAPACITY_COMMAND,
+    REVEAL_COMMAND,
+    STATUS_COMMAND,
+    INVITE_LINK_COMMAND,
+    JOIN_WITH_CODE_COMMAND,
+    JOINS_COMMAND,
+    ME_COMMAND,
+];
+
+/// The number of candidates the completion list shows at once, longer lists scroll
+const MAX_COMPLETION_ITEMS: usize = 8;
+
 struct Props {
     /// Active room that the user is chatting in
     active_room: Option<String>,
+    /// The maximum number of characters the server accepts for a message
+    max_message_length: Option<usize>,
+    accessibility: Accessibility,
+    locale: Locale,
+    aliases: Aliases,
+    /// The users in the active room, offered when completing `@`
+    room_users: Vec<String>,
+    /// Offered when completing `#`
+    rooms: Vec<String>,
+    /// The slash commands and the aliases of the user, offered when completing `/`
+    commands: Vec<String>,
 }
 
 impl From<&State> for Props {
     fn from(state: &State) -> Self {
         Self {
             active_room: state.active_room.clone(),
+            max_message_length: state.max_message_length,
+            accessibility: state.accessibility,
+            locale: state.locale,
+            aliases: state.aliases.clone(),
+            room_users: state
+                .active_room
+                .as_ref()
+                .and_then(|active_room| state.room_data_map.get(active_room))
+                .map(|room_data| room_data.users.iter().cloned().collect())
+                .unwrap_or_default(),
+            rooms: state.room_data_map.keys().cloned().collect(),
+            commands: COMMANDS
+                .iter()
+                .map(|command| String::from(*command))
+                .chain(state.aliases.names().map(|name| format!("/{name}")))
+                .collect(),
         }
     }
 }
@@ -35,21 +156,339 @@ pub struct MessageInputBox {
     props: Props,
     // Internal State for the Component
     pub input_box: InputBox,
+    /// The candidates for the word being completed, while the user cycles through them
+    completion: Option<Completion>,
 }
 
 impl MessageInputBox {
+    fn message_length(&self) -> usize {
+        self.input_box.text().chars().count()
+    }
+
+    fn is_over_length_limit(&self) -> bool {
+        self.props
+            .max_message_length
+            .map(|max_message_length| self.message_length() > max_message_length)
+            .unwrap_or(false)
+    }
+
     fn submit_message(&mut self) {
-        if self.input_box.is_empty() {
+        // the server would reject the message anyway, keep the text so the user can shorten it
+        if self.input_box.is_empty() || self.is_over_length_limit() {
             return;
         }
 
+        let action = parse_input(&self.props.aliases.expand(self.input_box.text()));
+
         // TODO: handle the error scenario
-        let _ = self.action_tx.send(Action::SendMessage {
-            content: String::from(self.input_box.text()),
-        });
+        let _ = self.action_tx.send(action);
 
         self.input_box.reset();
     }
+
+    /// Completes the word before the cursor, or picks the next candidate if Tab has been pressed before
+    fn complete(&mut self, is_forward: bool) {
+        match self.completion.as_mut() {
+            Some(completion) if is_forward => completion.select_next(),
+            Some(completion) => completion.select_previous(),
+            None => {
+                let source = CompletionSource {
+                    users: &self.props.room_users,
+                    rooms: &self.props.rooms,
+                    commands: &self.props.commands,
+                };
+
+                self.completion = Completion::start(
+                    self.input_box.text(),
+                    self.input_box.cursor_position(),
+                    &source,
+                );
+            }
+        }
+
+        let Some(completion) = self.completion.as_ref() else {
+            return;
+        };
+        if completion.is_unique() {
+            // nothing to pick from, the word is done
+            self.input_box
+                .replace_before_cursor(completion.start, &format!("{} ", completion.current()));
+            self.completion = None;
+        } else {
+            self.input_box
+                .replace_before_cursor(completion.start, completion.current());
+        }
+    }
+
+    fn render_completion(&self, frame: &mut Frame, area: Rect, completion: &Completion) {
+        let height = completion.candidates.len().min(MAX_COMPLETION_ITEMS) as u16 + 2;
+        let width = completion
+            .candidates
+            .iter()
+            .map(|candidate| candidate.chars().count())
+            .max()
+            .unwrap_or_default() as u16
+            + 4;
+        let width = width.min(area.width);
+        // right above the completed word, kept within the width of the input box
+        let popup = Rect {
+            x: (area.x + 1 + completion.start as u16).min(area.right().saturating_sub(width)),
+            y: area.y.saturating_sub(height),
+            width,
+            height: height.min(area.y),
+        };
+
+        let list = List::new(completion.candidates.iter().map(String::as_str))
+            .block(self.props.accessibility.block())
+            .highlight_style(Style::new().reversed());
+        let mut list_state = ListState::default().with_selected(Some(completion.selected));
+
+        frame.render_widget(Clear, popup);
+        frame.render_stateful_widget(list, popup, &mut list_state);
+    }
+
+    /// Sends the text bound to the function key, returns false if the key is not bound
+    /// The input box is left untouched, so a macro can be used while typing.
+    pub fn send_key_macro(&mut self, number: u8) -> bool {
+        let Some(text) = self.props.aliases.key_macro(number) else {
+            return false;
+        };
+
+        if self.props.active_room.is_some() {
+            let _ = self
+                .action_tx
+                .send(parse_input(&self.props.aliases.expand(text)));
+        }
+
+        true
+    }
+}
+
+/// The action for a text submitted in the input box, either a slash command or a message
+fn parse_input(text: &str) -> Action {
+    if let Some(message) = strip_command(text, WELCOME_COMMAND) {
+        // an empty welcome message removes the welcome message of the room
+        Action::SetWelcomeMessage {
+            message: Some(String::from(message)).filter(|message| !message.is_empty()),
+        }
+    } else if strip_command(text, SESSIONS_COMMAND).is_some() {
+        Action::ListSessions
+    } else if let Some(session_id) =
+        strip_command(text, DISCONNECT_COMMAND).filter(|session_id| !session_id.is_empty())
+    {
+        Action::DisconnectSession {
+            session_id: String::from(session_id),
+        }
+    } else if let Some(user_id) = strip_command(text, WHOIS_COMMAND)
+        .map(|user_id| user_id.trim_start_matches('@'))
+        .filter(|user_id| !user_id.is_empty())
+    {
+        Action::GetUserInfo {
+            user_id: String::from(user_id),
+        }
+    } else if let Some(query) = strip_command(text, USERS_COMMAND)
+        .map(|query| query.trim_start_matches('@'))
+        .filter(|query| !query.is_empty())
+    {
+        Action::SearchUsers {
+            query: String::from(query),
+        }
+    } else if strip_command(text, ROOM_INFO_COMMAND).is_some() {
+        Action::GetRoomInfo
+    } else if let Some((user_id, reason)) = strip_command(text, REPORT_COMMAND)
+        .and_then(|args| args.split_once(' '))
+        .map(|(user_id, reason)| (user_id.trim_start_matches('@'), reason.trim()))
+        .filter(|(user_id, reason)| !user_id.is_empty() && !reason.is_empty())
+    {
+        Action::ReportMessage {
+            user_id: String::from(user_id),
+            reason: String::from(reason),
+        }
+    } else if strip_command(text, REPORTS_COMMAND).is_some() {
+        Action::ListReports
+    } else if let Some(report_id) =
+        strip_command(text, RESOLVE_COMMAND).filter(|report_id| !report_id.is_empty())
+    {
+        Action::ResolveReport {
+            report_id: String::from(report_id),
+        }
+    } else if let Some(user_id) = strip_command(text, APPROVE_COMMAND)
+        .map(|user_id| user_id.trim_start_matches('@'))
+        .filter(|user_id| !user_id.is_empty())
+    {
+        Action::ApproveJoinRequest {
+            user_id: String::from(user_id),
+        }
+    } else if let Some(user_id) = strip_command(text, DENY_COMMAND)
+        .map(|user_id| user_id.trim_start_matches('@'))
+        .filter(|user_id| !user_id.is_empty())
+    {
+        Action::DenyJoinRequest {
+            user_id: String::from(user_id),
+        }
+    } else if let Some(fingerprint) =
+        strip_command(text, SHARE_KEY_COMMAND).filter(|fingerprint| !fingerprint.is_empty())
+    {
+        Action::ShareRoomKey {
+            fingerprint: String::from(fingerprint),
+        }
+    } else if let Some(room) = strip_command(text, JOIN_COMMAND)
+        .map(|room| room.trim_start_matches('#'))
+        .filter(|room| !room.is_empty())
+    {
+        Action::SelectRoom {
+            room: String::from(room),
+        }
+    } else if let Some(room) = strip_command(text, LEAVE_COMMAND) {
+        Action::LeaveRoom {
+            room: Some(String::from(room.trim_start_matches('#'))).filter(|room| !room.is_empty()),
+        }
+    } else if let Some(args) = strip_command(text, STATS_COMMAND) {
+        Action::GetRoomStats {
+            all_rooms: args == "all",
+        }
+    } else if let Some((room, delay_ms)) =
+        strip_command(text, ARCHIVE_COMMAND).and_then(parse_archive_args)
+    {
+        Action::ArchiveRoom { room, delay_ms }
+    } else if let Some(room) = strip_command(text, REOPEN_COMMAND) {
+        Action::ReopenRoom {
+            room: Some(String::from(room.trim_start_matches('#'))).filter(|room| !room.is_empty()),
+        }
+    } else if let Some(description) =
+        strip_command(text, DESCRIBE_COMMAND).filter(|description| !description.is_empty())
+    {
+        Action::UpdateRoomMetadata {
+            description: Some(String::from(description)),
+            capacity: None,
+        }
+    } else if let Some(capacity) =
+        strip_command(text, CAPACITY_COMMAND).and_then(|capacity| capacity.parse().ok())
+    {
+        Action::UpdateRoomMetadata {
+            description: None,
+            capacity: Some(capacity),
+        }
+    } else if let Some(text) = strip_command(text, STATUS_COMMAND) {
+        Action::SetStatusText {
+            text: Some(String::from(text)).filter(|text| !text.is_empty()),
+        }
+    } else if let Some((ttl_secs, max_uses)) =
+        strip_command(text, INVITE_LINK_COMMAND).and_then(parse_invite_link_args)
+    {
+        Action::CreateInviteCode { ttl_secs, max_uses }
+    } else if let Some(code) =
+        strip_command(text, JOIN_WITH_CODE_COMMAND).filter(|code| !code.is_empty())
+    {
+        Action::JoinWithCode {
+            code: String::from(code),
+        }
+    } else if strip_command(text, JOINS_COMMAND).is_some() {
+        Action::ToggleParticipation
+    } else if let Some(nth) = strip_command(text, REVEAL_COMMAND).and_then(|nth| {
+        // the newest message unless another one is given
+        if nth.is_empty() {
+            Some(1)
+        } else {
+            nth.parse().ok().filter(|nth| *nth > 0)
+        }
+    }) {
+        Action::ToggleRevealed { nth }
+    } else if let Some(action) = strip_command(text, ME_COMMAND).filter(|action| !action.is_empty())
+    {
+        Action::SendMessage {
+            content: String::from(action),
+            content_type: ContentType::Action,
+        }
+    } else if let Some(code) = strip_code_fence(text) {
+        Action::SendMessage {
+            content: String::from(code),
+            content_type: ContentType::Code,
+        }
+    } else {
+        Action::SendMessage {
+            content: String::from(text),
+            content_type: ContentType::Plain,
+        }
+    }
+}
+
+/// Returns the code in the text if it is wrapped in a fence, e.g. "```ls -la```"
+fn strip_code_fence(text: &str) -> Option<&str> {
+    text.strip_prefix(CODE_FENCE)?
+        .strip_suffix(CODE_FENCE)
+        .map(|code| code.trim_matches('\n'))
+        .filter(|code| !code.trim().is_empty())
+}
+
+/// The room and the delay of `[#room] [in <duration>]`, None if they can not be parsed
+fn parse_archive_args(args: &str) -> Option<(Option<String>, Option<u64>)> {
+    let mut words = args.split_whitespace().peekable();
+    let room = words
+        .next_if(|word| *word != "in")
+        .map(|room| String::from(room.trim_start_matches('#')));
+
+    let delay_ms = match (words.next(), words.next()) {
+        (None, _) => None,
+        (Some("in"), Some(duration)) => Some(parse_duration_ms(duration)?),
+        _ => return None,
+    };
+
+    words.next().is_none().then_some((room, delay_ms))
+}
+
+/// How long an invite code is valid and by how many users it can be used, from `[<duration>] [<uses>]`
+fn parse_invite_link_args(args: &str) -> Option<(Option<u64>, Option<u32>)> {
+    let mut words = args.split_whitespace();
+    let ttl_secs = match words.next() {
+        None => None,
+        Some(duration) => Some(parse_duration_ms(duration)? / 1000),
+    };
+    let max_uses = match words.next() {
+        None => None,
+        Some(max_uses) => Some(max_uses.parse().ok()?),
+    };
+
+    words.next().is_none().then_some((ttl_secs, max_uses))
+}
+
+/// Parse a duration made of one or more `<number><unit>` segments such as `10m` or `1h30m`, like the server does
+/// Supported units are `s`, `m`, `h` and `d`
+fn parse_duration_ms(text: &str) -> Option<u64> {
+    let mut seconds: u64 = 0;
+    let mut number = String::new();
+
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let unit_seconds = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            _ => return None,
+        };
+        let value: u64 = number.parse().ok()?;
+        seconds = seconds.checked_add(value.checked_mul(unit_seconds)?)?;
+        number.clear();
+    }
+
+    // a trailing number without a unit is ambiguous
+    if !number.is_empty() || seconds == 0 {
+        return None;
+    }
+
+    seconds.checked_mul(1000)
+}
+
+/// Returns the trimmed arguments of the text if it is the given command, e.g. "/welcome hi" or "/welcome"
+fn strip_command<'a>(text: &'a str, command: &str) -> Option<&'a str> {
+    text.strip_prefix(command)
+        .filter(|args| args.is_empty() || args.starts_with(' '))
+        .map(str::trim)
 }
 
 impl Component for MessageInputBox {
@@ -59,6 +498,7 @@ impl Component for MessageInputBox {
             props: Props::from(state),
             //
             input_box: InputBox::new(state, action_tx),
+            completion: None,
         }
     }
 
@@ -73,7 +513,7 @@ impl Component for MessageInputBox {
     }
 
     fn name(&self) -> &str {
-        "Message Input"
+        self.props.locale.strings().message_input_name()
     }
 
     fn handle_key_event(&mut self, key: KeyEvent) {
@@ -81,11 +521,23 @@ impl Component for MessageInputBox {
             return;
         }
 
-        if self.props.active_room.is_some() {
-            self.input_box.handle_key_event(key);
+        if self.props.active_room.is_none() {
+            return;
+        }
 
-            if key.code == KeyCode::Enter {
-                self.submit_message();
+        match key.code {
+            KeyCode::Tab => self.complete(true),
+            KeyCode::BackTab => self.complete(false),
+            // picks the candidate in the input box instead of sending the message
+            KeyCode::Enter if self.completion.is_some() => self.completion = None,
+            _ => {
+                // typing on keeps the candidate in the input box
+                self.completion = None;
+                self.input_box.handle_key_event(key);
+
+                if key.code == KeyCode::Enter {
+                    self.submit_message();
+                }
             }
         }
     }
@@ -96,6 +548,7 @@ impl SectionActivation for MessageInputBox {
 
     fn deactivate(&mut self) {
         self.input_box.reset();
+        self.completion = None;
     }
 }
 
@@ -103,43 +556,83 @@ pub struct RenderProps {
     pub area: Rect,
     pub border_color: Color,
     pub show_cursor: bool,
+    /// Appended to the title, e.g. to tell that the section is active without relying on colors
+    pub title_marker: &'static str,
 }
 
 impl ComponentRender<RenderProps> for MessageInputBox {
     fn render(&self, frame: &mut Frame, props: RenderProps) {
+        let strings = self.props.locale.strings();
+        let mut title = match self.props.max_message_length {
+            Some(max_message_length) => format!(
+                "{} ({}/{})",
+                strings.message_input_title(),
+                self.message_length(),
+                max_message_length
+            ),
+            None => strings.message_input_title().into(),
+        };
+        if self.props.accessibility.high_contrast && self.is_over_length_limit() {
+            title.push_str(strings.too_long_marker());
+        }
+        title.push_str(props.title_marker);
+
         self.input_box.render(
             frame,
             input_box::RenderProps {
-                title: "Message Input".into(),
+                title,
                 area: props.area,
-                border_color: props.border_color,
+                border_color: if self.is_over_length_limit() {
+                    Color::Red
+                } else {
+                    props.border_color
+                },
                 show_cursor: props.show_cursor,
+                accessibility: self.props.accessibility,
             },
-        )
+        );
+
+        if let Some(completion) = self.completion.as_ref() {
+            self.render_completion(frame, props.area, completion);
+        }
     }
 }
 
 impl HasUsageInfo for MessageInputBox {
     fn usage_info(&self) -> UsageInfo {
+        let strings = self.props.locale.strings();
+
         if self.props.active_room.is_none() {
             UsageInfo {
-                description: Some("You can not send a message until you enter a room.".into()),
+                description: Some(strings.enter_room_first().into()),
                 lines: vec![UsageInfoLine {
                     keys: vec!["Esc".into()],
-                    description: "to cancel".into(),
+                    description: strings.to_cancel().into(),
+                }],
+            }
+        } else if self.is_over_length_limit() {
+            UsageInfo {
+                description: Some(strings.shorten_message().into()),
+                lines: vec![UsageInfoLine {
+                    keys: vec!["Esc".into()],
+                    description: strings.to_cancel().into(),
                 }],
             }
         } else {
             UsageInfo {
-                description: Some("Type your message to send a message to the active room".into()),
+                description: Some(strings.type_message().into()),
                 lines: vec![
                     UsageInfoLine {
                         keys: vec!["Esc".into()],
-                        description: "to cancel".into(),
+                        description: strings.to_cancel().into(),
                     },
                     UsageInfoLine {
                         keys: vec!["Enter".into()],
-                        description: "to send your message".into(),
+                        description: strings.to_send_message().into(),
+                    },
+                    UsageInfoLine {
+                        keys: vec!["Tab".into()],
+                        description: strings.to_complete().into(),
                     },
                 ],
             }
@@ -1,2 +1,3 @@
+pub mod completion;
 pub mod message_input_box;
 pub mod room_list;
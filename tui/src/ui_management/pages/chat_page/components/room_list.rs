@@ -1,33 +1,70 @@
+use std::collections::HashSet;
+
 use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
 use ratatui::{
     prelude::Rect,
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, ListState},
+    widgets::{List, ListItem, ListState, Tabs},
     Frame,
 };
 use tokio::sync::mpsc::UnboundedSender;
 
 use super::super::section::usage::{HasUsageInfo, UsageInfo, UsageInfoLine};
 use crate::{
-    state_store::{action::Action, State},
+    accessibility::Accessibility,
+    keymap::Keymap,
+    locale::Locale,
+    presentation::sparkline,
+    state_store::{action::Action, RoomData, State},
     ui_management::pages::chat_page::section::SectionActivation,
 };
 
 use crate::ui_management::components::{Component, ComponentRender};
 
+#[allow(dead_code)]
 pub struct RoomState {
     pub name: String,
     pub description: String,
     pub has_joined: bool,
     pub has_unread: bool,
+    pub encrypted: bool,
+    /// Archived rooms are listed in their own section after the others
+    pub archived: bool,
+    /// The category the room is listed under, uncategorized rooms are listed first
+    pub category: Option<String>,
+    /// Rooms the server no longer lists are kept at the end of the list, to read their messages
+    pub defunct: bool,
+    /// The number of messages in each of the last minutes, empty if the room has been quiet
+    pub activity: Vec<u32>,
 }
 
 struct Props {
     /// List of rooms and current state of those rooms
     rooms: Vec<RoomState>,
+    /// The rooms the user has left lately and not rejoined since, the latest first
+    recent_rooms: Vec<RoomState>,
     /// Current active room
     active_room: Option<String>,
+    accessibility: Accessibility,
+    keymap: Keymap,
+    locale: Locale,
+}
+
+impl RoomState {
+    fn new(name: &str, room_data: &RoomData) -> Self {
+        RoomState {
+            name: String::from(name),
+            description: room_data.description.clone(),
+            has_joined: room_data.has_joined,
+            has_unread: room_data.has_unread,
+            encrypted: room_data.encrypted,
+            archived: room_data.archived,
+            category: room_data.category.clone(),
+            defunct: room_data.defunct,
+            activity: room_data.recent_activity(),
+        }
+    }
 }
 
 impl From<&State> for Props {
@@ -35,38 +72,70 @@ impl From<&State> for Props {
         let mut rooms = state
             .room_data_map
             .iter()
-            .map(|(name, room_data)| RoomState {
-                name: name.clone(),
-                description: room_data.description.clone(),
-                has_joined: room_data.has_joined,
-                has_unread: room_data.has_unread,
-            })
+            .map(|(name, room_data)| RoomState::new(name, room_data))
             .collect::<Vec<RoomState>>();
+        // rooms which no longer exist are forgotten once the server has been asked about them
+        let recent_rooms = state
+            .recent_rooms
+            .iter()
+            .filter_map(|name| {
+                let room_data = state.room_data_map.get(name)?;
+                (!room_data.has_joined).then(|| RoomState::new(name, room_data))
+            })
+            .collect();
 
-        rooms.sort_by(|room_a, room_b| room_a.name.cmp(&room_b.name));
+        // archived rooms are listed in their own section, whatever their category, and the defunct ones after them
+        rooms.sort_by_cached_key(|room_state| {
+            (
+                room_state.defunct,
+                room_state.archived,
+                RoomList::listed_category(room_state).map(String::from),
+                room_state.name.clone(),
+            )
+        });
 
         Self {
             rooms,
+            recent_rooms,
             active_room: state.active_room.clone(),
+            accessibility: state.accessibility,
+            keymap: state.keymap,
+            locale: state.locale,
         }
     }
 }
 
+/// A line of the room list, the rooms of a category follow its header unless it is collapsed
+enum Row<'a> {
+    Category {
+        name: &'a str,
+        collapsed: bool,
+        /// The number of rooms of the category with unread messages, so collapsed categories still tell
+        unread_count: usize,
+    },
+    Room(&'a RoomState),
+    /// A room the user has left lately, listed again at the start to rejoin it quickly
+    Recent(&'a RoomState),
+}
+
 pub struct RoomList {
     /// Sending actions to the state store
     action_tx: UnboundedSender<Action>,
     /// State Mapped RoomList Props
     props: Props,
     // Internal Component State
-    /// List with optional selection and current offset
+    /// List with optional selection and current offset, over the rows rather than the rooms
     pub list_state: ListState,
+    /// The categories whose rooms are hidden
+    collapsed_categories: HashSet<String>,
 }
 
 impl RoomList {
     fn next(&mut self) {
+        let row_count = self.rows().len();
         let i = match self.list_state.selected() {
             Some(i) => {
-                if i >= self.props.rooms.len() - 1 {
+                if i + 1 >= row_count {
                     0
                 } else {
                     i + 1
@@ -78,10 +147,11 @@ impl RoomList {
     }
 
     fn previous(&mut self) {
+        let row_count = self.rows().len();
         let i = match self.list_state.selected() {
             Some(i) => {
                 if i == 0 {
-                    self.props.rooms.len() - 1
+                    row_count.saturating_sub(1)
                 } else {
                     i - 1
                 }
@@ -92,23 +162,92 @@ impl RoomList {
         self.list_state.select(Some(i));
     }
 
-    pub(super) fn rooms(&self) -> &Vec<RoomState> {
-        &self.props.rooms
-    }
-
-    fn get_room_idx(&self, name: &str) -> Option<usize> {
-        self.props
-            .rooms
+    /// The rooms left lately, then the uncategorized ones, then each category headed by its name and the archived rooms last
+    fn rows(&self) -> Vec<Row<'_>> {
+        let mut rows = self
+            .props
+            .recent_rooms
             .iter()
-            .enumerate()
-            .find_map(|(idx, room_state)| {
-                if room_state.name == name {
-                    Some(idx)
-                } else {
-                    None
+            .map(Row::Recent)
+            .collect::<Vec<_>>();
+        let mut current_category = None;
+
+        for room_state in &self.props.rooms {
+            if let Some(category) = Self::listed_category(room_state) {
+                let collapsed = self.collapsed_categories.contains(category);
+                if current_category != Some(category) {
+                    current_category = Some(category);
+                    rows.push(Row::Category {
+                        name: category,
+                        collapsed,
+                        unread_count: self
+                            .props
+                            .rooms
+                            .iter()
+                            .filter(|room_state| {
+                                room_state.has_unread
+                                    && Self::listed_category(room_state) == Some(category)
+                            })
+                            .count(),
+                    });
                 }
+                if collapsed {
+                    continue;
+                }
+            }
+
+            rows.push(Row::Room(room_state));
+        }
+
+        rows
+    }
+
+    /// The category the room is listed under, archived and defunct rooms are listed at the end instead
+    fn listed_category(room_state: &RoomState) -> Option<&str> {
+        room_state
+            .category
+            .as_deref()
+            .filter(|_| !room_state.archived && !room_state.defunct)
+    }
+
+    /// The row of the room, or the header of its category if the category is collapsed
+    fn get_row_idx(&self, name: &str) -> Option<usize> {
+        let rows = self.rows();
+
+        rows.iter()
+            .position(|row| matches!(row, Row::Room(room_state) if room_state.name == name))
+            .or_else(|| {
+                let category = self
+                    .props
+                    .rooms
+                    .iter()
+                    .find(|room_state| room_state.name == name)
+                    .and_then(Self::listed_category)?;
+
+                rows.iter()
+                    .position(|row| matches!(row, Row::Category { name, .. } if *name == category))
             })
     }
+
+    /// Whether the header of a category is selected rather than a room, Enter toggles it instead of opening a room
+    pub fn has_category_selected(&self) -> bool {
+        self.list_state.selected().is_some_and(|selected_idx| {
+            matches!(self.rows().get(selected_idx), Some(Row::Category { .. }))
+        })
+    }
+
+    /// Collapse the category if it is expanded or expand it otherwise, keeping its header selected
+    fn toggle_category(&mut self, category: String) {
+        if !self.collapsed_categories.remove(&category) {
+            self.collapsed_categories.insert(category.clone());
+        }
+
+        let header_idx = self
+            .rows()
+            .iter()
+            .position(|row| matches!(row, Row::Category { name, .. } if *name == category));
+        self.list_state.select(header_idx);
+    }
 }
 
 impl Component for RoomList {
@@ -118,6 +257,7 @@ impl Component for RoomList {
             props: Props::from(state),
             //
             list_state: ListState::default(),
+            collapsed_categories: HashSet::new(),
         }
     }
 
@@ -125,14 +265,26 @@ impl Component for RoomList {
     where
         Self: Sized,
     {
-        Self {
+        let mut room_list = Self {
             props: Props::from(state),
             ..self
+        };
+
+        // the list may shrink, e.g. when the access to a private room is revoked
+        let row_count = room_list.rows().len();
+        if room_list
+            .list_state
+            .selected()
+            .is_some_and(|selected_idx| selected_idx >= row_count)
+        {
+            room_list.list_state.select(row_count.checked_sub(1));
         }
+
+        room_list
     }
 
     fn name(&self) -> &str {
-        "Room List"
+        self.props.locale.strings().room_list_name()
     }
 
     fn handle_key_event(&mut self, key: KeyEvent) {
@@ -140,23 +292,71 @@ impl Component for RoomList {
             return;
         }
 
+        // only the keys moving up and down are taken from the keymap, `l` leaves the room
+        let key = match self.props.keymap.translate(key) {
+            translated @ KeyEvent {
+                code: KeyCode::Up | KeyCode::Down,
+                ..
+            } => translated,
+            _ => key,
+        };
+
         match key.code {
-            KeyCode::Up => {
+            // the rooms are laid out from left to right as tabs in narrow terminals
+            KeyCode::Up | KeyCode::Left => {
                 self.previous();
             }
-            KeyCode::Down => {
+            KeyCode::Down | KeyCode::Right => {
                 self.next();
             }
             KeyCode::Enter if self.list_state.selected().is_some() => {
                 let selected_idx = self.list_state.selected().unwrap();
 
-                let rooms = self.rooms();
-                let room_state = rooms.get(selected_idx).unwrap();
+                match self.rows().get(selected_idx) {
+                    // selecting a room the user has left joins it again
+                    Some(Row::Room(room_state) | Row::Recent(room_state)) => {
+                        // TODO: handle the error scenario somehow
+                        let _ = self.action_tx.send(Action::SelectRoom {
+                            room: room_state.name.clone(),
+                        });
+                    }
+                    Some(Row::Category { name, .. }) => {
+                        let category = name.to_string();
+                        self.toggle_category(category);
+                    }
+                    None => (),
+                }
+            }
+            // collapses the category of the selected room as well, not only the one of the selected header
+            KeyCode::Char(' ') if self.list_state.selected().is_some() => {
+                let selected_idx = self.list_state.selected().unwrap();
 
-                // TODO: handle the error scenario somehow
-                let _ = self.action_tx.send(Action::SelectRoom {
-                    room: room_state.name.clone(),
-                });
+                let category = match self.rows().get(selected_idx) {
+                    Some(Row::Category { name, .. }) => Some(name.to_string()),
+                    Some(Row::Room(room_state)) => {
+                        Self::listed_category(room_state).map(String::from)
+                    }
+                    Some(Row::Recent(_)) | None => None,
+                };
+                if let Some(category) = category {
+                    self.toggle_category(category);
+                }
+            }
+            KeyCode::Char('l') if self.list_state.selected().is_some() => {
+                let selected_idx = self.list_state.selected().unwrap();
+
+                if let Some(Row::Room(room_state)) = self.rows().get(selected_idx) {
+                    let _ = self.action_tx.send(Action::LeaveRoom {
+                        room: Some(room_state.name.clone()),
+                    });
+                }
+            }
+            KeyCode::Char('r') => {
+                if let Some(room_state) = self.props.recent_rooms.first() {
+                    let _ = self.action_tx.send(Action::SelectRoom {
+                        room: room_state.name.clone(),
+                    });
+                }
             }
             _ => (),
         }
@@ -169,7 +369,7 @@ impl SectionActivation for RoomList {
             .props
             .active_room
             .as_ref()
-            .and_then(|room_name| self.get_room_idx(room_name.as_str()))
+            .and_then(|room_name| self.get_row_idx(room_name.as_str()))
             .unwrap_or(0);
 
         *self.list_state.offset_mut() = 0;
@@ -185,50 +385,280 @@ impl SectionActivation for RoomList {
 pub struct RenderProps {
     pub border_color: Color,
     pub area: Rect,
+    /// Appended to the title, e.g. to tell that the section is active without relying on colors
+    pub title_marker: &'static str,
+    /// Render the rooms as a single line of tabs instead of a list, for narrow terminals
+    pub as_tabs: bool,
+}
+
+impl RoomList {
+    /// The name of the room along with its state, e.g. `#general*` for unread messages
+    fn room_tag(&self, room_state: &RoomState) -> String {
+        let strings = self.props.locale.strings();
+        let is_active = self.props.active_room.as_ref() == Some(&room_state.name);
+        let encrypted_marker = if room_state.encrypted {
+            strings.encrypted_room_marker()
+        } else {
+            ""
+        };
+
+        if self.props.accessibility.high_contrast {
+            // spell the state of the room out instead of relying on styles
+            format!(
+                "#{}{}{}{}",
+                room_state.name,
+                encrypted_marker,
+                if is_active {
+                    strings.active_room_marker()
+                } else {
+                    ""
+                },
+                if room_state.has_unread {
+                    strings.unread_room_marker()
+                } else {
+                    ""
+                }
+            )
+        } else {
+            format!(
+                "#{}{}{}",
+                room_state.name,
+                encrypted_marker,
+                if room_state.has_unread { "*" } else { "" }
+            )
+        }
+    }
+
+    fn room_style(&self, room_state: &RoomState) -> Style {
+        let is_active = self.props.active_room.as_ref() == Some(&room_state.name);
+
+        let style = if self.list_state.selected().is_none() && is_active {
+            Style::default().add_modifier(Modifier::BOLD)
+        } else if room_state.has_unread && !self.props.accessibility.high_contrast {
+            Style::default().add_modifier(Modifier::SLOW_BLINK | Modifier::ITALIC)
+        } else if (room_state.archived || room_state.defunct)
+            && !self.props.accessibility.high_contrast
+        {
+            Style::default().fg(Color::DarkGray)
+        } else {
+            Style::default()
+        };
+
+        style.bg(Color::Reset)
+    }
+
+    /// The name of the category along with whether it is collapsed and how many of its rooms are unread
+    fn category_tag(&self, name: &str, collapsed: bool, unread_count: usize) -> String {
+        let marker = match (collapsed, self.props.accessibility.ascii) {
+            (false, false) => "▾",
+            (true, false) => "▸",
+            (false, true) => "v",
+            (true, true) => ">",
+        };
+
+        if unread_count > 0 {
+            format!(
+                "{} {} ({})",
+                marker,
+                name,
+                self.props.locale.strings().unread_rooms(unread_count)
+            )
+        } else {
+            format!("{} {}", marker, name)
+        }
+    }
+
+    fn category_style(&self, unread_count: usize) -> Style {
+        let style = if unread_count > 0 && !self.props.accessibility.high_contrast {
+            Style::default().add_modifier(Modifier::BOLD | Modifier::SLOW_BLINK | Modifier::ITALIC)
+        } else {
+            Style::default().add_modifier(Modifier::BOLD)
+        };
+
+        style.bg(Color::Reset)
+    }
+
+    fn highlight_style(&self) -> Style {
+        if self.props.accessibility.high_contrast {
+            Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD)
+        } else {
+            Style::default()
+                // yellow that would work for both dark / light modes
+                .bg(Color::Rgb(255, 223, 102))
+                .add_modifier(Modifier::BOLD)
+        }
+    }
+
+    /// Renders the rooms as tabs, scrolled so the selected or active room is always visible
+    fn render_tabs(&self, frame: &mut Frame, props: RenderProps) {
+        let strings = self.props.locale.strings();
+        let block = self
+            .props
+            .accessibility
+            .block()
+            .border_style(Style::new().fg(props.border_color))
+            .title(format!("{}{}", strings.rooms_title(), props.title_marker));
+        let width = block.inner(props.area).width as usize;
+
+        // there is no room for a section of their own, archived rooms are marked instead
+        let tags = self
+            .rows()
+            .into_iter()
+            .map(|row| match row {
+                Row::Category {
+                    name,
+                    collapsed,
+                    unread_count,
+                } => (
+                    self.category_tag(name, collapsed, unread_count),
+                    self.category_style(unread_count),
+                ),
+                Row::Room(room_state) => {
+                    let mut tag = self.room_tag(room_state);
+                    if room_state.defunct {
+                        tag.push_str(strings.removed_room_marker());
+                    } else if room_state.archived {
+                        tag.push_str(strings.archived_room_marker());
+                    }
+
+                    (tag, self.room_style(room_state))
+                }
+                Row::Recent(room_state) => (
+                    format!(
+                        "{}{}",
+                        self.room_tag(room_state),
+                        strings.recent_room_marker()
+                    ),
+                    self.room_style(room_state),
+                ),
+            })
+            .collect::<Vec<_>>();
+        let selected_idx = self.list_state.selected().or_else(|| {
+            self.props
+                .active_room
+                .as_ref()
+                .and_then(|room_name| self.get_row_idx(room_name))
+        });
+
+        // go back from the selected tab as long as the tabs up to it fit, every tab is padded and followed by a divider
+        let mut first_idx = selected_idx.unwrap_or(0);
+        let mut tabs_width = tags
+            .get(first_idx)
+            .map(|(tag, _)| tag.chars().count() + 3)
+            .unwrap_or(0);
+        while first_idx > 0 && tabs_width + tags[first_idx - 1].0.chars().count() + 3 <= width {
+            first_idx -= 1;
+            tabs_width += tags[first_idx].0.chars().count() + 3;
+        }
+
+        let tabs = Tabs::new(
+            tags.into_iter()
+                .skip(first_idx)
+                .map(|(tag, style)| Line::from(Span::styled(tag, style))),
+        )
+        .block(block)
+        .select(selected_idx.map(|idx| idx - first_idx))
+        .highlight_style(if self.list_state.selected().is_some() {
+            self.highlight_style()
+        } else {
+            Style::default().add_modifier(Modifier::BOLD)
+        });
+
+        frame.render_widget(tabs, props.area);
+    }
 }
 
 impl ComponentRender<RenderProps> for RoomList {
     fn render(&self, frame: &mut Frame, props: RenderProps) {
-        let active_room = self.props.active_room.clone();
-        let room_list: Vec<ListItem> = self
-            .rooms()
+        if props.as_tabs {
+            return self.render_tabs(frame, props);
+        }
+
+        let strings = self.props.locale.strings();
+        let rows = self.rows();
+        let first_archived_idx = rows
             .iter()
-            .map(|room_state| {
-                let room_tag = format!(
-                    "#{}{}",
-                    room_state.name,
-                    if room_state.has_unread { "*" } else { "" }
-                );
-                let content = Line::from(Span::raw(room_tag));
-
-                let style = if self.list_state.selected().is_none()
-                    && active_room.is_some()
-                    && active_room.as_ref().unwrap().eq(&room_state.name)
-                {
-                    Style::default().add_modifier(Modifier::BOLD)
-                } else if room_state.has_unread {
-                    Style::default().add_modifier(Modifier::SLOW_BLINK | Modifier::ITALIC)
+            .position(|row| matches!(row, Row::Room(room_state) if room_state.archived && !room_state.defunct));
+        let first_recent_idx = rows.iter().position(|row| matches!(row, Row::Recent(_)));
+        let first_listed_idx = rows.iter().position(|row| !matches!(row, Row::Recent(_)));
+        let room_list: Vec<ListItem> = rows
+            .into_iter()
+            .enumerate()
+            .map(|(idx, row)| {
+                let room_state = match row {
+                    Row::Category {
+                        name,
+                        collapsed,
+                        unread_count,
+                    } => {
+                        return ListItem::new(self.category_tag(name, collapsed, unread_count))
+                            .style(self.category_style(unread_count));
+                    }
+                    Row::Recent(room_state) => {
+                        // the recent section is headed by its first room and set apart from the rest by a gap
+                        let mut lines = Vec::new();
+                        if first_recent_idx == Some(idx) {
+                            lines.push(Line::styled(
+                                format!("── {} ──", strings.recent_rooms_title()),
+                                Style::default().add_modifier(Modifier::ITALIC),
+                            ));
+                        }
+                        lines.push(Line::raw(self.room_tag(room_state)));
+                        if first_listed_idx == Some(idx + 1) {
+                            lines.push(Line::raw(""));
+                        }
+
+                        return ListItem::new(lines).style(self.room_style(room_state));
+                    }
+                    Row::Room(room_state) => room_state,
+                };
+
+                // the rooms of a category are indented below its header
+                let indent = if Self::listed_category(room_state).is_some() {
+                    "  "
                 } else {
-                    Style::default()
+                    ""
                 };
+                // the activity follows the name, so busy rooms stand out at a glance
+                let mut spans = vec![Span::raw(indent), Span::raw(self.room_tag(room_state))];
+                if room_state.defunct {
+                    spans.push(Span::raw(strings.removed_room_marker()));
+                }
+                if !room_state.activity.is_empty() {
+                    spans.push(Span::raw(" "));
+                    spans.push(Span::styled(
+                        sparkline(&room_state.activity, self.props.accessibility.ascii),
+                        if self.props.accessibility.high_contrast {
+                            Style::default()
+                        } else {
+                            Style::default().fg(Color::DarkGray)
+                        },
+                    ));
+                }
+
+                // the archived section is headed by the first archived room, the list can only select rooms
+                let mut lines = Vec::new();
+                if first_archived_idx == Some(idx) {
+                    lines.push(Line::styled(
+                        format!("── {} ──", strings.archived_rooms_title()),
+                        Style::default().add_modifier(Modifier::ITALIC),
+                    ));
+                }
+                lines.push(Line::from(spans));
 
-                ListItem::new(content).style(style.bg(Color::Reset))
+                ListItem::new(lines).style(self.room_style(room_state))
             })
             .collect();
 
         let room_list = List::new(room_list)
             .block(
-                Block::default()
-                    .borders(Borders::ALL)
+                self.props
+                    .accessibility
+                    .block()
                     .border_style(Style::new().fg(props.border_color))
-                    .title("Rooms"),
-            )
-            .highlight_style(
-                Style::default()
-                    // yellow that would work for both dark / light modes
-                    .bg(Color::Rgb(255, 223, 102))
-                    .add_modifier(Modifier::BOLD),
+                    .title(format!("{}{}", strings.rooms_title(), props.title_marker)),
             )
+            .highlight_style(self.highlight_style())
             .highlight_symbol(">");
 
         let mut app_room_list_state = self.list_state.clone();
@@ -238,22 +668,47 @@ impl ComponentRender<RenderProps> for RoomList {
 
 impl HasUsageInfo for RoomList {
     fn usage_info(&self) -> UsageInfo {
+        let strings = self.props.locale.strings();
+
+        let mut lines = vec![
+            UsageInfoLine {
+                keys: vec!["Esc".into()],
+                description: strings.to_cancel().into(),
+            },
+            UsageInfoLine {
+                keys: vec!["↑".into(), "↓".into()],
+                description: strings.to_navigate().into(),
+            },
+            UsageInfoLine {
+                keys: vec!["Enter".into()],
+                description: strings.to_join_room().into(),
+            },
+            UsageInfoLine {
+                keys: vec!["l".into()],
+                description: strings.to_leave_room().into(),
+            },
+        ];
+        if !self.props.recent_rooms.is_empty() {
+            lines.push(UsageInfoLine {
+                keys: vec!["r".into()],
+                description: strings.to_rejoin_room().into(),
+            });
+        }
+        if self
+            .props
+            .rooms
+            .iter()
+            .any(|room_state| Self::listed_category(room_state).is_some())
+        {
+            lines.push(UsageInfoLine {
+                keys: vec!["Space".into()],
+                description: strings.to_toggle_category().into(),
+            });
+        }
+
         UsageInfo {
-            description: Some("Select the room to talk in".into()),
-            lines: vec![
-                UsageInfoLine {
-                    keys: vec!["Esc".into()],
-                    description: "to cancel".into(),
-                },
-                UsageInfoLine {
-                    keys: vec!["↑".into(), "↓".into()],
-                    description: "to navigate".into(),
-                },
-                UsageInfoLine {
-                    keys: vec!["Enter".into()],
-                    description: "to join room".into(),
-                },
-            ],
+            description: Some(strings.select_room().into()),
+            lines,
         }
     }
 }
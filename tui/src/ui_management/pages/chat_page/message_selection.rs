@@ -0,0 +1,89 @@
+use crate::state_store::{MessageBoxItem, RoomData};
+
+/// [MessageSelection] is the message the user has picked in the message pane of the active room, to act on it
+///
+/// The message is kept by its id instead of its position, so the selection stays on it while new messages arrive
+/// or older ones are loaded. Notifications have no id and are skipped. If the selected message is removed,
+/// nothing is shown as selected until the user moves the selection again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageSelection {
+    room: String,
+    message_id: String,
+}
+
+impl MessageSelection {
+    /// Select the newest message of the room, None if it has no messages yet
+    pub fn newest(room_data: &RoomData) -> Option<Self> {
+        room_data
+            .messages
+            .iter()
+            .find_map(message_id)
+            .map(|message_id| MessageSelection {
+                room: room_data.name.clone(),
+                message_id: String::from(message_id),
+            })
+    }
+
+    pub fn room(&self) -> &str {
+        &self.room
+    }
+
+    /// The position of the selected message among the items of the room, the newest one being 0
+    pub fn position(&self, room_data: &RoomData) -> Option<usize> {
+        room_data
+            .messages
+            .iter()
+            .position(|item| message_id(item) == Some(self.message_id.as_str()))
+    }
+
+    /// Move the selection to the next older message, it stays put at the oldest one
+    pub fn select_older(&mut self, room_data: &RoomData) {
+        let Some(position) = self.position(room_data) else {
+            return self.select_newest(room_data);
+        };
+
+        if let Some(older) = room_data
+            .messages
+            .iter()
+            .skip(position + 1)
+            .find_map(message_id)
+        {
+            self.message_id = String::from(older);
+        }
+    }
+
+    /// Move the selection to the next newer message, it stays put at the newest one
+    pub fn select_newer(&mut self, room_data: &RoomData) {
+        let Some(position) = self.position(room_data) else {
+            return self.select_newest(room_data);
+        };
+
+        if let Some(newer) = room_data
+            .messages
+            .iter()
+            .take(position)
+            .filter_map(message_id)
+            .last()
+        {
+            self.message_id = String::from(newer);
+        }
+    }
+
+    fn select_newest(&mut self, room_data: &RoomData) {
+        if let Some(newest) = Self::newest(room_data) {
+            *self = newest;
+        }
+    }
+}
+
+/// The id of the item if it can be selected, i.e. if it is a message the server has broadcast
+fn message_id(item: &MessageBoxItem) -> Option<&str> {
+    match item {
+        MessageBoxItem::Message {
+            id,
+            pending_nonce: None,
+            ..
+        } => Some(id),
+        _ => None,
+    }
+}
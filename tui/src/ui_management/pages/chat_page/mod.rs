@@ -1,6 +1,8 @@
 #[allow(clippy::module_inception)]
 mod chat_page;
 mod components;
+mod message_selection;
 mod section;
 
 pub use chat_page::ChatPage;
+pub(super) use section::usage;
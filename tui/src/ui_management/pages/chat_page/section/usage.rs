@@ -3,6 +3,8 @@ use ratatui::{
     text::{Line, Span, Text},
 };
 
+use crate::accessibility::Accessibility;
+
 #[derive(Debug, Clone)]
 pub struct UsageInfoLine {
     pub keys: Vec<String>,
@@ -19,11 +21,11 @@ pub trait HasUsageInfo {
     fn usage_info(&self) -> UsageInfo;
 }
 
-fn key_to_span<'a>(key: &String) -> Span<'a> {
-    Span::from(format!("({})", key)).bold()
+fn key_to_span<'a>(key: &str, accessibility: &Accessibility) -> Span<'a> {
+    Span::from(format!("({})", accessibility.key_name(key))).bold()
 }
 
-pub fn widget_usage_to_text<'a>(usage: UsageInfo) -> Text<'a> {
+pub fn widget_usage_to_text<'a>(usage: UsageInfo, accessibility: &Accessibility) -> Text<'a> {
     let mut lines: Vec<Line> = vec![];
     if let Some(description) = usage.description {
         lines.push(Line::from(description));
@@ -32,22 +34,22 @@ pub fn widget_usage_to_text<'a>(usage: UsageInfo) -> Text<'a> {
     for wuk in usage.lines {
         let mut bindings: Vec<Span> = match wuk.keys.len() {
             0 => vec![],
-            1 => vec![key_to_span(&wuk.keys[0])],
+            1 => vec![key_to_span(&wuk.keys[0], accessibility)],
             2 => vec![
-                key_to_span(&wuk.keys[0]),
+                key_to_span(&wuk.keys[0], accessibility),
                 " or ".into(),
-                key_to_span(&wuk.keys[1]),
+                key_to_span(&wuk.keys[1], accessibility),
             ],
             _ => {
                 let mut bindings: Vec<Span> = Vec::with_capacity(wuk.keys.len() * 2);
 
                 for key in wuk.keys.iter().take(wuk.keys.len() - 1) {
-                    bindings.push(key_to_span(key));
+                    bindings.push(key_to_span(key, accessibility));
                     bindings.push(", ".into());
                 }
 
                 bindings.push("or".into());
-                bindings.push(key_to_span(wuk.keys.last().unwrap()));
+                bindings.push(key_to_span(wuk.keys.last().unwrap(), accessibility));
 
                 bindings
             }
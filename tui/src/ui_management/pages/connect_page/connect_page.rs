@@ -2,23 +2,52 @@ use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use ratatui::{prelude::*, widgets::*, Frame};
 use tokio::sync::mpsc::UnboundedSender;
 
+use crate::accessibility::Accessibility;
+use crate::locale::Locale;
+use crate::presentation::Presenter;
+use crate::server_address;
 use crate::state_store::ServerConnectionStatus;
 use crate::state_store::{action::Action, State};
 
 use crate::ui_management::components::input_box;
 use crate::ui_management::components::{input_box::InputBox, Component, ComponentRender};
+use crate::ui_management::pages::Page;
 
 struct Props {
+    accessibility: Accessibility,
+    locale: Locale,
     error_message: Option<String>,
+    /// The address from the settings, the input starts over with it when it changes
+    server_address: String,
+    /// The page to visit and the code to enter while logging in with the identity provider
+    authorization: Option<(String, String)>,
+    /// Whether a connection is being established, it can be cancelled meanwhile
+    is_connecting: bool,
 }
 
 impl From<&State> for Props {
     fn from(state: &State) -> Self {
         Props {
+            accessibility: state.accessibility,
+            locale: state.locale,
+            server_address: state.server_address.clone(),
+            authorization: if let ServerConnectionStatus::Authorizing {
+                verification_uri,
+                user_code,
+            } = &state.server_connection_status
+            {
+                Some((verification_uri.clone(), user_code.clone()))
+            } else {
+                None
+            },
+            is_connecting: matches!(
+                state.server_connection_status,
+                ServerConnectionStatus::Connecting | ServerConnectionStatus::Authorizing { .. }
+            ),
             error_message: if let ServerConnectionStatus::Errored { err } =
                 &state.server_connection_status
             {
-                Some(err.to_string())
+                Some(Presenter::new(state.locale, state.accessibility).connection_error(err))
             } else {
                 None
             },
@@ -38,7 +67,8 @@ pub struct ConnectPage {
 
 impl ConnectPage {
     fn connect_to_server(&mut self) {
-        if self.input_box.is_empty() {
+        // the problem with the address is shown instead of how to connect
+        if self.input_box.is_empty() || self.address_problem().is_err() {
             return;
         }
 
@@ -46,9 +76,16 @@ impl ConnectPage {
             addr: self.input_box.text().to_string(),
         });
     }
-}
 
-const DEFAULT_SERVER_ADDR: &str = "localhost:8080";
+    /// Nothing is wrong with an address not entered yet
+    fn address_problem(&self) -> Result<(), server_address::AddressProblem> {
+        if self.input_box.is_empty() {
+            Ok(())
+        } else {
+            server_address::check(self.input_box.text())
+        }
+    }
+}
 
 impl Component for ConnectPage {
     fn new(state: &State, action_tx: UnboundedSender<Action>) -> Self
@@ -56,7 +93,7 @@ impl Component for ConnectPage {
         Self: Sized,
     {
         let mut input_box = InputBox::new(state, action_tx.clone());
-        input_box.set_text(DEFAULT_SERVER_ADDR);
+        input_box.set_text(&state.server_address);
 
         ConnectPage {
             action_tx: action_tx.clone(),
@@ -72,8 +109,15 @@ impl Component for ConnectPage {
     where
         Self: Sized,
     {
+        let props = Props::from(state);
+        let mut input_box = self.input_box;
+        if props.server_address != self.props.server_address {
+            input_box.set_text(&props.server_address);
+        }
+
         ConnectPage {
-            props: Props::from(state),
+            props,
+            input_box,
             ..self
         }
     }
@@ -93,6 +137,9 @@ impl Component for ConnectPage {
             KeyCode::Enter => {
                 self.connect_to_server();
             }
+            KeyCode::Esc if self.props.is_connecting => {
+                let _ = self.action_tx.send(Action::CancelConnection);
+            }
             KeyCode::Char('q') => {
                 let _ = self.action_tx.send(Action::Exit);
             }
@@ -104,8 +151,11 @@ impl Component for ConnectPage {
     }
 }
 
+impl Page for ConnectPage {}
+
 impl ComponentRender<()> for ConnectPage {
     fn render(&self, frame: &mut Frame, _props: ()) {
+        let strings = self.props.locale.strings();
         let [_, vertical_centered, _] = *Layout::default()
             .direction(Direction::Vertical)
             .constraints(
@@ -155,31 +205,52 @@ impl ComponentRender<()> for ConnectPage {
         self.input_box.render(
             frame,
             input_box::RenderProps {
-                title: "Server Host and Port".into(),
+                title: strings.server_address_title().into(),
                 area: container_addr_input,
                 border_color: Color::Yellow,
                 show_cursor: true,
+                accessibility: self.props.accessibility,
             },
         );
 
-        let help_text = Paragraph::new(Text::from(Line::from(vec![
-            "Press ".into(),
-            "<Enter>".bold(),
-            " to connect".into(),
-        ])));
+        let help_text = Paragraph::new(Text::from(Line::from(
+            if let Some((verification_uri, user_code)) = self.props.authorization.as_ref() {
+                let [before_uri, before_code] = strings.log_in_parts();
+                vec![
+                    before_uri.into(),
+                    Span::from(verification_uri.clone()).bold(),
+                    before_code.into(),
+                    Span::from(user_code.clone()).bold(),
+                ]
+            } else if self.props.is_connecting {
+                let [before_key, after_key] = strings.connecting_parts();
+                vec![before_key.into(), "<Esc>".bold(), after_key.into()]
+            } else if let Err(problem) = self.address_problem() {
+                vec![Presenter::new(self.props.locale, self.props.accessibility)
+                    .address_problem(problem)
+                    .italic()]
+            } else {
+                let [before_key, after_key] = strings.press_to_connect_parts();
+                vec![before_key.into(), "<Enter>".bold(), after_key.into()]
+            },
+        )))
+        .wrap(Wrap { trim: true });
         frame.render_widget(help_text, container_help_text);
 
         let error_message = Paragraph::new(if let Some(err) = self.props.error_message.as_ref() {
-            Text::from(format!("Error: {}", err.as_str()))
+            Text::from(strings.error(err))
         } else {
             Text::from("")
         })
         .wrap(Wrap { trim: true })
-        .style(
+        .style(if self.props.accessibility.high_contrast {
+            // the message is prefixed with "Error:" already, blinking makes it harder to read
+            Style::default().add_modifier(Modifier::BOLD)
+        } else {
             Style::default()
                 .fg(Color::Red)
-                .add_modifier(Modifier::SLOW_BLINK | Modifier::ITALIC),
-        );
+                .add_modifier(Modifier::SLOW_BLINK | Modifier::ITALIC)
+        });
 
         frame.render_widget(error_message, container_error_message);
     }
@@ -0,0 +1,264 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use ratatui::{prelude::*, widgets::*, Frame};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    accessibility::Accessibility,
+    locale::Locale,
+    presentation::Presenter,
+    state_store::{action::Action, ServerConnectionStatus, State, TrafficLog},
+    ui_management::{
+        components::{Component, ComponentRender},
+        pages::{
+            usage::{widget_usage_to_text, UsageInfo, UsageInfoLine},
+            Navigation, Page,
+        },
+    },
+};
+
+/// What is known about a room, ordered by name
+struct RoomSummary {
+    name: String,
+    has_joined: bool,
+    user_count: usize,
+    /// The number of messages and notices kept for the room
+    stored_message_count: usize,
+}
+
+struct Props {
+    connection_status: ServerConnectionStatus,
+    user_id: String,
+    session_count: usize,
+    latency_ms: Option<u64>,
+    timer: usize,
+    is_focused: bool,
+    rooms: Vec<RoomSummary>,
+    /// The frames recorded by the traffic inspector, None while it is off
+    traffic_log: Option<TrafficLog>,
+    accessibility: Accessibility,
+    locale: Locale,
+}
+
+impl From<&State> for Props {
+    fn from(state: &State) -> Self {
+        let mut rooms = state
+            .room_data_map
+            .values()
+            .map(|room_data| RoomSummary {
+                name: room_data.name.clone(),
+                has_joined: room_data.has_joined,
+                user_count: room_data.users.len(),
+                stored_message_count: room_data.messages.len(),
+            })
+            .collect::<Vec<_>>();
+        rooms.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Props {
+            connection_status: state.server_connection_status.clone(),
+            user_id: state.user_id.clone(),
+            session_count: state.session_count,
+            latency_ms: state.latency_ms,
+            timer: state.timer,
+            is_focused: state.is_focused,
+            rooms,
+            traffic_log: state.traffic_log.clone(),
+            accessibility: state.accessibility,
+            locale: state.locale,
+        }
+    }
+}
+
+/// DebugPage shows what the client knows about the connection and the rooms, e.g. to attach to a bug report
+///
+/// The traffic inspector shows the latest frames sent and received, e.g. to debug the protocol against a
+/// modified server, and exports them to a file.
+pub struct DebugPage {
+    action_tx: UnboundedSender<Action>,
+    props: Props,
+    /// The number of lines of the traffic scrolled past, the newest frame is at the top
+    traffic_scroll: usize,
+}
+
+impl DebugPage {
+    fn usage_info(&self) -> UsageInfo {
+        let strings = self.props.locale.strings();
+
+        let mut lines = vec![UsageInfoLine {
+            keys: vec!["t".into()],
+            description: strings.to_toggle_traffic().into(),
+        }];
+        if self.props.traffic_log.is_some() {
+            lines.push(UsageInfoLine {
+                keys: vec!["↑".into(), "↓".into()],
+                description: strings.to_scroll_traffic().into(),
+            });
+            lines.push(UsageInfoLine {
+                keys: vec!["e".into()],
+                description: strings.to_export_traffic().into(),
+            });
+        }
+        lines.push(UsageInfoLine {
+            keys: vec!["Esc".into()],
+            description: strings.to_go_back().into(),
+        });
+
+        UsageInfo {
+            description: None,
+            lines,
+        }
+    }
+
+    fn render_traffic(&self, frame: &mut Frame, area: Rect, traffic_log: &TrafficLog) {
+        let accessibility = self.props.accessibility;
+        let strings = self.props.locale.strings();
+        let presenter = Presenter::new(self.props.locale, accessibility);
+
+        let mut lines = Vec::new();
+        if let Some(export_result) = traffic_log.export_result.as_ref() {
+            lines.push(Line::from(match export_result {
+                Ok(path) => strings.traffic_exported(path),
+                Err(err) => strings.traffic_not_exported(err),
+            }));
+            lines.push(Line::default());
+        }
+        // only the frames down to the bottom of the pane are presented, there may be hundreds of them
+        let bottom = self.traffic_scroll + area.height as usize;
+        for traffic_frame in traffic_log.frames() {
+            if lines.len() > bottom {
+                break;
+            }
+            lines.extend(presenter.traffic_frame(traffic_frame).lines);
+        }
+
+        let traffic_paragraph = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .scroll((self.traffic_scroll.min(u16::MAX as usize) as u16, 0))
+            .block(
+                accessibility
+                    .block()
+                    .title(strings.traffic_title(traffic_log.len())),
+            );
+        frame.render_widget(traffic_paragraph, area);
+    }
+}
+
+impl Component for DebugPage {
+    fn new(state: &State, action_tx: UnboundedSender<Action>) -> Self
+    where
+        Self: Sized,
+    {
+        DebugPage {
+            action_tx,
+            props: Props::from(state),
+            traffic_scroll: 0,
+        }
+    }
+
+    fn move_with_state(self, state: &State) -> Self
+    where
+        Self: Sized,
+    {
+        DebugPage {
+            props: Props::from(state),
+            ..self
+        }
+    }
+
+    fn name(&self) -> &str {
+        self.props.locale.strings().debug_title()
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) {
+        let _ = self.handle_page_key_event(key);
+    }
+}
+
+impl Page for DebugPage {
+    fn handle_page_key_event(&mut self, key: KeyEvent) -> Navigation {
+        if key.kind != KeyEventKind::Press {
+            return Navigation::Stay;
+        }
+
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => return Navigation::Back,
+            KeyCode::Char('t') => {
+                self.traffic_scroll = 0;
+                let _ = self.action_tx.send(Action::ToggleTrafficInspector);
+            }
+            KeyCode::Char('e') if self.props.traffic_log.is_some() => {
+                let _ = self.action_tx.send(Action::ExportTraffic);
+            }
+            KeyCode::Up => self.traffic_scroll = self.traffic_scroll.saturating_sub(1),
+            KeyCode::Down if self.props.traffic_log.is_some() => self.traffic_scroll += 1,
+            _ => {}
+        }
+
+        Navigation::Stay
+    }
+}
+
+impl ComponentRender<()> for DebugPage {
+    fn render(&self, frame: &mut Frame, _props: ()) {
+        let accessibility = self.props.accessibility;
+        let strings = self.props.locale.strings();
+        let presenter = Presenter::new(self.props.locale, accessibility);
+        let usage_text = widget_usage_to_text(self.usage_info(), &accessibility);
+        let area = frame.area();
+        let [details, traffic, usage] = Layout::vertical([
+            Constraint::Min(1),
+            // the traffic takes up most of the page while it is recorded
+            Constraint::Percentage(if self.props.traffic_log.is_some() {
+                60
+            } else {
+                0
+            }),
+            Constraint::Length(usage_text.height() as u16 + 2),
+        ])
+        .areas(area);
+
+        let mut lines = vec![
+            Line::from(strings.server(&presenter.connection_status(&self.props.connection_status))),
+            Line::from(strings.user(&self.props.user_id, self.props.session_count)),
+            Line::from(strings.latency(self.props.latency_ms)),
+            Line::from(strings.chatting_for(self.props.timer)),
+            Line::from(strings.terminal_details(area.width, area.height, self.props.is_focused)),
+            Line::from(
+                strings.joined_rooms(
+                    self.props
+                        .rooms
+                        .iter()
+                        .filter(|room| room.has_joined)
+                        .count(),
+                    self.props.rooms.len(),
+                ),
+            ),
+        ];
+        lines.extend(
+            self.props
+                .rooms
+                .iter()
+                .filter(|room| room.has_joined || room.stored_message_count > 0)
+                .map(|room| {
+                    Line::from(strings.room_details(
+                        &room.name,
+                        room.user_count,
+                        room.stored_message_count,
+                    ))
+                }),
+        );
+
+        let details_paragraph = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(accessibility.block().title(strings.debug_title()));
+        frame.render_widget(details_paragraph, details);
+
+        if let Some(traffic_log) = self.props.traffic_log.as_ref() {
+            self.render_traffic(frame, traffic, traffic_log);
+        }
+
+        let usage_paragraph = Paragraph::new(usage_text)
+            .wrap(Wrap { trim: true })
+            .block(accessibility.block().title(strings.usage_title()));
+        frame.render_widget(usage_paragraph, usage);
+    }
+}
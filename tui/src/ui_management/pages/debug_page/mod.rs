@@ -0,0 +1,4 @@
+#[allow(clippy::module_inception)]
+mod debug_page;
+
+pub use debug_page::DebugPage;
@@ -0,0 +1,226 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::{prelude::*, widgets::*, Frame};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::accessibility::Accessibility;
+use crate::locale::Locale;
+use crate::presentation::Presenter;
+use crate::state_store::ServerConnectionStatus;
+use crate::state_store::{action::Action, State};
+
+use crate::ui_management::components::input_box;
+use crate::ui_management::components::{input_box::InputBox, Component, ComponentRender};
+use crate::ui_management::pages::Page;
+
+struct Props {
+    accessibility: Accessibility,
+    locale: Locale,
+    /// The name from the settings, the input starts over with it when it changes
+    username: Option<String>,
+    /// Whether the server has not answered the last login yet
+    is_pending: bool,
+    error_message: Option<String>,
+}
+
+impl From<&State> for Props {
+    fn from(state: &State) -> Self {
+        let (is_pending, rejection) = match &state.server_connection_status {
+            ServerConnectionStatus::LoggingIn {
+                is_pending,
+                rejection,
+                ..
+            } => (*is_pending, rejection.as_ref()),
+            _ => (false, None),
+        };
+
+        Props {
+            accessibility: state.accessibility,
+            locale: state.locale,
+            username: state.username.clone(),
+            is_pending,
+            error_message: rejection
+                .map(|err| Presenter::new(state.locale, state.accessibility).connection_error(err)),
+        }
+    }
+}
+
+/// LoginPage asks for the name to log in with once connected, and again if the server refuses it
+pub struct LoginPage {
+    /// Action sender
+    pub action_tx: UnboundedSender<Action>,
+    // Mapped Props from State
+    props: Props,
+    // Internal Components
+    input_box: InputBox,
+}
+
+impl LoginPage {
+    fn log_in(&mut self) {
+        if self.props.is_pending {
+            return;
+        }
+
+        let _ = self.action_tx.send(Action::Login {
+            username: Some(String::from(self.input_box.text().trim()))
+                .filter(|username| !username.is_empty()),
+        });
+    }
+}
+
+impl Component for LoginPage {
+    fn new(state: &State, action_tx: UnboundedSender<Action>) -> Self
+    where
+        Self: Sized,
+    {
+        let mut input_box = InputBox::new(state, action_tx.clone());
+        input_box.set_text(state.username.as_deref().unwrap_or_default());
+
+        LoginPage {
+            action_tx: action_tx.clone(),
+            //
+            props: Props::from(state),
+            //
+            input_box,
+        }
+        .move_with_state(state)
+    }
+
+    fn move_with_state(self, state: &State) -> Self
+    where
+        Self: Sized,
+    {
+        let props = Props::from(state);
+        let mut input_box = self.input_box;
+        if props.username != self.props.username {
+            input_box.set_text(props.username.as_deref().unwrap_or_default());
+        }
+
+        LoginPage {
+            props,
+            input_box,
+            ..self
+        }
+    }
+
+    fn name(&self) -> &str {
+        "Login Page"
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) {
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+
+        match key.code {
+            KeyCode::Enter => {
+                self.log_in();
+            }
+            // not typed into the name
+            KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                if !self.props.is_pending {
+                    let _ = self.action_tx.send(Action::LoginAsGuest);
+                }
+            }
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let _ = self.action_tx.send(Action::Exit);
+            }
+            _ => self.input_box.handle_key_event(key),
+        }
+    }
+}
+
+impl Page for LoginPage {}
+
+impl ComponentRender<()> for LoginPage {
+    fn render(&self, frame: &mut Frame, _props: ()) {
+        let strings = self.props.locale.strings();
+        let [_, vertical_centered, _] = *Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Ratio(1, 3),
+                    Constraint::Min(1),
+                    Constraint::Ratio(1, 3),
+                ]
+                .as_ref(),
+            )
+            .split(frame.area())
+        else {
+            panic!("The main layout should have 3 chunks")
+        };
+
+        let [_, both_centered, _] = *Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(
+                [
+                    Constraint::Ratio(1, 3),
+                    Constraint::Min(1),
+                    Constraint::Ratio(1, 3),
+                ]
+                .as_ref(),
+            )
+            .split(vertical_centered)
+        else {
+            panic!("The horizontal layout should have 3 chunks")
+        };
+
+        let [container_username_input, container_help_text, container_error_message] =
+            *Layout::default()
+                .direction(Direction::Vertical)
+                .constraints(
+                    [
+                        Constraint::Length(3),
+                        Constraint::Length(3),
+                        Constraint::Min(1),
+                    ]
+                    .as_ref(),
+                )
+                .split(both_centered)
+        else {
+            panic!("The left layout should have 3 chunks")
+        };
+
+        self.input_box.render(
+            frame,
+            input_box::RenderProps {
+                title: strings.username_setting().into(),
+                area: container_username_input,
+                border_color: Color::Yellow,
+                show_cursor: !self.props.is_pending,
+                accessibility: self.props.accessibility,
+            },
+        );
+
+        let help_text = Paragraph::new(Text::from(Line::from(if self.props.is_pending {
+            vec![strings.waiting_for_login().into()]
+        } else {
+            let [before_key, between_keys, after_keys] = strings.press_to_log_in_parts();
+            vec![
+                before_key.into(),
+                "<Enter>".bold(),
+                between_keys.into(),
+                "<Ctrl+G>".bold(),
+                after_keys.into(),
+            ]
+        })))
+        .wrap(Wrap { trim: true });
+        frame.render_widget(help_text, container_help_text);
+
+        let error_message = Paragraph::new(if let Some(err) = self.props.error_message.as_ref() {
+            Text::from(strings.error(err))
+        } else {
+            Text::from("")
+        })
+        .wrap(Wrap { trim: true })
+        .style(if self.props.accessibility.high_contrast {
+            // the message is prefixed with "Error:" already, blinking makes it harder to read
+            Style::default().add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+                .fg(Color::Red)
+                .add_modifier(Modifier::SLOW_BLINK | Modifier::ITALIC)
+        });
+
+        frame.render_widget(error_message, container_error_message);
+    }
+}
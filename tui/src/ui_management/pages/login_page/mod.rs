@@ -0,0 +1,4 @@
+#[allow(clippy::module_inception)]
+mod login_page;
+
+pub use login_page::LoginPage;
@@ -1,55 +1,170 @@
 use crossterm::event::KeyEvent;
-use ratatui::Frame;
+use ratatui::{
+    layout::{Alignment, Constraint, Layout},
+    widgets::{Paragraph, Wrap},
+    Frame,
+};
 use tokio::sync::mpsc::UnboundedSender;
 
-use crate::state_store::{action::Action, ServerConnectionStatus, State};
+use crate::{
+    locale::Locale,
+    state_store::{action::Action, ServerConnectionStatus, State},
+};
 
-use self::{chat_page::ChatPage, connect_page::ConnectPage};
+use self::{
+    chat_page::{usage, ChatPage},
+    connect_page::ConnectPage,
+    debug_page::DebugPage,
+    login_page::LoginPage,
+    onboarding_page::OnboardingPage,
+    room_browser_page::RoomBrowserPage,
+    settings_page::SettingsPage,
+};
 
 use super::components::{Component, ComponentRender};
 
 mod chat_page;
 mod connect_page;
+mod debug_page;
+mod login_page;
+mod onboarding_page;
+mod room_browser_page;
+mod settings_page;
 
-enum ActivePage {
-    ChatPage,
-    ConnectPage,
+/// The full-screen views of the application
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PageKind {
+    /// Guides new users through the settings before connecting for the first time
+    Onboarding,
+    Connect,
+    /// Asks for the name to log in with once connected
+    Login,
+    /// Every room of the server, to find one to open
+    RoomBrowser,
+    Chat,
+    Settings,
+    /// What the client knows about the connection and the rooms, e.g. for bug reports
+    Debug,
 }
 
+impl PageKind {
+    /// Whether the page shows what is only known while connected to a server
+    fn requires_connection(&self) -> bool {
+        matches!(self, PageKind::RoomBrowser | PageKind::Chat)
+    }
+}
+
+/// Where to go once a page has handled a key
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Navigation {
+    Stay,
+    /// Show the page on top of the current one, going back returns to the current one
+    Open(PageKind),
+    /// Return to the page shown before the current one
+    Back,
+}
+
+/// [Page] is a full-screen view shown by the [AppRouter]
+///
+/// Only the page on top of the navigation stack receives the keys, it decides where to navigate to.
+pub trait Page: Component + ComponentRender<()> {
+    fn handle_page_key_event(&mut self, key: KeyEvent) -> Navigation {
+        self.handle_key_event(key);
+
+        Navigation::Stay
+    }
+}
+
+/// The smallest terminal the pages can be laid out in, smaller ones are asked to be resized instead
+const MIN_WIDTH: u16 = 40;
+const MIN_HEIGHT: u16 = 12;
+
 struct Props {
-    active_page: ActivePage,
+    /// The page at the bottom of the navigation stack, depending on the connection
+    root_page: PageKind,
+    locale: Locale,
 }
 
 impl From<&State> for Props {
     fn from(state: &State) -> Self {
         Props {
-            active_page: match state.server_connection_status {
-                ServerConnectionStatus::Connected { .. } => ActivePage::ChatPage,
-                _ => ActivePage::ConnectPage,
+            root_page: match state.server_connection_status {
+                ServerConnectionStatus::Connected { .. }
+                | ServerConnectionStatus::Replaying { .. } => PageKind::Chat,
+                ServerConnectionStatus::LoggingIn { .. } => PageKind::Login,
+                _ if state.is_first_run => PageKind::Onboarding,
+                _ => PageKind::Connect,
             },
+            locale: state.locale,
         }
     }
 }
 
+/// [AppRouter] shows the page on top of its navigation stack
+///
+/// The root page follows the connection, the chat page once logged in, the login page while logging in
+/// and the connect page otherwise, or the onboarding page as long as the client has not been set up.
+/// The pages opened on top of it are closed again when the root page changes, as far as they need a connection.
 pub struct AppRouter {
     props: Props,
+    /// The pages opened on top of the root page, the last one is shown
+    navigation_stack: Vec<PageKind>,
     //
     chat_page: ChatPage,
+    onboarding_page: OnboardingPage,
     connect_page: ConnectPage,
+    login_page: LoginPage,
+    room_browser_page: RoomBrowserPage,
+    settings_page: SettingsPage,
+    debug_page: DebugPage,
 }
 
 impl AppRouter {
-    fn get_active_page_component(&self) -> &dyn Component {
-        match self.props.active_page {
-            ActivePage::ChatPage => &self.chat_page,
-            ActivePage::ConnectPage => &self.connect_page,
+    fn active_page(&self) -> PageKind {
+        self.navigation_stack
+            .last()
+            .copied()
+            .unwrap_or(self.props.root_page)
+    }
+
+    fn get_page(&self, page: PageKind) -> &dyn Page {
+        match page {
+            PageKind::Onboarding => &self.onboarding_page,
+            PageKind::Connect => &self.connect_page,
+            PageKind::Login => &self.login_page,
+            PageKind::RoomBrowser => &self.room_browser_page,
+            PageKind::Chat => &self.chat_page,
+            PageKind::Settings => &self.settings_page,
+            PageKind::Debug => &self.debug_page,
         }
     }
 
-    fn get_active_page_component_mut(&mut self) -> &mut dyn Component {
-        match self.props.active_page {
-            ActivePage::ChatPage => &mut self.chat_page,
-            ActivePage::ConnectPage => &mut self.connect_page,
+    fn get_page_mut(&mut self, page: PageKind) -> &mut dyn Page {
+        match page {
+            PageKind::Onboarding => &mut self.onboarding_page,
+            PageKind::Connect => &mut self.connect_page,
+            PageKind::Login => &mut self.login_page,
+            PageKind::RoomBrowser => &mut self.room_browser_page,
+            PageKind::Chat => &mut self.chat_page,
+            PageKind::Settings => &mut self.settings_page,
+            PageKind::Debug => &mut self.debug_page,
+        }
+    }
+
+    fn navigate(&mut self, navigation: Navigation) {
+        match navigation {
+            Navigation::Stay => {}
+            // a page opened again is brought back instead of being stacked twice
+            Navigation::Open(page) if page == self.props.root_page => self.navigation_stack.clear(),
+            Navigation::Open(page) => {
+                if let Some(idx) = self.navigation_stack.iter().position(|open| *open == page) {
+                    self.navigation_stack.truncate(idx);
+                }
+                self.navigation_stack.push(page);
+            }
+            Navigation::Back => {
+                self.navigation_stack.pop();
+            }
         }
     }
 }
@@ -61,9 +176,15 @@ impl Component for AppRouter {
     {
         AppRouter {
             props: Props::from(state),
+            navigation_stack: Vec::new(),
             //
             chat_page: ChatPage::new(state, action_tx.clone()),
+            onboarding_page: OnboardingPage::new(state, action_tx.clone()),
             connect_page: ConnectPage::new(state, action_tx.clone()),
+            login_page: LoginPage::new(state, action_tx.clone()),
+            room_browser_page: RoomBrowserPage::new(state, action_tx.clone()),
+            settings_page: SettingsPage::new(state, action_tx.clone()),
+            debug_page: DebugPage::new(state, action_tx.clone()),
         }
         .move_with_state(state)
     }
@@ -72,29 +193,72 @@ impl Component for AppRouter {
     where
         Self: Sized,
     {
+        let props = Props::from(state);
+        let mut navigation_stack = self.navigation_stack;
+        if props.root_page != self.props.root_page {
+            navigation_stack.retain(|page| !page.requires_connection());
+        }
+
         AppRouter {
-            props: Props::from(state),
+            props,
+            navigation_stack,
             //
             chat_page: self.chat_page.move_with_state(state),
+            onboarding_page: self.onboarding_page.move_with_state(state),
             connect_page: self.connect_page.move_with_state(state),
+            login_page: self.login_page.move_with_state(state),
+            room_browser_page: self.room_browser_page.move_with_state(state),
+            settings_page: self.settings_page.move_with_state(state),
+            debug_page: self.debug_page.move_with_state(state),
         }
     }
 
     // route all functions to the active page
     fn name(&self) -> &str {
-        self.get_active_page_component().name()
+        self.get_page(self.active_page()).name()
     }
 
     fn handle_key_event(&mut self, key: KeyEvent) {
-        self.get_active_page_component_mut().handle_key_event(key)
+        let navigation = self
+            .get_page_mut(self.active_page())
+            .handle_page_key_event(key);
+
+        self.navigate(navigation);
+    }
+}
+
+impl AppRouter {
+    fn render_too_small(&self, frame: &mut Frame) {
+        let area = frame.area();
+        let message = self.props.locale.strings().terminal_too_small(
+            area.width,
+            area.height,
+            MIN_WIDTH,
+            MIN_HEIGHT,
+        );
+
+        // vertically centered as far as the wrapped message allows
+        let [_, middle, _] = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(3),
+            Constraint::Fill(1),
+        ])
+        .areas(area);
+        let paragraph = Paragraph::new(message)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true });
+        frame.render_widget(paragraph, middle);
     }
 }
 
 impl ComponentRender<()> for AppRouter {
     fn render(&self, frame: &mut Frame, props: ()) {
-        match self.props.active_page {
-            ActivePage::ChatPage => self.chat_page.render(frame, props),
-            ActivePage::ConnectPage => self.connect_page.render(frame, props),
+        // the layouts of the pages would overlap or panic in a smaller terminal
+        let area = frame.area();
+        if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+            return self.render_too_small(frame);
         }
+
+        self.get_page(self.active_page()).render(frame, props);
     }
 }
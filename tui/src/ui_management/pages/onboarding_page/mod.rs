@@ -0,0 +1,4 @@
+#[allow(clippy::module_inception)]
+mod onboarding_page;
+
+pub use onboarding_page::OnboardingPage;
@@ -0,0 +1,335 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::{prelude::*, widgets::*, Frame};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    accessibility::Accessibility,
+    config::ClientConfig,
+    locale::Locale,
+    state_store::{action::Action, State},
+    ui_management::{
+        components::{
+            input_box::{self, InputBox},
+            Component, ComponentRender,
+        },
+        pages::{
+            usage::{widget_usage_to_text, UsageInfo, UsageInfoLine},
+            Navigation, Page,
+        },
+    },
+};
+
+/// The steps of the wizard, in the order they are taken
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Step {
+    Username,
+    ServerAddress,
+    Theme,
+    AutoJoinRooms,
+}
+
+impl Step {
+    const ALL: [Step; 4] = [
+        Step::Username,
+        Step::ServerAddress,
+        Step::Theme,
+        Step::AutoJoinRooms,
+    ];
+
+    fn position(&self) -> usize {
+        Step::ALL.iter().position(|step| step == self).unwrap_or(0)
+    }
+}
+
+/// What has been picked so far, saved to the config file once the wizard is finished
+#[derive(Debug, Default)]
+struct Draft {
+    username: String,
+    server_address: String,
+    high_contrast: bool,
+    auto_join_rooms: String,
+}
+
+struct Props {
+    /// The settings in effect, the picked ones are added to them
+    config: ClientConfig,
+    accessibility: Accessibility,
+    locale: Locale,
+}
+
+impl From<&State> for Props {
+    fn from(state: &State) -> Self {
+        Props {
+            config: state.config(),
+            accessibility: state.accessibility,
+            locale: state.locale,
+        }
+    }
+}
+
+/// OnboardingPage guides new users through the settings that matter before connecting for the first time
+///
+/// It is shown instead of the connect page as long as there is no config file, see [ClientConfig].
+pub struct OnboardingPage {
+    action_tx: UnboundedSender<Action>,
+    props: Props,
+    step: Step,
+    draft: Draft,
+    /// The value being typed for the current step, unless it is picked from a list
+    input_box: InputBox,
+}
+
+impl OnboardingPage {
+    /// The text typed for the given step
+    fn draft_text_mut(&mut self, step: Step) -> Option<&mut String> {
+        match step {
+            Step::Username => Some(&mut self.draft.username),
+            Step::ServerAddress => Some(&mut self.draft.server_address),
+            Step::AutoJoinRooms => Some(&mut self.draft.auto_join_rooms),
+            Step::Theme => None,
+        }
+    }
+
+    fn go_to(&mut self, step: Step) {
+        let text = self.input_box.text().trim().to_string();
+        if let Some(draft_text) = self.draft_text_mut(self.step) {
+            *draft_text = text;
+        }
+
+        self.step = step;
+        let text = self.draft_text_mut(step).cloned().unwrap_or_default();
+        self.input_box.set_text(&text);
+    }
+
+    fn next_step(&mut self) {
+        // the address can not be empty, there would be nothing to connect to
+        if self.step == Step::ServerAddress && self.input_box.text().trim().is_empty() {
+            return;
+        }
+
+        match Step::ALL.get(self.step.position() + 1) {
+            Some(step) => self.go_to(*step),
+            None => {
+                self.go_to(self.step);
+                self.finish();
+            }
+        }
+    }
+
+    fn previous_step(&mut self) {
+        if let Some(step) = self.step.position().checked_sub(1) {
+            self.go_to(Step::ALL[step]);
+        }
+    }
+
+    /// Saves the picked settings and connects to the picked server
+    fn finish(&mut self) {
+        let mut config = self.props.config.clone();
+        let mut accessibility = self.props.accessibility;
+        accessibility.high_contrast = self.draft.high_contrast;
+        config.accessibility = Some(accessibility);
+        config.username = Some(self.draft.username.clone()).filter(|username| !username.is_empty());
+        config.server_address = Some(self.draft.server_address.clone());
+        config.auto_join_rooms = Some(
+            self.draft
+                .auto_join_rooms
+                .split(',')
+                .map(|room| room.trim().trim_start_matches('#'))
+                .filter(|room| !room.is_empty())
+                .map(String::from)
+                .collect(),
+        );
+
+        let _ = self.action_tx.send(Action::UpdateSettings {
+            config: Box::new(config),
+        });
+        let _ = self.action_tx.send(Action::ConnectToServerRequest {
+            addr: self.draft.server_address.clone(),
+        });
+    }
+
+    /// The accessibility settings as they will be with the picked theme, the wizard previews it
+    fn accessibility(&self) -> Accessibility {
+        Accessibility {
+            high_contrast: self.draft.high_contrast,
+            ..self.props.accessibility
+        }
+    }
+
+    fn title_of(&self, step: Step) -> &'static str {
+        let strings = self.props.locale.strings();
+
+        match step {
+            Step::Username => strings.username_setting(),
+            Step::ServerAddress => strings.server_address_setting(),
+            Step::Theme => strings.theme_setting(),
+            Step::AutoJoinRooms => strings.auto_join_rooms_setting(),
+        }
+    }
+
+    fn prompt_of(&self, step: Step) -> &'static str {
+        let strings = self.props.locale.strings();
+
+        match step {
+            Step::Username => strings.pick_username(),
+            Step::ServerAddress => strings.pick_server_address(),
+            Step::Theme => strings.pick_theme(),
+            Step::AutoJoinRooms => strings.pick_auto_join_rooms(),
+        }
+    }
+
+    fn usage_info(&self) -> UsageInfo {
+        let strings = self.props.locale.strings();
+
+        let mut lines = Vec::new();
+        if self.step == Step::Theme {
+            lines.push(UsageInfoLine {
+                keys: vec!["↑".into(), "↓".into()],
+                description: strings.to_navigate().into(),
+            });
+        }
+        lines.push(UsageInfoLine {
+            keys: vec!["Enter".into()],
+            description: if self.step == Step::AutoJoinRooms {
+                strings.to_save_and_connect().into()
+            } else {
+                strings.to_continue().into()
+            },
+        });
+        if self.step != Step::Username {
+            lines.push(UsageInfoLine {
+                keys: vec!["Esc".into()],
+                description: strings.to_go_back().into(),
+            });
+        }
+
+        UsageInfo {
+            description: Some(strings.onboarding_intro().into()),
+            lines,
+        }
+    }
+}
+
+impl Component for OnboardingPage {
+    fn new(state: &State, action_tx: UnboundedSender<Action>) -> Self
+    where
+        Self: Sized,
+    {
+        OnboardingPage {
+            action_tx: action_tx.clone(),
+            props: Props::from(state),
+            step: Step::Username,
+            draft: Draft {
+                server_address: state.server_address.clone(),
+                high_contrast: state.accessibility.high_contrast,
+                ..Draft::default()
+            },
+            input_box: InputBox::new(state, action_tx),
+        }
+    }
+
+    fn move_with_state(self, state: &State) -> Self
+    where
+        Self: Sized,
+    {
+        OnboardingPage {
+            props: Props::from(state),
+            ..self
+        }
+    }
+
+    fn name(&self) -> &str {
+        self.props.locale.strings().welcome_title()
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) {
+        let _ = self.handle_page_key_event(key);
+    }
+}
+
+impl Page for OnboardingPage {
+    fn handle_page_key_event(&mut self, key: KeyEvent) -> Navigation {
+        if key.kind != KeyEventKind::Press {
+            return Navigation::Stay;
+        }
+
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let _ = self.action_tx.send(Action::Exit);
+            }
+            KeyCode::Enter => self.next_step(),
+            KeyCode::Esc => self.previous_step(),
+            KeyCode::Up | KeyCode::Down if self.step == Step::Theme => {
+                self.draft.high_contrast = !self.draft.high_contrast;
+            }
+            _ if self.step != Step::Theme => self.input_box.handle_key_event(key),
+            _ => {}
+        }
+
+        Navigation::Stay
+    }
+}
+
+impl ComponentRender<()> for OnboardingPage {
+    fn render(&self, frame: &mut Frame, _props: ()) {
+        let accessibility = self.accessibility();
+        let strings = self.props.locale.strings();
+        let usage_text = widget_usage_to_text(self.usage_info(), &accessibility);
+        let [_, centered, _] = Layout::horizontal([
+            Constraint::Ratio(1, 6),
+            Constraint::Min(1),
+            Constraint::Ratio(1, 6),
+        ])
+        .areas(frame.area());
+        let [step, prompt, value, _, usage] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Length(2),
+            Constraint::Length(if self.step == Step::Theme { 4 } else { 3 }),
+            Constraint::Min(0),
+            Constraint::Length(usage_text.height() as u16 + 2),
+        ])
+        .areas(centered);
+
+        let step_paragraph = Paragraph::new(Line::from(vec![
+            Span::from(strings.welcome_title()).bold(),
+            " ".into(),
+            strings
+                .onboarding_step(self.step.position() + 1, Step::ALL.len())
+                .into(),
+        ]));
+        frame.render_widget(step_paragraph, step);
+
+        let prompt_paragraph = Paragraph::new(self.prompt_of(self.step)).wrap(Wrap { trim: true });
+        frame.render_widget(prompt_paragraph, prompt);
+
+        if self.step == Step::Theme {
+            let items = vec![
+                ListItem::new(strings.regular_theme()),
+                ListItem::new(strings.high_contrast_setting()),
+            ];
+            let mut list_state =
+                ListState::default().with_selected(Some(self.draft.high_contrast as usize));
+            let list = List::new(items)
+                .block(accessibility.block().title(self.title_of(self.step)))
+                .highlight_symbol(if accessibility.ascii { "> " } else { "❯ " })
+                .highlight_style(Style::new().reversed());
+            frame.render_stateful_widget(list, value, &mut list_state);
+        } else {
+            self.input_box.render(
+                frame,
+                input_box::RenderProps {
+                    title: String::from(self.title_of(self.step)),
+                    area: value,
+                    border_color: Color::Yellow,
+                    show_cursor: true,
+                    accessibility,
+                },
+            );
+        }
+
+        let usage_paragraph = Paragraph::new(usage_text)
+            .wrap(Wrap { trim: true })
+            .block(accessibility.block().title(strings.usage_title()));
+        frame.render_widget(usage_paragraph, usage);
+    }
+}
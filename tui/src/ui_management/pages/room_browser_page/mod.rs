@@ -0,0 +1,4 @@
+#[allow(clippy::module_inception)]
+mod room_browser_page;
+
+pub use room_browser_page::RoomBrowserPage;
@@ -0,0 +1,248 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use ratatui::{prelude::*, widgets::*, Frame};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    accessibility::Accessibility,
+    locale::Locale,
+    state_store::{action::Action, State},
+    ui_management::{
+        components::{
+            input_box::{self, InputBox},
+            Component, ComponentRender,
+        },
+        pages::{
+            usage::{widget_usage_to_text, UsageInfo, UsageInfoLine},
+            Navigation, Page,
+        },
+    },
+};
+
+struct RoomEntry {
+    name: String,
+    description: String,
+    encrypted: bool,
+    archived: bool,
+    has_joined: bool,
+}
+
+struct Props {
+    /// Every room of the server, ordered by name
+    rooms: Vec<RoomEntry>,
+    accessibility: Accessibility,
+    locale: Locale,
+}
+
+impl From<&State> for Props {
+    fn from(state: &State) -> Self {
+        let mut rooms = state
+            .room_data_map
+            .values()
+            .map(|room_data| RoomEntry {
+                name: room_data.name.clone(),
+                description: room_data.description.clone(),
+                encrypted: room_data.encrypted,
+                archived: room_data.archived,
+                has_joined: room_data.has_joined,
+            })
+            .collect::<Vec<_>>();
+        rooms.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Props {
+            rooms,
+            accessibility: state.accessibility,
+            locale: state.locale,
+        }
+    }
+}
+
+/// RoomBrowserPage lists every room of the server along with its description, filtered by what the user types
+pub struct RoomBrowserPage {
+    action_tx: UnboundedSender<Action>,
+    props: Props,
+    /// The text the names and descriptions of the listed rooms contain
+    filter_input: InputBox,
+    list_state: ListState,
+}
+
+impl RoomBrowserPage {
+    fn filtered_rooms(&self) -> Vec<&RoomEntry> {
+        let filter = self.filter_input.text().trim().to_lowercase();
+
+        self.props
+            .rooms
+            .iter()
+            .filter(|room| {
+                room.name.to_lowercase().contains(&filter)
+                    || room.description.to_lowercase().contains(&filter)
+            })
+            .collect()
+    }
+
+    fn select_next(&mut self, step: isize) {
+        let len = self.filtered_rooms().len();
+        if len == 0 {
+            self.list_state.select(None);
+            return;
+        }
+
+        let selected = match self.list_state.selected() {
+            Some(selected) => (selected as isize + step).rem_euclid(len as isize) as usize,
+            None => 0,
+        };
+        self.list_state.select(Some(selected));
+    }
+
+    fn usage_info(&self) -> UsageInfo {
+        let strings = self.props.locale.strings();
+
+        UsageInfo {
+            description: Some(strings.filter_rooms().into()),
+            lines: vec![
+                UsageInfoLine {
+                    keys: vec!["↑".into(), "↓".into()],
+                    description: strings.to_navigate().into(),
+                },
+                UsageInfoLine {
+                    keys: vec!["Enter".into()],
+                    description: strings.to_open_room().into(),
+                },
+                UsageInfoLine {
+                    keys: vec!["Esc".into()],
+                    description: strings.to_go_back().into(),
+                },
+            ],
+        }
+    }
+}
+
+impl Component for RoomBrowserPage {
+    fn new(state: &State, action_tx: UnboundedSender<Action>) -> Self
+    where
+        Self: Sized,
+    {
+        RoomBrowserPage {
+            action_tx: action_tx.clone(),
+            props: Props::from(state),
+            filter_input: InputBox::new(state, action_tx),
+            list_state: ListState::default(),
+        }
+    }
+
+    fn move_with_state(self, state: &State) -> Self
+    where
+        Self: Sized,
+    {
+        RoomBrowserPage {
+            props: Props::from(state),
+            ..self
+        }
+    }
+
+    fn name(&self) -> &str {
+        self.props.locale.strings().room_browser_title()
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) {
+        let _ = self.handle_page_key_event(key);
+    }
+}
+
+impl Page for RoomBrowserPage {
+    fn handle_page_key_event(&mut self, key: KeyEvent) -> Navigation {
+        if key.kind != KeyEventKind::Press {
+            return Navigation::Stay;
+        }
+
+        match key.code {
+            KeyCode::Up => self.select_next(-1),
+            KeyCode::Down => self.select_next(1),
+            KeyCode::Enter => {
+                let selected = self.list_state.selected().unwrap_or(0);
+                let Some(room) = self
+                    .filtered_rooms()
+                    .get(selected)
+                    .map(|room| room.name.clone())
+                else {
+                    return Navigation::Stay;
+                };
+
+                let _ = self.action_tx.send(Action::SelectRoom { room });
+                // the browser starts over the next time it is opened
+                self.filter_input.reset();
+                self.list_state.select(None);
+
+                return Navigation::Back;
+            }
+            KeyCode::Esc => return Navigation::Back,
+            _ => {
+                self.filter_input.handle_key_event(key);
+                // the filter has changed the list, the first match is picked
+                self.list_state.select(Some(0));
+            }
+        }
+
+        Navigation::Stay
+    }
+}
+
+impl ComponentRender<()> for RoomBrowserPage {
+    fn render(&self, frame: &mut Frame, _props: ()) {
+        let accessibility = self.props.accessibility;
+        let strings = self.props.locale.strings();
+        let usage_text = widget_usage_to_text(self.usage_info(), &accessibility);
+        let [filter, rooms, usage] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(1),
+            Constraint::Length(usage_text.height() as u16 + 2),
+        ])
+        .areas(frame.area());
+
+        self.filter_input.render(
+            frame,
+            input_box::RenderProps {
+                title: strings.room_filter_title().into(),
+                area: filter,
+                border_color: Color::Yellow,
+                show_cursor: true,
+                accessibility,
+            },
+        );
+
+        let filtered_rooms = self.filtered_rooms();
+        let items = if filtered_rooms.is_empty() {
+            vec![ListItem::new(strings.no_rooms_found())]
+        } else {
+            filtered_rooms
+                .iter()
+                .map(|room| {
+                    let mut spans = vec![Span::from(format!("#{}", room.name)).bold()];
+                    if room.encrypted {
+                        spans.push(strings.encrypted_room_marker().into());
+                    }
+                    if room.archived {
+                        spans.push(strings.archived_room_marker().into());
+                    }
+                    if room.has_joined {
+                        spans.push(strings.joined_room_marker().into());
+                    }
+                    spans.push(" ".into());
+                    spans.push(Span::from(room.description.clone()).italic());
+
+                    ListItem::new(Line::from(spans))
+                })
+                .collect()
+        };
+        let mut list_state = self.list_state.clone();
+        let list = List::new(items)
+            .block(accessibility.block().title(strings.room_browser_title()))
+            .highlight_symbol(if accessibility.ascii { "> " } else { "❯ " })
+            .highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(list, rooms, &mut list_state);
+
+        let usage_paragraph = Paragraph::new(usage_text)
+            .wrap(Wrap { trim: true })
+            .block(accessibility.block().title(strings.usage_title()));
+        frame.render_widget(usage_paragraph, usage);
+    }
+}
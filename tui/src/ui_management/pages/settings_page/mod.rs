@@ -0,0 +1,4 @@
+#[allow(clippy::module_inception)]
+mod settings_page;
+
+pub use settings_page::SettingsPage;
@@ -0,0 +1,564 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use ratatui::{prelude::*, widgets::*, Frame};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    accessibility::Accessibility,
+    aliases::Aliases,
+    config::ClientConfig,
+    keymap::Keymap,
+    locale::Locale,
+    notice_filter::NoticeFilter,
+    state_store::{action::Action, NoticeKind, State},
+    status_bar::StatusBar,
+    time_format::{TimeFormat, TimeZone},
+    ui_management::{
+        components::{
+            input_box::{self, InputBox},
+            Component, ComponentRender,
+        },
+        pages::{
+            usage::{widget_usage_to_text, UsageInfo, UsageInfoLine},
+            Navigation, Page,
+        },
+    },
+};
+
+/// The rows of the settings page, in the order they are shown
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Setting {
+    Username,
+    Language,
+    HighContrast,
+    Ascii,
+    Linear,
+    Keymap,
+    AutoAway,
+    GroupingWindow,
+    TimeFormat,
+    TimeZone,
+    RelativeTimes,
+    WatchWords,
+    MaskedWords,
+    HiddenNotices,
+    ServerAddress,
+    AutoJoinRooms,
+    /// Only shown, the status bar is set up through its environment variables
+    StatusBar,
+    /// Only shown, the aliases are set up through their environment variables
+    Aliases,
+}
+
+impl Setting {
+    const ALL: [Setting; 18] = [
+        Setting::Username,
+        Setting::Language,
+        Setting::HighContrast,
+        Setting::Ascii,
+        Setting::Linear,
+        Setting::Keymap,
+        Setting::AutoAway,
+        Setting::GroupingWindow,
+        Setting::TimeFormat,
+        Setting::TimeZone,
+        Setting::RelativeTimes,
+        Setting::WatchWords,
+        Setting::MaskedWords,
+        Setting::HiddenNotices,
+        Setting::ServerAddress,
+        Setting::AutoJoinRooms,
+        Setting::StatusBar,
+        Setting::Aliases,
+    ];
+
+    /// Whether the setting is changed by typing its value rather than by switching between values
+    fn is_typed(&self) -> bool {
+        matches!(
+            self,
+            Setting::Username
+                | Setting::GroupingWindow
+                | Setting::TimeFormat
+                | Setting::WatchWords
+                | Setting::MaskedWords
+                | Setting::HiddenNotices
+                | Setting::ServerAddress
+                | Setting::AutoJoinRooms
+        )
+    }
+
+    fn is_editable(&self) -> bool {
+        !matches!(self, Setting::StatusBar | Setting::Aliases)
+    }
+}
+
+/// The value after the given one, starting over after the last one
+fn next_of<T: Copy + PartialEq, const N: usize>(values: [T; N], current: T) -> T {
+    let idx = values
+        .iter()
+        .position(|value| *value == current)
+        .unwrap_or(0);
+
+    values[(idx + 1) % N]
+}
+
+struct Props {
+    /// The settings in effect, changed and sent back to the state store
+    config: ClientConfig,
+    accessibility: Accessibility,
+    locale: Locale,
+    keymap: Keymap,
+    status_bar: StatusBar,
+    aliases: Aliases,
+    config_error: Option<String>,
+}
+
+impl From<&State> for Props {
+    fn from(state: &State) -> Self {
+        Props {
+            config: state.config(),
+            accessibility: state.accessibility,
+            locale: state.locale,
+            keymap: state.keymap,
+            status_bar: state.status_bar.clone(),
+            aliases: state.aliases.clone(),
+            config_error: state.config_error.clone(),
+        }
+    }
+}
+
+/// SettingsPage lets the user change how the user interface looks and behaves
+///
+/// Changes take effect right away and are saved to the config file, see [ClientConfig].
+pub struct SettingsPage {
+    action_tx: UnboundedSender<Action>,
+    props: Props,
+    list_state: ListState,
+    /// The value being typed for the selected setting
+    input_box: InputBox,
+    /// Whether the value of the selected setting is being typed
+    is_editing: bool,
+}
+
+impl SettingsPage {
+    fn selected_setting(&self) -> Setting {
+        Setting::ALL[self.list_state.selected().unwrap_or(0)]
+    }
+
+    fn time_format(&self) -> TimeFormat {
+        self.props.config.time_format.clone().unwrap_or_default()
+    }
+
+    fn value_of(&self, setting: Setting) -> String {
+        let strings = self.props.locale.strings();
+        let accessibility = self.props.accessibility;
+        let list = |names: Vec<&str>| {
+            if names.is_empty() {
+                String::from(strings.none_setting())
+            } else {
+                names.join(", ")
+            }
+        };
+
+        match setting {
+            Setting::Username => self
+                .props
+                .config
+                .username
+                .clone()
+                .unwrap_or_else(|| String::from(strings.none_setting())),
+            Setting::Language => String::from(self.props.locale.name()),
+            Setting::HighContrast => String::from(strings.on_off(accessibility.high_contrast)),
+            Setting::Ascii => String::from(strings.on_off(accessibility.ascii)),
+            Setting::Linear => String::from(strings.on_off(accessibility.linear)),
+            Setting::Keymap => String::from(self.props.keymap.name()),
+            Setting::AutoAway => {
+                String::from(strings.on_off(self.props.config.auto_away.unwrap_or_default()))
+            }
+            Setting::GroupingWindow => match self.props.config.grouping_window_secs {
+                Some(0) | None => String::from(strings.on_off(false)),
+                Some(grouping_window_secs) => grouping_window_secs.to_string(),
+            },
+            Setting::TimeFormat => String::from(self.time_format().pattern_name()),
+            Setting::TimeZone => String::from(self.time_format().time_zone.name()),
+            Setting::RelativeTimes => String::from(strings.on_off(self.time_format().relative)),
+            Setting::WatchWords => list(
+                self.props
+                    .config
+                    .watch_words
+                    .iter()
+                    .flatten()
+                    .map(String::as_str)
+                    .collect(),
+            ),
+            Setting::MaskedWords => list(
+                self.props
+                    .config
+                    .masked_words
+                    .iter()
+                    .flatten()
+                    .map(String::as_str)
+                    .collect(),
+            ),
+            Setting::HiddenNotices => list(
+                self.props
+                    .config
+                    .hidden_notices
+                    .iter()
+                    .flatten()
+                    .map(NoticeKind::name)
+                    .collect(),
+            ),
+            Setting::ServerAddress => self.props.config.server_address.clone().unwrap_or_default(),
+            Setting::AutoJoinRooms => list(
+                self.props
+                    .config
+                    .auto_join_rooms
+                    .iter()
+                    .flatten()
+                    .map(String::as_str)
+                    .collect(),
+            ),
+            Setting::StatusBar => list(
+                self.props
+                    .status_bar
+                    .segments
+                    .iter()
+                    .map(|segment| segment.kind.name())
+                    .collect(),
+            ),
+            Setting::Aliases => list(self.props.aliases.names().collect()),
+        }
+    }
+
+    fn name_of(&self, setting: Setting) -> &'static str {
+        let strings = self.props.locale.strings();
+
+        match setting {
+            Setting::Username => strings.username_setting(),
+            Setting::Language => strings.language_setting(),
+            Setting::HighContrast => strings.high_contrast_setting(),
+            Setting::Ascii => strings.ascii_setting(),
+            Setting::Linear => strings.linear_setting(),
+            Setting::Keymap => strings.keymap_setting(),
+            Setting::AutoAway => strings.auto_away_setting(),
+            Setting::GroupingWindow => strings.grouping_window_setting(),
+            Setting::TimeFormat => strings.time_format_setting(),
+            Setting::TimeZone => strings.time_zone_setting(),
+            Setting::RelativeTimes => strings.relative_times_setting(),
+            Setting::WatchWords => strings.watch_words_setting(),
+            Setting::MaskedWords => strings.masked_words_setting(),
+            Setting::HiddenNotices => strings.hidden_notices_setting(),
+            Setting::ServerAddress => strings.server_address_setting(),
+            Setting::AutoJoinRooms => strings.auto_join_rooms_setting(),
+            Setting::StatusBar => strings.status_bar_setting(),
+            Setting::Aliases => strings.aliases_setting(),
+        }
+    }
+
+    /// Switches the selected setting to its next value, or starts typing its value
+    fn change_selected(&mut self) {
+        let setting = self.selected_setting();
+        if setting.is_typed() {
+            let config = &self.props.config;
+            self.input_box.set_text(&match setting {
+                Setting::Username => config.username.clone().unwrap_or_default(),
+                Setting::GroupingWindow => {
+                    config.grouping_window_secs.unwrap_or_default().to_string()
+                }
+                Setting::TimeFormat => String::from(self.time_format().pattern_name()),
+                Setting::WatchWords => config.watch_words.clone().unwrap_or_default().join(", "),
+                Setting::MaskedWords => config.masked_words.clone().unwrap_or_default().join(", "),
+                Setting::HiddenNotices => config
+                    .hidden_notices
+                    .iter()
+                    .flatten()
+                    .map(NoticeKind::name)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                Setting::AutoJoinRooms => config
+                    .auto_join_rooms
+                    .clone()
+                    .unwrap_or_default()
+                    .join(", "),
+                _ => config.server_address.clone().unwrap_or_default(),
+            });
+            self.is_editing = true;
+
+            return;
+        }
+
+        let mut config = self.props.config.clone();
+        let mut accessibility = self.props.accessibility;
+        match setting {
+            Setting::Language => config.language = Some(next_of(Locale::ALL, self.props.locale)),
+            Setting::HighContrast => accessibility.high_contrast = !accessibility.high_contrast,
+            Setting::Ascii => accessibility.ascii = !accessibility.ascii,
+            Setting::Linear => accessibility.linear = !accessibility.linear,
+            Setting::Keymap => config.keymap = Some(next_of(Keymap::ALL, self.props.keymap)),
+            Setting::AutoAway => config.auto_away = config.auto_away.map(|auto_away| !auto_away),
+            Setting::TimeZone => {
+                let mut time_format = self.time_format();
+                time_format.time_zone = next_of(TimeZone::ALL, time_format.time_zone);
+                config.time_format = Some(time_format);
+            }
+            Setting::RelativeTimes => {
+                let mut time_format = self.time_format();
+                time_format.relative = !time_format.relative;
+                config.time_format = Some(time_format);
+            }
+            _ => return,
+        }
+        config.accessibility = Some(accessibility);
+
+        self.update(config);
+    }
+
+    /// Takes the typed value of the setting being edited
+    fn confirm_editing(&mut self) {
+        self.is_editing = false;
+        let text = self.input_box.text().trim();
+
+        let mut config = self.props.config.clone();
+        match self.selected_setting() {
+            Setting::Username => {
+                config.username = Some(String::from(text)).filter(|username| !username.is_empty())
+            }
+            // anything but a number of seconds leaves the window as it was, 0 turns grouping off
+            Setting::GroupingWindow => match text.parse() {
+                Ok(grouping_window_secs) => {
+                    config.grouping_window_secs = Some(grouping_window_secs)
+                }
+                Err(_) => return,
+            },
+            // a format string chrono does not know leaves the format as it was
+            Setting::TimeFormat => match TimeFormat::parse_pattern(text) {
+                Some(pattern) => {
+                    let mut time_format = self.time_format();
+                    time_format.pattern = pattern;
+                    config.time_format = Some(time_format);
+                }
+                None => return,
+            },
+            Setting::WatchWords => {
+                config.watch_words = Some(
+                    text.split(',')
+                        .map(str::trim)
+                        .filter(|watch_word| !watch_word.is_empty())
+                        .map(String::from)
+                        .collect(),
+                )
+            }
+            Setting::MaskedWords => {
+                config.masked_words = Some(
+                    text.split(',')
+                        .map(str::trim)
+                        .filter(|masked_word| !masked_word.is_empty())
+                        .map(String::from)
+                        .collect(),
+                )
+            }
+            // unknown kinds are dropped, the page shows which ones were taken
+            Setting::HiddenNotices => {
+                config.hidden_notices = Some(NoticeFilter::parse(text).hidden_kinds().to_vec())
+            }
+            Setting::AutoJoinRooms => {
+                config.auto_join_rooms = Some(
+                    text.split(',')
+                        .map(|room| room.trim().trim_start_matches('#'))
+                        .filter(|room| !room.is_empty())
+                        .map(String::from)
+                        .collect(),
+                )
+            }
+            // the address can not be empty, the connect page would have nothing to start with
+            Setting::ServerAddress if !text.is_empty() => {
+                config.server_address = Some(String::from(text))
+            }
+            _ => return,
+        }
+
+        self.update(config);
+    }
+
+    fn update(&mut self, config: ClientConfig) {
+        // the page shows the change once the state store has applied it
+        let _ = self.action_tx.send(Action::UpdateSettings {
+            config: Box::new(config),
+        });
+    }
+
+    fn select_next(&mut self, step: isize) {
+        let len = Setting::ALL.len() as isize;
+        let selected = self.list_state.selected().unwrap_or(0) as isize;
+
+        self.list_state
+            .select(Some((selected + step).rem_euclid(len) as usize));
+    }
+
+    fn usage_info(&self) -> UsageInfo {
+        let strings = self.props.locale.strings();
+
+        if self.is_editing {
+            return UsageInfo {
+                description: Some(strings.type_setting().into()),
+                lines: vec![
+                    UsageInfoLine {
+                        keys: vec!["Enter".into()],
+                        description: strings.to_save_setting().into(),
+                    },
+                    UsageInfoLine {
+                        keys: vec!["Esc".into()],
+                        description: strings.to_cancel().into(),
+                    },
+                ],
+            };
+        }
+
+        UsageInfo {
+            description: Some(strings.settings_saved_to_config().into()),
+            lines: vec![
+                UsageInfoLine {
+                    keys: vec!["↑".into(), "↓".into()],
+                    description: strings.to_navigate().into(),
+                },
+                UsageInfoLine {
+                    keys: vec!["Enter".into(), "Space".into()],
+                    description: strings.to_change_setting().into(),
+                },
+                UsageInfoLine {
+                    keys: vec!["Esc".into()],
+                    description: strings.to_go_back().into(),
+                },
+            ],
+        }
+    }
+}
+
+impl Component for SettingsPage {
+    fn new(state: &State, action_tx: UnboundedSender<Action>) -> Self
+    where
+        Self: Sized,
+    {
+        SettingsPage {
+            action_tx: action_tx.clone(),
+            props: Props::from(state),
+            list_state: ListState::default().with_selected(Some(0)),
+            input_box: InputBox::new(state, action_tx.clone()),
+            is_editing: false,
+        }
+    }
+
+    fn move_with_state(self, state: &State) -> Self
+    where
+        Self: Sized,
+    {
+        SettingsPage {
+            props: Props::from(state),
+            ..self
+        }
+    }
+
+    fn name(&self) -> &str {
+        self.props.locale.strings().settings_title()
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) {
+        let _ = self.handle_page_key_event(key);
+    }
+}
+
+impl Page for SettingsPage {
+    fn handle_page_key_event(&mut self, key: KeyEvent) -> Navigation {
+        if key.kind != KeyEventKind::Press {
+            return Navigation::Stay;
+        }
+
+        if self.is_editing {
+            match key.code {
+                KeyCode::Enter => self.confirm_editing(),
+                KeyCode::Esc => self.is_editing = false,
+                _ => self.input_box.handle_key_event(key),
+            }
+
+            return Navigation::Stay;
+        }
+
+        match self.props.keymap.translate(key).code {
+            KeyCode::Up => self.select_next(-1),
+            KeyCode::Down => self.select_next(1),
+            KeyCode::Enter | KeyCode::Char(' ') => self.change_selected(),
+            KeyCode::Esc | KeyCode::Char('q') => return Navigation::Back,
+            _ => {}
+        }
+
+        Navigation::Stay
+    }
+}
+
+impl ComponentRender<()> for SettingsPage {
+    fn render(&self, frame: &mut Frame, _props: ()) {
+        let accessibility = self.props.accessibility;
+        let strings = self.props.locale.strings();
+        let usage_text = widget_usage_to_text(self.usage_info(), &accessibility);
+        let error_height = if self.props.config_error.is_some() {
+            1
+        } else {
+            0
+        };
+        let [settings, error, input, usage] = Layout::vertical([
+            Constraint::Min(1),
+            Constraint::Length(error_height),
+            Constraint::Length(if self.is_editing { 3 } else { 0 }),
+            Constraint::Length(usage_text.height() as u16 + 2),
+        ])
+        .areas(frame.area());
+
+        let items = Setting::ALL
+            .iter()
+            .map(|setting| {
+                let name = Span::from(format!("{}: ", self.name_of(*setting))).bold();
+                let value = Span::from(self.value_of(*setting));
+
+                ListItem::new(Line::from(if setting.is_editable() {
+                    vec![name, value]
+                } else {
+                    vec![name, value.italic(), strings.read_only_marker().into()]
+                }))
+            })
+            .collect::<Vec<_>>();
+        let mut list_state = self.list_state.clone();
+        let list = List::new(items)
+            .block(accessibility.block().title(strings.settings_title()))
+            .highlight_symbol(if accessibility.ascii { "> " } else { "❯ " })
+            .highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(list, settings, &mut list_state);
+
+        if let Some(config_error) = self.props.config_error.as_ref() {
+            let error_paragraph =
+                Paragraph::new(strings.error(config_error)).style(if accessibility.high_contrast {
+                    Style::default().add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(Color::Red)
+                });
+            frame.render_widget(error_paragraph, error);
+        }
+
+        if self.is_editing {
+            self.input_box.render(
+                frame,
+                input_box::RenderProps {
+                    title: String::from(self.name_of(self.selected_setting())),
+                    area: input,
+                    border_color: Color::Yellow,
+                    show_cursor: true,
+                    accessibility,
+                },
+            );
+        }
+
+        let usage_paragraph = Paragraph::new(usage_text)
+            .wrap(Wrap { trim: true })
+            .block(accessibility.block().title(strings.usage_title()));
+        frame.render_widget(usage_paragraph, usage);
+    }
+}
@@ -5,18 +5,26 @@ use std::{
 
 use anyhow::Context;
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture, Event, EventStream},
+    event::{
+        DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture, Event,
+        EventStream,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::prelude::*;
-use tokio::sync::{
-    broadcast,
-    mpsc::{self, UnboundedReceiver},
+use tokio::{
+    sync::{
+        broadcast,
+        mpsc::{self, UnboundedReceiver},
+        watch,
+    },
+    time::Instant,
 };
 use tokio_stream::StreamExt;
 
 use crate::{
+    keymap,
     state_store::{action::Action, State},
     ui_management::components::{Component, ComponentRender},
     Interrupted,
@@ -24,7 +32,22 @@ use crate::{
 
 use super::pages::AppRouter;
 
-const RENDERING_TICK_RATE: Duration = Duration::from_millis(250);
+/// How many frames a second are rendered at most, unless the user has picked another cap
+const DEFAULT_MAX_FPS: u32 = 30;
+
+/// Reads the most frames a second to render from the `CHAT_TUI_MAX_FPS` environment variable
+/// Returns how long to wait between frames, 0 renders every change right away
+fn frame_interval_from_env() -> Duration {
+    let max_fps = std::env::var("CHAT_TUI_MAX_FPS")
+        .ok()
+        .and_then(|max_fps| max_fps.trim().parse().ok())
+        .unwrap_or(DEFAULT_MAX_FPS);
+    if max_fps == 0 {
+        return Duration::ZERO;
+    }
+
+    Duration::from_secs(1) / max_fps
+}
 
 pub struct UiManager {
     action_tx: mpsc::UnboundedSender<Action>,
@@ -39,48 +62,69 @@ impl UiManager {
 
     pub async fn main_loop(
         self,
-        mut state_rx: UnboundedReceiver<State>,
+        mut state_rx: watch::Receiver<State>,
         mut interrupt_rx: broadcast::Receiver<Interrupted>,
     ) -> anyhow::Result<Interrupted> {
         // consume the first state to initialize the ui app
         let mut app_router = {
-            let state = state_rx.recv().await.unwrap();
+            state_rx
+                .changed()
+                .await
+                .context("the state store has stopped before sending the first state")?;
 
-            AppRouter::new(&state, self.action_tx.clone())
+            AppRouter::new(&state_rx.borrow_and_update(), self.action_tx.clone())
         };
 
         let mut terminal = setup_terminal()?;
-        let mut ticker = tokio::time::interval(RENDERING_TICK_RATE);
         let mut crossterm_events = EventStream::new();
+        let frame_interval = frame_interval_from_env();
+        // the frame is only rendered again once something has changed, at most once per frame interval
+        let mut is_dirty = true;
+        let mut last_frame_at = Instant::now();
 
         let result: anyhow::Result<Interrupted> = loop {
             tokio::select! {
-                // Tick to terminate the select every N milliseconds
-                _ = ticker.tick() => (),
+                // Render the changes once the next frame is due
+                _ = tokio::time::sleep_until(last_frame_at + frame_interval), if is_dirty => {
+                    if let Err(err) = terminal
+                        .draw(|frame| app_router.render(frame, ()))
+                        .context("could not render to the terminal")
+                    {
+                        break Err(err);
+                    }
+
+                    is_dirty = false;
+                    last_frame_at = Instant::now();
+                },
                 // Catch and handle crossterm events
                maybe_event = crossterm_events.next() => match maybe_event {
                     Some(Ok(Event::Key(key)))  => {
-                        app_router.handle_key_event(key);
+                        if let Some(key) = keymap::normalize(key) {
+                            app_router.handle_key_event(key);
+                            is_dirty = true;
+                        }
+                    },
+                    Some(Ok(Event::FocusGained)) => {
+                        let _ = self.action_tx.send(Action::FocusChanged { is_focused: true });
                     },
+                    Some(Ok(Event::FocusLost)) => {
+                        let _ = self.action_tx.send(Action::FocusChanged { is_focused: false });
+                    },
+                    // the pages are laid out for the new size by the render below
+                    Some(Ok(Event::Resize(_, _))) => is_dirty = true,
                     None => break Ok(Interrupted::UserInt),
                     _ => (),
                 },
-                // Handle state updates
-                Some(state) = state_rx.recv() => {
-                    app_router = app_router.move_with_state(&state);
+                // Handle state updates, only the latest state is kept while the previous one renders
+                Ok(()) = state_rx.changed() => {
+                    app_router = app_router.move_with_state(&state_rx.borrow_and_update());
+                    is_dirty = true;
                 },
                 // Catch and handle interrupt signal to gracefully shutdown
                 Ok(interrupted) = interrupt_rx.recv() => {
                     break Ok(interrupted);
                 }
             }
-
-            if let Err(err) = terminal
-                .draw(|frame| app_router.render(frame, ()))
-                .context("could not render to the terminal")
-            {
-                break Err(err);
-            }
         };
 
         restore_terminal(&mut terminal)?;
@@ -94,7 +138,13 @@ fn setup_terminal() -> anyhow::Result<Terminal<CrosstermBackend<Stdout>>> {
 
     enable_raw_mode()?;
 
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    // terminals which do not report focus changes simply never send the events
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableFocusChange
+    )?;
 
     Ok(Terminal::new(CrosstermBackend::new(stdout))?)
 }
@@ -105,7 +155,8 @@ fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> anyhow
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableFocusChange
     )?;
 
     Ok(terminal.show_cursor()?)